@@ -0,0 +1,43 @@
+//! Links the LiteRT-LM C library for the `ffi` feature, and generates the
+//! bindings to its C API
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if std::env::var_os("CARGO_FEATURE_FFI").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-env-changed=LITERT_LM_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=LITERT_LM_LIB");
+    if let Some(dir) = std::env::var_os("LITERT_LM_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={}", dir.to_string_lossy());
+    }
+    let lib = std::env::var("LITERT_LM_LIB").unwrap_or_else(|_| "litert_lm_c".to_string());
+    println!("cargo:rustc-link-lib=dylib={}", lib);
+    #[cfg(feature = "ffi")]
+    bindings();
+}
+
+/// Generate `$OUT_DIR/litert_lm.rs` from `c/engine.h` in
+/// `$LITERT_LM_INCLUDE_DIR` (a LiteRT-LM checkout, or its installed headers)
+#[cfg(feature = "ffi")]
+fn bindings() {
+    use std::path::PathBuf;
+
+    println!("cargo:rerun-if-env-changed=LITERT_LM_INCLUDE_DIR");
+    let include = std::env::var_os("LITERT_LM_INCLUDE_DIR")
+        .map(PathBuf::from)
+        .expect("The ffi feature needs LITERT_LM_INCLUDE_DIR, the directory holding LiteRT-LM's c/engine.h");
+    let header = include.join("c").join("engine.h");
+    println!("cargo:rerun-if-changed={}", header.display());
+    let out = PathBuf::from(std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR")).join("litert_lm.rs");
+    bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .clang_arg(format!("-I{}", include.display()))
+        .allowlist_function("litert_lm_.*")
+        .rustified_enum("InputDataType")
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .generate()
+        .unwrap_or_else(|e| panic!("Failed to generate bindings from {}: {}", header.display(), e))
+        .write_to_file(&out)
+        .unwrap_or_else(|e| panic!("Failed to write {}: {}", out.display(), e));
+}
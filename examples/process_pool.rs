@@ -18,7 +18,7 @@ async fn main() -> Result<()> {
     let manager = LitManager::new().await?;
 
     // Create multiple concurrent requests
-    let prompts = vec![
+    let prompts = [
         "What is 2+2?",
         "Name a color",
         "What is the capital of Japan?",
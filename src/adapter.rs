@@ -0,0 +1,147 @@
+//! LoRA adapters for downloaded models
+//!
+//! An adapter is stored under `<cache_dir>/adapters/<base>/` and used as the
+//! model `<base>:<name>`, e.g. `gemma-3n-E4B:support`. Its processes run the
+//! base model with the adapter file passed to `lit run`, so a fine-tuned
+//! variant gets its own pool and is listed, permitted, and removed like any
+//! other model.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Separates the base model from the adapter name in a model name
+pub const SEPARATOR: char = ':';
+
+/// `lit run` flag that loads a LoRA adapter
+pub const LIT_FLAG: &str = "--lora_path";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Adapter {
+    /// Model the adapter was trained for
+    pub base: String,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Adapter {
+    /// Model name the adapter is used as
+    pub fn model(&self) -> String {
+        format!("{}{}{}", self.base, SEPARATOR, self.name)
+    }
+}
+
+impl std::fmt::Display for Adapter {
+    /// A listing line: model name and file
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}  {}", self.model(), self.path.display())
+    }
+}
+
+/// Base model and adapter name of `<base>:<name>`
+pub fn split_model(model: &str) -> Option<(&str, &str)> {
+    model
+        .rsplit_once(SEPARATOR)
+        .filter(|(base, name)| valid_part(base) && valid_part(name))
+}
+
+/// Whether `part` can be used as a file or directory name in the store
+fn valid_part(part: &str) -> bool {
+    !part.is_empty() && !part.starts_with('.') && !part.contains(['/', '\\', SEPARATOR])
+}
+
+/// Adapter files in the cache directory
+#[derive(Debug, Clone)]
+pub struct AdapterStore {
+    dir: PathBuf,
+}
+
+impl AdapterStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Copy the adapter at `source` into the store as `<base>:<name>`,
+    /// replacing any adapter of that name
+    pub fn add(&self, base: &str, name: &str, source: &Path) -> Result<Adapter> {
+        for part in [base, name] {
+            if !valid_part(part) {
+                anyhow::bail!("Invalid adapter or model name '{}'", part);
+            }
+        }
+        let file_name = match source.extension() {
+            Some(ext) => format!("{}.{}", name, ext.to_string_lossy()),
+            None => name.to_string(),
+        };
+        let dir = self.dir.join(base);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create adapter directory {}", dir.display()))?;
+
+        // Replace rather than keep two files with different extensions
+        if let Some(existing) = self.get(&format!("{}{}{}", base, SEPARATOR, name)) {
+            std::fs::remove_file(&existing.path).ok();
+        }
+        let path = dir.join(file_name);
+        std::fs::copy(source, &path)
+            .with_context(|| format!("Failed to copy adapter {}", source.display()))?;
+        tracing::info!(base = %base, adapter = %name, path = %path.display(), "Added adapter");
+
+        Ok(Adapter {
+            base: base.to_string(),
+            name: name.to_string(),
+            path,
+        })
+    }
+
+    /// The adapter `model` names, if it names one
+    pub fn get(&self, model: &str) -> Option<Adapter> {
+        let (base, name) = split_model(model)?;
+        self.list_base(base).into_iter().find(|adapter| adapter.name == name)
+    }
+
+    /// Every stored adapter, sorted by model name
+    pub fn list(&self) -> Vec<Adapter> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut adapters: Vec<Adapter> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .flat_map(|entry| self.list_base(&entry.file_name().to_string_lossy()))
+            .collect();
+        adapters.sort_by_key(Adapter::model);
+        adapters
+    }
+
+    fn list_base(&self, base: &str) -> Vec<Adapter> {
+        let Ok(entries) = std::fs::read_dir(self.dir.join(base)) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+                Some(Adapter {
+                    base: base.to_string(),
+                    name,
+                    path,
+                })
+            })
+            .collect()
+    }
+
+    /// Delete the adapter `model` names; false if there is none
+    pub fn remove(&self, model: &str) -> Result<bool> {
+        let Some(adapter) = self.get(model) else {
+            return Ok(false);
+        };
+        std::fs::remove_file(&adapter.path)
+            .with_context(|| format!("Failed to remove adapter {}", adapter.path.display()))?;
+        // Drop the base model's directory once its last adapter is gone
+        std::fs::remove_dir(self.dir.join(&adapter.base)).ok();
+        tracing::info!(model = %model, "Removed adapter");
+        Ok(true)
+    }
+}
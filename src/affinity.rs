@@ -0,0 +1,155 @@
+//! Conversation affinity
+//!
+//! lit keeps what it has read and generated in its context, so a
+//! conversation's next turn is cheapest on the process that answered the
+//! previous one: only the new messages need to be sent. A pool remembers
+//! which process holds each conversation (named by the request's `session_id`,
+//! else its `user`) and how many of its turns. A turn goes back to that
+//! process while it is idle and nothing else ran on it since; otherwise the
+//! whole history is replayed on whichever process is free. The process checks
+//! again once the turn's prompt comes up, as another request may have picked
+//! it in the meantime.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Instant;
+
+use anyhow::Result;
+use futures::Stream;
+
+use crate::context::Turn;
+use crate::process::{self, GenerationTiming, LitProcess};
+use crate::tokenizer::Tokenizer;
+
+/// Conversations remembered per pool; the least recently used are forgotten first
+const MAX_CONVERSATIONS: usize = 1024;
+
+/// Where a conversation's turn is sent
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub process: Arc<LitProcess>,
+    /// Leading turns of the conversation the process already holds; only the
+    /// rest are sent. 0 replays the whole history.
+    pub held: usize,
+    // `served()` of the process once this turn is answered, if nothing else
+    // runs on it in between
+    served_after: u64,
+}
+
+impl Placement {
+    /// Send every turn to `process`
+    pub fn replay(process: Arc<LitProcess>) -> Self {
+        let served_after = process.served() + process.pending() as u64 + 1;
+        Self { process, held: 0, served_after }
+    }
+
+    /// Stream the reply to the turn: only `new_turns`, the turns after those
+    /// the process holds, unless another prompt reaches the process first;
+    /// then (and when it holds none) `conversation`, all of it, is sent
+    pub async fn send_stream(&self, new_turns: &str, conversation: &str) -> Result<impl Stream<Item = Result<String>>> {
+        let resume = (self.held > 0).then(|| self.served_after - 1);
+        self.process.send_turn_stream(new_turns, conversation, resume).await
+    }
+
+    /// [`send_stream`](Self::send_stream), collecting the reply up to
+    /// `max_tokens`
+    pub async fn send(
+        &self,
+        new_turns: &str,
+        conversation: &str,
+        max_tokens: Option<usize>,
+        tokenizer: Arc<dyn Tokenizer>,
+    ) -> Result<(String, GenerationTiming)> {
+        let started = Instant::now();
+        let stream = self.send_stream(new_turns, conversation).await?;
+        process::collect_reply(started, stream, max_tokens, tokenizer).await
+    }
+}
+
+// What a process holds of a conversation
+struct Held {
+    process: Weak<LitProcess>,
+    served: u64,
+    turns: usize,
+    digest: u64,
+    used: Instant,
+}
+
+/// The conversations a pool's processes hold
+#[derive(Default)]
+pub struct Conversations {
+    by_key: Mutex<HashMap<String, Held>>,
+}
+
+impl std::fmt::Debug for Conversations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Conversations")
+            .field("len", &self.by_key.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Conversations {
+    /// The process still holding the start of `turns` for `conversation`, if
+    /// it is one of `processes` and idle
+    pub fn place(&self, conversation: &str, turns: &[Turn], processes: &[Arc<LitProcess>]) -> Option<Placement> {
+        let by_key = self.by_key.lock().unwrap();
+        let held = by_key.get(conversation)?;
+        let process = held.process.upgrade()?;
+        let unchanged = processes.iter().any(|p| Arc::ptr_eq(p, &process))
+            && process.is_alive()
+            && process.pending() == 0
+            && process.served() == held.served;
+        // Only new turns are sent, so the client's history must continue
+        // exactly what the process holds
+        let continues = turns.len() > held.turns && digest(&turns[..held.turns]) == held.digest;
+        (unchanged && continues).then(|| Placement {
+            held: held.turns,
+            served_after: held.served + 1,
+            process,
+        })
+    }
+
+    /// Note that `placement`'s process now holds `turns`, the conversation
+    /// including the reply it just generated
+    pub fn remember(&self, conversation: &str, placement: &Placement, turns: &[Turn]) {
+        let mut by_key = self.by_key.lock().unwrap();
+        if by_key.len() >= MAX_CONVERSATIONS && !by_key.contains_key(conversation) {
+            let oldest = by_key.iter().min_by_key(|(_, held)| held.used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                by_key.remove(&oldest);
+            }
+        }
+        by_key.insert(
+            conversation.to_string(),
+            Held {
+                process: Arc::downgrade(&placement.process),
+                served: placement.served_after,
+                turns: turns.len(),
+                digest: digest(turns),
+                used: Instant::now(),
+            },
+        );
+    }
+
+    /// Number of conversations remembered
+    pub fn len(&self) -> usize {
+        self.by_key.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Clients send back trimmed replies, so whitespace at the ends doesn't count
+fn digest(turns: &[Turn]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for turn in turns {
+        turn.role.hash(&mut hasher);
+        turn.content.trim().hash(&mut hasher);
+    }
+    hasher.finish()
+}
@@ -0,0 +1,343 @@
+//! Tool-execution loop for function calling
+//!
+//! The lit binary has no native tool calling, so tools are described in the
+//! prompt and the model is asked to answer with a JSON object naming a tool
+//! and its arguments. The loop executes the tool, appends the result to the
+//! transcript, and asks again until the model replies with plain text.
+//!
+//! Tools are Rust closures registered through [`ToolRegistry::register_fn`], or
+//! shell/HTTP tools declared in the config file.
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{ToolConfig, ToolKind};
+use crate::context::Turn;
+use crate::template::ChatTemplate;
+
+/// Name, description, and argument schema shown to the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// JSON Schema for the arguments object
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+}
+
+/// A tool invocation parsed from model output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+type ToolFn = Arc<dyn Fn(serde_json::Value) -> BoxFuture<'static, Result<String>> + Send + Sync>;
+
+#[derive(Clone)]
+enum ToolHandler {
+    Function(ToolFn),
+    Shell { command: Vec<String>, timeout: Duration },
+    Http { url: String, timeout: Duration },
+}
+
+#[derive(Clone)]
+struct RegisteredTool {
+    definition: ToolDefinition,
+    handler: ToolHandler,
+}
+
+/// Tools available to the agent loop, keyed by name
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Arc<RwLock<HashMap<String, RegisteredTool>>>,
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field("tools", &self.names())
+            .finish()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry holding the shell and HTTP tools from the config file
+    pub fn from_config(tools: &[ToolConfig]) -> Self {
+        let registry = Self::new();
+        for tool in tools {
+            let timeout = Duration::from_secs(tool.timeout_secs);
+            let handler = match &tool.kind {
+                ToolKind::Shell { command } => ToolHandler::Shell {
+                    command: command.clone(),
+                    timeout,
+                },
+                ToolKind::Http { url } => ToolHandler::Http {
+                    url: url.clone(),
+                    timeout,
+                },
+            };
+            registry.insert(
+                ToolDefinition {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    parameters: tool.parameters.clone(),
+                },
+                handler,
+            );
+        }
+        registry
+    }
+
+    /// Register a Rust closure as a tool, replacing any tool with the same name
+    pub fn register_fn<F, Fut>(&self, definition: ToolDefinition, f: F)
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let f: ToolFn = Arc::new(move |args| Box::pin(f(args)));
+        self.insert(definition, ToolHandler::Function(f));
+    }
+
+    fn insert(&self, definition: ToolDefinition, handler: ToolHandler) {
+        self.tools.write().unwrap().insert(
+            definition.name.clone(),
+            RegisteredTool { definition, handler },
+        );
+    }
+
+    pub fn remove(&self, name: &str) -> bool {
+        self.tools.write().unwrap().remove(name).is_some()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tools.read().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions: Vec<ToolDefinition> = self
+            .tools
+            .read()
+            .unwrap()
+            .values()
+            .map(|tool| tool.definition.clone())
+            .collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// Registry limited to the named tools; unknown names are an error
+    pub fn subset(&self, names: &[String]) -> Result<Self> {
+        let tools = self.tools.read().unwrap();
+        let subset = Self::new();
+        for name in names {
+            let tool = tools
+                .get(name)
+                .with_context(|| format!("Unknown tool '{}'", name))?;
+            subset.insert(tool.definition.clone(), tool.handler.clone());
+        }
+        Ok(subset)
+    }
+
+    /// Execute a tool call
+    pub async fn call(&self, call: &ToolCall) -> Result<String> {
+        let handler = self
+            .tools
+            .read()
+            .unwrap()
+            .get(&call.name)
+            .map(|tool| tool.handler.clone())
+            .with_context(|| format!("Unknown tool '{}'", call.name))?;
+
+        match handler {
+            ToolHandler::Function(f) => f(call.arguments.clone()).await,
+            ToolHandler::Shell { command, timeout } => {
+                tokio::time::timeout(timeout, run_shell_tool(&command, &call.arguments))
+                    .await
+                    .with_context(|| format!("Tool '{}' timed out after {:?}", call.name, timeout))?
+            }
+            ToolHandler::Http { url, timeout } => {
+                let response = reqwest::Client::new()
+                    .post(&url)
+                    .timeout(timeout)
+                    .json(&call.arguments)
+                    .send()
+                    .await
+                    .with_context(|| format!("Tool '{}' request failed", call.name))?
+                    .error_for_status()?;
+                Ok(response.text().await?)
+            }
+        }
+    }
+}
+
+async fn run_shell_tool(command: &[String], arguments: &serde_json::Value) -> Result<String> {
+    let (program, args) = command.split_first().context("Shell tool has an empty command")?;
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(arguments.to_string().as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// Describe the tools and the calling convention for the model
+pub fn render_tools_prompt(tools: &[ToolDefinition]) -> String {
+    let mut prompt = String::from("You can use the following tools:\n");
+    for tool in tools {
+        prompt.push_str(&format!(
+            "- {}: {}\n  arguments (JSON Schema): {}\n",
+            tool.name, tool.description, tool.parameters
+        ));
+    }
+    prompt.push_str(
+        "To call a tool, reply with only a JSON object of the form \
+         {\"tool\": \"<name>\", \"arguments\": {...}} and nothing else. \
+         When you can answer without a tool, reply with the answer as plain text.",
+    );
+    prompt
+}
+
+/// Parse a tool call from model output, if the output is one
+///
+/// Accepts `{"tool": .., "arguments": ..}` as well as the OpenAI-style
+/// `{"name": .., "arguments": ..}`, optionally inside a fenced code block.
+pub fn parse_tool_call(output: &str) -> Option<ToolCall> {
+    let start = output.find('{')?;
+    let end = output.rfind('}')?;
+    if end < start {
+        return None;
+    }
+
+    let value: serde_json::Value = serde_json::from_str(&output[start..=end]).ok()?;
+    let name = value
+        .get("tool")
+        .or_else(|| value.get("name"))?
+        .as_str()?
+        .to_string();
+    let arguments = match value.get("arguments") {
+        // Some models double-encode the arguments as a string
+        Some(serde_json::Value::String(s)) => serde_json::from_str(s).ok()?,
+        Some(args) => args.clone(),
+        None => serde_json::json!({}),
+    };
+    Some(ToolCall { name, arguments })
+}
+
+/// Whether output that has begun as `text` may still turn out to be a tool
+/// call, so a stream has to hold it back rather than send it as text
+pub fn may_be_tool_call(text: &str) -> bool {
+    let text = text.trim_start();
+    text.is_empty() || text.starts_with('{') || text.starts_with('`')
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentOptions {
+    /// Tool calls allowed before giving up
+    pub max_steps: usize,
+}
+
+impl Default for AgentOptions {
+    fn default() -> Self {
+        Self { max_steps: 8 }
+    }
+}
+
+/// One executed tool call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStep {
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    /// Tool output, or the error message fed back to the model
+    pub result: String,
+    pub is_error: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRun {
+    pub answer: String,
+    pub steps: Vec<AgentStep>,
+}
+
+/// Run the tool loop on the conversation `turns`, calling `generate` with the
+/// full transcript, rendered in `template`, each turn
+pub async fn run_agent<G, Fut>(
+    tools: &ToolRegistry,
+    turns: &[Turn],
+    template: ChatTemplate,
+    options: &AgentOptions,
+    mut generate: G,
+) -> Result<AgentRun>
+where
+    G: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let mut transcript = vec![Turn::new("system", render_tools_prompt(&tools.definitions()))];
+    transcript.extend_from_slice(turns);
+    let mut steps = Vec::new();
+
+    loop {
+        let output = generate(template.render(&transcript)).await?;
+        let Some(call) = parse_tool_call(&output) else {
+            return Ok(AgentRun {
+                answer: output.trim().to_string(),
+                steps,
+            });
+        };
+
+        if steps.len() >= options.max_steps {
+            anyhow::bail!(
+                "Agent did not reach a final answer within {} tool calls",
+                options.max_steps
+            );
+        }
+
+        tracing::info!(tool = %call.name, step = steps.len() + 1, "Executing tool call");
+        let (result, is_error) = match tools.call(&call).await {
+            Ok(result) => (result, false),
+            Err(e) => {
+                tracing::warn!(tool = %call.name, error = %e, "Tool call failed");
+                (format!("error: {:#}", e), true)
+            }
+        };
+
+        let request = serde_json::json!({ "tool": call.name, "arguments": call.arguments });
+        transcript.push(Turn::new("assistant", request.to_string()));
+        transcript.push(Turn::new("tool", format!("{}: {}", call.name, result)));
+        steps.push(AgentStep {
+            tool: call.name,
+            arguments: call.arguments,
+            result,
+            is_error,
+        });
+    }
+}
@@ -0,0 +1,153 @@
+//! Inference behind a trait
+//!
+//! Code that only needs completions can take an [`InferenceBackend`] instead of
+//! a [`LitManager`](crate::LitManager), so it can be exercised against
+//! [`MockBackend`](crate::mock::MockBackend) (with the `mock` feature) in tests
+//! that have neither a GPU nor the lit binary, or run on another engine
+//! through [`HttpBackend`](crate::http_backend::HttpBackend). lit processes,
+//! through the manager or a single [`ProcessPool`], are the default.
+//!
+//! [`Backend`] is the hardware lit itself runs a model on.
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::manager::LitManager;
+use crate::process::ProcessPool;
+use crate::tenant::{matches_pattern, Tenant};
+
+/// Generated text, chunk by chunk
+pub type TextStream = BoxStream<'static, Result<String>>;
+
+/// Something that generates completions for a model and prompt
+pub trait InferenceBackend: Send + Sync {
+    /// Stream the completion of `prompt` as it is generated
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>>;
+
+    /// The whole completion of `prompt`
+    fn complete<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<String>> {
+        async move {
+            let mut stream = self.complete_stream(model, prompt).await?;
+            let mut text = String::new();
+            while let Some(chunk) = stream.next().await {
+                text.push_str(&chunk?);
+            }
+            Ok(text)
+        }
+        .boxed()
+    }
+}
+
+/// lit subprocesses, through the manager's process pools
+impl InferenceBackend for LitManager {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        async move {
+            let stream = self.run_completion_stream(model, prompt).await?;
+            Ok(stream.map(|chunk| chunk.map(|chunk| chunk.text)).boxed())
+        }
+        .boxed()
+    }
+
+    fn complete<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<String>> {
+        self.run_completion(model, prompt).boxed()
+    }
+}
+
+/// The processes of one model's pool, bypassing the manager; requests wait
+/// for a slot in the pool like anyone else's
+impl InferenceBackend for ProcessPool {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        async move {
+            if model != self.model() {
+                anyhow::bail!("Pool of '{}' can't run '{}'", self.model(), model);
+            }
+            // Held until the stream is done
+            let slot = self.admit(&Tenant::unrestricted()).await?;
+            let stream = self.get_process().await?.send_prompt_stream(prompt).await?;
+            Ok(stream
+                .inspect(move |_| {
+                    let _ = &slot;
+                })
+                .boxed())
+        }
+        .boxed()
+    }
+}
+
+impl<T: InferenceBackend + ?Sized> InferenceBackend for std::sync::Arc<T> {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        (**self).complete_stream(model, prompt)
+    }
+
+    fn complete<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<String>> {
+        (**self).complete(model, prompt)
+    }
+}
+
+impl<T: InferenceBackend + ?Sized> InferenceBackend for Box<T> {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        (**self).complete_stream(model, prompt)
+    }
+
+    fn complete<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<String>> {
+        (**self).complete(model, prompt)
+    }
+}
+
+/// Hardware lit runs a model on, passed as `--backend`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// The GPU, falling back to the CPU when lit can't start on it
+    #[default]
+    Auto,
+    Gpu,
+    Cpu,
+    Npu,
+}
+
+impl Backend {
+    /// Backend of `model`: the most specific matching pattern's in
+    /// `config.backends`, else [`Backend::Auto`]
+    pub fn for_model(config: &HashMap<String, Backend>, model: &str) -> Self {
+        config
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, &backend)| backend)
+            .unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Auto => "auto",
+            Backend::Gpu => "gpu",
+            Backend::Cpu => "cpu",
+            Backend::Npu => "npu",
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Backend::Auto),
+            "gpu" => Ok(Backend::Gpu),
+            "cpu" => Ok(Backend::Cpu),
+            "npu" => Ok(Backend::Npu),
+            _ => Err(format!("'{}' is not a backend; expected auto, gpu, cpu, or npu", s)),
+        }
+    }
+}
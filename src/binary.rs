@@ -1,76 +1,237 @@
 use anyhow::{Context, Result};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use futures::StreamExt;
 use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache_lock::CacheLock;
+use crate::cancel;
+use crate::metrics::Metrics;
+use crate::progress::{self, Phase, Progress, ProgressReporter};
+use crate::retry::{ErrorClass, Permanent, RetryPolicy};
 
 const VERSION: &str = "v0.7.0";
 const BASE_URL: &str = "https://github.com/google-ai-edge/LiteRT-LM/releases/download";
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/google-ai-edge/LiteRT-LM/releases/latest";
+/// How long the update check may take
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for another process that is downloading the binary
+const DOWNLOAD_LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+/// How long `lit --version` may take
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Where the version reported by the installed binary is recorded
+const VERSION_FILE: &str = "lit.version";
 
 #[derive(Debug, Clone)]
 pub struct BinaryManager {
     cache_dir: PathBuf,
+    metrics: Arc<Metrics>,
+    cancel: Option<CancellationToken>,
+    progress: Option<Arc<dyn ProgressReporter>>,
+    /// Overrides the artifact picked for this platform
+    artifact: Option<String>,
+    retry: RetryPolicy,
 }
 
 impl BinaryManager {
+    /// Uses `$LITERT_CACHE_DIR` if set (e.g. a directory shared over NFS),
+    /// otherwise `<cache_dir>/litert-lm`
     pub fn new() -> Result<Self> {
-        let cache_dir = dirs::cache_dir()
-            .context("Failed to get cache directory")?
-            .join("litert-lm");
+        Self::with_cache_dir(default_cache_dir()?)
+    }
+
+    /// Keep the binary and cached state under `cache_dir`, created if missing
+    pub fn with_cache_dir(cache_dir: impl Into<PathBuf>) -> Result<Self> {
+        let cache_dir = cache_dir.into();
 
         tracing::debug!(cache_dir = %cache_dir.display(), "Setting up binary manager");
         fs::create_dir_all(&cache_dir)?;
         tracing::trace!(cache_dir = %cache_dir.display(), "Cache directory ready");
 
-        Ok(Self { cache_dir })
+        Ok(Self {
+            cache_dir,
+            metrics: Arc::new(Metrics::new()),
+            cancel: None,
+            progress: None,
+            artifact: None,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Directory holding the lit binary and other cached state
+    pub fn cache_dir(&self) -> &PathBuf {
+        &self.cache_dir
+    }
+
+    /// Record downloads into a shared metrics registry
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Abort a download in progress, removing the partial file, once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Report download progress to `reporter`
+    pub fn with_progress(mut self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+
+    /// Retry failed and corrupt downloads as `policy` says
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Download `artifact` from the release instead of the one for this platform
+    pub fn with_artifact(mut self, artifact: Option<String>) -> Self {
+        self.artifact = artifact;
+        self
     }
 
     pub async fn ensure_binary(&self) -> Result<PathBuf> {
-        let binary_path = self.get_binary_path();
+        let binary_path = self.get_binary_path()?;
 
         if binary_path.exists() {
             tracing::debug!(path = %binary_path.display(), "Binary already exists");
             return Ok(binary_path);
         }
 
+        // Another process sharing the cache may be downloading it already
+        let cancel = self.cancel.as_ref();
+        let _lock = cancel::run(cancel, CacheLock::acquire(&self.cache_dir, "binary", DOWNLOAD_LOCK_TIMEOUT)).await?;
+        if binary_path.exists() {
+            tracing::debug!(path = %binary_path.display(), "Binary downloaded by another process");
+            return Ok(binary_path);
+        }
+
+        // Download next to the destination and rename it into place, so the
+        // binary is never visible half-written and a failed or cancelled
+        // download leaves nothing behind
         tracing::info!(path = %binary_path.display(), "Binary not found, downloading...");
-        self.download_binary(&binary_path).await?;
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            tracing::debug!("Setting executable permissions");
-            let mut perms = fs::metadata(&binary_path)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&binary_path, perms)?;
+        let partial = binary_path.with_extension(format!("partial-{}", uuid::Uuid::new_v4().simple()));
+        let result = cancel::run(cancel, self.install_binary(&partial, &binary_path)).await;
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
         }
+        result?;
 
         tracing::info!(path = %binary_path.display(), "Binary ready");
         Ok(binary_path)
     }
 
-    fn get_binary_path(&self) -> PathBuf {
-        let filename = self.get_binary_filename();
-        self.cache_dir.join(filename)
+    async fn install_binary(&self, partial: &PathBuf, binary_path: &PathBuf) -> Result<()> {
+        // A truncated download or a build for the wrong architecture won't
+        // run; catch that here rather than when the first model loads
+        let mut attempt = 1;
+        let version = loop {
+            let (class, error) = match self.download_binary(partial).await {
+                Err(e) => (ErrorClass::Network, e),
+                Ok(()) => {
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::PermissionsExt;
+                        tracing::debug!("Setting executable permissions");
+                        let mut perms = fs::metadata(partial)?.permissions();
+                        perms.set_mode(0o755);
+                        fs::set_permissions(partial, perms)?;
+                    }
+
+                    match verify_binary(partial).await {
+                        Ok(version) => break version,
+                        Err(e) => {
+                            self.metrics.binary_downloads.with_label_values(&["corrupt"]).inc();
+                            let gives_up = !self.retry.should_retry(ErrorClass::Corrupt, attempt, &e);
+                            let e = if gives_up && attempt > 1 {
+                                e.context(format!("Downloaded binary still fails to run after {} attempts", attempt))
+                            } else {
+                                e
+                            };
+                            (ErrorClass::Corrupt, e)
+                        }
+                    }
+                }
+            };
+            self.retry
+                .wait_to_retry("binary_download", class, attempt, error, self.cancel.as_ref(), &self.metrics)
+                .await?;
+            attempt += 1;
+        };
+
+        if !version.contains(VERSION.trim_start_matches('v')) {
+            tracing::warn!(expected = VERSION, reported = %version, "Downloaded binary reports an unexpected version");
+        }
+        fs::rename(partial, binary_path).context("Failed to move downloaded binary into place")?;
+        if let Err(e) = fs::write(self.cache_dir.join(VERSION_FILE), &version) {
+            tracing::debug!(error = %e, "Failed to record binary version");
+        }
+        Ok(())
+    }
+
+    /// Tag of the latest LiteRT-LM release, if it isn't the one this build
+    /// downloads
+    ///
+    /// Only reported: the binary is pinned to the release this build was
+    /// tested against, so moving to a new one means upgrading litert-lm.
+    pub async fn check_for_update(&self) -> Result<Option<String>> {
+        #[derive(serde::Deserialize)]
+        struct Release {
+            tag_name: String,
+        }
+        let release: Release = reqwest::Client::builder()
+            .timeout(UPDATE_CHECK_TIMEOUT)
+            .user_agent(concat!("litert-lm/", env!("CARGO_PKG_VERSION")))
+            .build()?
+            .get(LATEST_RELEASE_URL)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to look up the latest LiteRT-LM release")?
+            .json()
+            .await
+            .context("Unexpected response from the GitHub releases API")?;
+        Ok((release.tag_name != VERSION).then_some(release.tag_name))
+    }
+
+    /// Version the installed binary reported when it was downloaded
+    pub fn installed_version(&self) -> Option<String> {
+        fs::read_to_string(self.cache_dir.join(VERSION_FILE)).ok()
     }
 
-    fn get_binary_filename(&self) -> &'static str {
-        match (env::consts::OS, env::consts::ARCH) {
-            ("linux", "aarch64") => "lit.linux_arm64",
-            ("linux", "x86_64") => "lit.linux_x86_64",
-            ("macos", "aarch64") => "lit.macos_arm64",
-            ("windows", "x86_64") => "lit.windows_x86_64.exe",
-            _ => panic!("Unsupported platform: {}/{}", env::consts::OS, env::consts::ARCH),
+    fn get_binary_path(&self) -> Result<PathBuf> {
+        Ok(self.cache_dir.join(self.get_binary_filename()?))
+    }
+
+    fn get_binary_filename(&self) -> Result<String> {
+        if let Some(artifact) = &self.artifact {
+            return Ok(artifact.clone());
         }
+        let libc = if env::consts::OS == "linux" { detect_libc(Path::new("/")) } else { None };
+        artifact_name(env::consts::OS, env::consts::ARCH, libc).map(str::to_string)
     }
 
     async fn download_binary(&self, dest: &PathBuf) -> Result<()> {
-        let filename = self.get_binary_filename();
+        let filename = self.get_binary_filename()?;
         let url = format!("{}/{}/{}", BASE_URL, VERSION, filename);
 
         tracing::info!(url = %url, "Downloading binary");
 
-        let response = reqwest::get(&url)
+        let result = self.fetch_binary(&url, dest).await;
+        let label = if result.is_ok() { "success" } else { "error" };
+        self.metrics.binary_downloads.with_label_values(&[label]).inc();
+        result
+    }
+
+    async fn fetch_binary(&self, url: &str, dest: &PathBuf) -> Result<()> {
+        let response = reqwest::get(url)
             .await
             .context("Failed to download binary")?;
 
@@ -80,22 +241,129 @@ impl BinaryManager {
                 status = %response.status(),
                 "Download request failed"
             );
-            anyhow::bail!("Failed to download binary: HTTP {}", response.status());
+            let status = response.status();
+            let message = format!("Failed to download binary: HTTP {}", status);
+            // A missing artifact won't appear by asking again
+            if status.is_client_error() && !matches!(status.as_u16(), 408 | 429) {
+                return Err(anyhow::Error::new(Permanent).context(message));
+            }
+            anyhow::bail!(message);
         }
 
-        tracing::debug!("Download response received, reading bytes");
-        let bytes = response.bytes().await?;
-        tracing::debug!(size_bytes = bytes.len(), "Binary downloaded, writing to disk");
+        let total = response.content_length();
+        tracing::debug!(size_bytes = ?total, "Download response received, streaming to disk");
+        let report = |progress: Progress| progress::report(self.progress.as_ref(), progress);
+        let downloading = |bytes| Progress::new(Phase::DownloadingBinary, None).bytes(bytes, total);
+        report(downloading(0));
 
         let mut file = tokio::fs::File::create(dest).await?;
-        file.write_all(&bytes).await?;
-        file.flush().await?;
+        let mut body = response.bytes_stream();
+        let mut downloaded = 0u64;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.context("Failed to download binary")?;
+            file.write_all(&chunk).await?;
+            downloaded += chunk.len() as u64;
+            report(downloading(downloaded));
+        }
+        file.sync_all().await?;
+        self.metrics.binary_download_bytes.inc_by(downloaded);
+        if total.is_none() {
+            report(downloading(downloaded).percent(100.0));
+        }
 
         tracing::info!(
             path = %dest.display(),
-            size_bytes = bytes.len(),
+            size_bytes = downloaded,
             "Binary downloaded successfully"
         );
         Ok(())
     }
 }
+
+/// `$LITERT_CACHE_DIR` if set, otherwise `<cache_dir>/litert-lm`
+pub fn default_cache_dir() -> Result<PathBuf> {
+    match env::var_os("LITERT_CACHE_DIR") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(dirs::cache_dir()
+            .context("Failed to get cache directory")?
+            .join("litert-lm")),
+    }
+}
+
+/// C library of a Linux system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Libc {
+    Glibc,
+    Musl,
+}
+
+/// The C library the system under `root` runs programs with, if it can tell
+///
+/// musl systems (such as Alpine) have an `ld-musl-*` loader in `/lib`. One that
+/// also has a glibc loader (`ld-linux-*`, e.g. from `gcompat`) can run glibc
+/// builds, so it counts as glibc.
+pub fn detect_libc(root: &Path) -> Option<Libc> {
+    let loaders: Vec<String> = ["lib", "lib64"]
+        .iter()
+        .filter_map(|dir| fs::read_dir(root.join(dir)).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    if loaders.iter().any(|name| name.starts_with("ld-linux")) {
+        Some(Libc::Glibc)
+    } else if loaders.iter().any(|name| name.starts_with("ld-musl-")) {
+        Some(Libc::Musl)
+    } else {
+        None
+    }
+}
+
+/// Release artifact of lit for a platform
+///
+/// The Linux builds link against glibc, so a musl system gets an error saying
+/// what to do rather than a binary that fails with `not found` when it runs.
+pub fn artifact_name(os: &str, arch: &str, libc: Option<Libc>) -> Result<&'static str> {
+    if libc == Some(Libc::Musl) {
+        anyhow::bail!(
+            "This system uses musl libc (e.g. Alpine Linux), but lit is only released for glibc. \
+             Install glibc compatibility (`apk add gcompat`), use a glibc-based image (e.g. Debian), \
+             or set `binary.artifact` in the config to a release artifact that runs here"
+        );
+    }
+    match (os, arch) {
+        ("linux", "aarch64") => Ok("lit.linux_arm64"),
+        ("linux", "x86_64") => Ok("lit.linux_x86_64"),
+        ("macos", "aarch64") => Ok("lit.macos_arm64"),
+        ("windows", "x86_64") => Ok("lit.windows_x86_64.exe"),
+        _ => anyhow::bail!(
+            "lit is not released for {}/{}; set `binary.artifact` in the config to a release artifact that runs here",
+            os,
+            arch
+        ),
+    }
+}
+
+/// Run `binary --version`, returning what it printed
+///
+/// Fails if the binary can't be executed, exits unsuccessfully, or hangs.
+pub async fn verify_binary(binary: &std::path::Path) -> Result<String> {
+    let output = tokio::time::timeout(
+        VERIFY_TIMEOUT,
+        tokio::process::Command::new(binary).arg("--version").kill_on_drop(true).output(),
+    )
+    .await
+    .with_context(|| format!("{} --version timed out", binary.display()))?
+    .with_context(|| format!("Failed to execute {}", binary.display()))?;
+
+    // Some builds print the version to stderr
+    let printed = [&output.stdout[..], &output.stderr[..]]
+        .iter()
+        .map(|stream| String::from_utf8_lossy(stream).trim().to_string())
+        .find(|text| !text.is_empty())
+        .unwrap_or_default();
+    if !output.status.success() {
+        anyhow::bail!("{} --version failed ({}): {}", binary.display(), output.status, printed);
+    }
+    tracing::debug!(version = %printed, "Verified binary");
+    Ok(printed)
+}
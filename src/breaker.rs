@@ -0,0 +1,247 @@
+//! Backing off models whose processes keep crashing
+//!
+//! A model whose lit processes crash `max_crashes` times within `window_secs`
+//! (for instance because GPU initialization fails every time) trips its
+//! circuit breaker. While the breaker is open, requests for the model fail
+//! fast with [`Unstable`] instead of hitting dead processes. Once the cooldown
+//! is over the breaker half-opens: the manager starts the model's pool afresh,
+//! and the first successful generation closes the breaker again. A crash while
+//! half-open reopens it with twice the cooldown, up to `max_cooldown_secs`.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::CircuitBreakerConfig;
+use crate::schedule::format_utc;
+
+/// Whether a model is being served
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    /// Requests fail fast until the cooldown is over
+    Open,
+    /// A fresh pool is on trial
+    HalfOpen,
+}
+
+/// A change of breaker state, reported to the breaker's listener
+#[derive(Debug, Clone, PartialEq)]
+pub enum CircuitChange {
+    Opened { crashes: usize, until: u64 },
+    HalfOpened,
+    Closed,
+}
+
+/// A model's breaker as reported by [`LitManager::circuit`](crate::LitManager::circuit)
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub model: String,
+    pub state: CircuitState,
+    /// Crashes within the window
+    pub recent_crashes: usize,
+    /// Times the breaker opened
+    pub trips: u32,
+    /// Unix timestamp (seconds) when an open breaker half-opens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<u64>,
+}
+
+/// Error for requests to a model whose breaker is open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unstable {
+    pub model: String,
+    pub crashes: usize,
+    pub window_secs: u64,
+    /// Unix timestamp (seconds) when the model is tried again
+    pub until: u64,
+}
+
+impl Unstable {
+    /// Seconds until the model is tried again
+    pub fn retry_after_secs(&self) -> u64 {
+        self.until.saturating_sub(unix_now()).max(1)
+    }
+}
+
+impl std::fmt::Display for Unstable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Model '{}' is unstable ({} crashes within {}s); backed off until {}",
+            self.model,
+            self.crashes,
+            self.window_secs,
+            format_utc(self.until)
+        )
+    }
+}
+
+impl std::error::Error for Unstable {}
+
+type Listener = Box<dyn Fn(&CircuitChange) + Send + Sync>;
+
+struct Inner {
+    state: CircuitState,
+    crashes: VecDeque<Instant>,
+    trips: u32,
+    cooldown: Duration,
+    // When an open breaker half-opens
+    reopen_at: Option<Instant>,
+    until: u64,
+}
+
+/// Crash tracking for one model
+pub struct CircuitBreaker {
+    model: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+    listener: Option<Listener>,
+}
+
+impl std::fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("model", &self.model)
+            .field("state", &self.inner.lock().unwrap().state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(model: impl Into<String>, config: CircuitBreakerConfig) -> Self {
+        let cooldown = Duration::from_secs(config.cooldown_secs);
+        Self {
+            model: model.into(),
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                crashes: VecDeque::new(),
+                trips: 0,
+                cooldown,
+                reopen_at: None,
+                until: 0,
+            }),
+            listener: None,
+        }
+    }
+
+    /// Call `listener` on every change of state
+    pub fn with_listener(mut self, listener: impl Fn(&CircuitChange) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Whether the model may be used; `Ok(true)` when the breaker just
+    /// half-opened, so the model's pool should be started afresh
+    pub fn check(&self) -> Result<bool, Unstable> {
+        let change = {
+            let mut inner = self.inner.lock().unwrap();
+            match inner.state {
+                CircuitState::Closed | CircuitState::HalfOpen => return Ok(false),
+                CircuitState::Open if inner.reopen_at.is_some_and(|at| Instant::now() < at) => {
+                    return Err(Unstable {
+                        model: self.model.clone(),
+                        crashes: inner.crashes.len(),
+                        window_secs: self.config.window_secs,
+                        until: inner.until,
+                    });
+                }
+                CircuitState::Open => {
+                    inner.state = CircuitState::HalfOpen;
+                    inner.reopen_at = None;
+                    CircuitChange::HalfOpened
+                }
+            }
+        };
+        tracing::info!(model = %self.model, "Circuit breaker half-open, restarting the model");
+        self.notify(&change);
+        Ok(true)
+    }
+
+    /// A process of the model crashed or failed to start
+    pub fn record_crash(&self) {
+        let change = {
+            let mut inner = self.inner.lock().unwrap();
+            let now = Instant::now();
+            let window = Duration::from_secs(self.config.window_secs);
+            inner.crashes.push_back(now);
+            while inner.crashes.front().is_some_and(|at| now.duration_since(*at) > window) {
+                inner.crashes.pop_front();
+            }
+            match inner.state {
+                CircuitState::Closed if inner.crashes.len() >= self.config.max_crashes.max(1) => {
+                    inner.cooldown = Duration::from_secs(self.config.cooldown_secs);
+                }
+                CircuitState::HalfOpen => {
+                    let max = Duration::from_secs(self.config.max_cooldown_secs.max(self.config.cooldown_secs));
+                    inner.cooldown = inner.cooldown.saturating_mul(2).min(max);
+                }
+                // Not tripping yet, or the other processes of an open breaker's pool
+                _ => return,
+            }
+            inner.state = CircuitState::Open;
+            inner.trips += 1;
+            inner.reopen_at = Some(now + inner.cooldown);
+            inner.until = unix_now() + inner.cooldown.as_secs();
+            CircuitChange::Opened {
+                crashes: inner.crashes.len(),
+                until: inner.until,
+            }
+        };
+        if let CircuitChange::Opened { crashes, until } = &change {
+            tracing::warn!(
+                model = %self.model,
+                crashes,
+                until = %format_utc(*until),
+                "Model keeps crashing, circuit breaker open"
+            );
+        }
+        self.notify(&change);
+    }
+
+    /// A generation on the model succeeded
+    pub fn record_success(&self) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.state != CircuitState::HalfOpen {
+                return;
+            }
+            inner.state = CircuitState::Closed;
+            inner.crashes.clear();
+            inner.cooldown = Duration::from_secs(self.config.cooldown_secs);
+        }
+        tracing::info!(model = %self.model, "Model recovered, circuit breaker closed");
+        self.notify(&CircuitChange::Closed);
+    }
+
+    pub fn status(&self) -> CircuitStatus {
+        let inner = self.inner.lock().unwrap();
+        CircuitStatus {
+            model: self.model.clone(),
+            state: inner.state,
+            recent_crashes: inner.crashes.len(),
+            trips: inner.trips,
+            until: (inner.state == CircuitState::Open).then_some(inner.until),
+        }
+    }
+
+    fn notify(&self, change: &CircuitChange) {
+        if let Some(listener) = &self.listener {
+            listener(change);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,202 @@
+//! Cooperative locking for a cache directory shared between processes or hosts
+//!
+//! Locks are plain files created with `O_EXCL` under `<cache_dir>/locks`, which
+//! unlike `flock` also works over NFS. The holder refreshes the file's mtime
+//! while it runs; a lock whose holder died (or whose mtime stopped moving) is
+//! considered stale and taken over by the next process that wants it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often a held lock's mtime is refreshed
+const HEARTBEAT: Duration = Duration::from_secs(15);
+/// A lock that hasn't been refreshed for this long is stale
+const STALE_AFTER: Duration = Duration::from_secs(120);
+const POLL: Duration = Duration::from_millis(250);
+
+/// Who holds a lock, as written into the lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub host: String,
+    pub acquired_at: u64,
+    /// Random for every acquisition, so a holder can tell its lock from a
+    /// later one taken by the same process
+    #[serde(default)]
+    pub nonce: String,
+}
+
+impl LockOwner {
+    /// This process on this host
+    pub fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: hostname(),
+            acquired_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            nonce: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+
+    /// Whether the owner is known to be gone: a dead process on this host
+    fn is_dead(&self) -> bool {
+        self.host == hostname() && self.pid != std::process::id() && !process_alive(self.pid)
+    }
+}
+
+/// A held cache lock; released when dropped
+#[derive(Debug)]
+pub struct CacheLock {
+    path: PathBuf,
+    nonce: String,
+    heartbeat: tokio::task::JoinHandle<()>,
+}
+
+impl CacheLock {
+    /// Wait up to `timeout` for the lock `name` in `cache_dir`, taking over stale locks
+    pub async fn acquire(cache_dir: &Path, name: &str, timeout: Duration) -> Result<Self> {
+        let dir = cache_dir.join("locks");
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create lock directory {}", dir.display()))?;
+        let path = dir.join(format!("{}.lock", sanitize(name)));
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut waiting = false;
+
+        loop {
+            match Self::try_create(&path) {
+                Ok(nonce) => {
+                    tracing::debug!(lock = %path.display(), "Acquired cache lock");
+                    let heartbeat = tokio::spawn(heartbeat(path.clone(), nonce.clone()));
+                    return Ok(Self { path, nonce, heartbeat });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create lock {}", path.display()));
+                }
+            }
+
+            if let Some(reason) = stale_reason(&path) {
+                tracing::warn!(lock = %path.display(), reason = %reason, "Recovering stale cache lock");
+                // Renaming first means only one waiter wins the takeover
+                let claimed = dir.join(format!(".{}.stale-{}", sanitize(name), uuid::Uuid::new_v4().simple()));
+                if fs::rename(&path, &claimed).is_ok() {
+                    // Another waiter may have recovered it and re-locked in between;
+                    // hand a fresh lock back (hard_link never overwrites)
+                    if stale_reason(&claimed).is_none() {
+                        let _ = fs::hard_link(&claimed, &path);
+                    }
+                    let _ = fs::remove_file(&claimed);
+                }
+                continue;
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                let owner = read_owner(&path)
+                    .map(|o| format!(" (held by pid {} on {})", o.pid, o.host))
+                    .unwrap_or_default();
+                anyhow::bail!("Timed out waiting for cache lock '{}'{}", name, owner);
+            }
+            if !waiting {
+                tracing::info!(lock = %path.display(), "Waiting for cache lock held by another process");
+                waiting = true;
+            }
+            tokio::time::sleep(POLL).await;
+        }
+    }
+
+    // Create the lock file; returns the nonce written into it
+    fn try_create(path: &Path) -> std::io::Result<String> {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        let owner = LockOwner::current();
+        file.write_all(&serde_json::to_vec(&owner).map_err(std::io::Error::other)?)?;
+        file.sync_all()?;
+        Ok(owner.nonce)
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        // Only remove the file if it is still ours; it may have been recovered
+        // as stale and taken again, possibly by this same process
+        if is_ours(&self.path, &self.nonce) {
+            if let Err(e) = fs::remove_file(&self.path) {
+                tracing::warn!(lock = %self.path.display(), error = %e, "Failed to release cache lock");
+            }
+        }
+    }
+}
+
+async fn heartbeat(path: PathBuf, nonce: String) {
+    let mut ticker = tokio::time::interval(HEARTBEAT);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        // Keeping someone else's lock fresh would hide it going stale
+        if !is_ours(&path, &nonce) {
+            tracing::warn!(lock = %path.display(), "Cache lock was taken over while held");
+            return;
+        }
+        let touched = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            tracing::warn!(lock = %path.display(), error = %e, "Failed to refresh cache lock");
+        }
+    }
+}
+
+/// Why the lock at `path` can be taken over, if it can
+fn stale_reason(path: &Path) -> Option<String> {
+    if let Some(owner) = read_owner(path) {
+        if owner.is_dead() {
+            return Some(format!("holder pid {} has exited", owner.pid));
+        }
+    }
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let idle = SystemTime::now().duration_since(modified).unwrap_or_default();
+    (idle > STALE_AFTER).then(|| format!("not refreshed for {}s", idle.as_secs()))
+}
+
+fn is_ours(path: &Path, nonce: &str) -> bool {
+    read_owner(path).is_some_and(|owner| owner.nonce == nonce)
+}
+
+fn read_owner(path: &Path) -> Option<LockOwner> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect()
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        // Without a portable liveness check, rely on the heartbeat going stale
+        true
+    }
+}
@@ -0,0 +1,73 @@
+//! Aborting downloads and generations from the embedding application
+//!
+//! A handle from [`LitManager::with_cancellation`](crate::LitManager::with_cancellation)
+//! checks its [`CancellationToken`] around everything that can take a while:
+//! downloading the lit binary, pulling models, starting a model's process
+//! pool, and generating. Cancelled operations fail with [`Cancelled`], after
+//! removing whatever they had half-downloaded.
+
+use anyhow::Result;
+use futures::stream::BoxStream;
+use futures::{Future, Stream, StreamExt};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+/// Error returned by an operation whose cancellation token was cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Run `future` until it finishes or `token` is cancelled, in which case the
+/// future is dropped; a token that is already cancelled stops it from starting
+pub(crate) async fn run<T>(token: Option<&CancellationToken>, future: impl Future<Output = Result<T>>) -> Result<T> {
+    let Some(token) = token else {
+        return future.await;
+    };
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(Cancelled.into()),
+        result = future => result,
+    }
+}
+
+/// Pass `stream` through until `token` is cancelled, then end it with a [`Cancelled`] error
+pub(crate) fn stream<T: Send + 'static>(
+    token: Option<CancellationToken>,
+    stream: impl Stream<Item = Result<T>> + Send + 'static,
+) -> BoxStream<'static, Result<T>> {
+    let Some(token) = token else {
+        return stream.boxed();
+    };
+    futures::stream::unfold((stream.boxed(), Some(token)), |(mut stream, token)| async move {
+        let token = token?;
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Some((Err(Cancelled.into()), (stream, None))),
+            item = stream.next() => item.map(|item| (item, (stream, Some(token)))),
+        }
+    })
+    // Callers may keep polling after the end (e.g. to send closing events)
+    .fuse()
+    .boxed()
+}
+
+/// A token cancelled once `a` or `b` is; the link is dropped with the guard
+pub(crate) fn either(a: &CancellationToken, b: &CancellationToken) -> (CancellationToken, DropGuard) {
+    let either = a.child_token();
+    let (b, linked, done) = (b.clone(), either.clone(), CancellationToken::new());
+    let guard = done.clone().drop_guard();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = b.cancelled() => linked.cancel(),
+            _ = linked.cancelled() => {}
+            _ = done.cancelled() => {}
+        }
+    });
+    (either, guard)
+}
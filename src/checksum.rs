@@ -0,0 +1,126 @@
+//! SHA-256 checksums of downloaded models
+//!
+//! After a pull, the model file lit stored is hashed and the digest kept in
+//! `model_checksums.json` under the cache directory. A digest given with the
+//! pull (`litert-lm pull --sha256`, or the one the Hugging Face Hub lists
+//! for the file) has to match, or the model is removed again.
+//! [`LitManager::verify`](crate::LitManager::verify) (`litert-lm verify`)
+//! hashes a model again later, to find a damaged file before lit fails to
+//! load it with an error that doesn't say why.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cache_lock::CacheLock;
+
+/// How long an update waits for another process updating the checksums
+const CHECKSUM_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The checksum recorded for a model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChecksumRecord {
+    pub model: String,
+    /// Lowercase hex digest
+    pub sha256: String,
+    pub size_bytes: u64,
+    /// The file that was hashed
+    pub path: PathBuf,
+    /// Unix timestamp (seconds) of the last time the file was hashed
+    pub checked_at: u64,
+}
+
+/// `sha256` in lowercase, if it is a hex SHA-256 digest
+pub fn parse_sha256(sha256: &str) -> Result<String> {
+    let sha256 = sha256.trim().trim_start_matches("sha256:");
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("'{}' is not a SHA-256 digest (64 hex digits)", sha256);
+    }
+    Ok(sha256.to_ascii_lowercase())
+}
+
+/// Hex SHA-256 digest and size of the file at `path`
+pub fn sha256_file(path: &Path) -> Result<(String, u64)> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf).with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    let digest: String = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok((digest, size))
+}
+
+/// [`sha256_file`] on a blocking thread; models are gigabytes
+pub async fn sha256_file_async(path: PathBuf) -> Result<(String, u64)> {
+    tokio::task::spawn_blocking(move || sha256_file(&path)).await?
+}
+
+/// Checksums of downloaded models, shared by every handle and process using
+/// the cache directory
+pub struct ChecksumStore {
+    path: PathBuf,
+}
+
+impl ChecksumStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Recorded checksums by model name; empty if nothing was recorded yet
+    pub fn load(&self) -> BTreeMap<String, ChecksumRecord> {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, model: &str) -> Option<ChecksumRecord> {
+        self.load().remove(model)
+    }
+
+    pub async fn record(&self, record: ChecksumRecord) -> Result<()> {
+        let _lock = self.lock().await?;
+        let mut all = self.load();
+        all.insert(record.model.clone(), record);
+        self.save(&all)
+    }
+
+    /// Forget a removed model
+    pub async fn forget(&self, model: &str) -> Result<()> {
+        let _lock = self.lock().await?;
+        let mut all = self.load();
+        if all.remove(model).is_some() {
+            self.save(&all)?;
+        }
+        Ok(())
+    }
+
+    async fn lock(&self) -> Result<CacheLock> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        CacheLock::acquire(dir, "checksums", CHECKSUM_LOCK_TIMEOUT).await
+    }
+
+    fn save(&self, all: &BTreeMap<String, ChecksumRecord>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so readers never see a partial file
+        let tmp = self.path.with_extension(format!("json.{}", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(all)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to update {}", self.path.display()))?;
+        Ok(())
+    }
+}
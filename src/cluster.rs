@@ -0,0 +1,378 @@
+//! Cluster mode
+//!
+//! A [`Cluster`] fronts several remote litert-lm servers so a rack of small
+//! devices appears as one OpenAI endpoint (`litert-lm router`). Nodes come from
+//! a static list or mDNS discovery. Periodic health checks track which nodes are
+//! up and which models they have downloaded; each request goes to the least busy
+//! healthy node with the model, failing over to the next one when a node is
+//! unreachable or returns a server error.
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// mDNS service type litert-lm servers advertise themselves under
+pub const SERVICE_TYPE: &str = "_litert-lm._tcp.local.";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Snapshot of one node, as reported by `/v1/cluster`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub url: String,
+    pub healthy: bool,
+    /// Models downloaded on the node, as of the last health check
+    pub models: Vec<String>,
+    /// Requests currently being proxied to the node
+    pub in_flight: usize,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Node {
+    url: String,
+    state: Mutex<NodeState>,
+    in_flight: AtomicUsize,
+}
+
+#[derive(Debug, Default)]
+struct NodeState {
+    healthy: bool,
+    models: Vec<String>,
+    last_error: Option<String>,
+}
+
+impl Node {
+    fn status(&self) -> NodeStatus {
+        let state = self.state.lock().unwrap();
+        NodeStatus {
+            url: self.url.clone(),
+            healthy: state.healthy,
+            models: state.models.clone(),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            last_error: state.last_error.clone(),
+        }
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.state.lock().unwrap().healthy
+    }
+
+    fn has_model(&self, model: &str) -> bool {
+        self.state.lock().unwrap().models.iter().any(|m| m == model)
+    }
+
+    fn mark_down(&self, error: String) {
+        tracing::warn!(node = %self.url, error = %error, "Cluster node marked unhealthy");
+        let mut state = self.state.lock().unwrap();
+        state.healthy = false;
+        state.last_error = Some(error);
+    }
+}
+
+/// Counts a request against a node until the proxied response is dropped
+#[derive(Debug)]
+pub struct InFlight(Arc<Node>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A response from the node that accepted a forwarded request
+#[derive(Debug)]
+pub struct Forwarded {
+    /// Base URL of the node that answered
+    pub node: String,
+    pub response: reqwest::Response,
+    /// Keep alive until the response body has been fully relayed
+    pub guard: InFlight,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// A set of remote litert-lm nodes behind one endpoint
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    nodes: Arc<RwLock<Vec<Arc<Node>>>>,
+    client: reqwest::Client,
+    // Rotates the starting node so equally busy nodes share the load
+    next: Arc<AtomicUsize>,
+}
+
+impl Cluster {
+    /// Create a cluster from node base URLs; nodes count as down until checked
+    pub fn new<I: IntoIterator<Item = String>>(urls: I) -> Self {
+        let cluster = Self {
+            nodes: Arc::new(RwLock::new(Vec::new())),
+            client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            next: Arc::new(AtomicUsize::new(0)),
+        };
+        for url in urls {
+            cluster.add_node(&url);
+        }
+        cluster
+    }
+
+    /// Add a node unless it is already known; returns whether it was added
+    pub fn add_node(&self, url: &str) -> bool {
+        let url = url.trim_end_matches('/').to_string();
+        let mut nodes = self.nodes.write().unwrap();
+        if nodes.iter().any(|node| node.url == url) {
+            return false;
+        }
+        nodes.push(Arc::new(Node {
+            url,
+            state: Mutex::new(NodeState::default()),
+            in_flight: AtomicUsize::new(0),
+        }));
+        true
+    }
+
+    /// Status of every node
+    pub fn nodes(&self) -> Vec<NodeStatus> {
+        self.nodes.read().unwrap().iter().map(|node| node.status()).collect()
+    }
+
+    /// Whether any node is currently healthy
+    pub fn is_healthy(&self) -> bool {
+        self.nodes.read().unwrap().iter().any(|node| node.is_healthy())
+    }
+
+    /// Models available on at least one healthy node
+    pub fn models(&self) -> Vec<String> {
+        let mut models: Vec<String> = self
+            .nodes()
+            .into_iter()
+            .filter(|node| node.healthy)
+            .flat_map(|node| node.models)
+            .collect();
+        models.sort();
+        models.dedup();
+        models
+    }
+
+    /// Check every node's `/health` and refresh its model list
+    pub async fn check_health(&self) {
+        let nodes: Vec<Arc<Node>> = self.nodes.read().unwrap().clone();
+        futures::future::join_all(nodes.iter().map(|node| self.check_node(node))).await;
+    }
+
+    async fn check_node(&self, node: &Node) {
+        let health = self
+            .client
+            .get(format!("{}/health", node.url))
+            .timeout(HEALTH_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = health {
+            if node.is_healthy() {
+                node.mark_down(e.to_string());
+            } else {
+                node.state.lock().unwrap().last_error = Some(e.to_string());
+            }
+            return;
+        }
+
+        let models = match self
+            .client
+            .get(format!("{}/v1/models", node.url))
+            .timeout(HEALTH_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => response.json::<ModelList>().await.ok(),
+            Err(_) => None,
+        };
+
+        let mut state = node.state.lock().unwrap();
+        if !state.healthy {
+            tracing::info!(node = %node.url, "Cluster node is healthy");
+        }
+        state.healthy = true;
+        state.last_error = None;
+        if let Some(list) = models {
+            state.models = list.data.into_iter().map(|m| m.id).collect();
+        }
+    }
+
+    /// Re-check node health every `interval` in the background
+    pub fn spawn_health_checks(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let cluster = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                cluster.check_health().await;
+            }
+        })
+    }
+
+    /// Healthy nodes in the order they should be tried: nodes that already have
+    /// the model first, then the least busy
+    fn candidates(&self, model: Option<&str>) -> Vec<Arc<Node>> {
+        let mut nodes: Vec<Arc<Node>> = self
+            .nodes
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|node| node.is_healthy())
+            .cloned()
+            .collect();
+        if !nodes.is_empty() {
+            let offset = self.next.fetch_add(1, Ordering::Relaxed) % nodes.len();
+            nodes.rotate_left(offset);
+        }
+        nodes.sort_by_key(|node| {
+            (
+                model.is_some_and(|model| !node.has_model(model)),
+                node.in_flight.load(Ordering::Relaxed),
+            )
+        });
+        nodes
+    }
+
+    /// POST `body` to `path` on the best node for `model`, failing over on
+    /// connection errors and 5xx responses
+    ///
+    /// Failover only happens before a response starts; a stream that breaks
+    /// midway is passed through as-is.
+    pub async fn forward(&self, path: &str, model: Option<&str>, headers: HeaderMap, body: Bytes) -> Result<Forwarded> {
+        let candidates = self.candidates(model);
+        if candidates.is_empty() {
+            anyhow::bail!("No healthy nodes in the cluster");
+        }
+
+        let mut last_failure = None;
+        for node in candidates {
+            node.in_flight.fetch_add(1, Ordering::Relaxed);
+            let guard = InFlight(node.clone());
+
+            let result = self
+                .client
+                .post(format!("{}{}", node.url, path))
+                .headers(headers.clone())
+                .body(body.clone())
+                .send()
+                .await;
+
+            match result {
+                Ok(response) if response.status().is_server_error() => {
+                    tracing::warn!(node = %node.url, status = %response.status(), "Node failed request, trying next");
+                    node.state.lock().unwrap().last_error = Some(format!("HTTP {}", response.status()));
+                    last_failure = Some(Forwarded {
+                        node: node.url.clone(),
+                        response,
+                        guard,
+                    });
+                }
+                Ok(response) => {
+                    tracing::debug!(node = %node.url, path = %path, "Forwarded request");
+                    return Ok(Forwarded {
+                        node: node.url.clone(),
+                        response,
+                        guard,
+                    });
+                }
+                Err(e) => node.mark_down(e.to_string()),
+            }
+        }
+
+        // Every node failed; surface the last server error if there was one
+        last_failure.ok_or_else(|| anyhow::anyhow!("All cluster nodes failed the request"))
+    }
+
+    /// Serve the cluster's OpenAI-compatible endpoint on `port`, checking node
+    /// health every `health_interval`
+    pub async fn serve(&self, port: u16, health_interval: Duration) -> Result<()> {
+        self.check_health().await;
+        let _health_checks = self.spawn_health_checks(health_interval);
+
+        let app = crate::server::create_cluster_router(self.clone());
+        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
+            .await
+            .context("Failed to bind to port")?;
+
+        let healthy = self.nodes().iter().filter(|node| node.healthy).count();
+        tracing::info!(nodes = self.nodes().len(), healthy, "Router listening on http://0.0.0.0:{}", port);
+
+        axum::serve(listener, app).await.context("Server error")?;
+        Ok(())
+    }
+
+    /// Add nodes advertised over mDNS as they appear
+    #[cfg(feature = "mdns")]
+    pub fn discover(&self) -> Result<tokio::task::JoinHandle<()>> {
+        let daemon = mdns_sd::ServiceDaemon::new().context("Failed to start mDNS daemon")?;
+        let events = daemon
+            .browse(SERVICE_TYPE)
+            .context("Failed to browse for litert-lm nodes")?;
+        let cluster = self.clone();
+
+        Ok(tokio::spawn(async move {
+            let _daemon = daemon;
+            while let Ok(event) = events.recv_async().await {
+                let mdns_sd::ServiceEvent::ServiceResolved(info) = event else {
+                    continue;
+                };
+                // Prefer IPv4 so a node reachable on several addresses is added once
+                let addresses = info.get_addresses();
+                let Some(address) = addresses
+                    .iter()
+                    .find(|address| address.is_ipv4())
+                    .or_else(|| addresses.iter().next())
+                else {
+                    continue;
+                };
+                let url = match address {
+                    std::net::IpAddr::V4(ip) => format!("http://{}:{}", ip, info.get_port()),
+                    std::net::IpAddr::V6(ip) => format!("http://[{}]:{}", ip, info.get_port()),
+                };
+                if cluster.add_node(&url) {
+                    tracing::info!(node = %url, service = %info.get_fullname(), "Discovered cluster node");
+                    cluster.check_health().await;
+                }
+            }
+        }))
+    }
+}
+
+/// Advertise a server on `port` over mDNS; it stays advertised while the
+/// returned daemon is alive
+#[cfg(feature = "mdns")]
+pub fn advertise(port: u16) -> Result<mdns_sd::ServiceDaemon> {
+    let daemon = mdns_sd::ServiceDaemon::new().context("Failed to start mDNS daemon")?;
+    let instance = format!("litert-lm-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let info = mdns_sd::ServiceInfo::new(
+        SERVICE_TYPE,
+        &instance,
+        &format!("{}.local.", instance),
+        "",
+        port,
+        None,
+    )
+    .context("Invalid mDNS service info")?
+    .enable_addr_auto();
+    daemon.register(info).context("Failed to advertise over mDNS")?;
+    tracing::info!(instance = %instance, port, "Advertising server over mDNS");
+    Ok(daemon)
+}
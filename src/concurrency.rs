@@ -0,0 +1,119 @@
+//! Per-model limits on requests in flight
+//!
+//! A pool's size bounds how many generations run at once, but not how many
+//! wait for a process. The `concurrency` config section caps the requests a
+//! model may have running or queued; the server answers requests beyond that
+//! with 429 rather than letting a heavyweight model's queue grow, so smaller
+//! models on the same device stay responsive. `max_queue` caps the requests
+//! waiting for a process of any one pool the same way.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::config::ConcurrencyConfig;
+use crate::tenant::matches_pattern;
+
+/// Error for a request to a model that is at its limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Saturated {
+    pub model: String,
+    pub limit: usize,
+}
+
+impl std::fmt::Display for Saturated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Model '{}' already has {} requests in flight, its limit; retry shortly",
+            self.model, self.limit
+        )
+    }
+}
+
+impl std::error::Error for Saturated {}
+
+/// Error for a request to a pool whose queue is full (see `concurrency.max_queue`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueFull {
+    pub model: String,
+    pub max_queue: usize,
+}
+
+impl std::fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Model '{}' already has {} requests waiting for a process; retry shortly",
+            self.model, self.max_queue
+        )
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+/// A request counted against its model's limit until dropped
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Requests in flight per model, against the configured limits
+#[derive(Debug, Default)]
+pub struct ConcurrencyLimits {
+    config: ConcurrencyConfig,
+    in_flight: Mutex<HashMap<String, Arc<AtomicUsize>>>,
+}
+
+impl ConcurrencyLimits {
+    pub fn from_config(config: &ConcurrencyConfig) -> Self {
+        Self {
+            config: config.clone(),
+            in_flight: Mutex::default(),
+        }
+    }
+
+    /// Limit of `model`: the most specific matching pattern's, else the
+    /// default; `None` for unlimited
+    pub fn limit(&self, model: &str) -> Option<usize> {
+        self.config
+            .limits
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, &limit)| limit)
+            .or(self.config.default_limit)
+    }
+
+    /// Requests of `model` running or queued right now
+    pub fn in_flight(&self, model: &str) -> usize {
+        self.in_flight
+            .lock()
+            .unwrap()
+            .get(model)
+            .map_or(0, |count| count.load(Ordering::Acquire))
+    }
+
+    /// Count a request against `model`'s limit, or fail if it is reached
+    pub fn try_acquire(&self, model: &str) -> Result<ConcurrencyPermit, Saturated> {
+        let in_flight = self.in_flight.lock().unwrap().entry(model.to_string()).or_default().clone();
+        let limit = self.limit(model);
+        let admitted = in_flight.fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| match limit {
+            Some(limit) if count >= limit => None,
+            _ => Some(count + 1),
+        });
+        match admitted {
+            Ok(_) => Ok(ConcurrencyPermit { in_flight }),
+            Err(_) => Err(Saturated {
+                model: model.to_string(),
+                limit: limit.unwrap_or_default(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,590 @@
+//! Optional configuration file
+//!
+//! Read from `$LITERT_CONFIG` if set, otherwise `<config_dir>/litert-lm/config.json`
+//! (e.g. `~/.config/litert-lm/config.json`). A missing file means defaults; a file
+//! that fails to parse is an error rather than being silently ignored.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::backend::Backend;
+use crate::constraints::Constraint;
+use crate::context::Overflow;
+use crate::keep_alive::KeepAlive;
+use crate::limits::ResourceLimits;
+use crate::sampling::Sampling;
+use crate::template::ChatTemplate;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Tools the agent loop can execute
+    pub tools: Vec<ToolConfig>,
+    /// External MCP servers whose tools are offered to the agent loop
+    pub mcp_servers: Vec<McpServerConfig>,
+    /// Router mode and node advertisement
+    pub cluster: ClusterConfig,
+    /// Tenants and the models they may use; empty means every caller is unrestricted
+    pub tenants: Vec<TenantConfig>,
+    /// API keys that may call the server with every model, in addition to
+    /// the tenants' keys and `$LITERT_API_KEYS`; with any key set, requests
+    /// without a known one are refused
+    pub api_keys: Vec<String>,
+    /// Context lengths and what to do with conversations that exceed them
+    pub context: ContextConfig,
+    /// Logical model names whose traffic is split between models
+    pub splits: Vec<SplitConfig>,
+    /// Reuse responses to repeated prompts
+    pub prompt_cache: PromptCacheConfig,
+    /// Server-wide limit on generated tokens per second
+    pub throttle: ThrottleConfig,
+    /// Default generation settings for a model, or for a new name served by one
+    pub profiles: Vec<ProfileConfig>,
+    /// Variants of model families (e.g. `gemma-3n`), largest first, picked by
+    /// device memory; replaces the built-in list for a family
+    pub variants: HashMap<String, Vec<VariantConfig>>,
+    /// Disk space downloaded models may take up
+    pub model_cache: ModelCacheConfig,
+    /// Registry manifests (URLs or paths, JSON or TOML) of models to list and
+    /// pull by name besides lit's registry; see [`RegistryManifest`](crate::registry::RegistryManifest)
+    pub registries: Vec<String>,
+    /// Which lit release artifact to download
+    pub binary: BinaryConfig,
+    /// Log files kept in addition to the terminal output
+    pub log: LogConfig,
+    /// Models loaded and unloaded at set times while serving
+    pub schedule: Vec<ScheduleConfig>,
+    /// Backing off models whose processes keep crashing
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Requests a model may have running or queued at once
+    pub concurrency: ConcurrencyConfig,
+    /// How long models stay loaded without requests
+    pub keep_alive: KeepAliveConfig,
+    /// Containment of lit processes
+    pub sandbox: SandboxConfig,
+    /// Memory, priority, and CPUs of lit processes
+    pub limits: LimitsConfig,
+    /// Housekeeping while serving
+    pub maintenance: MaintenanceConfig,
+    /// Embedding of ingested documents
+    pub retrieval: RetrievalConfig,
+    /// Hugging Face `tokenizer.json` files by model name or pattern (e.g.
+    /// `gemma-3n-*`), for token counts in `usage`; other models' counts are
+    /// estimated
+    pub tokenizers: HashMap<String, PathBuf>,
+    /// Backend lit runs a model on, by model name or pattern (e.g.
+    /// `{"gemma-3n-*": "cpu"}`); `auto`, the default, tries the GPU and falls
+    /// back to the CPU
+    pub backends: HashMap<String, Backend>,
+    /// Sampling settings lit starts a model's processes with, by model name
+    /// or pattern; requests override them
+    pub sampling: HashMap<String, Sampling>,
+    /// Chat templates by model name or pattern, overriding the built-in ones
+    /// (e.g. `"my-gemma-*": "gemma"`)
+    pub templates: HashMap<String, ChatTemplate>,
+    /// Seconds a generation may take, from when its prompt is sent, before it
+    /// fails with a 504 and its process is respawned (default 120, 0 disables);
+    /// `$LITERT_REQUEST_TIMEOUT_SECS` overrides it
+    pub request_timeout_secs: Option<u64>,
+    /// Seconds lit may go without printing during a generation before the
+    /// generation fails with a 504 and the process is respawned (default 60,
+    /// 0 disables); `$LITERT_STALL_TIMEOUT_SECS` overrides it
+    pub stall_timeout_secs: Option<u64>,
+    /// Completions a lit process serves before it is replaced by a fresh one
+    /// (unset or 0 never replaces it); `$LITERT_MAX_REQUESTS_PER_PROCESS`
+    /// overrides it
+    pub max_requests_per_process: Option<u64>,
+}
+
+/// Housekeeping run on a schedule while serving, so devices left unattended
+/// stay healthy; off by default
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    pub enabled: bool,
+    /// When to run, as a cron expression in UTC
+    pub at: String,
+    /// Partial downloads and temporary files older than this are removed;
+    /// younger ones may still be written to
+    pub stale_after_secs: u64,
+    /// Skip the steps that need the network (the registry refresh and the
+    /// update check)
+    pub offline: bool,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            at: "0 3 * * *".to_string(),
+            stale_after_secs: 24 * 60 * 60,
+            offline: false,
+        }
+    }
+}
+
+/// How documents are embedded for [retrieval](crate::retrieval)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetrievalConfig {
+    /// Directory of a sentence-embedding model (`config.json`,
+    /// `tokenizer.json`, and `model.safetensors`, e.g. a download of
+    /// `sentence-transformers/all-MiniLM-L6-v2`); needs the `embeddings`
+    /// feature. Unset, documents are embedded by hashing their words
+    pub embedding_model: Option<PathBuf>,
+}
+
+/// How lit processes are contained; everything is off by default, so lit
+/// runs with the server's own user, environment, and working directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// User to run lit as: a name, a uid, or `uid:gid`; switching users
+    /// requires the server to run as root
+    pub user: Option<String>,
+    /// Environment variables passed on to lit; unset passes the server's
+    /// whole environment
+    pub env: Option<Vec<String>>,
+    /// Working directory of lit processes
+    pub working_dir: Option<PathBuf>,
+    /// Linux: keep lit and its children from gaining privileges, e.g.
+    /// through setuid binaries
+    pub no_new_privileges: bool,
+    /// Linux: block syscalls an inference process never needs (ptrace,
+    /// mount, module loading, ...); implies `no_new_privileges`
+    pub seccomp: bool,
+}
+
+/// Resources lit processes may use, so that one runaway model can't take the
+/// host down; unlimited by default. See [`ResourceLimits`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// By model name or pattern (e.g. `gemma-3n-*`); the most specific
+    /// matching pattern applies
+    pub models: HashMap<String, ResourceLimits>,
+    /// Models no pattern matches
+    pub default: Option<ResourceLimits>,
+    /// Linux: a cgroup v2 directory the server may create groups in (e.g.
+    /// one delegated by systemd), with the memory controller enabled. Each
+    /// process then gets a group with `memory.max` set; without it memory is
+    /// limited through `RLIMIT_AS`.
+    pub cgroup: Option<PathBuf>,
+}
+
+/// Limits on requests in flight (running or waiting for a process) per
+/// model, independent of pool size; the server answers requests beyond a
+/// model's limit with 429
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    /// Limits by model name or pattern (e.g. `gemma-3n-*`); the most specific
+    /// matching pattern applies
+    pub limits: HashMap<String, usize>,
+    /// Limit of models no pattern matches; unlimited when unset
+    pub default_limit: Option<usize>,
+    /// Most requests a process pool lets wait for a free process; beyond
+    /// it requests are refused with 429. Unlimited when unset
+    pub max_queue: Option<usize>,
+}
+
+/// How long a model's pools stay loaded after its last request, unless a
+/// request asks for something else; see [`KeepAlive`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeepAliveConfig {
+    /// By model name or pattern (e.g. `gemma-3n-*`); the most specific
+    /// matching pattern applies
+    pub models: HashMap<String, KeepAlive>,
+    /// Models no pattern matches; loaded until unloaded when unset
+    pub default: Option<KeepAlive>,
+}
+
+/// When a model counts as unstable, and how long it is backed off
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    /// Crashes within `window_secs` that open the breaker
+    pub max_crashes: usize,
+    pub window_secs: u64,
+    /// How long the model is backed off the first time
+    pub cooldown_secs: u64,
+    /// Longest back-off, doubled up to after every failed retry
+    pub max_cooldown_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_crashes: 3,
+            window_secs: 60,
+            cooldown_secs: 30,
+            max_cooldown_secs: 600,
+        }
+    }
+}
+
+/// When to load and unload a model, as cron expressions in UTC (e.g. `0 22 * * *`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub model: String,
+    #[serde(default)]
+    pub load: Option<String>,
+    #[serde(default)]
+    pub unload: Option<String>,
+}
+
+/// Rotating log files, for servers with nobody watching their output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Write log files (also enabled by `--log-file`)
+    pub file: bool,
+    /// Defaults to `logs` under the cache directory
+    pub dir: Option<PathBuf>,
+    /// Least severe level written to the files (`error` to `trace`)
+    pub level: String,
+    /// Size at which the current file is rotated
+    pub max_bytes: u64,
+    /// Rotated files kept besides the current one
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            file: false,
+            dir: None,
+            level: "info".to_string(),
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// The lit binary downloaded from the LiteRT-LM release
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BinaryConfig {
+    /// Release artifact to download instead of the one picked for this
+    /// platform (e.g. `lit.linux_x86_64`); also lifts the musl check
+    pub artifact: Option<String>,
+}
+
+/// Limit on the space taken by downloaded models
+///
+/// A pull that would exceed `max_bytes` first removes the least recently used
+/// models that aren't pinned or loaded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelCacheConfig {
+    /// Unlimited when unset
+    pub max_bytes: Option<u64>,
+    /// Models that are never evicted
+    pub pinned: Vec<String>,
+    /// Only log (and count) what would be evicted, then pull anyway
+    pub dry_run: bool,
+    /// Where lit keeps downloaded models (`<dir>/<alias>`, a file or a
+    /// directory), for exporting them when `lit list` doesn't print paths
+    pub models_dir: Option<PathBuf>,
+}
+
+/// One size of a model family
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantConfig {
+    pub model: String,
+    /// Device memory needed, in GB
+    #[serde(default)]
+    pub min_memory_gb: f64,
+    #[serde(default)]
+    pub requires_gpu: bool,
+}
+
+/// Generation defaults for requests that name `name`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Model name requests use (e.g. `gemma-3n-E4B-json`)
+    pub name: String,
+    /// Model that serves the profile; defaults to `name`
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    /// Output is cut at the first of these
+    #[serde(default)]
+    pub stop: Vec<String>,
+    /// System message for conversations that don't have one
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Format the completion must satisfy (e.g. `{"type": "json"}`)
+    #[serde(default)]
+    pub constraint: Option<Constraint>,
+}
+
+/// Token bucket shared by every model's processes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThrottleConfig {
+    /// Average generated tokens per second; unlimited when unset
+    pub tokens_per_second: Option<f64>,
+    /// Tokens that may be generated at full speed after an idle period
+    pub burst_tokens: f64,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            tokens_per_second: None,
+            burst_tokens: 256.0,
+        }
+    }
+}
+
+/// Response cache for repeated prompts (same tenant, model, options, and text)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptCacheConfig {
+    pub enabled: bool,
+    /// Entries kept in memory
+    pub memory_entries: usize,
+    /// Also keep responses in `prompt_cache.db` under the cache directory, across restarts
+    pub persist: bool,
+    /// Size the database is trimmed to, least recently used entries first
+    pub max_disk_bytes: u64,
+}
+
+impl Default for PromptCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            memory_entries: 256,
+            persist: false,
+            max_disk_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Requests for `name` are split between `arms` in proportion to their weights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitConfig {
+    pub name: String,
+    pub arms: Vec<SplitArm>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitArm {
+    pub model: String,
+    pub weight: u32,
+}
+
+/// How conversations are fitted to a model's context window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContextConfig {
+    /// Context length in tokens by model pattern (e.g. `"gemma3-*": 8192`),
+    /// overriding the built-in table
+    pub lengths: HashMap<String, usize>,
+    /// Context length of models that match nothing
+    pub default_length: usize,
+    /// Tokens kept free for the reply
+    pub reserve_tokens: usize,
+    /// Drop (`truncate`) or `summarize` the oldest turns of a conversation that doesn't fit
+    pub overflow: Overflow,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            lengths: HashMap::new(),
+            default_length: 4096,
+            reserve_tokens: 512,
+            overflow: Overflow::default(),
+        }
+    }
+}
+
+/// A tenant on a shared machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenantConfig {
+    pub name: String,
+    /// API keys (sent as `Authorization: Bearer <key>`) that act as this tenant
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// Models the tenant may see and run; `*` is a wildcard (e.g. `gemma-*`)
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Whether the tenant may also pull and remove those models
+    #[serde(default)]
+    pub manage_models: bool,
+    /// Relative share of a saturated pool (a weight of 2 gets twice the slots of 1)
+    #[serde(default = "default_tenant_weight")]
+    pub weight: u32,
+}
+
+/// Settings for `litert-lm router` and for nodes joining a cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClusterConfig {
+    /// Base URLs of the litert-lm servers the router fronts
+    pub nodes: Vec<String>,
+    /// Also find nodes over mDNS (requires the `mdns` feature)
+    pub discover: bool,
+    /// Advertise `serve` over mDNS so routers can discover it (requires the `mdns` feature)
+    pub advertise: bool,
+    /// Seconds between node health checks
+    pub health_interval_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            discover: false,
+            advertise: false,
+            health_interval_secs: 10,
+        }
+    }
+}
+
+/// A shell or HTTP tool made available to the agent loop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolConfig {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema for the tool's arguments
+    #[serde(default = "empty_object_schema")]
+    pub parameters: serde_json::Value,
+    #[serde(flatten)]
+    pub kind: ToolKind,
+    #[serde(default = "default_tool_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ToolKind {
+    /// Run `command[0]` with the remaining elements as arguments; the call's JSON
+    /// arguments are written to stdin and stdout is the result
+    Shell { command: Vec<String> },
+    /// POST the call's JSON arguments to `url`; the response body is the result
+    Http { url: String },
+}
+
+/// An MCP server to connect to as a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Prefix for the server's tools (`<name>.<tool>`)
+    pub name: String,
+    #[serde(flatten)]
+    pub transport: McpServerTransport,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "lowercase")]
+pub enum McpServerTransport {
+    /// Spawn `command[0]` with the remaining elements as arguments and talk over stdio
+    Stdio {
+        command: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    /// Streamable HTTP endpoint
+    Http { url: String },
+}
+
+fn empty_object_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Request timeout when neither the config nor the environment sets one
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 120;
+
+/// Stall timeout when neither the config nor the environment sets one
+const DEFAULT_STALL_TIMEOUT_SECS: u64 = 60;
+
+fn default_tool_timeout_secs() -> u64 {
+    30
+}
+
+fn default_tenant_weight() -> u32 {
+    1
+}
+
+impl Config {
+    /// Path the config is read from
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var_os("LITERT_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::config_dir().map(|dir| dir.join("litert-lm").join("config.json")),
+        }
+    }
+
+    /// `api_keys` and the comma-separated keys in `$LITERT_API_KEYS`
+    pub fn api_keys(&self) -> Vec<String> {
+        let from_env = std::env::var("LITERT_API_KEYS").unwrap_or_default();
+        self.api_keys
+            .iter()
+            .map(String::as_str)
+            .chain(from_env.split(','))
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// How long a generation may take, from `$LITERT_REQUEST_TIMEOUT_SECS`,
+    /// then `request_timeout_secs`; `None` when disabled
+    pub fn request_timeout(&self) -> Option<Duration> {
+        let from_env = std::env::var("LITERT_REQUEST_TIMEOUT_SECS").ok().and_then(|secs| secs.trim().parse().ok());
+        match from_env.or(self.request_timeout_secs).unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// How long lit may go without printing during a generation, from
+    /// `$LITERT_STALL_TIMEOUT_SECS`, then `stall_timeout_secs`; `None` when disabled
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        let from_env = std::env::var("LITERT_STALL_TIMEOUT_SECS").ok().and_then(|secs| secs.trim().parse().ok());
+        match from_env.or(self.stall_timeout_secs).unwrap_or(DEFAULT_STALL_TIMEOUT_SECS) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Completions a lit process serves before it is replaced, from
+    /// `$LITERT_MAX_REQUESTS_PER_PROCESS`, then `max_requests_per_process`;
+    /// `None` when processes are never replaced
+    pub fn max_requests_per_process(&self) -> Option<u64> {
+        let from_env = std::env::var("LITERT_MAX_REQUESTS_PER_PROCESS").ok().and_then(|n| n.trim().parse().ok());
+        from_env.or(self.max_requests_per_process).filter(|&max| max > 0)
+    }
+
+    /// Load the config file, falling back to defaults when it doesn't exist
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            tracing::debug!(path = %path.display(), "No config file, using defaults");
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let config: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        tracing::debug!(path = %path.display(), "Loaded config file");
+        Ok(config)
+    }
+}
@@ -0,0 +1,88 @@
+//! Output constraints for small models
+//!
+//! LiteRT-LM has no constrained sampling, so constraints are enforced after the
+//! fact: the generated text is validated and, on a violation, the model is
+//! re-prompted with the reason until it complies or the retry budget runs out
+//! (see [`generation`](crate::generation)).
+
+use serde::{Deserialize, Serialize};
+
+/// A format the whole completion must satisfy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Constraint {
+    /// The trimmed output must fully match this regular expression
+    Regex { pattern: String },
+    /// The output must be exactly one of these options (case-insensitive)
+    Choice { options: Vec<String> },
+    /// The output must be a single JSON value (code fences are stripped)
+    Json,
+}
+
+impl Constraint {
+    /// Reject constraints that can never be checked, such as invalid patterns
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Constraint::Regex { pattern } => full_match(pattern).map(|_| ()),
+            Constraint::Choice { options } if options.is_empty() => {
+                Err("choice constraint needs at least one option".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Instruction telling the model what shape of answer is expected
+    pub fn describe(&self) -> String {
+        match self {
+            Constraint::Regex { pattern } => {
+                format!("text matching the regular expression `{}`", pattern)
+            }
+            Constraint::Choice { options } => format!("exactly one of: {}", options.join(", ")),
+            Constraint::Json => "a single valid JSON value".to_string(),
+        }
+    }
+
+    /// Validate a completion, returning the normalized output or why it was rejected
+    pub fn validate(&self, output: &str) -> Result<String, String> {
+        let output = output.trim();
+        match self {
+            Constraint::Regex { pattern } => {
+                if full_match(pattern)?.is_match(output) {
+                    Ok(output.to_string())
+                } else {
+                    Err(format!("`{}` does not match `{}`", output, pattern))
+                }
+            }
+            Constraint::Choice { options } => {
+                let candidate = output.trim_end_matches(['.', '!']).trim_matches(['"', '\'', '`']);
+                options
+                    .iter()
+                    .find(|option| option.eq_ignore_ascii_case(candidate))
+                    .cloned()
+                    .ok_or_else(|| format!("`{}` is not one of: {}", output, options.join(", ")))
+            }
+            Constraint::Json => {
+                let unfenced = output
+                    .strip_prefix("```json")
+                    .or_else(|| output.strip_prefix("```"))
+                    .and_then(|rest| rest.strip_suffix("```"))
+                    .unwrap_or(output)
+                    .trim();
+                serde_json::from_str::<serde_json::Value>(unfenced)
+                    .map(|_| unfenced.to_string())
+                    .map_err(|e| format!("not valid JSON: {}", e))
+            }
+        }
+    }
+}
+
+/// Compile a regex constraint to match the whole output
+///
+/// The `regex` crate runs in time linear in the input, and the compiled size is
+/// capped so a request can't make the server build a huge automaton.
+fn full_match(pattern: &str) -> Result<regex::Regex, String> {
+    regex::RegexBuilder::new(&format!("^(?:{})$", pattern))
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("invalid regex `{}`: {}", pattern, e))
+}
@@ -0,0 +1,317 @@
+//! Context window management
+//!
+//! lit silently truncates (or fails on) prompts longer than the model's
+//! context. Conversations are fitted to the window before prompting instead:
+//! system messages and the latest turn are always kept, and the oldest turns in
+//! between are dropped or, with [`Overflow::Summarize`], replaced by a summary
+//! the model writes itself.
+//!
+//! Every count, of the conversation, the summary request, and the summary
+//! itself, goes through one [`Tokenizer`]: the model's, or the length-based
+//! [`Estimate`].
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+use crate::config::ContextConfig;
+use crate::template::ChatTemplate;
+use crate::tenant::matches_pattern;
+use crate::tokenizer::{Estimate, Tokenizer};
+
+/// Context lengths of models in the lit registry, by name pattern
+const KNOWN_LENGTHS: &[(&str, usize)] = &[
+    ("gemma-3n-*", 4096),
+    ("gemma3-1b*", 4096),
+    ("gemma3-*", 8192),
+    ("qwen2.5-*", 4096),
+    ("phi-4-mini*", 4096),
+];
+
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation: ";
+
+/// What happens to the oldest turns of a conversation that doesn't fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Drop them
+    #[default]
+    Truncate,
+    /// Replace them with a summary written by the model
+    Summarize,
+}
+
+/// One message of a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub role: String,
+    pub content: String,
+}
+
+impl Turn {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+
+    fn is_system(&self) -> bool {
+        self.role == "system"
+    }
+}
+
+/// Render turns as a plain `role: content` transcript (see [`ChatTemplate`])
+pub fn render(turns: &[Turn]) -> String {
+    turns
+        .iter()
+        .map(|turn| format!("{}: {}", turn.role, turn.content))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The token budget of one model
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextWindow {
+    /// Context length in tokens
+    pub length: usize,
+    /// Tokens kept free for the reply
+    pub reserve: usize,
+    pub overflow: Overflow,
+}
+
+impl ContextWindow {
+    /// Window for `model`: the config's most specific matching pattern, then
+    /// the built-in table, then the configured default
+    pub fn for_model(config: &ContextConfig, model: &str) -> Self {
+        let configured = config
+            .lengths
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, &length)| length);
+        let known = KNOWN_LENGTHS
+            .iter()
+            .find(|(pattern, _)| matches_pattern(pattern, model))
+            .map(|&(_, length)| length);
+
+        Self {
+            length: configured.or(known).unwrap_or(config.default_length),
+            reserve: config.reserve_tokens,
+            overflow: config.overflow,
+        }
+    }
+
+    /// Tokens available to the prompt
+    pub fn budget(&self) -> usize {
+        self.length.saturating_sub(self.reserve)
+    }
+
+    fn fits(&self, turns: &[Turn], template: ChatTemplate, tokenizer: &dyn Tokenizer) -> bool {
+        tokenizer.count(&template.render(turns)) <= self.budget()
+    }
+
+    /// Tokens set aside for a summary of dropped turns
+    fn summary_budget(&self) -> usize {
+        (self.budget() / 4).min(256)
+    }
+}
+
+/// A conversation fitted to a context window
+#[derive(Debug, Clone)]
+pub struct Fitted {
+    pub prompt: String,
+    /// Turns removed from the conversation (summarized or not)
+    pub dropped: usize,
+    pub summarized: bool,
+}
+
+/// Even the system messages and the latest turn don't fit the window
+#[derive(Debug, Clone)]
+pub struct ContextOverflow {
+    pub tokens: usize,
+    pub limit: usize,
+}
+
+impl std::fmt::Display for ContextOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Prompt needs about {} tokens but the model's context allows {}",
+            self.tokens, self.limit
+        )
+    }
+}
+
+impl std::error::Error for ContextOverflow {}
+
+/// Fit `turns` into `window`, calling `generate` to summarize dropped turns
+/// when the window asks for it
+///
+/// A failed summary falls back to dropping the turns.
+pub async fn fit<G, Fut>(turns: Vec<Turn>, window: &ContextWindow, generate: G) -> anyhow::Result<Fitted>
+where
+    G: FnOnce(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    fit_with(turns, window, ChatTemplate::Plain, &Estimate, generate).await
+}
+
+/// [`fit`], rendering the prompt (and the summary request) in `template` and
+/// counting tokens with `tokenizer`
+pub async fn fit_with<G, Fut>(
+    turns: Vec<Turn>,
+    window: &ContextWindow,
+    template: ChatTemplate,
+    tokenizer: &dyn Tokenizer,
+    generate: G,
+) -> anyhow::Result<Fitted>
+where
+    G: FnOnce(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let total = tokenizer.count(&template.render(&turns));
+    if total <= window.budget() {
+        return Ok(Fitted {
+            prompt: template.render(&turns),
+            dropped: 0,
+            summarized: false,
+        });
+    }
+
+    // Only turns between the system messages and the latest turn can go
+    let last = turns.len().saturating_sub(1);
+    let droppable: Vec<usize> = (0..last).filter(|&i| !turns[i].is_system()).collect();
+    let pinned: Vec<Turn> = turns
+        .iter()
+        .enumerate()
+        .filter(|(i, turn)| turn.is_system() || *i == last)
+        .map(|(_, turn)| turn.clone())
+        .collect();
+    if !window.fits(&pinned, template, tokenizer) {
+        return Err(ContextOverflow {
+            tokens: tokenizer.count(&template.render(&pinned)),
+            limit: window.budget(),
+        }
+        .into());
+    }
+
+    let headroom = match window.overflow {
+        // Room for the summary turn, including its role and prefix
+        Overflow::Summarize => window.summary_budget() + tokenizer.count(SUMMARY_PREFIX) + 3,
+        Overflow::Truncate => 0,
+    };
+    let target = ContextWindow {
+        reserve: window.reserve + headroom,
+        ..window.clone()
+    };
+    // Without the `dropped` oldest droppable turns
+    let keep = |dropped: usize| -> Vec<Turn> {
+        let cutoff = droppable[..dropped].last().map_or(0, |&i| i + 1);
+        turns
+            .iter()
+            .enumerate()
+            .filter(|(i, turn)| *i >= cutoff || turn.is_system())
+            .map(|(_, turn)| turn.clone())
+            .collect()
+    };
+
+    // Drop the fewest oldest turns that make the rest fit. Each turn's tokens
+    // are counted once and taken off the total as it goes; templates don't
+    // render turns quite independently (Gemma folds system messages into the
+    // next turn), so the rendered result is checked and the cut moved from
+    // there, which takes a step or two at most.
+    let empty = tokenizer.count(&template.render(&[]));
+    let mut excess = total.saturating_sub(target.budget());
+    let mut dropped = 0;
+    while excess > 0 && dropped < droppable.len() {
+        let turn = &turns[droppable[dropped]];
+        excess = excess.saturating_sub(tokenizer.count(&template.render(std::slice::from_ref(turn))).saturating_sub(empty));
+        dropped += 1;
+    }
+    while dropped < droppable.len() && !target.fits(&keep(dropped), template, tokenizer) {
+        dropped += 1;
+    }
+    while dropped > 1 && target.fits(&keep(dropped - 1), template, tokenizer) {
+        dropped -= 1;
+    }
+    let mut kept = keep(dropped);
+    // With only the pinned turns left there may be no room for a summary
+    let summarize = window.overflow == Overflow::Summarize && target.fits(&kept, template, tokenizer);
+    tracing::debug!(dropped, budget = window.budget(), summarize, "Conversation exceeds context window");
+
+    let mut summarized = false;
+    if summarize {
+        let old: Vec<Turn> = droppable[..dropped].iter().map(|&i| turns[i].clone()).collect();
+        match summarize_turns(&old, window, template, tokenizer, generate).await {
+            Ok(summary) => {
+                let at = kept.iter().take_while(|turn| turn.is_system()).count();
+                kept.insert(
+                    at,
+                    Turn::new("system", format!("{}{}", SUMMARY_PREFIX, summary)),
+                );
+                summarized = true;
+            }
+            Err(e) => tracing::warn!(error = %e, "Failed to summarize dropped turns, dropping them instead"),
+        }
+    }
+
+    Ok(Fitted {
+        prompt: template.render(&kept),
+        dropped,
+        summarized,
+    })
+}
+
+/// Ask the model for a summary of `turns` no longer than the window's summary budget
+async fn summarize_turns<G, Fut>(
+    turns: &[Turn],
+    window: &ContextWindow,
+    template: ChatTemplate,
+    tokenizer: &dyn Tokenizer,
+    generate: G,
+) -> anyhow::Result<String>
+where
+    G: FnOnce(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let instruction = "Summarize the conversation above in a few sentences, keeping the names, \
+                       facts, and decisions needed to continue it.";
+    // Other templates get the transcript quoted in a single user turn
+    let request = |transcript: &str| match template {
+        ChatTemplate::Plain => format!("{}\n{}", transcript, render(&[Turn::new("user", instruction)])),
+        _ => template.render_prompt(None, &format!("{} {}", transcript, instruction)),
+    };
+    // The summarization prompt must fit too; keep the most recent part of the transcript
+    let room = window.budget().saturating_sub(tokenizer.count(&request("")) + 1);
+    let transcript = ChatTemplate::Plain.render(turns);
+    let transcript = clip(&transcript, room, tokenizer, true);
+
+    let prompt = request(transcript);
+    let summary = generate(prompt).await?;
+    let summary = summary.trim();
+    if summary.is_empty() {
+        anyhow::bail!("Model returned an empty summary");
+    }
+    Ok(clip(summary, window.summary_budget(), tokenizer, false).to_string())
+}
+
+/// The longest start of `text` (or end, with `from_end`) of at most `limit` tokens
+fn clip<'a>(text: &'a str, limit: usize, tokenizer: &dyn Tokenizer, from_end: bool) -> &'a str {
+    let bounds: Vec<usize> = text.char_indices().map(|(i, _)| i).chain([text.len()]).collect();
+    let chars = bounds.len() - 1;
+    let part = |kept: usize| match from_end {
+        true => &text[bounds[chars - kept]..],
+        false => &text[..bounds[kept]],
+    };
+    // Binary search on the characters kept; counts grow with the text
+    let (mut low, mut high) = (0, chars);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if tokenizer.count(part(mid)) <= limit {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    part(low)
+}
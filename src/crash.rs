@@ -0,0 +1,155 @@
+//! Crash reports for lit child processes
+//!
+//! When a child exits while the process loop still expects it to be running,
+//! a JSON report is written to `<cache_dir>/crashes/` and an error event is
+//! logged, so GPU driver crashes can be triaged after the fact.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+
+use crate::history::unix_now;
+
+/// Bytes of stderr kept per process, for crash reports and diagnostics
+const STDERR_TAIL_BYTES: usize = 16 * 1024;
+
+/// Rolling buffer of a child's most recent stderr lines, up to
+/// [`STDERR_TAIL_BYTES`]
+#[derive(Debug, Clone, Default)]
+pub struct StderrTail {
+    lines: Arc<Mutex<Lines>>,
+}
+
+#[derive(Debug, Default)]
+struct Lines {
+    lines: VecDeque<String>,
+    bytes: usize,
+}
+
+impl StderrTail {
+    pub fn push(&self, text: &str) {
+        let mut tail = self.lines.lock().unwrap();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            tail.bytes += line.len();
+            tail.lines.push_back(line.to_string());
+        }
+        // The newest line is kept even if it is longer than the limit
+        while tail.bytes > STDERR_TAIL_BYTES && tail.lines.len() > 1 {
+            let dropped = tail.lines.pop_front().unwrap_or_default();
+            tail.bytes -= dropped.len();
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<String> {
+        self.lines.lock().unwrap().lines.iter().cloned().collect()
+    }
+
+    /// The newest `n` lines, oldest first
+    pub fn last(&self, n: usize) -> Vec<String> {
+        let tail = self.lines.lock().unwrap();
+        tail.lines.iter().skip(tail.lines.len().saturating_sub(n)).cloned().collect()
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, so reports can be matched to prompts without storing them
+pub fn prompt_hash(prompt: &str) -> String {
+    let hash = prompt.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub model: String,
+    pub backend: String,
+    pub pid: Option<u32>,
+    /// Unix timestamp (seconds) when the exit was noticed
+    pub crashed_at: u64,
+    pub exit_status: String,
+    pub exit_code: Option<i32>,
+    /// Terminating signal, on Unix
+    pub signal: Option<i32>,
+    /// Hash of the prompt being generated when the process died, if any
+    pub in_flight_prompt_hash: Option<String>,
+    pub stderr_tail: Vec<String>,
+}
+
+impl CrashReport {
+    pub fn new(
+        model: &str,
+        backend: &str,
+        pid: Option<u32>,
+        status: Option<ExitStatus>,
+        in_flight_prompt_hash: Option<String>,
+        stderr_tail: Vec<String>,
+    ) -> Self {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.and_then(|s| s.signal())
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            model: model.to_string(),
+            backend: backend.to_string(),
+            pid,
+            crashed_at: unix_now(),
+            exit_status: status
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            exit_code: status.and_then(|s| s.code()),
+            signal,
+            in_flight_prompt_hash,
+            stderr_tail,
+        }
+    }
+
+    /// Write the report as `<dir>/<timestamp>-<model>-<pid>.json`
+    pub fn write_to(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create crash report directory {}", dir.display()))?;
+
+        let model: String = self
+            .model
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+        let path = dir.join(format!(
+            "{}-{}-{}.json",
+            self.crashed_at,
+            model,
+            self.pid.unwrap_or(0)
+        ));
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write crash report {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Log the crash as an error event and, if a directory is given, persist it
+    pub fn emit(&self, dir: Option<&Path>) {
+        let path = dir.and_then(|dir| match self.write_to(dir) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to write crash report");
+                None
+            }
+        });
+
+        tracing::error!(
+            model = %self.model,
+            backend = %self.backend,
+            pid = ?self.pid,
+            exit_status = %self.exit_status,
+            in_flight_prompt_hash = ?self.in_flight_prompt_hash,
+            report = ?path,
+            last_stderr = ?self.stderr_tail.last(),
+            "lit process crashed"
+        );
+    }
+}
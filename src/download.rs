@@ -0,0 +1,168 @@
+//! Resumable model downloads
+//!
+//! A pull that lit downloads itself starts from zero every time it fails. For
+//! models pulled from an `http(s)://` URL (including resolved `s3://` and
+//! `gs://` references), the manager downloads the file into `downloads/`
+//! under the cache directory first and hands it to lit over a loopback
+//! connection. A try that fails leaves a `.partial` file, and the next one
+//! (the pull's retry, or a pull run again later) asks for the rest with an
+//! HTTP `Range` request. Servers that ignore ranges send the whole file again.
+//! Turned off with
+//! [`LitManager::with_resumable_downloads`](crate::LitManager::with_resumable_downloads)
+//! or `litert-lm pull --resume=false`.
+//!
+//! Servers that want credentials other than a Hugging Face token get them as
+//! extra request headers, set with
+//! [`LitManager::with_download_headers`](crate::LitManager::with_download_headers)
+//! or `litert-lm pull --header`. Pulls with headers are always downloaded
+//! here, since lit can't send them.
+
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+use crate::retry::Permanent;
+
+/// Directory under the cache directory holding downloads in progress
+pub const DOWNLOADS_DIR: &str = "downloads";
+
+/// Whether lit's pull of `target` can be downloaded here instead
+pub fn is_resumable(target: &str) -> bool {
+    target.starts_with("https://") || target.starts_with("http://")
+}
+
+/// Where the download of `url` goes under `dir`: a directory per URL, holding
+/// the file under the URL's file name, which lit names the model after
+pub fn path_for(dir: &Path, url: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|file| !file.is_empty() && !file.contains(':'))
+        .unwrap_or("model");
+    dir.join(format!("{:016x}", hasher.finish())).join(file)
+}
+
+/// A `Name: value` header, as given to `litert-lm pull --header`
+pub fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue)> {
+    let (name, value) = header
+        .split_once(':')
+        .with_context(|| format!("'{}' is not a header; expected 'Name: value'", header))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .with_context(|| format!("'{}' is not a header name", name.trim()))?;
+    let mut value = HeaderValue::from_str(value.trim()).with_context(|| format!("Invalid value for header {}", name))?;
+    // Kept out of logs and debug output
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+/// The `.partial` file a download of `dest` is written to
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+/// Download `url` to `dest`, continuing a partial file an earlier try left;
+/// `headers` are sent along, and take the place of the bearer `token` if they
+/// include `Authorization`. `progress` gets the bytes so far and the total,
+/// when known. Returns the file's size.
+pub async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    token: Option<&str>,
+    headers: &HeaderMap,
+    mut progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64> {
+    if let Ok(done) = tokio::fs::metadata(dest).await {
+        // Finished by a try whose pull failed afterwards
+        return Ok(done.len());
+    }
+    let partial = partial_path(dest);
+    if let Some(dir) = partial.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let offset = tokio::fs::metadata(&partial).await.map(|meta| meta.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request = request.headers(headers.clone());
+    let response = request.send().await.context("Download request failed")?;
+    let status = response.status();
+    let resumed = match status.as_u16() {
+        206 => {
+            // Only the rest of the file is appended to it
+            let range = response.headers().get(reqwest::header::CONTENT_RANGE);
+            let expected = format!("bytes {}-", offset);
+            if !range.and_then(|range| range.to_str().ok()).is_some_and(|range| range.starts_with(&expected)) {
+                let _ = tokio::fs::remove_file(&partial).await;
+                bail!("Server sent another range than asked for; starting over");
+            }
+            true
+        }
+        // The partial file is the whole file
+        416 if offset > 0 => {
+            tokio::fs::rename(&partial, dest).await?;
+            return Ok(offset);
+        }
+        _ if status.is_success() => false,
+        _ => {
+            let message = format!("Download failed: HTTP {}", status);
+            // A missing or forbidden file won't appear by asking again
+            if status.is_client_error() && !matches!(status.as_u16(), 408 | 429) {
+                return Err(anyhow::Error::new(Permanent).context(message));
+            }
+            bail!(message);
+        }
+    };
+
+    let mut done = if resumed { offset } else { 0 };
+    if resumed {
+        tracing::info!(url = %url, offset, "Resuming download");
+    }
+    let total = response.content_length().map(|len| len + done);
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&partial)
+        .await
+        .with_context(|| format!("Failed to open {}", partial.display()))?;
+    progress(done, total);
+    let mut body = response.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // What arrived is kept for the next try
+                file.flush().await?;
+                return Err(anyhow::Error::new(e).context("Download interrupted"));
+            }
+        };
+        file.write_all(&chunk).await?;
+        done += chunk.len() as u64;
+        progress(done, total);
+    }
+    file.sync_all().await?;
+    if let Some(total) = total.filter(|&total| done < total) {
+        bail!("Download ended after {} of {} bytes", done, total);
+    }
+    tokio::fs::rename(&partial, dest).await?;
+    Ok(done)
+}
@@ -0,0 +1,224 @@
+//! Adapter for DSPy-rs structured prompts
+//!
+//! DSPy sends the same long format preamble (field declarations, structure
+//! rules, and the task's instructions) ahead of every question. Small models
+//! do better with just the question, so the server strips the preamble and
+//! wraps the answer in DSPy's field markers itself. Parsed preambles are kept
+//! in a [`SignatureCache`] keyed by their hash, so repeated calls skip the
+//! parsing and reuse the exact same system prompt for the instructions.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use crate::context::{self, Turn};
+use crate::template::ChatTemplate;
+
+/// Signatures kept by default
+const DEFAULT_CAPACITY: usize = 64;
+
+/// Where the question starts after the preamble
+const QUESTION_MARKER: &str = "user: [[ ## ";
+
+/// Check if this is a DSpy-rs formatted prompt by looking for multiple specific patterns
+pub fn is_dspy_request(prompt: &str) -> bool {
+    // DSpy-rs has very specific patterns - we need at least 3 of these to be confident:
+    // 1. "Your input fields are:" or "Your output fields are:"
+    // 2. Field markers like "[[ ## field_name ## ]]"
+    // 3. "All interactions will be structured"
+    // 4. "Given the fields" instruction pattern
+
+    let has_field_declaration = prompt.contains("Your input fields are:")
+        || prompt.contains("Your output fields are:");
+    let has_field_markers = prompt.contains("[[ ## ") && prompt.contains(" ## ]]");
+    let has_structure_instruction = prompt.contains("All interactions will be structured");
+    let has_completion_marker = prompt.contains("[[ ## completed ## ]]")
+        || prompt.contains("ending with the marker for `completed`");
+
+    // Require at least 3 of these patterns to be present
+    let pattern_count = [
+        has_field_declaration,
+        has_field_markers,
+        has_structure_instruction,
+        has_completion_marker,
+    ].iter().filter(|&&x| x).count();
+
+    pattern_count >= 3
+}
+
+/// Extract output field names from DSpy-rs formatted prompt
+fn extract_dspy_output_fields(prompt: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+
+    // Look for "Your output fields are:" section
+    if let Some(output_section) = prompt.split("Your output fields are:").nth(1) {
+        // Extract field names from lines like "1. `field_name` (String)"
+        for line in output_section.lines() {
+            if let Some(field_start) = line.find('`') {
+                if let Some(field_end) = line[field_start + 1..].find('`') {
+                    let field_name = &line[field_start + 1..field_start + 1 + field_end];
+                    fields.push(field_name.to_string());
+                }
+            }
+            // Stop at the next section
+            if line.contains("All interactions will be structured") {
+                break;
+            }
+        }
+    }
+
+    fields
+}
+
+/// The task's instructions, from DSPy's "your objective is:" paragraph
+fn extract_dspy_instructions(preamble: &str) -> Option<String> {
+    let objective = preamble.split("your objective is:").nth(1)?;
+    let instructions = objective.split("\n\n").next().unwrap_or(objective);
+    let instructions = instructions.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!instructions.is_empty()).then_some(instructions)
+}
+
+/// Extract the actual user question from DSpy-rs formatted prompt
+pub fn extract_dspy_question(prompt: &str) -> Option<String> {
+    // Find the user's actual question after the format template
+    // Look for pattern: user: [[ ## <field> ## ]]\n<actual_question>
+    if let Some(user_section) = prompt.split(QUESTION_MARKER).nth(1) {
+        if let Some(question_start) = user_section.find("## ]]\n") {
+            let question = &user_section[question_start + 6..];
+            return Some(question.trim().to_string());
+        }
+    }
+    None
+}
+
+/// The part of a DSpy prompt before the question, which is the same for
+/// every call with one signature
+pub fn preamble(prompt: &str) -> &str {
+    prompt.split(QUESTION_MARKER).next().unwrap_or(prompt)
+}
+
+/// Format LLM response with DSpy-rs field markers
+pub fn format_dspy_response(llm_output: &str, output_fields: &[String]) -> String {
+    let cleaned_output = llm_output.trim();
+
+    // For now, put the entire response in the first output field
+    // This is a simple heuristic - could be improved with better parsing
+    let mut formatted = String::new();
+
+    if let Some(first_field) = output_fields.first() {
+        formatted.push_str(&format!("[[ ## {} ## ]]\n", first_field));
+        formatted.push_str(cleaned_output);
+        formatted.push_str("\n\n");
+    }
+
+    // Add completion marker
+    formatted.push_str("[[ ## completed ## ]]\n");
+
+    formatted
+}
+
+/// What a DSpy preamble declares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Names of the fields the answer goes into, in order
+    pub output_fields: Vec<String>,
+    /// The task, e.g. "Given the fields `question`, produce the fields `answer`."
+    pub instructions: Option<String>,
+    /// Rendered system turn carrying the instructions, shared by every call
+    system_prompt: Option<String>,
+}
+
+impl Signature {
+    pub fn parse(preamble: &str) -> Self {
+        let instructions = extract_dspy_instructions(preamble);
+        Self {
+            output_fields: extract_dspy_output_fields(preamble),
+            system_prompt: instructions
+                .as_deref()
+                .map(|instructions| context::render(&[Turn::new("system", instructions)])),
+            instructions,
+        }
+    }
+
+    /// The prompt a small model gets for `question`: the instructions, if
+    /// any, then the question alone
+    pub fn prompt_for(&self, question: &str) -> String {
+        match &self.system_prompt {
+            Some(system_prompt) => format!("{}\n{}", system_prompt, question),
+            None => question.to_string(),
+        }
+    }
+
+    /// [`Signature::prompt_for`], in a model's chat template
+    pub fn prompt_in(&self, template: ChatTemplate, question: &str) -> String {
+        match template {
+            ChatTemplate::Plain => self.prompt_for(question),
+            _ => template.render_prompt(self.instructions.as_deref(), question),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_hash: HashMap<u64, Arc<Signature>>,
+    /// Oldest first, for eviction
+    order: VecDeque<u64>,
+}
+
+/// Parsed signatures keyed by the hash of their preamble
+#[derive(Debug)]
+pub struct SignatureCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl SignatureCache {
+    /// Keep up to `capacity` signatures, dropping the oldest beyond that
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Signature of a DSpy `prompt`, parsed on first sight of its preamble
+    pub fn get(&self, prompt: &str) -> Arc<Signature> {
+        let preamble = preamble(prompt);
+        let mut hasher = DefaultHasher::new();
+        preamble.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(signature) = entries.by_hash.get(&key) {
+            tracing::trace!(signature = key, "Reusing parsed DSpy signature");
+            return signature.clone();
+        }
+
+        let signature = Arc::new(Signature::parse(preamble));
+        tracing::debug!(signature = key, fields = ?signature.output_fields, "Parsed DSpy signature");
+        if entries.order.len() >= self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.by_hash.remove(&oldest);
+            }
+        }
+        entries.order.push_back(key);
+        entries.by_hash.insert(key, signature.clone());
+        signature
+    }
+
+    /// Number of cached signatures
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
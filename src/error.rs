@@ -0,0 +1,66 @@
+//! Errors callers can tell apart
+//!
+//! Fallible functions return [`anyhow::Error`], with context added on the way
+//! up. Failures a caller may want to handle differently (a model that isn't
+//! downloaded, a lit binary that can't be had, a damaged model file, a
+//! process that died, a generation that ran out of time) are a [`LitError`] underneath, found with
+//! [`LitError::of`]; the server maps them to status codes. Other errors of
+//! their own kind, such as [`Cancelled`](crate::Cancelled),
+//! [`QueueFull`](crate::QueueFull), or [`Unstable`](crate::Unstable), are
+//! downcast the same way.
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LitError {
+    /// lit doesn't have the model; it needs to be pulled first
+    #[error("Model '{model}' is not downloaded; pull it first")]
+    ModelNotFound { model: String },
+    /// The lit binary couldn't be downloaded, verified, or installed
+    #[error("lit binary is unavailable: {reason}")]
+    BinaryUnavailable { reason: String },
+    /// Pulling a model failed
+    #[error("Failed to pull model '{model}'{}", detail(.reason))]
+    DownloadFailed { model: String, reason: String },
+    /// A model file's SHA-256 isn't the one expected: given with the pull,
+    /// listed by the Hub, or recorded when the model was pulled
+    #[error("Model '{model}' is damaged: its SHA-256 is {actual}, expected {expected}")]
+    ChecksumMismatch { model: String, expected: String, actual: String },
+    /// lit exited while a prompt was running or queued on it; `stderr` holds
+    /// the last lines it wrote there, oldest first
+    #[error("lit process died{}", detail(&join(.stderr)))]
+    ProcessCrashed { stderr: Vec<String> },
+    /// A generation didn't finish within the request timeout; its process is
+    /// killed and the pool restarted
+    #[error("Generation did not finish within {}s", .after.as_secs())]
+    Timeout { after: Duration },
+    /// lit printed nothing for the stall timeout during a generation; its
+    /// process is killed and respawned
+    #[error("lit stopped generating: no output for {}s", .after.as_secs())]
+    Stalled { after: Duration },
+}
+
+impl LitError {
+    /// The `LitError` behind `error`: the error itself or one of its causes
+    pub fn of(error: &anyhow::Error) -> Option<&LitError> {
+        error.chain().find_map(|cause| cause.downcast_ref())
+    }
+
+    /// Whether the generation ran out of time, by the request or the stall
+    /// timeout
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, LitError::Timeout { .. } | LitError::Stalled { .. })
+    }
+}
+
+fn join(lines: &[String]) -> String {
+    lines.join("; ")
+}
+
+// ": <text>" unless there is none
+fn detail(text: &str) -> String {
+    match text.trim() {
+        "" => String::new(),
+        text => format!(": {}", text),
+    }
+}
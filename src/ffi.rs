@@ -0,0 +1,291 @@
+//! LiteRT-LM linked in-process (`ffi` feature)
+//!
+//! Instead of running lit and reading its REPL up to the prompt marker,
+//! [`FfiBackend`] calls the LiteRT-LM C API (`c/engine.h`) directly: text is
+//! handed over through a callback as it is generated, a generation ends when
+//! the engine says so (or once nobody reads the reply), and no process sits
+//! in between. The bindings are generated with bindgen from the header in
+//! `$LITERT_LM_INCLUDE_DIR`, and the library is linked at build time from
+//! `$LITERT_LM_LIB_DIR` (named by `$LITERT_LM_LIB`, `litert_lm_c` by default).
+//!
+//! Models are `.litertlm` files, registered by name or given by path. A
+//! model's engine is created on first use and kept loaded; its generations run
+//! one at a time on blocking threads.
+//! [`with_ffi_backend`](crate::LitManager::with_ffi_backend) serves a
+//! manager's completions this way, loading models from where they were pulled.
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::BoxFuture;
+use futures::{FutureExt, StreamExt};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::backend::{Backend, InferenceBackend, TextStream};
+
+/// Extension of files taken as model paths rather than registered names
+const MODEL_EXTENSION: &str = ".litertlm";
+
+/// How often a generation checks whether its reply is still read
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Completions from LiteRT-LM models loaded into this process
+#[derive(Debug, Default)]
+pub struct FfiBackend {
+    models: HashMap<String, PathBuf>,
+    backend: Backend,
+    // Loaded engines by model name; the lock is held while one loads
+    engines: tokio::sync::Mutex<HashMap<String, Arc<Engine>>>,
+}
+
+impl FfiBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the `.litertlm` file at `path` as `model`
+    pub fn with_model(mut self, model: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.models.insert(model.into(), path.into());
+        self
+    }
+
+    /// Hardware engines are created on; [`Backend::Auto`], the default, tries
+    /// the GPU, then the CPU
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Models with a loaded engine
+    pub async fn loaded(&self) -> Vec<String> {
+        let mut models: Vec<String> = self.engines.lock().await.keys().cloned().collect();
+        models.sort();
+        models
+    }
+
+    /// Free `model`'s engine once the generations using it are done; returns
+    /// whether it was loaded
+    pub async fn unload(&self, model: &str) -> bool {
+        self.engines.lock().await.remove(model).is_some()
+    }
+
+    /// Whether `model` is registered, or is the path of a model file
+    pub fn has_model(&self, model: &str) -> bool {
+        self.path(model).is_ok()
+    }
+
+    /// Stream the completion of `prompt` by `model`, loading its engine from
+    /// `path` unless it is loaded already
+    pub async fn complete_stream_at(&self, model: &str, path: &Path, prompt: &str) -> Result<TextStream> {
+        let engine = self.engine(model, Some(path)).await?;
+        Self::generate(engine, prompt)
+    }
+
+    fn generate(engine: Arc<Engine>, prompt: &str) -> Result<TextStream> {
+        let prompt = CString::new(prompt).context("Prompt contains a NUL byte")?;
+        let (tx, rx) = mpsc::channel(32);
+        tokio::task::spawn_blocking(move || engine.generate(&prompt, tx));
+        Ok(ReceiverStream::new(rx).boxed())
+    }
+
+    fn path(&self, model: &str) -> Result<PathBuf> {
+        if let Some(path) = self.models.get(model) {
+            return Ok(path.clone());
+        }
+        if model.ends_with(MODEL_EXTENSION) && Path::new(model).is_file() {
+            return Ok(PathBuf::from(model));
+        }
+        bail!("Unknown model '{}': register its .litertlm file with FfiBackend::with_model", model)
+    }
+
+    async fn engine(&self, model: &str, path: Option<&Path>) -> Result<Arc<Engine>> {
+        let mut engines = self.engines.lock().await;
+        if let Some(engine) = engines.get(model) {
+            return Ok(engine.clone());
+        }
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => self.path(model)?,
+        };
+        let backend = self.backend;
+        tracing::info!(model = %model, path = %path.display(), backend = %backend, "Loading LiteRT-LM engine");
+        let engine = tokio::task::spawn_blocking(move || match backend {
+            Backend::Auto => Engine::load(&path, Backend::Gpu).or_else(|e| {
+                tracing::warn!(error = %e, "GPU engine failed; falling back to CPU");
+                Engine::load(&path, Backend::Cpu)
+            }),
+            backend => Engine::load(&path, backend),
+        })
+        .await??;
+        let engine = Arc::new(engine);
+        engines.insert(model.to_string(), engine.clone());
+        Ok(engine)
+    }
+}
+
+impl InferenceBackend for FfiBackend {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        async move {
+            let engine = self.engine(model, None).await?;
+            Self::generate(engine, prompt)
+        }
+        .boxed()
+    }
+}
+
+/// A model loaded by LiteRT-LM
+#[derive(Debug)]
+struct Engine {
+    raw: NonNull<sys::LiteRtLmEngine>,
+    // Held for the length of a generation
+    turn: Mutex<()>,
+}
+
+// SAFETY: the engine is only used through the C API, and sessions are
+// created and run one at a time under `turn`
+unsafe impl Send for Engine {}
+unsafe impl Sync for Engine {}
+
+impl Engine {
+    fn load(path: &Path, backend: Backend) -> Result<Self> {
+        let model_path = CString::new(path.to_string_lossy().as_bytes()).context("Model path contains a NUL byte")?;
+        let backend_name = CString::new(backend.as_str()).expect("backend names have no NUL bytes");
+        // SAFETY: the strings outlive the calls; settings are freed right after
+        // the engine is created from them
+        let raw = unsafe {
+            let settings = sys::litert_lm_engine_settings_create(
+                model_path.as_ptr(),
+                backend_name.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+            );
+            if settings.is_null() {
+                bail!("LiteRT-LM rejected the settings for {}", path.display());
+            }
+            let engine = sys::litert_lm_engine_create(settings);
+            sys::litert_lm_engine_settings_delete(settings);
+            engine
+        };
+        let raw = NonNull::new(raw)
+            .ok_or_else(|| anyhow!("LiteRT-LM could not load {} on the {}", path.display(), backend))?;
+        Ok(Self { raw, turn: Mutex::new(()) })
+    }
+
+    /// Run `prompt` in a fresh session, sending the text as it comes; blocks
+    /// until the engine is done, or the reply is no longer read
+    fn generate(&self, prompt: &CStr, tx: mpsc::Sender<Result<String>>) {
+        let _turn = self.turn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // SAFETY: a null config asks for the defaults
+        let session = unsafe { sys::litert_lm_engine_create_session(self.raw.as_ptr(), std::ptr::null_mut()) };
+        if session.is_null() {
+            let _ = tx.blocking_send(Err(anyhow!("LiteRT-LM could not start a session")));
+            return;
+        }
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let call = Call { tx, done: done_tx, finished: AtomicBool::new(false) };
+        let input = sys::InputData {
+            type_: sys::InputDataType::kInputText,
+            data: prompt.as_ptr().cast(),
+            size: prompt.to_bytes().len(),
+        };
+        // SAFETY: `call`, `input`, and `session` outlive the generation: they
+        // are only dropped once the session is deleted, which stops it
+        unsafe {
+            let status = sys::litert_lm_session_generate_content_stream(
+                session,
+                &input,
+                1,
+                Some(on_chunk),
+                &call as *const Call as *mut c_void,
+            );
+            if status == 0 {
+                loop {
+                    match done_rx.recv_timeout(CANCEL_POLL) {
+                        Err(RecvTimeoutError::Timeout) if call.tx.is_closed() => {
+                            // Deleting the session below stops the generation
+                            tracing::debug!("LiteRT-LM reply no longer read; stopping the generation");
+                            call.finished.store(true, Ordering::Release);
+                            break;
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        _ => break,
+                    }
+                }
+            } else {
+                let _ = call.tx.blocking_send(Err(anyhow!("LiteRT-LM failed to generate (status {})", status)));
+            }
+            sys::litert_lm_session_delete(session);
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // SAFETY: no session is left; each is deleted when its generation ends
+        unsafe { sys::litert_lm_engine_delete(self.raw.as_ptr()) }
+    }
+}
+
+// What the stream callback reports to
+struct Call {
+    tx: mpsc::Sender<Result<String>>,
+    done: std::sync::mpsc::Sender<()>,
+    // Set once the generation ended (or was given up); callbacks after that
+    // are ignored
+    finished: AtomicBool,
+}
+
+impl Call {
+    // Report the end, once
+    fn finish(&self) {
+        if !self.finished.swap(true, Ordering::AcqRel) {
+            let _ = self.done.send(());
+        }
+    }
+}
+
+// Called by LiteRT-LM for every chunk of text, on a thread of its own or the
+// generating one; a reply nobody reads anymore ends the generation
+unsafe extern "C" fn on_chunk(data: *mut c_void, chunk: *const c_char, is_final: bool, error: *const c_char) {
+    // SAFETY: `data` is the `Call` passed by `generate`, alive until the
+    // session is deleted
+    let call = unsafe { &*(data as *const Call) };
+    if call.finished.load(Ordering::Acquire) {
+        return;
+    }
+    if !error.is_null() {
+        // SAFETY: LiteRT-LM passes NUL-terminated strings
+        let message = unsafe { CStr::from_ptr(error) }.to_string_lossy();
+        let _ = call.tx.blocking_send(Err(anyhow!("LiteRT-LM: {}", message)));
+        call.finish();
+        return;
+    }
+    if call.tx.is_closed() {
+        call.finish();
+        return;
+    }
+    if !chunk.is_null() {
+        // SAFETY: as above
+        let text = unsafe { CStr::from_ptr(chunk) }.to_string_lossy().into_owned();
+        if !text.is_empty() {
+            let _ = call.tx.blocking_send(Ok(text));
+        }
+    }
+    if is_final {
+        call.finish();
+    }
+}
+
+// Generated by build.rs from LiteRT-LM's `c/engine.h`
+#[allow(non_upper_case_globals, non_camel_case_types, non_snake_case, dead_code)]
+mod sys {
+    include!(concat!(env!("OUT_DIR"), "/litert_lm.rs"));
+}
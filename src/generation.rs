@@ -0,0 +1,335 @@
+//! Generation options shared by the library, HTTP, and MCP entry points
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::constraints::Constraint;
+use crate::context::Turn;
+use crate::lit_args::LitArgs;
+use crate::sampling::Sampling;
+use crate::stream::Chunking;
+use crate::template::ChatTemplate;
+
+/// Upper bound on `best_of`, so one request can't monopolize a pool
+pub const MAX_CANDIDATES: usize = 16;
+
+/// Options for a single generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenOptions {
+    /// Format the completion must satisfy
+    pub constraint: Option<Constraint>,
+    /// Re-prompts allowed after a constraint violation
+    pub constraint_retries: usize,
+    /// Completions to return
+    pub n: usize,
+    /// Candidates to generate (in parallel) and choose the best `n` from;
+    /// defaults to `n`
+    pub best_of: Option<usize>,
+    /// How candidates are ranked when `best_of` exceeds one
+    pub scorer: Scorer,
+    /// Whether a cached response may be returned, when the prompt cache is enabled
+    pub cache: bool,
+    /// Sampling temperature; the model's default when unset
+    pub temperature: Option<f32>,
+    /// Nucleus sampling threshold; the model's default when unset
+    pub top_p: Option<f32>,
+    /// Tokens sampled from; the model's default when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Seed of the sampler, for repeatable output; lit's own when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// The completion is cut at the first of these
+    pub stop: Vec<String>,
+    /// Most tokens to generate; longer completions are cut there and finish
+    /// with `length`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// lit flags to generate with; served by a pool started with them
+    #[serde(skip_serializing_if = "LitArgs::is_empty")]
+    pub args: LitArgs,
+    /// How a streamed completion is divided into chunks; the server's
+    /// default when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<Chunking>,
+    /// Send generation stats as an `x_litert.stats` event at the end of a
+    /// stream
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stats: bool,
+    /// Send the prompt as written, without the model's chat template
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub raw: bool,
+    /// How long a library call may take, waiting for a process included;
+    /// it then fails with [`LitError::Timeout`](crate::LitError::Timeout).
+    /// The server and MCP tools use `request_timeout_secs` instead.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+}
+
+impl Default for GenOptions {
+    fn default() -> Self {
+        Self {
+            constraint: None,
+            constraint_retries: 2,
+            n: 1,
+            best_of: None,
+            scorer: Scorer::default(),
+            cache: true,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            seed: None,
+            stop: Vec::new(),
+            max_tokens: None,
+            args: LitArgs::default(),
+            chunking: None,
+            stats: false,
+            raw: false,
+            timeout: None,
+        }
+    }
+}
+
+impl GenOptions {
+    /// Reject options that can never be satisfied
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(constraint) = &self.constraint {
+            constraint.check()?;
+        }
+        let candidates = self.candidates();
+        if self.n == 0 {
+            return Err("n must be at least 1".to_string());
+        }
+        if candidates < self.n {
+            return Err(format!("best_of ({}) must be at least n ({})", candidates, self.n));
+        }
+        if candidates > MAX_CANDIDATES {
+            return Err(format!("best_of can be at most {}", MAX_CANDIDATES));
+        }
+        self.sampling().check()?;
+        if self.stop.iter().any(String::is_empty) {
+            return Err("stop sequences can't be empty".to_string());
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be at least 1".to_string());
+        }
+        self.args.check()
+    }
+
+    /// Sampling settings lit should generate with; unset ones are the model's
+    pub fn sampling(&self) -> Sampling {
+        Sampling {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            seed: self.seed,
+        }
+    }
+
+    /// Number of candidates that will be generated
+    pub fn candidates(&self) -> usize {
+        self.best_of.unwrap_or(self.n)
+    }
+}
+
+/// `text` up to the earliest of the `stop` sequences
+pub fn truncate_at_stop(mut text: String, stop: &[String]) -> String {
+    if let Some(end) = stop.iter().filter_map(|s| text.find(s.as_str())).min() {
+        text.truncate(end);
+    }
+    text
+}
+
+/// Ranks best-of-n candidates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scorer {
+    /// Favor longer answers (logarithmically) that don't repeat themselves
+    #[default]
+    Length,
+    /// Ask the model to rate each candidate from 1 to 10
+    SelfEval,
+}
+
+/// Score of a candidate under [`Scorer::Length`]: `ln(1 + words)` scaled by
+/// the share of distinct words, so truncated and looping answers rank low
+pub fn length_score(text: &str) -> f64 {
+    let words: Vec<String> = text.split_whitespace().map(|w| w.to_lowercase()).collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+    let distinct = words.iter().collect::<std::collections::HashSet<_>>().len();
+    (1.0 + words.len() as f64).ln() * distinct as f64 / words.len() as f64
+}
+
+/// Every attempt violated the requested constraint
+#[derive(Debug, Clone)]
+pub struct ConstraintViolation {
+    pub attempts: usize,
+    pub reason: String,
+    pub last_output: String,
+}
+
+impl std::fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Output violated the constraint after {} attempts: {}",
+            self.attempts, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+/// Run `generate` until its text satisfies the options' constraint
+///
+/// `generate` returns the completion text plus any per-attempt data (such as
+/// timing); the data of the accepted attempt is returned alongside the
+/// normalized text. Without a constraint this is a single call. Output is cut
+/// at the options' stop sequences before it is validated. The instruction and
+/// re-prompts are added to `prompt` as turns in its `template`.
+pub async fn generate_with<G, Fut, T>(
+    prompt: &str,
+    template: ChatTemplate,
+    options: &GenOptions,
+    mut generate: G,
+) -> anyhow::Result<(String, T)>
+where
+    G: FnMut(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<(String, T)>>,
+{
+    let Some(constraint) = &options.constraint else {
+        let (output, data) = generate(prompt.to_string()).await?;
+        return Ok((truncate_at_stop(output, &options.stop), data));
+    };
+    constraint.check().map_err(anyhow::Error::msg)?;
+
+    let instruction = Turn::new("system", format!("Reply with only {}.", constraint.describe()));
+    let mut transcript = template.continue_with(prompt, &[instruction]);
+    let attempts = options.constraint_retries + 1;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let (output, data) = generate(transcript.clone()).await?;
+        let output = truncate_at_stop(output, &options.stop);
+        match constraint.validate(&output) {
+            Ok(valid) => return Ok((valid, data)),
+            Err(reason) if attempt >= attempts => {
+                return Err(ConstraintViolation {
+                    attempts,
+                    reason,
+                    last_output: output,
+                }
+                .into());
+            }
+            Err(reason) => {
+                tracing::debug!(attempt, reason = %reason, "Constraint violated, re-prompting");
+                let retry = [
+                    Turn::new("assistant", output.trim()),
+                    Turn::new(
+                        "user",
+                        format!("That answer is invalid ({}). Reply with only {}.", reason, constraint.describe()),
+                    ),
+                ];
+                transcript = template.continue_with(&transcript, &retry);
+            }
+        }
+    }
+}
+
+/// Generate `options.best_of` candidates concurrently and return the best
+/// `options.n`, best first
+///
+/// Candidates that fail (including constraint violations) are skipped; the
+/// first error is returned only if every candidate failed.
+pub async fn generate_best<G, Fut, T>(
+    prompt: &str,
+    template: ChatTemplate,
+    options: &GenOptions,
+    generate: G,
+) -> anyhow::Result<Vec<(String, T)>>
+where
+    G: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<(String, T)>>,
+{
+    options.check().map_err(anyhow::Error::msg)?;
+    let count = options.candidates();
+    if count == 1 {
+        return Ok(vec![generate_with(prompt, template, options, &generate).await?]);
+    }
+
+    let results = futures::future::join_all((0..count).map(|_| generate_with(prompt, template, options, &generate))).await;
+    let mut first_error = None;
+    let mut candidates = Vec::new();
+    for result in results {
+        match result {
+            Ok(candidate) => candidates.push(candidate),
+            Err(e) => {
+                tracing::debug!(error = %e, "Best-of candidate failed");
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if candidates.is_empty() {
+        return Err(first_error.expect("at least one candidate was generated"));
+    }
+
+    let scores: Vec<(f64, f64)> = match options.scorer {
+        Scorer::Length => candidates.iter().map(|(text, _)| (length_score(text), 0.0)).collect(),
+        Scorer::SelfEval => {
+            let ratings = futures::future::join_all(
+                candidates.iter().map(|(text, _)| self_eval(prompt, template, text, &generate)),
+            )
+            .await;
+            // Ties between equal ratings go to the length heuristic
+            ratings
+                .into_iter()
+                .zip(&candidates)
+                .map(|(rating, (text, _))| (rating, length_score(text)))
+                .collect()
+        }
+    };
+
+    let mut ranked: Vec<_> = scores.into_iter().zip(candidates).collect();
+    ranked.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    tracing::debug!(
+        candidates = ranked.len(),
+        best_score = ranked[0].0 .0,
+        scorer = ?options.scorer,
+        "Ranked best-of candidates"
+    );
+    Ok(ranked.into_iter().take(options.n).map(|(_, candidate)| candidate).collect())
+}
+
+/// The model's 1-10 rating of `answer`, or 0 if it couldn't produce one
+async fn self_eval<G, Fut, T>(prompt: &str, template: ChatTemplate, answer: &str, generate: &G) -> f64
+where
+    G: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<(String, T)>>,
+{
+    let review = [
+        Turn::new("assistant", answer.trim()),
+        Turn::new(
+            "user",
+            "Rate how well the previous answer responds to the request, from 1 (poor) to 10 (excellent).",
+        ),
+    ];
+    let review = template.continue_with(prompt, &review);
+    let options = GenOptions {
+        constraint: Some(Constraint::Regex {
+            pattern: "10|[1-9]".to_string(),
+        }),
+        ..Default::default()
+    };
+    match generate_with(&review, template, &options, generate).await {
+        Ok((rating, _)) => rating.parse().unwrap_or(0.0),
+        Err(e) => {
+            tracing::debug!(error = %e, "Self-evaluation failed");
+            0.0
+        }
+    }
+}
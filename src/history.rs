@@ -0,0 +1,276 @@
+//! Persistent request history
+//!
+//! Every completion (library, HTTP, or MCP) is recorded in an embedded SQLite
+//! database under the cache directory, so changes in prompt behavior can be
+//! traced over time. Only metadata is stored by default; prompt and response
+//! text are kept when content recording is enabled (`LITERT_HISTORY_CONTENT=1`).
+//! Set `LITERT_HISTORY=off` to disable recording entirely.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStatus {
+    Success,
+    Error,
+}
+
+impl HistoryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HistoryStatus::Success => "success",
+            HistoryStatus::Error => "error",
+        }
+    }
+}
+
+impl std::str::FromStr for HistoryStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "success" | "ok" => Ok(HistoryStatus::Success),
+            "error" | "failed" => Ok(HistoryStatus::Error),
+            _ => anyhow::bail!("Unknown status '{}' (expected success or error)", s),
+        }
+    }
+}
+
+/// One recorded completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub id: String,
+    pub model: String,
+    /// Where the request came from: `library`, `http`, or `mcp`
+    pub source: String,
+    /// Unix timestamp (seconds) when the request started
+    pub created_at: u64,
+    pub duration_ms: u64,
+    pub status: HistoryStatus,
+    pub error: Option<String>,
+    pub prompt_chars: usize,
+    pub response_chars: usize,
+    pub prompt: Option<String>,
+    pub response: Option<String>,
+}
+
+impl HistoryRecord {
+    /// Start a record for a request that begins now
+    pub fn new(id: impl Into<String>, model: &str, source: &str, prompt: &str) -> Self {
+        Self {
+            id: id.into(),
+            model: model.to_string(),
+            source: source.to_string(),
+            created_at: unix_now(),
+            duration_ms: 0,
+            status: HistoryStatus::Success,
+            error: None,
+            prompt_chars: prompt.chars().count(),
+            response_chars: 0,
+            prompt: Some(prompt.to_string()),
+            response: None,
+        }
+    }
+
+    /// Fill in the outcome of the request
+    pub fn finish(mut self, started: std::time::Instant, outcome: std::result::Result<&str, String>) -> Self {
+        self.duration_ms = started.elapsed().as_millis() as u64;
+        match outcome {
+            Ok(response) => {
+                self.status = HistoryStatus::Success;
+                self.response_chars = response.chars().count();
+                self.response = Some(response.to_string());
+            }
+            Err(e) => {
+                self.status = HistoryStatus::Error;
+                self.error = Some(e);
+            }
+        }
+        self
+    }
+}
+
+/// Filters for [`HistoryStore::query`]
+#[derive(Debug, Clone)]
+pub struct HistoryQuery {
+    pub model: Option<String>,
+    /// Only records created at or after this Unix timestamp
+    pub since: Option<u64>,
+    /// Only records created before this Unix timestamp
+    pub until: Option<u64>,
+    pub status: Option<HistoryStatus>,
+    pub limit: usize,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            model: None,
+            since: None,
+            until: None,
+            status: None,
+            limit: 50,
+        }
+    }
+}
+
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+    record_content: bool,
+}
+
+impl std::fmt::Debug for HistoryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HistoryStore")
+            .field("record_content", &self.record_content)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path, record_content: bool) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open history database at {}", path.display()))?;
+        Self::with_connection(conn, record_content)
+    }
+
+    /// In-memory store, mostly useful for tests
+    pub fn open_in_memory(record_content: bool) -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?, record_content)
+    }
+
+    fn with_connection(conn: Connection, record_content: bool) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS history (
+                id TEXT PRIMARY KEY,
+                model TEXT NOT NULL,
+                source TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                error TEXT,
+                prompt_chars INTEGER NOT NULL,
+                response_chars INTEGER NOT NULL,
+                prompt TEXT,
+                response TEXT
+            );
+            CREATE INDEX IF NOT EXISTS history_model_created ON history (model, created_at);
+            CREATE INDEX IF NOT EXISTS history_created ON history (created_at);",
+        )
+        .context("Failed to initialize history schema")?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            record_content,
+        })
+    }
+
+    pub fn record_content(&self) -> bool {
+        self.record_content
+    }
+
+    pub fn record(&self, record: &HistoryRecord) -> Result<()> {
+        let (prompt, response) = if self.record_content {
+            (record.prompt.as_deref(), record.response.as_deref())
+        } else {
+            (None, None)
+        };
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO history
+                (id, model, source, created_at, duration_ms, status, error,
+                 prompt_chars, response_chars, prompt, response)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                record.id,
+                record.model,
+                record.source,
+                record.created_at as i64,
+                record.duration_ms as i64,
+                record.status.as_str(),
+                record.error,
+                record.prompt_chars as i64,
+                record.response_chars as i64,
+                prompt,
+                response,
+            ],
+        )
+        .context("Failed to record history entry")?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<HistoryRecord>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("SELECT {} FROM history WHERE id = ?1", COLUMNS),
+            params![id],
+            row_to_record,
+        )
+        .optional()
+        .context("Failed to read history entry")
+    }
+
+    /// Most recent records first, matching every filter that is set
+    pub fn query(&self, query: &HistoryQuery) -> Result<Vec<HistoryRecord>> {
+        let mut sql = format!("SELECT {} FROM history WHERE 1 = 1", COLUMNS);
+        let mut args: Vec<rusqlite::types::Value> = Vec::new();
+
+        if let Some(model) = &query.model {
+            sql.push_str(" AND model = ?");
+            args.push(model.clone().into());
+        }
+        if let Some(since) = query.since {
+            sql.push_str(" AND created_at >= ?");
+            args.push((since as i64).into());
+        }
+        if let Some(until) = query.until {
+            sql.push_str(" AND created_at < ?");
+            args.push((until as i64).into());
+        }
+        if let Some(status) = query.status {
+            sql.push_str(" AND status = ?");
+            args.push(status.as_str().to_string().into());
+        }
+        sql.push_str(" ORDER BY created_at DESC, rowid DESC LIMIT ?");
+        args.push((query.limit as i64).into());
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args), row_to_record)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to query history")
+    }
+}
+
+const COLUMNS: &str = "id, model, source, created_at, duration_ms, status, error, \
+                       prompt_chars, response_chars, prompt, response";
+
+fn row_to_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<HistoryRecord> {
+    let status: String = row.get(5)?;
+    Ok(HistoryRecord {
+        id: row.get(0)?,
+        model: row.get(1)?,
+        source: row.get(2)?,
+        created_at: row.get::<_, i64>(3)? as u64,
+        duration_ms: row.get::<_, i64>(4)? as u64,
+        status: status.parse().unwrap_or(HistoryStatus::Error),
+        error: row.get(6)?,
+        prompt_chars: row.get::<_, i64>(7)? as usize,
+        response_chars: row.get::<_, i64>(8)? as usize,
+        prompt: row.get(9)?,
+        response: row.get(10)?,
+    })
+}
@@ -0,0 +1,246 @@
+//! Approximate nearest-neighbour search over normalized vectors
+//!
+//! [`Hnsw`] is a hierarchical navigable small world graph (Malkov and
+//! Yashunin): every vector is a node linked to its nearest neighbours on the
+//! bottom layer, and a random few are linked on sparser layers above it. A
+//! search walks down from the top layer to the neighbourhood of the query and
+//! explores it, so it looks at a small part of the index however large it
+//! grows. Similarity is the dot product, which is the cosine for the
+//! normalized vectors embedders return.
+//!
+//! Removed vectors stay in the graph as stepping stones and are left out of
+//! results; [`Hnsw::removed`] tells when rebuilding is worth it.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+/// Links a node keeps on the layers above the bottom one; twice as many on it
+const LINKS: usize = 16;
+/// Candidates kept while linking a new node
+const EF_CONSTRUCTION: usize = 100;
+/// Candidates kept while searching, at least
+const EF_SEARCH: usize = 64;
+
+#[derive(Debug)]
+pub struct Hnsw {
+    nodes: Vec<Node>,
+    // Node searches start from, on the top layer
+    entry: Option<usize>,
+    top: usize,
+    removed: usize,
+    rng: fastrand::Rng,
+}
+
+#[derive(Debug)]
+struct Node {
+    vector: Vec<f32>,
+    // Neighbours on each layer the node is on, from the bottom
+    links: Vec<Vec<usize>>,
+    removed: bool,
+}
+
+// A node and its similarity to the vector being looked for
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    similarity: f32,
+    id: usize,
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity.total_cmp(&other.similarity).then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Default for Hnsw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hnsw {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            entry: None,
+            top: 0,
+            removed: 0,
+            // Seeded, so an index is built the same way every time
+            rng: fastrand::Rng::with_seed(0x5eed),
+        }
+    }
+
+    /// Vectors that can be found
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Vectors removed but still in the graph
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Add `vector`; returns its id, numbered from 0 in the order added
+    pub fn insert(&mut self, vector: Vec<f32>) -> usize {
+        let level = self.random_level();
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            vector,
+            links: vec![Vec::new(); level + 1],
+            removed: false,
+        });
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(id);
+            self.top = level;
+            return id;
+        };
+
+        let vector = std::mem::take(&mut self.nodes[id].vector);
+        for layer in (level + 1..=self.top).rev() {
+            entry = self.closest(&vector, entry, layer);
+        }
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(self.top)).rev() {
+            let found = self.search_layer(&vector, &entries, EF_CONSTRUCTION, layer);
+            let neighbours: Vec<usize> = found.iter().take(LINKS).map(|scored| scored.id).collect();
+            for &neighbour in &neighbours {
+                self.link(neighbour, id, layer, &vector);
+            }
+            self.nodes[id].links[layer] = neighbours;
+            entries = found.iter().map(|scored| scored.id).collect();
+        }
+        self.nodes[id].vector = vector;
+
+        if level > self.top {
+            self.top = level;
+            self.entry = Some(id);
+        }
+        id
+    }
+
+    /// Leave the vector with `id` out of results from now on
+    pub fn remove(&mut self, id: usize) {
+        if let Some(node) = self.nodes.get_mut(id).filter(|node| !node.removed) {
+            node.removed = true;
+            self.removed += 1;
+        }
+    }
+
+    /// Ids and similarities of the `k` vectors most similar to `query`, most
+    /// similar first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(usize, f32)> {
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+        for layer in (1..=self.top).rev() {
+            entry = self.closest(query, entry, layer);
+        }
+        // Removed nodes take up candidate slots without being results
+        let ef = EF_SEARCH.max(k) + self.removed.min(EF_SEARCH);
+        self.search_layer(query, &[entry], ef, 0)
+            .into_iter()
+            .filter(|scored| !self.nodes[scored.id].removed)
+            .take(k)
+            .map(|scored| (scored.id, scored.similarity))
+            .collect()
+    }
+
+    // Levels are geometric: each layer holds about 1/LINKS of the one below
+    fn random_level(&mut self) -> usize {
+        let uniform = 1.0 - self.rng.f64();
+        (-uniform.ln() / (LINKS as f64).ln()) as usize
+    }
+
+    fn similarity(&self, query: &[f32], id: usize) -> f32 {
+        query.iter().zip(&self.nodes[id].vector).map(|(a, b)| a * b).sum()
+    }
+
+    // Greedy walk on `layer` to the node most similar to `query`
+    fn closest(&self, query: &[f32], mut entry: usize, layer: usize) -> usize {
+        let mut best = self.similarity(query, entry);
+        loop {
+            let next = self.nodes[entry].links[layer]
+                .iter()
+                .map(|&id| (id, self.similarity(query, id)))
+                .filter(|&(_, similarity)| similarity > best)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            match next {
+                Some((id, similarity)) => (entry, best) = (id, similarity),
+                None => return entry,
+            }
+        }
+    }
+
+    // The `ef` nodes on `layer` most similar to `query` reachable from
+    // `entries`, most similar first
+    fn search_layer(&self, query: &[f32], entries: &[usize], ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        // The least similar found so far on top
+        let mut found = BinaryHeap::new();
+        for &id in entries {
+            let scored = Scored { similarity: self.similarity(query, id), id };
+            candidates.push(scored);
+            found.push(Reverse(scored));
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = found.peek().map_or(f32::MIN, |Reverse(worst): &Reverse<Scored>| worst.similarity);
+            if candidate.similarity < worst && found.len() >= ef {
+                break;
+            }
+            for &id in &self.nodes[candidate.id].links[layer] {
+                if !visited.insert(id) {
+                    continue;
+                }
+                let scored = Scored { similarity: self.similarity(query, id), id };
+                let worst = found.peek().map_or(f32::MIN, |Reverse(worst)| worst.similarity);
+                if found.len() < ef || scored.similarity > worst {
+                    candidates.push(scored);
+                    found.push(Reverse(scored));
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec().into_iter().map(|Reverse(scored)| scored).collect()
+    }
+
+    // Link `from` to `to` on `layer`, dropping `from`'s least similar
+    // neighbour when it has too many
+    fn link(&mut self, from: usize, to: usize, layer: usize, to_vector: &[f32]) {
+        let max = if layer == 0 { 2 * LINKS } else { LINKS };
+        self.nodes[from].links[layer].push(to);
+        if self.nodes[from].links[layer].len() <= max {
+            return;
+        }
+        let origin = &self.nodes[from].vector;
+        let mut scored: Vec<Scored> = self.nodes[from].links[layer]
+            .iter()
+            .map(|&id| {
+                let vector = if id == to { to_vector } else { &self.nodes[id].vector };
+                let similarity = origin.iter().zip(vector).map(|(a, b)| a * b).sum();
+                Scored { similarity, id }
+            })
+            .collect();
+        scored.sort_unstable_by(|a, b| b.cmp(a));
+        scored.truncate(max);
+        self.nodes[from].links[layer] = scored.into_iter().map(|scored| scored.id).collect();
+    }
+}
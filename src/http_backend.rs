@@ -0,0 +1,124 @@
+//! Completions from an OpenAI-compatible server
+//!
+//! [`HttpBackend`] implements [`InferenceBackend`] over a server's
+//! `/v1/completions` endpoint, streamed as server-sent events, so an engine
+//! other than LiteRT-LM (llama.cpp's `llama-server`, vLLM, another
+//! litert-lm node) can stand in where a [`LitManager`](crate::LitManager)
+//! would be used. Prompts are sent as they are, without a chat template.
+
+use anyhow::{bail, Context, Result};
+use futures::future::BoxFuture;
+use futures::{FutureExt, StreamExt};
+use std::time::Duration;
+
+use crate::backend::{InferenceBackend, TextStream};
+
+/// Longest wait for a connection to the server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A server with an OpenAI-style `/v1/completions` endpoint
+#[derive(Debug, Clone)]
+pub struct HttpBackend {
+    url: String,
+    api_key: Option<String>,
+    max_tokens: Option<usize>,
+    client: reqwest::Client,
+}
+
+impl HttpBackend {
+    /// `base_url` is the server's address, e.g. `http://localhost:8080`
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            url: format!("{}/v1/completions", base_url.trim_end_matches('/')),
+            api_key: None,
+            max_tokens: None,
+            client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+
+    /// Sent as a bearer token
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Most tokens generated per completion; the server's default when unset
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+}
+
+impl InferenceBackend for HttpBackend {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        async move {
+            let mut body = serde_json::json!({ "model": model, "prompt": prompt, "stream": true });
+            if let Some(max_tokens) = self.max_tokens {
+                body["max_tokens"] = max_tokens.into();
+            }
+            let mut request = self.client.post(&self.url).json(&body);
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = request
+                .send()
+                .await
+                .with_context(|| format!("Request to {} failed", self.url))?;
+            let status = response.status();
+            if !status.is_success() {
+                let text = response.text().await.unwrap_or_default();
+                bail!("{} answered HTTP {}: {}", self.url, status, text.trim());
+            }
+
+            // Server-sent events; a line may span chunks
+            let events = response.bytes_stream();
+            let stream = futures::stream::unfold((events, String::new()), |(mut events, mut pending)| async move {
+                loop {
+                    while let Some(end) = pending.find('\n') {
+                        let line: String = pending.drain(..=end).collect();
+                        match sse_text(line.trim()) {
+                            Some(Event::Text(text)) => return Some((Ok(text), (events, pending))),
+                            Some(Event::Error(message)) => {
+                                return Some((Err(anyhow::anyhow!(message)), (events, String::new())))
+                            }
+                            Some(Event::Done) => return None,
+                            None => {}
+                        }
+                    }
+                    match events.next().await? {
+                        Ok(chunk) => pending.push_str(&String::from_utf8_lossy(&chunk)),
+                        Err(e) => {
+                            let error = anyhow::Error::new(e).context("Stream interrupted");
+                            return Some((Err(error), (events, pending)));
+                        }
+                    }
+                }
+            });
+            Ok(stream.boxed())
+        }
+        .boxed()
+    }
+}
+
+enum Event {
+    Text(String),
+    Error(String),
+    Done,
+}
+
+/// What a `data:` line of a completion stream carries
+fn sse_text(line: &str) -> Option<Event> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return Some(Event::Done);
+    }
+    let chunk: serde_json::Value = serde_json::from_str(data).ok()?;
+    if let Some(message) = chunk["error"]["message"].as_str() {
+        return Some(Event::Error(message.to_string()));
+    }
+    let text = chunk["choices"][0]["text"].as_str()?;
+    (!text.is_empty()).then(|| Event::Text(text.to_string()))
+}
@@ -0,0 +1,337 @@
+//! Model discovery on the Hugging Face Hub
+//!
+//! Besides lit's own registry, LiteRT-LM models are published on the Hugging
+//! Face Hub. Search queries the Hub's model API and keeps the repositories
+//! that ship a `.litertlm` file; a repository can then be pulled by its id,
+//! which lit downloads from the file's URL at the repository's current
+//! commit. Where each such model came from is recorded in
+//! `model_provenance.json` under the cache directory.
+//!
+//! `HF_ENDPOINT` points the client at a mirror, and `HF_TOKEN` is used for
+//! gated repositories when no token is passed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cache_lock::CacheLock;
+use crate::history::unix_now;
+
+/// The public Hub, unless `HF_ENDPOINT` says otherwise
+pub const HUB_URL: &str = "https://huggingface.co";
+/// Library the Hub lists LiteRT-LM models under
+pub const LIBRARY: &str = "litert-lm";
+/// Files lit can run
+const MODEL_EXTENSION: &str = ".litertlm";
+/// How long a Hub API request may take
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long an update waits for another process updating the provenance file
+const PROVENANCE_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What to search the Hub for
+#[derive(Debug, Clone)]
+pub struct HubQuery {
+    /// Words matched against repository ids
+    pub search: String,
+    /// Only repositories of this library (`litert-lm` by default)
+    pub library: Option<String>,
+    /// Only repositories with every one of these tags
+    pub tags: Vec<String>,
+    /// Repositories asked of the Hub, most downloaded first
+    pub limit: usize,
+}
+
+impl HubQuery {
+    pub fn new(search: impl Into<String>) -> Self {
+        Self {
+            search: search.into(),
+            library: Some(LIBRARY.to_string()),
+            tags: Vec::new(),
+            limit: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Sibling {
+    rfilename: String,
+    /// Set for files stored with Git LFS, with `blobs=true`
+    #[serde(default)]
+    lfs: Option<LfsInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LfsInfo {
+    sha256: String,
+}
+
+/// A model repository on the Hub
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HubModel {
+    /// Repository id, e.g. `litert-community/Gemma3-1B-IT`
+    pub id: String,
+    /// Commit the listing describes
+    #[serde(default)]
+    pub sha: Option<String>,
+    #[serde(default)]
+    pub downloads: u64,
+    #[serde(default)]
+    pub likes: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub library_name: Option<String>,
+    /// Whether downloading needs accepting the license (and a token)
+    #[serde(default, deserialize_with = "deserialize_gated")]
+    pub gated: bool,
+    /// `.litertlm` files in the repository
+    #[serde(default, rename = "siblings", deserialize_with = "deserialize_model_files")]
+    pub files: Vec<String>,
+    /// SHA-256 of the model files, by name, where the Hub lists one; only
+    /// filled in by [`HubClient::model`]
+    #[serde(default, skip_deserializing)]
+    pub sha256: BTreeMap<String, String>,
+}
+
+impl HubModel {
+    /// Name a pull saves the model under unless given an alias: the
+    /// repository name, lowercased
+    pub fn default_alias(&self) -> String {
+        self.id.rsplit('/').next().unwrap_or(&self.id).to_lowercase()
+    }
+}
+
+// `gated` is `false`, `"auto"`, or `"manual"`
+fn deserialize_gated<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Bool(gated) => gated,
+        serde_json::Value::String(_) => true,
+        _ => false,
+    })
+}
+
+fn deserialize_model_files<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let siblings = Option::<Vec<Sibling>>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(siblings
+        .into_iter()
+        .map(|sibling| sibling.rfilename)
+        .filter(|name| name.ends_with(MODEL_EXTENSION))
+        .collect())
+}
+
+/// Client for the Hub's model API
+#[derive(Debug, Clone)]
+pub struct HubClient {
+    endpoint: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HubClient {
+    /// A client for `$HF_ENDPOINT` (or the public Hub), authenticating with
+    /// `token` or else `$HF_TOKEN`
+    pub fn new(token: Option<&str>) -> Self {
+        let endpoint = std::env::var("HF_ENDPOINT").unwrap_or_else(|_| HUB_URL.to_string());
+        let token = token.map(str::to_string).or_else(|| std::env::var("HF_TOKEN").ok());
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            token: token.filter(|token| !token.is_empty()),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Repositories matching `query` that have a file lit can run
+    pub async fn search(&self, query: &HubQuery) -> Result<Vec<HubModel>> {
+        let mut params = vec![
+            ("search", query.search.clone()),
+            ("limit", query.limit.to_string()),
+            ("sort", "downloads".to_string()),
+            ("direction", "-1".to_string()),
+            ("full", "true".to_string()),
+        ];
+        if let Some(library) = &query.library {
+            params.push(("library", library.clone()));
+        }
+        params.extend(query.tags.iter().map(|tag| ("filter", tag.clone())));
+
+        let models: Vec<HubModel> = self.get(&format!("{}/api/models", self.endpoint), &params).await?;
+        Ok(models.into_iter().filter(|model| !model.files.is_empty()).collect())
+    }
+
+    /// One repository, with its files and their checksums
+    pub async fn model(&self, repo: &str) -> Result<HubModel> {
+        check_repo_id(repo)?;
+        let json: serde_json::Value = self
+            .get(&format!("{}/api/models/{}", self.endpoint, repo), &[("blobs", "true".to_string())])
+            .await
+            .with_context(|| format!("Failed to look up {} on the Hugging Face Hub", repo))?;
+        let siblings: Vec<Sibling> = serde_json::from_value(json["siblings"].clone()).unwrap_or_default();
+        let mut model: HubModel =
+            serde_json::from_value(json).context("Unexpected response from the Hugging Face Hub")?;
+        model.sha256 = siblings
+            .into_iter()
+            .filter(|sibling| model.files.contains(&sibling.rfilename))
+            .filter_map(|sibling| Some((sibling.rfilename, sibling.lfs?.sha256)))
+            .collect();
+        Ok(model)
+    }
+
+    /// URL of `file` in `repo` at `revision`
+    pub fn file_url(&self, repo: &str, revision: &str, file: &str) -> String {
+        format!("{}/{}/resolve/{}/{}", self.endpoint, repo, revision, file)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, url: &str, params: &[(&str, String)]) -> Result<T> {
+        let mut request = self.client.get(url).query(params);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.context("Failed to reach the Hugging Face Hub")?;
+        let status = response.status();
+        if !status.is_success() {
+            let hint = match status.as_u16() {
+                401 | 403 => " (gated or private repositories need a token: --hf-token or HF_TOKEN)",
+                404 => " (no such repository)",
+                _ => "",
+            };
+            anyhow::bail!("Hugging Face Hub returned HTTP {}{}", status, hint);
+        }
+        response.json().await.context("Unexpected response from the Hugging Face Hub")
+    }
+}
+
+/// Reject ids that aren't `<owner>/<name>`
+fn check_repo_id(repo: &str) -> Result<()> {
+    let valid = repo.split('/').count() == 2
+        && repo.split('/').all(|part| {
+            !part.is_empty()
+                && part != ".."
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+    anyhow::ensure!(valid, "'{}' is not a Hugging Face repository id (expected <owner>/<name>)", repo);
+    Ok(())
+}
+
+/// The `.litertlm` file of `model` to pull: `file` if given, otherwise the
+/// repository's only one
+pub fn pick_file<'a>(model: &'a HubModel, file: Option<&str>) -> Result<&'a str> {
+    match file {
+        Some(file) => model
+            .files
+            .iter()
+            .find(|name| name.as_str() == file)
+            .map(String::as_str)
+            .with_context(|| format!("{} has no file '{}' (available: {})", model.id, file, model.files.join(", "))),
+        None => match model.files.as_slice() {
+            [] => anyhow::bail!("{} has no {} file lit can run", model.id, MODEL_EXTENSION),
+            [only] => Ok(only),
+            several => anyhow::bail!(
+                "{} has several model files; choose one with --file: {}",
+                model.id,
+                several.join(", ")
+            ),
+        },
+    }
+}
+
+/// Where a model pulled from the Hub came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelProvenance {
+    /// Name the model was saved under
+    pub model: String,
+    /// Always `huggingface` for now
+    pub source: String,
+    pub repo: String,
+    /// Commit the file was downloaded at
+    pub revision: String,
+    pub file: String,
+    pub url: String,
+    /// Unix timestamp (seconds) of the pull
+    pub pulled_at: u64,
+}
+
+impl ModelProvenance {
+    pub fn hub(model: &str, repo: &str, revision: &str, file: &str, url: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            source: "huggingface".to_string(),
+            repo: repo.to_string(),
+            revision: revision.to_string(),
+            file: file.to_string(),
+            url: url.to_string(),
+            pulled_at: unix_now(),
+        }
+    }
+}
+
+/// Provenance of every model pulled from the Hub, kept in a JSON file
+#[derive(Debug, Clone)]
+pub struct ProvenanceStore {
+    path: PathBuf,
+}
+
+impl ProvenanceStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Recorded provenance by model name; empty if nothing was recorded yet
+    pub fn load(&self) -> BTreeMap<String, ModelProvenance> {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, model: &str) -> Option<ModelProvenance> {
+        self.load().remove(model)
+    }
+
+    pub async fn record(&self, provenance: ModelProvenance) -> Result<()> {
+        let _lock = self.lock().await?;
+        let mut all = self.load();
+        all.insert(provenance.model.clone(), provenance);
+        self.save(&all)
+    }
+
+    /// Forget a removed model
+    pub async fn forget(&self, model: &str) -> Result<()> {
+        let _lock = self.lock().await?;
+        let mut all = self.load();
+        if all.remove(model).is_some() {
+            self.save(&all)?;
+        }
+        Ok(())
+    }
+
+    async fn lock(&self) -> Result<CacheLock> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        CacheLock::acquire(dir, "provenance", PROVENANCE_LOCK_TIMEOUT).await
+    }
+
+    fn save(&self, all: &BTreeMap<String, ModelProvenance>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so readers never see a partial file
+        let tmp = self.path.with_extension(format!("json.{}", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&tmp, serde_json::to_vec_pretty(all)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to update {}", self.path.display()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! Generations in progress, for finding and killing runaway ones
+//!
+//! Completions register here for as long as they run, each with its own
+//! cancellation token. Killing one by id cancels the token: its caller gets
+//! [`Cancelled`](crate::Cancelled) (a stream ends with it), and its pool slot
+//! and concurrency permit are freed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::history::unix_now;
+
+/// A generation in progress, as listed by [`LitManager::inflight`](crate::LitManager::inflight)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InflightRequest {
+    /// Completion id, as in the response (or the history entry)
+    pub id: String,
+    pub model: String,
+    pub tenant: String,
+    /// Who asked: the request's `user`, else the tenant
+    pub client: String,
+    /// Unix time in seconds
+    pub started_at: u64,
+    pub age_ms: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    model: String,
+    tenant: String,
+    client: String,
+    started_at: u64,
+    started: Instant,
+    token: CancellationToken,
+}
+
+/// Generations in progress, shared by every manager handle
+#[derive(Debug, Default)]
+pub struct InflightRequests {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl InflightRequests {
+    /// List a generation until the returned guard is dropped; `token` is
+    /// cancelled when it is killed
+    pub fn register(
+        self: &Arc<Self>,
+        id: &str,
+        model: &str,
+        tenant: &str,
+        client: &str,
+        token: CancellationToken,
+    ) -> InflightGuard {
+        let entry = Entry {
+            model: model.to_string(),
+            tenant: tenant.to_string(),
+            client: client.to_string(),
+            started_at: unix_now(),
+            started: Instant::now(),
+            token: token.clone(),
+        };
+        self.entries.lock().unwrap().insert(id.to_string(), entry);
+        InflightGuard {
+            id: id.to_string(),
+            token,
+            requests: self.clone(),
+        }
+    }
+
+    /// Generations in progress, oldest first
+    pub fn list(&self) -> Vec<InflightRequest> {
+        let mut requests: Vec<InflightRequest> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, entry)| InflightRequest {
+                id: id.clone(),
+                model: entry.model.clone(),
+                tenant: entry.tenant.clone(),
+                client: entry.client.clone(),
+                started_at: entry.started_at,
+                age_ms: entry.started.elapsed().as_millis() as u64,
+            })
+            .collect();
+        requests.sort_by_key(|request| std::cmp::Reverse(request.age_ms));
+        requests
+    }
+
+    /// Cancel the generation with this id; false if there is none
+    pub fn kill(&self, id: &str) -> bool {
+        match self.entries.lock().unwrap().get(id) {
+            Some(entry) => {
+                entry.token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Keeps a generation listed; see [`InflightRequests::register`]
+#[derive(Debug)]
+pub struct InflightGuard {
+    id: String,
+    token: CancellationToken,
+    requests: Arc<InflightRequests>,
+}
+
+impl InflightGuard {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Cancelled when the generation is killed
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.requests.entries.lock().unwrap().remove(&self.id);
+    }
+}
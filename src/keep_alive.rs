@@ -0,0 +1,111 @@
+//! Unloading models nobody uses
+//!
+//! Like Ollama's `keep_alive`, a model's pools (and the weights their lit
+//! processes hold) are torn down once they have gone without requests for a
+//! while, and started again by the next request. How long is set per model in
+//! the `keep_alive` config section and per request with a `keep_alive` field;
+//! unset means models stay loaded until they are unloaded.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::time::Duration;
+
+use crate::config::KeepAliveConfig;
+use crate::manager::LitManager;
+use crate::tenant::matches_pattern;
+
+/// How often idle pools are looked for while serving
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a model stays loaded after its last request
+///
+/// Written as seconds or a duration such as `"5m"` (`s`, `m`, and `h` units),
+/// as in Ollama: `0` unloads the model as soon as the request is done, and a
+/// negative value keeps it loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeepAlive {
+    #[default]
+    Forever,
+    For(Duration),
+}
+
+impl KeepAlive {
+    /// Keep-alive of `model`: the config's most specific matching pattern,
+    /// then its default, then [`KeepAlive::Forever`]
+    pub fn for_model(config: &KeepAliveConfig, model: &str) -> Self {
+        config
+            .models
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, &keep_alive)| keep_alive)
+            .or(config.default)
+            .unwrap_or_default()
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let invalid = || format!("'{}' is not a keep_alive duration; expected seconds or e.g. \"5m\"", value);
+        let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+            Some(at) => value.split_at(at),
+            None => (value, "s"),
+        };
+        let number: f64 = number.trim().parse().map_err(|_| invalid())?;
+        let secs = match unit {
+            "s" => number,
+            "m" => number * 60.0,
+            "h" => number * 3600.0,
+            _ => return Err(invalid()),
+        };
+        Self::from_secs(secs).ok_or_else(invalid)
+    }
+
+    fn from_secs(secs: f64) -> Option<Self> {
+        match secs {
+            secs if secs < 0.0 => Some(KeepAlive::Forever),
+            secs => Duration::try_from_secs_f64(secs).ok().map(KeepAlive::For),
+        }
+    }
+
+    /// Whether a pool idle for `idle` should be unloaded
+    pub fn expired(&self, idle: Duration) -> bool {
+        match self {
+            KeepAlive::Forever => false,
+            KeepAlive::For(keep) => idle >= *keep,
+        }
+    }
+}
+
+impl Serialize for KeepAlive {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            KeepAlive::Forever => serializer.serialize_i64(-1),
+            KeepAlive::For(keep) => serializer.serialize_f64(keep.as_secs_f64()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeepAlive {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Secs(f64),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Secs(secs) => {
+                KeepAlive::from_secs(secs).ok_or_else(|| serde::de::Error::custom("keep_alive is out of range"))
+            }
+            Raw::Text(text) => KeepAlive::parse(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Unload idle models every second, for as long as the server runs
+pub async fn run(manager: LitManager) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        manager.unload_idle().await;
+    }
+}
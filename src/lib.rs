@@ -4,8 +4,15 @@
 //! - Model download and management
 //! - Process pool management for efficient inference
 //! - Streaming completions
-//! - MCP (Model Context Protocol) service
-//! - OpenAI-compatible API server
+//! - MCP (Model Context Protocol) service (`mcp` feature)
+//! - OpenAI-compatible API server (`server` feature)
+//! - Local document retrieval (RAG)
+//! - A scripted mock backend for tests (`mock` feature)
+//! - LiteRT-LM linked in-process instead of run as lit processes (`ffi` feature)
+//!
+//! The `server`, `mcp`, and `cli` features are on by default. Embedders that
+//! only need [`LitManager`] and the process pool can turn them off
+//! (`default-features = false`) to leave out axum, rmcp, and clap.
 //!
 //! # Example
 //!
@@ -27,17 +34,130 @@
 //! }
 //! ```
 
+pub mod adapter;
+pub mod affinity;
+pub mod agent;
+pub mod backend;
 pub mod binary;
+pub mod breaker;
+pub mod cache_lock;
+pub mod cancel;
+pub mod checksum;
+#[cfg(feature = "server")]
+pub mod cluster;
+pub mod concurrency;
+pub mod config;
+pub mod constraints;
+pub mod context;
+pub mod crash;
+pub mod download;
+pub mod dspy;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod generation;
+pub mod history;
+pub mod hnsw;
+pub mod http_backend;
+pub mod hub;
+pub mod inflight;
+pub mod keep_alive;
+pub mod limits;
+pub mod lit_args;
+pub mod loadtest;
+pub mod log_file;
+pub mod maintenance;
 pub mod manager;
+#[cfg(feature = "mcp")]
 pub mod mcp;
+#[cfg(feature = "mcp")]
+pub mod mcp_client;
+pub mod metrics;
+pub mod moderation;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod package;
 pub mod process;
+pub mod profile;
+pub mod progress;
+pub mod prompt_cache;
+pub mod quota;
+pub mod registry;
+pub mod replay;
+pub mod retry;
+pub mod retrieval;
+pub mod sampling;
+pub mod sandbox;
+pub mod schedule;
+pub mod scheduler;
+#[cfg(feature = "server")]
 pub mod server;
+pub mod session;
+pub mod source;
+pub mod split;
+pub mod stream;
+pub mod telemetry;
+pub mod template;
+pub mod tenant;
+pub mod throttle;
+pub mod tokenizer;
+pub mod variant;
 
 // Re-export main types for library users
-pub use manager::LitManager;
+pub use adapter::{Adapter, AdapterStore};
+pub use agent::{AgentOptions, AgentRun, ToolDefinition, ToolRegistry};
+pub use backend::{Backend, InferenceBackend};
+pub use breaker::{CircuitBreaker, CircuitState, CircuitStatus, Unstable};
+pub use cancel::Cancelled;
+pub use checksum::ChecksumRecord;
+#[cfg(feature = "server")]
+pub use cluster::Cluster;
+pub use concurrency::{ConcurrencyLimits, QueueFull, Saturated};
+pub use config::Config;
+pub use constraints::Constraint;
+pub use context::{ContextWindow, Turn};
+pub use error::LitError;
+pub use generation::GenOptions;
+pub use history::{HistoryQuery, HistoryRecord, HistoryStatus};
+pub use http_backend::HttpBackend;
+pub use hub::{HubModel, HubQuery, ModelProvenance};
+pub use inflight::InflightRequest;
+pub use keep_alive::KeepAlive;
+pub use limits::{Limits, ResourceLimits};
+pub use lit_args::LitArgs;
+pub use maintenance::MaintenanceSummary;
+pub use manager::{LitManager, LitManagerBuilder, ManagerEvent, PoolDiagnostics};
+#[cfg(feature = "mcp")]
 pub use mcp::LiteRtMcpService;
-pub use process::{LitProcess, ProcessPool};
-pub use server::{AppState, ChatCompletionRequest, create_router};
+#[cfg(feature = "mcp")]
+pub use mcp_client::McpClientManager;
+pub use metrics::Metrics;
+pub use moderation::ModerationResult;
+pub use package::PackageManifest;
+pub use process::{GenerationTiming, LitProcess, PoolStats, ProcessDiagnostics, ProcessPool, ProcessStats};
+pub use profile::{Profile, Profiles};
+pub use progress::{Phase, Progress, ProgressReporter};
+pub use registry::{ManifestModel, ModelInfo, RegistryEntry, RegistryManifest, RegistryMatch};
+pub use retrieval::{DocumentIndex, SearchHit};
+pub use retry::{ErrorClass, RetryPolicy};
+pub use sampling::{Sampling, SamplingDefaults};
+#[cfg(feature = "server")]
+pub use server::{
+    AppState, ChatCompletionRequest, Readiness, ReadyState, ServeOptions, TlsConfig, create_cluster_router,
+    create_router,
+};
+pub use session::{ChatSession, SessionEvent, TranscriptFormat};
+pub use source::{ModelSource, PullTarget};
+pub use split::{Split, Splits};
+pub use stream::{
+    ChatCompletionChunk, ChoiceChunk, Chunking, CompletionChunk, CompletionEvent, CompletionStream, Delta, FinishReason,
+    LitertExtension, StreamStats, Usage,
+};
+pub use template::ChatTemplate;
+pub use tenant::{Tenant, Tenants};
+pub use tokenizer::{Tokenizer, Tokenizers};
+pub use variant::{DeviceInfo, Variants};
 
 // Re-export common types
+pub use tokio_util::sync::CancellationToken;
 pub type Result<T> = std::result::Result<T, anyhow::Error>;
@@ -0,0 +1,256 @@
+//! Resource limits of lit processes
+//!
+//! A model that leaks or loads more than the machine has can take the whole
+//! host down with it. The `limits` config section caps a model's processes:
+//! memory (a cgroup v2 `memory.max` when the server is given a cgroup to
+//! manage, else the address space through `RLIMIT_AS`), scheduling priority,
+//! and the CPUs they may run on. A process over its memory limit is killed
+//! (or fails to allocate) and respawned like any other crash.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::config::LimitsConfig;
+use crate::tenant::matches_pattern;
+
+/// CPUs a process can be pinned to (`CPU_SETSIZE`, the size of `cpu_set_t`)
+const MAX_CPUS: usize = 1024;
+
+/// Limits of one model's processes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Most memory a process may use, in MiB
+    pub memory_mb: Option<u64>,
+    /// Niceness, from -20 (favored) to 19; values below 0 need privileges
+    pub nice: Option<i32>,
+    /// Linux: CPUs the process may run on, e.g. `[0, 1, 2, 3]`
+    pub cpus: Option<Vec<usize>>,
+}
+
+impl ResourceLimits {
+    /// Limits of `model`: the config's most specific matching pattern, then
+    /// its default, then none
+    pub fn for_model(config: &LimitsConfig, model: &str) -> Self {
+        config
+            .models
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, limits)| limits)
+            .or(config.default.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.memory_mb.is_none() && self.nice.is_none() && self.cpus.is_none()
+    }
+
+    /// Fail for values out of range and limits this platform can't enforce
+    pub fn check(&self) -> Result<()> {
+        if self.memory_mb == Some(0) {
+            bail!("limits.memory_mb must be above 0");
+        }
+        if self.memory_mb.is_some() && !RLIMIT_SUPPORTED {
+            bail!("limits.memory_mb is only supported on Linux and macOS");
+        }
+        if let Some(nice) = self.nice {
+            if !(-20..=19).contains(&nice) {
+                bail!("limits.nice must be between -20 and 19, not {}", nice);
+            }
+            if !cfg!(unix) {
+                bail!("limits.nice is only supported on Unix");
+            }
+        }
+        if let Some(cpus) = &self.cpus {
+            if !cfg!(target_os = "linux") {
+                bail!("limits.cpus is only supported on Linux");
+            }
+            if cpus.is_empty() {
+                bail!("limits.cpus must list at least one CPU");
+            }
+            if let Some(cpu) = cpus.iter().find(|&&cpu| cpu >= MAX_CPUS) {
+                bail!("limits.cpus lists CPU {}, above the highest ({})", cpu, MAX_CPUS - 1);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check every configured limit, and that the cgroup can be managed
+pub fn check(config: &LimitsConfig) -> Result<()> {
+    for limits in config.models.values().chain(config.default.as_ref()) {
+        limits.check()?;
+    }
+    if let Some(dir) = &config.cgroup {
+        if !cfg!(target_os = "linux") {
+            bail!("limits.cgroup is only supported on Linux");
+        }
+        if !dir.join("cgroup.procs").is_file() {
+            bail!("{} is not a cgroup v2 directory", dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// [`ResourceLimits`] of a pool's processes, applied as each one is spawned
+#[derive(Debug, Clone)]
+pub struct Limits {
+    resources: ResourceLimits,
+    // Parent of the group made for each process
+    cgroup: Option<PathBuf>,
+}
+
+impl Limits {
+    /// Limits checked with [`check`]; memory is limited through a group
+    /// under `cgroup` if set
+    pub fn new(resources: ResourceLimits, cgroup: Option<PathBuf>) -> Self {
+        Self { resources, cgroup }
+    }
+
+    pub fn resources(&self) -> &ResourceLimits {
+        &self.resources
+    }
+
+    /// Set up `command` to start within the limits. The process's cgroup,
+    /// if any, is removed when the returned guard is dropped, which must
+    /// not happen before the process has exited.
+    pub(crate) fn apply(&self, command: &mut Command) -> Result<Option<Cgroup>> {
+        let memory = self.resources.memory_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+        let cgroup = match (&self.cgroup, memory) {
+            (Some(parent), Some(bytes)) => Some(Cgroup::create(parent, bytes)?),
+            _ => None,
+        };
+        #[cfg(unix)]
+        {
+            let exec = sys::Exec {
+                address_space: memory.filter(|_| cgroup.is_none()),
+                nice: self.resources.nice,
+                cpus: self.resources.cpus.as_deref().map(sys::cpu_mask),
+                cgroup_procs: cgroup.as_ref().map(Cgroup::procs_fd),
+            };
+            // SAFETY: `limit` only makes async-signal-safe syscalls
+            unsafe {
+                command.pre_exec(move || exec.limit());
+            }
+        }
+        Ok(cgroup)
+    }
+}
+
+/// A cgroup made for one lit process, removed on drop
+#[derive(Debug)]
+pub(crate) struct Cgroup {
+    dir: PathBuf,
+    // Open until the process has joined; see `apply`
+    procs: std::fs::File,
+}
+
+impl Cgroup {
+    fn create(parent: &Path, memory: u64) -> Result<Self> {
+        let dir = parent.join(format!("lit-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir(&dir).with_context(|| format!("Failed to create cgroup {}", dir.display()))?;
+        let procs = match std::fs::OpenOptions::new().write(true).open(dir.join("cgroup.procs")) {
+            Ok(procs) => procs,
+            Err(e) => {
+                let _ = std::fs::remove_dir(&dir);
+                return Err(e).with_context(|| format!("Failed to open {}/cgroup.procs", dir.display()));
+            }
+        };
+        let cgroup = Self { dir, procs };
+        std::fs::write(cgroup.dir.join("memory.max"), memory.to_string())
+            .with_context(|| format!("Failed to set memory.max of {}", cgroup.dir.display()))?;
+        Ok(cgroup)
+    }
+
+    // The child joins the group before exec by writing "0" (itself) to it;
+    // the descriptor is closed on exec
+    #[cfg(unix)]
+    fn procs_fd(&self) -> i32 {
+        use std::os::unix::io::AsRawFd;
+        self.procs.as_raw_fd()
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir(&self.dir) {
+            tracing::debug!(cgroup = %self.dir.display(), error = %e, "Failed to remove cgroup");
+        }
+    }
+}
+
+// RLIMIT_AS is only known for these targets
+const RLIMIT_SUPPORTED: bool = cfg!(any(target_os = "linux", target_os = "macos"));
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::c_int;
+
+    #[cfg(target_os = "linux")]
+    pub type CpuMask = libc::cpu_set_t;
+    // Pinning is Linux-only; `ResourceLimits::check` rejects `cpus` elsewhere
+    #[cfg(not(target_os = "linux"))]
+    pub type CpuMask = ();
+
+    #[cfg(target_os = "linux")]
+    pub fn cpu_mask(cpus: &[usize]) -> CpuMask {
+        // SAFETY: an all-zero `cpu_set_t` is the empty set, and `check`
+        // keeps every CPU below `MAX_CPUS`, the set's size
+        unsafe {
+            let mut mask: libc::cpu_set_t = std::mem::zeroed();
+            for &cpu in cpus {
+                libc::CPU_SET(cpu, &mut mask);
+            }
+            mask
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn cpu_mask(_cpus: &[usize]) -> CpuMask {}
+
+    /// What the forked child sets up right before exec; built beforehand, as
+    /// the child may not allocate
+    pub struct Exec {
+        pub address_space: Option<u64>,
+        pub nice: Option<i32>,
+        pub cpus: Option<CpuMask>,
+        pub cgroup_procs: Option<c_int>,
+    }
+
+    impl Exec {
+        pub fn limit(&self) -> std::io::Result<()> {
+            let check = |result: c_int| match result {
+                0 => Ok(()),
+                _ => Err(std::io::Error::last_os_error()),
+            };
+            // SAFETY: plain syscalls on values owned by `self`
+            unsafe {
+                if let Some(fd) = self.cgroup_procs {
+                    if libc::write(fd, b"0".as_ptr().cast(), 1) != 1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                #[cfg(any(target_os = "linux", target_os = "macos"))]
+                if let Some(bytes) = self.address_space {
+                    let limit = libc::rlimit {
+                        rlim_cur: bytes,
+                        rlim_max: bytes,
+                    };
+                    check(libc::setrlimit(libc::RLIMIT_AS, &limit))?;
+                }
+                if let Some(nice) = self.nice {
+                    check(libc::setpriority(libc::PRIO_PROCESS, 0, nice))?;
+                }
+                #[cfg(target_os = "linux")]
+                if let Some(mask) = &self.cpus {
+                    check(libc::sched_setaffinity(0, std::mem::size_of::<CpuMask>(), mask))?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
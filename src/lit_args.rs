@@ -0,0 +1,85 @@
+//! Extra lit flags for a single request
+//!
+//! lit reads its settings from command-line flags when a process starts, so a
+//! completion asking for different ones (through `x_litert.args`) is served by
+//! a pool started with them, kept next to the model's default pool. Only the
+//! flags in [`ALLOWED`] are accepted, and their values are checked, so a
+//! request can't hand lit arbitrary arguments.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A lit flag requests may set
+#[derive(Debug, Clone, Copy)]
+pub struct AllowedArg {
+    /// Name in `x_litert.args`, and of the flag (`--<name>`)
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Largest value accepted; values are whole numbers from 1
+    pub max: u64,
+}
+
+/// Flags requests may set
+pub const ALLOWED: &[AllowedArg] = &[
+    AllowedArg {
+        name: "max_num_tokens",
+        description: "context size in tokens (prompt and completion)",
+        max: 131_072,
+    },
+    AllowedArg {
+        name: "num_cpu_threads",
+        description: "threads used by the CPU backend",
+        max: 256,
+    },
+];
+
+/// Flags to start lit with, by name (e.g. `{"max_num_tokens": 4096}`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LitArgs(BTreeMap<String, serde_json::Value>);
+
+impl LitArgs {
+    /// Add a flag; see [`check`](Self::check) for what is accepted
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.0.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reject flags that aren't in [`ALLOWED`] and out-of-range values
+    pub fn check(&self) -> Result<(), String> {
+        for (name, value) in &self.0 {
+            let Some(allowed) = ALLOWED.iter().find(|arg| arg.name == name) else {
+                let names: Vec<&str> = ALLOWED.iter().map(|arg| arg.name).collect();
+                return Err(format!("args.{} is not supported; allowed args are {}", name, names.join(", ")));
+            };
+            if !value.as_u64().is_some_and(|v| (1..=allowed.max).contains(&v)) {
+                return Err(format!(
+                    "args.{} ({}) must be a whole number from 1 to {}",
+                    name, allowed.description, allowed.max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Command-line arguments for lit, in name order
+    pub fn flags(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .flat_map(|(name, value)| [format!("--{}", name), value.to_string()])
+            .collect()
+    }
+
+    /// Name of the pool serving `model` with these flags; `model` when there are none
+    pub fn pool_key(&self, model: &str) -> String {
+        if self.is_empty() {
+            model.to_string()
+        } else {
+            format!("{} {}", model, self.flags().join(" "))
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Built-in load testing
+//!
+//! `litert-lm loadtest` keeps a fixed number of streaming requests in flight
+//! against a running server (or a local pool, skipping HTTP) for a set time,
+//! then reports latency and time-to-first-token percentiles, throughput, and
+//! errors.
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::manager::LitManager;
+use crate::process::ProcessPool;
+use crate::tokenizer::{Estimate, Tokenizer};
+
+/// What the load test drives
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// An OpenAI-compatible server, e.g. `litert-lm serve`
+    Server { url: String, api_key: Option<String> },
+    /// The model's process pool in this process
+    Pool(Box<LitManager>),
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadTest {
+    pub model: String,
+    pub prompt: String,
+    /// Requests kept in flight
+    pub concurrency: usize,
+    /// How long new requests are started for
+    pub duration: Duration,
+}
+
+/// Latency distribution in milliseconds
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Percentiles {
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Percentiles {
+    pub fn from_durations(mut durations: Vec<Duration>) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+        durations.sort();
+        // Nearest-rank percentile
+        let at = |p: f64| {
+            let rank = ((p / 100.0) * durations.len() as f64).ceil() as usize;
+            durations[rank.clamp(1, durations.len()) - 1].as_millis() as u64
+        };
+        Self {
+            p50_ms: at(50.0),
+            p90_ms: at(90.0),
+            p99_ms: at(99.0),
+            max_ms: at(100.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadTestReport {
+    pub model: String,
+    pub target: String,
+    pub concurrency: usize,
+    /// Time until the last request finished
+    pub elapsed_secs: f64,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub requests_per_second: f64,
+    /// Estimated generated tokens per second across all requests
+    pub tokens_per_second: f64,
+    pub latency: Percentiles,
+    pub ttft: Percentiles,
+    /// Error messages and how often each occurred
+    pub error_counts: BTreeMap<String, u64>,
+}
+
+impl std::fmt::Display for LoadTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Target:        {} ({})", self.target, self.model)?;
+        writeln!(f, "Concurrency:   {}", self.concurrency)?;
+        writeln!(f, "Elapsed:       {:.1}s", self.elapsed_secs)?;
+        writeln!(
+            f,
+            "Requests:      {} ({:.2}/s), {} errors ({:.1}%)",
+            self.requests,
+            self.requests_per_second,
+            self.errors,
+            self.error_rate * 100.0
+        )?;
+        writeln!(f, "Throughput:    {:.1} tokens/s", self.tokens_per_second)?;
+        writeln!(f)?;
+        writeln!(f, "{:<10} {:>8} {:>8} {:>8} {:>8}", "", "P50", "P90", "P99", "MAX")?;
+        for (name, p) in [("latency", &self.latency), ("ttft", &self.ttft)] {
+            writeln!(
+                f,
+                "{:<10} {:>6}ms {:>6}ms {:>6}ms {:>6}ms",
+                name, p.p50_ms, p.p90_ms, p.p99_ms, p.max_ms
+            )?;
+        }
+        for (error, count) in &self.error_counts {
+            writeln!(f, "\n{:>6}x {}", count, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// One finished request
+#[derive(Debug)]
+struct Sample {
+    latency: Duration,
+    ttft: Option<Duration>,
+    tokens: u64,
+}
+
+/// Run the load test and summarize it
+pub async fn run(test: &LoadTest, target: &Target) -> Result<LoadTestReport> {
+    let driver = match target {
+        Target::Server { url, api_key } => Driver::Server {
+            client: reqwest::Client::new(),
+            url: format!("{}/v1/chat/completions", url.trim_end_matches('/')),
+            api_key: api_key.clone(),
+        },
+        Target::Pool(manager) => {
+            // Start the pool up front so spawning isn't measured
+            let pool = manager.pool(&test.model).await?;
+            Driver::Pool {
+                manager: manager.clone(),
+                pool,
+            }
+        }
+    };
+    let driver = Arc::new(driver);
+    let results = Arc::new(Mutex::new(Vec::new()));
+
+    tracing::info!(
+        model = %test.model,
+        concurrency = test.concurrency,
+        duration_secs = test.duration.as_secs(),
+        "Starting load test"
+    );
+    let started = Instant::now();
+    let deadline = started + test.duration;
+    let workers: Vec<_> = (0..test.concurrency.max(1))
+        .map(|_| {
+            let driver = driver.clone();
+            let results = results.clone();
+            let test = test.clone();
+            tokio::spawn(async move {
+                while Instant::now() < deadline {
+                    let result = driver.request(&test).await;
+                    results.lock().unwrap().push(result.map_err(|e| e.to_string()));
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.await.context("Load test worker panicked")?;
+    }
+    let elapsed = started.elapsed();
+
+    let results = std::mem::take(&mut *results.lock().unwrap());
+    Ok(summarize(test, target, elapsed, results))
+}
+
+fn summarize(
+    test: &LoadTest,
+    target: &Target,
+    elapsed: Duration,
+    results: Vec<std::result::Result<Sample, String>>,
+) -> LoadTestReport {
+    let requests = results.len() as u64;
+    let mut latencies = Vec::new();
+    let mut ttfts = Vec::new();
+    let mut tokens = 0;
+    let mut error_counts = BTreeMap::new();
+    for result in results {
+        match result {
+            Ok(sample) => {
+                latencies.push(sample.latency);
+                ttfts.extend(sample.ttft);
+                tokens += sample.tokens;
+            }
+            Err(e) => *error_counts.entry(e).or_insert(0) += 1,
+        }
+    }
+    let errors = error_counts.values().sum::<u64>();
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+
+    LoadTestReport {
+        model: test.model.clone(),
+        target: match target {
+            Target::Server { url, .. } => url.clone(),
+            Target::Pool(_) => "local pool".to_string(),
+        },
+        concurrency: test.concurrency,
+        elapsed_secs: elapsed.as_secs_f64(),
+        requests,
+        errors,
+        error_rate: if requests > 0 { errors as f64 / requests as f64 } else { 0.0 },
+        requests_per_second: requests as f64 / secs,
+        tokens_per_second: tokens as f64 / secs,
+        latency: Percentiles::from_durations(latencies),
+        ttft: Percentiles::from_durations(ttfts),
+        error_counts,
+    }
+}
+
+enum Driver {
+    Server {
+        client: reqwest::Client,
+        url: String,
+        api_key: Option<String>,
+    },
+    Pool {
+        manager: Box<LitManager>,
+        pool: Arc<ProcessPool>,
+    },
+}
+
+impl Driver {
+    async fn request(&self, test: &LoadTest) -> Result<Sample> {
+        let started = Instant::now();
+        let mut ttft = None;
+        let mut generated = String::new();
+        // Counted by the server or the manager's tokenizer for the model
+        let mut tokens = None;
+
+        match self {
+            Driver::Server { client, url, api_key } => {
+                let body = serde_json::json!({
+                    "model": test.model,
+                    "messages": [{ "role": "user", "content": test.prompt }],
+                    "stream": true,
+                    "stream_options": { "include_usage": true },
+                });
+                let mut request = client.post(url).json(&body);
+                if let Some(key) = api_key {
+                    request = request.bearer_auth(key);
+                }
+                let response = request.send().await.context("Request failed")?;
+                if !response.status().is_success() {
+                    anyhow::bail!("HTTP {}", response.status());
+                }
+
+                // Server-sent events; a line may span chunks
+                let mut stream = response.bytes_stream();
+                let mut pending = String::new();
+                while let Some(chunk) = stream.next().await {
+                    pending.push_str(&String::from_utf8_lossy(&chunk.context("Stream interrupted")?));
+                    while let Some(end) = pending.find('\n') {
+                        let line: String = pending.drain(..=end).collect();
+                        if let Some(content) = sse_content(line.trim()) {
+                            ttft.get_or_insert_with(|| started.elapsed());
+                            generated.push_str(&content);
+                        }
+                        tokens = sse_completion_tokens(line.trim()).or(tokens);
+                    }
+                }
+            }
+            Driver::Pool { manager, pool } => {
+                let _slot = pool.admit(manager.tenant()).await?;
+                let process = pool.get_process().await?;
+                let mut stream = process.send_prompt_stream(&test.prompt).await?;
+                while let Some(chunk) = stream.next().await {
+                    ttft.get_or_insert_with(|| started.elapsed());
+                    generated.push_str(&chunk?);
+                }
+                tokens = Some(manager.tokenizers().count(&test.model, &generated) as u64);
+            }
+        }
+
+        Ok(Sample {
+            latency: started.elapsed(),
+            ttft,
+            // Servers that don't report usage are estimated
+            tokens: tokens.unwrap_or_else(|| Estimate.count(&generated) as u64),
+        })
+    }
+}
+
+/// Generated text in a `data:` line of a chat completion stream
+fn sse_content(line: &str) -> Option<String> {
+    let data = line.strip_prefix("data:")?.trim();
+    let chunk: serde_json::Value = serde_json::from_str(data).ok()?;
+    let content = chunk["choices"][0]["delta"]["content"].as_str()?;
+    (!content.is_empty()).then(|| content.to_string())
+}
+
+/// Completion tokens in the `usage` of a `data:` line of a chat completion stream
+fn sse_completion_tokens(line: &str) -> Option<u64> {
+    let data = line.strip_prefix("data:")?.trim();
+    let chunk: serde_json::Value = serde_json::from_str(data).ok()?;
+    chunk["usage"]["completion_tokens"].as_u64()
+}
@@ -0,0 +1,109 @@
+//! Log files that rotate by size
+//!
+//! Long-running servers on headless devices have no terminal to scroll back
+//! through, so `log.file` (or `--log-file`) also writes the log to
+//! `litert-lm.log` under the log directory. Once that file reaches
+//! `log.max_bytes` it is renamed to `litert-lm.log.1`, older files shift up one
+//! number, and files beyond `log.max_files` are deleted. Maintenance can also
+//! ask for a rotation regardless of size; see [`request_rotation`].
+
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Name of the file currently written to
+pub const LOG_FILE: &str = "litert-lm.log";
+
+// Set once a log file is open, so callers know whether a rotation will happen
+static OPEN: AtomicBool = AtomicBool::new(false);
+static ROTATE: AtomicBool = AtomicBool::new(false);
+
+/// Rotate the open log file before its next write, whatever its size; false
+/// if no log file is written
+pub fn request_rotation() -> bool {
+    ROTATE.store(true, Ordering::SeqCst);
+    OPEN.load(Ordering::SeqCst)
+}
+
+/// Append-only log file in `dir` that rotates once it reaches `max_bytes`
+#[derive(Debug)]
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    /// Open (or create) the log in `dir`, keeping up to `max_files` rotated files
+    pub fn open(dir: &Path, max_bytes: u64, max_files: usize) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+        let (file, written) = Self::open_current(dir)?;
+        OPEN.store(true, Ordering::SeqCst);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_bytes: max_bytes.max(1),
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    /// The file currently written to
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(LOG_FILE)
+    }
+
+    fn open_current(dir: &Path) -> Result<(File, u64)> {
+        let path = dir.join(LOG_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open log file {}", path.display()))?;
+        let written = file.metadata()?.len();
+        Ok((file, written))
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}", LOG_FILE, n))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let _ = fs::remove_file(self.rotated(self.max_files));
+        for n in (1..self.max_files).rev() {
+            let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        if self.max_files == 0 {
+            fs::remove_file(self.path())?;
+        } else {
+            fs::rename(self.path(), self.rotated(1))?;
+        }
+        let (file, written) = Self::open_current(&self.dir).map_err(io::Error::other)?;
+        self.file = file;
+        self.written = written;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    /// Each log event arrives as one write, so rotating before a write that
+    /// would overflow keeps events whole
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let requested = ROTATE.swap(false, Ordering::SeqCst);
+        if self.written > 0 && (requested || self.written + buf.len() as u64 > self.max_bytes) {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
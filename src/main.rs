@@ -1,5 +1,14 @@
+use anyhow::Context;
 use clap::{Parser, Subcommand, ValueEnum};
-use litert_lm::{LitManager, LiteRtMcpService, Result};
+use litert_lm::loadtest::{self, LoadTest, Target};
+use litert_lm::manager::ManagerStats;
+use indicatif::{ProgressBar, ProgressStyle};
+use litert_lm::progress::{Phase, Progress, ProgressReporter};
+use litert_lm::replay::{self, ReplayStatus};
+use litert_lm::{
+    AgentOptions, ChatSession, Cluster, Config, GenerationTiming, HistoryQuery, HistoryStatus, HubQuery, InflightRequest, LitManager, LiteRtMcpService,
+    LitertExtension, Result, SessionEvent, TlsConfig, TranscriptFormat, Turn,
+};
 
 #[derive(Parser)]
 #[command(name = "litert-lm")]
@@ -7,6 +16,9 @@ use litert_lm::{LitManager, LiteRtMcpService, Result};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Also write logs to rotating files (see `log` in the config file)
+    #[arg(long, global = true)]
+    log_file: bool,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -27,26 +39,240 @@ enum Commands {
         #[arg(long)]
         show_all: bool,
     },
-    /// Download a model from registry or URL
+    /// Download a model from the registry, a URL, object storage, or a file
+    ///
+    /// A model family (e.g. `gemma-3n`) pulls the largest variant this device
+    /// can run. `s3://bucket/key` and `gs://bucket/object` pull from object
+    /// storage with the `aws` or `gcloud` CLI's credentials, URLs can be given
+    /// `--header`s, and a file path imports a local model file.
     Pull {
         model: String,
-        /// Alias to save the model as (only for URLs)
+        /// Variant of the model family to pull instead of the one picked for this device
+        #[arg(long)]
+        variant: Option<String>,
+        /// Alias to save the model as (not for registry models)
         #[arg(long)]
         alias: Option<String>,
         /// Hugging Face API token for authentication
         #[arg(long)]
         hf_token: Option<String>,
+        /// Treat the model as a Hugging Face Hub repository id (owner/name)
+        #[arg(long)]
+        hub: bool,
+        /// Model file to pull from a Hub repository with several
+        #[arg(long, requires = "hub")]
+        file: Option<String>,
+        /// Download URLs here and resume a pull that failed halfway; `--resume=false` leaves them to lit
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+        resume: bool,
+        /// SHA-256 the model file must have; a model that doesn't is removed again
+        #[arg(long, conflicts_with = "hub")]
+        sha256: Option<String>,
+        /// Header to send when downloading a URL, as 'Name: value' (repeatable), e.g. an API key
+        #[arg(long = "header", conflicts_with = "hub")]
+        headers: Vec<String>,
+    },
+    /// Hash a downloaded model and compare it with the checksum recorded when it was pulled
+    Verify { model: String },
+    /// Search the model registry by name, size, or tag
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Search the Hugging Face Hub instead of lit's registry
+        #[arg(long)]
+        hub: bool,
+        /// Only Hub repositories with this tag (repeatable)
+        #[arg(long = "tag", requires = "hub")]
+        tags: Vec<String>,
+        /// Only Hub repositories of this library
+        #[arg(long, default_value = litert_lm::hub::LIBRARY, requires = "hub")]
+        library: String,
     },
-    /// Remove a locally downloaded model
+    /// Remove a locally downloaded model or adapter
     Rm { model: String },
+    /// Package a downloaded model into a directory, to import on another machine
+    Cp {
+        model: String,
+        /// Directory to create the package in
+        dest: std::path::PathBuf,
+    },
+    /// Pull the model in a package made by `cp`
+    Import {
+        /// Package directory
+        package: std::path::PathBuf,
+        /// Alias to save the model as instead of the one it was exported with
+        #[arg(long)]
+        alias: Option<String>,
+    },
+    /// Manage LoRA adapters, which run as `<base>:<name>`
+    Adapter {
+        #[command(subcommand)]
+        command: AdapterCommand,
+    },
     /// Run a LiteRT-LM model and start an interactive session
-    Run { model: String },
+    ///
+    /// Type `/save [path]` to save the conversation, `/load <id or path>` to
+    /// continue a saved one, `/export [markdown|jsonl] [path]` to write a
+    /// transcript, and `/exit` to quit.
+    Run {
+        model: String,
+        /// Continue a saved session (an id from `/save` or a file path)
+        #[arg(long)]
+        resume: Option<String>,
+        /// Use lit's own interactive session (no saving or context management)
+        #[arg(long)]
+        raw: bool,
+        /// Print NDJSON events (ready, token, done, stats, error) instead of text
+        #[arg(long, conflicts_with = "raw")]
+        json: bool,
+    },
     /// Generate completion script
     Completion { shell: String },
     /// Start OpenAI-compatible API server
     Serve {
         #[arg(short, long, default_value = "8080")]
         port: u16,
+        /// Address to listen on, e.g. 127.0.0.1 for local clients only
+        #[arg(long, default_value = "0.0.0.0")]
+        host: std::net::IpAddr,
+        /// Refuse completions (503) until the preloaded pool has finished a warm-up generation
+        #[arg(long)]
+        wait_ready: bool,
+        /// Start process pools for every downloaded model at startup, concurrently
+        #[arg(long)]
+        preload_all: bool,
+        /// Split streamed completions into raw chunks, sentences, or clauses (for text-to-speech)
+        #[arg(long, default_value = "raw")]
+        chunking: litert_lm::stream::Chunking,
+        /// Require this API key (`Authorization: Bearer <key>`); repeat for several
+        #[arg(long = "api-key")]
+        api_keys: Vec<String>,
+        /// Seconds to let open requests finish after SIGINT/SIGTERM before stopping
+        #[arg(long, default_value = "30")]
+        shutdown_timeout: u64,
+        /// Serve HTTPS with this certificate chain (PEM); needs --tls-key
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<std::path::PathBuf>,
+        /// Private key (PEM) for --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<std::path::PathBuf>,
+        /// Run every model on this backend (auto, gpu, cpu, or npu) instead of the config's
+        #[arg(long)]
+        backend: Option<litert_lm::Backend>,
+    },
+    /// Show recorded completions from the request history
+    History {
+        /// Only show requests for this model
+        #[arg(long)]
+        model: Option<String>,
+        /// Only show requests newer than this age (e.g. 30m, 2h, 7d)
+        #[arg(long)]
+        since: Option<humantime::Duration>,
+        /// Only show requests with this status (success or error)
+        #[arg(long)]
+        status: Option<HistoryStatus>,
+        /// Maximum number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+        /// Print entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-run recorded requests and diff the new responses with the recorded ones
+    Replay {
+        /// Request ID from `history`, or a JSON lines file from `history --json`
+        source: String,
+        /// Model to replay against (defaults to each request's own model)
+        #[arg(long)]
+        model: Option<String>,
+        /// Print results as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Answer a prompt with the agent loop, using configured tools and MCP servers
+    Agent {
+        prompt: String,
+        /// Model to run (defaults to $LITERT_MODEL or gemma-3n-E4B)
+        #[arg(long)]
+        model: Option<String>,
+        /// Tool calls allowed before giving up
+        #[arg(long, default_value = "8")]
+        max_steps: usize,
+        /// Print each tool call and its result
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Add documents (txt, md, pdf) to the local retrieval index
+    Ingest {
+        /// Files to ingest; re-ingesting a file replaces its earlier version
+        #[arg(required = true)]
+        paths: Vec<std::path::PathBuf>,
+    },
+    /// Show loaded models, queue depth, and errors of a running server
+    Status {
+        /// Base URL of the running server
+        #[arg(long, default_value = "http://localhost:8080")]
+        url: String,
+    },
+    /// List the model processes of a running server, or its generations in progress
+    Ps {
+        /// List generations in progress (id, model, age, client) instead
+        #[arg(long)]
+        requests: bool,
+        /// Base URL of the running server
+        #[arg(long, default_value = "http://localhost:8080")]
+        url: String,
+        /// API key for servers with tenants configured
+        #[arg(long)]
+        api_key: Option<String>,
+    },
+    /// Kill a runaway generation of a running server, by an id from `ps --requests`
+    Kill {
+        id: String,
+        /// Base URL of the running server
+        #[arg(long, default_value = "http://localhost:8080")]
+        url: String,
+        /// API key for servers with tenants configured
+        #[arg(long)]
+        api_key: Option<String>,
+    },
+    /// Measure latency, time to first token, and throughput under concurrent load
+    Loadtest {
+        /// Model to request (defaults to $LITERT_MODEL or gemma-3n-E4B)
+        #[arg(long)]
+        model: Option<String>,
+        /// Requests kept in flight
+        #[arg(short, long, default_value = "4")]
+        concurrency: usize,
+        /// How long to keep starting requests (e.g. 30s, 5m)
+        #[arg(short, long, default_value = "60s")]
+        duration: humantime::Duration,
+        /// Prompt sent with every request
+        #[arg(long, default_value = "Write a short paragraph about the ocean.")]
+        prompt: String,
+        /// Base URL of the server to drive
+        #[arg(long, default_value = "http://localhost:8080")]
+        url: String,
+        /// API key for servers with tenants configured
+        #[arg(long)]
+        api_key: Option<String>,
+        /// Drive a local process pool directly instead of a server
+        #[arg(long)]
+        direct: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Front several litert-lm servers as one OpenAI-compatible endpoint
+    Router {
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+        /// Base URL of a node (repeatable; added to `cluster.nodes` from the config file)
+        #[arg(long = "node")]
+        nodes: Vec<String>,
+        /// Discover nodes advertised over mDNS (requires the mdns feature)
+        #[arg(long)]
+        discover: bool,
     },
     /// Start MCP (Model Context Protocol) server
     Mcp {
@@ -56,7 +282,32 @@ enum Commands {
         /// Port for SSE/HTTP transports (ignored for stdio)
         #[arg(short, long, default_value = "3000")]
         port: u16,
+        /// Address the SSE/HTTP transports listen on, e.g. 127.0.0.1 for local clients only
+        #[arg(long, default_value = "0.0.0.0")]
+        host: std::net::IpAddr,
+        /// Serve the SSE transport over HTTPS with this certificate chain (PEM); needs --tls-key
+        #[arg(long, requires = "tls_key")]
+        tls_cert: Option<std::path::PathBuf>,
+        /// Private key (PEM) for --tls-cert
+        #[arg(long, requires = "tls_cert")]
+        tls_key: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdapterCommand {
+    /// Store an adapter file for a downloaded model
+    Add {
+        /// Model the adapter was trained for
+        base: String,
+        /// Adapter file
+        path: std::path::PathBuf,
+        /// Name to use it by (defaults to the file name)
+        #[arg(long)]
+        name: Option<String>,
     },
+    /// List stored adapters
+    List,
 }
 
 #[tokio::main]
@@ -66,34 +317,657 @@ async fn main() -> Result<()> {
     // Configure tracing based on command - for MCP stdio, write to stderr to avoid polluting stdout
     let use_stderr = matches!(cli.command, Commands::Mcp { transport: McpTransport::Stdio, .. });
 
-    if use_stderr {
-        tracing_subscriber::fmt()
-            .with_writer(std::io::stderr)
-            .init();
-    } else {
-        tracing_subscriber::fmt::init();
+    let mut log = Config::load()?.log;
+    log.file |= cli.log_file;
+    let _telemetry = litert_lm::telemetry::init_with_log(use_stderr, &log)?;
+
+    // Status only talks to a running server, so it doesn't need a local manager
+    if let Commands::Status { url } = &cli.command {
+        return print_status(url).await;
+    }
+    // Nor do ps and kill
+    if let Commands::Ps { requests, url, api_key } = &cli.command {
+        return print_ps(url, api_key.as_deref(), *requests).await;
+    }
+    if let Commands::Kill { id, url, api_key } = &cli.command {
+        return kill_request(url, api_key.as_deref(), id).await;
+    }
+    // Neither does the router, which only proxies to other servers
+    if let Commands::Router { port, nodes, discover } = &cli.command {
+        return run_router(*port, nodes, *discover).await;
+    }
+
+    let mut manager = LitManager::new().await?;
+
+    // Local commands and MCP clients act as the tenant named by $LITERT_TENANT, if any
+    if let Ok(name) = std::env::var("LITERT_TENANT") {
+        let tenant = manager
+            .tenants()
+            .by_name(&name)
+            .with_context(|| format!("Unknown tenant '{}' in LITERT_TENANT", name))?;
+        manager = manager.for_tenant(tenant);
+    }
+    // Local commands draw progress bars; the MCP service reports through its resources instead
+    if !matches!(cli.command, Commands::Mcp { .. } | Commands::Serve { .. }) {
+        manager = manager.with_progress(std::sync::Arc::new(TerminalProgress::default()));
     }
-    let manager = LitManager::new().await?;
 
     match cli.command {
         Commands::List { show_all } => manager.list(show_all).await?,
-        Commands::Pull { model, alias, hf_token } => manager.pull(&model, alias.as_deref(), hf_token.as_deref()).await?,
+        Commands::Pull { model, hub: true, file, alias, hf_token, resume, .. } => {
+            let provenance = manager
+                .with_resumable_downloads(resume)
+                .pull_from_hub(&model, file.as_deref(), alias.as_deref(), hf_token.as_deref())
+                .await?;
+            println!(
+                "Pulled {} from {} ({} at {})",
+                provenance.model, provenance.repo, provenance.file, provenance.revision
+            );
+        }
+        Commands::Pull { model, variant, alias, hf_token, hub: false, resume, sha256, headers, .. } => {
+            let model = match (manager.variants().family(&model), variant) {
+                (Some(variants), Some(variant)) => {
+                    if !variants.iter().any(|v| v.model == variant) {
+                        anyhow::bail!("'{}' is not a variant of {}", variant, model);
+                    }
+                    variant
+                }
+                (None, Some(_)) => anyhow::bail!("'{}' is not a model family", model),
+                (Some(_), None) => {
+                    let selected = manager.resolve_variant(&model);
+                    let device = litert_lm::DeviceInfo::current();
+                    println!(
+                        "Selected {} for this device ({} RAM, {})",
+                        selected,
+                        device.memory_gb().map_or("unknown".to_string(), |gb| format!("{:.1} GB", gb)),
+                        if device.gpu { "GPU" } else { "no GPU" }
+                    );
+                    selected
+                }
+                (None, None) => model,
+            };
+            let headers = headers
+                .iter()
+                .map(|header| litert_lm::download::parse_header(header))
+                .collect::<anyhow::Result<reqwest::header::HeaderMap>>()?;
+            let manager = manager.with_resumable_downloads(resume).with_download_headers(headers);
+            match sha256 {
+                Some(sha256) => {
+                    manager.pull_with_sha256(&model, alias.as_deref(), hf_token.as_deref(), &sha256).await?
+                }
+                None => manager.pull(&model, alias.as_deref(), hf_token.as_deref()).await?,
+            }
+        }
+        Commands::Verify { model } => {
+            let record = manager.verify(&model).await?;
+            println!("{} is intact (sha256 {}, {} bytes)", model, record.sha256, record.size_bytes);
+        }
+        Commands::Search { query, hub: true, tags, library } => {
+            let query = HubQuery {
+                search: query.join(" "),
+                library: Some(library).filter(|library| !library.is_empty()),
+                tags,
+                ..HubQuery::new("")
+            };
+            let models = manager.search_hub(&query, None).await?;
+            if models.is_empty() {
+                println!("No Hugging Face models match '{}'", query.search);
+            }
+            for model in &models {
+                println!(
+                    "{:<48} {:>9} downloads {:>5} likes{}",
+                    model.id,
+                    model.downloads,
+                    model.likes,
+                    if model.gated { "  (gated)" } else { "" }
+                );
+            }
+        }
+        Commands::Search { query, hub: false, .. } => {
+            let query = query.join(" ");
+            let matches = manager.search_registry(&query).await?;
+            if matches.is_empty() {
+                println!("No models match '{}'", query);
+            }
+            for found in &matches {
+                println!("{:<32} {}", found.entry.name, found.entry.details.join(" "));
+            }
+        }
         Commands::Rm { model } => manager.remove(&model).await?,
-        Commands::Run { model } => manager.run_interactive(&model).await?,
+        Commands::Cp { model, dest } => {
+            let manifest = manager.export(&model, &dest).await?;
+            println!("Exported {} ({} bytes) to {}", model, manifest.size_bytes, dest.display());
+        }
+        Commands::Import { package, alias } => {
+            let manifest = manager.import(&package, alias.as_deref()).await?;
+            println!("Imported {} as {}", manifest.name, alias.as_deref().unwrap_or(&manifest.alias));
+        }
+        Commands::Adapter { command: AdapterCommand::Add { base, path, name } } => {
+            let name = match name {
+                Some(name) => name,
+                None => path
+                    .file_stem()
+                    .context("Adapter path has no file name")?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            let adapter = manager.add_adapter(&base, &name, &path).await?;
+            println!("Added adapter {}", adapter.model());
+        }
+        Commands::Adapter { command: AdapterCommand::List } => {
+            for adapter in manager.adapters() {
+                println!("{}", adapter);
+            }
+        }
+        Commands::Run { model, raw: true, .. } => manager.run_interactive(&model).await?,
+        Commands::Run { model, resume, raw: false, json } => {
+            let session = match resume {
+                Some(saved) => ChatSession::resume_session(manager.clone(), &session_path(&manager, &saved))?,
+                None => manager.chat_session(&model),
+            };
+            run_chat(&manager, session, json).await?
+        }
         Commands::Completion { shell } => manager.generate_completion(&shell)?,
-        Commands::Serve { port } => manager.serve(port).await?,
-        Commands::Mcp { transport, port } => {
-            run_mcp_server(manager, transport, port).await?
+        Commands::Serve { port, host, wait_ready, preload_all, chunking, api_keys, shutdown_timeout, tls_cert, tls_key, backend } => {
+            if let Some(backend) = backend {
+                manager = manager.with_backend(backend);
+            }
+            let options = litert_lm::ServeOptions {
+                wait_ready,
+                preload_all,
+                chunking,
+                api_keys,
+                shutdown_timeout: Some(std::time::Duration::from_secs(shutdown_timeout)),
+                tls: tls_config(tls_cert, tls_key),
+            };
+            manager.serve_on_with(std::net::SocketAddr::new(host, port), &options).await?
+        }
+        Commands::Agent { prompt, model, max_steps, verbose } => {
+            let model = model
+                .or_else(|| std::env::var("LITERT_MODEL").ok())
+                .unwrap_or_else(|| "gemma-3n-E4B".to_string());
+            manager.connect_mcp_servers().await;
+
+            let options = AgentOptions { max_steps };
+            let turns = [Turn::new("user", prompt)];
+            let run = manager.run_agent(&model, &turns, manager.tools(), &options).await?;
+            if verbose {
+                for step in &run.steps {
+                    println!("→ {} {}", step.tool, step.arguments);
+                    println!("  {}", step.result.replace('\n', "\n  "));
+                }
+            }
+            println!("{}", run.answer);
+        }
+        Commands::Ingest { paths } => {
+            for document in manager.ingest_documents(&paths).await? {
+                println!("Ingested {} ({} chunks)", document.source, document.chunks);
+            }
+        }
+        Commands::History { model, since, status, limit, json } => {
+            let query = HistoryQuery {
+                model,
+                since: since.map(|age| {
+                    std::time::SystemTime::now()
+                        .checked_sub(age.into())
+                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0)
+                }),
+                until: None,
+                status,
+                limit,
+            };
+            print_history(&manager, &query, json).await?
+        }
+        Commands::Replay { source, model, json } => {
+            let records = replay::load(&manager, &source).await?;
+            let results = replay::replay(&manager, &records, model.as_deref()).await;
+            if json {
+                for result in &results {
+                    println!("{}", serde_json::to_string(result)?);
+                }
+            } else {
+                print!("{}", replay::render(&results));
+            }
+            let changed = results.iter().filter(|r| r.status == ReplayStatus::Changed).count();
+            if changed > 0 {
+                anyhow::bail!("{} of {} replayed responses changed", changed, results.len());
+            }
+        }
+        Commands::Loadtest { model, concurrency, duration, prompt, url, api_key, direct, json } => {
+            let test = LoadTest {
+                model: model
+                    .or_else(|| std::env::var("LITERT_MODEL").ok())
+                    .unwrap_or_else(|| "gemma-3n-E4B".to_string()),
+                prompt,
+                concurrency,
+                duration: duration.into(),
+            };
+            let target = if direct {
+                Target::Pool(Box::new(manager))
+            } else {
+                Target::Server { url, api_key }
+            };
+            let report = loadtest::run(&test, &target).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
         }
+        Commands::Status { .. } | Commands::Ps { .. } | Commands::Kill { .. } | Commands::Router { .. } => {
+            unreachable!("handled before manager setup")
+        }
+        Commands::Mcp { transport, port, host, tls_cert, tls_key } => {
+            let addr = std::net::SocketAddr::new(host, port);
+            run_mcp_server(manager, transport, addr, tls_config(tls_cert, tls_key)).await?
+        }
+    }
+
+    Ok(())
+}
+
+/// Progress bars on stderr for downloads and model loads, one at a time
+#[derive(Default)]
+struct TerminalProgress {
+    bar: std::sync::Mutex<Option<(Phase, ProgressBar)>>,
+}
+
+impl ProgressReporter for TerminalProgress {
+    fn report(&self, progress: &Progress) {
+        let mut current = self.bar.lock().unwrap();
+        if current.as_ref().is_some_and(|(phase, _)| *phase != progress.phase) {
+            if let Some((_, bar)) = current.take() {
+                bar.abandon();
+            }
+        }
+        let (_, bar) = current.get_or_insert_with(|| {
+            let (len, template) = match progress.total_bytes {
+                Some(total) => (total, "{msg} [{bar:40}] {bytes}/{total_bytes} ({eta})"),
+                None => (100, "{msg} [{bar:40}] {pos}%"),
+            };
+            let label = match progress.phase {
+                Phase::DownloadingBinary => "Downloading lit",
+                Phase::PullingModel => "Pulling",
+                Phase::LoadingModel => "Loading",
+                _ => "Working on",
+            };
+            let bar = ProgressBar::new(len).with_style(
+                ProgressStyle::with_template(template)
+                    .expect("valid progress template")
+                    .progress_chars("=> "),
+            );
+            bar.set_message(match &progress.model {
+                Some(model) => format!("{} {}", label, model),
+                None => label.to_string(),
+            });
+            (progress.phase, bar)
+        });
+
+        match (progress.total_bytes, progress.bytes, progress.percent) {
+            (Some(_), Some(bytes), _) => bar.set_position(bytes),
+            (_, _, Some(percent)) => bar.set_position(percent as u64),
+            _ => {}
+        }
+        if progress.is_finished() {
+            bar.finish();
+            *current = None;
+        }
+    }
+}
+
+/// A session id saved under the sessions directory, or a path to a session file
+fn session_path(manager: &LitManager, saved: &str) -> std::path::PathBuf {
+    let path = std::path::PathBuf::from(saved);
+    if path.exists() || saved.contains(std::path::MAIN_SEPARATOR) || saved.ends_with(".json") {
+        path
+    } else {
+        ChatSession::default_path(manager, saved)
+    }
+}
+
+/// Where `/export [markdown|jsonl] [path]` writes a transcript, and in which
+/// format: given one, else by the path's extension; `<session id>.md` in the
+/// current directory by default
+fn export_target(session: &ChatSession, argument: &str) -> (TranscriptFormat, std::path::PathBuf) {
+    let (first, rest) = argument.split_once(' ').unwrap_or((argument, ""));
+    let (format, path) = match first.parse::<TranscriptFormat>() {
+        Ok(format) => (Some(format), rest.trim()),
+        Err(_) => (None, argument),
+    };
+    let format = format.unwrap_or_else(|| match std::path::Path::new(path).extension() {
+        Some(extension) if extension == "jsonl" => TranscriptFormat::Jsonl,
+        _ => TranscriptFormat::Markdown,
+    });
+    let path = if path.is_empty() {
+        std::path::PathBuf::from(format!("{}.{}", session.id(), format.extension()))
+    } else {
+        std::path::PathBuf::from(path)
+    };
+    (format, path)
+}
+
+/// Interactive chat on top of a [`ChatSession`], with `/save`, `/load`,
+/// `/export`, and `/exit`
+///
+/// With `json`, everything on stdout is a [`SessionEvent`] per line instead.
+async fn run_chat(manager: &LitManager, mut session: ChatSession, json: bool) -> Result<()> {
+    use std::io::Write;
+    use tokio::io::AsyncBufReadExt;
+
+    let emit = |event: SessionEvent| {
+        if let Ok(line) = serde_json::to_string(&event) {
+            println!("{}", line);
+        }
+    };
+    let report_error = |e: anyhow::Error| {
+        if json {
+            emit(SessionEvent::Error { message: format!("{:#}", e) });
+        } else {
+            eprintln!("Error: {:#}", e);
+        }
+    };
+
+    if !json && !session.history().is_empty() {
+        println!("Resumed session {} ({} messages)", session.id(), session.history().len());
+    }
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        if json {
+            emit(SessionEvent::Ready {
+                session: session.id().to_string(),
+                model: session.model().to_string(),
+                messages: session.history().len(),
+            });
+        } else {
+            print!(">>> ");
+            std::io::stdout().flush()?;
+        }
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        match command {
+            "" => continue,
+            "/exit" | "/quit" => break,
+            "/save" => {
+                let path = if argument.is_empty() {
+                    ChatSession::default_path(manager, session.id())
+                } else {
+                    std::path::PathBuf::from(argument)
+                };
+                match session.save_session(&path) {
+                    Ok(()) if json => emit(SessionEvent::Saved { session: session.id().to_string(), path }),
+                    Ok(()) => println!("Saved session {} to {}", session.id(), path.display()),
+                    Err(e) => report_error(e),
+                }
+            }
+            "/export" => {
+                let (format, path) = export_target(&session, argument);
+                let written = session
+                    .export(format)
+                    .and_then(|transcript| {
+                        std::fs::write(&path, transcript).with_context(|| format!("Failed to write {}", path.display()))
+                    });
+                match written {
+                    Ok(()) if json => emit(SessionEvent::Exported { session: session.id().to_string(), path }),
+                    Ok(()) => println!("Exported session {} to {}", session.id(), path.display()),
+                    Err(e) => report_error(e),
+                }
+            }
+            "/load" if argument.is_empty() => report_error(anyhow::anyhow!("Usage: /load <session id or path>")),
+            "/load" => match ChatSession::resume_session(manager.clone(), &session_path(manager, argument)) {
+                Ok(loaded) => {
+                    session = loaded;
+                    if json {
+                        emit(SessionEvent::Loaded {
+                            session: session.id().to_string(),
+                            messages: session.history().len(),
+                        });
+                    } else {
+                        println!("Loaded session {} ({} messages)", session.id(), session.history().len());
+                    }
+                }
+                Err(e) => report_error(e),
+            },
+            _ => {
+                let started = std::time::Instant::now();
+                let mut first_token = None;
+                let result = session
+                    .send_with(line, |chunk| {
+                        first_token.get_or_insert_with(|| started.elapsed());
+                        if json {
+                            emit(SessionEvent::Token { text: chunk.to_string() });
+                        } else {
+                            print!("{}", chunk);
+                            let _ = std::io::stdout().flush();
+                        }
+                    })
+                    .await;
+                if !json {
+                    println!();
+                }
+                match result {
+                    Ok(reply) if json => {
+                        let timing = GenerationTiming {
+                            time_to_first_token: first_token,
+                            total: started.elapsed(),
+                        };
+                        emit(SessionEvent::Done { text: reply.trim().to_string() });
+                        let tokenizer = manager.tokenizers().for_model(session.model());
+                        emit(SessionEvent::Stats(LitertExtension::from_timing(&timing, &reply, &*tokenizer)));
+                    }
+                    Ok(_) => {}
+                    Err(e) => report_error(e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn print_history(manager: &LitManager, query: &HistoryQuery, json: bool) -> Result<()> {
+    let records = manager.history(query).await?;
+
+    if json {
+        for record in &records {
+            println!("{}", serde_json::to_string(record)?);
+        }
+        return Ok(());
+    }
+
+    if records.is_empty() {
+        println!("No matching requests recorded");
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:<24} {:<8} {:<8} {:>9} {:>8} {:>8}",
+        "TIME", "MODEL", "SOURCE", "STATUS", "DURATION", "PROMPT", "RESPONSE"
+    );
+    for record in &records {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(record.created_at);
+        let status = match record.status {
+            HistoryStatus::Success => "success",
+            HistoryStatus::Error => "error",
+        };
+        println!(
+            "{:<20} {:<24} {:<8} {:<8} {:>7}ms {:>8} {:>8}",
+            humantime::format_rfc3339_seconds(time).to_string(),
+            record.model,
+            record.source,
+            status,
+            record.duration_ms,
+            record.prompt_chars,
+            record.response_chars
+        );
+        if let Some(error) = &record.error {
+            println!("    error: {}", error);
+        }
+    }
+
+    Ok(())
+}
+
+async fn print_status(url: &str) -> Result<()> {
+    let base = url.trim_end_matches('/');
+    let client = reqwest::Client::new();
+
+    let health = client
+        .get(format!("{}/health", base))
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach server at {}", base))?;
+    if !health.status().is_success() {
+        anyhow::bail!("Server at {} is unhealthy: HTTP {}", base, health.status());
+    }
+
+    let stats: ManagerStats = client
+        .get(format!("{}/v1/stats", base))
+        .send()
+        .await
+        .context("Failed to query server stats")?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to parse server stats")?;
+
+    let metrics = &stats.metrics;
+    println!("Server:        {} (up {}s)", base, metrics.uptime_secs);
+    println!("Requests:      {}", metrics.http_requests_total);
+    println!(
+        "Errors:        {} total, {} in the last {}s",
+        metrics.http_errors_total, metrics.recent_errors, metrics.recent_error_window_secs
+    );
+    println!(
+        "Processes:     {} spawned, {} failed to spawn, {} exited",
+        metrics.process_spawns_total, metrics.process_spawn_failures_total, metrics.process_exits_total
+    );
+    println!();
+
+    print_pools(&stats);
+    Ok(())
+}
+
+fn print_pools(stats: &ManagerStats) {
+    if stats.pools.is_empty() {
+        println!("No models loaded");
+        return;
+    }
+    println!("{:<32} {:>10} {:>12} {:>13}", "MODEL", "PROCESSES", "QUEUE DEPTH", "CPU FALLBACK");
+    for pool in &stats.pools {
+        println!(
+            "{:<32} {:>10} {:>12} {:>13}",
+            pool.model, pool.processes, pool.queue_depth, pool.gpu_fallbacks
+        );
+    }
+    let fallbacks: usize = stats.pools.iter().map(|pool| pool.gpu_fallbacks).sum();
+    if fallbacks > 0 {
+        println!();
+        println!(
+            "Warning: {} process(es) fell back from GPU to CPU and will generate much more slowly",
+            fallbacks
+        );
+    }
+}
+
+/// GET `path` of a running server, authenticated with `api_key` if given
+async fn server_get<T: serde::de::DeserializeOwned>(url: &str, api_key: Option<&str>, path: &str) -> Result<T> {
+    let base = url.trim_end_matches('/');
+    let mut request = reqwest::Client::new().get(format!("{}{}", base, path));
+    if let Some(key) = api_key {
+        request = request.bearer_auth(key);
+    }
+    request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach server at {}", base))?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse the response of {}", path))
+}
+
+async fn print_ps(url: &str, api_key: Option<&str>, requests: bool) -> Result<()> {
+    if !requests {
+        let stats: ManagerStats = server_get(url, api_key, "/v1/stats").await?;
+        print_pools(&stats);
+        return Ok(());
     }
 
+    #[derive(serde::Deserialize)]
+    struct Requests {
+        data: Vec<InflightRequest>,
+    }
+    let requests = server_get::<Requests>(url, api_key, "/admin/requests").await?.data;
+    if requests.is_empty() {
+        println!("No generations in progress");
+        return Ok(());
+    }
+    println!("{:<46} {:<24} {:>9}  CLIENT", "ID", "MODEL", "AGE");
+    for request in &requests {
+        println!(
+            "{:<46} {:<24} {:>8.1}s  {}",
+            request.id,
+            request.model,
+            request.age_ms as f64 / 1000.0,
+            request.client
+        );
+    }
+    Ok(())
+}
+
+async fn kill_request(url: &str, api_key: Option<&str>, id: &str) -> Result<()> {
+    let base = url.trim_end_matches('/');
+    let mut request = reqwest::Client::new().delete(format!("{}/admin/requests/{}", base, id));
+    if let Some(key) = api_key {
+        request = request.bearer_auth(key);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach server at {}", base))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("No request '{}' in progress", id);
+    }
+    response.error_for_status()?;
+    println!("Killed {}", id);
     Ok(())
 }
 
+async fn run_router(port: u16, nodes: &[String], discover: bool) -> Result<()> {
+    let config = Config::load()?.cluster;
+    let cluster = Cluster::new(config.nodes.into_iter().chain(nodes.iter().cloned()));
+    let discover = discover || config.discover;
+
+    if discover {
+        #[cfg(feature = "mdns")]
+        let _discovery = cluster.discover()?;
+        #[cfg(not(feature = "mdns"))]
+        anyhow::bail!("Node discovery requires litert-lm built with the mdns feature");
+    } else if cluster.nodes().is_empty() {
+        anyhow::bail!("No cluster nodes: pass --node, set cluster.nodes in the config file, or use --discover");
+    }
+
+    cluster
+        .serve(port, std::time::Duration::from_secs(config.health_interval_secs))
+        .await
+}
+
+/// `--tls-cert` and `--tls-key`, which clap only accepts together
+fn tls_config(cert: Option<std::path::PathBuf>, key: Option<std::path::PathBuf>) -> Option<TlsConfig> {
+    cert.zip(key).map(|(cert, key)| TlsConfig { cert, key })
+}
+
 async fn run_mcp_server(
     manager: LitManager,
     transport: McpTransport,
-    port: u16,
+    addr: std::net::SocketAddr,
+    tls: Option<TlsConfig>,
 ) -> Result<()> {
     use rmcp::{ServiceExt, transport::stdio};
 
@@ -108,26 +982,43 @@ async fn run_mcp_server(
             tracing::info!("Server terminated");
         }
         McpTransport::Sse => {
-            tracing::info!("Starting MCP server with SSE transport on port {}", port);
+            tracing::info!("Starting MCP server with SSE transport on {}", addr);
 
             // Create SSE server config
             let ct = tokio_util::sync::CancellationToken::new();
             let config = rmcp::transport::sse_server::SseServerConfig {
-                bind: format!("0.0.0.0:{}", port).parse()?,
+                bind: addr,
                 sse_path: "/sse".to_string(),
                 post_path: "/message".to_string(),
                 ct: ct.clone(),
                 sse_keep_alive: Some(std::time::Duration::from_secs(30)),
             };
 
-            // Start SSE server
-            let sse_server = rmcp::transport::sse_server::SseServer::serve_with_config(config).await?;
+            match tls {
+                None => {
+                    // Start SSE server
+                    let sse_server = rmcp::transport::sse_server::SseServer::serve_with_config(config).await?;
 
-            // Serve with the service
-            let _ct = sse_server.with_service_directly(move || service.clone());
+                    // Serve with the service
+                    let _ct = sse_server.with_service_directly(move || service.clone());
 
-            // Keep running
-            tokio::signal::ctrl_c().await?;
+                    // Keep running
+                    tokio::signal::ctrl_c().await?;
+                }
+                Some(tls) => {
+                    // rmcp only listens on plain TCP itself; serve its router over TLS instead
+                    let rustls = tls.load().await?;
+                    let bind = config.bind;
+                    let (sse_server, router) = rmcp::transport::sse_server::SseServer::new(config);
+                    let _ct = sse_server.with_service_directly(move || service.clone());
+                    tracing::info!("MCP SSE endpoint: https://{}/sse", bind);
+                    tokio::select! {
+                        result = axum_server::bind_rustls(bind, rustls).serve(router.into_make_service()) => result?,
+                        _ = tokio::signal::ctrl_c() => {}
+                    }
+                    ct.cancel();
+                }
+            }
         }
         McpTransport::Http => {
             // Note: Streamable HTTP transport requires session management and is more complex.
@@ -0,0 +1,151 @@
+//! Housekeeping for servers left running unattended
+//!
+//! With `maintenance.enabled`, `serve` runs a few chores at the time set by
+//! `maintenance.at` (a cron expression in UTC, nightly by default): it removes
+//! partial downloads and temporary files left by interrupted runs, rotates the
+//! log file, refreshes the saved registry listing, and checks for a newer lit
+//! release. The network steps are skipped with `maintenance.offline`. Each run
+//! is logged and sent to [`LitManager::subscribe`] as
+//! [`ManagerEvent::MaintenanceFinished`].
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::MaintenanceConfig;
+use crate::history::unix_now;
+use crate::log_file;
+use crate::manager::{LitManager, ManagerEvent};
+use crate::schedule::Cron;
+
+/// What a maintenance run did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceSummary {
+    /// Unix time in seconds
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// Partial downloads and temporary files removed
+    pub pruned_files: usize,
+    pub pruned_bytes: u64,
+    /// False when no log file is written
+    pub logs_rotated: bool,
+    /// Models in the refreshed registry listing; `None` if it wasn't refreshed
+    pub registry_models: Option<usize>,
+    /// Latest lit release, if this build doesn't use it
+    pub binary_update: Option<String>,
+    /// Whether the network steps were skipped
+    pub offline: bool,
+    /// Steps that failed, with their errors
+    pub errors: Vec<String>,
+}
+
+/// Whether `name` is a file left by an interrupted download
+/// (`<file>.partial-<id>`) or write (`<file>.json.<id>`)
+pub fn is_partial_file(name: &str) -> bool {
+    let Some((stem, suffix)) = name.rsplit_once('.') else {
+        return false;
+    };
+    let id = match suffix.strip_prefix("partial-") {
+        Some(id) => id,
+        None if stem.ends_with(".json") || stem.ends_with(".txt") => suffix,
+        None => return false,
+    };
+    id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Remove partial files under `dir` last modified more than `stale_after`
+/// ago; returns how many files and bytes were removed
+pub fn prune_partial_files(dir: &Path, stale_after: Duration) -> Result<(usize, u64)> {
+    let (mut files, mut bytes) = (0, 0);
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let (f, b) = prune_partial_files(&entry.path(), stale_after)?;
+            files += f;
+            bytes += b;
+            continue;
+        }
+        if !file_type.is_file() || !is_partial_file(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+        if age < stale_after {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => {
+                tracing::debug!(path = %entry.path().display(), "Removed partial file");
+                files += 1;
+                bytes += metadata.len();
+            }
+            Err(e) => tracing::warn!(path = %entry.path().display(), error = %e, "Failed to remove partial file"),
+        }
+    }
+    Ok((files, bytes))
+}
+
+/// Run every maintenance step once; failed steps are recorded in the
+/// summary rather than stopping the run
+pub async fn run_once(manager: &LitManager, config: &MaintenanceConfig) -> MaintenanceSummary {
+    let started = Instant::now();
+    let mut summary = MaintenanceSummary {
+        started_at: unix_now(),
+        offline: config.offline,
+        ..MaintenanceSummary::default()
+    };
+
+    match prune_partial_files(manager.cache_dir(), Duration::from_secs(config.stale_after_secs)) {
+        Ok((files, bytes)) => {
+            summary.pruned_files = files;
+            summary.pruned_bytes = bytes;
+        }
+        Err(e) => summary.errors.push(format!("prune: {:#}", e)),
+    }
+    summary.logs_rotated = log_file::request_rotation();
+
+    if !config.offline {
+        match manager.refresh_registry().await {
+            Ok(models) => summary.registry_models = Some(models),
+            Err(e) => summary.errors.push(format!("registry: {:#}", e)),
+        }
+        match manager.check_for_update().await {
+            Ok(update) => summary.binary_update = update,
+            Err(e) => summary.errors.push(format!("update check: {:#}", e)),
+        }
+    }
+
+    summary.duration_ms = started.elapsed().as_millis() as u64;
+    tracing::info!(
+        pruned_files = summary.pruned_files,
+        pruned_bytes = summary.pruned_bytes,
+        logs_rotated = summary.logs_rotated,
+        registry_models = ?summary.registry_models,
+        binary_update = ?summary.binary_update,
+        errors = ?summary.errors,
+        "Maintenance finished"
+    );
+    if let Some(tag) = &summary.binary_update {
+        tracing::warn!(latest = %tag, "A newer lit release is out; upgrade litert-lm to use it");
+    }
+    manager.emit(ManagerEvent::MaintenanceFinished { summary: summary.clone() });
+    summary
+}
+
+/// Run maintenance whenever `config.at` fires until the task is dropped
+pub async fn run(manager: LitManager, config: MaintenanceConfig, at: Cron) {
+    loop {
+        let now = unix_now();
+        let minute = now - now % 60 + 60;
+        tokio::time::sleep(Duration::from_secs(minute - now)).await;
+        if at.matches(minute) {
+            run_once(&manager, &config).await;
+        }
+    }
+}
@@ -1,39 +1,859 @@
 use anyhow::{Context, Result};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
-use tokio_stream::Stream;
+use futures::stream::BoxStream;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
 
+use crate::adapter::{Adapter, AdapterStore};
+use crate::agent::{self, AgentOptions, AgentRun, ToolRegistry};
+use crate::backend::{Backend, TextStream};
 use crate::binary::BinaryManager;
-use crate::process::ProcessPool;
-use crate::server::{create_router, AppState};
+use crate::breaker::{CircuitBreaker, CircuitChange, CircuitStatus};
+use crate::cache_lock::CacheLock;
+use crate::cancel::{self, Cancelled};
+use crate::checksum::{self, ChecksumRecord, ChecksumStore};
+use crate::concurrency::ConcurrencyLimits;
+use crate::config::Config;
+use crate::context::{self, ContextWindow, Fitted, Turn};
+use crate::template::ChatTemplate;
+use crate::download;
+use crate::dspy::SignatureCache;
+use crate::error::LitError;
+#[cfg(feature = "ffi")]
+use crate::ffi::FfiBackend;
+use crate::generation::{self, GenOptions};
+#[cfg(feature = "mcp")]
+use crate::mcp_client::McpClientManager;
+use crate::moderation::{self, ModerationResult};
+use crate::history::{HistoryQuery, HistoryRecord, HistoryStore};
+use crate::inflight::{InflightGuard, InflightRequest, InflightRequests};
+use crate::keep_alive::{self, KeepAlive};
+use crate::hub::{self, HubClient, HubModel, HubQuery, ModelProvenance, ProvenanceStore};
+use crate::limits::{self, Limits, ResourceLimits};
+use crate::lit_args::LitArgs;
+use crate::maintenance::MaintenanceSummary;
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::process::{self, PoolStats, ProcessDebugInfo, ProcessDiagnostics, ProcessPool};
+use crate::progress::{self, Phase, Progress, ProgressReporter};
+use crate::prompt_cache::PromptCache;
+use crate::quota::{self, CachedModel, ModelUsage};
+use crate::registry::{self, ManifestModel, ModelInfo, RegistryManifest, RegistryMatch};
+use crate::retrieval::{DocumentIndex, DocumentInfo, Embedder, HashingEmbedder, SearchHit};
+use crate::retry::{ErrorClass, Permanent, RetryPolicy};
+use crate::sampling::{Sampling, SamplingDefaults};
+use crate::sandbox::Sandbox;
+use crate::schedule::ScheduleAction;
+use crate::source::{self, FileServer, ModelSource, ModelSources, PullTarget};
+#[cfg(feature = "server")]
+use crate::server::{create_router, shutdown_signal, AppState, Readiness, ReadyState, ServeOptions, DEFAULT_SHUTDOWN_TIMEOUT};
+use crate::session::ChatSession;
+use crate::package::{self, PackageManifest};
+use crate::profile::Profiles;
+use crate::split::Splits;
+use crate::stream::{limit_tokens, stop_at, CompletionEvent, CompletionStream};
+use crate::tenant::{Tenant, Tenants};
+use crate::throttle::TokenBucket;
+use crate::tokenizer::{Tokenizer, Tokenizers};
+use crate::variant::{DeviceInfo, Variants};
+
+/// lit processes per model unless set with [`LitManagerBuilder::pool_size`]
+const DEFAULT_POOL_SIZE: usize = 2;
+/// How long a pull or removal waits for another process working on the same model
+const MODEL_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3600);
+/// Registry listing saved by [`LitManager::refresh_registry`], under the cache directory
+const REGISTRY_CACHE_FILE: &str = "registry.txt";
+
+/// Model name of a `lit list` output line, or `None` for headers and blank lines
+pub(crate) fn model_line_name(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    if trimmed.starts_with("Available") || trimmed.starts_with("Downloaded") || trimmed.starts_with("ALIAS") {
+        return None;
+    }
+    trimmed.split_whitespace().next()
+}
+
+/// Status of a single model's process pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStatus {
+    pub model: String,
+    pub processes: usize,
+    pub queue_depth: usize,
+    /// Processes running on CPU because the GPU backend failed
+    #[serde(default)]
+    pub gpu_fallbacks: usize,
+    /// Backend each process runs on (`gpu`, `cpu`, or `npu`)
+    #[serde(default)]
+    pub backends: Vec<String>,
+    /// Extra lit flags, for pools started for requests with `x_litert.args`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Seconds since the pool last had a request; unset while it has some
+    #[serde(default)]
+    pub idle_secs: Option<u64>,
+}
+
+/// Something that happened to the manager's pools, as sent to
+/// [`LitManager::subscribe`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ManagerEvent {
+    /// A pool for the model started
+    ModelLoaded { model: String },
+    /// The model's pools were dropped; their processes exit once idle
+    ModelUnloaded { model: String },
+    /// A transition from the `schedule` config couldn't be applied
+    ScheduleFailed { model: String, action: ScheduleAction, error: String },
+    /// The model's processes kept crashing; requests fail fast until `until`
+    /// (a Unix timestamp in seconds)
+    CircuitOpened { model: String, crashes: usize, until: u64 },
+    /// The model's back-off is over and a fresh pool is being tried
+    CircuitHalfOpen { model: String },
+    /// The fresh pool generated successfully; the model is served again
+    CircuitClosed { model: String },
+    /// A maintenance run (see `config.maintenance`) finished
+    MaintenanceFinished { summary: MaintenanceSummary },
+}
+
+/// A pull resolved through its model source
+struct PreparedPull {
+    /// The reference, for logs and metrics (never a signed URL)
+    model: String,
+    /// What lit pulls
+    target: String,
+    alias: Option<String>,
+    /// Digest a registry manifest lists for the model
+    sha256: Option<String>,
+    // Keeps a local file available to lit until the pull is done
+    _server: Option<FileServer>,
+}
+
+/// Events buffered for a slow subscriber before it starts missing them
+const EVENT_CAPACITY: usize = 64;
+
+/// Point-in-time snapshot of the manager, as returned by [`LitManager::stats`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagerStats {
+    pub pool_size: usize,
+    pub pools: Vec<PoolStatus>,
+    pub metrics: MetricsSnapshot,
+}
+
+/// Async runtime counters from tokio's runtime metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeInfo {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub global_queue_depth: usize,
+}
+
+/// Workers of one model's pool
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolDebugInfo {
+    pub model: String,
+    pub processes: Vec<ProcessDebugInfo>,
+}
+
+/// A pool's processes and their stderr tails, as returned by
+/// [`LitManager::diagnostics`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolDiagnostics {
+    pub model: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    pub processes: Vec<ProcessDiagnostics>,
+}
+
+/// Task dump returned by [`LitManager::debug_tasks`]
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskDump {
+    pub runtime: RuntimeInfo,
+    /// False while a pool is being created, since that holds the pool map lock
+    pub pools_available: bool,
+    pub pools: Vec<PoolDebugInfo>,
+    /// Number of processes flagged as stuck
+    pub stuck: usize,
+}
 
 #[derive(Debug, Clone)]
 pub struct LitManager {
-    binary_manager: BinaryManager,
+    binary_manager: Arc<BinaryManager>,
     binary_path: Arc<RwLock<Option<PathBuf>>>,
     // Map of pools, keyed by model name
     process_pools: Arc<Mutex<HashMap<String, Arc<ProcessPool>>>>,
     // Make pool size configurable
     pool_size: usize,
+    metrics: Arc<Metrics>,
+    history: Option<Arc<HistoryStore>>,
+    retrieval: Option<Arc<DocumentIndex>>,
+    prompt_cache: Option<Arc<PromptCache>>,
+    config: Arc<Config>,
+    tools: ToolRegistry,
+    #[cfg(feature = "mcp")]
+    mcp_clients: Arc<tokio::sync::OnceCell<McpClientManager>>,
+    tenants: Arc<Tenants>,
+    splits: Arc<Splits>,
+    profiles: Arc<Profiles>,
+    variants: Arc<Variants>,
+    dspy_signatures: Arc<SignatureCache>,
+    // Shared by every pool; see `config.throttle`
+    throttle: Option<Arc<TokenBucket>>,
+    // Who this handle acts for; see `for_tenant`
+    tenant: Arc<Tenant>,
+    // Aborts this handle's downloads and generations; see `with_cancellation`
+    cancel: Option<CancellationToken>,
+    // Follows downloads and model loads; see `with_progress`
+    progress: Option<Arc<dyn ProgressReporter>>,
+    // Overrides `config.backends` for every model; see `with_backend`
+    backend: Option<Backend>,
+    // How failed downloads, pulls, and pool starts are retried; see `with_retry_policy`
+    retry: Arc<RetryPolicy>,
+    // Where pulled models come from, by scheme; see `with_model_source`
+    sources: Arc<ModelSources>,
+    // Whether URL pulls are downloaded here, resumably; see `with_resumable_downloads`
+    resume: bool,
+    // Sent with URL downloads; see `with_download_headers`
+    download_headers: Arc<HeaderMap>,
+    // Digest the next pull must have; see `pull_with_sha256`
+    expected_sha256: Option<String>,
+    // Requests in flight per model; see `config.concurrency`
+    concurrency: Arc<ConcurrencyLimits>,
+    // Token counts for `usage`; see `config.tokenizers`
+    tokenizers: Arc<Tokenizers>,
+    // Sampling settings models' processes start with; see `config.sampling`
+    sampling: Arc<SamplingDefaults>,
+    // Containment of lit processes; see `config.sandbox`
+    sandbox: Option<Arc<Sandbox>>,
+    // Generations in progress; see `inflight`
+    inflight: Arc<InflightRequests>,
+    // Per-model crash tracking, created on first use; see `config.circuit_breaker`
+    breakers: Arc<std::sync::Mutex<HashMap<String, Arc<CircuitBreaker>>>>,
+    // Pool loads and unloads; see `subscribe`
+    events: tokio::sync::broadcast::Sender<ManagerEvent>,
+    // Serves requests that name no model; see `LitManagerBuilder::default_model`
+    default_model: Option<String>,
+    // Runs completions in-process instead of on lit; see `with_ffi_backend`
+    #[cfg(feature = "ffi")]
+    ffi: Option<Arc<FfiBackend>>,
+}
+
+/// Sets up a [`LitManager`] without relying on environment variables and
+/// built-in defaults; anything left unset falls back to them, as with
+/// [`LitManager::new`]
+#[derive(Debug, Default)]
+pub struct LitManagerBuilder {
+    config: Option<Config>,
+    cache_dir: Option<PathBuf>,
+    pool_size: Option<usize>,
+    backend: Option<Backend>,
+    binary_path: Option<PathBuf>,
+    default_model: Option<String>,
+}
+
+impl LitManagerBuilder {
+    /// Use `config` instead of loading `$LITERT_CONFIG` or the config file
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Keep the lit binary, history, and other cached state under `dir`
+    /// instead of `$LITERT_CACHE_DIR` or the user's cache directory
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// lit processes per model; 2 by default
+    pub fn pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = Some(pool_size);
+        self
+    }
+
+    /// Hardware every model runs on, overriding `config.backends`
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Run this lit binary rather than downloading one into the cache
+    pub fn binary_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.binary_path = Some(path.into());
+        self
+    }
+
+    /// Model used when a completion names none
+    pub fn default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    pub async fn build(self) -> Result<LitManager> {
+        let pool_size = self.pool_size.unwrap_or(DEFAULT_POOL_SIZE);
+        if pool_size == 0 {
+            anyhow::bail!("pool_size must be at least 1");
+        }
+        if let Some(path) = &self.binary_path {
+            if !path.is_file() {
+                anyhow::bail!("lit binary {} does not exist", path.display());
+            }
+        }
+        let config = match self.config {
+            Some(config) => config,
+            None => Config::load()?,
+        };
+        let tools = ToolRegistry::from_config(&config.tools);
+        let tenants = Tenants::from_config(&config.tenants).with_api_keys(&config.api_keys());
+        let splits = Splits::from_config(&config.splits);
+        let profiles = Profiles::from_config(&config.profiles);
+        let variants = Variants::from_config(&config.variants);
+        let throttle = config
+            .throttle
+            .tokens_per_second
+            .map(|rate| Arc::new(TokenBucket::new(rate, config.throttle.burst_tokens)));
+        let concurrency = Arc::new(ConcurrencyLimits::from_config(&config.concurrency));
+        let tokenizers = Arc::new(Tokenizers::from_config(&config.tokenizers)?);
+        let sampling = Arc::new(SamplingDefaults::from_config(&config.sampling));
+        limits::check(&config.limits)?;
+        let sandbox = Sandbox::from_config(&config.sandbox)?;
+        let sandbox = sandbox.is_enabled().then(|| {
+            tracing::info!(sandbox = ?config.sandbox, "lit processes run in a sandbox");
+            Arc::new(sandbox)
+        });
+        let metrics = Arc::new(Metrics::new());
+        let binary_manager = match self.cache_dir {
+            Some(dir) => BinaryManager::with_cache_dir(dir)?,
+            None => BinaryManager::new()?,
+        };
+        let binary_manager = binary_manager
+            .with_metrics(metrics.clone())
+            .with_artifact(config.binary.artifact.clone());
+        let history = LitManager::open_history(binary_manager.cache_dir());
+        let retrieval = LitManager::open_retrieval(&config, binary_manager.cache_dir());
+        let prompt_cache = LitManager::open_prompt_cache(&config, binary_manager.cache_dir());
+
+        Ok(LitManager {
+            binary_manager: Arc::new(binary_manager),
+            binary_path: Arc::new(RwLock::new(self.binary_path)),
+            process_pools: Arc::new(Mutex::new(HashMap::new())),
+            pool_size,
+            metrics,
+            history,
+            retrieval,
+            prompt_cache,
+            config: Arc::new(config),
+            tools,
+            #[cfg(feature = "mcp")]
+            mcp_clients: Arc::new(tokio::sync::OnceCell::new()),
+            tenants: Arc::new(tenants),
+            splits: Arc::new(splits),
+            profiles: Arc::new(profiles),
+            variants: Arc::new(variants),
+            dspy_signatures: Arc::new(SignatureCache::default()),
+            throttle,
+            tenant: Arc::new(Tenant::unrestricted()),
+            cancel: None,
+            progress: None,
+            backend: self.backend,
+            retry: Arc::new(RetryPolicy::default()),
+            sources: Arc::new(ModelSources::default()),
+            resume: true,
+            download_headers: Arc::new(HeaderMap::new()),
+            expected_sha256: None,
+            concurrency,
+            tokenizers,
+            sampling,
+            sandbox,
+            inflight: Arc::new(InflightRequests::default()),
+            breakers: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            events: tokio::sync::broadcast::channel(EVENT_CAPACITY).0,
+            default_model: self.default_model,
+            #[cfg(feature = "ffi")]
+            ffi: None,
+        })
+    }
 }
 
 impl LitManager {
     pub async fn new() -> Result<Self> {
-        Self::new_with_pool_size(2).await
+        Self::builder().build().await
     }
 
     pub async fn new_with_pool_size(pool_size: usize) -> Result<Self> {
-        let binary_manager = BinaryManager::new()?;
+        Self::builder().pool_size(pool_size).build().await
+    }
 
-        Ok(Self {
-            binary_manager,
-            binary_path: Arc::new(RwLock::new(None)),
-            process_pools: Arc::new(Mutex::new(HashMap::new())),
-            pool_size,
+    pub fn builder() -> LitManagerBuilder {
+        LitManagerBuilder::default()
+    }
+
+    /// A handle to the same pools and stores that only lets `tenant` see,
+    /// run, and manage the models in its namespace
+    pub fn for_tenant(&self, tenant: Tenant) -> Self {
+        Self {
+            tenant: Arc::new(tenant),
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores whose binary downloads, pulls,
+    /// pool starts, and completions fail with [`Cancelled`] once `token` is
+    /// cancelled, cleaning up anything partially downloaded
+    pub fn with_cancellation(&self, token: CancellationToken) -> Self {
+        Self {
+            binary_manager: Arc::new(self.binary_manager.as_ref().clone().with_cancellation(token.clone())),
+            cancel: Some(token),
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores that starts new pools on
+    /// `backend`, whatever `config.backends` says; pools already running
+    /// keep theirs
+    pub fn with_backend(&self, backend: Backend) -> Self {
+        Self {
+            backend: Some(backend),
+            ..self.clone()
+        }
+    }
+
+    /// Backend new pools of `model` are started on
+    pub fn backend(&self, model: &str) -> Backend {
+        self.backend.unwrap_or_else(|| Backend::for_model(&self.config.backends, model))
+    }
+
+    /// A handle to the same pools and stores that reports the lit binary
+    /// download, model pulls, and pool starts to `reporter`
+    pub fn with_progress(&self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        Self {
+            binary_manager: Arc::new(self.binary_manager.as_ref().clone().with_progress(reporter.clone())),
+            progress: Some(reporter),
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores that retries the lit binary
+    /// download, model pulls, and pool starts as `policy` says
+    pub fn with_retry_policy(&self, policy: RetryPolicy) -> Self {
+        Self {
+            binary_manager: Arc::new(self.binary_manager.as_ref().clone().with_retry_policy(policy.clone())),
+            retry: Arc::new(policy),
+            ..self.clone()
+        }
+    }
+
+    /// How this handle retries transient failures
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Requests in flight per model, shared by every handle
+    pub fn concurrency(&self) -> &ConcurrencyLimits {
+        &self.concurrency
+    }
+
+    /// Tokenizers counting the `usage` of completions, by model
+    pub fn tokenizers(&self) -> &Tokenizers {
+        &self.tokenizers
+    }
+
+    /// Default sampling settings, by model
+    pub fn sampling(&self) -> &SamplingDefaults {
+        &self.sampling
+    }
+
+    /// A handle to the same pools and stores whose server also accepts `keys`
+    /// as API keys, with access to every model
+    pub fn with_api_keys(&self, keys: &[String]) -> Self {
+        Self {
+            tenants: Arc::new(self.tenants.with_api_keys(keys)),
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores that counts the tokens of models
+    /// matching `pattern` with `tokenizer`
+    pub fn with_tokenizer(&self, pattern: &str, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        Self {
+            tokenizers: Arc::new(self.tokenizers.with(pattern, tokenizer)),
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores that runs completions in-process
+    /// on `engine`, through the LiteRT-LM C API, instead of on lit processes;
+    /// models it has no file registered for are loaded from where they were
+    /// pulled
+    #[cfg(feature = "ffi")]
+    pub fn with_ffi_backend(&self, engine: Arc<FfiBackend>) -> Self {
+        Self {
+            ffi: Some(engine),
+            ..self.clone()
+        }
+    }
+
+    #[cfg(feature = "ffi")]
+    fn runs_in_process(&self) -> bool {
+        self.ffi.is_some()
+    }
+
+    #[cfg(not(feature = "ffi"))]
+    fn runs_in_process(&self) -> bool {
+        false
+    }
+
+    /// The completion of `prompt` by `model` on the in-process engine
+    #[cfg(feature = "ffi")]
+    async fn in_process_stream(&self, model: &str, prompt: &str) -> Result<TextStream> {
+        use crate::backend::InferenceBackend;
+
+        self.tenant.check_use(model)?;
+        let engine = self.ffi.as_ref().context("No in-process engine; see with_ffi_backend")?;
+        if engine.has_model(model) {
+            return engine.complete_stream(model, prompt).await;
+        }
+        let (_, file) = self.stored_model(model).await?;
+        engine.complete_stream_at(model, &file, prompt).await
+    }
+
+    #[cfg(not(feature = "ffi"))]
+    async fn in_process_stream(&self, _model: &str, _prompt: &str) -> Result<TextStream> {
+        anyhow::bail!("Running models in-process needs the ffi feature")
+    }
+
+    /// Generations in progress (of any handle), oldest first; a tenant sees
+    /// its own and those of models it may manage
+    pub fn inflight(&self) -> Vec<InflightRequest> {
+        self.inflight
+            .list()
+            .into_iter()
+            .filter(|request| request.tenant == self.tenant.name() || self.tenant.can_manage(&request.model))
+            .collect()
+    }
+
+    /// Cancel a generation listed by [`inflight`](Self::inflight); false if
+    /// there is no such generation
+    pub fn kill_request(&self, id: &str) -> bool {
+        if !self.inflight().iter().any(|request| request.id == id) {
+            return false;
+        }
+        tracing::warn!(id = %id, tenant = %self.tenant.name(), "Killing generation");
+        self.inflight.kill(id)
+    }
+
+    /// List a generation in [`inflight`](Self::inflight) until the guard is
+    /// dropped; its token, cancelled by this handle's cancellation too, must
+    /// abort the generation
+    pub fn track_request(&self, id: &str, model: &str, client: Option<&str>) -> InflightGuard {
+        let token = self.cancel.as_ref().map_or_else(CancellationToken::new, CancellationToken::child_token);
+        let tenant = self.tenant.name();
+        self.inflight.register(id, model, tenant, client.unwrap_or(tenant), token)
+    }
+
+    /// Receive an event whenever a model is loaded or unloaded, by any handle
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<ManagerEvent> {
+        self.events.subscribe()
+    }
+
+    pub(crate) fn emit(&self, event: ManagerEvent) {
+        // Nobody listening is fine
+        let _ = self.events.send(event);
+    }
+
+    /// The tenant this handle acts for
+    pub fn tenant(&self) -> &Tenant {
+        &self.tenant
+    }
+
+    /// Tenants declared in the config file
+    pub fn tenants(&self) -> &Tenants {
+        &self.tenants
+    }
+
+    /// Logical model names split between models, declared in the config file
+    pub fn splits(&self) -> &Splits {
+        &self.splits
+    }
+
+    /// Per-model generation defaults, declared in the config file
+    pub fn profiles(&self) -> &Profiles {
+        &self.profiles
+    }
+
+    /// Parsed DSpy preambles, shared by every handle
+    pub fn dspy_signatures(&self) -> &SignatureCache {
+        &self.dspy_signatures
+    }
+
+    /// Model families whose variant is picked by device capability
+    pub fn variants(&self) -> &Variants {
+        &self.variants
+    }
+
+    /// Model completions use when they name none; see
+    /// [`LitManagerBuilder::default_model`]
+    pub fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    // `model`, or the default model if it is blank
+    fn model_or_default<'a>(&'a self, model: &'a str) -> &'a str {
+        match &self.default_model {
+            Some(default) if model.trim().is_empty() => default,
+            _ => model,
+        }
+    }
+
+    /// Model that serves `model`: for a family such as `gemma-3n`, the variant
+    /// named by `$LITERT_VARIANT` or else the one picked for this device;
+    /// otherwise `model` itself
+    pub fn resolve_variant(&self, model: &str) -> String {
+        let Some(variants) = self.variants.family(model) else {
+            return model.to_string();
+        };
+        if let Ok(chosen) = std::env::var("LITERT_VARIANT") {
+            if variants.iter().any(|variant| variant.model == chosen) {
+                return chosen;
+            }
+        }
+        let device = DeviceInfo::current();
+        let Some(variant) = self.variants.select(model, device) else {
+            return model.to_string();
+        };
+        tracing::debug!(
+            family = %model,
+            model = %variant.model,
+            memory_gb = ?device.memory_gb(),
+            gpu = device.gpu,
+            "Selected model variant for this device"
+        );
+        variant.model.clone()
+    }
+
+    fn open_history(cache_dir: &std::path::Path) -> Option<Arc<HistoryStore>> {
+        let enabled = std::env::var("LITERT_HISTORY")
+            .map(|v| !matches!(v.as_str(), "0" | "off" | "false"))
+            .unwrap_or(true);
+        if !enabled {
+            tracing::debug!("Request history disabled");
+            return None;
+        }
+
+        let record_content = std::env::var("LITERT_HISTORY_CONTENT")
+            .map(|v| matches!(v.as_str(), "1" | "on" | "true"))
+            .unwrap_or(false);
+
+        let path = cache_dir.join("history.db");
+        match HistoryStore::open(&path, record_content) {
+            Ok(store) => {
+                tracing::debug!(path = %path.display(), record_content, "Request history enabled");
+                Some(Arc::new(store))
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to open request history, recording disabled");
+                None
+            }
+        }
+    }
+
+    /// Query recorded completions, most recent first
+    pub async fn history(&self, query: &HistoryQuery) -> Result<Vec<HistoryRecord>> {
+        let store = self
+            .history
+            .clone()
+            .context("Request history is disabled (LITERT_HISTORY=off or the database failed to open)")?;
+        let query = query.clone();
+        tokio::task::spawn_blocking(move || store.query(&query)).await?
+    }
+
+    /// One recorded completion by its request ID
+    pub async fn history_entry(&self, id: &str) -> Result<Option<HistoryRecord>> {
+        let store = self
+            .history
+            .clone()
+            .context("Request history is disabled (LITERT_HISTORY=off or the database failed to open)")?;
+        let id = id.to_string();
+        tokio::task::spawn_blocking(move || store.get(&id)).await?
+    }
+
+    /// Persist a finished completion; failures are logged, never surfaced
+    pub fn record_history(&self, record: HistoryRecord) {
+        if let Some(store) = &self.history {
+            if let Err(e) = store.record(&record) {
+                tracing::warn!(error = %e, id = %record.id, "Failed to record request history");
+            }
+        }
+    }
+
+    fn open_prompt_cache(config: &Config, cache_dir: &std::path::Path) -> Option<Arc<PromptCache>> {
+        let config = &config.prompt_cache;
+        if !config.enabled {
+            return None;
+        }
+        let path = config.persist.then(|| cache_dir.join("prompt_cache.db"));
+        match PromptCache::open(config, path.as_deref()) {
+            Ok(cache) => {
+                tracing::debug!(persist = config.persist, "Prompt cache enabled");
+                Some(Arc::new(cache))
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to open prompt cache, caching disabled");
+                None
+            }
+        }
+    }
+
+    /// Responses cached for this prompt, when the prompt cache is enabled and
+    /// the options allow it
+    pub fn cached_responses(&self, model: &str, prompt: &str, options: &GenOptions) -> Option<Vec<String>> {
+        let cache = self.prompt_cache.as_ref().filter(|_| options.cache)?;
+        let hit = cache.get(self.tenant.name(), model, prompt, options);
+        let result = hit.as_ref().map(|(_, tier)| tier.as_str()).unwrap_or("miss");
+        self.metrics
+            .prompt_cache_lookups
+            .with_label_values(&[model, result])
+            .inc();
+        hit.map(|(responses, _)| responses)
+    }
+
+    /// Remember responses for [`cached_responses`](Self::cached_responses)
+    pub fn cache_responses(&self, model: &str, prompt: &str, options: &GenOptions, responses: Vec<String>) {
+        if let Some(cache) = self.prompt_cache.as_ref().filter(|_| options.cache) {
+            cache.put(self.tenant.name(), model, prompt, options, responses);
+        }
+    }
+
+    fn open_retrieval(config: &Config, cache_dir: &std::path::Path) -> Option<Arc<DocumentIndex>> {
+        let Some(model) = &config.retrieval.embedding_model else {
+            return Self::open_index(cache_dir.join("retrieval.db"), Arc::new(HashingEmbedder::default()));
+        };
+
+        #[cfg(feature = "embeddings")]
+        {
+            // Vectors from different embedders can't be searched together, so
+            // each model gets an index of its own
+            let name = model.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            match crate::retrieval::ModelEmbedder::load(model) {
+                Ok(embedder) => Self::open_index(cache_dir.join(format!("retrieval-{}.db", name)), Arc::new(embedder)),
+                Err(e) => {
+                    tracing::warn!(model = %model.display(), error = %e, "Failed to load the embedding model, document search disabled");
+                    None
+                }
+            }
+        }
+        #[cfg(not(feature = "embeddings"))]
+        {
+            tracing::warn!(
+                model = %model.display(),
+                "retrieval.embedding_model needs the embeddings feature, document search disabled"
+            );
+            None
+        }
+    }
+
+    fn open_index(path: PathBuf, embedder: Arc<dyn Embedder>) -> Option<Arc<DocumentIndex>> {
+        match DocumentIndex::open_with(&path, embedder) {
+            Ok(index) => Some(Arc::new(index)),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to open retrieval index, document search disabled");
+                None
+            }
+        }
+    }
+
+    fn retrieval_index(&self) -> Result<Arc<DocumentIndex>> {
+        self.retrieval
+            .clone()
+            .context("Retrieval index failed to open; see earlier warnings")
+    }
+
+    /// Ingest documents (txt, md, pdf) into the local retrieval index
+    pub async fn ingest_documents(&self, paths: &[PathBuf]) -> Result<Vec<DocumentInfo>> {
+        let index = self.retrieval_index()?;
+        let paths = paths.to_vec();
+        tokio::task::spawn_blocking(move || {
+            paths.iter().map(|path| index.ingest_file(path)).collect()
+        })
+        .await?
+    }
+
+    /// Documents currently in the retrieval index
+    pub async fn documents(&self) -> Result<Vec<DocumentInfo>> {
+        let index = self.retrieval_index()?;
+        tokio::task::spawn_blocking(move || index.documents()).await?
+    }
+
+    /// Search ingested documents for the chunks most relevant to `query`
+    pub async fn search_documents(&self, query: &str, top_k: usize) -> Result<Vec<SearchHit>> {
+        let index = self.retrieval_index()?;
+        let query = query.to_string();
+        tokio::task::spawn_blocking(move || index.search(&query, top_k)).await?
+    }
+
+    /// Configuration loaded from the config file
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Tools available to [`run_agent`](Self::run_agent); register closures here
+    pub fn tools(&self) -> &ToolRegistry {
+        &self.tools
+    }
+
+    /// Connect to the MCP servers from the config file (once) and register
+    /// their tools in [`tools`](Self::tools)
+    #[cfg(feature = "mcp")]
+    pub async fn connect_mcp_servers(&self) -> &McpClientManager {
+        self.mcp_clients
+            .get_or_init(|| async {
+                let clients = McpClientManager::connect(&self.config.mcp_servers).await;
+                clients.register_tools(&self.tools);
+                clients
+            })
+            .await
+    }
+
+    /// Answer the conversation `turns` with the model, executing tool calls
+    /// until it gives a final answer
+    ///
+    /// The transcript is rendered in the model's chat template; a profile's
+    /// system prompt starts conversations that have none.
+    pub async fn run_agent(
+        &self,
+        model: &str,
+        turns: &[Turn],
+        tools: &ToolRegistry,
+        options: &AgentOptions,
+    ) -> Result<AgentRun> {
+        let model = self.model_or_default(model);
+        let mut turns = turns.to_vec();
+        let served = match self.profiles.get(model) {
+            Some(profile) => {
+                profile.apply_turns(&mut turns);
+                profile.model()
+            }
+            None => model,
+        };
+        // Already templated, so the completion sends it as is
+        let raw = GenOptions {
+            raw: true,
+            ..Default::default()
+        };
+        agent::run_agent(tools, &turns, self.chat_template(served), options, |transcript| {
+            let raw = &raw;
+            async move { self.run_completion_with(model, &transcript, raw).await }
         })
+        .await
+    }
+
+    /// Shared metrics registry for this manager and everything it spawns
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
     }
 
     async fn ensure_binary(&self) -> Result<PathBuf> {
@@ -52,7 +872,10 @@ impl LitManager {
         }
 
         tracing::info!("Ensuring binary is available");
-        let path = self.binary_manager.ensure_binary().await?;
+        let path = self.binary_manager.ensure_binary().await.map_err(|e| match e.is::<Cancelled>() {
+            true => e,
+            false => LitError::BinaryUnavailable { reason: format!("{:#}", e) }.into(),
+        })?;
         tracing::info!(path = %path.display(), "Binary path obtained");
         *write_lock = Some(path.clone());
         Ok(path)
@@ -64,11 +887,43 @@ impl LitManager {
 
     // Helper function to get-or-create a pool for a specific model
     async fn get_pool(&self, model: &str) -> Result<Arc<ProcessPool>> {
+        self.get_pool_with(model, &LitArgs::default(), Sampling::default()).await
+    }
+
+    /// Get or create the pool serving `model` with extra lit flags and
+    /// sampling settings; pools are keyed by model and flags
+    ///
+    /// Every pool starts lit with the model's default sampling settings, so
+    /// `sampling` only needs its own pool where it differs from them.
+    #[tracing::instrument(name = "manager.get_pool", skip(self))]
+    async fn get_pool_with(&self, model: &str, args: &LitArgs, sampling: Sampling) -> Result<Arc<ProcessPool>> {
+        let model = &self.resolve_variant(self.model_or_default(model));
+        self.tenant.check_use(model)?;
+        args.check().map_err(anyhow::Error::msg)?;
+        sampling.check().map_err(anyhow::Error::msg)?;
+        let defaults = self.sampling.for_model(model);
+        let sampling = sampling.or(defaults);
+        let mut key = args.pool_key(model);
+        if sampling != defaults {
+            key = format!("{} {}", key, sampling.flags().join(" "));
+        }
+
+        // Fail fast while the model is backed off
+        let restart = match self.breaker(model) {
+            Some(breaker) => breaker.check()?,
+            None => false,
+        };
+
         // 1. Lock the pool map
         let mut pools = self.process_pools.lock().await;
 
+        // Processes of a tripped breaker's pools are dead; start afresh
+        if restart {
+            pools.retain(|_, pool| retain_unless(pool, pool.model() == model));
+        }
+
         // 2. Check if a pool for this model already exists
-        if let Some(pool) = pools.get(model) {
+        if let Some(pool) = pools.get(&key) {
             tracing::debug!(model = %model, "Using existing process pool");
             return Ok(pool.clone());
         }
@@ -77,42 +932,650 @@ impl LitManager {
 
         // 3. If not, create, initialize, and insert it
         let binary_path = self.ensure_binary().await?;
-        let mut new_pool = ProcessPool::new(
-            binary_path,
-            model.to_string(),
-            self.pool_size,
-        );
+        let flags = args.flags().into_iter().chain(sampling.flags()).collect();
+        let mut new_pool = self.new_pool(binary_path, model).with_args(flags);
 
-        new_pool.initialize().await?; // Initialize *before* inserting
+        // Initialize *before* inserting; a cancelled start drops the processes
+        progress::report(self.progress.as_ref(), Progress::new(Phase::LoadingModel, Some(model)).percent(0.0));
+        self.start_pool(&mut new_pool).await?;
+        progress::report(self.progress.as_ref(), Progress::new(Phase::LoadingModel, Some(model)).percent(100.0));
 
         let pool_arc = Arc::new(new_pool);
-        pools.insert(model.to_string(), pool_arc.clone());
+        pools.insert(key, pool_arc.clone());
+        // Recorded once the map is unlocked, so a slow disk doesn't hold up
+        // requests for other models
+        drop(pools);
+        self.touch_model(model).await;
+        self.emit(ManagerEvent::ModelLoaded { model: model.to_string() });
         tracing::info!(model = %model, "Process pool created and initialized");
         Ok(pool_arc)
     }
 
-    pub async fn run_completion(&self, model: &str, prompt: &str) -> Result<String> {
-        tracing::debug!(model = %model, prompt_length = prompt.len(), "Running completion");
+    /// Initialize `pool`, trying again as the retry policy allows when its
+    /// processes fail to start, unless lit doesn't have the model or the
+    /// failures opened the model's circuit breaker
+    async fn start_pool(&self, pool: &mut ProcessPool) -> Result<()> {
+        let mut attempt = 1;
+        while let Err(e) = cancel::run(self.cancel.as_ref(), pool.initialize()).await {
+            if matches!(LitError::of(&e), Some(LitError::ModelNotFound { .. })) {
+                return Err(e);
+            }
+            if let Some(breaker) = self.breaker(pool.model()) {
+                breaker.check()?;
+            }
+            self.retry
+                .wait_to_retry("pool_start", ErrorClass::Process, attempt, e, self.cancel.as_ref(), &self.metrics)
+                .await?;
+            attempt += 1;
+        }
+        Ok(())
+    }
+
+    /// Circuit breaker of `model`, unless disabled in the config
+    fn breaker(&self, model: &str) -> Option<Arc<CircuitBreaker>> {
+        let config = &self.config.circuit_breaker;
+        if !config.enabled {
+            return None;
+        }
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(model.to_string()).or_insert_with(|| {
+            let events = self.events.clone();
+            let name = model.to_string();
+            let breaker = CircuitBreaker::new(model, config.clone()).with_listener(move |change| {
+                let model = name.clone();
+                let event = match change {
+                    CircuitChange::Opened { crashes, until } => ManagerEvent::CircuitOpened {
+                        model,
+                        crashes: *crashes,
+                        until: *until,
+                    },
+                    CircuitChange::HalfOpened => ManagerEvent::CircuitHalfOpen { model },
+                    CircuitChange::Closed => ManagerEvent::CircuitClosed { model },
+                };
+                let _ = events.send(event);
+            });
+            Arc::new(breaker)
+        });
+        Some(breaker.clone())
+    }
+
+    /// State of `model`'s circuit breaker, once the model was used
+    pub fn circuit(&self, model: &str) -> Option<CircuitStatus> {
+        let model = self.resolve_variant(model);
+        self.breakers.lock().unwrap().get(&model).map(|breaker| breaker.status())
+    }
+
+    /// An uninitialized pool for `model`, set up with the manager's metrics,
+    /// tokenizer, throttle, circuit breaker, and adapters
+    fn new_pool(&self, binary_path: PathBuf, model: &str) -> ProcessPool {
+        let mut pool = ProcessPool::new(binary_path, model.to_string(), self.pool_size)
+            .with_backend(self.backend(model))
+            .with_metrics(self.metrics.clone())
+            .with_tokenizer(self.tokenizers.for_model(model))
+            .with_crash_dir(self.binary_manager.cache_dir().join("crashes"))
+            .with_timeout(self.config.request_timeout())
+            .with_stall_timeout(self.config.stall_timeout())
+            .with_max_requests_per_process(self.config.max_requests_per_process())
+            .with_max_queue(self.config.concurrency.max_queue);
+        if let Some(breaker) = self.breaker(model) {
+            pool = pool.with_breaker(breaker);
+        }
+        if let Some(sandbox) = &self.sandbox {
+            pool = pool.with_sandbox(sandbox.clone());
+        }
+        let limits = ResourceLimits::for_model(&self.config.limits, model);
+        if !limits.is_empty() {
+            pool = pool.with_limits(Arc::new(Limits::new(limits, self.config.limits.cgroup.clone())));
+        }
+        if let Some(throttle) = &self.throttle {
+            pool = pool.with_throttle(throttle.clone());
+        }
+        if let Some(adapter) = self.adapter_store().get(model) {
+            tracing::info!(model = %model, adapter = %adapter.path.display(), "Loading LoRA adapter");
+            pool = pool.with_adapter(adapter.path);
+        }
+        pool
+    }
+
+    /// Start the pools of several models at once, e.g. before serving
+    ///
+    /// The binary is checked once and the pools initialize concurrently,
+    /// without holding up requests for models that are already loaded.
+    /// Progress is reported as one [`Phase::LoadingModel`] view covering
+    /// every model. Fails on the first pool that can't start.
+    pub async fn preload(&self, models: &[String]) -> Result<Vec<Arc<ProcessPool>>> {
+        let mut resolved: Vec<String> = Vec::new();
+        for model in models {
+            let model = self.resolve_variant(model);
+            self.tenant.check_use(&model)?;
+            if !resolved.contains(&model) {
+                resolved.push(model);
+            }
+        }
+        let binary_path = self.ensure_binary().await?;
+
+        let loaded: Vec<String> = self.process_pools.lock().await.keys().cloned().collect();
+        let pending: Vec<&String> = resolved.iter().filter(|model| !loaded.contains(model)).collect();
+        tracing::info!(models = ?pending, pool_size = self.pool_size, "Preloading process pools");
+
+        let total = pending.len();
+        let done = std::sync::atomic::AtomicUsize::new(0);
+        let report = |done: usize| {
+            let percent = if total == 0 { 100.0 } else { done as f32 * 100.0 / total as f32 };
+            progress::report(self.progress.as_ref(), Progress::new(Phase::LoadingModel, None).percent(percent));
+        };
+        report(0);
+        let started = futures::future::try_join_all(pending.into_iter().map(|model| {
+            let mut pool = self.new_pool(binary_path.clone(), model);
+            let (done, report) = (&done, &report);
+            async move {
+                self.start_pool(&mut pool)
+                    .await
+                    .with_context(|| format!("Failed to preload {}", model))?;
+                report(done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+                anyhow::Ok(pool)
+            }
+        }))
+        .await?;
+
+        let started_models: Vec<String> = started.iter().map(|pool| pool.model().to_string()).collect();
+        let preloaded = {
+            let mut pools = self.process_pools.lock().await;
+            for pool in started {
+                // A request may have started the same model meanwhile; keep that pool
+                if let std::collections::hash_map::Entry::Vacant(entry) = pools.entry(pool.model().to_string()) {
+                    self.emit(ManagerEvent::ModelLoaded { model: pool.model().to_string() });
+                    entry.insert(Arc::new(pool));
+                }
+            }
+            resolved.iter().filter_map(|model| pools.get(model).cloned()).collect()
+        };
+        for model in &started_models {
+            self.touch_model(model).await;
+        }
+        Ok(preloaded)
+    }
+
+    /// Process pool serving `model`, started on first use
+    pub async fn pool(&self, model: &str) -> Result<Arc<ProcessPool>> {
+        self.get_pool(model).await
+    }
+
+    /// Start the pool of `model` ahead of its first request; see [`Self::preload`]
+    pub async fn load(&self, model: &str) -> Result<Arc<ProcessPool>> {
+        let pools = self.preload(&[model.to_string()]).await?;
+        pools.into_iter().next().with_context(|| format!("{} was unloaded while loading", model))
+    }
+
+    /// The pools currently loaded, a model's default pool before those with
+    /// extra flags
+    pub async fn loaded_models(&self) -> Vec<PoolStatus> {
+        self.pool_statuses().await
+    }
+
+    /// State, latency, and queue of every loaded pool and its processes, in
+    /// the order of [`loaded_models`](Self::loaded_models)
+    pub async fn pool_stats(&self) -> Vec<PoolStats> {
+        let mut stats: Vec<PoolStats> = self.process_pools.lock().await.values().map(|pool| pool.stats()).collect();
+        stats.sort_by(|a, b| a.model.cmp(&b.model).then_with(|| a.args.cmp(&b.args)));
+        stats
+    }
+
+    /// Every loaded pool's processes with what lit last wrote to stderr,
+    /// limited to models the tenant may manage, since stderr can quote
+    /// anyone's prompts
+    pub async fn diagnostics(&self) -> Vec<PoolDiagnostics> {
+        let mut pools: Vec<PoolDiagnostics> = self
+            .process_pools
+            .lock()
+            .await
+            .values()
+            .filter(|pool| self.tenant.can_manage(pool.model()))
+            .map(|pool| PoolDiagnostics {
+                model: pool.model().to_string(),
+                args: pool.args().to_vec(),
+                processes: pool.diagnostics(),
+            })
+            .collect();
+        pools.sort_by(|a, b| a.model.cmp(&b.model).then_with(|| a.args.cmp(&b.args)));
+        pools
+    }
+
+    /// Drop the pools serving `model`, including those with extra flags;
+    /// requests already holding one finish first, then the processes stop.
+    /// Returns whether any were loaded.
+    pub async fn unload(&self, model: &str) -> bool {
+        let model = self.resolve_variant(model);
+        let mut pools = self.process_pools.lock().await;
+        let (unloaded, kept): (HashMap<_, _>, HashMap<_, _>) = pools.drain().partition(|(_, pool)| pool.model() == model);
+        *pools = kept;
+        drop(pools);
+        if unloaded.is_empty() {
+            return false;
+        }
+        for pool in unloaded.into_values() {
+            stop_when_idle(pool);
+        }
+        tracing::info!(model = %model, "Unloaded process pools");
+        self.emit(ManagerEvent::ModelUnloaded { model });
+        true
+    }
+
+    /// Unload the pools that have gone without requests for longer than
+    /// their model's keep-alive (see [`KeepAlive`]); the server does this
+    /// every second. Returns the unloaded models.
+    pub async fn unload_idle(&self) -> Vec<String> {
+        let mut pools = self.process_pools.lock().await;
+        let (idle, kept): (HashMap<_, _>, HashMap<_, _>) = pools.drain().partition(|(_, pool)| {
+            let keep_alive = pool.keep_alive().unwrap_or_else(|| KeepAlive::for_model(&self.config.keep_alive, pool.model()));
+            pool.idle_for().is_some_and(|idle| keep_alive.expired(idle))
+        });
+        *pools = kept;
+        let still_loaded: Vec<String> = pools.values().map(|pool| pool.model().to_string()).collect();
+        drop(pools);
+
+        let mut models = Vec::new();
+        for pool in idle.into_values() {
+            tracing::info!(model = %pool.model(), "Unloading idle process pool");
+            let model = pool.model().to_string();
+            stop_when_idle(pool);
+            if !still_loaded.contains(&model) && !models.contains(&model) {
+                models.push(model);
+            }
+        }
+        for model in &models {
+            self.emit(ManagerEvent::ModelUnloaded { model: model.clone() });
+        }
+        models
+    }
+
+    /// Stop the processes of every pool, waiting for generations in progress
+    ///
+    /// Unlike [`unload`](Self::unload), this doesn't rely on the pools being
+    /// dropped, so lit is stopped even while a server or a stream still holds
+    /// on to a pool.
+    pub async fn shutdown(&self) {
+        let pools: Vec<_> = self.process_pools.lock().await.drain().map(|(_, pool)| pool).collect();
+        tracing::info!(pools = pools.len(), "Stopping lit processes");
+        let stopped = futures::future::join_all(pools.iter().map(|pool| pool.shutdown())).await;
+        for (pool, result) in pools.iter().zip(stopped) {
+            match result {
+                Ok(()) => self.emit(ManagerEvent::ModelUnloaded { model: pool.model().to_string() }),
+                Err(e) => tracing::warn!(model = %pool.model(), error = %e, "Failed to stop process pool"),
+            }
+        }
+    }
+
+    /// Process pool serving `model` with extra lit flags, started on first use
+    pub async fn pool_with_args(&self, model: &str, args: &LitArgs) -> Result<Arc<ProcessPool>> {
+        self.get_pool_with(model, args, Sampling::default()).await
+    }
+
+    /// Process pool serving `model` with the lit flags and sampling settings
+    /// of `options`, started on first use
+    pub async fn pool_with_options(&self, model: &str, options: &GenOptions) -> Result<Arc<ProcessPool>> {
+        self.get_pool_with(model, &options.args, options.sampling()).await
+    }
+
+    /// Context window of `model` per the config file
+    pub fn context_window(&self, model: &str) -> ContextWindow {
+        ContextWindow::for_model(&self.config.context, model)
+    }
+
+    /// Chat template of `model` per the config file
+    pub fn chat_template(&self, model: &str) -> ChatTemplate {
+        ChatTemplate::for_model(&self.config.templates, model)
+    }
+
+    /// Render a conversation as a prompt in the pool's model's template that
+    /// fits its context, dropping or summarizing (with the same pool) the
+    /// oldest turns if it doesn't
+    pub async fn fit_context(&self, pool: &Arc<ProcessPool>, turns: Vec<Turn>) -> Result<Fitted> {
+        let model = pool.model();
+        let window = self.context_window(model);
+        let template = self.chat_template(model);
+        let tokenizer = self.tokenizers.for_model(model);
+        let result = context::fit_with(turns, &window, template, &*tokenizer, |prompt| async move {
+            let _slot = pool.admit(&self.tenant).await?;
+            pool.send_prompt(&prompt).await
+        })
+        .await;
+
+        let action = match &result {
+            Ok(fitted) if fitted.summarized => "summarized",
+            Ok(fitted) if fitted.dropped > 0 => "truncated",
+            Ok(_) => return result,
+            Err(_) => "rejected",
+        };
+        tracing::info!(model = %model, action, "Conversation exceeded the context window");
+        self.metrics.context_overflows.with_label_values(&[model, action]).inc();
+        result
+    }
+
+    /// Snapshot of the currently loaded pools and their queue depth
+    pub async fn stats(&self) -> ManagerStats {
+        let pools = self.pool_statuses().await;
+
+        ManagerStats {
+            pool_size: self.pool_size,
+            pools,
+            metrics: self.metrics.snapshot(),
+        }
+    }
+
+    /// Dump the runtime and pool workers, for diagnosing hangs
+    ///
+    /// Never waits on the pool map lock, so it still answers while a pool is
+    /// stuck initializing.
+    pub fn debug_tasks(&self) -> TaskDump {
+        let handle = tokio::runtime::Handle::current();
+        let runtime_metrics = handle.metrics();
+        let runtime = RuntimeInfo {
+            workers: runtime_metrics.num_workers(),
+            alive_tasks: runtime_metrics.num_alive_tasks(),
+            global_queue_depth: runtime_metrics.global_queue_depth(),
+        };
+
+        let (pools_available, mut pools) = match self.process_pools.try_lock() {
+            Ok(pools) => (
+                true,
+                pools
+                    .values()
+                    .map(|pool| PoolDebugInfo {
+                        model: pool.model().to_string(),
+                        processes: pool.debug_info(),
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Err(_) => (false, Vec::new()),
+        };
+        pools.sort_by(|a, b| a.model.cmp(&b.model));
+
+        let stuck = pools
+            .iter()
+            .flat_map(|pool| &pool.processes)
+            .filter(|process| process.stuck)
+            .count();
+
+        TaskDump {
+            runtime,
+            pools_available,
+            pools,
+            stuck,
+        }
+    }
+
+    /// Render all metrics in the Prometheus text format
+    pub async fn render_metrics(&self) -> String {
+        // Pool gauges are sampled at scrape time rather than tracked continuously
+        self.pool_statuses().await;
+        self.metrics.render()
+    }
+
+    async fn pool_statuses(&self) -> Vec<PoolStatus> {
+        let pools = self.process_pools.lock().await;
+        let mut statuses: Vec<PoolStatus> = pools
+            .values()
+            .map(|pool| PoolStatus {
+                model: pool.model().to_string(),
+                processes: pool.size(),
+                queue_depth: pool.queue_depth(),
+                gpu_fallbacks: pool.gpu_fallbacks(),
+                backends: pool.backends(),
+                args: pool.args().to_vec(),
+                idle_secs: pool.idle_for().map(|idle| idle.as_secs()),
+            })
+            .collect();
+        // A model's default pool comes before those started with extra flags
+        statuses.sort_by(|a, b| a.model.cmp(&b.model).then_with(|| a.args.cmp(&b.args)));
+
+        // Gauges are per model, so pools with extra flags add to their model's
+        let mut totals: HashMap<&str, (usize, usize)> = HashMap::new();
+        let mut backends: HashMap<(&str, &str), usize> = HashMap::new();
+        for status in &statuses {
+            let total = totals.entry(status.model.as_str()).or_default();
+            total.0 += status.queue_depth;
+            total.1 += status.processes;
+            for backend in &status.backends {
+                *backends.entry((status.model.as_str(), backend.as_str())).or_default() += 1;
+            }
+        }
+        for (model, (queue_depth, processes)) in totals {
+            self.metrics.pool_queue_depth.with_label_values(&[model]).set(queue_depth as i64);
+            self.metrics.pool_processes.with_label_values(&[model]).set(processes as i64);
+            // Reset backends a model no longer runs on, e.g. after a fallback
+            for backend in [Backend::Gpu, Backend::Cpu, Backend::Npu] {
+                let count = backends.get(&(model, backend.as_str())).copied().unwrap_or_default();
+                self.metrics.pool_backend_processes.with_label_values(&[model, backend.as_str()]).set(count as i64);
+            }
+        }
+
+        statuses
+    }
+
+    /// [`run_completion_with`](Self::run_completion_with) with the default options
+    pub async fn run_completion(&self, model: &str, prompt: &str) -> Result<String> {
+        self.run_completion_with(model, prompt, &GenOptions::default()).await
+    }
+
+    /// Run a completion with generation options such as `max_tokens`,
+    /// sampling settings, `stop` sequences, a `timeout`, or an output
+    /// constraint; with `best_of`, the best candidate is returned
+    ///
+    /// If `model` names a profile, its model serves the completion and its
+    /// settings fill in the options left unset.
+    #[tracing::instrument(name = "completion", skip_all, fields(model = %model))]
+    pub async fn run_completion_with(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: &GenOptions,
+    ) -> Result<String> {
+        let model = self.model_or_default(model);
+        let mut options = options.clone();
+        let mut system_prompt = None;
+        let model = match self.profiles.get(model) {
+            Some(profile) => {
+                profile.apply(&mut options);
+                system_prompt = profile.system_prompt();
+                profile.model()
+            }
+            None => model,
+        };
+        let prompt = match options.raw {
+            true => prompt.to_string(),
+            false => self.chat_template(model).render_prompt(system_prompt, prompt),
+        };
+        let (prompt, options) = (prompt.as_str(), &options);
+
+        tracing::debug!(model = %model, prompt_length = prompt.len(), "Running completion");
+        let window = self.context_window(model);
+        if self.tokenizers.count(model, prompt) > window.budget() {
+            tracing::warn!(
+                model = %model,
+                budget = window.budget(),
+                "Prompt likely exceeds the model's context window; use fit_context for conversations"
+            );
+        }
+
+        let started = std::time::Instant::now();
+        let record = HistoryRecord::new(uuid::Uuid::new_v4().to_string(), model, "library", prompt);
+        let request = self.track_request(&record.id, model, None);
+
+        // A cache hit doesn't need the model's pool started
+        let cached = self
+            .cached_responses(model, prompt, options)
+            .filter(|_| self.tenant.can_use(model));
+
+        let result = match cached {
+            Some(mut cached) => Ok(cached.swap_remove(0)),
+            None => {
+                let generate = process::with_timeout(options.timeout, self.generate_uncached(model, prompt, options));
+                cancel::run(Some(request.token()), generate).await
+            }
+        };
+        self.record_history(record.finish(
+            started,
+            result.as_deref().map_err(|e| e.to_string()),
+        ));
+
+        let response = result?;
+        tracing::debug!(model = %model, response_length = response.len(), "Completion finished");
+        Ok(response)
+    }
+
+    /// Generate the best candidate on the model's pool (or the in-process
+    /// engine) and cache the candidates
+    async fn generate_uncached(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<String> {
+        let pool = match self.runs_in_process() {
+            true => None,
+            false => Some(self.get_pool_with(model, &options.args, options.sampling()).await?),
+        };
+        let tokenizer = self.tokenizers.for_model(model);
+        let template = match options.raw {
+            true => ChatTemplate::Plain,
+            false => self.chat_template(model),
+        };
+        let candidates = generation::generate_best(prompt, template, options, |prompt| {
+            let pool = pool.clone();
+            let tenant = self.tenant.clone();
+            let tokenizer = tokenizer.clone();
+            async move {
+                let Some(pool) = pool else {
+                    let started = std::time::Instant::now();
+                    let stream = self.in_process_stream(model, &prompt).await?;
+                    let (text, _) = process::collect_reply(started, stream, options.max_tokens, tokenizer).await?;
+                    return Ok((text, ()));
+                };
+                let _slot = pool.admit(&tenant).await?;
+                let (text, _) = pool.send_prompt_limited(&prompt, options.max_tokens, tokenizer).await?;
+                Ok((text, ()))
+            }
+        })
+        .await?;
+
+        let mut texts: Vec<String> = candidates.into_iter().map(|(text, _)| text).collect();
+        self.cache_responses(model, prompt, options, texts.clone());
+        Ok(texts.swap_remove(0))
+    }
+
+    /// Stream a completion as numbered, timed chunks, ending with a chunk that
+    /// carries the finish reason
+    pub async fn run_completion_stream(&self, model: &str, prompt: &str) -> Result<CompletionStream> {
+        self.stream_completion(model, prompt, &GenOptions::default(), CancellationToken::new()).await
+    }
+
+    /// [`run_completion_stream`](Self::run_completion_stream) with generation
+    /// options, as for [`run_completion_with`](Self::run_completion_with); the
+    /// stream ends at a `stop` sequence or `max_tokens`, and with
+    /// [`LitError::Timeout`] once the `timeout` has passed. Options that take
+    /// the whole reply (`n`, `best_of`, and a constraint) are ignored.
+    pub async fn run_completion_stream_with(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: &GenOptions,
+    ) -> Result<CompletionStream> {
+        self.stream_completion(model, prompt, options, CancellationToken::new()).await
+    }
 
-        // Get the correct pool for the requested model
-        let pool = self.get_pool(model).await?;
+    /// [`run_completion_stream_with`](Self::run_completion_stream_with) as
+    /// [`CompletionEvent`]s, which tell the generated tokens from how the
+    /// completion ended (its finish reason, token usage, and duration, or its
+    /// error)
+    pub async fn run_completion_events(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: &GenOptions,
+    ) -> Result<BoxStream<'static, CompletionEvent>> {
+        let stream = self.stream_completion(model, prompt, options, CancellationToken::new()).await?;
+        Ok(stream.into_events())
+    }
 
-        // Use the pool
-        let response = pool.send_prompt(prompt).await?;
-        tracing::debug!(model = %model, response_length = response.len(), "Completion finished");
-        Ok(response)
+    /// [`run_completion_stream`](Self::run_completion_stream) that stops once
+    /// `cancel` is cancelled: lit is interrupted mid-reply, the stream ends
+    /// with [`Cancelled`], and the process is free for the next request as
+    /// soon as lit is back at its prompt
+    pub async fn run_completion_stream_cancellable(
+        &self,
+        model: &str,
+        prompt: &str,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream> {
+        self.stream_completion(model, prompt, &GenOptions::default(), cancel).await
     }
 
-    // New streaming method
-    pub async fn run_completion_stream(
+    #[tracing::instrument(name = "completion", skip_all, fields(model = %model))]
+    async fn stream_completion(
         &self,
         model: &str,
         prompt: &str,
-    ) -> Result<impl Stream<Item = Result<String>>> {
-        let pool = self.get_pool(model).await?;
-        let process = pool.get_process().await?;
-        let stream = process.send_prompt_stream(prompt).await?;
-        Ok(stream)
+        options: &GenOptions,
+        cancel: CancellationToken,
+    ) -> Result<CompletionStream> {
+        let model = self.model_or_default(model);
+        let mut options = options.clone();
+        let mut system_prompt = None;
+        let model = match self.profiles.get(model) {
+            Some(profile) => {
+                profile.apply(&mut options);
+                system_prompt = profile.system_prompt();
+                profile.model()
+            }
+            None => model,
+        };
+        options.check().map_err(anyhow::Error::msg)?;
+        let started = std::time::Instant::now();
+        let deadline = tokio::time::Instant::now();
+        let request = self.track_request(&uuid::Uuid::new_v4().to_string(), model, None);
+        // Killed through `/admin/requests` or cancelled by the caller
+        let (token, link) = cancel::either(request.token(), &cancel);
+        let prompt = match options.raw {
+            true => prompt.to_string(),
+            false => self.chat_template(model).render_prompt(system_prompt, prompt),
+        };
+        let start = async {
+            if self.runs_in_process() {
+                let stream = self.in_process_stream(model, &prompt).await?;
+                // Keep the listing until the stream is finished or dropped
+                let stream: TextStream = Box::pin(stream.map(move |item| {
+                    let _ = (&request, &link);
+                    item
+                }));
+                return Ok(stream);
+            }
+            let pool = self.get_pool_with(model, &options.args, options.sampling()).await?;
+            let slot = pool.admit(&self.tenant).await?;
+            let process = pool.get_process().await?;
+            let stream = process.send_prompt_stream_cancellable(&prompt, token.clone()).await?;
+            // Keep the pool slot and the listing until the stream is finished or dropped
+            let stream: TextStream = Box::pin(stream.map(move |item| {
+                let _ = (&slot, &request, &link);
+                item
+            }));
+            Ok(stream)
+        };
+        let stream = cancel::run(Some(&token), process::with_timeout(options.timeout, start)).await?;
+        let tokenizer = self.tokenizers.for_model(model);
+        let prompt_tokens = tokenizer.count(&prompt);
+        let stream = stop_at(stream, options.stop.clone());
+        let stream = limit_tokens(stream, options.max_tokens, tokenizer.clone());
+        let stream = process::stream_with_timeout(options.timeout, deadline, token, stream);
+        Ok(CompletionStream::new(model, stream)
+            .started_at(started)
+            .counted_by(tokenizer)
+            .max_tokens(options.max_tokens)
+            .prompt_tokens(prompt_tokens))
+    }
+
+    /// Classify `input` with `model` (see [`moderation`](crate::moderation))
+    pub async fn moderate(&self, model: &str, input: &str) -> Result<ModerationResult> {
+        let prompt = moderation::classification_prompt(input);
+        let output = self
+            .run_completion_with(model, &prompt, &moderation::classification_options())
+            .await?;
+        moderation::parse_scores(&output)
     }
 
     fn run_lit_command(&self, binary_path: &PathBuf, args: &[&str]) -> Result<String> {
@@ -145,33 +1608,387 @@ impl LitManager {
 
     pub async fn list(&self, show_all: bool) -> Result<()> {
         let binary_path = self.ensure_binary().await?;
-        let args = if show_all {
-            vec!["list", "--show_all"]
-        } else {
-            vec!["list"]
+        let output = match show_all {
+            true => self.registry_listing(&binary_path).await?,
+            false => self.run_lit_command(&binary_path, &["list"])?,
         };
-        let output = self.run_lit_command(&binary_path, &args)?;
         println!("{}", output);
+
+        let adapters = self.adapters();
+        if !adapters.is_empty() {
+            println!("Adapters:");
+            for adapter in adapters {
+                println!("{}", adapter);
+            }
+        }
         Ok(())
     }
 
     /// List models and return the output as a String (library API)
     pub async fn list_models(&self, show_all: bool) -> Result<String> {
         let binary_path = self.ensure_binary().await?;
-        let args = if show_all {
-            vec!["list", "--show_all"]
+        let output = match show_all {
+            true => self.registry_listing(&binary_path).await?,
+            false => self.run_lit_command(&binary_path, &["list"])?,
+        };
+        Ok(self.visible_listing(&output))
+    }
+
+    /// `lit list --show_all`, followed by the models of the configured
+    /// registry manifests it doesn't list
+    async fn registry_listing(&self, binary_path: &PathBuf) -> Result<String> {
+        let mut listing = self.run_lit_command(binary_path, &["list", "--show_all"])?;
+        for manifest in self.registry_manifests().await {
+            let rows = manifest.listing_rows(&listing);
+            listing.push_str(&rows);
+        }
+        Ok(listing)
+    }
+
+    /// The configured registry manifests that could be read; the others are
+    /// logged and left out
+    async fn registry_manifests(&self) -> Vec<RegistryManifest> {
+        let mut manifests = Vec::new();
+        for location in &self.config.registries {
+            match RegistryManifest::load(location).await {
+                Ok(manifest) => manifests.push(manifest),
+                Err(e) => tracing::warn!(registry = %location, error = %format!("{:#}", e), "Skipping registry manifest"),
+            }
+        }
+        manifests
+    }
+
+    /// The first configured registry manifest's entry for `name`
+    async fn manifest_model(&self, name: &str) -> Option<ManifestModel> {
+        if self.config.registries.is_empty() {
+            return None;
+        }
+        self.registry_manifests()
+            .await
+            .into_iter()
+            .find_map(|manifest| manifest.get(name).cloned())
+    }
+
+    /// Downloaded models, and with `show_all` the rest of the registry after
+    /// them; only models the tenant may use are listed
+    pub async fn list_models_structured(&self, show_all: bool) -> Result<Vec<ModelInfo>> {
+        let binary_path = self.ensure_binary().await?;
+        let downloaded = registry::parse_listing(&self.run_lit_command(&binary_path, &["list"])?);
+        let mut models: Vec<ModelInfo> = downloaded.iter().map(|entry| ModelInfo::from_entry(entry, true)).collect();
+        if show_all {
+            let available = registry::parse_listing(&self.registry_listing(&binary_path).await?);
+            for entry in &available {
+                if !models.iter().any(|model| model.alias == entry.name) {
+                    models.push(ModelInfo::from_entry(entry, false));
+                }
+            }
+        }
+        models.retain(|model| self.tenant.can_use(&model.alias));
+        Ok(models)
+    }
+
+    /// Hide models outside the tenant's namespace, keeping the headers
+    fn visible_listing(&self, output: &str) -> String {
+        output
+            .lines()
+            .filter(|line| match model_line_name(line) {
+                Some(model) => self.tenant.can_use(model),
+                None => true,
+            })
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Registry models matching `query`, best first
+    ///
+    /// Every word of the query has to match a model's name or details,
+    /// ignoring case and separators; see [`registry::search`]. When lit can't
+    /// list the registry (e.g. offline), the listing saved by the last
+    /// [`refresh_registry`](Self::refresh_registry) is searched instead.
+    pub async fn search_registry(&self, query: &str) -> Result<Vec<RegistryMatch>> {
+        let listing = match self.list_models(true).await {
+            Ok(listing) => listing,
+            Err(e) => match std::fs::read_to_string(self.registry_cache_path()) {
+                Ok(saved) => {
+                    tracing::warn!(error = %format!("{:#}", e), "Registry unavailable; searching the saved listing");
+                    self.visible_listing(&saved)
+                }
+                Err(_) => return Err(e),
+            },
+        };
+        Ok(registry::search(&registry::parse_listing(&listing), query))
+    }
+
+    /// List the registry and save the listing for searches while it can't be
+    /// reached; returns the number of models listed
+    pub async fn refresh_registry(&self) -> Result<usize> {
+        let binary_path = self.ensure_binary().await?;
+        let listing = self.registry_listing(&binary_path).await?;
+        let path = self.registry_cache_path();
+        // Written aside and renamed, so searches never see a partial listing
+        let tmp = path.with_extension(format!("txt.{}", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&tmp, &listing).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(registry::parse_listing(&listing).len())
+    }
+
+    fn registry_cache_path(&self) -> PathBuf {
+        self.binary_manager.cache_dir().join(REGISTRY_CACHE_FILE)
+    }
+
+    /// Tag of the latest lit release, if this build doesn't use it; see
+    /// [`BinaryManager::check_for_update`]
+    pub async fn check_for_update(&self) -> Result<Option<String>> {
+        self.binary_manager.check_for_update().await
+    }
+
+    /// Search the Hugging Face Hub for repositories with a model lit can run
+    pub async fn search_hub(&self, query: &HubQuery, hf_token: Option<&str>) -> Result<Vec<HubModel>> {
+        HubClient::new(hf_token).search(query).await
+    }
+
+    /// Pull a model from a Hugging Face Hub repository by its id, saved as
+    /// `alias` (or the repository name), and record where it came from
+    ///
+    /// `file` picks one of several `.litertlm` files in the repository. The
+    /// file is downloaded at the repository's current commit, so the recorded
+    /// provenance names exactly what was pulled.
+    pub async fn pull_from_hub(
+        &self,
+        repo: &str,
+        file: Option<&str>,
+        alias: Option<&str>,
+        hf_token: Option<&str>,
+    ) -> Result<ModelProvenance> {
+        let hub = HubClient::new(hf_token);
+        let found = hub.model(repo).await?;
+        let file = hub::pick_file(&found, file)?;
+        let revision = found.sha.clone().unwrap_or_else(|| "main".to_string());
+        let alias = alias.map(str::to_string).unwrap_or_else(|| found.default_alias());
+        self.tenant.check_manage(&alias)?;
+
+        let url = hub.file_url(&found.id, &revision, file);
+        tracing::info!(repo = %found.id, file = %file, revision = %revision, alias = %alias, "Pulling model from the Hugging Face Hub");
+        self.pull(&url, Some(&alias), hub.token()).await?;
+        // Checked against what the pull recorded, if it found the file
+        if let Some(listed) = found.sha256.get(file) {
+            match self.model_checksum(&alias) {
+                Some(record) if !record.sha256.eq_ignore_ascii_case(listed) => {
+                    let _ = self.remove_quiet(&alias).await;
+                    return Err(LitError::ChecksumMismatch {
+                        model: alias,
+                        expected: listed.clone(),
+                        actual: record.sha256,
+                    }
+                    .into());
+                }
+                Some(_) => tracing::debug!(model = %alias, "Checksum matches the Hub's"),
+                None => tracing::warn!(model = %alias, "Model file not found; not checked against the Hub's checksum"),
+            }
+        }
+
+        let provenance = ModelProvenance::hub(&alias, &found.id, &revision, file, &url);
+        if let Err(e) = self.provenance_store().record(provenance.clone()).await {
+            tracing::warn!(model = %alias, error = %e, "Failed to record model provenance");
+        }
+        Ok(provenance)
+    }
+
+    /// Where `model` came from, if it was pulled from the Hugging Face Hub
+    pub fn model_provenance(&self, model: &str) -> Option<ModelProvenance> {
+        self.provenance_store().get(model)
+    }
+
+    fn provenance_store(&self) -> ProvenanceStore {
+        ProvenanceStore::new(self.binary_manager.cache_dir().join("model_provenance.json"))
+    }
+
+    fn adapter_store(&self) -> AdapterStore {
+        AdapterStore::new(self.binary_manager.cache_dir().join("adapters"))
+    }
+
+    /// LoRA adapters visible to the tenant, usable as `<base>:<name>`
+    pub fn adapters(&self) -> Vec<Adapter> {
+        self.adapter_store()
+            .list()
+            .into_iter()
+            .filter(|adapter| self.tenant.can_use(&adapter.model()))
+            .collect()
+    }
+
+    /// Store the LoRA adapter file at `path` as the model `<base>:<name>`
+    pub async fn add_adapter(&self, base: &str, name: &str, path: &std::path::Path) -> Result<Adapter> {
+        let model = format!("{}{}{}", base, crate::adapter::SEPARATOR, name);
+        self.tenant.check_manage(&model)?;
+        let _lock = self.lock_model(&model).await?;
+        self.adapter_store().add(base, name, path)
+    }
+
+    /// Serialize pulls and removals of `model` across every process sharing the cache
+    async fn lock_model(&self, model: &str) -> Result<CacheLock> {
+        CacheLock::acquire(
+            self.binary_manager.cache_dir(),
+            &format!("model-{}", model),
+            MODEL_LOCK_TIMEOUT,
+        )
+        .await
+    }
+
+    /// A handle to the same pools and stores that pulls references with
+    /// `source`'s URL schemes through it, e.g. for another object store
+    pub fn with_model_source(&self, source: Arc<dyn ModelSource>) -> Self {
+        Self {
+            sources: Arc::new(self.sources.with(source)),
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores that leaves downloading models
+    /// from URLs to lit, starting over when a pull fails, if `resume` is
+    /// false; see [`download`]
+    pub fn with_resumable_downloads(&self, resume: bool) -> Self {
+        Self {
+            resume,
+            ..self.clone()
+        }
+    }
+
+    /// A handle to the same pools and stores that sends `headers` (e.g. an
+    /// API key for an internal model server) with the downloads of URL pulls,
+    /// which are then always downloaded here; see [`download`]
+    pub fn with_download_headers(&self, headers: HeaderMap) -> Self {
+        Self {
+            download_headers: Arc::new(headers),
+            ..self.clone()
+        }
+    }
+
+    /// Download what `pull` fetches from a URL into the cache, resuming an
+    /// earlier try, and serve it to lit; `None` when lit pulls it itself
+    async fn fetch_for_lit(&self, pull: &PreparedPull, hf_token: Option<&str>) -> Result<Option<(FileServer, PathBuf)>> {
+        let headers = self.download_headers.as_ref();
+        if (!self.resume && headers.is_empty()) || pull._server.is_some() || !download::is_resumable(&pull.target) {
+            return Ok(None);
+        }
+        let dest = download::path_for(&self.cache_dir().join(download::DOWNLOADS_DIR), &pull.target);
+        if !self.resume {
+            // Downloaded here only for the headers; every try starts over
+            let _ = tokio::fs::remove_file(download::partial_path(&dest)).await;
+        }
+        let model = pull.model.as_str();
+        let report = |done, total| {
+            progress::report(self.progress.as_ref(), Progress::new(Phase::PullingModel, Some(model)).bytes(done, total))
+        };
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("litert-lm/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let size = cancel::run(self.cancel.as_ref(), download::fetch(&client, &pull.target, &dest, hf_token, headers, report))
+            .await
+            .map_err(|e| match e.is::<Cancelled>() || e.is::<Permanent>() {
+                true => e,
+                false => LitError::DownloadFailed { model: model.to_string(), reason: format!("{:#}", e) }.into(),
+            })?;
+        tracing::debug!(model = %model, size_bytes = size, path = %dest.display(), "Downloaded model for lit");
+        Ok(Some((FileServer::start(dest.clone()).await?, dest)))
+    }
+
+    /// Remove a download lit has pulled
+    fn discard_download(local: Option<(FileServer, PathBuf)>) {
+        if let Some(dir) = local.and_then(|(_, file)| file.parent().map(PathBuf::from)) {
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                tracing::debug!(path = %dir.display(), error = %e, "Failed to remove finished download");
+            }
+        }
+    }
+
+    /// Resolve `reference` through its model source
+    async fn prepare_pull(&self, reference: &str, alias: Option<&str>) -> Result<PreparedPull> {
+        let mut source = self.sources.select(reference)?;
+        let mut listed = None;
+        let model = if source.schemes().is_empty() {
+            let model = self.resolve_variant(reference);
+            // Names in a registry manifest are pulled from the URL it lists
+            listed = self.manifest_model(&model).await;
+            if let Some(listed) = &listed {
+                source = self.sources.select(&listed.url)?;
+            }
+            model
         } else {
-            vec!["list"]
+            reference.to_string()
+        };
+        let url = listed.as_ref().map_or(model.as_str(), |listed| listed.url.as_str());
+        let (target, server) = match source.resolve(url).await? {
+            PullTarget::Lit(target) => (target, None),
+            PullTarget::File(path) => {
+                let server = FileServer::start(path).await?;
+                (server.url().to_string(), Some(server))
+            }
+        };
+        // lit names what it pulls itself after the URL, which is no longer
+        // the reference's once resolved
+        let alias = match alias {
+            Some(alias) => Some(alias.to_string()),
+            None if listed.is_some() => Some(model.clone()),
+            None if target != model => source::default_alias(reference),
+            None => None,
+        };
+        tracing::debug!(model = %model, source = source.name(), alias = ?alias, "Resolved model source");
+        Ok(PreparedPull {
+            model,
+            target,
+            alias,
+            sha256: listed.and_then(|listed| listed.sha256),
+            _server: server,
+        })
+    }
+
+    /// [`pull`](Self::pull) a model whose file must have the SHA-256 digest
+    /// `sha256` (hex); one that doesn't is removed again
+    pub async fn pull_with_sha256(
+        &self,
+        model: &str,
+        alias: Option<&str>,
+        hf_token: Option<&str>,
+        sha256: &str,
+    ) -> Result<()> {
+        let checked = Self {
+            expected_sha256: Some(checksum::parse_sha256(sha256)?),
+            ..self.clone()
         };
-        self.run_lit_command(&binary_path, &args)
+        checked.pull(model, alias, hf_token).await
     }
 
     pub async fn pull(&self, model: &str, alias: Option<&str>, hf_token: Option<&str>) -> Result<()> {
+        if self.progress.is_some() {
+            // The reporter takes the place of lit's own progress bar
+            return self.pull_with_progress(model, alias, hf_token, |_| {}).await.map(|_| ());
+        }
+        let pull = self.prepare_pull(model, alias).await?;
+        let (model, alias) = (pull.model.as_str(), pull.alias.as_deref());
+        self.tenant.check_manage(alias.unwrap_or(model))?;
         let binary_path = self.ensure_binary().await?;
+        let _lock = self.lock_model(alias.unwrap_or(model)).await?;
+        self.make_room_for(&binary_path, model, alias).await?;
         tracing::info!("Pulling model: {}", model);
 
-        let mut cmd = Command::new(&binary_path);
-        cmd.arg("pull").arg(model);
+        let mut attempt = 1;
+        while let Err(e) = self.pull_once(&binary_path, &pull, hf_token).await {
+            self.retry
+                .wait_to_retry("model_pull", ErrorClass::Network, attempt, e, self.cancel.as_ref(), &self.metrics)
+                .await?;
+            attempt += 1;
+        }
+        self.check_pulled(&binary_path, alias.unwrap_or(model), pull.sha256.as_deref()).await?;
+        self.touch_model(alias.unwrap_or(model)).await;
+        Ok(())
+    }
+
+    /// One try of [`pull`](Self::pull), with lit's own progress bar
+    async fn pull_once(&self, binary_path: &PathBuf, pull: &PreparedPull, hf_token: Option<&str>) -> Result<()> {
+        let (model, alias) = (pull.model.as_str(), pull.alias.as_deref());
+        let local = self.fetch_for_lit(pull, hf_token).await?;
+        let mut cmd = Command::new(binary_path);
+        cmd.arg("pull").arg(local.as_ref().map_or(pull.target.as_str(), |(server, _)| server.url()));
 
         if let Some(alias_val) = alias {
             cmd.arg("--alias").arg(alias_val);
@@ -181,16 +1998,28 @@ impl LitManager {
             cmd.arg("--hf_token").arg(token);
         }
 
-        let output = cmd
+        let existed = self.cancel.is_some() && self.is_downloaded(binary_path, alias.unwrap_or(model));
+        let mut child = tokio::process::Command::from(cmd)
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
-            .status()
+            .kill_on_drop(true)
+            .spawn()
             .context("Failed to pull model")?;
+        let output = match cancel::run(self.cancel.as_ref(), async { Ok(child.wait().await?) }).await {
+            Err(e) if e.is::<Cancelled>() => {
+                self.abandon_pull(&mut child, binary_path, model, alias, existed).await;
+                return Err(e);
+            }
+            result => result.context("Failed to pull model")?,
+        };
 
         if !output.success() {
-            anyhow::bail!("Failed to pull model");
+            self.metrics.model_pulls.with_label_values(&[model, "error"]).inc();
+            return Err(LitError::DownloadFailed { model: model.to_string(), reason: output.to_string() }.into());
         }
 
+        self.metrics.model_pulls.with_label_values(&[model, "success"]).inc();
+        Self::discard_download(local);
         Ok(())
     }
 
@@ -206,7 +2035,12 @@ impl LitManager {
     where
         F: FnMut(f32) + Send + 'static,
     {
+        let pull = self.prepare_pull(model, alias).await?;
+        let (model, alias) = (pull.model.as_str(), pull.alias.as_deref());
+        self.tenant.check_manage(alias.unwrap_or(model))?;
         let binary_path = self.ensure_binary().await?;
+        let _lock = self.lock_model(alias.unwrap_or(model)).await?;
+        self.make_room_for(&binary_path, model, alias).await?;
         tracing::info!(
             model = %model,
             alias = ?alias,
@@ -214,8 +2048,44 @@ impl LitManager {
             "Pulling model with progress tracking"
         );
 
-        let mut cmd = Command::new(&binary_path);
-        cmd.arg("pull").arg(model);
+        let mut attempt = 1;
+        let reported_done = loop {
+            match self.pull_once_with_progress(&binary_path, &pull, hf_token, &mut progress_callback).await {
+                Ok(reported_done) => break reported_done,
+                Err(e) => {
+                    self.retry
+                        .wait_to_retry("model_pull", ErrorClass::Network, attempt, e, self.cancel.as_ref(), &self.metrics)
+                        .await?;
+                    attempt += 1;
+                }
+            }
+        };
+
+        if !reported_done {
+            progress::report(self.progress.as_ref(), Progress::new(Phase::PullingModel, Some(model)).percent(100.0));
+        }
+        self.check_pulled(&binary_path, alias.unwrap_or(model), pull.sha256.as_deref()).await?;
+        self.touch_model(alias.unwrap_or(model)).await;
+        tracing::info!(model = %model, "Model pull completed successfully");
+        Ok("Download completed".to_string())
+    }
+
+    /// One try of [`pull_with_progress`](Self::pull_with_progress); returns
+    /// whether lit reported the download as finished
+    async fn pull_once_with_progress<F>(
+        &self,
+        binary_path: &PathBuf,
+        pull: &PreparedPull,
+        hf_token: Option<&str>,
+        progress_callback: &mut F,
+    ) -> Result<bool>
+    where
+        F: FnMut(f32) + Send,
+    {
+        let (model, alias) = (pull.model.as_str(), pull.alias.as_deref());
+        let local = self.fetch_for_lit(pull, hf_token).await?;
+        let mut cmd = Command::new(binary_path);
+        cmd.arg("pull").arg(local.as_ref().map_or(pull.target.as_str(), |(server, _)| server.url()));
 
         if let Some(alias_val) = alias {
             cmd.arg("--alias").arg(alias_val);
@@ -228,13 +2098,16 @@ impl LitManager {
         use tokio::io::BufReader;
         use tokio::process::Command as TokioCommand;
 
+        let existed = self.cancel.is_some() && self.is_downloaded(binary_path, alias.unwrap_or(model));
         let mut child = TokioCommand::from(cmd)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()
             .context("Failed to spawn pull command")?;
 
         let stdout = child.stdout.take().context("Failed to capture stdout")?;
+        let stderr = child.stderr.take();
 
         // Read stdout byte by byte to handle carriage returns
         use tokio::io::AsyncReadExt;
@@ -242,37 +2115,55 @@ impl LitManager {
         let mut buffer = vec![0u8; 4096];
         let mut current_line = String::new();
 
-        loop {
-            match stdout_reader.read(&mut buffer).await {
-                Ok(0) => break, // EOF
-                Ok(n) => {
-                    let chunk = String::from_utf8_lossy(&buffer[..n]);
-                    for ch in chunk.chars() {
-                        if ch == '\r' || ch == '\n' {
-                            if !current_line.is_empty() {
-                                tracing::debug!("Pull output line: {}", current_line);
-                                // Parse progress from lines like: "[                    ] 1.23%"
-                                if let Some(percent_str) = current_line.split(']').nth(1) {
-                                    if let Some(pct) = percent_str.trim().strip_suffix('%') {
-                                        if let Ok(progress) = pct.parse::<f32>() {
-                                            tracing::debug!("Parsed progress: {}%", progress);
-                                            progress_callback(progress);
+        let mut reported_done = false;
+
+        let pulled = cancel::run(self.cancel.as_ref(), async {
+            loop {
+                match stdout_reader.read(&mut buffer).await {
+                    Ok(0) => break, // EOF
+                    Ok(n) => {
+                        let chunk = String::from_utf8_lossy(&buffer[..n]);
+                        for ch in chunk.chars() {
+                            if ch == '\r' || ch == '\n' {
+                                if !current_line.is_empty() {
+                                    tracing::debug!("Pull output line: {}", current_line);
+                                    // Parse progress from lines like: "[                    ] 1.23%"
+                                    if let Some(percent_str) = current_line.split(']').nth(1) {
+                                        if let Some(pct) = percent_str.trim().strip_suffix('%') {
+                                            if let Ok(progress) = pct.parse::<f32>() {
+                                                tracing::debug!("Parsed progress: {}%", progress);
+                                                progress_callback(progress);
+                                            reported_done = progress >= 100.0;
+                                            progress::report(
+                                                self.progress.as_ref(),
+                                                Progress::new(Phase::PullingModel, Some(model)).percent(progress),
+                                            );
+                                            }
                                         }
                                     }
+                                    current_line.clear();
                                 }
-                                current_line.clear();
+                            } else {
+                                current_line.push(ch);
                             }
-                        } else {
-                            current_line.push(ch);
                         }
                     }
+                    Err(_) => break,
                 }
-                Err(_) => break,
             }
-        }
+            child.wait().await.context("Failed to wait for pull")
+        })
+        .await;
+        let status = match pulled {
+            Err(e) if e.is::<Cancelled>() => {
+                self.abandon_pull(&mut child, binary_path, model, alias, existed).await;
+                return Err(e);
+            }
+            result => result?,
+        };
 
-        let stderr = child.stderr.take();
-        let status = child.wait().await.context("Failed to wait for pull")?;
+        let label = if status.success() { "success" } else { "error" };
+        self.metrics.model_pulls.with_label_values(&[model, label]).inc();
 
         if !status.success() {
             if let Some(mut stderr_pipe) = stderr {
@@ -284,15 +2175,130 @@ impl LitManager {
                     stderr = %stderr_content,
                     "Model pull failed"
                 );
-                anyhow::bail!("Failed to pull model: {}", stderr_content);
+                return Err(LitError::DownloadFailed { model: model.to_string(), reason: stderr_content }.into());
             } else {
                 tracing::error!(model = %model, "Model pull failed (no stderr)");
-                anyhow::bail!("Failed to pull model");
+                return Err(LitError::DownloadFailed { model: model.to_string(), reason: status.to_string() }.into());
             }
         }
 
-        tracing::info!(model = %model, "Model pull completed successfully");
-        Ok("Download completed".to_string())
+        Self::discard_download(local);
+        Ok(reported_done)
+    }
+
+    /// Record when models were last used, for the model cache limit
+    fn model_usage(&self) -> ModelUsage {
+        ModelUsage::new(self.binary_manager.cache_dir().join("model_usage.json"))
+    }
+
+    async fn touch_model(&self, model: &str) {
+        if let Err(e) = self.model_usage().touch(model).await {
+            tracing::debug!(model = %model, error = %e, "Failed to record model use");
+        }
+    }
+
+    /// Remove the least recently used models until pulling `model` fits
+    /// under `model_cache.max_bytes`
+    async fn make_room_for(&self, binary_path: &PathBuf, model: &str, alias: Option<&str>) -> Result<()> {
+        let cache = &self.config.model_cache;
+        let Some(max_bytes) = cache.max_bytes else {
+            return Ok(());
+        };
+        let downloaded = registry::parse_listing(&self.run_lit_command(binary_path, &["list"])?);
+        if downloaded.iter().any(|entry| entry.name == alias.unwrap_or(model)) {
+            return Ok(());
+        }
+        let available = registry::parse_listing(&self.registry_listing(binary_path).await?);
+        let size_of = |name: &str| {
+            downloaded
+                .iter()
+                .chain(&available)
+                .filter(|entry| entry.name == name)
+                .find_map(|entry| entry.size_bytes())
+        };
+        let Some(incoming_bytes) = size_of(model) else {
+            tracing::warn!(model = %model, "Model size unknown; pulling without checking the model cache limit");
+            return Ok(());
+        };
+
+        let usage = self.model_usage().load();
+        let cached: Vec<CachedModel> = downloaded
+            .iter()
+            .map(|entry| CachedModel {
+                name: entry.name.clone(),
+                size_bytes: size_of(&entry.name).unwrap_or(0),
+                last_used: usage.get(&entry.name).copied(),
+            })
+            .collect();
+        let loaded: Vec<String> = self.process_pools.lock().await.values().map(|pool| pool.model().to_string()).collect();
+        let plan = quota::plan_eviction(&cached, incoming_bytes, max_bytes, |name| {
+            cache.pinned.iter().any(|pinned| pinned == name) || loaded.iter().any(|loaded| loaded == name)
+        });
+        if plan.evict.is_empty() && plan.fits {
+            return Ok(());
+        }
+
+        if cache.dry_run {
+            for victim in &plan.evict {
+                tracing::warn!(model = %victim.name, size_bytes = victim.size_bytes, pulling = %model, "Would evict model (dry run)");
+                self.metrics.model_evictions.with_label_values(&[&victim.name, "dry_run"]).inc();
+            }
+            if !plan.fits {
+                tracing::warn!(model = %model, total_bytes = plan.total_bytes, max_bytes, "Pull would exceed the model cache limit (dry run)");
+            }
+            return Ok(());
+        }
+        if !plan.fits {
+            anyhow::bail!(
+                "Pulling {} ({} bytes) would take the model cache to {} bytes, over its limit of {} bytes,                  even after evicting every unpinned model that isn't loaded",
+                model,
+                incoming_bytes,
+                plan.total_bytes,
+                max_bytes
+            );
+        }
+
+        for victim in &plan.evict {
+            let _lock = self.lock_model(&victim.name).await?;
+            self.run_lit_command(binary_path, &["rm", &victim.name])
+                .with_context(|| format!("Failed to evict {} from the model cache", victim.name))?;
+            let _ = self.model_usage().forget(&victim.name).await;
+            tracing::warn!(
+                model = %victim.name,
+                size_bytes = victim.size_bytes,
+                pulling = %model,
+                "Evicted least recently used model to stay under the model cache limit"
+            );
+            self.metrics.model_evictions.with_label_values(&[&victim.name, "evicted"]).inc();
+        }
+        Ok(())
+    }
+
+    /// Whether `lit list` shows `model` as downloaded
+    fn is_downloaded(&self, binary_path: &PathBuf, model: &str) -> bool {
+        self.run_lit_command(binary_path, &["list"])
+            .map(|output| output.lines().any(|line| model_line_name(line) == Some(model)))
+            .unwrap_or(false)
+    }
+
+    /// Stop a cancelled pull and remove what it downloaded, unless the model
+    /// was already there before the pull started
+    async fn abandon_pull(
+        &self,
+        child: &mut tokio::process::Child,
+        binary_path: &PathBuf,
+        model: &str,
+        alias: Option<&str>,
+        existed: bool,
+    ) {
+        let _ = child.kill().await;
+        self.metrics.model_pulls.with_label_values(&[model, "cancelled"]).inc();
+        tracing::info!(model = %model, "Model pull cancelled");
+        if !existed {
+            if let Err(e) = self.run_lit_command(binary_path, &["rm", alias.unwrap_or(model)]) {
+                tracing::debug!(model = %model, error = %e, "Nothing to clean up after cancelled pull");
+            }
+        }
     }
 
     /// Pull a model without writing to stdout (for library/MCP usage) - simple version
@@ -301,23 +2307,182 @@ impl LitManager {
     }
 
     pub async fn remove(&self, model: &str) -> Result<()> {
-        let binary_path = self.ensure_binary().await?;
-        let output = self.run_lit_command(&binary_path, &["rm", model])?;
+        let output = self.remove_quiet(model).await?;
         println!("{}", output);
         Ok(())
     }
 
-    /// Remove a model and return the output (for library/MCP usage)
+    /// Remove a model (or adapter) and return the output (for library/MCP usage)
     pub async fn remove_quiet(&self, model: &str) -> Result<String> {
+        self.tenant.check_manage(model)?;
+        let _lock = self.lock_model(model).await?;
+        if self.adapter_store().remove(model)? {
+            return Ok(format!("Removed adapter {}", model));
+        }
         let binary_path = self.ensure_binary().await?;
-        self.run_lit_command(&binary_path, &["rm", model])
+        let output = self.run_lit_command(&binary_path, &["rm", model])?;
+        let _ = self.model_usage().forget(model).await;
+        let _ = self.provenance_store().forget(model).await;
+        let _ = self.checksum_store().forget(model).await;
+        Ok(output)
+    }
+
+    /// Package the downloaded `model` into the directory `dest`, to carry to
+    /// another machine and [`import`](Self::import) there; see [`package`]
+    pub async fn export(&self, model: &str, dest: &std::path::Path) -> Result<PackageManifest> {
+        self.tenant.check_use(model)?;
+        let (info, file) = self.stored_model(model).await?;
+        // Not removed or replaced while it is copied
+        let _lock = self.lock_model(model).await?;
+        let manifest = package::write(&file, &info, dest).await?;
+        tracing::info!(model = %model, dest = %dest.display(), size_bytes = manifest.size_bytes, "Exported model");
+        Ok(manifest)
+    }
+
+    /// The downloaded `model` and its model file
+    async fn stored_model(&self, model: &str) -> Result<(ModelInfo, PathBuf)> {
+        let info = self
+            .list_models_structured(false)
+            .await?
+            .into_iter()
+            .find(|info| info.alias == model)
+            .ok_or_else(|| LitError::ModelNotFound { model: model.to_string() })?;
+        let stored = match (&info.path, &self.config.model_cache.models_dir) {
+            (Some(path), _) => path.clone(),
+            (None, Some(dir)) => [dir.join(model), dir.join(format!("{}.litertlm", model))]
+                .into_iter()
+                .find(|path| path.exists())
+                .with_context(|| format!("{} is not in {}", model, dir.display()))?,
+            (None, None) => anyhow::bail!(
+                "lit doesn't say where {} is stored; set model_cache.models_dir to find it",
+                model
+            ),
+        };
+        let file = package::model_file(&stored)?;
+        Ok((info, file))
+    }
+
+    /// Hash the file of `model` and compare it with the checksum recorded
+    /// when it was pulled, recording one if there is none yet
+    ///
+    /// A mismatch is a [`LitError::ChecksumMismatch`]; the model is left in
+    /// place, to pull again.
+    pub async fn verify(&self, model: &str) -> Result<ChecksumRecord> {
+        self.tenant.check_use(model)?;
+        let (_, file) = self.stored_model(model).await?;
+        let (sha256, size_bytes) = checksum::sha256_file_async(file.clone()).await?;
+        if let Some(recorded) = self.checksum_store().get(model) {
+            if recorded.sha256 != sha256 {
+                return Err(LitError::ChecksumMismatch {
+                    model: model.to_string(),
+                    expected: recorded.sha256,
+                    actual: sha256,
+                }
+                .into());
+            }
+        }
+        let record = ChecksumRecord {
+            model: model.to_string(),
+            sha256,
+            size_bytes,
+            path: file,
+            checked_at: crate::history::unix_now(),
+        };
+        self.checksum_store().record(record.clone()).await?;
+        Ok(record)
+    }
+
+    /// Checksum recorded for `model` by its pull or last [`verify`](Self::verify)
+    pub fn model_checksum(&self, model: &str) -> Option<ChecksumRecord> {
+        self.checksum_store().get(model)
+    }
+
+    fn checksum_store(&self) -> ChecksumStore {
+        ChecksumStore::new(self.binary_manager.cache_dir().join("model_checksums.json"))
+    }
+
+    /// Hash the model a pull just stored as `model` and record it; when the
+    /// pull expected a checksum (given with it, or `listed` by a registry
+    /// manifest), a model that doesn't have it is removed
+    async fn check_pulled(&self, binary_path: &PathBuf, model: &str, listed: Option<&str>) -> Result<()> {
+        let expected = self.expected_sha256.as_deref().or(listed);
+        let file = match self.stored_model(model).await {
+            Ok((_, file)) => file,
+            Err(e) if self.expected_sha256.is_some() => {
+                return Err(e.context(format!("Can't check the SHA-256 of {}", model)))
+            }
+            Err(e) if expected.is_some() => {
+                // As for the Hub's digests, a manifest's can't be insisted on
+                // when lit doesn't say where it stores models
+                tracing::warn!(model = %model, error = %format!("{:#}", e), "Model file not found; listed checksum not checked");
+                return Ok(());
+            }
+            Err(e) => {
+                tracing::debug!(model = %model, error = %format!("{:#}", e), "Model file not found; no checksum recorded");
+                return Ok(());
+            }
+        };
+        let (sha256, size_bytes) = checksum::sha256_file_async(file.clone()).await?;
+        if let Some(expected) = expected.filter(|&expected| expected != sha256) {
+            tracing::error!(model = %model, expected = %expected, actual = %sha256, "Pulled model has the wrong checksum");
+            if let Err(e) = self.run_lit_command(binary_path, &["rm", model]) {
+                tracing::warn!(model = %model, error = %e, "Failed to remove damaged model");
+            }
+            return Err(LitError::ChecksumMismatch {
+                model: model.to_string(),
+                expected: expected.to_string(),
+                actual: sha256,
+            }
+            .into());
+        }
+        tracing::info!(model = %model, sha256 = %sha256, verified = expected.is_some(), "Recorded model checksum");
+        let record = ChecksumRecord {
+            model: model.to_string(),
+            sha256,
+            size_bytes,
+            path: file,
+            checked_at: crate::history::unix_now(),
+        };
+        self.checksum_store().record(record).await
     }
 
+    /// Pull the model in a package made by [`export`](Self::export), as
+    /// `alias` or else the alias it was exported with
+    pub async fn import(&self, package: &std::path::Path, alias: Option<&str>) -> Result<PackageManifest> {
+        let (manifest, file) = package::read(package)?;
+        let alias = alias.unwrap_or(&manifest.alias);
+        self.pull(&file.to_string_lossy(), Some(alias), None).await?;
+        tracing::info!(model = %alias, package = %package.display(), "Imported model");
+        Ok(manifest)
+    }
+
+    /// Start a multi-turn conversation with `model`
+    pub fn chat_session(&self, model: &str) -> ChatSession {
+        ChatSession::new(self.clone(), model)
+    }
+
+    /// Directory holding the lit binary, downloads, and other cached state
+    pub fn cache_dir(&self) -> &std::path::Path {
+        self.binary_manager.cache_dir()
+    }
+
+    /// Where chat sessions are saved by default
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.binary_manager.cache_dir().join("sessions")
+    }
+
+    /// Hand the terminal to lit's own interactive session
     pub async fn run_interactive(&self, model: &str) -> Result<()> {
+        let model = &self.resolve_variant(self.model_or_default(model));
+        self.tenant.check_use(model)?;
         let binary_path = self.ensure_binary().await?;
 
-        let status = Command::new(&binary_path)
-            .args(&["run", model])
+        let mut command = Command::new(&binary_path);
+        match self.adapter_store().get(model) {
+            Some(adapter) => command.args(["run", &adapter.base, crate::adapter::LIT_FLAG]).arg(&adapter.path),
+            None => command.args(["run", model]),
+        };
+        let status = command
             .stdin(Stdio::inherit())
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
@@ -336,8 +2501,38 @@ impl LitManager {
         Ok(())
     }
 
+    #[cfg(feature = "server")]
     pub async fn serve(&self, port: u16) -> Result<()> {
-        tracing::info!("Starting server on port {}", port);
+        self.serve_with(port, &ServeOptions::default()).await
+    }
+
+    /// Serve on every interface at `port`
+    #[cfg(feature = "server")]
+    pub async fn serve_with(&self, port: u16, options: &ServeOptions) -> Result<()> {
+        self.serve_on_with(std::net::SocketAddr::from(([0, 0, 0, 0], port)), options).await
+    }
+
+    /// Serve on `addr` only, e.g. `127.0.0.1:8080` to stay off the network
+    #[cfg(feature = "server")]
+    pub async fn serve_on(&self, addr: std::net::SocketAddr) -> Result<()> {
+        self.serve_on_with(addr, &ServeOptions::default()).await
+    }
+
+    #[cfg(feature = "server")]
+    pub async fn serve_on_with(&self, addr: std::net::SocketAddr, options: &ServeOptions) -> Result<()> {
+        let port = addr.port();
+        tracing::info!("Starting server on {}", addr);
+        let schedules = self
+            .config
+            .schedule
+            .iter()
+            .map(crate::schedule::ModelSchedule::from_config)
+            .collect::<Result<Vec<_>>>()?;
+        let maintenance = &self.config.maintenance;
+        let maintenance_at = maintenance
+            .enabled
+            .then(|| crate::schedule::Cron::parse(&maintenance.at).context("Invalid maintenance.at"))
+            .transpose()?;
 
         // Ensure binary is ready
         let binary_path = self.ensure_binary().await?;
@@ -347,28 +2542,166 @@ impl LitManager {
         let model = std::env::var("LITERT_MODEL")
             .unwrap_or_else(|_| "gemma-3n-E4B".to_string());
 
-        // Pre-initialize pool for default model
-        let pool = self.get_pool(&model).await?;
+        // Pre-initialize pool for default model, and every downloaded model if asked to
+        let pool = if options.preload_all {
+            let listing = self.list_models(false).await?;
+            let models: Vec<String> = std::iter::once(model.clone())
+                .chain(listing.lines().filter_map(model_line_name).map(str::to_string))
+                .collect();
+            let pools = self.preload(&models).await?;
+            tracing::info!(models = pools.len(), "Preloaded process pools for every downloaded model");
+            pools.into_iter().next().context("No model to serve")?
+        } else {
+            self.get_pool(&model).await?
+        };
         tracing::info!("Process pool initialized for model '{}' with {} instances", model, self.pool_size);
 
+        #[cfg(feature = "mcp")]
+        if !self.config.mcp_servers.is_empty() {
+            let clients = self.connect_mcp_servers().await;
+            tracing::info!(servers = ?clients.servers(), "MCP server tools available to /v1/agents");
+        }
+        #[cfg(not(feature = "mcp"))]
+        if !self.config.mcp_servers.is_empty() {
+            tracing::warn!("mcp_servers is set, but litert-lm was built without the mcp feature");
+        }
+
+        // Load and unload models at the times set in the config
+        if !schedules.is_empty() {
+            tracing::info!(models = schedules.len(), "Starting model schedule");
+            tokio::spawn(crate::schedule::run(self.clone(), schedules));
+        }
+        // Unload models once they outlive their keep-alive
+        tokio::spawn(keep_alive::run(self.clone()));
+        if let Some(at) = maintenance_at {
+            tracing::info!(at = %maintenance.at, offline = maintenance.offline, "Starting maintenance schedule");
+            tokio::spawn(crate::maintenance::run(self.clone(), maintenance.clone(), at));
+        }
+
+        // Orchestrators may route traffic here once the warm-up generation succeeds
+        let readiness = if options.wait_ready {
+            let readiness = Readiness::warming_up();
+            let (pool, warming) = (pool.clone(), readiness.clone());
+            tokio::spawn(async move {
+                match pool.warm_up().await {
+                    Ok(()) => {
+                        tracing::info!("Warm-up generation finished; serving completions");
+                        warming.set(ReadyState::Ready);
+                    }
+                    Err(e) => {
+                        tracing::error!(error = %e, "Warm-up generation failed; completions stay unavailable");
+                        warming.set(ReadyState::Failed { error: format!("{:#}", e) });
+                    }
+                }
+            });
+            readiness
+        } else {
+            Readiness::default()
+        };
+
         // Start server - AppState holds both pool and manager
         let app_state = AppState {
             pool,
-            manager: Arc::new(self.clone()),
+            manager: Arc::new(self.with_api_keys(&options.api_keys)),
+            readiness,
+            chunking: options.chunking,
         };
         let app = create_router(app_state);
 
-        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
+        // Bad certificates fail the start rather than the first handshake
+        let tls = match &options.tls {
+            Some(tls) => Some(tls.load().await?),
+            None => None,
+        };
+
+        let listener = tokio::net::TcpListener::bind(addr)
             .await
-            .context("Failed to bind to port")?;
+            .with_context(|| format!("Failed to bind to {}", addr))?;
+
+        // Routers find this server for as long as the daemon is alive
+        #[cfg(feature = "mdns")]
+        let _advertisement = if self.config.cluster.advertise && addr.ip().is_loopback() {
+            tracing::warn!("cluster.advertise is set, but the server only listens on loopback; not advertising");
+            None
+        } else if self.config.cluster.advertise {
+            Some(crate::cluster::advertise(port)?)
+        } else {
+            None
+        };
+        #[cfg(not(feature = "mdns"))]
+        if self.config.cluster.advertise {
+            tracing::warn!("cluster.advertise is set, but litert-lm was built without the mdns feature");
+        }
 
-        tracing::info!("Server listening on http://0.0.0.0:{}", port);
-        tracing::info!("OpenAI-compatible endpoint: http://localhost:{}/v1/chat/completions", port);
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        tracing::info!("Server listening on {}://{}", scheme, addr);
+        let endpoint = match addr.ip().is_unspecified() {
+            true => format!("localhost:{}", port),
+            false => addr.to_string(),
+        };
+        tracing::info!("OpenAI-compatible endpoint: {}://{}/v1/chat/completions", scheme, endpoint);
 
-        axum::serve(listener, app)
-            .await
-            .context("Server error")?;
+        // On SIGINT/SIGTERM stop accepting connections and let open ones
+        // finish, for up to `shutdown_timeout`
+        let drain_timeout = options.shutdown_timeout.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+        match tls {
+            Some(tls) => {
+                let handle = axum_server::Handle::new();
+                let signalled = handle.clone();
+                tokio::spawn(async move {
+                    shutdown_signal().await;
+                    tracing::info!(timeout_secs = drain_timeout.as_secs(), "Shutting down; waiting for open requests");
+                    signalled.graceful_shutdown(Some(drain_timeout));
+                });
+                axum_server::from_tcp_rustls(listener.into_std()?, tls)
+                    .handle(handle)
+                    .serve(app.into_make_service())
+                    .await
+                    .context("Server error")?;
+            }
+            None => {
+                let signalled = Arc::new(tokio::sync::Notify::new());
+                let notify = signalled.clone();
+                let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+                    shutdown_signal().await;
+                    notify.notify_one();
+                });
+                tokio::select! {
+                    result = server => result.context("Server error")?,
+                    _ = async {
+                        signalled.notified().await;
+                        tracing::info!(timeout_secs = drain_timeout.as_secs(), "Shutting down; waiting for open requests");
+                        tokio::time::sleep(drain_timeout).await;
+                    } => tracing::warn!("Requests still open after the shutdown timeout; closing them"),
+                }
+            }
+        }
 
+        self.shutdown().await;
+        tracing::info!("Server stopped");
         Ok(())
     }
 }
+
+// For `retain`: keep `pool` unless `remove`, in which case stop respawning its
+// processes, so they stop with the last request holding the pool
+fn retain_unless(pool: &ProcessPool, remove: bool) -> bool {
+    if remove {
+        pool.retire();
+    }
+    !remove
+}
+
+// Stop the processes of a pool taken out of the map once the requests
+// holding it are done; the server's default pool is never dropped otherwise
+fn stop_when_idle(pool: Arc<ProcessPool>) {
+    pool.retire();
+    tokio::spawn(async move {
+        while pool.idle_for().is_none() {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        if let Err(e) = pool.shutdown().await {
+            tracing::warn!(model = %pool.model(), error = %e, "Failed to stop process pool");
+        }
+    });
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use rmcp::{
     handler::server::{router::tool::ToolRouter, wrapper::Parameters},
     model::{ErrorData as McpError, *},
@@ -10,7 +10,10 @@ use std::{borrow::Cow, collections::HashMap, sync::Arc};
 use tokio::sync::{RwLock, Mutex};
 use uuid::Uuid;
 
+use crate::constraints::Constraint;
+use crate::generation::GenOptions;
 use crate::manager::LitManager;
+use crate::progress::{Phase, Progress};
 
 // Download progress tracking
 #[derive(Debug, Clone, Serialize)]
@@ -57,10 +60,10 @@ pub struct ListModelsRequest {
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct PullModelRequest {
-    #[schemars(description = "The model name or URL to download (e.g., 'gemma-3n-E4B' or Hugging Face URL)")]
+    #[schemars(description = "The model name or URL to download (e.g., 'gemma-3n-E4B', a Hugging Face URL, or s3://bucket/model.litertlm)")]
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[schemars(description = "Alias to save the model as (only for URLs)")]
+    #[schemars(description = "Alias to save the model as (not for registry models)")]
     pub alias: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "Hugging Face API token for authentication")]
@@ -81,25 +84,49 @@ pub struct RunCompletionRequest {
     pub prompt: String,
     #[serde(default = "default_max_tokens")]
     #[schemars(description = "Maximum tokens to generate (default: 2048)")]
-    #[allow(dead_code)]
     pub max_tokens: u32,
-    #[serde(default = "default_temperature")]
-    #[schemars(description = "Temperature for sampling (default: 0.7)")]
-    #[allow(dead_code)]
-    pub temperature: f32,
+    #[serde(default)]
+    #[schemars(description = "Temperature for sampling, from 0 to 2 (default: the model's)")]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    #[schemars(description = "Nucleus sampling threshold, above 0 and at most 1 (default: the model's)")]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    #[schemars(description = "Number of most likely tokens sampled from (default: the model's)")]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    #[schemars(description = "Optional format the output must satisfy (regex, choice, or json)")]
+    pub constraint: Option<Constraint>,
 }
 
-fn default_max_tokens() -> u32 {
-    2048
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SearchDocumentsRequest {
+    #[schemars(description = "What to search the ingested documents for")]
+    pub query: String,
+    #[serde(default = "default_top_k")]
+    #[schemars(description = "Number of chunks to return (default: 5)")]
+    pub top_k: usize,
+}
+
+fn default_top_k() -> usize {
+    5
 }
 
-fn default_temperature() -> f32 {
-    0.7
+fn default_max_tokens() -> u32 {
+    2048
 }
 
 #[tool_router(router = tool_router)]
 impl LiteRtMcpService {
     pub async fn new(manager: LitManager) -> Result<Self> {
+        // Pull progress is reported from the manager and applied to the
+        // litert://downloads resources by a task that owns the service
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel::<Progress>();
+        let manager = manager.with_progress(Arc::new(move |progress: &Progress| {
+            if progress.phase == Phase::PullingModel && progress.model.is_some() {
+                let _ = progress_tx.send(progress.clone());
+            }
+        }));
         let manager_arc = Arc::new(manager);
 
         tracing::info!("Initializing MCP service, loading model registry...");
@@ -107,79 +134,62 @@ impl LiteRtMcpService {
         let download_progress = Self::initialize_model_registry(manager_arc.clone()).await?;
         tracing::info!("Model registry loaded with {} models", download_progress.len());
 
-        Ok(Self {
+        let service = Self {
             manager: manager_arc,
             tool_router: Self::tool_router(),
             download_progress: Arc::new(RwLock::new(download_progress)),
             subscriptions: Arc::new(Mutex::new(HashMap::new())),
-        })
-    }
-
-    /// Initialize model registry by listing all available models
-    async fn initialize_model_registry(manager: Arc<LitManager>) -> Result<HashMap<String, DownloadProgress>> {
-        let binary_path = manager.ensure_binary_path().await?;
-
-        // Get list of all models in registry
-        let output = std::process::Command::new(&binary_path)
-            .arg("list")
-            .arg("--show_all")
-            .output()?;
-
-        if !output.status.success() {
-            anyhow::bail!("Failed to list models from registry");
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut progress_map = HashMap::new();
-
-        // Parse the output - it's column formatted with ALIAS and MODEL
-        // Skip header lines and extract the ALIAS (first column)
-        for line in stdout.lines() {
-            let trimmed = line.trim();
-
-            // Skip empty lines, headers, and separator lines
-            if trimmed.is_empty()
-                || trimmed.starts_with("Available")
-                || trimmed.starts_with("Downloaded")
-                || trimmed.starts_with("ALIAS") {
-                continue;
-            }
-
-            // Extract the first word/column (ALIAS)
-            let model = trimmed.split_whitespace().next();
-            if let Some(model_name) = model {
-                // Check if model is already downloaded
-                let is_downloaded = Self::check_if_downloaded(&binary_path, model_name).await?;
+        };
 
-                let status = if is_downloaded {
+        let tracker = service.clone();
+        tokio::spawn(async move {
+            while let Some(progress) = progress_rx.recv().await {
+                let pct = progress.percent.unwrap_or(0.0);
+                let status = if progress.is_finished() {
                     DownloadStatus::Complete
+                } else if pct > 0.0 {
+                    DownloadStatus::Downloading
                 } else {
                     DownloadStatus::Pending
                 };
-
-                progress_map.insert(model_name.to_string(), DownloadProgress {
-                    model: model_name.to_string(),
-                    progress: if is_downloaded { 100 } else { 0 },
-                    status,
-                });
+                let model = progress.model.unwrap_or_default();
+                // Updates that lag behind a failed pull must not hide the failure
+                let failed = matches!(
+                    tracker.query_download_progress(&model).await,
+                    Some(DownloadProgress { status: DownloadStatus::Failed(_), .. })
+                );
+                if !failed {
+                    tracker.update_progress(model, pct as u8, status).await;
+                }
             }
-        }
+        });
 
-        Ok(progress_map)
+        Ok(service)
     }
 
-    /// Check if a model is already downloaded
-    async fn check_if_downloaded(binary_path: &std::path::PathBuf, model: &str) -> Result<bool> {
-        let output = std::process::Command::new(binary_path)
-            .arg("list")
-            .output()?;
-
-        if !output.status.success() {
-            return Ok(false);
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        Ok(stdout.lines().any(|line| line.trim() == model))
+    /// Initialize model registry by listing all available models
+    async fn initialize_model_registry(manager: Arc<LitManager>) -> Result<HashMap<String, DownloadProgress>> {
+        let models = manager
+            .list_models_structured(true)
+            .await
+            .context("Failed to list models from registry")?;
+
+        Ok(models
+            .into_iter()
+            .map(|model| {
+                let (progress, status) = if model.downloaded {
+                    (100, DownloadStatus::Complete)
+                } else {
+                    (0, DownloadStatus::Pending)
+                };
+                let progress = DownloadProgress {
+                    model: model.alias.clone(),
+                    progress,
+                    status,
+                };
+                (model.alias, progress)
+            })
+            .collect())
     }
 
     /// Get current download progress for a model (library API)
@@ -254,41 +264,29 @@ impl LiteRtMcpService {
         &self,
         Parameters(request): Parameters<ListModelsRequest>,
     ) -> Result<CallToolResult, McpError> {
-        let manager = self.manager.clone();
-        let show_all = request.show_all;
-
-        let result = tokio::task::spawn_blocking(move || {
-            tokio::runtime::Handle::current().block_on(async move {
-                // Use the binary to list models
-                let binary_path = manager.ensure_binary_path().await
-                    .map_err(|e| format!("Failed to get binary: {}", e))?;
-
-                let mut cmd = std::process::Command::new(&binary_path);
-                cmd.arg("list");
-                if show_all {
-                    cmd.arg("--show_all");
-                }
-
-                let output = cmd
-                    .output()
-                    .map_err(|e| format!("Failed to execute list: {}", e))?;
+        let models = self
+            .manager
+            .list_models_structured(request.show_all)
+            .await
+            .map_err(|e| McpError {
+                code: ErrorCode(-32603),
+                message: Cow::from(format!("Failed to list models: {}", e)),
+                data: None,
+            })?;
 
-                Ok::<String, String>(String::from_utf8_lossy(&output.stdout).to_string())
-            })
-        })
-        .await
-        .map_err(|e| McpError {
+        let mut listing = serde_json::to_string_pretty(&models).map_err(|e| McpError {
             code: ErrorCode(-32603),
-            message: Cow::from(format!("Task failed: {}", e)),
-            data: None,
-        })?
-        .map_err(|e| McpError {
-            code: ErrorCode(-32603),
-            message: Cow::from(e),
+            message: Cow::from(format!("Failed to serialize models: {}", e)),
             data: None,
         })?;
-
-        Ok(CallToolResult::success(vec![Content::text(result)]))
+        let adapters = self.manager.adapters();
+        if !adapters.is_empty() {
+            listing.push_str("\n\nAdapters:\n");
+            for adapter in adapters {
+                listing.push_str(&format!("{}\n", adapter));
+            }
+        }
+        Ok(CallToolResult::success(vec![Content::text(listing)]))
     }
 
     /// Download a model from registry or URL
@@ -305,42 +303,8 @@ impl LiteRtMcpService {
         // Initialize progress
         self.update_progress(model.clone(), 0, DownloadStatus::Pending).await;
 
-        // Use real progress tracking from lit binary with channel
-        let progress_tracker = self.clone();
-        let progress_model = model.clone();
-
-        // Create a channel to send progress updates
-        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-
-        // Spawn task to handle progress updates
-        let update_task = tokio::spawn(async move {
-            while let Some(pct) = rx.recv().await {
-                let status = if pct >= 100.0 {
-                    DownloadStatus::Complete
-                } else if pct > 0.0 {
-                    DownloadStatus::Downloading
-                } else {
-                    DownloadStatus::Pending
-                };
-                progress_tracker.update_progress(progress_model.clone(), pct as u8, status).await;
-            }
-        });
-
-        let result = manager.pull_with_progress(
-            &model,
-            alias.as_deref(),
-            hf_token.as_deref(),
-            {
-                let tx = tx.clone();
-                move |pct| {
-                    let _ = tx.send(pct);
-                }
-            }
-        ).await;
-
-        // Clean up - drop the original sender to signal completion
-        drop(tx);
-        update_task.await.ok();
+        // The manager's progress reporter updates the resource as lit reports progress
+        let result = manager.pull_quiet(&model, alias.as_deref(), hf_token.as_deref()).await;
 
         match result {
             Ok(output) => {
@@ -404,10 +368,18 @@ impl LiteRtMcpService {
         let manager = self.manager.clone();
         let model = request.model.clone();
         let prompt = request.prompt.clone();
+        let options = GenOptions {
+            constraint: request.constraint,
+            max_tokens: Some(request.max_tokens as usize),
+            temperature: request.temperature,
+            top_p: request.top_p,
+            top_k: request.top_k,
+            ..Default::default()
+        };
 
         let result = tokio::task::spawn_blocking(move || {
             tokio::runtime::Handle::current().block_on(async move {
-                manager.run_completion(&model, &prompt).await
+                manager.run_completion_with(&model, &prompt, &options).await
                     .map_err(|e| format!("Failed to run completion: {}", e))
             })
         })
@@ -426,6 +398,30 @@ impl LiteRtMcpService {
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 
+    /// Search locally ingested documents
+    #[tool(description = "Search locally ingested documents (txt, md, pdf) and return the most relevant passages")]
+    async fn search_documents(
+        &self,
+        Parameters(request): Parameters<SearchDocumentsRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let hits = self
+            .manager
+            .search_documents(&request.query, request.top_k)
+            .await
+            .map_err(|e| McpError {
+                code: ErrorCode(-32603),
+                message: Cow::from(format!("Failed to search documents: {}", e)),
+                data: None,
+            })?;
+
+        let json = serde_json::to_string_pretty(&hits).map_err(|e| McpError {
+            code: ErrorCode(-32603),
+            message: Cow::from(format!("Failed to serialize results: {}", e)),
+            data: None,
+        })?;
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
     /// Get download progress for a model
     #[tool(description = "Get download progress for a model (if currently downloading)")]
     async fn check_download_progress(
@@ -466,7 +462,7 @@ impl ServerHandler for LiteRtMcpService {
                 website_url: None,
             },
             instructions: Some(
-                "LiteRT-LM MCP server. Tools: list_models, pull_model, remove_model, run_completion, check_download_progress. Resources: litert://downloads/{model} for download progress tracking with subscription support."
+                "LiteRT-LM MCP server. Tools: list_models, pull_model, remove_model, run_completion, search_documents, check_download_progress. Resources: litert://downloads/{model} for download progress tracking with subscription support."
                     .into(),
             ),
         }
@@ -0,0 +1,146 @@
+//! MCP client mode
+//!
+//! [`McpClientManager`] connects to the MCP servers listed in the config file
+//! and registers their tools (as `<server>.<tool>`) in a [`ToolRegistry`], so
+//! the agent loop can offer them to the local model and route its calls back
+//! to the right server.
+
+use anyhow::{Context, Result};
+use rmcp::model::{CallToolRequestParam, Tool};
+use rmcp::service::{RoleClient, RunningService};
+use rmcp::transport::StreamableHttpClientTransport;
+use rmcp::ServiceExt;
+use std::process::Stdio;
+
+use crate::agent::{ToolDefinition, ToolRegistry};
+use crate::config::{McpServerConfig, McpServerTransport};
+
+struct McpConnection {
+    name: String,
+    service: RunningService<RoleClient, ()>,
+    tools: Vec<Tool>,
+    // Stdio servers are killed when the connection is dropped
+    _child: Option<tokio::process::Child>,
+}
+
+/// Live connections to external MCP servers
+#[derive(Default)]
+pub struct McpClientManager {
+    connections: Vec<McpConnection>,
+}
+
+impl std::fmt::Debug for McpClientManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpClientManager")
+            .field("servers", &self.servers())
+            .finish()
+    }
+}
+
+impl McpClientManager {
+    /// Connect to every configured server; servers that fail are logged and skipped
+    pub async fn connect(servers: &[McpServerConfig]) -> Self {
+        let mut connections = Vec::new();
+        for server in servers {
+            match Self::connect_one(server).await {
+                Ok(connection) => {
+                    tracing::info!(
+                        server = %server.name,
+                        tools = connection.tools.len(),
+                        "Connected to MCP server"
+                    );
+                    connections.push(connection);
+                }
+                Err(e) => {
+                    tracing::warn!(server = %server.name, error = %e, "Failed to connect to MCP server");
+                }
+            }
+        }
+        Self { connections }
+    }
+
+    async fn connect_one(server: &McpServerConfig) -> Result<McpConnection> {
+        let (service, child) = match &server.transport {
+            McpServerTransport::Stdio { command, env } => {
+                let (program, args) = command
+                    .split_first()
+                    .context("MCP server has an empty command")?;
+                let mut child = tokio::process::Command::new(program)
+                    .args(args)
+                    .envs(env)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .with_context(|| format!("Failed to start MCP server {}", program))?;
+                let stdout = child.stdout.take().context("Failed to get stdout")?;
+                let stdin = child.stdin.take().context("Failed to get stdin")?;
+                let service = ().serve((stdout, stdin)).await?;
+                (service, Some(child))
+            }
+            McpServerTransport::Http { url } => {
+                let transport = StreamableHttpClientTransport::from_uri(url.as_str());
+                (().serve(transport).await?, None)
+            }
+        };
+
+        let tools = service
+            .list_all_tools()
+            .await
+            .with_context(|| format!("Failed to list tools of MCP server '{}'", server.name))?;
+
+        Ok(McpConnection {
+            name: server.name.clone(),
+            service,
+            tools,
+            _child: child,
+        })
+    }
+
+    /// Names of the connected servers
+    pub fn servers(&self) -> Vec<String> {
+        self.connections.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Register every remote tool in `registry` as `<server>.<tool>`
+    pub fn register_tools(&self, registry: &ToolRegistry) {
+        for connection in &self.connections {
+            for tool in &connection.tools {
+                let definition = ToolDefinition {
+                    name: format!("{}.{}", connection.name, tool.name),
+                    description: tool.description.as_deref().unwrap_or_default().to_string(),
+                    parameters: serde_json::Value::Object((*tool.input_schema).clone()),
+                };
+                let peer = connection.service.peer().clone();
+                let remote_name = tool.name.clone();
+
+                registry.register_fn(definition, move |arguments| {
+                    let peer = peer.clone();
+                    let name = remote_name.clone();
+                    async move {
+                        let arguments = match arguments {
+                            serde_json::Value::Object(map) => Some(map),
+                            serde_json::Value::Null => None,
+                            other => anyhow::bail!("Tool arguments must be an object, got {}", other),
+                        };
+                        let result = peer
+                            .call_tool(CallToolRequestParam { name, arguments })
+                            .await?;
+
+                        let text = result
+                            .content
+                            .iter()
+                            .filter_map(|content| content.as_text().map(|t| t.text.clone()))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        if result.is_error.unwrap_or(false) {
+                            anyhow::bail!("{}", text);
+                        }
+                        Ok(text)
+                    }
+                });
+            }
+        }
+    }
+}
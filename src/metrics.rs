@@ -0,0 +1,462 @@
+//! Crate-wide metrics registry
+//!
+//! A single [`Metrics`] instance is owned by [`LitManager`](crate::LitManager) and
+//! shared (via `Arc`) with the binary manager, process pools, processes, and the
+//! HTTP server. It backs both the Prometheus `/metrics` endpoint and
+//! [`LitManager::stats`](crate::LitManager::stats).
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::process::GenerationTiming;
+
+/// Window used when reporting recent errors
+pub const RECENT_ERROR_WINDOW: Duration = Duration::from_secs(300);
+
+pub struct Metrics {
+    registry: Registry,
+    started: Instant,
+    recent_errors: Mutex<VecDeque<Instant>>,
+
+    // Binary downloads
+    pub binary_downloads: IntCounterVec,
+    pub binary_download_bytes: IntCounter,
+
+    // Model pulls
+    pub model_pulls: IntCounterVec,
+    pub model_evictions: IntCounterVec,
+    pub retries: IntCounterVec,
+
+    // Pool scheduling
+    pub pool_dispatches: IntCounterVec,
+    pub affinity_lookups: IntCounterVec,
+    pub pool_queue_depth: IntGaugeVec,
+    pub pool_processes: IntGaugeVec,
+    pub pool_backend_processes: IntGaugeVec,
+    pub concurrency_rejections: IntCounterVec,
+    pub tenant_waiting: IntGaugeVec,
+    pub tenant_wait_duration: HistogramVec,
+
+    // Process lifecycle
+    pub process_spawns: IntCounterVec,
+    pub process_exits: IntCounterVec,
+    pub process_restarts: IntCounterVec,
+    pub process_recycles: IntCounterVec,
+    pub gpu_fallbacks: IntCounterVec,
+    pub completions: IntCounterVec,
+    pub context_overflows: IntCounterVec,
+    pub prompt_cache_lookups: IntCounterVec,
+    pub time_to_first_token: HistogramVec,
+    pub tokens_per_second: HistogramVec,
+
+    // Traffic splits
+    pub split_requests: IntCounterVec,
+    pub split_request_duration: HistogramVec,
+
+    // HTTP handlers
+    pub http_requests: IntCounterVec,
+    pub http_errors: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics")
+            .field("started", &self.started)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Totals read from the registry, as reported by `stats()`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub http_requests_total: u64,
+    pub http_errors_total: u64,
+    pub recent_errors: u64,
+    pub recent_error_window_secs: u64,
+    pub binary_downloads_total: u64,
+    pub model_pulls_total: u64,
+    pub model_pull_failures_total: u64,
+    pub process_spawns_total: u64,
+    pub process_spawn_failures_total: u64,
+    pub process_exits_total: u64,
+    #[serde(default)]
+    pub process_restarts_total: u64,
+    #[serde(default)]
+    pub gpu_fallbacks_total: u64,
+    pub completions_total: u64,
+}
+
+fn counter_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+    registry.register(Box::new(counter.clone())).expect("unique metric");
+    counter
+}
+
+fn gauge_vec(registry: &Registry, name: &str, help: &str, labels: &[&str]) -> IntGaugeVec {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), labels).expect("valid metric");
+    registry.register(Box::new(gauge.clone())).expect("unique metric");
+    gauge
+}
+
+/// Sum a counter family over all label values, optionally filtered by one label
+fn sum_counter(counter: &IntCounterVec, filter: Option<(&str, &str)>) -> u64 {
+    use prometheus::core::Collector;
+
+    counter
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .filter(|metric| match filter {
+            Some((name, value)) => metric
+                .get_label()
+                .iter()
+                .any(|l| l.name() == name && l.value() == value),
+            None => true,
+        })
+        .map(|metric| metric.get_counter().get_value() as u64)
+        .sum()
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("litert".to_string()), None)
+            .expect("valid registry prefix");
+
+        let binary_downloads = counter_vec(
+            &registry,
+            "binary_downloads_total",
+            "lit binary downloads by result",
+            &["result"],
+        );
+        let binary_download_bytes =
+            IntCounter::new("binary_download_bytes_total", "Bytes of lit binary downloaded")
+                .expect("valid metric");
+        registry
+            .register(Box::new(binary_download_bytes.clone()))
+            .expect("unique metric");
+
+        let model_pulls = counter_vec(
+            &registry,
+            "model_pulls_total",
+            "Model pulls by result",
+            &["model", "result"],
+        );
+
+        let model_evictions = counter_vec(
+            &registry,
+            "model_evictions_total",
+            "Models removed (or, in dry-run mode, picked for removal) to stay under the model cache limit",
+            &["model", "result"],
+        );
+        let retries = counter_vec(
+            &registry,
+            "retries_total",
+            "Binary downloads, model pulls, and pool starts retried after a failure, by error class",
+            &["operation", "class"],
+        );
+
+        let pool_dispatches = counter_vec(
+            &registry,
+            "pool_dispatches_total",
+            "Prompts dispatched to a pool process",
+            &["model", "process"],
+        );
+        let affinity_lookups = counter_vec(
+            &registry,
+            "affinity_lookups_total",
+            "Conversation turns sent to the process holding the conversation (hit) or replayed (miss)",
+            &["model", "result"],
+        );
+        let pool_queue_depth = gauge_vec(
+            &registry,
+            "pool_queue_depth",
+            "Prompts queued on or running in a pool",
+            &["model"],
+        );
+        let pool_processes = gauge_vec(
+            &registry,
+            "pool_processes",
+            "Processes in a pool",
+            &["model"],
+        );
+        let pool_backend_processes = gauge_vec(
+            &registry,
+            "pool_backend_processes",
+            "Processes in a model's pools by the backend they run on",
+            &["model", "backend"],
+        );
+        let concurrency_rejections = counter_vec(
+            &registry,
+            "concurrency_rejections_total",
+            "Requests turned away because their model had its limit of requests in flight",
+            &["model"],
+        );
+
+        let tenant_waiting = gauge_vec(
+            &registry,
+            "tenant_waiting",
+            "Requests waiting for a pool slot, by tenant",
+            &["model", "tenant"],
+        );
+        let tenant_wait_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "tenant_wait_seconds",
+                "Time a request waited for a pool slot, by tenant",
+            )
+            .buckets(vec![0.001, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["model", "tenant"],
+        )
+        .expect("valid metric");
+        registry
+            .register(Box::new(tenant_wait_duration.clone()))
+            .expect("unique metric");
+
+        let process_spawns = counter_vec(
+            &registry,
+            "process_spawns_total",
+            "lit process spawn attempts by backend and result",
+            &["model", "backend", "result"],
+        );
+        let process_exits = counter_vec(
+            &registry,
+            "process_exits_total",
+            "lit processes that stopped, by reason",
+            &["model", "reason"],
+        );
+        let process_restarts = counter_vec(
+            &registry,
+            "process_restarts_total",
+            "lit processes respawned after they died",
+            &["model"],
+        );
+        let process_recycles = counter_vec(
+            &registry,
+            "process_recycles_total",
+            "lit processes replaced after serving their maximum number of requests",
+            &["model"],
+        );
+        let gpu_fallbacks = counter_vec(
+            &registry,
+            "gpu_fallbacks_total",
+            "lit processes started on CPU because the GPU backend failed",
+            &["model"],
+        );
+        let completions = counter_vec(
+            &registry,
+            "completions_total",
+            "Completed generations by result",
+            &["model", "result"],
+        );
+        let context_overflows = counter_vec(
+            &registry,
+            "context_overflows_total",
+            "Conversations fitted to the context window, by action",
+            &["model", "action"],
+        );
+        let prompt_cache_lookups = counter_vec(
+            &registry,
+            "prompt_cache_lookups_total",
+            "Prompt cache lookups by result (memory, disk, or miss)",
+            &["model", "result"],
+        );
+
+        // Labeled by backend so a process that fell back to CPU shows up as
+        // its own, much slower series
+        let time_to_first_token = HistogramVec::new(
+            HistogramOpts::new(
+                "time_to_first_token_seconds",
+                "Time from a prompt reaching a process to its first generated text",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["model", "backend"],
+        )
+        .expect("valid metric");
+        registry
+            .register(Box::new(time_to_first_token.clone()))
+            .expect("unique metric");
+        let tokens_per_second = HistogramVec::new(
+            HistogramOpts::new(
+                "tokens_per_second",
+                "Generation throughput (estimated tokens over total generation time)",
+            )
+            .buckets(vec![1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 80.0, 160.0, 320.0]),
+            &["model", "backend"],
+        )
+        .expect("valid metric");
+        registry
+            .register(Box::new(tokens_per_second.clone()))
+            .expect("unique metric");
+
+        let split_requests = counter_vec(
+            &registry,
+            "split_requests_total",
+            "Requests for a split model, by arm and result",
+            &["split", "model", "result"],
+        );
+        let split_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "split_request_duration_seconds",
+                "Time until the response (or stream) is ready, by split arm",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+            &["split", "model"],
+        )
+        .expect("valid metric");
+        registry
+            .register(Box::new(split_request_duration.clone()))
+            .expect("unique metric");
+
+        let http_requests = counter_vec(
+            &registry,
+            "http_requests_total",
+            "HTTP requests by endpoint",
+            &["endpoint"],
+        );
+        let http_errors = counter_vec(
+            &registry,
+            "http_errors_total",
+            "HTTP requests that failed, by endpoint",
+            &["endpoint"],
+        );
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time until the HTTP response (or stream) is ready",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+            &["endpoint"],
+        )
+        .expect("valid metric");
+        registry
+            .register(Box::new(http_request_duration.clone()))
+            .expect("unique metric");
+
+        Self {
+            registry,
+            started: Instant::now(),
+            recent_errors: Mutex::new(VecDeque::new()),
+            binary_downloads,
+            binary_download_bytes,
+            model_pulls,
+            model_evictions,
+            retries,
+            pool_dispatches,
+            affinity_lookups,
+            pool_queue_depth,
+            pool_processes,
+            pool_backend_processes,
+            concurrency_rejections,
+            tenant_waiting,
+            tenant_wait_duration,
+            process_spawns,
+            process_exits,
+            process_restarts,
+            process_recycles,
+            gpu_fallbacks,
+            completions,
+            context_overflows,
+            prompt_cache_lookups,
+            time_to_first_token,
+            tokens_per_second,
+            split_requests,
+            split_request_duration,
+            http_requests,
+            http_errors,
+            http_request_duration,
+        }
+    }
+
+    pub fn record_http_request(&self, endpoint: &str) {
+        self.http_requests.with_label_values(&[endpoint]).inc();
+    }
+
+    pub fn record_http_error(&self, endpoint: &str) {
+        self.http_errors.with_label_values(&[endpoint]).inc();
+        let mut recent = self.recent_errors.lock().unwrap();
+        recent.push_back(Instant::now());
+        Self::prune(&mut recent);
+    }
+
+    /// Record the outcome of a request served by one arm of a split
+    pub fn record_split(&self, split: &str, model: &str, success: bool, duration: Duration) {
+        let result = if success { "success" } else { "error" };
+        self.split_requests.with_label_values(&[split, model, result]).inc();
+        self.split_request_duration
+            .with_label_values(&[split, model])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Record the latency of a successful generation on `backend`
+    pub fn record_generation(&self, model: &str, backend: &str, timing: &GenerationTiming, tokens: u64) {
+        if let Some(ttft) = timing.time_to_first_token {
+            self.time_to_first_token
+                .with_label_values(&[model, backend])
+                .observe(ttft.as_secs_f64());
+        }
+        let secs = timing.total.as_secs_f64();
+        if tokens > 0 && secs > 0.0 {
+            self.tokens_per_second
+                .with_label_values(&[model, backend])
+                .observe(tokens as f64 / secs);
+        }
+    }
+
+    /// Number of HTTP errors recorded within the last [`RECENT_ERROR_WINDOW`]
+    pub fn recent_errors(&self) -> u64 {
+        let mut recent = self.recent_errors.lock().unwrap();
+        Self::prune(&mut recent);
+        recent.len() as u64
+    }
+
+    fn prune(recent: &mut VecDeque<Instant>) {
+        while let Some(at) = recent.front() {
+            if at.elapsed() > RECENT_ERROR_WINDOW {
+                recent.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            uptime_secs: self.started.elapsed().as_secs(),
+            http_requests_total: sum_counter(&self.http_requests, None),
+            http_errors_total: sum_counter(&self.http_errors, None),
+            recent_errors: self.recent_errors(),
+            recent_error_window_secs: RECENT_ERROR_WINDOW.as_secs(),
+            binary_downloads_total: sum_counter(&self.binary_downloads, Some(("result", "success"))),
+            model_pulls_total: sum_counter(&self.model_pulls, Some(("result", "success"))),
+            model_pull_failures_total: sum_counter(&self.model_pulls, Some(("result", "error"))),
+            process_spawns_total: sum_counter(&self.process_spawns, Some(("result", "success"))),
+            process_spawn_failures_total: sum_counter(&self.process_spawns, Some(("result", "error"))),
+            process_exits_total: sum_counter(&self.process_exits, None),
+            process_restarts_total: sum_counter(&self.process_restarts, None),
+            gpu_fallbacks_total: sum_counter(&self.gpu_fallbacks, None),
+            completions_total: sum_counter(&self.completions, None),
+        }
+    }
+
+    /// Render every registered metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
@@ -0,0 +1,176 @@
+//! Scripted stand-ins for tests (requires the `mock` feature)
+//!
+//! [`MockBackend`] implements [`InferenceBackend`] with canned responses and
+//! configurable latency, and records every prompt it receives.
+//! [`MockManager`] wraps one behind the completion methods of
+//! [`LitManager`](crate::LitManager), for code written against those.
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use futures::{FutureExt, StreamExt};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::backend::{InferenceBackend, TextStream};
+use crate::generation::{self, GenOptions};
+use crate::process;
+use crate::stream::{limit_tokens, stop_at};
+use crate::template::ChatTemplate;
+use crate::tokenizer::Estimate;
+
+/// A prompt the mock received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    pub model: String,
+    pub prompt: String,
+}
+
+#[derive(Debug, Clone)]
+enum Scripted {
+    Text(String),
+    Error(String),
+}
+
+/// Backend that replays scripted responses
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    script: Mutex<VecDeque<Scripted>>,
+    fallback: Option<String>,
+    first_token_delay: Duration,
+    token_delay: Duration,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+impl MockBackend {
+    /// A backend with nothing scripted; every completion fails until
+    /// responses are added
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer the next prompt with `text`; responses are used in order
+    pub fn respond(self, text: impl Into<String>) -> Self {
+        self.script.lock().unwrap().push_back(Scripted::Text(text.into()));
+        self
+    }
+
+    /// Fail the next prompt with `message`
+    pub fn fail(self, message: impl Into<String>) -> Self {
+        self.script.lock().unwrap().push_back(Scripted::Error(message.into()));
+        self
+    }
+
+    /// Answer with `text` once the script runs out
+    pub fn fallback(mut self, text: impl Into<String>) -> Self {
+        self.fallback = Some(text.into());
+        self
+    }
+
+    /// Wait this long before the first chunk
+    pub fn first_token_delay(mut self, delay: Duration) -> Self {
+        self.first_token_delay = delay;
+        self
+    }
+
+    /// Wait this long between chunks (one per word)
+    pub fn token_delay(mut self, delay: Duration) -> Self {
+        self.token_delay = delay;
+        self
+    }
+
+    /// Every prompt received so far, oldest first
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn next(&self, model: &str, prompt: &str) -> Result<String> {
+        self.calls.lock().unwrap().push(MockCall {
+            model: model.to_string(),
+            prompt: prompt.to_string(),
+        });
+        match self.script.lock().unwrap().pop_front() {
+            Some(Scripted::Text(text)) => Ok(text),
+            Some(Scripted::Error(message)) => Err(anyhow::anyhow!(message)),
+            None => self
+                .fallback
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("MockBackend has no response scripted for: {}", prompt)),
+        }
+    }
+}
+
+impl InferenceBackend for MockBackend {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        async move {
+            let text = self.next(model, prompt)?;
+            tokio::time::sleep(self.first_token_delay).await;
+
+            // One chunk per word, keeping the whitespace after it
+            let chunks: Vec<String> = text.split_inclusive(char::is_whitespace).map(str::to_string).collect();
+            let token_delay = self.token_delay;
+            let stream = futures::stream::iter(chunks.into_iter().enumerate()).then(move |(i, chunk)| async move {
+                if i > 0 {
+                    tokio::time::sleep(token_delay).await;
+                }
+                Ok(chunk)
+            });
+            Ok(stream.boxed())
+        }
+        .boxed()
+    }
+}
+
+/// The completion methods of [`LitManager`](crate::LitManager), answered by a
+/// [`MockBackend`]
+#[derive(Debug, Clone)]
+pub struct MockManager {
+    backend: Arc<MockBackend>,
+}
+
+impl MockManager {
+    pub fn new(backend: MockBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    pub fn backend(&self) -> &MockBackend {
+        &self.backend
+    }
+
+    pub async fn run_completion(&self, model: &str, prompt: &str) -> Result<String> {
+        self.backend.complete(model, prompt).await
+    }
+
+    /// Like [`LitManager::run_completion_with`](crate::LitManager::run_completion_with),
+    /// including constraint retries and best-of candidates
+    pub async fn run_completion_with(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<String> {
+        let mut candidates = generation::generate_best(prompt, ChatTemplate::Plain, options, |prompt| async move {
+            Ok((self.backend.complete(model, &prompt).await?, ()))
+        })
+        .await?;
+        Ok(candidates.swap_remove(0).0)
+    }
+
+    pub async fn run_completion_stream(&self, model: &str, prompt: &str) -> Result<TextStream> {
+        self.backend.complete_stream(model, prompt).await
+    }
+
+    /// Like [`LitManager::run_completion_stream_with`](crate::LitManager::run_completion_stream_with),
+    /// with `max_tokens` counted by estimate
+    pub async fn run_completion_stream_with(&self, model: &str, prompt: &str, options: &GenOptions) -> Result<TextStream> {
+        let deadline = tokio::time::Instant::now();
+        let stream = process::with_timeout(options.timeout, self.backend.complete_stream(model, prompt)).await?;
+        let stream = stop_at(stream, options.stop.clone());
+        let stream = limit_tokens(stream, options.max_tokens, Arc::new(Estimate));
+        let token = tokio_util::sync::CancellationToken::new();
+        Ok(process::stream_with_timeout(options.timeout, deadline, token, stream))
+    }
+}
+
+impl InferenceBackend for MockManager {
+    fn complete_stream<'a>(&'a self, model: &'a str, prompt: &'a str) -> BoxFuture<'a, Result<TextStream>> {
+        self.backend.complete_stream(model, prompt)
+    }
+}
@@ -0,0 +1,92 @@
+//! Content moderation with the local model
+//!
+//! Input is classified by prompting the model with a fixed instruction that
+//! asks for a JSON object of per-category scores, so moderation gates keep
+//! working without a network connection. Small models are rough classifiers:
+//! treat the scores as a first-pass filter, not a policy decision.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::constraints::Constraint;
+use crate::generation::GenOptions;
+
+/// Categories scored for every input, as named by the OpenAI moderation API
+pub const CATEGORIES: &[&str] = &[
+    "harassment",
+    "harassment/threatening",
+    "hate",
+    "hate/threatening",
+    "illicit",
+    "illicit/violent",
+    "self-harm",
+    "self-harm/intent",
+    "self-harm/instructions",
+    "sexual",
+    "sexual/minors",
+    "violence",
+    "violence/graphic",
+];
+
+/// Score at or above which a category is flagged
+pub const FLAG_THRESHOLD: f32 = 0.5;
+
+/// Classification of one input
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModerationResult {
+    /// Whether any category is flagged
+    pub flagged: bool,
+    pub categories: BTreeMap<String, bool>,
+    /// 0-1 per category
+    pub category_scores: BTreeMap<String, f32>,
+}
+
+/// The prompt that asks the model to score `input`
+pub fn classification_prompt(input: &str) -> String {
+    format!(
+        "You are a content moderation classifier. Rate how strongly the text below belongs to each \
+         category, from 0 (not at all) to 1 (clearly). Answer with only a JSON object mapping every \
+         category name to its score.\n\
+         Categories: {}\n\n\
+         Text:\n\"\"\"\n{}\n\"\"\"",
+        CATEGORIES.join(", "),
+        input.replace("\"\"\"", "\"\""),
+    )
+}
+
+/// Options for classification, which has to produce JSON
+pub fn classification_options() -> GenOptions {
+    GenOptions {
+        constraint: Some(Constraint::Json),
+        ..GenOptions::default()
+    }
+}
+
+/// Read the model's JSON scores; missing or non-numeric categories score 0
+/// and scores are clamped to 0-1
+pub fn parse_scores(output: &str) -> Result<ModerationResult> {
+    let json = Constraint::Json
+        .validate(output)
+        .map_err(|e| anyhow::anyhow!("Moderation output is {}", e))?;
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    let scores = value.as_object().context("Moderation output is not a JSON object")?;
+
+    let category_scores: BTreeMap<String, f32> = CATEGORIES
+        .iter()
+        .map(|category| {
+            let score = scores.get(*category).and_then(serde_json::Value::as_f64).unwrap_or(0.0);
+            (category.to_string(), (score as f32).clamp(0.0, 1.0))
+        })
+        .collect();
+    let categories: BTreeMap<String, bool> = category_scores
+        .iter()
+        .map(|(category, score)| (category.clone(), *score >= FLAG_THRESHOLD))
+        .collect();
+
+    Ok(ModerationResult {
+        flagged: categories.values().any(|flagged| *flagged),
+        categories,
+        category_scores,
+    })
+}
@@ -0,0 +1,111 @@
+//! Model packages for moving models between machines
+//!
+//! [`LitManager::export`](crate::LitManager::export) (`litert-lm cp`) copies a
+//! downloaded model into a directory, next to a `manifest.json` saying what
+//! it is. The directory can be carried to a host without network access and
+//! [`import`](crate::LitManager::import)ed there, which pulls the model file
+//! under the alias it had.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::history::unix_now;
+use crate::registry::ModelInfo;
+
+/// Name of the manifest in a package
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Name of the model file in a package
+pub const MODEL_FILE: &str = "model.litertlm";
+/// Version of the package layout; packages of a newer one are refused
+pub const PACKAGE_FORMAT: u32 = 1;
+
+/// What a package holds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub format: u32,
+    /// Name the model is imported as unless another is given
+    pub alias: String,
+    /// The model it is; see [`ModelInfo::name`]
+    pub name: String,
+    /// Size of the model file, checked on import
+    pub size_bytes: u64,
+    /// Unix timestamp (seconds) of the export
+    pub exported_at: u64,
+    /// litert-lm version that made the package
+    pub exported_by: String,
+}
+
+/// The model file at `path`: the file itself, or the one `.litertlm` file in
+/// a model directory
+pub fn model_file(path: &Path) -> Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    let files: Vec<PathBuf> = std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|file| file.extension().is_some_and(|ext| ext == "litertlm"))
+        .collect();
+    match files.as_slice() {
+        [file] => Ok(file.clone()),
+        [] => bail!("No .litertlm file in {}", path.display()),
+        _ => bail!("Several .litertlm files in {}", path.display()),
+    }
+}
+
+/// Copy `file`, the model described by `info`, into a package at `dest`
+pub async fn write(file: &Path, info: &ModelInfo, dest: &Path) -> Result<PackageManifest> {
+    if dest.join(MANIFEST_FILE).exists() {
+        bail!("{} already holds a package", dest.display());
+    }
+    tokio::fs::create_dir_all(dest)
+        .await
+        .with_context(|| format!("Failed to create {}", dest.display()))?;
+    let size_bytes = tokio::fs::copy(file, dest.join(MODEL_FILE))
+        .await
+        .with_context(|| format!("Failed to copy {} to {}", file.display(), dest.display()))?;
+    let manifest = PackageManifest {
+        format: PACKAGE_FORMAT,
+        alias: info.alias.clone(),
+        name: info.name.clone(),
+        size_bytes,
+        exported_at: unix_now(),
+        exported_by: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    // Written last, so a package with a manifest is complete
+    tokio::fs::write(dest.join(MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?).await?;
+    Ok(manifest)
+}
+
+/// The manifest and model file of the package at `package`, checked against
+/// each other
+pub fn read(package: &Path) -> Result<(PackageManifest, PathBuf)> {
+    let manifest_path = package.join(MANIFEST_FILE);
+    let manifest = std::fs::read(&manifest_path)
+        .with_context(|| format!("{} is not a model package: no {}", package.display(), MANIFEST_FILE))?;
+    let manifest: PackageManifest = serde_json::from_slice(&manifest)
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+    if manifest.format > PACKAGE_FORMAT {
+        bail!(
+            "{} is package format {}; this version of litert-lm reads up to {}",
+            package.display(),
+            manifest.format,
+            PACKAGE_FORMAT
+        );
+    }
+    let file = package.join(MODEL_FILE);
+    let size = std::fs::metadata(&file)
+        .with_context(|| format!("{} has no {}", package.display(), MODEL_FILE))?
+        .len();
+    if size != manifest.size_bytes {
+        bail!(
+            "{} is {} bytes, but the manifest says {}; the package is incomplete or damaged",
+            file.display(),
+            size,
+            manifest.size_bytes
+        );
+    }
+    Ok((manifest, file))
+}
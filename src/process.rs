@@ -1,12 +1,39 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use futures::StreamExt as _;
 use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::Stream;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::adapter;
+use crate::affinity::{Conversations, Placement};
+use crate::backend::Backend;
+use crate::breaker::{CircuitBreaker, CircuitState};
+use crate::cancel;
+use crate::concurrency::QueueFull;
+use crate::context::Turn;
+use crate::crash::{self, CrashReport, StderrTail};
+use crate::error::LitError;
+use crate::keep_alive::KeepAlive;
+use crate::limits::Limits;
+use crate::metrics::Metrics;
+use crate::registry;
+use crate::sandbox::Sandbox;
+use crate::scheduler::{Admission, FairScheduler, Rejected};
+use crate::stream::limit_tokens;
+use crate::telemetry;
+use crate::tenant::Tenant;
+use crate::throttle::TokenBucket;
+use crate::tokenizer::{Estimate, Tokenizer};
 
 // Command sent to the process's internal loop
 enum ProcessCommand {
@@ -14,21 +41,524 @@ enum ProcessCommand {
         prompt: String,
         // Send tokens back on this channel
         response_tx: mpsc::Sender<Result<String>>,
+        // Span of the caller; the generation span is created as its child
+        parent: tracing::Span,
+        // Covers the time spent queued behind other commands
+        queue_span: tracing::Span,
+        // Cancelled once nobody reads the reply (e.g. the client disconnected)
+        cancel: CancellationToken,
+        // For a prompt continuing a conversation lit holds: the completions
+        // the process has to have served when the prompt's turn comes, and
+        // the whole conversation, sent instead once something else ran
+        resume: Option<(u64, String)>,
     },
 }
 
+// How a generation ended, as seen by the process loop
+struct Outcome {
+    succeeded: bool,
+    // lit was interrupted because the reply was no longer wanted
+    interrupted: bool,
+    // lit printed nothing for the stall timeout
+    stalled: bool,
+    timing: GenerationTiming,
+    tokens: u64,
+}
+
+/// Ask lit to stop generating, as Ctrl-C would; it then prints its prompt
+/// marker and waits for the next prompt
+#[cfg(unix)]
+fn interrupt(pid: u32) {
+    // SAFETY: plain kill call; the child isn't reaped while its loop runs,
+    // so the pid can't have been reused
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) } != 0 {
+        tracing::debug!(pid, error = %std::io::Error::last_os_error(), "Failed to interrupt lit");
+    }
+}
+
+#[cfg(not(unix))]
+fn interrupt(_pid: u32) {}
+
+// How a pool's processes are started, besides the model and backend
+#[derive(Debug, Clone, Default)]
+struct Launch {
+    // Auto tries the GPU, then the CPU
+    backend: Backend,
+    crash_dir: Option<PathBuf>,
+    adapter: Option<PathBuf>,
+    args: Vec<String>,
+    // Told about crashes and successful generations
+    breaker: Option<Arc<CircuitBreaker>>,
+    sandbox: Option<Arc<Sandbox>>,
+    limits: Option<Arc<Limits>>,
+    // Longest a generation may take before the process is killed
+    timeout: Option<Duration>,
+    // Longest lit may go without printing during a generation
+    stall_timeout: Option<Duration>,
+    // Completions a process serves before the pool replaces it
+    max_requests: Option<u64>,
+    // Wakes the supervisor when a process has served `max_requests`, timed
+    // out, or quit on an interrupt
+    wake: Arc<Notify>,
+    // Counts generated tokens for metrics and the throttle; the estimate
+    // without one
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+}
+
+/// Whether lit has no `model` downloaded, going by `lit list`; `None` when
+/// that can't be told. Asked once a process fails to load, as lit's own
+/// messages for a missing model aren't stable
+async fn model_missing(binary_path: &Path, model: &str) -> Option<bool> {
+    let listing = Command::new(binary_path).arg("list").kill_on_drop(true).output();
+    let output = match tokio::time::timeout(LIST_TIMEOUT, listing).await {
+        Ok(Ok(output)) if output.status.success() => output,
+        _ => return None,
+    };
+    let downloaded = registry::parse_listing(&String::from_utf8_lossy(&output.stdout));
+    Some(!downloaded.iter().any(|entry| entry.name == model))
+}
+
+/// [`LitError::ProcessCrashed`] with lit's last stderr lines; stderr is read
+/// by a task of its own, so it gets a moment to catch up with lit's last words
+async fn died(tail: &StderrTail) -> LitError {
+    tokio::time::sleep(STDERR_SETTLE).await;
+    LitError::ProcessCrashed { stderr: tail.last(ERROR_STDERR_LINES) }
+}
+
+// Resolves once lit has printed nothing for `PROMPT_SETTLE` after what looks
+// like its prompt, or never
+async fn prompt_settled(at_prompt: bool) {
+    match at_prompt {
+        true => tokio::time::sleep(PROMPT_SETTLE).await,
+        false => std::future::pending().await,
+    }
+}
+
+// Resolves once `after` has passed, or never
+async fn stall(after: Option<Duration>) {
+    match after {
+        Some(after) => tokio::time::sleep(after).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Run `future`, failing with [`LitError::Timeout`] if it takes longer than `timeout`
+pub async fn with_timeout<T>(timeout: Option<Duration>, future: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    match timeout {
+        Some(after) => tokio::time::timeout(after, future)
+            .await
+            .unwrap_or_else(|_| Err(LitError::Timeout { after }.into())),
+        None => future.await,
+    }
+}
+
+/// Pass `stream` through until `timeout` has passed since `started`, then
+/// cancel `token`, which interrupts lit, and end the stream with
+/// [`LitError::Timeout`]
+pub fn stream_with_timeout<T: Send + 'static>(
+    timeout: Option<Duration>,
+    started: tokio::time::Instant,
+    token: CancellationToken,
+    stream: impl Stream<Item = Result<T>> + Send + 'static,
+) -> futures::stream::BoxStream<'static, Result<T>> {
+    let Some(after) = timeout else {
+        return stream.boxed();
+    };
+    futures::stream::unfold(Some(stream.boxed()), move |stream| {
+        let token = token.clone();
+        async move {
+            let mut stream = stream?;
+            tokio::select! {
+                biased;
+                _ = tokio::time::sleep_until(started + after) => {
+                    token.cancel();
+                    Some((Err(LitError::Timeout { after }.into()), None))
+                }
+                item = stream.next() => item.map(|item| (item, Some(stream))),
+            }
+        }
+    })
+    // Callers may keep polling after the end (e.g. to send closing events)
+    .fuse()
+    .boxed()
+}
+
+/// The text of `stream` up to `max_tokens`, timed from `started`
+pub(crate) async fn collect_reply(
+    started: Instant,
+    stream: impl Stream<Item = Result<String>> + Send + 'static,
+    max_tokens: Option<usize>,
+    tokenizer: Arc<dyn Tokenizer>,
+) -> Result<(String, GenerationTiming)> {
+    let mut stream = limit_tokens(stream, max_tokens, tokenizer);
+    let mut response = String::new();
+    let mut timing = GenerationTiming::default();
+
+    while let Some(result) = stream.next().await {
+        let chunk = result?;
+        timing.time_to_first_token.get_or_insert_with(|| started.elapsed());
+        response.push_str(&chunk);
+    }
+
+    timing.total = started.elapsed();
+    Ok((response, timing))
+}
+
+/// Timing of a single generation, measured from when the prompt was submitted
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenerationTiming {
+    pub time_to_first_token: Option<Duration>,
+    pub total: Duration,
+}
+
+/// lit's prompt, printed once a model is loaded and after every reply
+const PROMPT_MARKER: &str = ">>>";
+
+/// How long lit must print nothing after what looks like its prompt for it to
+/// be the prompt; a reply line that starts with `>>>` goes on within this
+const PROMPT_SETTLE: Duration = Duration::from_millis(100);
+
+/// Text read from lit, and whether its prompt came after it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub text: String,
+    /// lit printed its prompt: the reply is complete
+    pub end: bool,
+}
+
+/// Splits lit's stdout into replies
+///
+/// A reply ends with lit's `>>>` prompt, which starts a line and is the last
+/// thing printed before lit waits for input; the newline before it isn't part
+/// of the reply. A `>>>` inside a line, or followed by more text on its line
+/// (a Python session in a reply, say), is reply text. ANSI escape sequences
+/// (colored prompts) and carriage returns are dropped, and UTF-8 characters
+/// split across reads are put back together. Text that may still turn out to
+/// be the prompt is held back until the next read tells; a line that is just
+/// the prompt so far is only the prompt once lit goes quiet after it (see
+/// [`at_prompt`](Self::at_prompt) and [`settle`](Self::settle)).
+#[derive(Debug)]
+pub struct Framer {
+    // Bytes of a UTF-8 character split across reads
+    partial: Vec<u8>,
+    escape: Escape,
+    // At the start of a line, where the prompt can appear
+    line_start: bool,
+    // A newline held back in case the prompt follows
+    newline: bool,
+    // The start of a line that may be the prompt (`>`, `>>`, `>>> `, ...)
+    candidate: String,
+}
+
+// Where the framer is in an ANSI escape sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    // After ESC
+    Start,
+    // `ESC [`, up to a final byte in `@`..=`~`
+    Csi,
+    // `ESC ]`, up to BEL or `ESC \`
+    Osc,
+    OscEsc,
+}
+
+impl Default for Framer {
+    fn default() -> Self {
+        Self {
+            partial: Vec::new(),
+            escape: Escape::None,
+            line_start: true,
+            newline: false,
+            candidate: String::new(),
+        }
+    }
+}
+
+impl Framer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget anything held back, e.g. before writing the next prompt
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Frame the next bytes read from lit; text held back from earlier reads
+    /// is released once it is known not to be the prompt. The reply never
+    /// ends here: see [`settle`](Self::settle).
+    pub fn push(&mut self, bytes: &[u8]) -> Frame {
+        self.partial.extend_from_slice(bytes);
+        let mut decoded = String::new();
+        let mut rest = &self.partial[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    decoded.push_str(valid);
+                    rest = &[];
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    decoded.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(invalid) => {
+                            decoded.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[invalid..];
+                        }
+                        // Incomplete character; the rest comes with the next read
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.partial = rest.to_vec();
+
+        let mut text = String::new();
+        for c in decoded.chars() {
+            if self.skip_escape(c) || c == '\r' {
+                continue;
+            }
+            if self.line_start {
+                let marker = self.candidate.len() < PROMPT_MARKER.len() && c == '>';
+                let padding = self.candidate.len() >= PROMPT_MARKER.len() && c == ' ';
+                if marker || padding {
+                    self.candidate.push(c);
+                    continue;
+                }
+                // Not the prompt after all
+                self.release(&mut text);
+            }
+            if c == '\n' {
+                if self.newline {
+                    text.push('\n');
+                }
+                self.newline = true;
+                self.line_start = true;
+            } else {
+                text.push(c);
+            }
+        }
+
+        Frame { text, end: false }
+    }
+
+    /// Whether what lit printed so far stops at what looks like its prompt,
+    /// alone on its line
+    pub fn at_prompt(&self) -> bool {
+        let settled = self.partial.is_empty() && self.escape == Escape::None;
+        settled && self.line_start && self.candidate.trim_end() == PROMPT_MARKER
+    }
+
+    /// lit printed nothing more for a moment: when that was after its prompt,
+    /// the reply is complete
+    pub fn settle(&mut self) -> Frame {
+        if !self.at_prompt() {
+            return Frame::default();
+        }
+        self.reset();
+        Frame { text: String::new(), end: true }
+    }
+
+    // Whether `c` belongs to an escape sequence
+    fn skip_escape(&mut self, c: char) -> bool {
+        self.escape = match (self.escape, c) {
+            (Escape::None, '\x1b') => Escape::Start,
+            (Escape::None, _) => return false,
+            (Escape::Start, '[') => Escape::Csi,
+            (Escape::Start, ']') => Escape::Osc,
+            (Escape::Start, _) => Escape::None,
+            (Escape::Csi, '@'..='~') => Escape::None,
+            (Escape::Csi, _) => Escape::Csi,
+            (Escape::Osc, '\x07') => Escape::None,
+            (Escape::Osc, '\x1b') => Escape::OscEsc,
+            (Escape::Osc, _) => Escape::Osc,
+            (Escape::OscEsc, '\\') => Escape::None,
+            (Escape::OscEsc, _) => Escape::Osc,
+        };
+        true
+    }
+
+    // Give back the held newline and line start as reply text
+    fn release(&mut self, text: &mut String) {
+        if self.newline {
+            text.push('\n');
+            self.newline = false;
+        }
+        text.push_str(&self.candidate);
+        self.candidate.clear();
+        self.line_start = false;
+    }
+}
+
+/// Prompt of the generation that warms up a pool
+const WARM_UP_PROMPT: &str = "user: Hi";
+
+/// Processes a pool starts at once while initializing
+const MAX_PARALLEL_SPAWNS: usize = 4;
+
+/// A generation running longer than this is reported as stuck
+pub const STUCK_AFTER: Duration = Duration::from_secs(120);
+
+/// How often a pool looks for processes that died
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wait before respawning a dead process, doubled after every respawn that
+/// dies again within [`MAX_RESPAWN_BACKOFF`], up to that
+const MIN_RESPAWN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESPAWN_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often a process taken out of its pool is checked for prompts it still
+/// has to answer before it is stopped
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a process taken out of its pool gets to answer those prompts
+/// before it is killed
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long lit may take to load the model
+const INIT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// stderr lines quoted in a [`LitError::ProcessCrashed`] error
+const ERROR_STDERR_LINES: usize = 5;
+
+/// How long a process that died is given to finish writing to stderr
+const STDERR_SETTLE: Duration = Duration::from_millis(50);
+
+/// How long `lit list` may take when checking why a process failed to load
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// What the process loop is doing right now, and since when
+#[derive(Debug, Clone, Copy)]
+struct Activity {
+    state: &'static str,
+    since: Instant,
+}
+
+impl Activity {
+    fn new(state: &'static str) -> Self {
+        Self { state, since: Instant::now() }
+    }
+}
+
+/// Snapshot of one pool worker, as reported by `/debug/tasks`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessDebugInfo {
+    pub index: usize,
+    pub pid: Option<u32>,
+    pub backend: String,
+    /// Why the GPU backend failed, if the process fell back to CPU
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu_fallback: Option<String>,
+    /// `idle`, `generating`, or `exited`
+    pub state: &'static str,
+    pub state_ms: u64,
+    /// Commands queued on or running in the process
+    pub pending: usize,
+    /// Completions served since lit started
+    pub served: u64,
+    /// Generating for longer than [`STUCK_AFTER`], or exited with commands pending
+    pub stuck: bool,
+}
+
+/// One process of a pool and what lit last wrote to stderr, as reported by
+/// [`ProcessPool::diagnostics`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessDiagnostics {
+    pub index: usize,
+    pub pid: Option<u32>,
+    pub backend: String,
+    /// `idle`, `generating`, or `exited`
+    pub state: &'static str,
+    /// lit's most recent stderr lines, oldest first
+    pub stderr: Vec<String>,
+}
+
+/// One process of a pool, as reported by [`ProcessPool::stats`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStats {
+    pub index: usize,
+    pub pid: Option<u32>,
+    /// Backend the process runs on (`gpu`, `cpu`, or `npu`)
+    pub backend: String,
+    /// Generating a reply right now
+    pub busy: bool,
+    /// `idle`, `generating`, or `exited`
+    pub state: &'static str,
+    /// Prompts queued on or running in the process
+    pub pending: usize,
+    /// Completions served since lit started
+    pub served: u64,
+    /// Duration of the last successful generation
+    pub last_latency_ms: Option<u64>,
+    /// Time to first token of the last successful generation
+    pub last_ttft_ms: Option<u64>,
+    /// Seconds since lit was started
+    pub uptime_secs: u64,
+}
+
+/// A pool's processes and its queue, as reported by [`ProcessPool::stats`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    pub model: String,
+    /// Extra lit flags the processes were started with
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Backend the pool starts its processes on
+    pub backend: Backend,
+    pub processes: Vec<ProcessStats>,
+    /// Requests admitted to the pool, generating or about to
+    pub running: usize,
+    /// Requests waiting to be admitted
+    pub waiting: usize,
+    /// Most requests that may wait; unset when unlimited
+    pub max_queue: Option<usize>,
+    /// Prompts queued on or running in the processes
+    pub queue_depth: usize,
+    /// Seconds since the pool last had a request; unset while it has some
+    pub idle_secs: Option<u64>,
+}
+
 pub struct LitProcess {
     // Kept to send commands *to* the process
     command_tx: mpsc::Sender<ProcessCommand>,
-    // Kept for cleanup/shutdown, but not directly accessed in normal flow
-    #[allow(dead_code)]
-    child_handle: tokio::task::JoinHandle<()>,
+    // Number of commands sent to the process that have not finished yet
+    pending: Arc<AtomicUsize>,
+    // Number of completions the process loop has run
+    served: Arc<AtomicU64>,
+    // Timing of the last successful generation, set by the process loop
+    last_timing: Arc<Mutex<Option<GenerationTiming>>>,
+    // When lit was started
+    started: Instant,
+    // The end of what lit wrote to stderr
+    stderr: StderrTail,
+    // Updated by the process loop, read by debug_info()
+    activity: Arc<Mutex<Activity>>,
+    pid: Option<u32>,
+    backend: String,
+    // Why the GPU backend failed, when this process runs on CPU instead
+    gpu_fallback: Option<String>,
+    // Paces the text handed out, shared with every other process
+    throttle: Option<Arc<TokenBucket>>,
+    // Counts the text handed out, for the throttle
+    tokenizer: Arc<dyn Tokenizer>,
+    // Asks the process loop to stop once it is idle; see `shutdown`
+    stop: CancellationToken,
+    // Finishes once the process loop has stopped lit
+    child_handle: tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
 
 impl std::fmt::Debug for LitProcess {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LitProcess")
             .field("command_tx", &"<mpsc::Sender>")
+            .field("pending", &self.pending.load(Ordering::Relaxed))
+            .field("backend", &self.backend)
+            .field("gpu_fallback", &self.gpu_fallback)
             .field("child_handle", &"<JoinHandle>")
             .finish()
     }
@@ -36,37 +566,145 @@ impl std::fmt::Debug for LitProcess {
 
 impl LitProcess {
     pub async fn spawn(binary_path: PathBuf, model: String) -> Result<Self> {
-        // Try GPU first, fall back to CPU if it fails
-        match Self::spawn_with_backend(binary_path.clone(), model.clone(), "gpu").await {
+        Self::spawn_with_metrics(binary_path, model, Arc::new(Metrics::new())).await
+    }
+
+    /// Limit how fast generated text is handed out, e.g. with a bucket shared by
+    /// every process on the machine
+    pub fn with_throttle(mut self, throttle: Arc<TokenBucket>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Spawn a process that records its lifecycle into a shared metrics registry
+    pub async fn spawn_with_metrics(
+        binary_path: PathBuf,
+        model: String,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self> {
+        Self::spawn_inner(binary_path, model, metrics, Launch::default()).await
+    }
+
+    async fn spawn_inner(binary_path: PathBuf, model: String, metrics: Arc<Metrics>, launch: Launch) -> Result<Self> {
+        if launch.backend != Backend::Auto {
+            let backend = launch.backend.as_str();
+            return Self::spawn_with_backend(binary_path, model, backend, metrics, launch).await;
+        }
+        // Try GPU first, fall back to CPU if it fails to load the model
+        match Self::spawn_with_backend(binary_path.clone(), model.clone(), "gpu", metrics.clone(), launch.clone()).await {
             Ok(process) => Ok(process),
+            // Not the GPU's fault; the CPU won't find it either
+            Err(e) if matches!(LitError::of(&e), Some(LitError::ModelNotFound { .. })) => Err(e),
             Err(e) => {
-                tracing::warn!("GPU backend failed: {}. Trying CPU backend...", e);
-                Self::spawn_with_backend(binary_path, model, "cpu").await
+                tracing::warn!(model = %model, error = %e, "GPU backend failed. Trying CPU backend...");
+                let mut process =
+                    Self::spawn_with_backend(binary_path, model.clone(), "cpu", metrics.clone(), launch).await?;
+                // Generation still works, just far slower, so make the fallback visible
+                tracing::warn!(
+                    model = %model,
+                    reason = %e,
+                    "lit process fell back from GPU to CPU; expect much slower generation"
+                );
+                metrics.gpu_fallbacks.with_label_values(&[model.as_str()]).inc();
+                process.gpu_fallback = Some(e.to_string());
+                Ok(process)
             }
         }
     }
 
-    async fn spawn_with_backend(binary_path: PathBuf, model: String, backend: &str) -> Result<Self> {
+    #[tracing::instrument(name = "process.spawn", skip(binary_path, metrics, launch), fields(args = ?launch.args))]
+    async fn spawn_with_backend(
+        binary_path: PathBuf,
+        model: String,
+        backend: &str,
+        metrics: Arc<Metrics>,
+        launch: Launch,
+    ) -> Result<Self> {
+        let result = Self::start_with_backend(binary_path, model.clone(), backend, metrics.clone(), launch).await;
+        let label = if result.is_ok() { "success" } else { "error" };
+        metrics
+            .process_spawns
+            .with_label_values(&[model.as_str(), backend, label])
+            .inc();
+        result
+    }
+
+    async fn start_with_backend(
+        binary_path: PathBuf,
+        model: String,
+        backend: &str,
+        metrics: Arc<Metrics>,
+        launch: Launch,
+    ) -> Result<Self> {
         tracing::info!("Attempting to spawn lit process with backend={}", backend);
+        let Launch {
+            backend: _,
+            crash_dir,
+            adapter,
+            args,
+            breaker,
+            sandbox,
+            limits,
+            timeout,
+            stall_timeout,
+            max_requests,
+            wake,
+            tokenizer,
+        } = launch;
+        let tokenizer = tokenizer.unwrap_or_else(|| Arc::new(Estimate));
 
-        let mut child = Command::new(&binary_path)
-            .arg("run")
-            .arg(&model)
+        // `<base>:<name>` runs the base model with the adapter loaded
+        let lit_model = match &adapter {
+            Some(_) => adapter::split_model(&model).map_or(model.as_str(), |(base, _)| base),
+            None => model.as_str(),
+        }
+        .to_string();
+        let mut command = Command::new(&binary_path);
+        command.arg("run").arg(&lit_model);
+        if let Some(path) = &adapter {
+            command.arg(adapter::LIT_FLAG).arg(path);
+        }
+        if let Some(sandbox) = &sandbox {
+            sandbox.apply(&mut command);
+        }
+        let cgroup = limits.as_deref().map(|limits| limits.apply(&mut command)).transpose()?.flatten();
+        let mut child = command
             .arg("--backend")
             .arg(backend)
+            .args(&args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            // Don't leave lit running if the runtime goes away without a shutdown
+            .kill_on_drop(true)
             .spawn()
             .with_context(|| format!("Failed to spawn lit process with backend={}", backend))?;
 
+        let pid = child.id();
         let mut stdin = child.stdin.take().context("Failed to get stdin")?;
-        let stdout = child.stdout.take().context("Failed to get stdout")?;
+        let mut stdout = child.stdout.take().context("Failed to get stdout")?;
         let mut stderr = child.stderr.take().context("Failed to get stderr")?;
 
         let (command_tx, mut command_rx) = mpsc::channel::<ProcessCommand>(32);
+        let pending = Arc::new(AtomicUsize::new(0));
+        let loop_pending = pending.clone();
+        let served = Arc::new(AtomicU64::new(0));
+        let loop_served = served.clone();
+        let last_timing = Arc::new(Mutex::new(None));
+        let loop_last_timing = last_timing.clone();
+        let loop_model = model.clone();
+        let loop_tokenizer = tokenizer.clone();
+        let activity = Arc::new(Mutex::new(Activity::new("idle")));
+        let loop_activity = activity.clone();
+        let set_activity = move |state| *loop_activity.lock().unwrap() = Activity::new(state);
+        let loop_backend = backend.to_string();
+        let stop = CancellationToken::new();
+        let loop_stop = stop.clone();
 
-        // Spawn a task to log stderr
+        // Spawn a task to log stderr, keeping the tail for crash reports
+        let stderr_tail = StderrTail::default();
+        let loop_stderr_tail = stderr_tail.clone();
+        let process_stderr_tail = stderr_tail.clone();
         tokio::spawn(async move {
             use tokio::io::AsyncReadExt;
             let mut buf = [0u8; 1024];
@@ -76,254 +714,640 @@ impl LitProcess {
                 }
                 let msg = String::from_utf8_lossy(&buf[..n]);
                 tracing::debug!("lit stderr: {}", msg.trim());
+                stderr_tail.push(&msg);
             }
         });
 
-        // Spawn the long-running task that owns the process
-        let child_handle = tokio::spawn(async move {
+        // Wait for the model to load - look for the prompt marker ">>>"
+        tracing::info!("Waiting for model to load...");
+        let mut framer = Framer::new();
+        let mut temp_buf = [0u8; 1024];
+        let init_result = tokio::time::timeout(INIT_TIMEOUT, async {
             use tokio::io::AsyncReadExt;
 
-            let mut stdout = stdout;
-            let mut buffer = Vec::new();
-            let mut temp_buf = [0u8; 1024];
-            let mut pending_commands = Vec::new();
-
-            // Wait for model to load - look for the prompt marker ">>>"
-            tracing::info!("Waiting for model to load...");
-            let init_timeout = tokio::time::Duration::from_secs(120); // 2 minute timeout
-            let init_result = tokio::time::timeout(init_timeout, async {
-                loop {
-                    tokio::select! {
-                        // Check for incoming commands while initializing - buffer them
-                        cmd = command_rx.recv() => {
-                            if let Some(cmd) = cmd {
-                                tracing::debug!("Buffering command during initialization");
-                                pending_commands.push(cmd);
+            let mut loading = String::new();
+            loop {
+                tokio::select! {
+                    result = stdout.read(&mut temp_buf) => {
+                        match result {
+                            Ok(0) => {
+                                tracing::error!("Process stdout closed before model loaded");
+                                return Err(died(&loop_stderr_tail).await.into());
                             }
-                        }
-                        // Read from stdout
-                        result = stdout.read(&mut temp_buf) => {
-                            match result {
-                                Ok(0) => {
-                                    tracing::error!("Process stdout closed before model loaded");
-                                    return Err(anyhow::anyhow!("Process died during initialization"));
-                                }
-                                Ok(n) => {
-                                    buffer.extend_from_slice(&temp_buf[..n]);
-                                    let text = String::from_utf8_lossy(&buffer);
-
-                                    // Check for error messages
-                                    if text.contains("Error") || text.contains("error") || text.contains("failed") {
-                                        tracing::error!("Initialization error: {}", text);
-                                        return Err(anyhow::anyhow!("Process initialization failed: {}", text.trim()));
-                                    }
-
-                                    // Check if model is loaded
-                                    if text.contains("Model '") && text.contains("' loaded.") {
-                                        tracing::info!("Model loaded successfully");
-                                    }
-
-                                    // Wait for the initial prompt marker
-                                    if text.contains(">>>") {
-                                        tracing::info!("Process ready to accept prompts");
-                                        buffer.clear();
-                                        return Ok(());
-                                    }
+                            Ok(n) => {
+                                let frame = framer.push(&temp_buf[..n]);
+                                loading.push_str(&frame.text);
+                                let text = &loading;
+
+                                // Check for error messages
+                                if text.contains("Error") || text.contains("error") || text.contains("failed") {
+                                    tracing::error!("Initialization error: {}", text);
+                                    return Err(anyhow::anyhow!("Process initialization failed: {}", text.trim()));
                                 }
-                                Err(e) => {
-                                    tracing::error!("Error reading process output during init: {}", e);
-                                    return Err(e.into());
+
+                                // Check if model is loaded
+                                if text.contains("Model '") && text.contains("' loaded.") {
+                                    tracing::info!("Model loaded successfully");
                                 }
                             }
+                            Err(e) => {
+                                tracing::error!("Error reading process output during init: {}", e);
+                                return Err(e.into());
+                            }
                         }
                     }
+                    // Wait for the initial prompt marker
+                    _ = prompt_settled(framer.at_prompt()) => {
+                        framer.settle();
+                        tracing::info!("Process ready to accept prompts");
+                        return Ok(());
+                    }
                 }
-            }).await;
+            }
+        })
+        .await;
+
+        let (error, reason) = match init_result {
+            Ok(Ok(())) => (None, ""),
+            Ok(Err(e)) => {
+                tracing::error!("Initialization failed: {}", e);
+                let e = match model_missing(&binary_path, &lit_model).await {
+                    Some(true) => LitError::ModelNotFound { model: model.clone() }.into(),
+                    _ => e,
+                };
+                telemetry::record_error(&model, "init");
+                (Some(e), "init_failed")
+            }
+            Err(_) => {
+                tracing::error!("Initialization timed out after 2 minutes");
+                telemetry::record_error(&model, "init_timeout");
+                (Some(anyhow::anyhow!("Process initialization timed out")), "init_timeout")
+            }
+        };
+        if let Some(e) = error {
+            let _ = child.kill().await;
+            metrics.process_exits.with_label_values(&[model.as_str(), reason]).inc();
+            if let Some(breaker) = &breaker {
+                breaker.record_crash();
+            }
+            return Err(e);
+        }
+
+        // Spawn the long-running task that owns the process
+        let child_handle = tokio::spawn(async move {
+            // Removed once the task ends, after lit was reaped
+            let _cgroup = cgroup;
 
-            match init_result {
-                Ok(Ok(())) => {
-                    tracing::info!("Model initialization complete, processing {} buffered commands", pending_commands.len());
+            // A child that exits on its own (while idle or mid-generation) is
+            // a crash
+            let crash = loop {
+                set_activity("idle");
+                let mut cmd = tokio::select! {
+                    cmd = command_rx.recv() => match cmd {
+                        Some(cmd) => cmd,
+                        None => break None,
+                    },
+                    status = child.wait() => break Some((status.ok(), None, "crashed")),
+                    _ = loop_stop.cancelled() => break None,
+                };
+
+                // Decided here, as prompts are only ever taken in order here
+                let ProcessCommand::Run { prompt, resume, .. } = &mut cmd;
+                if let Some((served, conversation)) = resume.take() {
+                    if loop_served.load(Ordering::Relaxed) != served {
+                        tracing::debug!(model = %loop_model, "Another prompt ran first; replaying the conversation");
+                        *prompt = conversation;
+                    }
+                }
+                let ProcessCommand::Run { prompt, response_tx, cancel, .. } = &cmd;
+                // Nobody waits for it anymore; don't start it
+                if cancel.is_cancelled() {
+                    tracing::debug!(model = %loop_model, "Skipping prompt cancelled while queued");
+                    metrics.completions.with_label_values(&[loop_model.as_str(), "cancelled"]).inc();
+                    loop_pending.fetch_sub(1, Ordering::Relaxed);
+                    continue;
                 }
-                Ok(Err(e)) => {
-                    tracing::error!("Initialization failed: {}", e);
-                    // Drain buffered commands with error
-                    for cmd in pending_commands {
-                        let ProcessCommand::Run { response_tx, .. } = cmd;
-                        let _ = response_tx.send(Err(anyhow::anyhow!("Process initialization failed: {}", e))).await;
+                let in_flight = crash::prompt_hash(prompt);
+                let timeout_tx = response_tx.clone();
+                set_activity("generating");
+                let handled = Self::handle_command(&loop_model, &metrics, &*loop_tokenizer, pid, stall_timeout, &loop_stderr_tail, cmd, &mut stdin, &mut stdout, &mut framer, &mut temp_buf);
+                let handled = match timeout {
+                    Some(after) => tokio::time::timeout(after, handled).await.map_err(|_| after),
+                    None => Ok(handled.await),
+                };
+                let outcome = match handled {
+                    Ok(handled) => handled,
+                    Err(after) => {
+                        // lit is wedged; its reply can't be told apart from the
+                        // next one anymore, so the pool respawns this process
+                        tracing::error!(model = %loop_model, timeout_secs = after.as_secs(), "Generation timed out; killing the process");
+                        telemetry::record_error(&loop_model, "timeout");
+                        metrics.completions.with_label_values(&[loop_model.as_str(), "timeout"]).inc();
+                        let _ = timeout_tx.send(Err(LitError::Timeout { after }.into())).await;
+                        loop_pending.fetch_sub(1, Ordering::Relaxed);
+                        let _ = child.kill().await;
+                        break Some((child.wait().await.ok(), Some(in_flight), "timed_out"));
                     }
+                };
+                drop(timeout_tx);
+                if outcome.succeeded && !outcome.interrupted {
+                    *loop_last_timing.lock().unwrap() = Some(outcome.timing);
+                    metrics.record_generation(&loop_model, &loop_backend, &outcome.timing, outcome.tokens);
+                    if let Some(breaker) = &breaker {
+                        breaker.record_success();
+                    }
+                }
+                loop_pending.fetch_sub(1, Ordering::Relaxed);
+                let count = loop_served.fetch_add(1, Ordering::Relaxed) + 1;
+                if max_requests == Some(count) {
+                    wake.notify_one();
+                }
+
+                // A wedged lit can't be resynchronized; the pool respawns it
+                if outcome.stalled {
                     let _ = child.kill().await;
-                    return;
+                    break Some((child.wait().await.ok(), Some(in_flight), "stalled"));
                 }
-                Err(_) => {
-                    tracing::error!("Initialization timed out after 2 minutes");
-                    for cmd in pending_commands {
-                        let ProcessCommand::Run { response_tx, .. } = cmd;
-                        let _ = response_tx.send(Err(anyhow::anyhow!("Process initialization timed out"))).await;
+                if !outcome.succeeded {
+                    // Give the child a moment to be reaped after closing its pipes
+                    if let Ok(status) = tokio::time::timeout(Duration::from_secs(2), child.wait()).await {
+                        // A lit that quits on the interrupt instead of returning to its
+                        // prompt didn't crash, but the pool needs a new process
+                        if outcome.interrupted {
+                            break Some((status.ok(), Some(in_flight), "interrupted"));
+                        }
+                        break Some((status.ok(), Some(in_flight), "crashed"));
                     }
-                    let _ = child.kill().await;
-                    return;
                 }
-            }
+            };
 
-            // Process any buffered commands first
-            for cmd in pending_commands {
-                Self::handle_command(cmd, &mut stdin, &mut stdout, &mut buffer, &mut temp_buf).await;
-            }
+            set_activity("exited");
+            match crash {
+                Some((status, in_flight, reason)) => {
+                    // Killed or quit on purpose rather than crashed: respawned
+                    // right away instead of at the next health check
+                    if matches!(reason, "timed_out" | "interrupted") {
+                        wake.notify_one();
+                    }
+                    let crashed = reason != "interrupted";
+                    if crashed {
+                        CrashReport::new(&loop_model, &loop_backend, pid, status, in_flight, loop_stderr_tail.snapshot())
+                            .emit(crash_dir.as_deref());
+                        telemetry::record_error(&loop_model, "crash");
+                        if let Some(breaker) = &breaker {
+                            breaker.record_crash();
+                        }
+                    }
+                    metrics.process_exits.with_label_values(&[loop_model.as_str(), reason]).inc();
 
-            // Now handle commands
-            while let Some(cmd) = command_rx.recv().await {
-                Self::handle_command(cmd, &mut stdin, &mut stdout, &mut buffer, &mut temp_buf).await;
-            }
+                    // Fail anything still queued instead of dropping it silently
+                    command_rx.close();
+                    let crash_error = LitError::ProcessCrashed { stderr: loop_stderr_tail.last(ERROR_STDERR_LINES) };
+                    let error = || match reason {
+                        "interrupted" => anyhow::anyhow!("lit process exited"),
+                        "stalled" => anyhow::anyhow!("lit process stalled"),
+                        _ => crash_error.clone().into(),
+                    };
+                    while let Ok(ProcessCommand::Run { response_tx, .. }) = command_rx.try_recv() {
+                        let _ = response_tx.send(Err(error())).await;
+                        loop_pending.fetch_sub(1, Ordering::Relaxed);
+                    }
+                }
+                None => {
+                    // Cleanup: kill child process when command loop exits
+                    let _ = child.kill().await;
+                    metrics.process_exits.with_label_values(&[loop_model.as_str(), "shutdown"]).inc();
 
-            // Cleanup: kill child process when command loop exits
-            let _ = child.kill().await;
+                    command_rx.close();
+                    while let Ok(ProcessCommand::Run { response_tx, .. }) = command_rx.try_recv() {
+                        let _ = response_tx.send(Err(anyhow::anyhow!("lit process shut down"))).await;
+                        loop_pending.fetch_sub(1, Ordering::Relaxed);
+                    }
+                }
+            }
         });
 
         Ok(Self {
             command_tx,
-            child_handle,
+            pending,
+            served,
+            last_timing,
+            started: Instant::now(),
+            stderr: process_stderr_tail,
+            activity,
+            pid,
+            backend: backend.to_string(),
+            gpu_fallback: None,
+            throttle: None,
+            tokenizer,
+            stop,
+            child_handle: tokio::sync::Mutex::new(Some(child_handle)),
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_command(
+        model: &str,
+        metrics: &Metrics,
+        tokenizer: &dyn Tokenizer,
+        pid: Option<u32>,
+        stall_timeout: Option<Duration>,
+        stderr: &StderrTail,
         cmd: ProcessCommand,
         stdin: &mut tokio::process::ChildStdin,
         stdout: &mut tokio::process::ChildStdout,
-        buffer: &mut Vec<u8>,
+        framer: &mut Framer,
+        temp_buf: &mut [u8; 1024],
+    ) -> Outcome {
+        match cmd {
+            ProcessCommand::Run { prompt, response_tx, parent, queue_span, cancel, .. } => {
+                // Queue wait ends as soon as the loop picks up the command
+                drop(queue_span);
+                let span = tracing::info_span!(
+                    parent: &parent,
+                    "process.generate",
+                    model = %model,
+                    prompt_length = prompt.len()
+                );
+                let outcome = Self::generate(model, tokenizer, prompt, response_tx, &cancel, pid, stall_timeout, stderr, stdin, stdout, framer, temp_buf)
+                    .instrument(span)
+                    .await;
+                let label = match outcome {
+                    Outcome { stalled: true, .. } => "stalled",
+                    Outcome { interrupted: true, .. } => "cancelled",
+                    Outcome { succeeded: true, .. } => "success",
+                    Outcome { succeeded: false, .. } => "error",
+                };
+                metrics.completions.with_label_values(&[model, label]).inc();
+                outcome
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn generate(
+        model: &str,
+        tokenizer: &dyn Tokenizer,
+        prompt: String,
+        response_tx: mpsc::Sender<Result<String>>,
+        cancel: &CancellationToken,
+        pid: Option<u32>,
+        stall_timeout: Option<Duration>,
+        stderr: &StderrTail,
+        stdin: &mut tokio::process::ChildStdin,
+        stdout: &mut tokio::process::ChildStdout,
+        framer: &mut Framer,
         temp_buf: &mut [u8; 1024],
-    ) {
+    ) -> Outcome {
         use tokio::io::AsyncReadExt;
 
-        match cmd {
-            ProcessCommand::Run { prompt, response_tx } => {
-                tracing::trace!("Writing prompt to process stdin");
-                // 1. Write prompt to the process's stdin
-                if let Err(e) = stdin.write_all(prompt.as_bytes()).await {
-                    tracing::error!(error = %e, "Failed to write prompt to stdin");
-                    let _ = response_tx.send(Err(e.into())).await;
-                    return;
+        let started = Instant::now();
+        let mut timing = GenerationTiming::default();
+
+        tracing::trace!("Writing prompt to process stdin");
+        // 1. Write prompt to the process's stdin
+        if let Err(e) = stdin.write_all(prompt.as_bytes()).await {
+            tracing::error!(error = %e, "Failed to write prompt to stdin");
+            let _ = response_tx.send(Err(e.into())).await;
+            return Outcome { succeeded: false, interrupted: false, stalled: false, timing, tokens: 0 };
+        }
+        if let Err(e) = stdin.write_all(b"\n").await {
+            tracing::error!(error = %e, "Failed to write newline to stdin");
+            let _ = response_tx.send(Err(e.into())).await;
+            return Outcome { succeeded: false, interrupted: false, stalled: false, timing, tokens: 0 };
+        }
+        if let Err(e) = stdin.flush().await {
+            tracing::error!(error = %e, "Failed to flush stdin");
+            let _ = response_tx.send(Err(e.into())).await;
+            return Outcome { succeeded: false, interrupted: false, stalled: false, timing, tokens: 0 };
+        }
+
+        // 2. Read the reply up to lit's prompt, streaming it as it comes
+        framer.reset();
+        let mut generated = String::new();
+        let mut succeeded = true;
+        // Set once nobody listens (client gone, request killed); what lit
+        // still prints up to its prompt marker is read and dropped so that it
+        // doesn't end up in the next prompt's reply
+        let mut abandoned = false;
+        // Set once lit was asked to stop generating
+        let mut interrupted = false;
+        // Set once lit went quiet without printing its prompt
+        let mut stalled = false;
+
+        tracing::trace!("Reading response from process stdout");
+        loop {
+            let read = tokio::select! {
+                biased;
+                _ = cancel.cancelled(), if !interrupted => {
+                    tracing::debug!("Reply no longer wanted; interrupting lit");
+                    abandoned = true;
+                    interrupted = true;
+                    if let Some(pid) = pid {
+                        interrupt(pid);
+                    }
+                    continue;
                 }
-                if let Err(e) = stdin.write_all(b"\n").await {
-                    tracing::error!(error = %e, "Failed to write newline to stdin");
-                    let _ = response_tx.send(Err(e.into())).await;
-                    return;
+                read = stdout.read(temp_buf) => read,
+                _ = prompt_settled(framer.at_prompt()) => {
+                    framer.settle();
+                    tracing::trace!("Received end marker, finalizing response");
+                    break;
                 }
-                if let Err(e) = stdin.flush().await {
-                    tracing::error!(error = %e, "Failed to flush stdin");
-                    let _ = response_tx.send(Err(e.into())).await;
-                    return;
+                // Restarts with every read, so only silence counts
+                _ = stall(stall_timeout) => {
+                    let after = stall_timeout.unwrap_or_default();
+                    tracing::error!(timeout_secs = after.as_secs(), "lit printed nothing for the stall timeout");
+                    telemetry::record_error(model, "stalled");
+                    succeeded = false;
+                    stalled = true;
+                    let _ = response_tx.send(Err(LitError::Stalled { after }.into())).await;
+                    break;
                 }
-
-                // 2. Read character-by-character and stream tokens
-                buffer.clear();
-                let mut last_chunk = String::new();
-
-                tracing::trace!("Reading response from process stdout");
-                loop {
-                    match stdout.read(temp_buf).await {
-                        Ok(0) => {
-                            // EOF - process died
-                            tracing::error!("Process stdout closed unexpectedly");
-                            let _ = response_tx.send(Err(anyhow::anyhow!("Process stdout closed"))).await;
-                            break;
-                        }
-                        Ok(n) => {
-                            buffer.extend_from_slice(&temp_buf[..n]);
-                            let text = String::from_utf8_lossy(buffer).to_string();
-
-                            // Check if we've reached the end marker ">>>"
-                            if text.ends_with(">>>") || text.contains("\n>>>") {
-                                tracing::trace!("Received end marker, finalizing response");
-                                // Send the final chunk (without the >>>)
-                                let final_text = text.trim_end_matches(">>>").trim_end_matches('\n');
-                                if final_text.len() > last_chunk.len() {
-                                    let new_content = &final_text[last_chunk.len()..];
-                                    if !new_content.is_empty() {
-                                        if response_tx.send(Ok(new_content.to_string())).await.is_err() {
-                                            tracing::debug!("Response channel closed by receiver");
-                                            break;
-                                        }
-                                    }
-                                }
-                                buffer.clear();
-                                break;
-                            }
-
-                            // Send incremental updates
-                            if text.len() > last_chunk.len() {
-                                let new_content = &text[last_chunk.len()..];
-                                if response_tx.send(Ok(new_content.to_string())).await.is_err() {
-                                    // Client disconnected
-                                    buffer.clear();
-                                    break;
-                                }
-                                last_chunk = text;
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!(error = %e, "Error reading from process stdout");
-                            let _ = response_tx.send(Err(e.into())).await;
-                            break;
+            };
+            match read {
+                Ok(0) if interrupted => {
+                    tracing::warn!("lit exited when interrupted");
+                    succeeded = false;
+                    break;
+                }
+                Ok(0) => {
+                    // EOF - process died
+                    tracing::error!("Process stdout closed unexpectedly");
+                    telemetry::record_error(model, "process_exit");
+                    succeeded = false;
+                    let _ = response_tx.send(Err(died(stderr).await.into())).await;
+                    break;
+                }
+                Ok(n) => {
+                    let frame = framer.push(&temp_buf[..n]);
+                    if !frame.text.is_empty() {
+                        timing.time_to_first_token.get_or_insert_with(|| started.elapsed());
+                        generated.push_str(&frame.text);
+                        if !abandoned && response_tx.send(Ok(frame.text)).await.is_err() {
+                            tracing::debug!("Response channel closed by receiver; discarding the rest");
+                            abandoned = true;
                         }
                     }
                 }
-                // When done, `response_tx` is dropped, closing the stream
+                Err(e) => {
+                    tracing::error!(error = %e, "Error reading from process stdout");
+                    telemetry::record_error(model, "process_io");
+                    succeeded = false;
+                    let _ = response_tx.send(Err(e.into())).await;
+                    break;
+                }
             }
         }
+        let tokens = tokenizer.count(&generated) as u64;
+        telemetry::record_tokens(model, tokens);
+        timing.total = started.elapsed();
+        // When done, `response_tx` is dropped, closing the stream
+        Outcome { succeeded, interrupted, stalled, timing, tokens }
     }
 
     // New streaming method
     pub async fn send_prompt_stream(
         &self,
         prompt: &str,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        self.send_prompt_stream_cancellable(prompt, CancellationToken::new()).await
+    }
+
+    /// [`send_prompt_stream`](Self::send_prompt_stream) that can be stopped
+    /// early: once `cancel` is cancelled, lit is interrupted and the stream
+    /// ends with [`Cancelled`](crate::cancel::Cancelled). The process takes
+    /// its next prompt as soon as lit is back at its prompt.
+    pub async fn send_prompt_stream_cancellable(
+        &self,
+        prompt: &str,
+        cancel: CancellationToken,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        self.enqueue(prompt, None, cancel).await
+    }
+
+    /// Stream the reply to a conversation's turn: `new_turns`, continuing
+    /// what lit holds, as long as the process has served `resume` completions
+    /// when the turn comes, else `conversation`, all of it
+    pub(crate) async fn send_turn_stream(
+        &self,
+        new_turns: &str,
+        conversation: &str,
+        resume: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<String>>> {
+        match resume {
+            Some(served) => self.enqueue(new_turns, Some((served, conversation.to_string())), CancellationToken::new()).await,
+            None => self.enqueue(conversation, None, CancellationToken::new()).await,
+        }
+    }
+
+    async fn enqueue(
+        &self,
+        prompt: &str,
+        resume: Option<(u64, String)>,
+        cancel: CancellationToken,
     ) -> Result<impl Stream<Item = Result<String>>> {
         tracing::debug!(prompt_length = prompt.len(), "Creating prompt stream");
 
         // 1. Create a new, unique channel for *this* request's response
         let (response_tx, response_rx) = mpsc::channel(100); // Token buffer
 
-        // 2. Create the command
+        // 2. Create the command; cancelling it or dropping the stream
+        //    interrupts lit (or skips the prompt while it is still queued)
+        let command_cancel = cancel.child_token();
+        let cancel_on_drop = command_cancel.clone().drop_guard();
         let cmd = ProcessCommand::Run {
             prompt: prompt.to_string(),
             response_tx,
+            parent: tracing::Span::current(),
+            queue_span: tracing::info_span!("process.queue_wait"),
+            cancel: command_cancel,
+            resume,
         };
 
         // 3. Send the command to the process loop
+        self.pending.fetch_add(1, Ordering::Relaxed);
         self.command_tx.send(cmd).await.map_err(|e| {
             // Process loop died
+            self.pending.fetch_sub(1, Ordering::Relaxed);
             tracing::error!(error = %e, "Process command channel closed");
             anyhow::anyhow!("Failed to send command to process: {}", e)
         })?;
 
         tracing::debug!("Command sent to process, returning stream");
-        // 4. Return the receiver wrapped in a stream
-        Ok(ReceiverStream::new(response_rx))
+        // 4. Return the receiver wrapped in a stream, paced by the throttle;
+        //    while it waits the process can't finish and start its next prompt
+        let throttle = self.throttle.clone();
+        let tokenizer = self.tokenizer.clone();
+        let stream = ReceiverStream::new(response_rx).then(move |item| {
+            let _ = &cancel_on_drop;
+            let throttle = throttle.clone();
+            let tokenizer = tokenizer.clone();
+            async move {
+                if let (Some(throttle), Ok(chunk)) = (&throttle, &item) {
+                    throttle.consume(tokenizer.count(chunk) as u64).await;
+                }
+                item
+            }
+        });
+        Ok(cancel::stream(Some(cancel), stream))
     }
 
     // Keep the old non-streaming method for backward compatibility
     pub async fn send_prompt(&self, prompt: &str) -> Result<String> {
-        use futures::StreamExt;
+        let (response, _) = self.send_prompt_timed(prompt).await?;
+        Ok(response)
+    }
+
+    /// Non-streaming completion that also reports time-to-first-token and total time
+    pub async fn send_prompt_timed(&self, prompt: &str) -> Result<(String, GenerationTiming)> {
+        self.send_prompt_limited(prompt, None, self.tokenizer.clone()).await
+    }
+
+    /// [`send_prompt_timed`](Self::send_prompt_timed) that stops reading once
+    /// the reply reaches `max_tokens`, as counted by `tokenizer`, and
+    /// interrupts lit
+    pub async fn send_prompt_limited(
+        &self,
+        prompt: &str,
+        max_tokens: Option<usize>,
+        tokenizer: Arc<dyn Tokenizer>,
+    ) -> Result<(String, GenerationTiming)> {
+        let started = Instant::now();
+        let stream = self.send_prompt_stream(prompt).await?;
+        collect_reply(started, stream, max_tokens, tokenizer).await
+    }
+
+    /// Number of prompts queued on or running in this process
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
 
-        let mut stream = self.send_prompt_stream(prompt).await?;
-        let mut response = String::new();
+    /// Number of completions the process has run
+    pub fn served(&self) -> u64 {
+        self.served.load(Ordering::Relaxed)
+    }
+
+    /// Whether lit is still running
+    pub fn is_alive(&self) -> bool {
+        self.activity.lock().unwrap().state != "exited"
+    }
 
-        while let Some(result) = stream.next().await {
-            let line = result?;
-            response.push_str(&line);
-            response.push('\n');
+    /// How long the process has had nothing to do; `None` while it is
+    /// starting or has prompts to answer
+    pub fn idle_for(&self) -> Option<Duration> {
+        let activity = *self.activity.lock().unwrap();
+        match activity.state {
+            "idle" | "exited" if self.pending() == 0 => Some(activity.since.elapsed()),
+            _ => None,
         }
+    }
 
-        Ok(response)
+    /// OS process id of lit, if it is known
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Backend the process was started with (`gpu`, `cpu`, or `npu`)
+    pub fn backend(&self) -> &str {
+        &self.backend
+    }
+
+    /// Why the GPU backend failed, if the process fell back to CPU
+    pub fn gpu_fallback(&self) -> Option<&str> {
+        self.gpu_fallback.as_deref()
     }
 
-    #[allow(dead_code)]
-    pub async fn shutdown(self) -> Result<()> {
-        // Drop command_tx to signal shutdown
-        drop(self.command_tx);
-        // Wait for child task to finish
-        self.child_handle.await?;
+    pub fn debug_info(&self, index: usize) -> ProcessDebugInfo {
+        let activity = *self.activity.lock().unwrap();
+        let pending = self.pending();
+        let elapsed = activity.since.elapsed();
+        let stuck = match activity.state {
+            "generating" => elapsed > STUCK_AFTER,
+            "exited" => pending > 0,
+            _ => false,
+        };
+
+        ProcessDebugInfo {
+            index,
+            pid: self.pid,
+            backend: self.backend.clone(),
+            gpu_fallback: self.gpu_fallback.clone(),
+            state: activity.state,
+            state_ms: elapsed.as_millis() as u64,
+            pending,
+            served: self.served(),
+            stuck,
+        }
+    }
+
+    /// State, workload, and latency of the process
+    pub fn stats(&self, index: usize) -> ProcessStats {
+        let state = self.activity.lock().unwrap().state;
+        let last_timing = *self.last_timing.lock().unwrap();
+        ProcessStats {
+            index,
+            pid: self.pid,
+            backend: self.backend.clone(),
+            busy: state == "generating",
+            state,
+            pending: self.pending(),
+            served: self.served(),
+            last_latency_ms: last_timing.map(|timing| timing.total.as_millis() as u64),
+            last_ttft_ms: last_timing
+                .and_then(|timing| timing.time_to_first_token)
+                .map(|ttft| ttft.as_millis() as u64),
+            uptime_secs: self.started.elapsed().as_secs(),
+        }
+    }
+
+    /// The process and the end of what lit wrote to stderr, for finding out
+    /// why it fails
+    pub fn diagnostics(&self, index: usize) -> ProcessDiagnostics {
+        ProcessDiagnostics {
+            index,
+            pid: self.pid,
+            backend: self.backend.clone(),
+            state: self.activity.lock().unwrap().state,
+            stderr: self.stderr.snapshot(),
+        }
+    }
+
+    /// Stop lit once the generation in progress (if any) has finished;
+    /// prompts still queued fail
+    pub async fn shutdown(&self) -> Result<()> {
+        self.stop.cancel();
+        // Wait for the process loop to kill and reap the child
+        if let Some(handle) = self.child_handle.lock().await.take() {
+            handle.await?;
+        }
         Ok(())
     }
+
+    /// Stop lit once every prompt sent to it has been answered, for a process
+    /// that no longer gets new ones; it is killed if that takes longer than
+    /// [`DRAIN_TIMEOUT`]
+    async fn shutdown_when_drained(&self) {
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        // The first wait covers prompts sent by callers that picked the
+        // process just before it was replaced
+        loop {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            if self.pending() == 0 || Instant::now() >= deadline {
+                break;
+            }
+        }
+        self.stop.cancel();
+        let Some(mut handle) = self.child_handle.lock().await.take() else {
+            return;
+        };
+        let left = deadline.saturating_duration_since(Instant::now());
+        if tokio::time::timeout(left, &mut handle).await.is_err() {
+            tracing::warn!(pid = ?self.pid, "Replaced lit process still busy after {:?}; killing it", DRAIN_TIMEOUT);
+            handle.abort();
+            let _ = handle.await;
+        }
+    }
 }
 
 /// Manages a pool of isolated LitProcess instances
@@ -331,7 +1355,52 @@ impl LitProcess {
 pub struct ProcessPool {
     binary_path: PathBuf,
     model: String,
-    processes: Vec<Arc<LitProcess>>,
+    pool_size: usize,
+    // See `with_backend`
+    backend: Backend,
+    // Replaced in place when a process dies; see `supervise`
+    processes: Arc<std::sync::RwLock<Vec<Arc<LitProcess>>>>,
+    metrics: Arc<Metrics>,
+    crash_dir: Option<PathBuf>,
+    throttle: Option<Arc<TokenBucket>>,
+    adapter: Option<PathBuf>,
+    // Extra lit flags; see `with_args`
+    args: Vec<String>,
+    // See `with_breaker`
+    breaker: Option<Arc<CircuitBreaker>>,
+    // See `with_sandbox`
+    sandbox: Option<Arc<Sandbox>>,
+    // See `with_limits`
+    limits: Option<Arc<Limits>>,
+    // See `with_timeout`
+    timeout: Option<Duration>,
+    // See `with_stall_timeout`
+    stall_timeout: Option<Duration>,
+    // See `with_max_requests_per_process`
+    max_requests_per_process: Option<u64>,
+    // Woken by processes that reached it, timed out, or quit on an interrupt,
+    // so `supervise` replaces them
+    wake: Arc<Notify>,
+    // Shares the processes fairly between tenants
+    scheduler: Arc<FairScheduler>,
+    // See `with_max_queue`
+    max_queue: Option<usize>,
+    // Stops respawning dead processes; see `retire`
+    supervisor: CancellationToken,
+    // When a request was last admitted; see `idle_for`
+    last_used: Mutex<Instant>,
+    // Set by requests; overrides the config's keep-alive for this pool
+    keep_alive: Mutex<Option<KeepAlive>>,
+    // Which process holds which conversation; see `place`
+    conversations: Conversations,
+    // See `with_tokenizer`
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+}
+
+impl Drop for ProcessPool {
+    fn drop(&mut self) {
+        self.supervisor.cancel();
+    }
 }
 
 impl ProcessPool {
@@ -339,51 +1408,568 @@ impl ProcessPool {
         Self {
             binary_path,
             model,
-            processes: Vec::with_capacity(pool_size),
+            pool_size,
+            backend: Backend::Auto,
+            processes: Arc::default(),
+            metrics: Arc::new(Metrics::new()),
+            crash_dir: None,
+            throttle: None,
+            adapter: None,
+            args: Vec::new(),
+            breaker: None,
+            sandbox: None,
+            limits: None,
+            timeout: None,
+            stall_timeout: None,
+            max_requests_per_process: None,
+            wake: Arc::default(),
+            scheduler: FairScheduler::new(pool_size),
+            max_queue: None,
+            supervisor: CancellationToken::new(),
+            last_used: Mutex::new(Instant::now()),
+            keep_alive: Mutex::default(),
+            conversations: Conversations::default(),
+            tokenizer: None,
         }
     }
 
+    /// Record scheduling and process lifecycle into a shared metrics registry
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Start the processes on `backend`; with [`Backend::Auto`], the default,
+    /// each tries the GPU and falls back to the CPU
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Write crash reports for processes that exit unexpectedly into `dir`
+    pub fn with_crash_dir(mut self, dir: PathBuf) -> Self {
+        self.crash_dir = Some(dir);
+        self
+    }
+
+    /// Pace the output of the pool's processes with a (typically server-wide) token bucket
+    pub fn with_throttle(mut self, throttle: Arc<TokenBucket>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Count the tokens the pool's processes generate, for metrics and the
+    /// throttle, with the model's tokenizer rather than the estimate
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Run the base model with a LoRA adapter loaded; the pool's model is
+    /// `<base>:<name>`
+    pub fn with_adapter(mut self, path: PathBuf) -> Self {
+        self.adapter = Some(path);
+        self
+    }
+
+    /// Start lit with these flags in addition to the model and backend
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Report the processes' crashes and successful generations to the
+    /// model's circuit breaker
+    pub fn with_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    /// Start the pool's processes inside a sandbox
+    pub fn with_sandbox(mut self, sandbox: Arc<Sandbox>) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Start the pool's processes with limited memory, priority, or CPUs
+    pub fn with_limits(mut self, limits: Arc<Limits>) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Refuse requests with [`QueueFull`] while `max_queue` others are
+    /// already waiting for a process
+    pub fn with_max_queue(mut self, max_queue: Option<usize>) -> Self {
+        self.max_queue = max_queue;
+        self.scheduler = FairScheduler::with_max_waiting(self.pool_size, max_queue);
+        self
+    }
+
+    /// Kill a process whose generation takes longer than `timeout` from when
+    /// its prompt is sent, failing the generation with [`LitError::Timeout`];
+    /// the process is respawned
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Kill a process that prints nothing for `stall_timeout` during a
+    /// generation, failing the generation with [`LitError::Stalled`]; the
+    /// process is respawned
+    pub fn with_stall_timeout(mut self, stall_timeout: Option<Duration>) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Replace a process once it has served `max_requests` completions, as
+    /// long-lived lit sessions slowly grow and slow down
+    ///
+    /// The replacement loads the model before it takes over, and the old
+    /// process finishes the prompts it was given before it stops.
+    pub fn with_max_requests_per_process(mut self, max_requests: Option<u64>) -> Self {
+        self.max_requests_per_process = max_requests;
+        self
+    }
+
+    /// Extra flags the pool's processes were started with
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Stop respawning processes that die, e.g. once the manager has
+    /// replaced the pool; the processes themselves stop with the last handle
+    pub fn retire(&self) {
+        self.supervisor.cancel();
+    }
+
+    /// Whether the pool was replaced or unloaded, so new requests should ask
+    /// the manager for another
+    pub fn is_retired(&self) -> bool {
+        self.supervisor.is_cancelled()
+    }
+
+    /// How long the pool has gone without requests; `None` while any are
+    /// running or waiting
+    pub fn idle_for(&self) -> Option<Duration> {
+        if self.scheduler.running() + self.scheduler.waiting() > 0 {
+            return None;
+        }
+        let mut idle = self.last_used.lock().unwrap().elapsed();
+        for process in self.processes() {
+            idle = idle.min(process.idle_for()?);
+        }
+        Some(idle)
+    }
+
+    /// How long the pool stays loaded once idle, as last asked by a request
+    pub fn keep_alive(&self) -> Option<KeepAlive> {
+        *self.keep_alive.lock().unwrap()
+    }
+
+    pub fn set_keep_alive(&self, keep_alive: KeepAlive) {
+        *self.keep_alive.lock().unwrap() = Some(keep_alive);
+    }
+
+    // How the pool's processes are started
+    fn launch(&self) -> Launch {
+        Launch {
+            backend: self.backend,
+            crash_dir: self.crash_dir.clone(),
+            adapter: self.adapter.clone(),
+            args: self.args.clone(),
+            breaker: self.breaker.clone(),
+            sandbox: self.sandbox.clone(),
+            limits: self.limits.clone(),
+            timeout: self.timeout,
+            stall_timeout: self.stall_timeout,
+            max_requests: self.max_requests_per_process,
+            wake: self.wake.clone(),
+            tokenizer: self.tokenizer.clone(),
+        }
+    }
+
+    // Snapshot of the processes, for iterating without holding the lock
+    fn processes(&self) -> Vec<Arc<LitProcess>> {
+        self.processes.read().unwrap().clone()
+    }
+
+    #[tracing::instrument(name = "pool.initialize", skip(self), fields(model = %self.model))]
     pub async fn initialize(&mut self) -> Result<()> {
-        let pool_size = self.processes.capacity();
+        let pool_size = self.pool_size;
         tracing::info!(
             pool_size = pool_size,
             model = %self.model,
             "Initializing process pool"
         );
 
-        for i in 0..pool_size {
-            tracing::debug!(process_index = i, "Spawning process");
-            let process = LitProcess::spawn(self.binary_path.clone(), self.model.clone()).await?;
-            self.processes.push(Arc::new(process));
+        // Spawn concurrently, keeping the processes in index order
+        let mut spawned: Vec<Option<LitProcess>> = (0..pool_size).map(|_| None).collect();
+        let mut indexes = 0..pool_size;
+        let mut spawns = tokio::task::JoinSet::new();
+        loop {
+            while spawns.len() < MAX_PARALLEL_SPAWNS {
+                let Some(i) = indexes.next() else { break };
+                tracing::debug!(process_index = i, "Spawning process");
+                let spawn =
+                    LitProcess::spawn_inner(self.binary_path.clone(), self.model.clone(), self.metrics.clone(), self.launch());
+                spawns.spawn(async move { (i, spawn.await) }.in_current_span());
+            }
+            let Some(joined) = spawns.join_next().await else { break };
+            let (i, result) = joined.context("Process spawn task failed")?;
+            // Fail fast: returning drops the join set, aborting the other spawns
+            let mut process = result.with_context(|| format!("Failed to spawn process {}", i))?;
+            process.throttle = self.throttle.clone();
+            spawned[i] = Some(process);
             tracing::debug!(process_index = i, "Process spawned successfully");
         }
+        self.processes.write().unwrap().extend(spawned.into_iter().flatten().map(Arc::new));
 
         tracing::info!(pool_size = pool_size, "Process pool initialized successfully");
+        self.supervise();
+        Ok(())
+    }
+
+    /// Check the processes every [`HEALTH_CHECK_INTERVAL`] and respawn those
+    /// that died, backing off per process while they keep dying; processes
+    /// that served their maximum number of requests are replaced as soon as
+    /// they reach it
+    ///
+    /// Pools that are about to be replaced (the circuit breaker opened) are
+    /// left to the manager.
+    fn supervise(&self) {
+        struct Slot {
+            backoff: Duration,
+            next_try: Instant,
+            respawned_at: Option<Instant>,
+            // Loading a replacement for a spent process; whether it took over
+            replacing: Option<tokio::task::JoinHandle<bool>>,
+        }
+
+        let processes = self.processes.clone();
+        let binary_path = self.binary_path.clone();
+        let model = self.model.clone();
+        let metrics = self.metrics.clone();
+        let throttle = self.throttle.clone();
+        let launch = self.launch();
+        let stop = self.supervisor.clone();
+        let now = Instant::now();
+        let mut slots: Vec<Slot> = (0..self.pool_size)
+            .map(|_| Slot { backoff: MIN_RESPAWN_BACKOFF, next_try: now, respawned_at: None, replacing: None })
+            .collect();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {}
+                    _ = launch.wake.notified() => {}
+                }
+                let replacing = launch.breaker.as_ref().is_some_and(|breaker| breaker.status().state != CircuitState::Closed);
+                if replacing {
+                    continue;
+                }
+
+                let current: Vec<(bool, u64)> =
+                    processes.read().unwrap().iter().map(|p| (p.is_alive(), p.served())).collect();
+                for (i, (alive, served)) in current.into_iter().enumerate() {
+                    let slot = &mut slots[i];
+                    let now = Instant::now();
+                    // The slot is left alone while its replacement loads
+                    if let Some(replacing) = slot.replacing.take_if(|replacing| replacing.is_finished()) {
+                        if !replacing.await.unwrap_or(false) {
+                            slot.next_try = now + MIN_RESPAWN_BACKOFF;
+                        }
+                        continue;
+                    }
+                    if slot.replacing.is_some() {
+                        continue;
+                    }
+                    let spent = launch.max_requests.is_some_and(|max| served >= max);
+                    if alive && spent && now >= slot.next_try {
+                        // The old process keeps serving until its replacement is ready
+                        tracing::info!(model = %model, process_index = i, served, "lit process served its maximum number of requests; replacing it");
+                        let replace = Self::replace_spent(
+                            model.clone(),
+                            i,
+                            processes.clone(),
+                            LitProcess::spawn_inner(binary_path.clone(), model.clone(), metrics.clone(), launch.clone()),
+                            throttle.clone(),
+                            metrics.clone(),
+                            stop.clone(),
+                        );
+                        slot.replacing = Some(tokio::spawn(replace));
+                        continue;
+                    }
+                    if alive {
+                        // Survived long enough to start over with a short wait
+                        if slot.respawned_at.is_some_and(|at| now.duration_since(at) > MAX_RESPAWN_BACKOFF) {
+                            slot.backoff = MIN_RESPAWN_BACKOFF;
+                            slot.respawned_at = None;
+                        }
+                        continue;
+                    }
+                    if now < slot.next_try {
+                        continue;
+                    }
+
+                    tracing::warn!(model = %model, process_index = i, "lit process died; respawning");
+                    let spawn = LitProcess::spawn_inner(binary_path.clone(), model.clone(), metrics.clone(), launch.clone());
+                    let spawned = tokio::select! {
+                        // Retired while loading; dropping the spawn kills lit
+                        _ = stop.cancelled() => return,
+                        spawned = spawn => spawned,
+                    };
+                    let now = Instant::now();
+                    slot.next_try = now + slot.backoff;
+                    slot.backoff = (slot.backoff * 2).min(MAX_RESPAWN_BACKOFF);
+                    slot.respawned_at = Some(now);
+                    match spawned {
+                        Ok(mut process) => {
+                            process.throttle = throttle.clone();
+                            let process = Arc::new(process);
+                            // Retired while starting; don't leave it running
+                            if stop.is_cancelled() {
+                                let _ = process.shutdown().await;
+                                return;
+                            }
+                            processes.write().unwrap()[i] = process;
+                            metrics.process_restarts.with_label_values(&[model.as_str()]).inc();
+                            tracing::info!(model = %model, process_index = i, "lit process respawned");
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                model = %model,
+                                process_index = i,
+                                error = %e,
+                                retry_in_secs = slot.backoff.as_secs(),
+                                "Failed to respawn lit process"
+                            );
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Put the process `spawn` starts in place of process `i` once it has
+    /// loaded the model, and stop the old one once it has answered its
+    /// prompts; whether the replacement took over
+    async fn replace_spent(
+        model: String,
+        i: usize,
+        processes: Arc<std::sync::RwLock<Vec<Arc<LitProcess>>>>,
+        spawn: impl std::future::Future<Output = Result<LitProcess>>,
+        throttle: Option<Arc<TokenBucket>>,
+        metrics: Arc<Metrics>,
+        stop: CancellationToken,
+    ) -> bool {
+        let spawned = tokio::select! {
+            // Retired while loading; dropping the spawn kills lit
+            _ = stop.cancelled() => return false,
+            spawned = spawn => spawned,
+        };
+        let mut process = match spawned {
+            Ok(process) => process,
+            Err(e) => {
+                tracing::warn!(model = %model, process_index = i, error = %e, "Failed to start a replacement for a spent lit process");
+                return false;
+            }
+        };
+        process.throttle = throttle;
+        let old = std::mem::replace(&mut processes.write().unwrap()[i], Arc::new(process));
+        metrics.process_recycles.with_label_values(&[model.as_str()]).inc();
+        tokio::spawn(async move { old.shutdown_when_drained().await });
+        true
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Number of spawned processes in the pool
+    pub fn size(&self) -> usize {
+        self.processes.read().unwrap().len()
+    }
+
+    /// Total prompts queued on or running in the pool's processes
+    pub fn queue_depth(&self) -> usize {
+        self.processes().iter().map(|p| p.pending()).sum()
+    }
+
+    /// Backend the pool was asked to start its processes on
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Backend each process actually runs on, in process order
+    pub fn backends(&self) -> Vec<String> {
+        self.processes().iter().map(|p| p.backend().to_string()).collect()
+    }
+
+    /// Number of processes running on CPU because the GPU backend failed
+    pub fn gpu_fallbacks(&self) -> usize {
+        self.processes().iter().filter(|p| p.gpu_fallback.is_some()).count()
+    }
+
+    /// Run a short generation on every process, so that serving starts only
+    /// once the weights are loaded
+    pub async fn warm_up(&self) -> Result<()> {
+        futures::future::try_join_all(self.processes().iter().map(|p| p.send_prompt(WARM_UP_PROMPT)))
+            .await
+            .context("Warm-up generation failed")?;
+        Ok(())
+    }
+
+    /// Stop every process once its current generation has finished
+    pub async fn shutdown(&self) -> Result<()> {
+        self.retire();
+        futures::future::try_join_all(self.processes().iter().map(|p| p.shutdown())).await?;
         Ok(())
     }
 
+    /// The processes' state and latency, and the pool's queue, e.g. for a
+    /// dashboard
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            model: self.model.clone(),
+            args: self.args.clone(),
+            backend: self.backend,
+            processes: self.processes().iter().enumerate().map(|(i, p)| p.stats(i)).collect(),
+            running: self.scheduler.running(),
+            waiting: self.scheduler.waiting(),
+            max_queue: self.max_queue,
+            queue_depth: self.queue_depth(),
+            idle_secs: self.idle_for().map(|idle| idle.as_secs()),
+        }
+    }
+
+    /// Each process's state and stderr tail
+    pub fn diagnostics(&self) -> Vec<ProcessDiagnostics> {
+        self.processes()
+            .iter()
+            .enumerate()
+            .map(|(i, p)| p.diagnostics(i))
+            .collect()
+    }
+
+    /// Per-process state, for diagnosing hangs
+    pub fn debug_info(&self) -> Vec<ProcessDebugInfo> {
+        self.processes()
+            .iter()
+            .enumerate()
+            .map(|(i, p)| p.debug_info(i))
+            .collect()
+    }
+
+    /// Wait for a slot in the pool on behalf of `tenant`; hold the admission
+    /// for as long as the generation runs
+    ///
+    /// Fails with [`QueueFull`] instead of waiting when the pool's queue is full.
+    #[tracing::instrument(name = "pool.admit", skip_all, fields(model = %self.model, tenant = %tenant.name()))]
+    pub async fn admit(&self, tenant: &Tenant) -> Result<Admission> {
+        // Decrements the waiting gauge even if the caller gives up
+        struct Waiting(prometheus::IntGauge);
+        impl Drop for Waiting {
+            fn drop(&mut self) {
+                self.0.dec();
+            }
+        }
+
+        let labels = [self.model.as_str(), tenant.name()];
+        let waiting = Waiting(self.metrics.tenant_waiting.with_label_values(&labels));
+        waiting.0.inc();
+        let started = Instant::now();
+        *self.last_used.lock().unwrap() = started;
+
+        let admission = self.scheduler.acquire(tenant.name(), tenant.weight()).await;
+        drop(waiting);
+        let admission = match admission {
+            Ok(admission) => admission,
+            Err(Rejected::QueueFull) => {
+                let max_queue = self.max_queue.unwrap_or_default();
+                tracing::warn!(model = %self.model, max_queue, "Process pool queue is full; rejecting request");
+                self.metrics.concurrency_rejections.with_label_values(&[self.model.as_str()]).inc();
+                return Err(QueueFull { model: self.model.clone(), max_queue }.into());
+            }
+            Err(e) => return Err(e).context(format!("No slot in the pool for model '{}'", self.model)),
+        };
+
+        self.metrics
+            .tenant_wait_duration
+            .with_label_values(&labels)
+            .observe(started.elapsed().as_secs_f64());
+        Ok(admission)
+    }
+
+    #[tracing::instrument(name = "pool.dispatch", skip(self), fields(model = %self.model, process))]
     pub async fn get_process(&self) -> Result<Arc<LitProcess>> {
-        // Simple round-robin selection
-        // In a real implementation, you might want to track which processes are busy
-        use std::sync::atomic::{AtomicUsize, Ordering};
+        // The least busy process, so that admitted requests don't queue behind
+        // each other on one process while another is idle; ties go round-robin.
+        // Dead processes are passed over until they're respawned.
         static COUNTER: AtomicUsize = AtomicUsize::new(0);
 
-        if self.processes.is_empty() {
+        let processes = self.processes();
+        if processes.is_empty() {
             tracing::error!("Process pool is empty or not initialized");
             anyhow::bail!("Process pool not initialized")
         }
 
-        let idx = COUNTER.fetch_add(1, Ordering::Relaxed) % self.processes.len();
+        let start = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let idx = (0..processes.len())
+            .map(|offset| (start + offset) % processes.len())
+            .min_by_key(|&idx| (!processes[idx].is_alive(), processes[idx].pending()))
+            .unwrap_or_default();
+        tracing::Span::current().record("process", idx);
+        self.metrics
+            .pool_dispatches
+            .with_label_values(&[self.model.as_str(), idx.to_string().as_str()])
+            .inc();
         tracing::trace!(
             process_index = idx,
-            pool_size = self.processes.len(),
+            pool_size = processes.len(),
             "Selected process from pool"
         );
-        Ok(self.processes[idx].clone())
+        Ok(processes[idx].clone())
+    }
+
+    /// The process for the next turn of `conversation`: the one holding its
+    /// earlier turns while that is idle and unchanged, else the least busy
+    /// (see [`affinity`](crate::affinity))
+    pub async fn place(&self, conversation: &str, turns: &[Turn]) -> Result<Placement> {
+        let placement = match self.conversations.place(conversation, turns, &self.processes()) {
+            Some(placement) => placement,
+            None => Placement::replay(self.get_process().await?),
+        };
+        let result = if placement.held > 0 { "hit" } else { "miss" };
+        self.metrics.affinity_lookups.with_label_values(&[self.model.as_str(), result]).inc();
+        Ok(placement)
+    }
+
+    /// Note that the turn sent to `placement` was answered, leaving the
+    /// process holding `turns` (including its reply)
+    pub fn remember(&self, conversation: &str, placement: &Placement, turns: &[Turn]) {
+        self.conversations.remember(conversation, placement, turns);
     }
 
     pub async fn send_prompt(&self, prompt: &str) -> Result<String> {
         let process = self.get_process().await?;
         process.send_prompt(prompt).await
     }
+
+    pub async fn send_prompt_timed(&self, prompt: &str) -> Result<(String, GenerationTiming)> {
+        let process = self.get_process().await?;
+        process.send_prompt_timed(prompt).await
+    }
+
+    /// See [`LitProcess::send_prompt_limited`]
+    pub async fn send_prompt_limited(
+        &self,
+        prompt: &str,
+        max_tokens: Option<usize>,
+        tokenizer: Arc<dyn Tokenizer>,
+    ) -> Result<(String, GenerationTiming)> {
+        let process = self.get_process().await?;
+        process.send_prompt_limited(prompt, max_tokens, tokenizer).await
+    }
 }
@@ -0,0 +1,88 @@
+//! Per-model generation defaults
+//!
+//! A profile gives a model name default generation settings, e.g.
+//! `gemma-3n-E4B-json` served by `gemma-3n-E4B` with a JSON constraint and a
+//! system prompt baked in. Settings a request makes itself take precedence.
+
+use std::collections::HashMap;
+
+use crate::config::ProfileConfig;
+use crate::context::Turn;
+use crate::generation::GenOptions;
+
+/// Defaults for requests that name a profile
+#[derive(Debug, Clone)]
+pub struct Profile {
+    config: ProfileConfig,
+}
+
+impl Profile {
+    pub fn from_config(config: &ProfileConfig) -> Self {
+        Self { config: config.clone() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Model that serves the profile
+    pub fn model(&self) -> &str {
+        self.config.model.as_deref().unwrap_or(&self.config.name)
+    }
+
+    pub fn system_prompt(&self) -> Option<&str> {
+        self.config.system_prompt.as_deref()
+    }
+
+    /// Fill in the options a request left unset
+    pub fn apply(&self, options: &mut GenOptions) {
+        if options.temperature.is_none() {
+            options.temperature = self.config.temperature;
+        }
+        if options.top_p.is_none() {
+            options.top_p = self.config.top_p;
+        }
+        if options.top_k.is_none() {
+            options.top_k = self.config.top_k;
+        }
+        if options.stop.is_empty() {
+            options.stop = self.config.stop.clone();
+        }
+        if options.constraint.is_none() {
+            options.constraint = self.config.constraint.clone();
+        }
+    }
+
+    /// Start a conversation that has no system message with the profile's
+    pub fn apply_turns(&self, turns: &mut Vec<Turn>) {
+        if let Some(system_prompt) = self.system_prompt() {
+            if !turns.iter().any(|turn| turn.role == "system") {
+                turns.insert(0, Turn::new("system", system_prompt));
+            }
+        }
+    }
+}
+
+/// Every configured profile, by name
+#[derive(Debug, Clone, Default)]
+pub struct Profiles {
+    by_name: HashMap<String, Profile>,
+}
+
+impl Profiles {
+    pub fn from_config(configs: &[ProfileConfig]) -> Self {
+        let by_name = configs
+            .iter()
+            .map(|config| (config.name.clone(), Profile::from_config(config)))
+            .collect();
+        Self { by_name }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.by_name.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Profile> {
+        self.by_name.values()
+    }
+}
@@ -0,0 +1,105 @@
+//! Progress of long-running downloads and model loads
+//!
+//! Install a [`ProgressReporter`] with
+//! [`LitManager::with_progress`](crate::LitManager::with_progress) (or
+//! [`BinaryManager::with_progress`](crate::binary::BinaryManager::with_progress))
+//! to follow the lit binary download, model pulls, and process pool starts.
+//! The CLI draws terminal progress bars and the MCP service updates its
+//! `litert://downloads/{model}` resources; embedders can plug in their own.
+
+use std::sync::Arc;
+
+/// What is in progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Phase {
+    /// Downloading the lit binary
+    DownloadingBinary,
+    /// Pulling model weights with `lit pull`
+    PullingModel,
+    /// Starting the processes of a model's pool
+    LoadingModel,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::DownloadingBinary => "downloading_binary",
+            Phase::PullingModel => "pulling_model",
+            Phase::LoadingModel => "loading_model",
+        }
+    }
+}
+
+/// One progress update
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub phase: Phase,
+    /// Model being pulled or loaded; `None` for the lit binary, and for
+    /// [`LitManager::preload`](crate::LitManager::preload) loading several at once
+    pub model: Option<String>,
+    /// Bytes transferred so far, when known
+    pub bytes: Option<u64>,
+    /// Total bytes to transfer, when known
+    pub total_bytes: Option<u64>,
+    /// 0-100; the last update of a finished phase reports 100
+    pub percent: Option<f32>,
+}
+
+impl Progress {
+    pub fn new(phase: Phase, model: Option<&str>) -> Self {
+        Self {
+            phase,
+            model: model.map(str::to_string),
+            bytes: None,
+            total_bytes: None,
+            percent: None,
+        }
+    }
+
+    /// With `percent` set
+    pub fn percent(mut self, percent: f32) -> Self {
+        self.percent = Some(percent.clamp(0.0, 100.0));
+        self
+    }
+
+    /// With the byte counts set, and the percentage derived from them when the total is known
+    pub fn bytes(mut self, bytes: u64, total_bytes: Option<u64>) -> Self {
+        self.bytes = Some(bytes);
+        self.total_bytes = total_bytes;
+        if let Some(total) = total_bytes.filter(|total| *total > 0) {
+            self.percent = Some((bytes as f64 / total as f64 * 100.0).min(100.0) as f32);
+        }
+        self
+    }
+
+    /// Whether this is the last update of its phase
+    pub fn is_finished(&self) -> bool {
+        self.percent == Some(100.0)
+    }
+}
+
+/// Receives progress updates; called from async tasks, so it must not block
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, progress: &Progress);
+}
+
+impl std::fmt::Debug for dyn ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressReporter")
+    }
+}
+
+/// Any `Fn(&Progress)` closure is a reporter
+impl<F: Fn(&Progress) + Send + Sync> ProgressReporter for F {
+    fn report(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Report to `reporter`, if one is installed
+pub(crate) fn report(reporter: Option<&Arc<dyn ProgressReporter>>, progress: Progress) {
+    if let Some(reporter) = reporter {
+        reporter.report(&progress);
+    }
+}
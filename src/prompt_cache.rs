@@ -0,0 +1,217 @@
+//! Cache of responses to repeated prompts
+//!
+//! Responses are keyed by tenant, model, generation options, and the exact
+//! prompt. Recent entries are kept in memory; with `persist` enabled they are
+//! also written to an SQLite database under the cache directory so repeated
+//! runs (e.g. an eval suite) hit the cache across restarts. The database is
+//! trimmed to `max_disk_bytes` by evicting the least recently used entries.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::config::PromptCacheConfig;
+use crate::generation::GenOptions;
+use crate::history::unix_now;
+
+/// Where a cached response was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheHit {
+    Memory,
+    Disk,
+}
+
+impl CacheHit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheHit::Memory => "memory",
+            CacheHit::Disk => "disk",
+        }
+    }
+}
+
+pub struct PromptCache {
+    memory: Mutex<MemoryCache>,
+    memory_entries: usize,
+    disk: Option<Mutex<Connection>>,
+    max_disk_bytes: u64,
+}
+
+impl std::fmt::Debug for PromptCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptCache")
+            .field("memory_entries", &self.memory_entries)
+            .field("persist", &self.disk.is_some())
+            .field("max_disk_bytes", &self.max_disk_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Least recently used entries, evicted by scanning for the oldest use
+#[derive(Debug, Default)]
+struct MemoryCache {
+    entries: HashMap<String, (Vec<String>, u64)>,
+    clock: u64,
+}
+
+impl MemoryCache {
+    fn get(&mut self, key: &str) -> Option<Vec<String>> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (responses, used) = self.entries.get_mut(key)?;
+        *used = clock;
+        Some(responses.clone())
+    }
+
+    fn insert(&mut self, key: String, responses: Vec<String>, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&key) && self.entries.len() >= capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.entries.insert(key, (responses, self.clock));
+    }
+}
+
+impl PromptCache {
+    /// Memory-only cache, or one backed by the database at `disk` when given
+    pub fn open(config: &PromptCacheConfig, disk: Option<&Path>) -> Result<Self> {
+        let disk = match disk {
+            Some(path) => {
+                let conn = Connection::open(path)
+                    .with_context(|| format!("Failed to open prompt cache at {}", path.display()))?;
+                conn.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS prompt_cache (
+                        key TEXT PRIMARY KEY,
+                        model TEXT NOT NULL,
+                        responses TEXT NOT NULL,
+                        bytes INTEGER NOT NULL,
+                        created_at INTEGER NOT NULL,
+                        last_used INTEGER NOT NULL
+                    );
+                    CREATE INDEX IF NOT EXISTS prompt_cache_last_used ON prompt_cache (last_used);",
+                )
+                .context("Failed to initialize prompt cache schema")?;
+                Some(Mutex::new(conn))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            memory: Mutex::new(MemoryCache::default()),
+            memory_entries: config.memory_entries,
+            disk,
+            max_disk_bytes: config.max_disk_bytes,
+        })
+    }
+
+    /// Cached responses for this prompt, if any
+    pub fn get(&self, tenant: &str, model: &str, prompt: &str, options: &GenOptions) -> Option<(Vec<String>, CacheHit)> {
+        let key = cache_key(tenant, model, prompt, options);
+        if let Some(responses) = self.memory.lock().unwrap().get(&key) {
+            return Some((responses, CacheHit::Memory));
+        }
+
+        let responses = match self.disk_get(&key) {
+            Ok(responses) => responses?,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read prompt cache");
+                return None;
+            }
+        };
+        self.memory
+            .lock()
+            .unwrap()
+            .insert(key, responses.clone(), self.memory_entries);
+        Some((responses, CacheHit::Disk))
+    }
+
+    /// Remember the responses to this prompt; failures are logged, never surfaced
+    pub fn put(&self, tenant: &str, model: &str, prompt: &str, options: &GenOptions, responses: Vec<String>) {
+        let key = cache_key(tenant, model, prompt, options);
+        if let Err(e) = self.disk_put(&key, model, &responses) {
+            tracing::warn!(error = %e, "Failed to write prompt cache");
+        }
+        self.memory
+            .lock()
+            .unwrap()
+            .insert(key, responses, self.memory_entries);
+    }
+
+    fn disk_get(&self, key: &str) -> Result<Option<Vec<String>>> {
+        let Some(disk) = &self.disk else {
+            return Ok(None);
+        };
+        let conn = disk.lock().unwrap();
+        let responses: Option<String> = conn
+            .query_row(
+                "UPDATE prompt_cache SET last_used = ?2 WHERE key = ?1 RETURNING responses",
+                params![key, unix_now() as i64],
+                |row| row.get(0),
+            )
+            .optional()?;
+        responses
+            .map(|json| serde_json::from_str(&json).context("Corrupt prompt cache entry"))
+            .transpose()
+    }
+
+    fn disk_put(&self, key: &str, model: &str, responses: &[String]) -> Result<()> {
+        let Some(disk) = &self.disk else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(responses)?;
+        let bytes = (key.len() + json.len()) as i64;
+        let now = unix_now() as i64;
+
+        let conn = disk.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO prompt_cache (key, model, responses, bytes, created_at, last_used)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+            params![key, model, json, bytes, now],
+        )?;
+        // Keep the most recently used entries that fit the size limit
+        let evicted = conn.execute(
+            "DELETE FROM prompt_cache WHERE key IN (
+                SELECT key FROM (
+                    SELECT key, SUM(bytes) OVER (ORDER BY last_used DESC, rowid DESC) AS total
+                    FROM prompt_cache
+                ) WHERE total > ?1
+            )",
+            params![self.max_disk_bytes as i64],
+        )?;
+        if evicted > 0 {
+            tracing::debug!(evicted, "Evicted prompt cache entries");
+        }
+        Ok(())
+    }
+
+    /// Entries and bytes held on disk
+    pub fn disk_usage(&self) -> Result<(u64, u64)> {
+        let Some(disk) = &self.disk else {
+            return Ok((0, 0));
+        };
+        let conn = disk.lock().unwrap();
+        let (entries, bytes): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(bytes), 0) FROM prompt_cache",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((entries as u64, bytes as u64))
+    }
+}
+
+fn cache_key(tenant: &str, model: &str, prompt: &str, options: &GenOptions) -> String {
+    let options = serde_json::to_string(options).unwrap_or_default();
+    format!("{}\0{}\0{}\0{}", tenant, model, options, prompt)
+}
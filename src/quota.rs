@@ -0,0 +1,127 @@
+//! Disk quota for downloaded models
+//!
+//! lit keeps every model it pulls. With `model_cache.max_bytes` set, a pull
+//! that would take the downloaded models over the limit first removes the least
+//! recently used ones, skipping pinned and loaded models. When a model was last
+//! used is tracked in `model_usage.json` under the cache directory, updated on
+//! every pull and pool start; models with no record count as the oldest.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cache_lock::CacheLock;
+use crate::history::unix_now;
+
+/// How long an update waits for another process updating the usage file
+const USAGE_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A downloaded model, as weighed against the quota
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedModel {
+    pub name: String,
+    /// Zero when the listing doesn't say
+    pub size_bytes: u64,
+    /// Unix timestamp (seconds) of the last pull or pool start
+    pub last_used: Option<u64>,
+}
+
+/// Models to remove so that `incoming_bytes` more fit under the quota
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvictionPlan {
+    /// Least recently used first
+    pub evict: Vec<CachedModel>,
+    /// Size of the downloaded models after the eviction and the pull
+    pub total_bytes: u64,
+    /// Whether the pull fits once `evict` is removed
+    pub fits: bool,
+}
+
+/// Pick the least recently used models, for which `keep` is false, whose
+/// removal makes room for `incoming_bytes` under `max_bytes`
+pub fn plan_eviction(
+    cached: &[CachedModel],
+    incoming_bytes: u64,
+    max_bytes: u64,
+    keep: impl Fn(&str) -> bool,
+) -> EvictionPlan {
+    let mut total_bytes = cached.iter().map(|model| model.size_bytes).sum::<u64>() + incoming_bytes;
+    let mut candidates: Vec<&CachedModel> = cached
+        .iter()
+        .filter(|model| model.size_bytes > 0 && !keep(&model.name))
+        .collect();
+    candidates.sort_by_key(|model| model.last_used.unwrap_or(0));
+
+    let mut evict = Vec::new();
+    for model in candidates {
+        if total_bytes <= max_bytes {
+            break;
+        }
+        total_bytes -= model.size_bytes;
+        evict.push(model.clone());
+    }
+    EvictionPlan {
+        evict,
+        total_bytes,
+        fits: total_bytes <= max_bytes,
+    }
+}
+
+/// When each model was last used, kept in a JSON file
+#[derive(Debug, Clone)]
+pub struct ModelUsage {
+    path: PathBuf,
+}
+
+impl ModelUsage {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Last use of every recorded model; empty if nothing was recorded yet
+    pub fn load(&self) -> HashMap<String, u64> {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Record that `model` was used just now
+    pub async fn touch(&self, model: &str) -> Result<()> {
+        let _lock = self.lock().await?;
+        let mut usage = self.load();
+        usage.insert(model.to_string(), unix_now());
+        self.save(&usage)
+    }
+
+    /// Forget a removed model
+    pub async fn forget(&self, model: &str) -> Result<()> {
+        let _lock = self.lock().await?;
+        let mut usage = self.load();
+        if usage.remove(model).is_some() {
+            self.save(&usage)?;
+        }
+        Ok(())
+    }
+
+    /// Updates are read-modify-write, so concurrent CLI invocations and
+    /// servers sharing the cache take turns rather than losing each other's
+    async fn lock(&self) -> Result<CacheLock> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        CacheLock::acquire(dir, "usage", USAGE_LOCK_TIMEOUT).await
+    }
+
+    fn save(&self, usage: &HashMap<String, u64>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Written aside and renamed, so readers never see a partial file
+        let tmp = self.path.with_extension(format!("json.{}", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&tmp, serde_json::to_vec(usage)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to update {}", self.path.display()))?;
+        Ok(())
+    }
+}
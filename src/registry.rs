@@ -0,0 +1,273 @@
+//! Fuzzy search over the lit model registry
+//!
+//! `lit list --show_all` prints every model in the registry as a table, with
+//! the model name first and details (such as size or tags) after it. Search
+//! matches each word of a query against those rows, ranking name matches above
+//! detail matches, so `gemma 1b` finds `gemma3-1b` without scrolling the dump.
+//!
+//! Models lit's registry doesn't have can be listed in a [`RegistryManifest`]
+//! of one's own (`registries` in the config file). Its models are added to
+//! the listing and pulled by name from their URL, checked against their
+//! SHA-256 when the manifest gives one.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::checksum::parse_sha256;
+use crate::manager::model_line_name;
+
+/// How long fetching a registry manifest may take
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One model listed by the registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RegistryEntry {
+    pub name: String,
+    /// The row's other columns, as printed by lit
+    pub details: Vec<String>,
+}
+
+impl RegistryEntry {
+    /// Download size, if one of the details is a size such as `4.2GB`
+    pub fn size_bytes(&self) -> Option<u64> {
+        self.details.iter().enumerate().find_map(|(i, detail)| {
+            // The unit may be a column of its own, as in `4.2 GB`
+            parse_size(detail).or_else(|| parse_size(&format!("{}{}", detail, self.details.get(i + 1)?)))
+        })
+    }
+}
+
+/// Bytes in a size such as `512MB`, `4.2GB`, or `1.5GiB`
+pub fn parse_size(text: &str) -> Option<u64> {
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    // A bare `B` is left out: `1B` in a listing is a parameter count
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+/// A model as listed by lit, for callers that would otherwise parse the table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// What the model was pulled as, and what completions take: lit's
+    /// `ALIAS` column
+    pub alias: String,
+    /// The model it is, such as a Hugging Face repository: lit's `MODEL_ID`
+    /// column, or the alias when the listing has none
+    pub name: String,
+    /// Bytes on disk once downloaded, else the listed download size
+    pub size_bytes: Option<u64>,
+    pub downloaded: bool,
+    /// Where the model is stored, when the listing says
+    pub path: Option<PathBuf>,
+}
+
+impl ModelInfo {
+    pub fn from_entry(entry: &RegistryEntry, downloaded: bool) -> Self {
+        let path = entry
+            .details
+            .iter()
+            .map(Path::new)
+            .find(|path| path.is_absolute())
+            .map(Path::to_path_buf);
+        let name = entry
+            .details
+            .first()
+            // Not a size or parameter count such as `1B`, nor the path
+            .filter(|detail| !detail.starts_with(|c: char| c.is_ascii_digit()) && !Path::new(detail).is_absolute())
+            .unwrap_or(&entry.name);
+        let on_disk = path.as_deref().filter(|_| downloaded).and_then(disk_usage);
+        Self {
+            alias: entry.name.clone(),
+            name: name.clone(),
+            size_bytes: on_disk.or_else(|| entry.size_bytes()),
+            downloaded,
+            path,
+        }
+    }
+}
+
+/// Bytes taken by the file or directory at `path`
+fn disk_usage(path: &Path) -> Option<u64> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return Some(metadata.len());
+    }
+    let entries = std::fs::read_dir(path).ok()?;
+    Some(entries.flatten().filter_map(|entry| disk_usage(&entry.path())).sum())
+}
+
+/// A model in a [`RegistryManifest`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestModel {
+    /// Name the model is listed and pulled as
+    pub name: String,
+    /// Where it is pulled from: an `http(s)://`, `s3://`, or `gs://` URL
+    pub url: String,
+    /// SHA-256 the model file must have (hex)
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+}
+
+/// Models listed by a registry of one's own, as JSON or TOML:
+///
+/// ```json
+/// { "models": [{ "name": "acme-7b", "url": "s3://models/acme-7b.litertlm", "sha256": "9f86d0...", "size_bytes": 4200000000 }] }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryManifest {
+    #[serde(default)]
+    pub models: Vec<ManifestModel>,
+}
+
+impl RegistryManifest {
+    /// The manifest in `text`, TOML if `location` ends in `.toml` and JSON
+    /// otherwise
+    pub fn parse(text: &str, location: &str) -> Result<Self> {
+        let mut manifest: Self = if location.trim_end_matches('/').ends_with(".toml") {
+            toml::from_str(text).with_context(|| format!("Failed to parse registry manifest {}", location))?
+        } else {
+            serde_json::from_str(text).with_context(|| format!("Failed to parse registry manifest {}", location))?
+        };
+        for model in &mut manifest.models {
+            if let Some(sha256) = &model.sha256 {
+                model.sha256 = Some(parse_sha256(sha256).with_context(|| format!("Bad sha256 for {}", model.name))?);
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// The manifest at `location`, an `http(s)://` URL or a file path
+    pub async fn load(location: &str) -> Result<Self> {
+        let text = if location.starts_with("https://") || location.starts_with("http://") {
+            reqwest::Client::builder()
+                .timeout(MANIFEST_TIMEOUT)
+                .build()?
+                .get(location)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .with_context(|| format!("Failed to fetch registry manifest {}", location))?
+                .text()
+                .await?
+        } else {
+            let path = location.strip_prefix("file://").unwrap_or(location);
+            tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read registry manifest {}", path))?
+        };
+        Self::parse(&text, location)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ManifestModel> {
+        self.models.iter().find(|model| model.name == name)
+    }
+
+    /// Rows in the shape of `lit list` (name, URL, size) for the models whose
+    /// names aren't in `listing` already
+    pub fn listing_rows(&self, listing: &str) -> String {
+        let listed: Vec<RegistryEntry> = parse_listing(listing);
+        self.models
+            .iter()
+            .filter(|model| !listed.iter().any(|entry| entry.name == model.name))
+            .map(|model| match model.size_bytes {
+                Some(bytes) => format!("{}  {}  {:.2}GB\n", model.name, model.url, bytes as f64 / 1e9),
+                None => format!("{}  {}\n", model.name, model.url),
+            })
+            .collect()
+    }
+}
+
+/// A registry entry matching a search, best matches scoring highest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RegistryMatch {
+    #[serde(flatten)]
+    pub entry: RegistryEntry,
+    pub score: u32,
+}
+
+/// Models in `lit list` output, skipping headers and blank lines
+pub fn parse_listing(output: &str) -> Vec<RegistryEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let name = model_line_name(line)?;
+            Some(RegistryEntry {
+                name: name.to_string(),
+                details: line.split_whitespace().skip(1).map(str::to_string).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Entries matching every word of `query`, best first
+pub fn search(entries: &[RegistryEntry], query: &str) -> Vec<RegistryMatch> {
+    let terms: Vec<String> = query.split_whitespace().map(normalize).collect();
+    let mut matches: Vec<RegistryMatch> = entries
+        .iter()
+        .filter_map(|entry| {
+            let score = terms
+                .iter()
+                .map(|term| score_term(entry, term))
+                .try_fold(0, |total, score| score.map(|score| total + score))?;
+            Some(RegistryMatch {
+                entry: entry.clone(),
+                score,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.entry.name.cmp(&b.entry.name)));
+    matches
+}
+
+/// Lowercase with separators removed, so `gemma3 1b` and `Gemma3-1B` compare equal
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !matches!(c, '-' | '_' | '.' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// How well one query term matches an entry, or `None` if it doesn't
+fn score_term(entry: &RegistryEntry, term: &str) -> Option<u32> {
+    if term.is_empty() {
+        return Some(0);
+    }
+    let name = normalize(&entry.name);
+    if name == term {
+        Some(100)
+    } else if name.starts_with(term) {
+        Some(75)
+    } else if name.contains(term) {
+        Some(50)
+    } else if entry.details.iter().any(|detail| normalize(detail).contains(term)) {
+        Some(25)
+    } else if is_subsequence(term, &name) {
+        Some(10)
+    } else {
+        None
+    }
+}
+
+/// Whether the characters of `needle` appear in `haystack` in order
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
@@ -0,0 +1,200 @@
+//! Replaying recorded requests
+//!
+//! `litert-lm replay` re-runs prompts from the request history (or from a
+//! `history --json` export) against a chosen model and diffs each new response
+//! with the recorded one, so prompt behavior can be regression-tested after a
+//! model or binary upgrade. Only records kept with content recording enabled
+//! (`LITERT_HISTORY_CONTENT=1`) have a prompt to replay.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::generation::GenOptions;
+use crate::history::HistoryRecord;
+use crate::manager::LitManager;
+
+/// Records named by a request ID, or read from a JSON lines file of records
+/// such as the output of `litert-lm history --json`
+pub async fn load(manager: &LitManager, source: &str) -> Result<Vec<HistoryRecord>> {
+    let path = Path::new(source);
+    if path.is_file() {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return parse_records(&text).with_context(|| format!("Failed to parse {}", path.display()));
+    }
+    let record = manager
+        .history_entry(source)
+        .await?
+        .with_context(|| format!("No recorded request '{}' (and no such file)", source))?;
+    Ok(vec![record])
+}
+
+/// Records in JSON lines text, skipping blank lines
+pub fn parse_records(text: &str) -> Result<Vec<HistoryRecord>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("Invalid record on line {}", i + 1)))
+        .collect()
+}
+
+/// One line of a [`diff_lines`] result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "op", content = "line", rename_all = "lowercase")]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Line diff turning `old` into `new`, by longest common subsequence
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j]: length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|line| DiffLine::Removed(line.to_string())));
+    diff.extend(new[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+    diff
+}
+
+/// How a replayed request compares with its recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayStatus {
+    /// The new response matches the recorded one
+    Same,
+    /// The new response differs, or only one of the two runs failed
+    Changed,
+    /// Nothing to replay: the record has no prompt
+    Skipped,
+}
+
+/// The outcome of replaying one record
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayResult {
+    /// ID of the recorded request
+    pub id: String,
+    /// Model the request was recorded with
+    pub recorded_model: String,
+    /// Model it was replayed against
+    pub model: String,
+    pub status: ReplayStatus,
+    /// Why the request was skipped, or the replay's error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Recorded response (or error) against the replayed one; empty when the
+    /// two match
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diff: Vec<DiffLine>,
+}
+
+/// Re-run each record's prompt on `model` (or the model it was recorded
+/// with), bypassing the prompt cache, and compare the responses
+pub async fn replay(manager: &LitManager, records: &[HistoryRecord], model: Option<&str>) -> Vec<ReplayResult> {
+    // Recorded prompts are already in the model's template
+    let options = GenOptions {
+        cache: false,
+        raw: true,
+        ..GenOptions::default()
+    };
+    let mut results = Vec::with_capacity(records.len());
+    for record in records {
+        let model = model.unwrap_or(&record.model).to_string();
+        let mut result = ReplayResult {
+            id: record.id.clone(),
+            recorded_model: record.model.clone(),
+            model: model.clone(),
+            status: ReplayStatus::Skipped,
+            error: None,
+            diff: Vec::new(),
+        };
+        let Some(prompt) = &record.prompt else {
+            result.error = Some("No prompt recorded (enable LITERT_HISTORY_CONTENT=1)".to_string());
+            results.push(result);
+            continue;
+        };
+
+        tracing::debug!(id = %record.id, model = %model, "Replaying request");
+        let replayed = manager.run_completion_with(&model, prompt, &options).await;
+        let recorded = outcome_text(record.response.as_deref(), record.error.as_deref());
+        let current = match &replayed {
+            Ok(response) => response.clone(),
+            Err(e) => {
+                result.error = Some(e.to_string());
+                format!("error: {}", e)
+            }
+        };
+        if recorded.trim_end() == current.trim_end() {
+            result.status = ReplayStatus::Same;
+        } else {
+            result.status = ReplayStatus::Changed;
+            result.diff = diff_lines(&recorded, &current);
+        }
+        results.push(result);
+    }
+    results
+}
+
+/// What a recorded request produced, with errors in the same form as a
+/// failed replay so that a repeated failure compares equal
+fn outcome_text(response: Option<&str>, error: Option<&str>) -> String {
+    match (response, error) {
+        (_, Some(error)) => format!("error: {}", error),
+        (Some(response), None) => response.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Print results the way `litert-lm replay` shows them
+pub fn render(results: &[ReplayResult]) -> String {
+    let mut out = String::new();
+    for result in results {
+        let status = match result.status {
+            ReplayStatus::Same => "same",
+            ReplayStatus::Changed => "changed",
+            ReplayStatus::Skipped => "skipped",
+        };
+        out.push_str(&format!("{} {} ({})\n", status, result.id, result.model));
+        if result.status == ReplayStatus::Skipped {
+            if let Some(reason) = &result.error {
+                out.push_str(&format!("    {}\n", reason));
+            }
+        }
+        for line in &result.diff {
+            let (sign, text) = match line {
+                DiffLine::Same(text) => (' ', text),
+                DiffLine::Removed(text) => ('-', text),
+                DiffLine::Added(text) => ('+', text),
+            };
+            out.push_str(&format!("    {}{}\n", sign, text));
+        }
+    }
+    out
+}
@@ -0,0 +1,516 @@
+//! Local retrieval (RAG) over ingested documents
+//!
+//! Documents (`.txt`, `.md`, and `.pdf` via `pdftotext`) are split into
+//! overlapping chunks, embedded, and stored in an embedded SQLite index under
+//! the cache directory. Searches go through an HNSW graph ([`crate::hnsw`])
+//! built over the vectors when the index is opened.
+//!
+//! The lit binary does not expose embeddings, so chunks are embedded by a
+//! local sentence-embedding model ([`ModelEmbedder`], behind the `embeddings`
+//! feature) when `retrieval.embedding_model` points at one. Without it the
+//! [`HashingEmbedder`] is used, which matches words rather than meaning.
+//! Anything implementing [`Embedder`] can be plugged in with
+//! [`DocumentIndex::open_with`].
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::history::unix_now;
+use crate::hnsw::Hnsw;
+
+/// Target chunk size in characters
+pub const CHUNK_CHARS: usize = 1000;
+/// Characters shared between consecutive chunks
+pub const CHUNK_OVERLAP: usize = 200;
+
+pub trait Embedder: Send + Sync {
+    /// Identifies the embedding space; vectors from different ids are not comparable
+    fn id(&self) -> String;
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+/// Bag-of-words embedder using signed feature hashing
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self { dimensions: 512 }
+    }
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions }
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+impl Embedder for HashingEmbedder {
+    fn id(&self) -> String {
+        format!("hashing-{}", self.dimensions)
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect();
+
+        let bigrams = words.windows(2).map(|pair| format!("{} {}", pair[0], pair[1]));
+        for feature in words.iter().cloned().chain(bigrams) {
+            let hash = fnv1a(feature.as_bytes());
+            let index = (hash % self.dimensions as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[index] += sign;
+        }
+
+        normalize(&mut vector);
+        Ok(vector)
+    }
+}
+
+/// Sentence embeddings from a local BERT-family model (MiniLM, BGE, E5, ...)
+///
+/// The model directory holds `config.json`, `tokenizer.json`, and
+/// `model.safetensors`, as a Hugging Face repository does. A text embeds as
+/// the mean of its token embeddings, normalized; text past the model's
+/// position limit is cut off.
+#[cfg(feature = "embeddings")]
+pub struct ModelEmbedder {
+    id: String,
+    model: candle_transformers::models::bert::BertModel,
+    tokenizer: tokenizers::Tokenizer,
+    device: candle_core::Device,
+}
+
+#[cfg(feature = "embeddings")]
+impl ModelEmbedder {
+    pub fn load(dir: &Path) -> Result<Self> {
+        use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+
+        let config: Config = serde_json::from_str(
+            &std::fs::read_to_string(dir.join("config.json"))
+                .with_context(|| format!("Failed to read {}", dir.join("config.json").display()))?,
+        )
+        .context("Invalid embedding model config.json")?;
+        let mut tokenizer = tokenizers::Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", dir.join("tokenizer.json").display(), e))?;
+        tokenizer
+            .with_padding(None)
+            .with_truncation(Some(tokenizers::TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow::anyhow!("Failed to configure the embedding tokenizer: {}", e))?;
+
+        let device = candle_core::Device::Cpu;
+        // Safety: the weights file is not modified while it is mapped
+        let vb = unsafe {
+            candle_nn::VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DTYPE, &device)
+        }
+        .with_context(|| format!("Failed to read {}", dir.join("model.safetensors").display()))?;
+        let model = BertModel::load(vb, &config).context("Failed to load the embedding model")?;
+
+        let name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        Ok(Self {
+            id: format!("model:{}", name),
+            model,
+            tokenizer,
+            device,
+        })
+    }
+}
+
+#[cfg(feature = "embeddings")]
+impl std::fmt::Debug for ModelEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelEmbedder").field("id", &self.id).finish()
+    }
+}
+
+#[cfg(feature = "embeddings")]
+impl Embedder for ModelEmbedder {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        use candle_core::{DType, Tensor};
+
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| anyhow::anyhow!("Failed to tokenize for embedding: {}", e))?;
+        let ids = Tensor::new(encoding.get_ids(), &self.device)?.unsqueeze(0)?;
+        let type_ids = Tensor::new(encoding.get_type_ids(), &self.device)?.unsqueeze(0)?;
+        let mask = Tensor::new(encoding.get_attention_mask(), &self.device)?.unsqueeze(0)?;
+
+        // [1, tokens, hidden], averaged over the tokens the mask keeps
+        let hidden = self.model.forward(&ids, &type_ids, Some(&mask))?;
+        let mask = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let pooled = summed.broadcast_div(&mask.sum(1)?)?;
+
+        let mut vector = pooled.squeeze(0)?.to_vec1::<f32>()?;
+        normalize(&mut vector);
+        Ok(vector)
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// Split text into chunks of about `max_chars`, preferring paragraph and
+/// sentence boundaries, with `overlap` characters carried into the next chunk
+pub fn chunk_text(text: &str, max_chars: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + max_chars).min(chars.len());
+        if end < chars.len() {
+            // Back off to the last paragraph break, sentence end, or space in the second half
+            let window = &chars[start + max_chars / 2..end];
+            let boundary = find_last(window, &['\n', '\n'])
+                .or_else(|| window.iter().rposition(|c| matches!(c, '.' | '!' | '?')))
+                .or_else(|| window.iter().rposition(|c| c.is_whitespace()));
+            if let Some(offset) = boundary {
+                end = start + max_chars / 2 + offset + 1;
+            }
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+
+        if end == chars.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+
+    chunks
+}
+
+fn find_last(haystack: &[char], needle: &[char]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|w| w == needle)
+        .map(|i| i + needle.len() - 1)
+}
+
+/// Read a document as plain text; PDFs are converted with `pdftotext`
+pub fn read_document(path: &Path) -> Result<String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "pdf" => {
+            let output = std::process::Command::new("pdftotext")
+                .arg("-layout")
+                .arg(path)
+                .arg("-")
+                .output()
+                .context("Failed to run pdftotext (install poppler-utils to ingest PDFs)")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "pdftotext failed for {}: {}",
+                    path.display(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        "txt" | "md" | "markdown" | "" => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display())),
+        other => anyhow::bail!("Unsupported document type '.{}' (expected txt, md, or pdf)", other),
+    }
+}
+
+/// One ingested document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    pub source: String,
+    pub chunks: usize,
+    /// Unix timestamp (seconds)
+    pub ingested_at: u64,
+}
+
+/// A chunk matching a search query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub source: String,
+    /// Position of the chunk within its document
+    pub chunk: usize,
+    pub score: f32,
+    pub text: String,
+}
+
+struct IndexedChunk {
+    source: String,
+    ordinal: usize,
+    vector: Vec<f32>,
+    text: String,
+}
+
+// Chunks by graph id; removed chunks leave a `None` until the graph is rebuilt
+#[derive(Default)]
+struct Chunks {
+    graph: Hnsw,
+    by_id: Vec<Option<IndexedChunk>>,
+}
+
+impl Chunks {
+    fn insert(&mut self, chunk: IndexedChunk) {
+        let id = self.graph.insert(chunk.vector.clone());
+        debug_assert_eq!(id, self.by_id.len());
+        self.by_id.push(Some(chunk));
+    }
+
+    fn remove_source(&mut self, source: &str) {
+        for (id, slot) in self.by_id.iter_mut().enumerate() {
+            if slot.as_ref().is_some_and(|chunk| chunk.source == source) {
+                *slot = None;
+                self.graph.remove(id);
+            }
+        }
+        // Removed chunks slow searches down; rebuild once they outnumber the rest
+        if self.graph.removed() > self.graph.len() {
+            let live: Vec<IndexedChunk> = std::mem::take(&mut self.by_id).into_iter().flatten().collect();
+            *self = Self::default();
+            live.into_iter().for_each(|chunk| self.insert(chunk));
+        }
+    }
+}
+
+pub struct DocumentIndex {
+    conn: Mutex<Connection>,
+    embedder: Arc<dyn Embedder>,
+    chunks: Mutex<Chunks>,
+}
+
+impl std::fmt::Debug for DocumentIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DocumentIndex")
+            .field("embedder", &self.embedder.id())
+            .field("chunks", &self.chunks.lock().unwrap().graph.len())
+            .finish()
+    }
+}
+
+impl DocumentIndex {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, Arc::new(HashingEmbedder::default()))
+    }
+
+    pub fn open_with(path: &Path, embedder: Arc<dyn Embedder>) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open retrieval index at {}", path.display()))?;
+        Self::with_connection(conn, embedder)
+    }
+
+    /// In-memory index, mostly useful for tests
+    pub fn open_in_memory(embedder: Arc<dyn Embedder>) -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?, embedder)
+    }
+
+    fn with_connection(conn: Connection, embedder: Arc<dyn Embedder>) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS documents (
+                source TEXT PRIMARY KEY,
+                ingested_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS chunks (
+                source TEXT NOT NULL REFERENCES documents (source) ON DELETE CASCADE,
+                ordinal INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                PRIMARY KEY (source, ordinal)
+            );
+            PRAGMA foreign_keys = ON;",
+        )
+        .context("Failed to initialize retrieval schema")?;
+
+        let stored: Option<String> = conn
+            .query_row("SELECT value FROM meta WHERE key = 'embedder'", [], |row| row.get(0))
+            .optional()?;
+        match stored {
+            Some(id) if id != embedder.id() => anyhow::bail!(
+                "Retrieval index was built with embedder '{}' but '{}' is in use; re-ingest into a new index",
+                id,
+                embedder.id()
+            ),
+            Some(_) => {}
+            None => {
+                conn.execute(
+                    "INSERT INTO meta (key, value) VALUES ('embedder', ?1)",
+                    params![embedder.id()],
+                )?;
+            }
+        }
+
+        let mut chunks = Chunks::default();
+        {
+            let mut stmt =
+                conn.prepare("SELECT source, ordinal, text, embedding FROM chunks ORDER BY source, ordinal")?;
+            let rows = stmt.query_map([], |row| {
+                let blob: Vec<u8> = row.get(3)?;
+                Ok(IndexedChunk {
+                    source: row.get(0)?,
+                    ordinal: row.get::<_, i64>(1)? as usize,
+                    text: row.get(2)?,
+                    vector: decode_vector(&blob),
+                })
+            })?;
+            for row in rows {
+                chunks.insert(row.context("Failed to load retrieval index")?);
+            }
+        }
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            embedder,
+            chunks: Mutex::new(chunks),
+        })
+    }
+
+    /// Ingest a file, replacing any earlier version with the same path
+    pub fn ingest_file(&self, path: &Path) -> Result<DocumentInfo> {
+        let text = read_document(path)?;
+        self.ingest_text(&path.display().to_string(), &text)
+    }
+
+    /// Chunk, embed, and store `text` under `source`, replacing earlier content
+    pub fn ingest_text(&self, source: &str, text: &str) -> Result<DocumentInfo> {
+        let pieces = chunk_text(text, CHUNK_CHARS, CHUNK_OVERLAP);
+        let embedded = pieces
+            .into_iter()
+            .map(|piece| Ok((self.embedder.embed(&piece)?, piece)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let ingested_at = unix_now();
+        {
+            let mut conn = self.conn.lock().unwrap();
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM chunks WHERE source = ?1", params![source])?;
+            tx.execute(
+                "INSERT OR REPLACE INTO documents (source, ingested_at) VALUES (?1, ?2)",
+                params![source, ingested_at as i64],
+            )?;
+            for (ordinal, (vector, piece)) in embedded.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO chunks (source, ordinal, text, embedding) VALUES (?1, ?2, ?3, ?4)",
+                    params![source, ordinal as i64, piece, encode_vector(vector)],
+                )?;
+            }
+            tx.commit().context("Failed to store document chunks")?;
+        }
+
+        let count = embedded.len();
+        let mut chunks = self.chunks.lock().unwrap();
+        chunks.remove_source(source);
+        for (ordinal, (vector, text)) in embedded.into_iter().enumerate() {
+            chunks.insert(IndexedChunk {
+                source: source.to_string(),
+                ordinal,
+                vector,
+                text,
+            });
+        }
+
+        tracing::info!(source = %source, chunks = count, "Ingested document");
+        Ok(DocumentInfo {
+            source: source.to_string(),
+            chunks: count,
+            ingested_at,
+        })
+    }
+
+    /// Remove a document and its chunks; returns whether it existed
+    pub fn remove(&self, source: &str) -> Result<bool> {
+        let removed = {
+            let conn = self.conn.lock().unwrap();
+            conn.execute("DELETE FROM chunks WHERE source = ?1", params![source])?;
+            conn.execute("DELETE FROM documents WHERE source = ?1", params![source])? > 0
+        };
+        self.chunks.lock().unwrap().remove_source(source);
+        Ok(removed)
+    }
+
+    pub fn documents(&self) -> Result<Vec<DocumentInfo>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT d.source, d.ingested_at, COUNT(c.ordinal)
+             FROM documents d LEFT JOIN chunks c ON c.source = d.source
+             GROUP BY d.source ORDER BY d.source",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(DocumentInfo {
+                source: row.get(0)?,
+                ingested_at: row.get::<_, i64>(1)? as u64,
+                chunks: row.get::<_, i64>(2)? as usize,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to list documents")
+    }
+
+    /// The `top_k` chunks most similar to `query`, best first
+    pub fn search(&self, query: &str, top_k: usize) -> Result<Vec<SearchHit>> {
+        let query_vector = self.embedder.embed(query)?;
+        let chunks = self.chunks.lock().unwrap();
+
+        Ok(chunks
+            .graph
+            .search(&query_vector, top_k)
+            .into_iter()
+            .filter(|(_, score)| *score > 0.0)
+            .filter_map(|(id, score)| {
+                let chunk = chunks.by_id[id].as_ref()?;
+                Some(SearchHit {
+                    source: chunk.source.clone(),
+                    chunk: chunk.ordinal,
+                    score,
+                    text: chunk.text.clone(),
+                })
+            })
+            .collect())
+    }
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
@@ -0,0 +1,145 @@
+//! Retrying transient failures
+//!
+//! Downloading the lit binary, pulling models, and starting a model's
+//! processes can fail for reasons that go away on their own: a dropped
+//! connection, a truncated download, a process that dies while loading. A
+//! [`RetryPolicy`], set with
+//! [`LitManager::with_retry_policy`](crate::LitManager::with_retry_policy),
+//! decides how often each is tried and how long to wait in between. Cancelled
+//! operations and [`Permanent`] failures (such as a release that has no build
+//! for this platform) are never retried.
+
+use anyhow::Result;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::cancel::{self, Cancelled};
+use crate::metrics::Metrics;
+
+/// What kind of failure an operation hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// A binary download or model pull failed
+    Network,
+    /// A downloaded binary doesn't run
+    Corrupt,
+    /// A lit process failed to start or load its model
+    Process,
+}
+
+impl ErrorClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::Network => "network",
+            ErrorClass::Corrupt => "corrupt",
+            ErrorClass::Process => "process",
+        }
+    }
+}
+
+/// Marks a failure that trying again can't fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permanent;
+
+impl std::fmt::Display for Permanent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Permanent failure")
+    }
+}
+
+impl std::error::Error for Permanent {}
+
+/// How transient failures are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Tries in total, including the first; 1 disables retrying
+    pub attempts: usize,
+    /// Wait before the first retry, doubled for every retry after it
+    pub backoff: Duration,
+    /// Longest wait between tries
+    pub max_backoff: Duration,
+    /// Failures that are retried; others fail on the first try
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            retry_on: vec![ErrorClass::Network, ErrorClass::Corrupt, ErrorClass::Process],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Fail on the first error
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration, max_backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_retry_on(mut self, classes: impl IntoIterator<Item = ErrorClass>) -> Self {
+        self.retry_on = classes.into_iter().collect();
+        self
+    }
+
+    /// Wait after the failure of try `attempt` (from 1)
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31) as u32;
+        self.backoff.saturating_mul(1 << doublings).min(self.max_backoff)
+    }
+
+    /// Whether try `attempt` (from 1), which failed with `error`, is tried again
+    pub fn should_retry(&self, class: ErrorClass, attempt: usize, error: &anyhow::Error) -> bool {
+        attempt < self.attempts
+            && self.retry_on.contains(&class)
+            && !error.is::<Cancelled>()
+            && !error.is::<Permanent>()
+    }
+
+    /// After try `attempt` of `operation` failed with `error`, wait out the
+    /// backoff if it is retried, or return the error if not
+    pub(crate) async fn wait_to_retry(
+        &self,
+        operation: &str,
+        class: ErrorClass,
+        attempt: usize,
+        error: anyhow::Error,
+        cancel: Option<&CancellationToken>,
+        metrics: &Metrics,
+    ) -> Result<()> {
+        if !self.should_retry(class, attempt, &error) {
+            return Err(error);
+        }
+        let backoff = self.backoff(attempt);
+        metrics.retries.with_label_values(&[operation, class.as_str()]).inc();
+        tracing::warn!(
+            operation,
+            class = class.as_str(),
+            attempt,
+            backoff_ms = backoff.as_millis() as u64,
+            error = %format!("{:#}", error),
+            "Retrying after a failure"
+        );
+        cancel::run(cancel, async {
+            tokio::time::sleep(backoff).await;
+            Ok(())
+        })
+        .await
+    }
+}
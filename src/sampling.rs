@@ -0,0 +1,92 @@
+//! Sampling settings passed to lit
+//!
+//! lit reads `--temperature`, `--top_p`, `--top_k`, and `--seed` when a process starts,
+//! like the flags in [`crate::lit_args`]. A model's processes start with its
+//! defaults from the `sampling` config section; a request asking for other
+//! settings is served by a pool started with them, kept next to the model's
+//! default pool.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::tenant::matches_pattern;
+
+/// Temperature, nucleus, and top-k sampling; unset values are lit's own
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sampling {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    /// Seed of the sampler, for repeatable output
+    pub seed: Option<u64>,
+}
+
+impl Sampling {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// These settings, with `defaults` filling in the unset ones
+    pub fn or(self, defaults: Sampling) -> Sampling {
+        Sampling {
+            temperature: self.temperature.or(defaults.temperature),
+            top_p: self.top_p.or(defaults.top_p),
+            top_k: self.top_k.or(defaults.top_k),
+            seed: self.seed.or(defaults.seed),
+        }
+    }
+
+    /// Reject values lit can't sample with
+    pub fn check(&self) -> Result<(), String> {
+        if self.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            return Err("temperature must be between 0 and 2".to_string());
+        }
+        if self.top_p.is_some_and(|p| !(p > 0.0 && p <= 1.0)) {
+            return Err("top_p must be greater than 0 and at most 1".to_string());
+        }
+        if self.top_k == Some(0) {
+            return Err("top_k must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Command-line arguments for lit
+    pub fn flags(&self) -> Vec<String> {
+        // f32's Display is its shortest form (0.7, not 0.699999988)
+        [
+            ("temperature", self.temperature.map(|t| t.to_string())),
+            ("top_p", self.top_p.map(|p| p.to_string())),
+            ("top_k", self.top_k.map(|k| k.to_string())),
+            ("seed", self.seed.map(|seed| seed.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some([format!("--{}", name), value?]))
+        .flatten()
+        .collect()
+    }
+}
+
+/// Default sampling settings by model name or pattern (e.g. `gemma-3n-*`)
+#[derive(Debug, Clone, Default)]
+pub struct SamplingDefaults {
+    by_pattern: HashMap<String, Sampling>,
+}
+
+impl SamplingDefaults {
+    pub fn from_config(config: &HashMap<String, Sampling>) -> Self {
+        Self {
+            by_pattern: config.clone(),
+        }
+    }
+
+    /// Defaults of `model`: the most specific matching pattern's, else lit's own
+    pub fn for_model(&self, model: &str) -> Sampling {
+        self.by_pattern
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, &sampling)| sampling)
+            .unwrap_or_default()
+    }
+}
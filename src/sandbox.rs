@@ -0,0 +1,260 @@
+//! Containment for lit processes
+//!
+//! lit executes downloaded model files, so operators may want it to run with
+//! less than the server's own privileges without reaching for containers. The
+//! `sandbox` config section starts every lit process as another user, with a
+//! restricted environment and working directory, and on Linux with
+//! `no_new_privs` and a small seccomp filter.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::config::SandboxConfig;
+
+/// User (and group) lit processes run as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxUser {
+    /// `None` for a uid without an entry in `/etc/passwd`
+    pub name: Option<String>,
+    pub uid: u32,
+    pub gid: u32,
+    pub home: Option<PathBuf>,
+}
+
+impl SandboxUser {
+    /// Look up a user by name, uid, or `uid:gid` in `/etc/passwd`
+    pub fn lookup(spec: &str) -> Result<Self> {
+        let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+        Self::find(&passwd, spec).with_context(|| format!("Unknown sandbox user '{}'", spec))
+    }
+
+    /// Find a user in the contents of a passwd file; bare uids need no entry
+    pub fn find(passwd: &str, spec: &str) -> Option<Self> {
+        let (user, gid) = match spec.split_once(':') {
+            Some((uid, gid)) => (uid, Some(gid.parse().ok()?)),
+            None => (spec, None),
+        };
+        let entry = passwd.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let [name, _, uid, gid, _, home, ..] = fields[..] else {
+                return None;
+            };
+            if name != user && uid != user {
+                return None;
+            }
+            Some(Self {
+                name: Some(name.to_string()),
+                uid: uid.parse().ok()?,
+                gid: gid.parse().ok()?,
+                home: Some(PathBuf::from(home)),
+            })
+        });
+        let mut user = match entry {
+            Some(entry) => entry,
+            None => {
+                let uid = user.parse().ok()?;
+                Self { name: None, uid, gid: uid, home: None }
+            }
+        };
+        if let Some(gid) = gid {
+            user.gid = gid;
+        }
+        Some(user)
+    }
+}
+
+/// Resolved [`SandboxConfig`], applied to every lit process as it is spawned
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    user: Option<SandboxUser>,
+    env: Option<Vec<String>>,
+    working_dir: Option<PathBuf>,
+    no_new_privileges: bool,
+    seccomp: bool,
+}
+
+impl Sandbox {
+    /// Resolve the configured user and check the working directory, failing
+    /// for options this platform can't enforce
+    pub fn from_config(config: &SandboxConfig) -> Result<Self> {
+        let user = config.user.as_deref().map(SandboxUser::lookup).transpose()?;
+        if user.is_some() && !cfg!(unix) {
+            bail!("sandbox.user is only supported on Unix");
+        }
+        if let Some(dir) = &config.working_dir {
+            if !dir.is_dir() {
+                bail!("Sandbox working directory {} does not exist", dir.display());
+            }
+        }
+        if config.no_new_privileges && !cfg!(target_os = "linux") {
+            bail!("sandbox.no_new_privileges is only supported on Linux");
+        }
+        if config.seccomp && !SECCOMP_SUPPORTED {
+            bail!("sandbox.seccomp is only supported on Linux on x86_64 and aarch64");
+        }
+        Ok(Self {
+            user,
+            env: config.env.clone(),
+            working_dir: config.working_dir.clone(),
+            no_new_privileges: config.no_new_privileges,
+            seccomp: config.seccomp,
+        })
+    }
+
+    /// Whether any restriction is configured
+    pub fn is_enabled(&self) -> bool {
+        self.user.is_some()
+            || self.env.is_some()
+            || self.working_dir.is_some()
+            || self.no_new_privileges
+            || self.seccomp
+    }
+
+    pub fn user(&self) -> Option<&SandboxUser> {
+        self.user.as_ref()
+    }
+
+    pub fn working_dir(&self) -> Option<&Path> {
+        self.working_dir.as_deref()
+    }
+
+    /// Set up `command` to start inside the sandbox
+    pub(crate) fn apply(&self, command: &mut Command) {
+        if let Some(names) = &self.env {
+            command.env_clear();
+            for name in names {
+                if let Some(value) = std::env::var_os(name) {
+                    command.env(name, value);
+                }
+            }
+        }
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+        #[cfg(unix)]
+        if let Some(user) = &self.user {
+            // Supplementary groups are dropped along with root
+            command.uid(user.uid).gid(user.gid);
+            if let Some(home) = &user.home {
+                command.env("HOME", home);
+            }
+            if let Some(name) = &user.name {
+                command.env("USER", name).env("LOGNAME", name);
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if self.no_new_privileges || self.seccomp {
+            // Built before forking: the child may not allocate
+            let filter = self.seccomp.then(linux::filter);
+            // SAFETY: `restrict` only makes async-signal-safe prctl and seccomp calls
+            unsafe {
+                command.pre_exec(move || linux::restrict(filter.as_ref()));
+            }
+        }
+    }
+}
+
+// The seccomp filter knows the syscall numbers of these targets only
+const SECCOMP_SUPPORTED: bool = cfg!(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")));
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use seccompiler::{BpfProgram, SeccompAction, SeccompFilter};
+    use std::collections::BTreeMap;
+
+    /// Syscalls lit has no business making; they fail with EPERM
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED: &[i64] = &[
+        libc::SYS_ptrace,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_reboot,
+        libc::SYS_sethostname,
+        libc::SYS_setdomainname,
+        libc::SYS_init_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_acct,
+        libc::SYS_settimeofday,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_finit_module,
+        libc::SYS_kexec_file_load,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        libc::SYS_open_by_handle_at,
+    ];
+
+    /// Filters installed in order; the kernel applies the strictest answer
+    pub type Filters = Vec<BpfProgram>;
+
+    /// Filter that kills processes making syscalls of another architecture
+    /// and fails the denied syscalls with EPERM
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub fn filter() -> Filters {
+        let rules = DENIED.iter().map(|&nr| (nr, Vec::new())).collect::<BTreeMap<_, _>>();
+        let arch = std::env::consts::ARCH.try_into().expect("x86_64 and aarch64 are seccompiler targets");
+        let denied = SeccompFilter::new(rules, SeccompAction::Allow, SeccompAction::Errno(libc::EPERM as u32), arch)
+            .and_then(BpfProgram::try_from)
+            .expect("the denied syscalls make a valid filter");
+        let mut filters = vec![denied];
+        filters.extend(x32_filter());
+        filters
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn filter() -> Filters {
+        unreachable!("rejected by Sandbox::from_config")
+    }
+
+    /// x32 syscalls share the x86_64 audit arch, so seccompiler's arch check
+    /// lets them through under other numbers; fail all of them
+    #[cfg(target_arch = "x86_64")]
+    fn x32_filter() -> Option<BpfProgram> {
+        use seccompiler::sock_filter;
+        // `__X32_SYSCALL_BIT`, which libc only defines when targeting x32
+        const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+        let op = |code: u32, jt: u8, jf: u8, k: u32| sock_filter { code: code as u16, jt, jf, k };
+        Some(vec![
+            // seccomp_data.nr
+            op(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 0, 0, 0),
+            op(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, 0, 1, X32_SYSCALL_BIT),
+            op(libc::BPF_RET | libc::BPF_K, 0, 0, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32),
+            op(libc::BPF_RET | libc::BPF_K, 0, 0, libc::SECCOMP_RET_ALLOW),
+        ])
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    fn x32_filter() -> Option<BpfProgram> {
+        None
+    }
+
+    /// Runs in the forked child right before exec, after the user switch
+    pub fn restrict(filters: Option<&Filters>) -> std::io::Result<()> {
+        // SAFETY: a plain prctl call
+        // Also lets an unprivileged process install the seccomp filters
+        if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        for filter in filters.into_iter().flatten() {
+            seccompiler::apply_filter(filter).map_err(|e| match e {
+                seccompiler::Error::Prctl(e) | seccompiler::Error::Seccomp(e) => e,
+                // Only built filters reach here, so nothing else can go wrong
+                _ => std::io::Error::from_raw_os_error(libc::EINVAL),
+            })?;
+        }
+        Ok(())
+    }
+}
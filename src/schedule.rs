@@ -0,0 +1,242 @@
+//! Loading and unloading models on a schedule
+//!
+//! `schedule` entries in the config pair a model with cron expressions for when
+//! to load and unload it, e.g. a large model overnight for batch jobs and a
+//! small one during the day. Expressions have the usual five fields (minute,
+//! hour, day of month, month, day of week) and are evaluated in UTC. While
+//! serving, due transitions are applied at the start of every minute; on start,
+//! each entry is put in the state of whichever of its transitions fired last.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::ScheduleConfig;
+use crate::history::unix_now;
+use crate::manager::{LitManager, ManagerEvent};
+
+/// How far back the state on start is looked up
+const LOOKBACK_MINUTES: u64 = 31 * 24 * 60;
+
+/// What a schedule does to its model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleAction {
+    Load,
+    Unload,
+}
+
+/// A five-field cron expression, such as `0 22 * * 1-5`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // Standard cron matches either day field when both are restricted
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            anyhow::bail!(
+                "'{}' is not a cron expression; expected 5 fields (minute hour day month weekday)",
+                expression
+            );
+        };
+        let parse = |field, min, max, name| {
+            parse_field(field, min, max).with_context(|| format!("Invalid {} field in cron expression '{}'", name, expression))
+        };
+        let mut weekdays = parse(weekday, 0, 7, "weekday")?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse(minute, 0, 59, "minute")?,
+            hours: parse(hour, 0, 23, "hour")?,
+            days: parse(day, 1, 31, "day")?,
+            months: parse(month, 1, 12, "month")?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// Whether the expression fires in the minute containing `unix_secs` (UTC)
+    pub fn matches(&self, unix_secs: u64) -> bool {
+        let minute = unix_secs / 60 % 60;
+        let hour = unix_secs / 3600 % 24;
+        let days = unix_secs / 86_400;
+        let (_, month, day) = civil_from_days(days);
+        // 1970-01-01 was a Thursday
+        let weekday = (days + 4) % 7;
+
+        let day_matches = self.days & (1 << day) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+        let day_ok = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday_matches,
+            (false, true) => day_matches,
+            (false, false) => day_matches || weekday_matches,
+        };
+        self.minutes & (1 << minute) != 0 && self.hours & (1 << hour) != 0 && self.months & (1 << month) != 0 && day_ok
+    }
+
+    /// Start of the latest minute, at or before `unix_secs` and at most
+    /// `lookback_minutes` earlier, in which the expression fires
+    pub fn last_fired(&self, unix_secs: u64, lookback_minutes: u64) -> Option<u64> {
+        let minute = unix_secs - unix_secs % 60;
+        (0..=lookback_minutes)
+            .map_while(|back| minute.checked_sub(back * 60))
+            .find(|&at| self.matches(at))
+    }
+}
+
+/// Bitmask of the values a field allows: `*`, `5`, `1-5`, `*/15`, `0-30/10`, or a comma-separated list
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64> {
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().ok().filter(|s| *s > 0).context("step must be a positive number")?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (start.parse()?, end.parse()?),
+                None => {
+                    let value = range.parse()?;
+                    // `5/15` means from 5 to the end in steps of 15
+                    (value, if step > 1 { max } else { value })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            anyhow::bail!("'{}' is outside {}-{}", part, min, max);
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// (year, month, day) of a day counted from 1970-01-01
+fn civil_from_days(days: u64) -> (i64, u64, u64) {
+    // Howard Hinnant's days-to-civil algorithm
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `unix_secs` as an RFC 3339 UTC timestamp, e.g. `2026-10-16T12:00:00Z`
+pub fn format_utc(unix_secs: u64) -> String {
+    let (year, month, day) = civil_from_days(unix_secs / 86_400);
+    let secs = unix_secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// When a model is loaded and unloaded
+#[derive(Debug, Clone)]
+pub struct ModelSchedule {
+    pub model: String,
+    pub load: Option<Cron>,
+    pub unload: Option<Cron>,
+}
+
+impl ModelSchedule {
+    pub fn from_config(config: &ScheduleConfig) -> Result<Self> {
+        let parse = |expression: &Option<String>| expression.as_deref().map(Cron::parse).transpose();
+        let schedule = Self {
+            model: config.model.clone(),
+            load: parse(&config.load).with_context(|| format!("Invalid schedule for {}", config.model))?,
+            unload: parse(&config.unload).with_context(|| format!("Invalid schedule for {}", config.model))?,
+        };
+        if schedule.load.is_none() && schedule.unload.is_none() {
+            anyhow::bail!("The schedule for {} needs a load or unload time", config.model);
+        }
+        Ok(schedule)
+    }
+
+    /// Transition due in the minute starting at `unix_secs`; loading wins
+    /// when both fire
+    pub fn due(&self, unix_secs: u64) -> Option<ScheduleAction> {
+        if self.load.as_ref().is_some_and(|cron| cron.matches(unix_secs)) {
+            Some(ScheduleAction::Load)
+        } else if self.unload.as_ref().is_some_and(|cron| cron.matches(unix_secs)) {
+            Some(ScheduleAction::Unload)
+        } else {
+            None
+        }
+    }
+
+    /// The transition that fired last as of `unix_secs`, if any did in the past month
+    pub fn state_at(&self, unix_secs: u64) -> Option<ScheduleAction> {
+        let last = |cron: &Option<Cron>| cron.as_ref().and_then(|cron| cron.last_fired(unix_secs, LOOKBACK_MINUTES));
+        match (last(&self.load), last(&self.unload)) {
+            (Some(load), Some(unload)) if unload > load => Some(ScheduleAction::Unload),
+            (Some(_), _) => Some(ScheduleAction::Load),
+            (None, Some(_)) => Some(ScheduleAction::Unload),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Apply `schedules` to the manager's pools until the task is dropped
+pub async fn run(manager: LitManager, schedules: Vec<ModelSchedule>) {
+    let now = unix_now();
+    for schedule in &schedules {
+        if let Some(action) = schedule.state_at(now) {
+            apply(&manager, &schedule.model, action).await;
+        }
+    }
+    loop {
+        let now = unix_now();
+        let minute = now - now % 60 + 60;
+        tokio::time::sleep(Duration::from_secs(minute - now)).await;
+        for schedule in &schedules {
+            if let Some(action) = schedule.due(minute) {
+                apply(&manager, &schedule.model, action).await;
+            }
+        }
+    }
+}
+
+async fn apply(manager: &LitManager, model: &str, action: ScheduleAction) {
+    tracing::info!(model = %model, action = ?action, "Applying model schedule");
+    let result = match action {
+        ScheduleAction::Load => manager.preload(&[model.to_string()]).await.map(drop),
+        ScheduleAction::Unload => {
+            manager.unload(model).await;
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        tracing::error!(model = %model, action = ?action, error = %format!("{:#}", e), "Scheduled transition failed");
+        manager.emit(ManagerEvent::ScheduleFailed {
+            model: model.to_string(),
+            action,
+            error: format!("{:#}", e),
+        });
+    }
+}
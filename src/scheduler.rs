@@ -0,0 +1,210 @@
+//! Weighted fair queuing across tenants
+//!
+//! A pool admits one generation per process at a time. When more callers want
+//! a slot, [`FairScheduler`] uses start-time fair queuing: each request is
+//! tagged with `max(virtual clock, tenant's last finish tag)`, its tenant's
+//! finish tag advances by `1 / weight`, and the waiting request with the
+//! smallest tag is admitted next. A tenant with a deep backlog therefore can't
+//! starve a light one, and a tenant with weight 2 gets twice the share of a
+//! saturated pool.
+//!
+//! The queue can be capped, in which case callers beyond the cap are turned
+//! away rather than queued, so a burst can't push everyone's wait up.
+//!
+//! A tenant's finish tag is forgotten once it has nothing admitted or waiting,
+//! so a scheduler serving many short-lived tenants doesn't grow with them.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// Virtual cost of one request at weight 1
+const UNIT_COST: u64 = 1_000_000;
+
+#[derive(Debug)]
+pub struct FairScheduler {
+    capacity: usize,
+    max_waiting: Option<usize>,
+    state: Mutex<SchedulerState>,
+}
+
+#[derive(Debug, Default)]
+struct SchedulerState {
+    running: usize,
+    virtual_time: u64,
+    tenants: HashMap<String, TenantTags>,
+    waiting: BinaryHeap<Waiter>,
+    sequence: u64,
+}
+
+#[derive(Debug, Default)]
+struct TenantTags {
+    finish: u64,
+    // Requests admitted or waiting
+    outstanding: usize,
+}
+
+#[derive(Debug)]
+struct Waiter {
+    tag: u64,
+    sequence: u64,
+    tenant: String,
+    slot: oneshot::Sender<Admission>,
+}
+
+// Max-heap order reversed so the smallest tag (then the oldest request) pops first
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.tag, other.sequence).cmp(&(self.tag, self.sequence))
+    }
+}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        (self.tag, self.sequence) == (other.tag, other.sequence)
+    }
+}
+
+impl Eq for Waiter {}
+
+/// A slot in the pool; the next waiter is admitted when it is dropped
+#[derive(Debug)]
+pub struct Admission {
+    scheduler: Arc<FairScheduler>,
+    tenant: String,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.tenant);
+    }
+}
+
+/// Why [`FairScheduler::acquire`] didn't admit a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejected {
+    /// `max_waiting` requests are already queued
+    QueueFull,
+    /// The scheduler went away while the request waited
+    Closed,
+}
+
+impl std::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejected::QueueFull => write!(f, "The queue is full"),
+            Rejected::Closed => write!(f, "The scheduler was closed while waiting"),
+        }
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+impl FairScheduler {
+    /// Admit up to `capacity` concurrent requests
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Self::with_max_waiting(capacity, None)
+    }
+
+    /// Admit up to `capacity` concurrent requests, with at most `max_waiting`
+    /// more waiting for a slot
+    pub fn with_max_waiting(capacity: usize, max_waiting: Option<usize>) -> Arc<Self> {
+        Arc::new(Self {
+            capacity: capacity.max(1),
+            max_waiting,
+            state: Mutex::new(SchedulerState::default()),
+        })
+    }
+
+    /// Wait for a slot on behalf of `tenant`; fails with
+    /// [`Rejected::QueueFull`] right away when the queue is full
+    pub async fn acquire(self: &Arc<Self>, tenant: &str, weight: u32) -> Result<Admission, Rejected> {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            let free = state.running < self.capacity && state.waiting.is_empty();
+            if !free && self.max_waiting.is_some_and(|max| state.waiting.len() >= max) {
+                return Err(Rejected::QueueFull);
+            }
+            let virtual_time = state.virtual_time;
+            let tags = state.tenants.entry(tenant.to_string()).or_default();
+            let tag = virtual_time.max(tags.finish);
+            tags.finish = tag + UNIT_COST / u64::from(weight.max(1));
+            tags.outstanding += 1;
+
+            if free {
+                state.running += 1;
+                state.virtual_time = tag;
+                return Ok(Admission {
+                    scheduler: self.clone(),
+                    tenant: tenant.to_string(),
+                });
+            }
+
+            let (slot, receiver) = oneshot::channel();
+            state.sequence += 1;
+            let sequence = state.sequence;
+            state.waiting.push(Waiter {
+                tag,
+                sequence,
+                tenant: tenant.to_string(),
+                slot,
+            });
+            receiver
+        };
+
+        receiver.await.map_err(|_| Rejected::Closed)
+    }
+
+    /// Requests currently admitted
+    pub fn running(&self) -> usize {
+        self.state.lock().unwrap().running
+    }
+
+    /// Requests waiting for a slot
+    pub fn waiting(&self) -> usize {
+        self.state.lock().unwrap().waiting.len()
+    }
+
+    /// Tenants with requests admitted or waiting
+    pub fn tenants(&self) -> usize {
+        self.state.lock().unwrap().tenants.len()
+    }
+
+    fn release(self: &Arc<Self>, tenant: &str) {
+        let next = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(tags) = state.tenants.get_mut(tenant) {
+                tags.outstanding -= 1;
+                if tags.outstanding == 0 {
+                    state.tenants.remove(tenant);
+                }
+            }
+            match state.waiting.pop() {
+                Some(waiter) => {
+                    state.virtual_time = state.virtual_time.max(waiter.tag);
+                    Some(waiter)
+                }
+                None => {
+                    state.running -= 1;
+                    None
+                }
+            }
+        };
+
+        // The slot passes straight to the next waiter; if it gave up waiting,
+        // the returned admission is dropped and released again
+        if let Some(waiter) = next {
+            let _ = waiter.slot.send(Admission {
+                scheduler: self.clone(),
+                tenant: waiter.tenant,
+            });
+        }
+    }
+}
@@ -1,130 +1,522 @@
 use axum::{
-    extract::{Path, State},
+    extract::{rejection::JsonRejection, Path, Request, State},
+    middleware::{self, Next},
     http::StatusCode,
     response::sse::{Event, Sse},
     response::{IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    routing::{delete, get, post},
+    Extension, Json, Router,
 };
 use futures_util::stream::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Instant;
 use tower_http::trace::TraceLayer;
 
-use crate::process::ProcessPool;
-
-use crate::manager::LitManager;
+use crate::affinity::Placement;
+use crate::agent::{self, AgentOptions, AgentRun, ToolDefinition};
+use crate::breaker::Unstable;
+use crate::cancel;
+use crate::cache_lock::CacheLock;
+use crate::cluster::{Cluster, Forwarded};
+use crate::concurrency::{ConcurrencyPermit, QueueFull, Saturated};
+use crate::context::{self, ContextOverflow, Turn};
+use crate::dspy;
+use crate::error::LitError;
+use crate::generation::{self, ConstraintViolation, GenOptions};
+use crate::history::HistoryRecord;
+use crate::keep_alive::KeepAlive;
+use crate::moderation::ModerationResult;
+use crate::process::{GenerationTiming, ProcessPool};
+use crate::retrieval::SearchHit;
+use crate::session::ChatSession;
+use crate::telemetry;
+use crate::tenant::{AccessDenied, Tenant};
+use crate::tokenizer::Tokenizer;
+
+use crate::manager::{LitManager, PoolStatus};
+pub use crate::stream::{ChatCompletionChunk, ChatToolCall, ChoiceChunk, Delta, FunctionCall, LitertExtension, Usage};
+use crate::stream::{limit_tokens, rechunk, stop_at, Chunking, FinishReason, StreamStats};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: Arc<ProcessPool>,
     pub manager: Arc<LitManager>,
+    /// Whether completions are served yet; see [`ServeOptions::wait_ready`]
+    pub readiness: Readiness,
+    /// How streams are chunked unless a request sets `x_litert.chunking`
+    pub chunking: Chunking,
 }
 
-/// Check if this is a DSpy-rs formatted prompt by looking for multiple specific patterns
-fn is_dspy_request(prompt: &str) -> bool {
-    // DSpy-rs has very specific patterns - we need at least 3 of these to be confident:
-    // 1. "Your input fields are:" or "Your output fields are:"
-    // 2. Field markers like "[[ ## field_name ## ]]"
-    // 3. "All interactions will be structured"
-    // 4. "Given the fields" instruction pattern
-
-    let has_field_declaration = prompt.contains("Your input fields are:")
-        || prompt.contains("Your output fields are:");
-    let has_field_markers = prompt.contains("[[ ## ") && prompt.contains(" ## ]]");
-    let has_structure_instruction = prompt.contains("All interactions will be structured");
-    let has_completion_marker = prompt.contains("[[ ## completed ## ]]")
-        || prompt.contains("ending with the marker for `completed`");
-
-    // Require at least 3 of these patterns to be present
-    let pattern_count = [
-        has_field_declaration,
-        has_field_markers,
-        has_structure_instruction,
-        has_completion_marker,
-    ].iter().filter(|&&x| x).count();
-
-    pattern_count >= 3
-}
-
-/// Extract output field names from DSpy-rs formatted prompt
-fn extract_dspy_output_fields(prompt: &str) -> Vec<String> {
-    let mut fields = Vec::new();
-
-    // Look for "Your output fields are:" section
-    if let Some(output_section) = prompt.split("Your output fields are:").nth(1) {
-        // Extract field names from lines like "1. `field_name` (String)"
-        for line in output_section.lines() {
-            if let Some(field_start) = line.find('`') {
-                if let Some(field_end) = line[field_start + 1..].find('`') {
-                    let field_name = &line[field_start + 1..field_start + 1 + field_end];
-                    fields.push(field_name.to_string());
-                }
+/// How [`LitManager::serve_with`] runs the server
+#[derive(Debug, Clone, Default)]
+pub struct ServeOptions {
+    /// Answer `/v1/chat/completions` with 503 until every process of the
+    /// preloaded pool has finished a warm-up generation
+    pub wait_ready: bool,
+    /// Start pools for every downloaded model, not just the default one
+    pub preload_all: bool,
+    /// Default chunking of streamed completions (e.g. per sentence for TTS)
+    pub chunking: Chunking,
+    /// API keys accepted in addition to the configured ones; see
+    /// [`LitManager::with_api_keys`]
+    pub api_keys: Vec<String>,
+    /// How long open requests may run after SIGINT/SIGTERM before the server
+    /// closes them and stops lit (default [`DEFAULT_SHUTDOWN_TIMEOUT`])
+    pub shutdown_timeout: Option<std::time::Duration>,
+    /// Serve HTTPS with this certificate instead of plain HTTP
+    pub tls: Option<TlsConfig>,
+}
+
+/// PEM files of the certificate chain and private key to serve HTTPS with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub cert: std::path::PathBuf,
+    pub key: std::path::PathBuf,
+}
+
+impl TlsConfig {
+    /// Read the certificate chain and key, failing on missing or invalid files
+    pub async fn load(&self) -> anyhow::Result<axum_server::tls_rustls::RustlsConfig> {
+        use anyhow::Context;
+        axum_server::tls_rustls::RustlsConfig::from_pem_file(&self.cert, &self.key)
+            .await
+            .with_context(|| {
+                format!("Invalid TLS certificate {} or key {}", self.cert.display(), self.key.display())
+            })
+    }
+}
+
+/// How long a shutting-down server waits for open requests by default
+pub const DEFAULT_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Resolves on Ctrl-C, or on SIGTERM on Unix (as sent by systemd and container runtimes)
+pub(crate) async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!(error = %e, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
             }
-            // Stop at the next section
-            if line.contains("All interactions will be structured") {
-                break;
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
             }
         }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received Ctrl-C"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
     }
+}
 
-    fields
+/// Startup state of the preloaded pool, as reported by `/readyz`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReadyState {
+    WarmingUp,
+    Ready,
+    Failed { error: String },
 }
 
-/// Extract the actual user question from DSpy-rs formatted prompt
-fn extract_dspy_question(prompt: &str) -> Option<String> {
-    // Find the user's actual question after the format template
-    // Look for pattern: user: [[ ## <field> ## ]]\n<actual_question>
-    if let Some(user_section) = prompt.split("user: [[ ## ").nth(1) {
-        if let Some(question_start) = user_section.find("## ]]\n") {
-            let question = &user_section[question_start + 6..];
-            return Some(question.trim().to_string());
-        }
+/// Shared [`ReadyState`] of a server; ready unless created with
+/// [`Readiness::warming_up`]
+#[derive(Debug, Clone)]
+pub struct Readiness(Arc<std::sync::RwLock<ReadyState>>);
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self(Arc::new(std::sync::RwLock::new(ReadyState::Ready)))
     }
-    None
 }
 
-/// Format LLM response with DSpy-rs field markers
-fn format_dspy_response(llm_output: &str, output_fields: &[String]) -> String {
-    let cleaned_output = llm_output.trim();
+impl Readiness {
+    pub fn warming_up() -> Self {
+        Self(Arc::new(std::sync::RwLock::new(ReadyState::WarmingUp)))
+    }
+
+    pub fn state(&self) -> ReadyState {
+        self.0.read().unwrap().clone()
+    }
 
-    // For now, put the entire response in the first output field
-    // This is a simple heuristic - could be improved with better parsing
-    let mut formatted = String::new();
+    pub fn set(&self, state: ReadyState) {
+        *self.0.write().unwrap() = state;
+    }
 
-    if let Some(first_field) = output_fields.first() {
-        formatted.push_str(&format!("[[ ## {} ## ]]\n", first_field));
-        formatted.push_str(cleaned_output);
-        formatted.push_str("\n\n");
+    pub fn is_ready(&self) -> bool {
+        *self.0.read().unwrap() == ReadyState::Ready
     }
+}
 
-    // Add completion marker
-    formatted.push_str("[[ ## completed ## ]]\n");
+/// Seconds clients are told to wait before retrying while the server warms up
+const NOT_READY_RETRY_AFTER_SECS: u64 = 5;
+/// `Retry-After` of requests to a model at its concurrency limit or with a full queue
+const SATURATED_RETRY_AFTER_SECS: u64 = 1;
+
+/// Records a streamed completion in the request history once the stream is dropped,
+/// including streams abandoned by the client
+struct StreamHistory {
+    manager: Arc<LitManager>,
+    record: Option<HistoryRecord>,
+    started: Instant,
+    response: String,
+    error: Option<String>,
+    finished: bool,
+}
 
-    formatted
+impl Drop for StreamHistory {
+    fn drop(&mut self) {
+        if let Some(record) = self.record.take() {
+            let outcome = match self.error.take() {
+                Some(e) => Err(e),
+                None if !self.finished => {
+                    // Dropping the stream cancels the generation as well
+                    tracing::info!(completion_id = %record.id, "Client disconnected before the stream finished");
+                    Err("stream closed before completion".to_string())
+                }
+                None => Ok(self.response.as_str()),
+            };
+            self.manager.record_history(record.finish(self.started, outcome));
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ChatCompletionRequest {
+    /// The manager's default model when left out
+    #[serde(default)]
     pub model: String,
     pub messages: Vec<Message>,
     #[serde(default)]
     pub stream: bool,
-    #[serde(default = "default_max_tokens")]
-    #[allow(dead_code)]
-    pub max_tokens: u32,
-    #[serde(default = "default_temperature")]
-    #[allow(dead_code)]
-    pub temperature: f32,
+    /// Streaming only; see [`StreamOptions`]
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
+    /// Most tokens to generate; checked against the model's context window
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Not part of OpenAI's API, but sent by many of its clients
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    /// Sequences the completion is cut at
+    #[serde(default)]
+    pub stop: Option<StopSequences>,
+    /// Number of choices to return
+    #[serde(default)]
+    pub n: Option<usize>,
+    /// Candidates to generate and pick the best `n` from
+    #[serde(default)]
+    pub best_of: Option<usize>,
+    /// End user; keeps the user on one arm of a split model
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Conversation the request continues, so it goes back to the process
+    /// holding the earlier turns; defaults to `user`
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Functions the model may call; see [`ChatTool`]
+    #[serde(default)]
+    pub tools: Vec<ChatTool>,
+    #[serde(default)]
+    pub tool_choice: Option<ToolChoice>,
+    /// litert-specific generation options (e.g. an output constraint)
+    #[serde(default)]
+    pub x_litert: Option<GenOptions>,
+    /// How long the model stays loaded after this request, as in Ollama
+    #[serde(default)]
+    pub keep_alive: Option<KeepAlive>,
+    /// Fields this server doesn't read, kept so the ones it can't honor are rejected
+    #[serde(flatten)]
+    pub unknown: serde_json::Map<String, serde_json::Value>,
+}
+
+/// OpenAI's `stream_options`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamOptions {
+    /// End the stream with a chunk carrying the request's `usage` (and no choices)
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// A function offered to the model
+///
+/// lit has no native tool calling, so it is emulated: the tools are described
+/// in a system message, and a reply that is a JSON call to one of them (see
+/// [`agent::parse_tool_call`]) is returned as `tool_calls` for the client to
+/// execute. Use `/v1/agents` to have the server execute its own tools instead.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatTool {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: ToolDefinition,
+}
+
+/// OpenAI's `tool_choice`: `none`, `auto`, `required`, or a named function
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(String),
+    Function { function: ToolChoiceFunction },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolChoiceFunction {
+    pub name: String,
+}
+
+/// Roles a chat message may have
+const ROLES: &[&str] = &["system", "developer", "user", "assistant", "tool"];
+
+/// OpenAI request fields that would change the response in ways lit can't
+/// provide, with what to use instead. Other unknown fields (e.g. `seed` or
+/// `presence_penalty`) only tune sampling and are ignored, as is
+/// `parallel_tool_calls`: a reply holds at most one emulated call.
+const UNSUPPORTED_FIELDS: &[(&str, &str)] = &[
+    ("functions", "use tools"),
+    ("function_call", "use tools and tool_choice"),
+    ("logprobs", "lit does not expose token probabilities"),
+    ("top_logprobs", "lit does not expose token probabilities"),
+    ("logit_bias", "lit does not expose token probabilities"),
+    ("response_format", "use x_litert.constraint (e.g. {\"type\": \"json\"})"),
+];
+
+impl ChatCompletionRequest {
+    /// Reject requests that can't be served as asked, naming the offending field
+    pub fn validate(&self) -> Result<(), InvalidRequest> {
+        if self.model.trim().is_empty() {
+            return Err(InvalidRequest::new("model", "model must not be empty"));
+        }
+        if self.messages.is_empty() {
+            return Err(InvalidRequest::new("messages", "messages must contain at least one message"));
+        }
+        for (i, message) in self.messages.iter().enumerate() {
+            if !ROLES.contains(&message.role.as_str()) {
+                return Err(InvalidRequest::new(
+                    format!("messages[{}].role", i),
+                    format!("'{}' is not a supported role; expected one of {}", message.role, ROLES.join(", ")),
+                ));
+            }
+        }
+        if let Some(temperature) = self.temperature.filter(|t| !(0.0..=2.0).contains(t)) {
+            return Err(InvalidRequest::new(
+                "temperature",
+                format!("{} is out of range; temperature must be between 0 and 2", temperature),
+            ));
+        }
+        if let Some(top_p) = self.top_p.filter(|p| !(*p > 0.0 && *p <= 1.0)) {
+            return Err(InvalidRequest::new(
+                "top_p",
+                format!("{} is out of range; top_p must be greater than 0 and at most 1", top_p),
+            ));
+        }
+        if self.top_k == Some(0) {
+            return Err(InvalidRequest::new("top_k", "top_k must be at least 1"));
+        }
+        if self.max_tokens == Some(0) {
+            return Err(InvalidRequest::new("max_tokens", "max_tokens must be at least 1"));
+        }
+        if self.n == Some(0) {
+            return Err(InvalidRequest::new("n", "n must be at least 1"));
+        }
+        for (i, tool) in self.tools.iter().enumerate() {
+            if tool.kind != "function" {
+                return Err(InvalidRequest::new(
+                    format!("tools[{}].type", i),
+                    format!("'{}' is not a supported tool type; expected function", tool.kind),
+                ));
+            }
+        }
+        match &self.tool_choice {
+            Some(ToolChoice::Mode(mode)) if !["none", "auto", "required"].contains(&mode.as_str()) => {
+                return Err(InvalidRequest::new(
+                    "tool_choice",
+                    format!("'{}' is not a supported tool_choice; expected none, auto, required, or a function", mode),
+                ));
+            }
+            Some(ToolChoice::Mode(mode)) if mode == "required" && self.tools.is_empty() => {
+                return Err(InvalidRequest::new("tool_choice", "tool_choice 'required' needs tools"));
+            }
+            Some(ToolChoice::Function { function }) if !self.tools.iter().any(|t| t.function.name == function.name) => {
+                return Err(InvalidRequest::new(
+                    "tool_choice",
+                    format!("'{}' is not one of the request's tools", function.name),
+                ));
+            }
+            _ => {}
+        }
+        if self.stream_options.is_some() && !self.stream {
+            return Err(InvalidRequest::new(
+                "stream_options",
+                "stream_options is only allowed when stream is true",
+            ));
+        }
+        for (field, hint) in UNSUPPORTED_FIELDS {
+            // Explicit defaults such as `"logprobs": false` are harmless
+            let set = match self.unknown.get(*field) {
+                None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false)) => false,
+                Some(serde_json::Value::Array(items)) => !items.is_empty(),
+                Some(serde_json::Value::Object(format)) if *field == "response_format" => {
+                    format.get("type").and_then(|t| t.as_str()) != Some("text")
+                }
+                Some(_) => true,
+            };
+            if set {
+                return Err(InvalidRequest::new(*field, format!("{} is not supported; {}", field, hint)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Tools the model is offered: none with `tool_choice: "none"`, only the
+    /// named one when `tool_choice` names one
+    fn offered_tools(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .iter()
+            .map(|tool| &tool.function)
+            .filter(|function| match &self.tool_choice {
+                Some(ToolChoice::Mode(mode)) => mode != "none",
+                Some(ToolChoice::Function { function: chosen }) => chosen.name == function.name,
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// System message describing `tools` and how to call them
+    fn tools_prompt(&self, tools: &[ToolDefinition]) -> String {
+        // On one line, like the chat templates
+        let mut prompt = agent::render_tools_prompt(tools)
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let required = match &self.tool_choice {
+            Some(ToolChoice::Mode(mode)) => mode == "required",
+            Some(ToolChoice::Function { .. }) => true,
+            None => false,
+        };
+        if required {
+            prompt.push_str(" This reply must be a tool call.");
+        }
+        prompt
+    }
+
+    /// The conversation as turns, with earlier tool calls written the way the
+    /// model is told to make them and tool results named after their tool
+    fn turns(&self) -> Vec<Turn> {
+        let tool_name = |id: &str| {
+            self.messages
+                .iter()
+                .flat_map(|m| m.tool_calls.iter().flatten())
+                .find(|call| call.id == id)
+                .map(|call| call.function.name.clone())
+        };
+        self.messages
+            .iter()
+            .map(|m| {
+                let mut content = m.content_as_string();
+                for call in m.tool_calls.iter().flatten() {
+                    let arguments = serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| serde_json::Value::String(call.function.arguments.clone()));
+                    let call = serde_json::json!({ "tool": call.function.name, "arguments": arguments });
+                    content = [content, call.to_string()].join(" ").trim().to_string();
+                }
+                if let Some(name) = m.tool_call_id.as_deref().and_then(tool_name) {
+                    content = format!("{}: {}", name, content);
+                }
+                Turn::new(m.role.clone(), content)
+            })
+            .collect()
+    }
+}
+
+/// The call to one of `tools` that `text` is, if it is one
+fn offered_call(text: &str, tools: &[ToolDefinition]) -> Option<ChatToolCall> {
+    let call = agent::parse_tool_call(text)?;
+    tools
+        .iter()
+        .any(|tool| tool.name == call.name)
+        .then(|| ChatToolCall::new(&call))
+}
+
+/// A request the server rejects with a 400, in OpenAI's error format
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidRequest {
+    /// The offending field, if there is one
+    pub param: Option<String>,
+    pub message: String,
+}
+
+impl InvalidRequest {
+    pub fn new(param: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            param: Some(param.into()),
+            message: message.into(),
+        }
+    }
+
+    /// Not about any one field
+    pub fn general(message: impl Into<String>) -> Self {
+        Self {
+            param: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for InvalidRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InvalidRequest {}
+
+impl IntoResponse for InvalidRequest {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": {
+                    "message": self.message,
+                    "type": "invalid_request_error",
+                    "param": self.param,
+                    "code": null
+                }
+            })),
+        )
+            .into_response()
+    }
 }
 
-fn default_max_tokens() -> u32 {
-    2048
+/// OpenAI's `stop`: one sequence or several
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum StopSequences {
+    One(String),
+    Many(Vec<String>),
 }
 
-fn default_temperature() -> f32 {
-    0.7
+impl StopSequences {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            StopSequences::One(stop) => vec![stop],
+            StopSequences::Many(stops) => stops,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -139,12 +531,20 @@ pub struct Message {
     pub role: String,
     #[serde(serialize_with = "serialize_content")]
     pub content: MessageContent,
+    /// Calls the assistant made instead of answering
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+    /// The call a `tool` message answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub enum MessageContent {
     String(String),
     Parts(Vec<ContentPart>),
+    /// No content, as in an assistant message with only tool calls
+    Null,
 }
 
 fn serialize_content<S>(content: &MessageContent, serializer: S) -> Result<S::Ok, S::Error>
@@ -154,6 +554,7 @@ where
     match content {
         MessageContent::String(s) => serializer.serialize_str(s),
         MessageContent::Parts(parts) => parts.serialize(serializer),
+        MessageContent::Null => serializer.serialize_none(),
     }
 }
 
@@ -165,7 +566,12 @@ impl<'de> Deserialize<'de> for Message {
         #[derive(Deserialize)]
         struct MessageHelper {
             role: String,
+            #[serde(default)]
             content: serde_json::Value,
+            #[serde(default)]
+            tool_calls: Option<Vec<ChatToolCall>>,
+            #[serde(default)]
+            tool_call_id: Option<String>,
         }
 
         let helper = MessageHelper::deserialize(deserializer)?;
@@ -176,12 +582,15 @@ impl<'de> Deserialize<'de> for Message {
                     .map_err(serde::de::Error::custom)?;
                 MessageContent::Parts(parts)
             }
+            serde_json::Value::Null if helper.tool_calls.is_some() => MessageContent::Null,
             _ => return Err(serde::de::Error::custom("content must be string or array")),
         };
 
         Ok(Message {
             role: helper.role,
             content,
+            tool_calls: helper.tool_calls,
+            tool_call_id: helper.tool_call_id,
         })
     }
 }
@@ -200,6 +609,7 @@ impl Message {
                     .collect::<Vec<_>>()
                     .join("\n")
             }
+            MessageContent::Null => String::new(),
         }
     }
 }
@@ -212,6 +622,8 @@ pub struct ChatCompletionResponse {
     pub model: String,
     pub choices: Vec<Choice>,
     pub usage: Usage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_litert: Option<LitertExtension>,
 }
 
 #[derive(Debug, Serialize)]
@@ -221,55 +633,160 @@ pub struct Choice {
     pub finish_reason: String,
 }
 
-#[derive(Debug, Serialize)]
-pub struct Usage {
-    pub prompt_tokens: u32,
-    pub completion_tokens: u32,
-    pub total_tokens: u32,
-}
-
-#[derive(Debug, Serialize)]
-pub struct ChatCompletionChunk {
-    pub id: String,
-    pub object: &'static str,
-    pub created: u64,
-    pub model: String,
-    pub choices: Vec<ChoiceChunk>,
-}
-
-#[derive(Debug, Serialize)]
-pub struct ChoiceChunk {
-    pub index: u32,
-    pub delta: Delta,
-    pub finish_reason: Option<String>,
-}
-
-#[derive(Debug, Serialize)]
-pub struct Delta {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub role: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
-}
-
 pub async fn chat_completions(
     State(state): State<AppState>,
-    Json(req): Json<ChatCompletionRequest>,
+    Extension(tenant): Extension<Tenant>,
+    payload: Result<Json<ChatCompletionRequest>, JsonRejection>,
 ) -> Response {
+    if !state.readiness.is_ready() {
+        state.manager.metrics().record_http_request("chat_completions");
+        return not_ready_response();
+    }
+    // Malformed bodies get the same 400 as invalid fields, rather than axum's plain-text 422
+    let validated = payload
+        .map_err(|rejection| InvalidRequest::general(rejection.body_text()))
+        .map(|Json(mut req)| {
+            // Requests without a model go to the manager's default, if it has one
+            if let (true, Some(model)) = (req.model.trim().is_empty(), state.manager.default_model()) {
+                req.model = model.to_string();
+            }
+            req
+        })
+        .and_then(|req| req.validate().map(|()| req));
+    let mut req = match validated {
+        Ok(req) => req,
+        Err(e) => {
+            let metrics = state.manager.metrics();
+            metrics.record_http_request("chat_completions");
+            metrics.record_http_error("chat_completions");
+            return e.into_response();
+        }
+    };
+    // A split model is served by one of its arms, recorded per arm
+    let Some((split, arm)) = state
+        .manager
+        .splits()
+        .get(&req.model)
+        .and_then(|split| Some((split.name().to_string(), split.pick(req.user.as_deref())?.to_string())))
+    else {
+        return chat_completion(state, tenant, req).await;
+    };
+    tracing::debug!(split = %split, model = %arm, "Routing split model to arm");
+    req.model = arm.clone();
+
+    let metrics = state.manager.metrics();
+    let started = Instant::now();
+    let response = chat_completion(state, tenant, req).await;
+    metrics.record_split(&split, &arm, response.status().is_success(), started.elapsed());
+    response
+}
+
+#[tracing::instrument(
+    name = "completion",
+    skip_all,
+    fields(model = %req.model, stream = req.stream)
+)]
+async fn chat_completion(state: AppState, tenant: Tenant, mut req: ChatCompletionRequest) -> Response {
     tracing::info!(
         model = %req.model,
         message_count = req.messages.len(),
         stream = req.stream,
         "Received chat completion request"
     );
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("chat_completions");
+    let started = Instant::now();
 
-    // Build prompt from messages
-    let mut prompt = req
-        .messages
-        .iter()
-        .map(|m| format!("{}: {}", m.role, m.content_as_string()))
-        .collect::<Vec<_>>()
-        .join("\n");
+    let mut options = req.x_litert.clone().unwrap_or_default();
+    if let Some(n) = req.n {
+        options.n = n;
+    }
+    if req.best_of.is_some() {
+        options.best_of = req.best_of;
+    }
+    if req.temperature.is_some() {
+        options.temperature = req.temperature;
+    }
+    if req.top_p.is_some() {
+        options.top_p = req.top_p;
+    }
+    if req.top_k.is_some() {
+        options.top_k = req.top_k;
+    }
+    if let Some(stop) = req.stop.take() {
+        options.stop = stop.into_vec();
+    }
+    if let Some(max_tokens) = req.max_tokens {
+        options.max_tokens = Some(max_tokens as usize);
+    }
+
+    // A profile is served by its model, with its settings as defaults
+    let profile = state.manager.profiles().get(&req.model).cloned();
+    if let Some(profile) = &profile {
+        tracing::debug!(profile = %profile.name(), model = %profile.model(), "Applying generation profile");
+        profile.apply(&mut options);
+        req.model = profile.model().to_string();
+    }
+    let window = state.manager.context_window(&req.model);
+    let invalid = match options.check() {
+        Err(e) => Some(InvalidRequest::general(format!("Invalid generation options: {}", e))),
+        Ok(()) if req.stream && options.n > 1 => Some(InvalidRequest::new("n", "n > 1 is not supported when streaming")),
+        Ok(()) => req.max_tokens.filter(|max| *max as usize > window.length).map(|max| {
+            InvalidRequest::new(
+                "max_tokens",
+                format!("max_tokens ({}) exceeds the context window of {} ({} tokens)", max, req.model, window.length),
+            )
+        }),
+    };
+    if let Some(e) = invalid {
+        metrics.record_http_error("chat_completions");
+        return e.into_response();
+    }
+
+    // Counted against the model's limit until the response is sent
+    let permit = match admit_request(&state.manager, &req.model) {
+        Ok(permit) => permit,
+        Err(e) => {
+            metrics.record_http_error("chat_completions");
+            return model_error_response(&e);
+        }
+    };
+
+    let manager = state.manager.for_tenant(tenant);
+    let pool = match pool_for(&state, &manager, &req.model, &options).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to get process pool");
+            metrics.record_http_error("chat_completions");
+            telemetry::record_error(&req.model, "pool");
+            return model_error_response(&e);
+        }
+    };
+    if let Some(keep_alive) = req.keep_alive {
+        pool.set_keep_alive(keep_alive);
+    }
+
+    // Build prompt from messages, fitted to the model's context window
+    let mut turns = req.turns();
+    if let Some(profile) = &profile {
+        profile.apply_turns(&mut turns);
+    }
+    // Tool calling is emulated by describing the tools after the system messages
+    let tools = req.offered_tools();
+    if !tools.is_empty() {
+        let at = turns.iter().take_while(|turn| turn.role == "system").count();
+        turns.insert(at, Turn::new("system", req.tools_prompt(&tools)));
+    }
+    // DSpy-rs prompts are recognized by their plain transcript
+    let transcript = context::render(&turns);
+    let (mut prompt, whole) = match manager.fit_context(&pool, turns.clone()).await {
+        Ok(fitted) => (fitted.prompt, fitted.dropped == 0 && !fitted.summarized),
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to fit conversation to the context window");
+            metrics.record_http_error("chat_completions");
+            return model_error_response(&e);
+        }
+    };
 
     tracing::debug!(
         model = %req.model,
@@ -278,22 +795,17 @@ pub async fn chat_completions(
     );
     tracing::trace!(prompt = %prompt, "Full prompt text");
 
-    // Check if streaming is requested
-    if req.stream {
-        tracing::debug!("Routing to streaming handler");
-        return chat_completions_stream(state, req, prompt).await;
-    }
-
     // Detect if this is a DSpy-rs structured output request
-    let is_dspy = is_dspy_request(&prompt);
+    let is_dspy = dspy::is_dspy_request(&transcript);
     let output_fields = if is_dspy {
         tracing::debug!("Detected DSpy-rs structured output request");
-        // Extract output field names from the system message
-        let fields = extract_dspy_output_fields(&prompt);
-        tracing::debug!(fields = ?fields, "Extracted DSpy-rs output fields");
+        // Output field names and instructions come from the (cached) preamble
+        let signature = state.manager.dspy_signatures().get(&transcript);
+        tracing::debug!(fields = ?signature.output_fields, "Extracted DSpy-rs output fields");
 
         // For small models, simplify by extracting just the actual question
-        if let Some(question) = extract_dspy_question(&prompt) {
+        if let Some(question) = dspy::extract_dspy_question(&transcript) {
+            let question = signature.prompt_in(manager.chat_template(pool.model()), &question);
             tracing::debug!(original_length = prompt.len(), simplified_length = question.len(), "Simplified DSpy prompt for small model");
             prompt = question;
             tracing::trace!(simplified_prompt = %prompt, "Using simplified question");
@@ -301,89 +813,210 @@ pub async fn chat_completions(
             tracing::warn!("Failed to extract question from DSpy prompt, using original");
         }
 
-        fields
+        signature.output_fields.clone()
     } else {
         vec![]
     };
 
+    // A single reply to a whole conversation can go back to the process
+    // holding its earlier turns
+    let conversation = req
+        .session_id
+        .clone()
+        .or_else(|| req.user.clone())
+        .filter(|_| whole && !is_dspy && options.constraint.is_none() && options.candidates() == 1)
+        .map(|key| (format!("{}/{}", manager.tenant().name(), key), turns));
+
+    // Check if streaming is requested
+    if req.stream {
+        tracing::debug!("Routing to streaming handler");
+        let dspy = is_dspy.then_some(output_fields);
+        let response = chat_completions_stream(
+            state,
+            manager.tenant().clone(),
+            pool,
+            req,
+            prompt,
+            conversation,
+            dspy,
+            options,
+            started,
+        )
+        .await;
+        return hold_until_sent(response, permit);
+    }
+
     // Non-streaming response
     tracing::debug!("Sending prompt to process pool");
-    let mut response_text = match state.pool.send_prompt(&prompt).await {
-        Ok(text) => {
+    let completion_id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+    let record = HistoryRecord::new(completion_id.as_str(), &req.model, "http", &prompt);
+    // Listed by `/admin/requests` until answered; killing it cancels the generation
+    let request = manager.track_request(&completion_id, &req.model, req.user.as_deref());
+    let tokenizer = manager.tokenizers().for_model(&req.model);
+    let cached = manager.cached_responses(&req.model, &prompt, &options);
+    let is_cached = cached.is_some();
+    let result = match cached {
+        Some(texts) => Ok(texts.into_iter().map(|text| (text, GenerationTiming::default())).collect()),
+        // The pool's processes time out generations themselves, from when
+        // the prompt is sent rather than including the wait for a process
+        None => cancel::run(
+            Some(request.token()),
+            async {
+                if let Some((conversation, turns)) = &conversation {
+                    let _slot = pool.admit(manager.tenant()).await?;
+                    let reply = converse(&manager, &pool, conversation, turns, &prompt, &options, tokenizer.clone()).await?;
+                    return Ok(vec![reply]);
+                }
+                generation::generate_best(&prompt, manager.chat_template(pool.model()), &options, |prompt| {
+                    let pool = pool.clone();
+                    let tenant = manager.tenant().clone();
+                    let tokenizer = tokenizer.clone();
+                    async move {
+                        let _slot = pool.admit(&tenant).await?;
+                        pool.send_prompt_limited(&prompt, options.max_tokens, tokenizer).await
+                    }
+                })
+                .await
+            },
+        )
+        .await
+        .inspect(|candidates: &Vec<(String, GenerationTiming)>| {
+            let texts = candidates.iter().map(|(text, _)| text.clone()).collect();
+            manager.cache_responses(&req.model, &prompt, &options, texts);
+        }),
+    };
+    state.manager.record_history(record.finish(
+        started,
+        result.as_ref().map(|candidates| candidates[0].0.as_str()).map_err(|e| e.to_string()),
+    ));
+
+    let (mut response_texts, x_litert) = match result {
+        Ok(candidates) => {
+            let (text, timing) = &candidates[0];
             tracing::info!(
                 response_length = text.len(),
+                choices = candidates.len(),
                 "Received completion from LLM"
             );
             tracing::trace!(response = %text, "LLM response text");
-            text
+            let x_litert = LitertExtension {
+                cached: is_cached,
+                ..LitertExtension::from_timing(timing, text, &*tokenizer)
+            };
+            let texts: Vec<String> = candidates.into_iter().map(|(text, _)| text).collect();
+            (texts, x_litert)
+        }
+        Err(e) if e.is::<ConstraintViolation>() => {
+            tracing::warn!(error = %e, "Completion violated the requested constraint");
+            metrics.record_http_error("chat_completions");
+            telemetry::record_error(&req.model, "constraint");
+            return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response();
+        }
+        Err(e) if LitError::of(&e).is_some() => {
+            tracing::error!(error = %e, "Completion failed");
+            metrics.record_http_error("chat_completions");
+            return model_error_response(&e);
+        }
+        Err(e) if e.is::<QueueFull>() => {
+            metrics.record_http_error("chat_completions");
+            return model_error_response(&e);
         }
         Err(e) => {
             tracing::error!(error = %e, "Failed to get completion from process pool");
+            metrics.record_http_error("chat_completions");
+            telemetry::record_error(&req.model, "completion");
             return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
         }
     };
 
+    // Counted as generated, before any DSpy-rs markers are added
+    let completion_tokens: Vec<usize> = response_texts.iter().map(|text| tokenizer.count(text)).collect();
+    let usage = Usage::new(tokenizer.count(&prompt), completion_tokens.iter().sum());
+
     // If DSpy-rs request, format the response with field markers
     if is_dspy && !output_fields.is_empty() {
         tracing::debug!(field_count = output_fields.len(), "Formatting response for DSpy-rs");
-        response_text = format_dspy_response(&response_text, &output_fields);
-        tracing::trace!(formatted_response = %response_text, "DSpy-rs formatted response");
+        for response_text in &mut response_texts {
+            *response_text = dspy::format_dspy_response(response_text, &output_fields);
+            tracing::trace!(formatted_response = %response_text, "DSpy-rs formatted response");
+        }
     }
 
     let response = ChatCompletionResponse {
-        id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+        id: completion_id,
         object: "chat.completion".to_string(),
         created: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs(),
         model: req.model.clone(),
-        choices: vec![Choice {
-            index: 0,
-            message: Message {
-                role: "assistant".to_string(),
-                content: MessageContent::String(response_text),
-            },
-            finish_reason: "stop".to_string(),
-        }],
-        usage: Usage {
-            prompt_tokens: 0,
-            completion_tokens: 0,
-            total_tokens: 0,
-        },
+        choices: response_texts
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let (message, finish) = match offered_call(&text, &tools) {
+                    Some(call) => (
+                        Message {
+                            role: "assistant".to_string(),
+                            content: MessageContent::Null,
+                            tool_calls: Some(vec![call]),
+                            tool_call_id: None,
+                        },
+                        FinishReason::ToolCalls,
+                    ),
+                    None => (
+                        Message {
+                            role: "assistant".to_string(),
+                            content: MessageContent::String(text),
+                            tool_calls: None,
+                            tool_call_id: None,
+                        },
+                        finish_reason(completion_tokens[index], options.max_tokens),
+                    ),
+                };
+                Choice {
+                    index: index as u32,
+                    message,
+                    finish_reason: finish.as_str().to_string(),
+                }
+            })
+            .collect(),
+        usage,
+        x_litert: Some(x_litert),
     };
 
+    metrics
+        .http_request_duration
+        .with_label_values(&["chat_completions"])
+        .observe(started.elapsed().as_secs_f64());
     Json(response).into_response()
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn chat_completions_stream(
     state: AppState,
+    tenant: Tenant,
+    pool: Arc<ProcessPool>,
     req: ChatCompletionRequest,
-    mut prompt: String,
+    prompt: String,
+    conversation: Option<(String, Vec<Turn>)>,
+    dspy: Option<Vec<String>>,
+    options: GenOptions,
+    started: Instant,
 ) -> Response {
+    let metrics = state.manager.metrics();
     let model_name = req.model.clone();
     let completion_id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+    // Listed by `/admin/requests` until the stream ends; killing it ends the stream
+    let request = state
+        .manager
+        .for_tenant(tenant.clone())
+        .track_request(&completion_id, &model_name, req.user.as_deref());
+    let token = request.token().clone();
 
-    // Detect if this is a DSpy-rs structured output request and simplify for streaming
-    let is_dspy = is_dspy_request(&prompt);
-    let output_fields = if is_dspy {
-        tracing::debug!("Detected DSpy-rs structured output request in streaming mode");
-        let fields = extract_dspy_output_fields(&prompt);
-        tracing::debug!(fields = ?fields, "Extracted DSpy-rs output fields");
-
-        // Simplify by extracting just the actual question
-        if let Some(question) = extract_dspy_question(&prompt) {
-            tracing::debug!(original_length = prompt.len(), simplified_length = question.len(), "Simplified DSpy prompt for streaming");
-            prompt = question;
-            tracing::trace!(simplified_prompt = %prompt, "Using simplified question for streaming");
-        } else {
-            tracing::warn!("Failed to extract question from DSpy prompt in streaming mode, using original");
-        }
-
-        fields
-    } else {
-        vec![]
-    };
+    // DSpy-rs requests arrive with their question already extracted
+    let is_dspy = dspy.is_some();
+    let output_fields = dspy.unwrap_or_default();
 
     tracing::info!(
         completion_id = %completion_id,
@@ -392,26 +1025,123 @@ async fn chat_completions_stream(
         "Starting streaming completion"
     );
 
-    // Get a process from the pool and stream
-    let stream = match state.pool.get_process().await {
-        Ok(process) => {
-            tracing::debug!("Acquired process from pool for streaming");
-            match process.send_prompt_stream(&prompt).await {
-                Ok(s) => {
-                    tracing::debug!("Stream initialized successfully");
-                    s
-                }
-                Err(e) => {
-                    tracing::error!(error = %e, "Failed to initialize prompt stream");
-                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    // Completed at the end, when the request asked for usage
+    let tokenizer = state.manager.tokenizers().for_model(&model_name);
+    let include_usage = req.stream_options.as_ref().is_some_and(|o| o.include_usage);
+    let usage = include_usage.then(|| Usage::new(tokenizer.count(&prompt), 0));
+
+    // Filled in as the stream runs, when the request asked for stats
+    let mut stats = options.stats.then(|| StreamStats {
+        id: completion_id.clone(),
+        prompt_tokens: tokenizer.count(&prompt) as u64,
+        ..Default::default()
+    });
+
+    // Constrained output can only be validated and best-of candidates only
+    // ranked once complete, so they are generated up front and streamed as a
+    // single chunk
+    let stream = if options.constraint.is_some() || options.candidates() > 1 {
+        let result = cancel::run(
+            Some(&token),
+            generation::generate_best(&prompt, state.manager.chat_template(pool.model()), &options, |prompt| {
+                let pool = pool.clone();
+                let tenant = tenant.clone();
+                let tokenizer = tokenizer.clone();
+                async move {
+                    let _slot = pool.admit(&tenant).await?;
+                    let (text, _) = pool.send_prompt_limited(&prompt, options.max_tokens, tokenizer).await?;
+                    Ok((text, ()))
                 }
+            }),
+        )
+        .await;
+        match result {
+            Ok(mut candidates) => {
+                let (text, ()) = candidates.swap_remove(0);
+                futures_util::stream::once(async move { Ok(text) }).boxed()
+            }
+            Err(e) if e.is::<QueueFull>() || LitError::of(&e).is_some() => {
+                metrics.record_http_error("chat_completions");
+                return model_error_response(&e);
+            }
+            Err(e) => {
+                let status = if e.is::<ConstraintViolation>() {
+                    StatusCode::UNPROCESSABLE_ENTITY
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                };
+                tracing::error!(error = %e, "Constrained completion failed");
+                metrics.record_http_error("chat_completions");
+                telemetry::record_error(&model_name, "completion");
+                return (status, e.to_string()).into_response();
             }
         }
-        Err(e) => {
-            tracing::error!(error = %e, "Failed to acquire process from pool");
-            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    } else {
+        let queued = Instant::now();
+        // Killing a queued request gives up its place in line
+        let slot = match cancel::run(Some(&token), pool.admit(&tenant)).await {
+            Ok(slot) => slot,
+            Err(e) => {
+                metrics.record_http_error("chat_completions");
+                return model_error_response(&e);
+            }
+        };
+        let placement = match &conversation {
+            Some((conversation, turns)) => pool.place(conversation, turns).await,
+            None => pool.get_process().await.map(Placement::replay),
+        };
+        match placement {
+            Ok(placement) => {
+                tracing::debug!(held_turns = placement.held, "Acquired process from pool for streaming");
+                let process = placement.process.clone();
+                if let Some(stats) = &mut stats {
+                    stats.queue_ms = Some(queued.elapsed().as_millis() as u64);
+                    stats.backend = Some(process.backend().to_string());
+                    stats.pid = process.pid();
+                }
+                let sent = match (&conversation, placement.held) {
+                    (Some((_, turns)), held) if held > 0 => state.manager.chat_template(pool.model()).render(&turns[held..]),
+                    _ => prompt.clone(),
+                };
+                match placement.send_stream(&sent, &prompt).await {
+                    Ok(s) => {
+                        tracing::debug!("Stream initialized successfully");
+                        let s = match conversation {
+                            Some((conversation, turns)) => remember_reply(s, pool.clone(), conversation, placement, turns),
+                            None => s.boxed(),
+                        };
+                        // The pool slot and the listing are held until the stream ends
+                        let s = s.map(move |item| {
+                            let _ = (&slot, &request);
+                            item
+                        });
+                        cancel::stream(Some(token), s)
+                    }
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to initialize prompt stream");
+                        metrics.record_http_error("chat_completions");
+                        telemetry::record_error(&model_name, "completion");
+                        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to acquire process from pool");
+                metrics.record_http_error("chat_completions");
+                telemetry::record_error(&model_name, "pool");
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
         }
     };
+    metrics
+        .http_request_duration
+        .with_label_values(&["chat_completions"])
+        .observe(started.elapsed().as_secs_f64());
+    // Cut at the first stop sequence; dropping the rest of the stream
+    // interrupts lit, as does the client disconnecting
+    let stream = stop_at(stream, options.stop.clone());
+    let stream = limit_tokens(stream, options.max_tokens, tokenizer.clone());
+    let stream = rechunk(stream, options.chunking.unwrap_or(state.chunking));
 
     let created = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -420,95 +1150,217 @@ async fn chat_completions_stream(
 
     // Create state for the stream transformation
     struct StreamState {
+        role_sent: bool,
+        /// Tools offered to the model, when tool calling is emulated
+        tools: Vec<ToolDefinition>,
+        /// Text held back while it may still be a tool call
+        held: Option<String>,
+        tool_called: bool,
         dspy_header_sent: bool,
         is_dspy: bool,
         first_field: Option<String>,
         completion_sent: bool,
+        stats_sent: bool,
+        request_stats: Option<StreamStats>,
+        usage: Option<Usage>,
+        tokenizer: Arc<dyn Tokenizer>,
+        max_tokens: Option<usize>,
+        done_sent: bool,
+        first_token_at: Option<std::time::Duration>,
+        history: StreamHistory,
+    }
+
+    enum StreamItem {
+        Role,
+        Token(String),
+        ToolCall(ChatToolCall),
+        Done(LitertExtension, FinishReason),
+        Stats(StreamStats),
+        Usage(Usage),
+        End,
     }
 
+    let history = StreamHistory {
+        manager: state.manager.clone(),
+        record: Some(HistoryRecord::new(completion_id.as_str(), &model_name, "http", &prompt)),
+        started,
+        response: String::new(),
+        error: None,
+        finished: false,
+    };
+
+    let tools = req.offered_tools();
     let state = StreamState {
+        role_sent: false,
+        held: (!tools.is_empty()).then(String::new),
+        tools,
+        tool_called: false,
         dspy_header_sent: false,
-        is_dspy: is_dspy,
+        is_dspy,
         first_field: output_fields.first().cloned(),
         completion_sent: false,
+        stats_sent: false,
+        request_stats: stats,
+        usage,
+        tokenizer,
+        max_tokens: options.max_tokens,
+        done_sent: false,
+        first_token_at: None,
+        history,
     };
 
     use futures_util::stream;
 
     // Transform the stream to add DSpy markers if needed
     let transformed_stream = stream::unfold((stream, state), move |(mut s, mut state)| async move {
-        match s.next().await {
-            Some(Ok(mut token)) => {
-                // For DSpy requests, wrap the first chunk with field marker
-                if state.is_dspy && !state.dspy_header_sent {
-                    if let Some(ref first_field) = state.first_field {
-                        token = format!("[[ ## {} ## ]]\n{}", first_field, token);
-                        state.dspy_header_sent = true;
+        // Like OpenAI, open with the role before any text, so even an empty
+        // completion names it
+        if !state.role_sent {
+            state.role_sent = true;
+            return Some((Ok(StreamItem::Role), (s, state)));
+        }
+        // A failed completion ends with its error, without finishing normally
+        if state.history.error.is_some() {
+            return None;
+        }
+        loop {
+            match s.next().await {
+                Some(Ok(mut token)) => {
+                    state.first_token_at.get_or_insert_with(|| started.elapsed());
+                    state.history.response.push_str(&token);
+
+                    // A reply that may be a tool call is held back until it's complete
+                    if let Some(held) = &mut state.held {
+                        held.push_str(&token);
+                        if agent::may_be_tool_call(held) {
+                            continue;
+                        }
+                        token = std::mem::take(held);
+                        state.held = None;
                     }
-                }
 
-                Some((Ok(token), (s, state)))
-            }
-            Some(Err(e)) => Some((Err(e), (s, state))),
-            None => {
-                // Stream ended - if DSpy and haven't sent completion, send it now
-                if state.is_dspy && !state.completion_sent {
-                    state.completion_sent = true;
-                    Some((Ok("\n\n[[ ## completed ## ]]\n".to_string()), (s, state)))
-                } else {
-                    None
+                    // For DSpy requests, wrap the first chunk with field marker
+                    if state.is_dspy && !state.dspy_header_sent {
+                        if let Some(ref first_field) = state.first_field {
+                            token = format!("[[ ## {} ## ]]\n{}", first_field, token);
+                            state.dspy_header_sent = true;
+                        }
+                    }
+
+                    return Some((Ok(StreamItem::Token(token)), (s, state)));
+                }
+                Some(Err(e)) => {
+                    state.history.error = Some(e.to_string());
+                    return Some((Err(e), (s, state)));
+                }
+                None => {
+                    state.history.finished = true;
+
+                    let item = if let Some(held) = state.held.take().filter(|held| !held.trim().is_empty()) {
+                        // The whole reply was held back; it's either a call or text after all
+                        match offered_call(&held, &state.tools) {
+                            Some(call) => {
+                                state.tool_called = true;
+                                StreamItem::ToolCall(call)
+                            }
+                            None => StreamItem::Token(held),
+                        }
+                    } else if state.is_dspy && !state.completion_sent {
+                        // Stream ended - if DSpy and haven't sent completion, send it now
+                        state.completion_sent = true;
+                        StreamItem::Token("\n\n[[ ## completed ## ]]\n".to_string())
+                    } else if !state.stats_sent {
+                        // Close with a chunk carrying the latency stats
+                        state.stats_sent = true;
+                        let timing = GenerationTiming {
+                            time_to_first_token: state.first_token_at,
+                            total: started.elapsed(),
+                        };
+                        let stats = LitertExtension::from_timing(&timing, &state.history.response, &*state.tokenizer);
+                        if let Some(request_stats) = &mut state.request_stats {
+                            request_stats.ttft_ms = stats.ttft_ms;
+                            request_stats.generation_ms = stats.generation_ms;
+                            request_stats.completion_tokens = stats.completion_tokens;
+                        }
+                        let completion_tokens = state.tokenizer.count(&state.history.response);
+                        let finish = match state.tool_called {
+                            true => FinishReason::ToolCalls,
+                            false => finish_reason(completion_tokens, state.max_tokens),
+                        };
+                        StreamItem::Done(stats, finish)
+                    } else if let Some(request_stats) = state.request_stats.take() {
+                        StreamItem::Stats(request_stats)
+                    } else if let Some(usage) = state.usage.take() {
+                        let completion_tokens = state.tokenizer.count(&state.history.response);
+                        StreamItem::Usage(Usage::new(usage.prompt_tokens as usize, completion_tokens))
+                    } else if !state.done_sent {
+                        state.done_sent = true;
+                        StreamItem::End
+                    } else {
+                        return None;
+                    };
+                    return Some((Ok(item), (s, state)));
                 }
             }
         }
     });
 
-    let mut first_chunk = true;
-    let mut chunk_sent_completion = false;
     let sse_stream = transformed_stream.map(move |chunk_result| {
+        let chunk = |choices: Vec<ChoiceChunk>, usage: Option<Usage>, x_litert: Option<LitertExtension>| {
+            let chunk = ChatCompletionChunk {
+                id: completion_id.clone(),
+                object: "chat.completion.chunk".to_string(),
+                created,
+                model: model_name.clone(),
+                choices,
+                usage,
+                x_litert,
+            };
+            let json_data = serde_json::to_string(&chunk).unwrap_or_else(|_| "{}".to_string());
+            Event::default().data(json_data)
+        };
+        let choice = |delta: Delta, finish_reason: Option<FinishReason>| ChoiceChunk {
+            index: 0,
+            delta,
+            finish_reason: finish_reason.map(|reason| reason.as_str().to_string()),
+        };
+        let text = |content: String| Delta {
+            content: Some(content),
+            ..Default::default()
+        };
+
         let event = match chunk_result {
-            Ok(token) => {
-                // Check if this is a completion marker chunk (before moving token)
-                let is_completion = token.contains("[[ ## completed ## ]]");
-                let finish_reason = if is_completion && !chunk_sent_completion {
-                    chunk_sent_completion = true;
-                    Some("stop".to_string())
-                } else {
-                    None
+            Ok(StreamItem::Role) => {
+                let delta = Delta {
+                    role: Some("assistant".to_string()),
+                    ..text(String::new())
                 };
-
-                // First chunk includes the role
-                let delta = if first_chunk {
-                    first_chunk = false;
-                    Delta {
-                        role: Some("assistant".to_string()),
-                        content: Some(token),
-                    }
-                } else {
-                    Delta {
-                        role: None,
-                        content: Some(token),
-                    }
-                };
-
-                let chunk = ChatCompletionChunk {
-                    id: completion_id.clone(),
-                    object: "chat.completion.chunk",
-                    created,
-                    model: model_name.clone(),
-                    choices: vec![ChoiceChunk {
-                        index: 0,
-                        delta,
-                        finish_reason,
-                    }],
+                chunk(vec![choice(delta, None)], None, None)
+            }
+            Ok(StreamItem::Token(token)) => chunk(vec![choice(text(token), None)], None, None),
+            // The whole call in one delta, arguments included
+            Ok(StreamItem::ToolCall(call)) => {
+                let delta = Delta {
+                    tool_calls: Some(vec![ChatToolCall { index: Some(0), ..call }]),
+                    ..Default::default()
                 };
-
-                let json_data = serde_json::to_string(&chunk)
+                chunk(vec![choice(delta, None)], None, None)
+            }
+            // The last choice chunk: an empty delta and why generation ended
+            Ok(StreamItem::Done(stats, finish)) => chunk(vec![choice(Delta::default(), Some(finish))], None, Some(stats)),
+            // After the last choice chunk, with no choices, as OpenAI sends it
+            Ok(StreamItem::Usage(usage)) => chunk(vec![], Some(usage), None),
+            Ok(StreamItem::Stats(stats)) => {
+                let json_data = serde_json::to_string(&stats)
                     .unwrap_or_else(|_| "{}".to_string());
-
-                Event::default().data(json_data)
+                Event::default().event("x_litert.stats").data(json_data)
             }
+            // OpenAI clients stop reading at this sentinel
+            Ok(StreamItem::End) => Event::default().data("[DONE]"),
             Err(e) => {
                 // Send error event
+                metrics.record_http_error("chat_completions");
+                telemetry::record_error(&model_name, "stream");
                 Event::default().event("error").data(e.to_string())
             }
         };
@@ -525,6 +1377,29 @@ pub struct ModelObject {
     pub object: &'static str,
     pub created: u64,
     pub owned_by: &'static str,
+    /// Set for models with a loaded pool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_litert: Option<ModelExtension>,
+}
+
+/// State of a model's loaded pool, reported alongside the model
+#[derive(Debug, Serialize)]
+pub struct ModelExtension {
+    pub processes: usize,
+    /// Processes running on CPU because the GPU backend failed
+    pub gpu_fallbacks: usize,
+    /// Backend each process runs on (`gpu`, `cpu`, or `npu`)
+    pub backends: Vec<String>,
+}
+
+impl From<&PoolStatus> for ModelExtension {
+    fn from(pool: &PoolStatus) -> Self {
+        Self {
+            processes: pool.processes,
+            gpu_fallbacks: pool.gpu_fallbacks,
+            backends: pool.backends.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -533,40 +1408,474 @@ pub struct ModelsListResponse {
     pub data: Vec<ModelObject>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AgentRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    /// Names of registered tools to offer; all of them when omitted
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_steps: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgentResponse {
+    pub id: String,
+    pub object: &'static str,
+    pub created: u64,
+    pub model: String,
+    #[serde(flatten)]
+    pub run: AgentRun,
+}
+
+// Run the tool-execution loop until the model gives a final answer
+pub async fn agents(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Json(req): Json<AgentRequest>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("agents");
+
+    let tools = match &req.tools {
+        Some(names) => match state.manager.tools().subset(names) {
+            Ok(tools) => tools,
+            Err(e) => {
+                metrics.record_http_error("agents");
+                return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+            }
+        },
+        None => state.manager.tools().clone(),
+    };
+    let mut options = AgentOptions::default();
+    if let Some(max_steps) = req.max_steps {
+        options.max_steps = max_steps;
+    }
+
+    let turns: Vec<Turn> = req
+        .messages
+        .iter()
+        .map(|m| Turn::new(m.role.clone(), m.content_as_string()))
+        .collect();
+
+    let _permit = match admit_request(&state.manager, &req.model) {
+        Ok(permit) => permit,
+        Err(e) => {
+            metrics.record_http_error("agents");
+            return model_error_response(&e);
+        }
+    };
+    let manager = state.manager.for_tenant(tenant);
+    match manager.run_agent(&req.model, &turns, &tools, &options).await {
+        Ok(run) => Json(AgentResponse {
+            id: format!("agent-{}", uuid::Uuid::new_v4()),
+            object: "agent.run",
+            created: crate::history::unix_now(),
+            model: req.model,
+            run,
+        })
+        .into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Agent run failed");
+            metrics.record_http_error("agents");
+            model_error_response(&e)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetrievalRequest {
+    pub query: String,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetrievalResponse {
+    pub object: &'static str,
+    pub data: Vec<SearchHit>,
+}
+
+// Search ingested documents
+pub async fn retrieval(
+    State(state): State<AppState>,
+    Json(req): Json<RetrievalRequest>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("retrieval");
+
+    match state.manager.search_documents(&req.query, req.top_k).await {
+        Ok(data) => Json(RetrievalResponse { object: "list", data }).into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Document search failed");
+            metrics.record_http_error("retrieval");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationRequest {
+    pub input: ModerationInput,
+    /// Model that classifies; the server's default model when omitted
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// One text or several, as OpenAI's `input` accepts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ModerationInput {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl ModerationInput {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            ModerationInput::One(text) => vec![text],
+            ModerationInput::Many(texts) => texts,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    pub results: Vec<ModerationResult>,
+}
+
+// Classify each input with the local model
+pub async fn moderations(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Json(req): Json<ModerationRequest>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("moderations");
+
+    let model = req.model.unwrap_or_else(|| state.pool.model().to_string());
+    let inputs = req.input.into_vec();
+    if inputs.is_empty() {
+        metrics.record_http_error("moderations");
+        return (StatusCode::BAD_REQUEST, "input must not be empty").into_response();
+    }
+
+    let _permit = match admit_request(&state.manager, &model) {
+        Ok(permit) => permit,
+        Err(e) => {
+            metrics.record_http_error("moderations");
+            return model_error_response(&e);
+        }
+    };
+    let manager = state.manager.for_tenant(tenant);
+    let mut results = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        match manager.moderate(&model, input).await {
+            Ok(result) => results.push(result),
+            Err(e) => {
+                tracing::error!(error = %e, model = %model, "Moderation failed");
+                metrics.record_http_error("moderations");
+                return model_error_response(&e);
+            }
+        }
+    }
+
+    Json(ModerationResponse {
+        id: format!("modr-{}", uuid::Uuid::new_v4()),
+        model,
+        results,
+    })
+    .into_response()
+}
+
+/// How long a conversation update waits for another one on the same conversation
+const CONVERSATION_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+#[derive(Debug, Deserialize)]
+pub struct CreateConversationRequest {
+    pub model: String,
+    /// History to start from, e.g. a system prompt
+    #[serde(default)]
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AppendMessageRequest {
+    #[serde(default = "default_append_role")]
+    pub role: String,
+    pub content: String,
+}
+
+fn default_append_role() -> String {
+    "user".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationResponse {
+    pub id: String,
+    pub object: &'static str,
+    pub model: String,
+    pub messages: Vec<Turn>,
+}
+
+impl ConversationResponse {
+    fn from_session(session: &ChatSession) -> Self {
+        Self {
+            id: session.id().to_string(),
+            object: "conversation",
+            model: session.model().to_string(),
+            messages: session.history().to_vec(),
+        }
+    }
+}
+
+/// No saved conversation with this id belongs to the caller
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationNotFound {
+    pub id: String,
+}
+
+impl std::fmt::Display for ConversationNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No conversation found with id '{}'", self.id)
+    }
+}
+
+impl std::error::Error for ConversationNotFound {}
+
+/// Saved conversation `id` of the tenant (ids that aren't plain names never
+/// match, so they can't point outside the store)
+fn load_conversation(manager: &LitManager, id: &str) -> anyhow::Result<ChatSession> {
+    let not_found = || ConversationNotFound { id: id.to_string() };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(not_found().into());
+    }
+    let path = ChatSession::default_path(manager, id);
+    if !path.exists() {
+        return Err(not_found().into());
+    }
+    let session = ChatSession::resume_session(manager.clone(), &path)?;
+    if session.tenant() != manager.tenant().name() {
+        return Err(not_found().into());
+    }
+    Ok(session)
+}
+
+/// Serialize updates of one conversation across requests and processes
+async fn lock_conversation(manager: &LitManager, id: &str) -> anyhow::Result<CacheLock> {
+    CacheLock::acquire(&manager.sessions_dir(), &format!("conversation-{}", id), CONVERSATION_LOCK_TIMEOUT).await
+}
+
+fn invalid_role(param: String, role: &str) -> InvalidRequest {
+    InvalidRequest::new(
+        param,
+        format!("'{}' is not a supported role; expected one of {}", role, ROLES.join(", ")),
+    )
+}
+
+/// 404 for unknown conversations, 400 for invalid messages, otherwise as for completions
+fn conversation_error_response(e: &anyhow::Error) -> Response {
+    if let Some(invalid) = e.downcast_ref::<InvalidRequest>() {
+        return invalid.clone().into_response();
+    }
+    if !e.is::<ConversationNotFound>() {
+        return model_error_response(e);
+    }
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "error": {
+                "message": e.to_string(),
+                "type": "invalid_request_error",
+                "param": "conversation_id",
+                "code": "conversation_not_found"
+            }
+        })),
+    )
+        .into_response()
+}
+
+// Start a conversation stored on this server
+pub async fn create_conversation(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Json(req): Json<CreateConversationRequest>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("conversations");
+
+    let manager = state.manager.for_tenant(tenant);
+    let result = (|| {
+        manager.tenant().check_use(&req.model)?;
+        let mut session = manager.chat_session(&req.model);
+        for (i, message) in req.messages.iter().enumerate() {
+            if !ROLES.contains(&message.role.as_str()) {
+                return Err(invalid_role(format!("messages[{}].role", i), &message.role).into());
+            }
+            session.push_turn(Turn::new(message.role.clone(), message.content_as_string()));
+        }
+        session.save_session(&ChatSession::default_path(&manager, session.id()))?;
+        anyhow::Ok(session)
+    })();
+
+    match result {
+        Ok(session) => (StatusCode::CREATED, Json(ConversationResponse::from_session(&session))).into_response(),
+        Err(e) => {
+            metrics.record_http_error("conversations");
+            conversation_error_response(&e)
+        }
+    }
+}
+
+pub async fn get_conversation(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<String>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("conversations");
+
+    let manager = state.manager.for_tenant(tenant);
+    match load_conversation(&manager, &id) {
+        Ok(session) => Json(ConversationResponse::from_session(&session)).into_response(),
+        Err(e) => {
+            metrics.record_http_error("conversations");
+            conversation_error_response(&e)
+        }
+    }
+}
+
+// Append a message; a user message is answered by the model and both are stored
+pub async fn append_conversation_message(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<String>,
+    Json(req): Json<AppendMessageRequest>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("conversations");
+
+    let manager = state.manager.for_tenant(tenant);
+    let result = async {
+        if !ROLES.contains(&req.role.as_str()) {
+            return Err(invalid_role("role".to_string(), &req.role).into());
+        }
+        let _lock = lock_conversation(&manager, &id).await?;
+        let mut session = load_conversation(&manager, &id)?;
+        if req.role == "user" {
+            session.send(&req.content).await.inspect_err(|e| {
+                tracing::error!(error = %e, conversation = %id, "Conversation turn failed");
+                telemetry::record_error(session.model(), "conversation");
+            })?;
+        } else {
+            session.push_turn(Turn::new(req.role.clone(), req.content.clone()));
+        }
+        session.save_session(&ChatSession::default_path(&manager, session.id()))?;
+        anyhow::Ok(session)
+    }
+    .await;
+
+    match result {
+        Ok(session) => Json(ConversationResponse::from_session(&session)).into_response(),
+        Err(e) => {
+            metrics.record_http_error("conversations");
+            conversation_error_response(&e)
+        }
+    }
+}
+
+pub async fn delete_conversation(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<String>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("conversations");
+
+    let manager = state.manager.for_tenant(tenant);
+    let result = async {
+        let _lock = lock_conversation(&manager, &id).await?;
+        let session = load_conversation(&manager, &id)?;
+        std::fs::remove_file(ChatSession::default_path(&manager, session.id()))?;
+        anyhow::Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => Json(serde_json::json!({
+            "id": id,
+            "object": "conversation.deleted",
+            "deleted": true
+        }))
+        .into_response(),
+        Err(e) => {
+            metrics.record_http_error("conversations");
+            conversation_error_response(&e)
+        }
+    }
+}
+
 // List all locally downloaded models
-pub async fn list_models(State(state): State<AppState>) -> Response {
+pub async fn list_models(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+) -> Response {
     tracing::debug!("Listing locally downloaded models");
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("models");
+
+    // Split models whose arms the tenant may all use, and profiles of models
+    // it may use, are listed alongside
+    let splits: Vec<String> = state
+        .manager
+        .splits()
+        .iter()
+        .filter(|split| split.models().all(|model| tenant.can_use(model)))
+        .map(|split| split.name().to_string())
+        .chain(
+            state
+                .manager
+                .profiles()
+                .iter()
+                .filter(|profile| profile.name() != profile.model() && tenant.can_use(profile.model()))
+                .map(|profile| profile.name().to_string()),
+        )
+        .collect();
+    let adapters = state.manager.for_tenant(tenant.clone()).adapters();
 
-    // Get list of locally downloaded models
-    let models_output = match state.manager.list_models(false).await {
-        Ok(output) => {
-            tracing::debug!("Successfully retrieved model list");
-            output
+    // Locally downloaded models visible to the tenant
+    let downloaded = match state.manager.for_tenant(tenant).list_models_structured(false).await {
+        Ok(models) => {
+            tracing::debug!(models = models.len(), "Successfully retrieved model list");
+            models
         }
         Err(e) => {
             tracing::error!(error = %e, "Failed to list models");
+            metrics.record_http_error("models");
             return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
         }
     };
 
-    // Parse the output to extract model names
-    let model_names: Vec<String> = models_output
-        .lines()
-        .filter(|line| {
-            let trimmed = line.trim();
-            !trimmed.is_empty()
-                && !trimmed.starts_with("Available")
-                && !trimmed.starts_with("Downloaded")
-                && !trimmed.starts_with("ALIAS")
-        })
-        .filter_map(|line| line.split_whitespace().next())
-        .map(|s| s.to_string())
+    let model_names: Vec<String> = downloaded
+        .into_iter()
+        .map(|model| model.alias)
+        .chain(splits)
+        .chain(adapters.iter().map(|adapter| adapter.model()))
         .collect();
 
     // Create model objects
+    let pools = state.manager.stats().await.pools;
     let models: Vec<ModelObject> = model_names
         .into_iter()
         .map(|id| ModelObject {
+            x_litert: pools.iter().find(|pool| pool.model == id).map(ModelExtension::from),
             id,
             object: "model",
             created: 1700000000, // Static timestamp
@@ -585,30 +1894,42 @@ pub async fn list_models(State(state): State<AppState>) -> Response {
 // Get a specific model by ID
 pub async fn get_model(
     State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
     Path(model_id): Path<String>,
 ) -> Response {
     tracing::debug!(model_id = %model_id, "Looking up specific model");
-
-    // Get list of locally downloaded models
-    let models_output = match state.manager.list_models(false).await {
-        Ok(output) => output,
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("models");
+
+    let is_configured = state
+        .manager
+        .splits()
+        .get(&model_id)
+        .is_some_and(|split| split.models().all(|model| tenant.can_use(model)))
+        || state
+            .manager
+            .profiles()
+            .get(&model_id)
+            .is_some_and(|profile| profile.name() != profile.model() && tenant.can_use(profile.model()))
+        || state
+            .manager
+            .for_tenant(tenant.clone())
+            .adapters()
+            .iter()
+            .any(|adapter| adapter.model() == model_id);
+
+    // Locally downloaded models visible to the tenant
+    let downloaded = match state.manager.for_tenant(tenant).list_models_structured(false).await {
+        Ok(models) => models,
         Err(e) => {
             tracing::error!(error = %e, model_id = %model_id, "Failed to list models");
+            metrics.record_http_error("models");
             return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
         }
     };
 
     // Check if the requested model exists
-    let model_exists = models_output
-        .lines()
-        .filter(|line| {
-            let trimmed = line.trim();
-            !trimmed.is_empty()
-                && !trimmed.starts_with("Available")
-                && !trimmed.starts_with("Downloaded")
-                && !trimmed.starts_with("ALIAS")
-        })
-        .any(|line| line.trim() == model_id);
+    let model_exists = is_configured || downloaded.iter().any(|model| model.alias == model_id);
 
     if !model_exists {
         tracing::warn!(model_id = %model_id, "Model not found");
@@ -626,7 +1947,9 @@ pub async fn get_model(
     }
 
     tracing::debug!(model_id = %model_id, "Model found");
+    let pools = state.manager.stats().await.pools;
     let model = ModelObject {
+        x_litert: pools.iter().find(|pool| pool.model == model_id).map(ModelExtension::from),
         id: model_id,
         object: "model",
         created: 1700000000,
@@ -636,11 +1959,452 @@ pub async fn get_model(
     Json(model).into_response()
 }
 
+// Health, stats, and metrics endpoints
+pub async fn health() -> Response {
+    Json(serde_json::json!({ "status": "ok" })).into_response()
+}
+
+// Readiness probe: 503 until the preloaded pool has warmed up
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let ready = state.readiness.state();
+    let status = if ready == ReadyState::Ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(ready)).into_response()
+}
+
+/// `length` for completions that reached `max_tokens`
+fn finish_reason(completion_tokens: usize, max_tokens: Option<usize>) -> FinishReason {
+    match max_tokens {
+        Some(max_tokens) if completion_tokens >= max_tokens => FinishReason::Length,
+        _ => FinishReason::Stop,
+    }
+}
+
+fn not_ready_response() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(axum::http::header::RETRY_AFTER, NOT_READY_RETRY_AFTER_SECS.to_string())],
+        Json(serde_json::json!({
+            "error": {
+                "message": "The model is still loading; retry once /readyz reports ready",
+                "type": "server_error",
+                "param": null,
+                "code": "model_not_ready"
+            }
+        })),
+    )
+        .into_response()
+}
+
+// Report loaded models, queue depth, and error counts
+pub async fn stats(State(state): State<AppState>) -> Response {
+    Json(state.manager.stats().await).into_response()
+}
+
+// Prometheus scrape endpoint
+pub async fn metrics(State(state): State<AppState>) -> Response {
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.manager.render_metrics().await,
+    )
+        .into_response()
+}
+
+// Pool workers, pending generations, and stuck commands
+pub async fn debug_tasks(State(state): State<AppState>) -> Response {
+    Json(state.manager.debug_tasks()).into_response()
+}
+
+/// Generations in progress that the caller may see, oldest first
+pub async fn list_requests(State(state): State<AppState>, Extension(tenant): Extension<Tenant>) -> Response {
+    state.manager.metrics().record_http_request("requests");
+    let requests = state.manager.for_tenant(tenant).inflight();
+    Json(serde_json::json!({ "object": "list", "data": requests })).into_response()
+}
+
+/// lit processes of the models the caller manages, with the end of their stderr
+pub async fn list_processes(State(state): State<AppState>, Extension(tenant): Extension<Tenant>) -> Response {
+    state.manager.metrics().record_http_request("processes");
+    let pools = state.manager.for_tenant(tenant).diagnostics().await;
+    Json(serde_json::json!({ "object": "list", "data": pools })).into_response()
+}
+
+/// Kill a runaway generation: its completion fails, or its stream ends
+pub async fn kill_request(
+    State(state): State<AppState>,
+    Extension(tenant): Extension<Tenant>,
+    Path(id): Path<String>,
+) -> Response {
+    let metrics = state.manager.metrics();
+    metrics.record_http_request("requests");
+    if state.manager.for_tenant(tenant).kill_request(&id) {
+        return Json(serde_json::json!({ "id": id, "object": "request.killed", "killed": true })).into_response();
+    }
+    metrics.record_http_error("requests");
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "error": {
+                "message": format!("No request '{}' in progress", id),
+                "type": "invalid_request_error",
+                "code": "request_not_found"
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// Pool for the requested model, lit flags, and sampling settings, reusing
+/// the state's pool when it serves that model with the defaults
+async fn pool_for(state: &AppState, manager: &LitManager, model: &str, options: &GenOptions) -> anyhow::Result<Arc<ProcessPool>> {
+    // Once the model's breaker has tripped, the manager decides which pool
+    // (if any) is served
+    let tripped = manager.circuit(model).is_some_and(|circuit| circuit.trips > 0);
+    let defaults = manager.sampling().for_model(model);
+    let default_sampling = options.sampling().or(defaults) == defaults;
+    // A pool that was unloaded is started again
+    let restarting = state.pool.is_retired();
+    if model == state.pool.model() && options.args.is_empty() && default_sampling && !tripped && !restarting {
+        manager.tenant().check_use(model)?;
+        return Ok(state.pool.clone());
+    }
+    manager.pool_with_options(model, options).await
+}
+
+/// Generate the reply to a conversation, sending only its new turns when a
+/// process still holds the earlier ones, and `prompt` (all of it) otherwise
+async fn converse(
+    manager: &LitManager,
+    pool: &Arc<ProcessPool>,
+    conversation: &str,
+    turns: &[Turn],
+    prompt: &str,
+    options: &GenOptions,
+    tokenizer: Arc<dyn Tokenizer>,
+) -> anyhow::Result<(String, GenerationTiming)> {
+    let placement = pool.place(conversation, turns).await?;
+    let sent = match placement.held {
+        0 => prompt.to_string(),
+        held => manager.chat_template(pool.model()).render(&turns[held..]),
+    };
+    let (text, timing) = placement.send(&sent, prompt, options.max_tokens, tokenizer).await?;
+    let mut held: Vec<Turn> = turns.to_vec();
+    held.push(Turn::new("assistant", text.trim()));
+    pool.remember(conversation, &placement, &held);
+    Ok((text, timing))
+}
+
+/// Pass a conversation's streamed reply through, noting which process holds
+/// the conversation once the reply is complete
+fn remember_reply(
+    stream: impl futures_util::Stream<Item = anyhow::Result<String>> + Send + 'static,
+    pool: Arc<ProcessPool>,
+    conversation: String,
+    placement: Placement,
+    turns: Vec<Turn>,
+) -> futures_util::stream::BoxStream<'static, anyhow::Result<String>> {
+    let turn = Some((pool, conversation, placement, turns, String::new()));
+    futures_util::stream::unfold((stream.boxed(), turn), |(mut stream, mut turn)| async move {
+        match stream.next().await {
+            Some(Ok(text)) => {
+                if let Some((.., reply)) = &mut turn {
+                    reply.push_str(&text);
+                }
+                Some((Ok(text), (stream, turn)))
+            }
+            // A failed reply leaves the process holding an unknown part of it
+            Some(Err(e)) => Some((Err(e), (stream, None))),
+            None => {
+                if let Some((pool, conversation, placement, mut turns, reply)) = turn {
+                    turns.push(Turn::new("assistant", reply.trim()));
+                    pool.remember(&conversation, &placement, &turns);
+                }
+                None
+            }
+        }
+    })
+    .boxed()
+}
+
+/// Count a request against its model's limit of requests in flight
+fn admit_request(manager: &LitManager, model: &str) -> anyhow::Result<ConcurrencyPermit> {
+    manager.concurrency().try_acquire(model).map_err(|e| {
+        tracing::warn!(model = %model, limit = e.limit, "Model at its concurrency limit; rejecting request");
+        manager.metrics().concurrency_rejections.with_label_values(&[model]).inc();
+        e.into()
+    })
+}
+
+/// Keep `guard` alive until the response body has been sent (or the client
+/// went away)
+fn hold_until_sent(response: Response, guard: impl Send + 'static) -> Response {
+    response.map(|body| {
+        axum::body::Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let _ = &guard;
+            chunk
+        }))
+    })
+}
+
+/// 403 for models outside the caller's namespace, 400 for prompts that can't
+/// fit the context window, 404 for models that aren't downloaded, 429 for
+/// models at their limit of requests in flight, 502 for lit processes that
+/// died and pulls that failed, 503 for models backed off after crashing and a
+/// missing lit binary, 504 for generations that timed out or stalled, 500 for
+/// anything else
+fn model_error_response(e: &anyhow::Error) -> Response {
+    if e.is::<Saturated>() || e.is::<QueueFull>() {
+        let code = if e.is::<Saturated>() { "model_concurrency_exceeded" } else { "queue_full" };
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(axum::http::header::RETRY_AFTER, SATURATED_RETRY_AFTER_SECS.to_string())],
+            Json(serde_json::json!({
+                "error": {
+                    "message": e.to_string(),
+                    "type": "rate_limit_error",
+                    "code": code
+                }
+            })),
+        )
+            .into_response();
+    }
+    if let Some(unstable) = e.downcast_ref::<Unstable>() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(axum::http::header::RETRY_AFTER, unstable.retry_after_secs().to_string())],
+            Json(serde_json::json!({
+                "error": {
+                    "message": e.to_string(),
+                    "type": "server_error",
+                    "code": "model_unstable"
+                }
+            })),
+        )
+            .into_response();
+    }
+    if let Some(error) = LitError::of(e) {
+        let (status, kind, code) = match error {
+            LitError::ModelNotFound { .. } => (StatusCode::NOT_FOUND, "invalid_request_error", "model_not_found"),
+            LitError::BinaryUnavailable { .. } => (StatusCode::SERVICE_UNAVAILABLE, "server_error", "binary_unavailable"),
+            LitError::DownloadFailed { .. } => (StatusCode::BAD_GATEWAY, "server_error", "download_failed"),
+            LitError::ChecksumMismatch { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "server_error", "checksum_mismatch"),
+            LitError::ProcessCrashed { .. } => (StatusCode::BAD_GATEWAY, "server_error", "process_crashed"),
+            LitError::Timeout { .. } => (StatusCode::GATEWAY_TIMEOUT, "server_error", "timeout"),
+            LitError::Stalled { .. } => (StatusCode::GATEWAY_TIMEOUT, "server_error", "generation_stalled"),
+        };
+        return (
+            status,
+            Json(serde_json::json!({
+                "error": {
+                    "message": e.to_string(),
+                    "type": kind,
+                    "code": code
+                }
+            })),
+        )
+            .into_response();
+    }
+    let (status, code) = if e.is::<AccessDenied>() {
+        (StatusCode::FORBIDDEN, "model_not_permitted")
+    } else if e.is::<ContextOverflow>() {
+        (StatusCode::BAD_REQUEST, "context_length_exceeded")
+    } else {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "error": {
+                "message": e.to_string(),
+                "type": "invalid_request_error",
+                "code": code
+            }
+        })),
+    )
+        .into_response()
+}
+
+// Resolve the caller's tenant from its API key (`Authorization: Bearer <key>`)
+pub async fn authenticate(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let key = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    match state.manager.tenants().for_key(key) {
+        Some(tenant) => {
+            request.extensions_mut().insert(tenant);
+            next.run(request).await
+        }
+        None => {
+            state.manager.metrics().record_http_error("auth");
+            let message = match key {
+                None => "Missing API key; send it as 'Authorization: Bearer <key>'",
+                Some(_) => "Incorrect API key provided",
+            };
+            (
+                StatusCode::UNAUTHORIZED,
+                [(axum::http::header::WWW_AUTHENTICATE, "Bearer")],
+                Json(serde_json::json!({
+                    "error": {
+                        "message": message,
+                        "type": "invalid_request_error",
+                        "param": null,
+                        "code": "invalid_api_key"
+                    }
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Debug endpoints are on in debug builds, or with `LITERT_DEBUG_ENDPOINTS=1`
+fn debug_endpoints_enabled() -> bool {
+    cfg!(debug_assertions)
+        || std::env::var("LITERT_DEBUG_ENDPOINTS")
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+}
+
 pub fn create_router(state: AppState) -> Router {
-    Router::new()
+    let mut router = Router::new()
+        .route("/v1/stats", get(stats))
+        .route("/metrics", get(metrics))
         .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/agents", post(agents))
+        .route("/v1/retrieval", post(retrieval))
+        .route("/v1/moderations", post(moderations))
+        .route("/v1/conversations", post(create_conversation))
+        .route("/v1/conversations/:id", get(get_conversation).delete(delete_conversation))
+        .route("/v1/conversations/:id/messages", post(append_conversation_message))
         .route("/v1/models", get(list_models))
         .route("/v1/models/:model", get(get_model))
-        .layer(TraceLayer::new_for_http())
+        .route("/admin/requests", get(list_requests))
+        .route("/admin/requests/:id", delete(kill_request))
+        .route("/v1/internal/processes", get(list_processes));
+
+    if debug_endpoints_enabled() {
+        router = router.route("/debug/tasks", get(debug_tasks));
+    }
+
+    router
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .route("/health", get(health))
+        .route("/readyz", get(readyz))
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
         .with_state(state)
 }
+
+fn request_span(request: &axum::http::Request<axum::body::Body>) -> tracing::Span {
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+    );
+    telemetry::set_remote_parent(&span, request.headers());
+    span
+}
+
+// Cluster router: the OpenAI-compatible API proxied to remote nodes
+
+pub async fn cluster_health(State(cluster): State<Cluster>) -> Response {
+    if cluster.is_healthy() {
+        health().await
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "status": "no healthy nodes" })),
+        )
+            .into_response()
+    }
+}
+
+// Every node with its health, models, and in-flight requests
+pub async fn cluster_nodes(State(cluster): State<Cluster>) -> Response {
+    Json(serde_json::json!({ "object": "list", "data": cluster.nodes() })).into_response()
+}
+
+// Models available on any healthy node
+pub async fn cluster_models(State(cluster): State<Cluster>) -> Response {
+    let data = cluster
+        .models()
+        .into_iter()
+        .map(|id| ModelObject {
+            id,
+            object: "model",
+            created: 1700000000,
+            owned_by: "litert-lm",
+            x_litert: None,
+        })
+        .collect();
+    Json(ModelsListResponse { object: "list", data }).into_response()
+}
+
+// Forward a POST to the best node for the request's model and relay the response
+pub async fn cluster_proxy(
+    State(cluster): State<Cluster>,
+    uri: axum::http::Uri,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    use axum::http::header;
+
+    let model = serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|request| request.get("model")?.as_str().map(str::to_string));
+
+    let mut forwarded = axum::http::HeaderMap::new();
+    for name in [header::CONTENT_TYPE, header::ACCEPT, header::AUTHORIZATION] {
+        if let Some(value) = headers.get(&name) {
+            forwarded.insert(name, value.clone());
+        }
+    }
+    for name in ["traceparent", "tracestate"] {
+        if let Some(value) = headers.get(name) {
+            forwarded.insert(name, value.clone());
+        }
+    }
+
+    match cluster.forward(uri.path(), model.as_deref(), forwarded, body).await {
+        Ok(Forwarded { node, response, guard }) => {
+            let mut builder = Response::builder()
+                .status(response.status())
+                .header("x-litert-node", node);
+            if let Some(content_type) = response.headers().get(header::CONTENT_TYPE) {
+                builder = builder.header(header::CONTENT_TYPE, content_type);
+            }
+            // The node stays counted as busy until the whole body is relayed
+            let body = response.bytes_stream().map(move |chunk| {
+                let _ = &guard;
+                chunk
+            });
+            builder
+                .body(axum::body::Body::from_stream(body))
+                .unwrap_or_else(|e| (StatusCode::BAD_GATEWAY, e.to_string()).into_response())
+        }
+        Err(e) => {
+            tracing::error!(error = %e, path = %uri.path(), "Failed to forward request");
+            (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+        }
+    }
+}
+
+pub fn create_cluster_router(cluster: Cluster) -> Router {
+    Router::new()
+        .route("/health", get(cluster_health))
+        .route("/v1/cluster", get(cluster_nodes))
+        .route("/v1/models", get(cluster_models))
+        .route("/v1/chat/completions", post(cluster_proxy))
+        .route("/v1/agents", post(cluster_proxy))
+        .route("/v1/retrieval", post(cluster_proxy))
+        .route("/v1/moderations", post(cluster_proxy))
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
+        .with_state(cluster)
+}
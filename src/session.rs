@@ -0,0 +1,384 @@
+//! Multi-turn chat sessions
+//!
+//! A [`ChatSession`] keeps a conversation's history and sends it, fitted to the
+//! model's context window, with every new message. Sessions can be saved to a
+//! file and resumed later, even by another process. lit has no way to persist
+//! its KV cache, so a resumed session replays its history on the next turn.
+//! A session can also be exported as a Markdown or JSON lines transcript, to
+//! share a conversation with someone debugging the same model.
+
+use anyhow::{Context, Result};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::context::{Overflow, Turn};
+use crate::history::{unix_now, HistoryRecord};
+use crate::manager::LitManager;
+use crate::schedule::format_utc;
+use crate::stream::LitertExtension;
+
+/// One line of `run --json` output, for programs driving an interactive session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// Waiting for the next message or command on stdin
+    Ready { session: String, model: String, messages: usize },
+    /// Text generated so far for the reply, in order
+    Token { text: String },
+    /// The complete reply
+    Done { text: String },
+    /// Latency of the reply that just finished
+    Stats(LitertExtension),
+    Saved { session: String, path: PathBuf },
+    Exported { session: String, path: PathBuf },
+    Loaded { session: String, messages: usize },
+    Error { message: String },
+}
+
+/// A session as written by [`ChatSession::save_session`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub id: String,
+    pub model: String,
+    /// Unix timestamp (seconds)
+    pub saved_at: u64,
+    pub turns: Vec<Turn>,
+    /// Tenant that started the session; absent in sessions saved before tenants were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// When each turn was added (Unix timestamps in seconds); absent in
+    /// sessions saved before timestamps were recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timestamps: Vec<u64>,
+}
+
+/// Layout of [`ChatSession::export`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    /// A document with the settings up front and a section per message
+    #[default]
+    Markdown,
+    /// A `session` line with the settings, then a `message` line per message
+    Jsonl,
+}
+
+impl TranscriptFormat {
+    /// File extension for transcripts in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "md",
+            TranscriptFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl std::str::FromStr for TranscriptFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(TranscriptFormat::Markdown),
+            "jsonl" | "json" => Ok(TranscriptFormat::Jsonl),
+            _ => anyhow::bail!("Unknown transcript format '{}' (expected markdown or jsonl)", s),
+        }
+    }
+}
+
+/// Settings a session's replies are generated with, as exported
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptSettings {
+    /// Model serving the session when its model is a profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<String>,
+    /// System prompt the profile adds to every turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Context length in tokens
+    pub context_length: usize,
+    /// Tokens kept free for each reply
+    pub reserve_tokens: usize,
+    pub overflow: Overflow,
+}
+
+// Lines of a JSON lines transcript
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TranscriptLine<'a> {
+    Session {
+        id: &'a str,
+        model: &'a str,
+        tenant: &'a str,
+        exported_at: u64,
+        settings: &'a TranscriptSettings,
+    },
+    Message {
+        role: &'a str,
+        content: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timestamp: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    manager: LitManager,
+    id: String,
+    model: String,
+    turns: Vec<Turn>,
+    // When each turn was added; shorter than `turns` for resumed older sessions
+    timestamps: Vec<u64>,
+    tenant: String,
+}
+
+impl ChatSession {
+    pub fn new(manager: LitManager, model: &str) -> Self {
+        let tenant = manager.tenant().name().to_string();
+        Self {
+            manager,
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            model: model.to_string(),
+            turns: Vec::new(),
+            timestamps: Vec::new(),
+            tenant,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Name of the tenant that started the session
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    /// Messages so far, oldest first
+    pub fn history(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Add a message to the history without generating a reply, e.g. a
+    /// system prompt or an exchange that happened elsewhere
+    pub fn push_turn(&mut self, turn: Turn) {
+        self.add_turn(turn, unix_now());
+    }
+
+    /// When the turn at `index` of the history was added, if recorded
+    pub fn timestamp(&self, index: usize) -> Option<u64> {
+        // Timestamps of a resumed older session cover only the newest turns
+        let untimed = self.turns.len().saturating_sub(self.timestamps.len());
+        index.checked_sub(untimed).and_then(|i| self.timestamps.get(i).copied())
+    }
+
+    fn add_turn(&mut self, turn: Turn, at: u64) {
+        self.turns.push(turn);
+        self.timestamps.push(at);
+    }
+
+    /// Forget the conversation, keeping the session's id and model
+    pub fn reset(&mut self) {
+        self.turns.clear();
+        self.timestamps.clear();
+    }
+
+    /// Send a user message and return the reply
+    pub async fn send(&mut self, message: &str) -> Result<String> {
+        self.send_with(message, |_| {}).await
+    }
+
+    /// Send a user message, passing the reply to `on_chunk` as it is generated
+    ///
+    /// The exchange is only added to the history if generation succeeds.
+    pub async fn send_with(&mut self, message: &str, mut on_chunk: impl FnMut(&str)) -> Result<String> {
+        let mut stream = self.send_stream(message).await?;
+        let mut reply = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            on_chunk(&chunk);
+            reply.push_str(&chunk);
+        }
+        Ok(reply)
+    }
+
+    /// Send a user message and stream the reply as it is generated
+    ///
+    /// The history is sent along in the model's chat template, fitted to its
+    /// context window. The exchange is added to the history once the stream
+    /// ends without an error; a stream dropped early leaves the history as it
+    /// was.
+    pub async fn send_stream(&mut self, message: &str) -> Result<BoxStream<'_, Result<String>>> {
+        let started = std::time::Instant::now();
+        let sent_at = unix_now();
+        // A profile's system prompt is added on every turn rather than saved
+        let profile = self.manager.profiles().get(&self.model);
+        let model = profile.map_or(self.model.as_str(), |profile| profile.model());
+        let pool = self.manager.pool(model).await?;
+        let mut turns = self.turns.clone();
+        turns.push(Turn::new("user", message));
+        if let Some(profile) = profile {
+            profile.apply_turns(&mut turns);
+        }
+        let prompt = self.manager.fit_context(&pool, turns).await?.prompt;
+
+        let record = HistoryRecord::new(uuid::Uuid::new_v4().to_string(), model, "session", &prompt);
+        let start = async {
+            let slot = pool.admit(self.manager.tenant()).await?;
+            let process = pool.get_process().await?;
+            let stream = process.send_prompt_stream(&prompt).await?;
+            anyhow::Ok((slot, stream.boxed()))
+        }
+        .await;
+        let (slot, stream) = match start {
+            Ok(start) => start,
+            Err(e) => {
+                self.manager.record_history(record.finish(started, Err(e.to_string())));
+                return Err(e);
+            }
+        };
+
+        // The pool slot is kept until the reply is finished or dropped
+        let state = (self, stream, String::new(), record, slot, message.to_string());
+        let stream = futures::stream::unfold(Some(state), move |state| async move {
+            let (session, mut stream, mut reply, record, slot, message) = state?;
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    reply.push_str(&chunk);
+                    Some((Ok(chunk), Some((session, stream, reply, record, slot, message))))
+                }
+                Some(Err(e)) => {
+                    session.manager.record_history(record.finish(started, Err(e.to_string())));
+                    Some((Err(e), None))
+                }
+                None => {
+                    drop(slot);
+                    session.manager.record_history(record.finish(started, Ok(&reply)));
+                    session.add_turn(Turn::new("user", message), sent_at);
+                    session.add_turn(Turn::new("assistant", reply.trim()), unix_now());
+                    None
+                }
+            }
+        });
+        Ok(stream.boxed())
+    }
+
+    /// Settings the session's replies are generated with
+    pub fn settings(&self) -> TranscriptSettings {
+        let profile = self.manager.profiles().get(&self.model);
+        let served_by = profile.map(|profile| profile.model()).filter(|model| *model != self.model);
+        let window = self.manager.context_window(served_by.unwrap_or(&self.model));
+        TranscriptSettings {
+            served_by: served_by.map(str::to_string),
+            system_prompt: profile.and_then(|profile| profile.system_prompt()).map(str::to_string),
+            context_length: window.length,
+            reserve_tokens: window.reserve,
+            overflow: window.overflow,
+        }
+    }
+
+    /// The conversation so far as a transcript, with its timestamps, model,
+    /// and settings
+    pub fn export(&self, format: TranscriptFormat) -> Result<String> {
+        let settings = self.settings();
+        let exported_at = unix_now();
+        match format {
+            TranscriptFormat::Jsonl => {
+                let mut out = serde_json::to_string(&TranscriptLine::Session {
+                    id: &self.id,
+                    model: &self.model,
+                    tenant: &self.tenant,
+                    exported_at,
+                    settings: &settings,
+                })?;
+                out.push('\n');
+                for (i, turn) in self.turns.iter().enumerate() {
+                    out.push_str(&serde_json::to_string(&TranscriptLine::Message {
+                        role: &turn.role,
+                        content: &turn.content,
+                        timestamp: self.timestamp(i),
+                    })?);
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+            TranscriptFormat::Markdown => {
+                let mut out = format!("# Chat session {}\n\n", self.id);
+                out.push_str(&format!("- Model: {}\n", self.model));
+                if let Some(served_by) = &settings.served_by {
+                    out.push_str(&format!("- Served by: {}\n", served_by));
+                }
+                out.push_str(&format!("- Tenant: {}\n", self.tenant));
+                let overflow = match settings.overflow {
+                    Overflow::Truncate => "dropped",
+                    Overflow::Summarize => "summarized",
+                };
+                out.push_str(&format!(
+                    "- Context window: {} tokens, {} reserved for replies; oldest turns {} when full\n",
+                    settings.context_length, settings.reserve_tokens, overflow
+                ));
+                out.push_str(&format!("- Exported: {}\n", format_utc(exported_at)));
+                if let Some(system_prompt) = &settings.system_prompt {
+                    out.push_str(&format!("\n## system (profile)\n\n{}\n", system_prompt));
+                }
+                for (i, turn) in self.turns.iter().enumerate() {
+                    match self.timestamp(i) {
+                        Some(at) => out.push_str(&format!("\n## {} · {}\n\n", turn.role, format_utc(at))),
+                        None => out.push_str(&format!("\n## {}\n\n", turn.role)),
+                    }
+                    out.push_str(turn.content.trim_end());
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    /// Write the session to `path` so it can be resumed later
+    pub fn save_session(&self, path: &Path) -> Result<()> {
+        let saved = SavedSession {
+            id: self.id.clone(),
+            model: self.model.clone(),
+            saved_at: unix_now(),
+            turns: self.turns.clone(),
+            tenant: Some(self.tenant.clone()),
+            timestamps: self.timestamps.clone(),
+        };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create session directory {}", dir.display()))?;
+        }
+        let json = serde_json::to_vec_pretty(&saved)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to save session to {}", path.display()))?;
+        tracing::debug!(session = %self.id, path = %path.display(), turns = self.turns.len(), "Saved chat session");
+        Ok(())
+    }
+
+    /// Continue a session saved with [`save_session`](Self::save_session)
+    pub fn resume_session(manager: LitManager, path: &Path) -> Result<Self> {
+        let json = std::fs::read(path).with_context(|| format!("Failed to read session {}", path.display()))?;
+        let saved: SavedSession =
+            serde_json::from_slice(&json).with_context(|| format!("Invalid session file {}", path.display()))?;
+        manager.tenant().check_use(&saved.model)?;
+        tracing::debug!(session = %saved.id, turns = saved.turns.len(), "Resumed chat session");
+
+        let tenant = saved.tenant.unwrap_or_else(|| manager.tenant().name().to_string());
+        Ok(Self {
+            manager,
+            id: saved.id,
+            model: saved.model,
+            turns: saved.turns,
+            timestamps: saved.timestamps,
+            tenant,
+        })
+    }
+
+    /// Default location of a saved session: `<cache_dir>/sessions/<id>.json`
+    pub fn default_path(manager: &LitManager, id: &str) -> PathBuf {
+        manager.sessions_dir().join(format!("{}.json", id))
+    }
+}
@@ -0,0 +1,432 @@
+//! Where pulled models come from
+//!
+//! `lit pull` downloads models from its registry or from a URL. A
+//! [`ModelSource`] turns any other model reference into something lit can
+//! pull, picked by the reference's URL scheme:
+//!
+//! - plain names go to lit's registry, `http(s)://` URLs to lit as they are
+//! - `s3://bucket/key` and `gs://bucket/object` become HTTPS URLs of the
+//!   object, pre-signed with the `aws` or `gcloud` CLI's credentials when
+//!   those are installed
+//! - `file://` URLs and paths of existing files are served to lit over a
+//!   loopback connection for the duration of the pull
+//!
+//! Other schemes can be added with
+//! [`LitManager::with_model_source`](crate::LitManager::with_model_source).
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// How long pre-signed object storage URLs stay valid
+const PRESIGN_EXPIRY_SECS: u64 = 3600;
+/// Extension of model files, left out of default aliases
+const MODEL_EXTENSION: &str = ".litertlm";
+
+/// What `lit pull` is given for a model reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullTarget {
+    /// A registry name or URL lit downloads itself
+    Lit(String),
+    /// A file on this machine
+    File(PathBuf),
+}
+
+/// A kind of model reference, resolved to something lit can pull
+pub trait ModelSource: Send + Sync {
+    /// Short name for logs, e.g. `s3`
+    fn name(&self) -> &'static str;
+
+    /// URL schemes the source handles (without `://`); the source for plain
+    /// names has none
+    fn schemes(&self) -> &[&'static str];
+
+    /// What lit pulls for `reference`
+    fn resolve<'a>(&'a self, reference: &'a str) -> BoxFuture<'a, Result<PullTarget>>;
+}
+
+/// Models in lit's registry, by name
+#[derive(Debug, Clone, Default)]
+pub struct RegistrySource;
+
+impl ModelSource for RegistrySource {
+    fn name(&self) -> &'static str {
+        "registry"
+    }
+
+    fn schemes(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn resolve<'a>(&'a self, reference: &'a str) -> BoxFuture<'a, Result<PullTarget>> {
+        async move { Ok(PullTarget::Lit(reference.to_string())) }.boxed()
+    }
+}
+
+/// Model files at HTTP(S) URLs, downloaded by lit
+#[derive(Debug, Clone, Default)]
+pub struct UrlSource;
+
+impl ModelSource for UrlSource {
+    fn name(&self) -> &'static str {
+        "url"
+    }
+
+    fn schemes(&self) -> &[&'static str] {
+        &["http", "https"]
+    }
+
+    fn resolve<'a>(&'a self, reference: &'a str) -> BoxFuture<'a, Result<PullTarget>> {
+        async move { Ok(PullTarget::Lit(reference.to_string())) }.boxed()
+    }
+}
+
+/// Model files on this machine, as `file://` URLs or paths
+#[derive(Debug, Clone, Default)]
+pub struct LocalSource;
+
+impl ModelSource for LocalSource {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn schemes(&self) -> &[&'static str] {
+        &["file"]
+    }
+
+    fn resolve<'a>(&'a self, reference: &'a str) -> BoxFuture<'a, Result<PullTarget>> {
+        async move {
+            let path = Path::new(reference.strip_prefix("file://").unwrap_or(reference));
+            anyhow::ensure!(path.is_file(), "No model file at {}", path.display());
+            Ok(PullTarget::File(path.canonicalize()?))
+        }
+        .boxed()
+    }
+}
+
+/// Objects in Amazon S3 or an S3-compatible store
+#[derive(Debug, Clone)]
+pub struct S3Source {
+    endpoint: Option<String>,
+    presign: bool,
+}
+
+impl Default for S3Source {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl S3Source {
+    /// Objects at `$AWS_ENDPOINT_URL_S3` or `$AWS_ENDPOINT_URL` if set,
+    /// otherwise on AWS in `$AWS_REGION`, pre-signed with `aws s3 presign`
+    pub fn new() -> Self {
+        let endpoint = std::env::var("AWS_ENDPOINT_URL_S3")
+            .or_else(|_| std::env::var("AWS_ENDPOINT_URL"))
+            .ok()
+            .filter(|endpoint| !endpoint.is_empty());
+        Self { endpoint, presign: true }
+    }
+
+    /// Objects of an S3-compatible store (e.g. MinIO), addressed by path
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Pull objects by their plain URL, for public buckets
+    pub fn without_presigning(mut self) -> Self {
+        self.presign = false;
+        self
+    }
+
+    /// Plain HTTPS URL of an object
+    pub fn object_url(&self, bucket: &str, key: &str) -> String {
+        match &self.endpoint {
+            Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, encode_path(key)),
+            None => match std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION")) {
+                Ok(region) if !region.is_empty() => {
+                    format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, encode_path(key))
+                }
+                _ => format!("https://{}.s3.amazonaws.com/{}", bucket, encode_path(key)),
+            },
+        }
+    }
+}
+
+impl ModelSource for S3Source {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    fn schemes(&self) -> &[&'static str] {
+        &["s3"]
+    }
+
+    fn resolve<'a>(&'a self, reference: &'a str) -> BoxFuture<'a, Result<PullTarget>> {
+        async move {
+            let (bucket, key) = split_object_url(reference, "s3")?;
+            if self.presign {
+                let mut command = tokio::process::Command::new("aws");
+                command.args(["s3", "presign", reference, "--expires-in", &PRESIGN_EXPIRY_SECS.to_string()]);
+                if let Some(endpoint) = &self.endpoint {
+                    command.args(["--endpoint-url", endpoint]);
+                }
+                if let Some(url) = signed_url(command, "aws s3 presign").await {
+                    return Ok(PullTarget::Lit(url));
+                }
+            }
+            Ok(PullTarget::Lit(self.object_url(bucket, key)))
+        }
+        .boxed()
+    }
+}
+
+/// Objects in Google Cloud Storage
+#[derive(Debug, Clone)]
+pub struct GcsSource {
+    endpoint: String,
+    presign: bool,
+}
+
+impl Default for GcsSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GcsSource {
+    /// Objects on `storage.googleapis.com` (or `$STORAGE_EMULATOR_HOST`),
+    /// signed with `gcloud storage sign-url`
+    pub fn new() -> Self {
+        let endpoint = std::env::var("STORAGE_EMULATOR_HOST")
+            .ok()
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "https://storage.googleapis.com".to_string());
+        Self { endpoint, presign: true }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Pull objects by their plain URL, for public buckets
+    pub fn without_presigning(mut self) -> Self {
+        self.presign = false;
+        self
+    }
+
+    /// Plain HTTPS URL of an object
+    pub fn object_url(&self, bucket: &str, object: &str) -> String {
+        format!("{}/{}/{}", self.endpoint.trim_end_matches('/'), bucket, encode_path(object))
+    }
+}
+
+impl ModelSource for GcsSource {
+    fn name(&self) -> &'static str {
+        "gcs"
+    }
+
+    fn schemes(&self) -> &[&'static str] {
+        &["gs"]
+    }
+
+    fn resolve<'a>(&'a self, reference: &'a str) -> BoxFuture<'a, Result<PullTarget>> {
+        async move {
+            let (bucket, object) = split_object_url(reference, "gs")?;
+            if self.presign {
+                let mut command = tokio::process::Command::new("gcloud");
+                command.args([
+                    "storage",
+                    "sign-url",
+                    reference,
+                    &format!("--duration={}s", PRESIGN_EXPIRY_SECS),
+                    "--format=value(signed_url)",
+                ]);
+                if let Some(url) = signed_url(command, "gcloud storage sign-url").await {
+                    return Ok(PullTarget::Lit(url));
+                }
+            }
+            Ok(PullTarget::Lit(self.object_url(bucket, object)))
+        }
+        .boxed()
+    }
+}
+
+/// `<scheme>://<bucket>/<key>` as bucket and key
+fn split_object_url<'a>(reference: &'a str, scheme: &str) -> Result<(&'a str, &'a str)> {
+    reference
+        .strip_prefix(scheme)
+        .and_then(|rest| rest.strip_prefix("://"))
+        .and_then(|rest| rest.split_once('/'))
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .with_context(|| format!("'{}' is not a {}://<bucket>/<key> URL", reference, scheme))
+}
+
+/// The URL a signing CLI prints, or `None` (logged) if it isn't installed or fails
+async fn signed_url(mut command: tokio::process::Command, what: &str) -> Option<String> {
+    match command.stdin(std::process::Stdio::null()).output().await {
+        Ok(output) if output.status.success() => {
+            let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if url.starts_with("http") {
+                return Some(url);
+            }
+            tracing::warn!(command = what, "Signing printed no URL; pulling the object's plain URL");
+        }
+        Ok(output) => tracing::warn!(
+            command = what,
+            stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+            "Couldn't sign the object URL; pulling its plain URL"
+        ),
+        Err(e) => tracing::debug!(command = what, error = %e, "Signing CLI unavailable; pulling the plain URL"),
+    }
+    None
+}
+
+/// Percent-encode a key for a URL path, keeping its `/`s
+fn encode_path(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Name a model pulled from `reference` is saved under unless given an
+/// alias: the file name without `.litertlm`, lowercased; `None` for registry names
+pub fn default_alias(reference: &str) -> Option<String> {
+    let path = match reference.split_once("://") {
+        Some((_, rest)) => rest.split(['?', '#']).next().unwrap_or(rest),
+        None if Path::new(reference).is_file() => reference,
+        None => return None,
+    };
+    let file = path.trim_end_matches('/').rsplit('/').next().filter(|file| !file.is_empty())?;
+    Some(file.strip_suffix(MODEL_EXTENSION).unwrap_or(file).to_lowercase())
+}
+
+/// Model sources by scheme
+#[derive(Clone)]
+pub struct ModelSources {
+    // Searched in order, so added sources take precedence over built-in ones
+    sources: Vec<Arc<dyn ModelSource>>,
+}
+
+impl std::fmt::Debug for ModelSources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.sources.iter().map(|source| source.name())).finish()
+    }
+}
+
+impl Default for ModelSources {
+    fn default() -> Self {
+        Self {
+            sources: vec![
+                Arc::new(UrlSource),
+                Arc::new(S3Source::new()),
+                Arc::new(GcsSource::new()),
+                Arc::new(LocalSource),
+                Arc::new(RegistrySource),
+            ],
+        }
+    }
+}
+
+impl ModelSources {
+    /// These sources with `source` in front, replacing any for the same schemes
+    pub fn with(&self, source: Arc<dyn ModelSource>) -> Self {
+        let mut sources = vec![source];
+        sources.extend(self.sources.iter().cloned());
+        Self { sources }
+    }
+
+    /// Source for `reference`: the one for its URL scheme, local files for
+    /// existing paths, and the registry for anything else
+    pub fn select(&self, reference: &str) -> Result<Arc<dyn ModelSource>> {
+        let scheme = match reference.split_once("://") {
+            Some((scheme, _)) => Some(scheme.to_ascii_lowercase()),
+            None if Path::new(reference).is_file() => Some("file".to_string()),
+            None => None,
+        };
+        let found = self.sources.iter().find(|source| match &scheme {
+            Some(scheme) => source.schemes().contains(&scheme.as_str()),
+            None => source.schemes().is_empty(),
+        });
+        match (found, scheme) {
+            (Some(source), _) => Ok(source.clone()),
+            (None, Some(scheme)) => anyhow::bail!("No model source for {}:// URLs", scheme),
+            (None, None) => anyhow::bail!("No model source for model names"),
+        }
+    }
+}
+
+/// Serves one file on a loopback port until dropped, so lit can pull it
+#[derive(Debug)]
+pub struct FileServer {
+    url: String,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl FileServer {
+    pub async fn start(path: PathBuf) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let file_name = path
+            .file_name()
+            .map(|name| encode_path(&name.to_string_lossy()))
+            .unwrap_or_else(|| "model".to_string());
+        let url = format!("http://{}/{}", listener.local_addr()?, file_name);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let path = path.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve(stream, &path).await {
+                        tracing::debug!(error = %e, "Failed to serve model file");
+                    }
+                });
+            }
+        });
+        Ok(Self { url, task })
+    }
+
+    /// URL the file is served at
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for FileServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Answer one request with the whole file, whatever was asked for
+async fn serve(mut stream: tokio::net::TcpStream, path: &Path) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() > 64 * 1024 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let mut file = tokio::fs::File::open(path).await?;
+    let length = file.metadata().await?.len();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Type: application/octet-stream\r\nConnection: close\r\n\r\n",
+        length
+    );
+    stream.write_all(head.as_bytes()).await?;
+    if !request.starts_with(b"HEAD ") {
+        tokio::io::copy(&mut file, &mut stream).await?;
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Traffic splitting between models
+//!
+//! A split maps a logical model name to weighted arms, e.g. `chat` served 90/10
+//! by `gemma-3n-E4B` and `gemma-3n-E2B`, so variants can be compared on live
+//! traffic. Requests that name a user stick to one arm; others are assigned at
+//! random.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::config::SplitConfig;
+
+/// A logical model name and the models it is split between
+#[derive(Debug, Clone)]
+pub struct Split {
+    name: String,
+    /// Models with their cumulative weight
+    arms: Vec<(String, u64)>,
+}
+
+impl Split {
+    pub fn from_config(config: &SplitConfig) -> Self {
+        let mut total = 0;
+        let arms = config
+            .arms
+            .iter()
+            .filter(|arm| arm.weight > 0)
+            .map(|arm| {
+                total += u64::from(arm.weight);
+                (arm.model.clone(), total)
+            })
+            .collect();
+        Self {
+            name: config.name.clone(),
+            arms,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Models this split sends traffic to
+    pub fn models(&self) -> impl Iterator<Item = &str> {
+        self.arms.iter().map(|(model, _)| model.as_str())
+    }
+
+    /// Model serving a request, the same one every time for a given `user`
+    pub fn pick(&self, user: Option<&str>) -> Option<&str> {
+        let total = self.arms.last()?.1;
+        let point = match user {
+            Some(user) => {
+                let mut hasher = DefaultHasher::new();
+                (&self.name, user).hash(&mut hasher);
+                hasher.finish() % total
+            }
+            None => fastrand::u64(0..total),
+        };
+        self.arms
+            .iter()
+            .find(|(_, cumulative)| point < *cumulative)
+            .map(|(model, _)| model.as_str())
+    }
+}
+
+/// Every configured split, by logical model name
+#[derive(Debug, Clone, Default)]
+pub struct Splits {
+    by_name: HashMap<String, Split>,
+}
+
+impl Splits {
+    pub fn from_config(configs: &[SplitConfig]) -> Self {
+        let by_name = configs
+            .iter()
+            .map(|config| (config.name.clone(), Split::from_config(config)))
+            .filter(|(_, split)| !split.arms.is_empty())
+            .collect();
+        Self { by_name }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Split> {
+        self.by_name.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Split> {
+        self.by_name.values()
+    }
+}
@@ -0,0 +1,687 @@
+//! Typed completion streams for library users
+//!
+//! [`LitManager::run_completion_stream`](crate::LitManager::run_completion_stream)
+//! returns a [`CompletionStream`]: text chunks numbered and timed from the
+//! start of the request, closed by a chunk carrying the finish reason. It can
+//! also be turned into the OpenAI chat completion chunks the server sends, so
+//! callers that relay it don't have to shape the JSON themselves, or into
+//! [`CompletionEvent`]s, which tell tokens from the completion's outcome.
+
+use anyhow::Result;
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use crate::agent::ToolCall;
+use crate::process::GenerationTiming;
+use crate::tokenizer::{Estimate, Tokenizer};
+
+/// Why generation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FinishReason {
+    /// The model finished its reply
+    Stop,
+    /// The reply was cut at `max_tokens`
+    Length,
+    /// The reply is a call to one of the request's tools
+    ToolCalls,
+}
+
+impl FinishReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+        }
+    }
+}
+
+/// How streamed text is divided into chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Chunking {
+    /// As lit produces it
+    #[default]
+    Raw,
+    /// One chunk per sentence (or line), for text-to-speech
+    Sentence,
+    /// Sentences, further split at commas, semicolons, colons, and dashes
+    /// once a clause is long enough to be spoken on its own
+    Clause,
+}
+
+impl std::str::FromStr for Chunking {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Chunking::Raw),
+            "sentence" => Ok(Chunking::Sentence),
+            "clause" => Ok(Chunking::Clause),
+            _ => Err(format!("'{}' is not a chunking mode; expected raw, sentence, or clause", s)),
+        }
+    }
+}
+
+/// Clause breaks are skipped until a clause has this many characters
+const MIN_CLAUSE_CHARS: usize = 24;
+
+/// Abbreviations whose period doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &["mr", "mrs", "ms", "dr", "prof", "st", "vs", "etc", "e.g", "i.e", "no"];
+
+/// Regroups streamed text into sentences or clauses
+///
+/// A sentence ends at `.`, `!`, `?`, or `…` followed by whitespace (closing
+/// quotes and brackets may come between), at `。`, `！`, or `？`, and at line
+/// breaks. Text is passed through unchanged, so the units join back into the
+/// original.
+#[derive(Debug, Clone)]
+pub struct SentenceSplitter {
+    chunking: Chunking,
+    buffer: String,
+}
+
+impl SentenceSplitter {
+    pub fn new(chunking: Chunking) -> Self {
+        Self {
+            chunking,
+            buffer: String::new(),
+        }
+    }
+
+    /// Add streamed text, returning the units it completes
+    pub fn push(&mut self, text: &str) -> Vec<String> {
+        if self.chunking == Chunking::Raw {
+            return vec![text.to_string()];
+        }
+        self.buffer.push_str(text);
+        let mut units = Vec::new();
+        while let Some(end) = self.boundary() {
+            units.push(self.buffer.drain(..end).collect());
+        }
+        units
+    }
+
+    /// Whatever is left once the stream ends
+    pub fn finish(&mut self) -> Option<String> {
+        (!self.buffer.is_empty()).then(|| std::mem::take(&mut self.buffer))
+    }
+
+    /// Byte offset just past the first complete unit in the buffer
+    fn boundary(&self) -> Option<usize> {
+        let text = &self.buffer;
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let after = i + c.len_utf8();
+            let ends_clause = match c {
+                '\n' | '。' | '！' | '？' => return Some(after),
+                '.' | '!' | '?' | '…' => !(c == '.' && is_abbreviation(&text[..i])),
+                ',' | ';' | ':' | '—' => self.chunking == Chunking::Clause && text[..i].chars().count() >= MIN_CLAUSE_CHARS,
+                _ => false,
+            };
+            if !ends_clause {
+                continue;
+            }
+            // Closing quotes and brackets stay with their sentence
+            let mut end = after;
+            while let Some(&(j, next)) = chars.peek() {
+                if matches!(next, '"' | '\'' | '”' | '’' | ')' | ']') {
+                    end = j + next.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            match text[end..].chars().next() {
+                Some(space) if space.is_whitespace() => return Some(end + space.len_utf8()),
+                // Wait for the next chunk to tell whether the sentence ended
+                None => return None,
+                Some(_) => {}
+            }
+        }
+        None
+    }
+}
+
+/// Whether the period after `before` belongs to an abbreviation or an initial
+fn is_abbreviation(before: &str) -> bool {
+    let word = before.rsplit(|c: char| c.is_whitespace() || c == '(').next().unwrap_or("");
+    let word = word.to_lowercase();
+    (word.chars().count() == 1 && word.chars().all(char::is_alphabetic)) || ABBREVIATIONS.contains(&word.as_str())
+}
+
+/// Regroup a stream of generated text per `chunking`
+pub fn rechunk(
+    text: impl Stream<Item = Result<String>> + Send + 'static,
+    chunking: Chunking,
+) -> BoxStream<'static, Result<String>> {
+    if chunking == Chunking::Raw {
+        return text.boxed();
+    }
+    let splitter = SentenceSplitter::new(chunking);
+    futures::stream::unfold((text.boxed(), splitter, false), |(mut text, mut splitter, done)| async move {
+        if done {
+            return None;
+        }
+        loop {
+            match text.next().await {
+                Some(Ok(chunk)) => {
+                    let units = splitter.push(&chunk);
+                    if !units.is_empty() {
+                        let units = futures::stream::iter(units.into_iter().map(Ok));
+                        return Some((units.boxed(), (text, splitter, false)));
+                    }
+                }
+                Some(Err(e)) => return Some((futures::stream::once(async move { Err(e) }).boxed(), (text, splitter, false))),
+                None => {
+                    let rest = futures::stream::iter(splitter.finish().map(Ok));
+                    return Some((rest.boxed(), (text, splitter, true)));
+                }
+            }
+        }
+    })
+    .flatten()
+    .boxed()
+}
+
+/// Ends streamed text at the first of the `stop` sequences, which is left out
+///
+/// Text that may be the start of a stop sequence is held back until the next
+/// chunk shows whether it is, so no part of one is ever sent. The underlying
+/// stream is dropped as soon as a stop sequence appears.
+pub fn stop_at(
+    text: impl Stream<Item = Result<String>> + Send + 'static,
+    stop: Vec<String>,
+) -> BoxStream<'static, Result<String>> {
+    if stop.is_empty() {
+        return text.boxed();
+    }
+    futures::stream::unfold(Some((text.boxed(), String::new())), move |state| {
+        let stop = stop.clone();
+        async move {
+            let (mut text, mut pending) = state?;
+            loop {
+                match text.next().await {
+                    Some(Ok(chunk)) => {
+                        pending.push_str(&chunk);
+                        if let Some(end) = stop.iter().filter_map(|s| pending.find(s.as_str())).min() {
+                            pending.truncate(end);
+                            return Some((Ok(pending), None));
+                        }
+                        let ready = pending.drain(..held_back(&pending, &stop)).collect::<String>();
+                        if !ready.is_empty() {
+                            return Some((Ok(ready), Some((text, pending))));
+                        }
+                    }
+                    Some(Err(e)) => return Some((Err(e), Some((text, pending)))),
+                    None if pending.is_empty() => return None,
+                    None => return Some((Ok(pending), None)),
+                }
+            }
+        }
+    })
+    .filter(|item| futures::future::ready(!matches!(item, Ok(text) if text.is_empty())))
+    // Callers may keep polling after the end (e.g. to send closing events)
+    .fuse()
+    .boxed()
+}
+
+/// Ends streamed text once it reaches `max_tokens`, as counted by `tokenizer`
+///
+/// The chunk that crosses the limit is cut to fit it. As with [`stop_at`],
+/// the underlying stream is dropped at that point.
+pub fn limit_tokens(
+    text: impl Stream<Item = Result<String>> + Send + 'static,
+    max_tokens: Option<usize>,
+    tokenizer: Arc<dyn Tokenizer>,
+) -> BoxStream<'static, Result<String>> {
+    let Some(max_tokens) = max_tokens else {
+        return text.boxed();
+    };
+    futures::stream::unfold(Some((text.boxed(), String::new())), move |state| {
+        let tokenizer = tokenizer.clone();
+        async move {
+            let (mut text, mut generated) = state?;
+            match text.next().await {
+                Some(Ok(chunk)) => {
+                    let before = generated.len();
+                    generated.push_str(&chunk);
+                    if tokenizer.count(&generated) < max_tokens {
+                        return Some((Ok(chunk), Some((text, generated))));
+                    }
+                    // The longest prefix of the chunk that stays within the limit,
+                    // up to the end of its last token
+                    let fits = chunk
+                        .char_indices()
+                        .map(|(i, c)| i + c.len_utf8())
+                        .take_while(|&end| tokenizer.count(&generated[..before + end]) <= max_tokens)
+                        .last()
+                        .unwrap_or(0);
+                    Some((Ok(chunk[..fits].trim_end().to_string()), None))
+                }
+                Some(Err(e)) => Some((Err(e), Some((text, generated)))),
+                None => None,
+            }
+        }
+    })
+    .filter(|item| futures::future::ready(!matches!(item, Ok(text) if text.is_empty())))
+    // Callers may keep polling after the end (e.g. to send closing events)
+    .fuse()
+    .boxed()
+}
+
+/// Byte offset where the longest tail of `text` that starts a stop sequence begins
+fn held_back(text: &str, stop: &[String]) -> usize {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| stop.iter().any(|s| s.starts_with(&text[i..])))
+        .unwrap_or(text.len())
+}
+
+/// A piece of a streamed completion
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionChunk {
+    /// Position of the chunk in the stream, from 0
+    pub index: usize,
+    /// Generated text; empty in the final chunk
+    pub text: String,
+    /// Time since the request started
+    pub elapsed: Duration,
+    /// Set on the final chunk only
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl std::fmt::Display for CompletionChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// What happened next in a streamed completion; see [`CompletionStream::into_events`]
+#[derive(Debug, Clone)]
+pub enum CompletionEvent {
+    /// Generated text, in order
+    Token(String),
+    /// The completion finished; always the last event of one that didn't fail
+    Done {
+        finish_reason: FinishReason,
+        usage: Usage,
+        /// Time from the request to the end of generation
+        duration: Duration,
+    },
+    /// The completion failed (e.g. with a [`LitError`](crate::LitError),
+    /// found with [`LitError::of`](crate::LitError::of)); always the last event
+    Error(Arc<anyhow::Error>),
+}
+
+/// Chunks of one completion, ending with a chunk that has a finish reason
+pub struct CompletionStream {
+    id: String,
+    model: String,
+    created: u64,
+    started: Instant,
+    inner: BoxStream<'static, Result<String>>,
+    index: usize,
+    finished: bool,
+    // How tokens are counted, for `max_tokens` and `usage`
+    tokenizer: Arc<dyn Tokenizer>,
+    max_tokens: Option<usize>,
+    prompt_tokens: usize,
+    generated: String,
+}
+
+impl std::fmt::Debug for CompletionStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionStream")
+            .field("id", &self.id)
+            .field("model", &self.model)
+            .field("index", &self.index)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+impl CompletionStream {
+    /// Wrap a stream of generated text that started now
+    pub fn new(model: impl Into<String>, text: impl Stream<Item = Result<String>> + Send + 'static) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
+            model: model.into(),
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            started: Instant::now(),
+            inner: text.boxed(),
+            index: 0,
+            finished: false,
+            tokenizer: Arc::new(Estimate),
+            max_tokens: None,
+            prompt_tokens: 0,
+            generated: String::new(),
+        }
+    }
+
+    /// Count tokens with `tokenizer` instead of estimating them
+    pub fn counted_by(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Finish with [`FinishReason::Length`] when the text reaches
+    /// `max_tokens` (the text itself is cut with [`limit_tokens`])
+    pub fn max_tokens(mut self, max_tokens: Option<usize>) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Tokens in the prompt, for [`usage`](Self::usage)
+    pub fn prompt_tokens(mut self, prompt_tokens: usize) -> Self {
+        self.prompt_tokens = prompt_tokens;
+        self
+    }
+
+    /// Tokens of the prompt and of the text streamed so far
+    pub fn usage(&self) -> Usage {
+        Usage::new(self.prompt_tokens, self.tokenizer.count(&self.generated))
+    }
+
+    /// Time chunks from `started` (e.g. when the request was made) instead of now
+    pub fn started_at(mut self, started: Instant) -> Self {
+        self.started = started;
+        self
+    }
+
+    /// Completion id, as used in chat completion chunks
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Unix timestamp (seconds) of the request
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    /// The completion as [`CompletionEvent`]s: a `Token` per chunk, then
+    /// `Done` or `Error`
+    pub fn into_events(self) -> BoxStream<'static, CompletionEvent> {
+        futures::stream::unfold(Some(self), |stream| async move {
+            let mut stream = stream?;
+            let event = match stream.next().await? {
+                Ok(CompletionChunk { finish_reason: None, text, .. }) => {
+                    return Some((CompletionEvent::Token(text), Some(stream)));
+                }
+                Ok(CompletionChunk { finish_reason: Some(finish_reason), elapsed, .. }) => CompletionEvent::Done {
+                    finish_reason,
+                    usage: stream.usage(),
+                    duration: elapsed,
+                },
+                Err(e) => CompletionEvent::Error(Arc::new(e)),
+            };
+            Some((event, None))
+        })
+        .boxed()
+    }
+
+    /// Wait for the whole completion
+    pub async fn text(mut self) -> Result<String> {
+        let mut text = String::new();
+        while let Some(chunk) = self.next().await {
+            text.push_str(&chunk?.text);
+        }
+        Ok(text)
+    }
+
+    /// The completion as OpenAI chat completion chunks, as `/v1/chat/completions`
+    /// streams them; the last one carries the latency stats in `x_litert`
+    pub fn into_chat_chunks(self) -> impl Stream<Item = Result<ChatCompletionChunk>> + Send {
+        let (id, model, created) = (self.id.clone(), self.model.clone(), self.created);
+        let mut generated = String::new();
+        let mut first_token_at = None;
+        let tokenizer = self.tokenizer.clone();
+
+        self.map(move |chunk| {
+            let chunk = chunk?;
+            let (delta, x_litert) = match chunk.finish_reason {
+                None => {
+                    let role = first_token_at.is_none().then(|| "assistant".to_string());
+                    first_token_at.get_or_insert(chunk.elapsed);
+                    generated.push_str(&chunk.text);
+                    (
+                        Delta {
+                            role,
+                            content: Some(chunk.text),
+                            ..Default::default()
+                        },
+                        None,
+                    )
+                }
+                Some(_) => {
+                    let timing = GenerationTiming {
+                        time_to_first_token: first_token_at,
+                        total: chunk.elapsed,
+                    };
+                    let delta = Delta::default();
+                    (delta, Some(LitertExtension::from_timing(&timing, &generated, &*tokenizer)))
+                }
+            };
+
+            Ok(ChatCompletionChunk {
+                id: id.clone(),
+                object: "chat.completion.chunk".to_string(),
+                created,
+                model: model.clone(),
+                choices: vec![ChoiceChunk {
+                    index: 0,
+                    delta,
+                    finish_reason: chunk.finish_reason.map(|reason| reason.as_str().to_string()),
+                }],
+                usage: None,
+                x_litert,
+            })
+        })
+    }
+
+    /// Stop reading the generation, dropping it (and the pool slot it holds)
+    /// right away rather than with the stream
+    fn finish(&mut self) {
+        self.finished = true;
+        self.inner = futures::stream::empty().boxed();
+    }
+}
+
+impl Stream for CompletionStream {
+    type Item = Result<CompletionChunk>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.finished {
+            return Poll::Ready(None);
+        }
+        let (text, finish_reason) = match self.inner.poll_next_unpin(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Some(Err(e))) => {
+                // A failed completion has no finish reason
+                self.finish();
+                return Poll::Ready(Some(Err(e)));
+            }
+            Poll::Ready(Some(Ok(text))) => {
+                self.generated.push_str(&text);
+                (text, None)
+            }
+            Poll::Ready(None) => {
+                self.finish();
+                let reason = match self.max_tokens {
+                    Some(max_tokens) if self.tokenizer.count(&self.generated) >= max_tokens => FinishReason::Length,
+                    _ => FinishReason::Stop,
+                };
+                (String::new(), Some(reason))
+            }
+        };
+
+        let chunk = CompletionChunk {
+            index: self.index,
+            text,
+            elapsed: self.started.elapsed(),
+            finish_reason,
+        };
+        self.index += 1;
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+/// litert-specific metadata attached to responses and final stream chunks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LitertExtension {
+    /// Time from submitting the prompt to the first generated text
+    pub ttft_ms: Option<u64>,
+    /// Time from submitting the prompt to the end of generation
+    pub generation_ms: u64,
+    /// Number of generated tokens
+    pub completion_tokens: u64,
+    /// Decode throughput after the first token
+    pub tokens_per_second: f64,
+    /// Served from the prompt cache without generating
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+}
+
+impl LitertExtension {
+    /// Stats of a generation of `generated`, counted with `tokenizer`
+    pub fn from_timing(timing: &GenerationTiming, generated: &str, tokenizer: &dyn Tokenizer) -> Self {
+        let completion_tokens = tokenizer.count(generated) as u64;
+        let decode_time = timing.total - timing.time_to_first_token.unwrap_or_default();
+        let tokens_per_second = if decode_time.as_secs_f64() > 0.0 {
+            completion_tokens as f64 / decode_time.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        Self {
+            ttft_ms: timing.time_to_first_token.map(|d| d.as_millis() as u64),
+            generation_ms: timing.total.as_millis() as u64,
+            completion_tokens,
+            tokens_per_second,
+            cached: false,
+        }
+    }
+}
+
+/// Per-request generation stats, sent as an `x_litert.stats` event at the end
+/// of a stream when the request sets `x_litert.stats`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamStats {
+    /// Completion id of the stream
+    pub id: String,
+    /// Time spent waiting for a slot in the pool; unknown for completions
+    /// generated up front (constraints, best-of)
+    pub queue_ms: Option<u64>,
+    /// Time from the request to the first generated text
+    pub ttft_ms: Option<u64>,
+    /// Time from the request to the end of generation
+    pub generation_ms: u64,
+    /// Number of prompt tokens
+    pub prompt_tokens: u64,
+    /// Number of generated tokens
+    pub completion_tokens: u64,
+    /// Backend of the process that generated the text (`gpu` or `cpu`)
+    pub backend: Option<String>,
+    /// OS process id of that lit process
+    pub pid: Option<u32>,
+}
+
+/// One server-sent event of a streamed chat completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChoiceChunk>,
+    /// Only on the last chunk (with no choices), when the request set
+    /// `stream_options.include_usage`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_litert: Option<LitertExtension>,
+}
+
+/// Tokens a completion took; see [`crate::tokenizer`] for how they are counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+impl Usage {
+    pub fn new(prompt_tokens: usize, completion_tokens: usize) -> Self {
+        let (prompt_tokens, completion_tokens) = (prompt_tokens as u32, completion_tokens as u32);
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChoiceChunk {
+    pub index: u32,
+    pub delta: Delta,
+    pub finish_reason: Option<String>,
+}
+
+/// Text added by a chunk; the first chunk also names the role
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Delta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+}
+
+/// A tool call as OpenAI sends it, in a message or a streamed delta
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatToolCall {
+    /// Position among the message's calls; only in streamed deltas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+/// The function a [`ChatToolCall`] invokes, with its arguments as a JSON string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+impl ChatToolCall {
+    pub fn new(call: &ToolCall) -> Self {
+        Self {
+            index: None,
+            id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+            kind: "function".to_string(),
+            function: FunctionCall {
+                name: call.name.clone(),
+                arguments: call.arguments.to_string(),
+            },
+        }
+    }
+}
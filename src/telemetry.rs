@@ -0,0 +1,253 @@
+//! Tracing setup and optional OpenTelemetry export
+//!
+//! With the `otel` feature enabled, spans and metrics are exported over OTLP in
+//! addition to the normal log output. The exporter is configured through the
+//! standard `OTEL_*` environment variables (`OTEL_EXPORTER_OTLP_ENDPOINT`,
+//! `OTEL_SERVICE_NAME`, `OTEL_RESOURCE_ATTRIBUTES`, ...) and can be turned off at
+//! runtime with `OTEL_SDK_DISABLED=true`. Incoming W3C `traceparent` headers
+//! are honored so traces started by callers continue into the inference path.
+//!
+//! Without the feature, the recording helpers in this module are no-ops.
+
+use anyhow::Context;
+
+use crate::binary::default_cache_dir;
+use crate::config::LogConfig;
+use crate::log_file::RotatingFile;
+use crate::Result;
+
+/// Keeps the OpenTelemetry providers alive; flushes pending data on drop
+#[derive(Default)]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+    #[cfg(feature = "otel")]
+    meter_provider: Option<opentelemetry_sdk::metrics::SdkMeterProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        {
+            if let Some(provider) = self.tracer_provider.take() {
+                if let Err(e) = provider.shutdown() {
+                    eprintln!("Failed to flush OpenTelemetry traces: {}", e);
+                }
+            }
+            if let Some(provider) = self.meter_provider.take() {
+                if let Err(e) = provider.shutdown() {
+                    eprintln!("Failed to flush OpenTelemetry metrics: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Install the global tracing subscriber
+///
+/// Log output goes to stderr when `use_stderr` is set (needed for MCP stdio),
+/// otherwise to stdout. Keep the returned guard alive for the lifetime of the
+/// program so exported telemetry gets flushed on exit.
+pub fn init(use_stderr: bool) -> Result<TelemetryGuard> {
+    init_with_log(use_stderr, &LogConfig::default())
+}
+
+/// Like [`init`], also writing rotating log files when `log.file` is set
+pub fn init_with_log(use_stderr: bool, log: &LogConfig) -> Result<TelemetryGuard> {
+    use tracing_subscriber::filter::LevelFilter;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::Layer;
+
+    let fmt_layer = if use_stderr {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+    let fmt_layer = fmt_layer.with_filter(LevelFilter::INFO);
+
+    let file_layer = if log.file {
+        let level: LevelFilter = log
+            .level
+            .parse()
+            .with_context(|| format!("Invalid log level '{}'", log.level))?;
+        let dir = match &log.dir {
+            Some(dir) => dir.clone(),
+            None => default_cache_dir()?.join("logs"),
+        };
+        let file = RotatingFile::open(&dir, log.max_bytes, log.max_files)?;
+        Some(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(std::sync::Mutex::new(file))
+                .with_filter(level),
+        )
+    } else {
+        None
+    };
+    let fmt_layer = fmt_layer.and_then(file_layer);
+
+    #[cfg(feature = "otel")]
+    {
+        if otel_enabled() {
+            let (tracer_provider, meter_provider) = otel::providers()?;
+            let tracer = opentelemetry::trace::TracerProvider::tracer(&tracer_provider, "litert-lm");
+
+            tracing_subscriber::registry()
+                .with(fmt_layer)
+                .with(
+                    tracing_opentelemetry::layer()
+                        .with_tracer(tracer)
+                        .with_filter(LevelFilter::INFO),
+                )
+                .try_init()?;
+
+            tracing::info!("OpenTelemetry export enabled");
+            return Ok(TelemetryGuard {
+                tracer_provider: Some(tracer_provider),
+                meter_provider: Some(meter_provider),
+            });
+        }
+    }
+
+    tracing_subscriber::registry().with(fmt_layer).try_init()?;
+    Ok(TelemetryGuard::default())
+}
+
+#[cfg(feature = "otel")]
+fn otel_enabled() -> bool {
+    !std::env::var("OTEL_SDK_DISABLED")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Continue a caller's trace: parent `span` on the `traceparent` in `headers`
+///
+/// No-op without the `otel` feature or when the header is missing.
+#[cfg(feature = "server")]
+pub fn set_remote_parent(span: &tracing::Span, headers: &axum::http::HeaderMap) {
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::propagation::TextMapPropagator;
+        use opentelemetry_sdk::propagation::TraceContextPropagator;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        if !headers.contains_key("traceparent") {
+            return;
+        }
+        // The header is W3C trace context whichever propagator is installed
+        let cx = TraceContextPropagator::new().extract(&otel::HeaderExtractor(headers));
+        if let Err(e) = span.set_parent(cx) {
+            tracing::debug!(error = ?e, "Failed to attach remote trace context");
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, headers);
+}
+
+/// Count generated tokens for a model
+pub fn record_tokens(model: &str, tokens: u64) {
+    #[cfg(feature = "otel")]
+    otel::instruments()
+        .tokens
+        .add(tokens, &[opentelemetry::KeyValue::new("model", model.to_string())]);
+    #[cfg(not(feature = "otel"))]
+    let _ = (model, tokens);
+}
+
+/// Count a failed operation, labeled by model and a short error kind
+pub fn record_error(model: &str, kind: &'static str) {
+    #[cfg(feature = "otel")]
+    otel::instruments().errors.add(
+        1,
+        &[
+            opentelemetry::KeyValue::new("model", model.to_string()),
+            opentelemetry::KeyValue::new("kind", kind),
+        ],
+    );
+    #[cfg(not(feature = "otel"))]
+    let _ = (model, kind);
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::metrics::Counter;
+    #[cfg(feature = "server")]
+    use opentelemetry::propagation::Extractor;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use std::sync::OnceLock;
+
+    pub(super) struct Instruments {
+        pub tokens: Counter<u64>,
+        pub errors: Counter<u64>,
+    }
+
+    pub(super) fn instruments() -> &'static Instruments {
+        static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+        INSTRUMENTS.get_or_init(|| {
+            let meter = opentelemetry::global::meter("litert-lm");
+            Instruments {
+                tokens: meter
+                    .u64_counter("litert.tokens.generated")
+                    .with_description("Completion tokens generated")
+                    .build(),
+                errors: meter
+                    .u64_counter("litert.errors")
+                    .with_description("Failed completions and process errors")
+                    .build(),
+            }
+        })
+    }
+
+    #[cfg(feature = "server")]
+    pub(super) struct HeaderExtractor<'a>(pub &'a axum::http::HeaderMap);
+
+    #[cfg(feature = "server")]
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|v| v.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|k| k.as_str()).collect()
+        }
+    }
+
+    fn resource() -> Resource {
+        // OTEL_SERVICE_NAME wins when set; otherwise report as litert-lm
+        if std::env::var_os("OTEL_SERVICE_NAME").is_some() {
+            Resource::builder().build()
+        } else {
+            Resource::builder().with_service_name("litert-lm").build()
+        }
+    }
+
+    pub(super) fn providers() -> crate::Result<(SdkTracerProvider, SdkMeterProvider)> {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let span_exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()?;
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource())
+            .build();
+        opentelemetry::global::set_tracer_provider(tracer_provider.clone());
+
+        let metric_exporter = opentelemetry_otlp::MetricExporter::builder()
+            .with_http()
+            .build()?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource())
+            .build();
+        opentelemetry::global::set_meter_provider(meter_provider.clone());
+
+        Ok((tracer_provider, meter_provider))
+    }
+}
@@ -0,0 +1,160 @@
+//! Chat templates
+//!
+//! Models are trained on conversations in their own format: Gemma expects
+//! `<start_of_turn>user` ... `<end_of_turn>` turns and answers after
+//! `<start_of_turn>model`, and given a bare `user: ...` transcript it tends to
+//! ramble. Prompts are rendered in the model's template, picked by name from
+//! the `templates` config section, then the built-in table, with the plain
+//! transcript as the fallback.
+//!
+//! lit reads a prompt per line, so the line breaks of the reference templates
+//! are left out; the control tokens delimit turns on their own. Line breaks
+//! within messages are folded into spaces for the same reason.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::context::{self, Turn};
+use crate::tenant::matches_pattern;
+
+/// Templates of models in the lit registry, by name pattern
+const KNOWN_TEMPLATES: &[(&str, ChatTemplate)] = &[
+    ("gemma*", ChatTemplate::Gemma),
+    ("qwen2.5-*", ChatTemplate::ChatMl),
+    ("phi-4-mini*", ChatTemplate::Phi),
+];
+
+/// How a conversation is laid out in a prompt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatTemplate {
+    /// `role: content` lines
+    #[default]
+    Plain,
+    /// `<start_of_turn>user ...<end_of_turn>`, with system messages folded
+    /// into the next user turn (Gemma only has user and model turns)
+    Gemma,
+    /// `<|im_start|>user ...<|im_end|>` (Qwen)
+    ChatMl,
+    /// `<|user|>...<|end|>` (Phi)
+    Phi,
+}
+
+impl ChatTemplate {
+    /// Template for `model`: the config's most specific matching pattern, then
+    /// the built-in table, then [`ChatTemplate::Plain`]
+    pub fn for_model(config: &HashMap<String, ChatTemplate>, model: &str) -> Self {
+        let configured = config
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, &template)| template);
+        let known = KNOWN_TEMPLATES
+            .iter()
+            .find(|(pattern, _)| matches_pattern(pattern, model))
+            .map(|&(_, template)| template);
+        configured.or(known).unwrap_or_default()
+    }
+
+    /// Render `turns` as a prompt the model answers as the assistant, each
+    /// turn on one line
+    pub fn render(&self, turns: &[Turn]) -> String {
+        let turns: Vec<Turn> = turns.iter().map(|turn| Turn::new(turn.role.clone(), one_line(&turn.content))).collect();
+        let turns = turns.as_slice();
+        match self {
+            ChatTemplate::Plain => context::render(turns),
+            ChatTemplate::Gemma => {
+                let mut prompt = String::new();
+                let mut system = Vec::new();
+                for turn in turns {
+                    match turn.role.as_str() {
+                        "system" | "developer" => system.push(turn.content.as_str()),
+                        role => {
+                            // Tool results are read like user messages
+                            let role = if role == "assistant" { "model" } else { "user" };
+                            let content = match system.is_empty() {
+                                true => turn.content.clone(),
+                                false => format!("{} {}", system.join(" "), turn.content),
+                            };
+                            system.clear();
+                            prompt.push_str(&format!("<start_of_turn>{} {}<end_of_turn>", role, content));
+                        }
+                    }
+                }
+                // System messages with no turn after them
+                if !system.is_empty() {
+                    prompt.push_str(&format!("<start_of_turn>user {}<end_of_turn>", system.join(" ")));
+                }
+                prompt + self.reply_start()
+            }
+            ChatTemplate::ChatMl => {
+                let mut prompt: String = turns
+                    .iter()
+                    .map(|turn| format!("<|im_start|>{} {}<|im_end|>", turn.role, turn.content))
+                    .collect();
+                prompt.push_str(self.reply_start());
+                prompt
+            }
+            ChatTemplate::Phi => {
+                let mut prompt: String = turns
+                    .iter()
+                    .map(|turn| format!("<|{}|>{}<|end|>", turn.role, turn.content))
+                    .collect();
+                prompt.push_str(self.reply_start());
+                prompt
+            }
+        }
+    }
+
+    /// `prompt`, as rendered in this template, with `turns` added to its
+    /// conversation; the model answers after the last of them
+    pub fn continue_with(&self, prompt: &str, turns: &[Turn]) -> String {
+        match self {
+            ChatTemplate::Plain => format!("{}\n{}", prompt, self.render(turns)),
+            _ => {
+                let prompt = prompt.strip_suffix(self.reply_start()).unwrap_or(prompt);
+                format!("{}{}", prompt, self.render(turns))
+            }
+        }
+    }
+
+    // What a prompt ends with for the model to answer as the assistant
+    fn reply_start(&self) -> &'static str {
+        match self {
+            ChatTemplate::Plain => "",
+            ChatTemplate::Gemma => "<start_of_turn>model",
+            ChatTemplate::ChatMl => "<|im_start|>assistant",
+            ChatTemplate::Phi => "<|assistant|>",
+        }
+    }
+
+    /// Render a single prompt, after an optional system prompt
+    ///
+    /// A plain prompt is sent as written, so callers can keep passing their
+    /// own transcripts to models without a template.
+    pub fn render_prompt(&self, system: Option<&str>, prompt: &str) -> String {
+        let system = system.map(|system| Turn::new("system", system));
+        match self {
+            ChatTemplate::Plain => match system {
+                Some(system) => format!("{}\n{}", self.render(&[system]), prompt),
+                None => prompt.to_string(),
+            },
+            _ => {
+                let turns: Vec<Turn> = system.into_iter().chain([Turn::new("user", prompt)]).collect();
+                self.render(&turns)
+            }
+        }
+    }
+}
+
+/// `text` with its line breaks folded into spaces, leaving out blank lines
+fn one_line(text: &str) -> String {
+    if !text.contains(['\n', '\r']) {
+        return text.to_string();
+    }
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
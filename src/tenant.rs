@@ -0,0 +1,172 @@
+//! Tenant-scoped model namespaces
+//!
+//! Tenants are declared in the config file. Each has API keys and the model
+//! names it may see and run; a [`LitManager`](crate::LitManager) scoped to a
+//! tenant with [`for_tenant`](crate::LitManager::for_tenant) refuses anything
+//! else. Plain API keys (`api_keys`, `$LITERT_API_KEYS`, `serve --api-key`)
+//! act as the unrestricted tenant. Without tenants or keys every caller is
+//! unrestricted.
+
+use std::collections::HashMap;
+
+use crate::config::TenantConfig;
+
+/// The caller a manager acts for and what it may do
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tenant {
+    name: String,
+    /// Allowed model patterns; `None` allows every model
+    models: Option<Vec<String>>,
+    manage_models: bool,
+    /// Share of a busy pool relative to other tenants
+    weight: u32,
+}
+
+impl Default for Tenant {
+    fn default() -> Self {
+        Self::unrestricted()
+    }
+}
+
+impl Tenant {
+    /// A tenant that may see, run, pull, and remove every model
+    pub fn unrestricted() -> Self {
+        Self {
+            name: "default".to_string(),
+            models: None,
+            manage_models: true,
+            weight: 1,
+        }
+    }
+
+    pub fn from_config(config: &TenantConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            models: Some(config.models.clone()),
+            manage_models: config.manage_models,
+            weight: config.weight.max(1),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Fair-share weight when pools are saturated
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Whether the tenant may see and run `model`
+    pub fn can_use(&self, model: &str) -> bool {
+        match &self.models {
+            None => true,
+            Some(patterns) => patterns.iter().any(|pattern| matches_pattern(pattern, model)),
+        }
+    }
+
+    /// Whether the tenant may pull and remove `model`
+    pub fn can_manage(&self, model: &str) -> bool {
+        self.manage_models && self.can_use(model)
+    }
+
+    pub fn check_use(&self, model: &str) -> Result<(), AccessDenied> {
+        self.check(self.can_use(model), model, "use")
+    }
+
+    pub fn check_manage(&self, model: &str) -> Result<(), AccessDenied> {
+        self.check(self.can_manage(model), model, "manage")
+    }
+
+    fn check(&self, allowed: bool, model: &str, action: &'static str) -> Result<(), AccessDenied> {
+        if allowed {
+            return Ok(());
+        }
+        Err(AccessDenied {
+            tenant: self.name.clone(),
+            model: model.to_string(),
+            action,
+        })
+    }
+}
+
+/// `*` matches any run of characters, e.g. `gemma-*`
+pub(crate) fn matches_pattern(pattern: &str, model: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == model,
+        Some((prefix, rest)) => {
+            let Some(remaining) = model.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=remaining.len())
+                .filter(|&i| remaining.is_char_boundary(i))
+                .any(|i| matches_pattern(rest, &remaining[i..]))
+        }
+    }
+}
+
+/// A tenant tried to touch a model outside its namespace
+#[derive(Debug, Clone)]
+pub struct AccessDenied {
+    pub tenant: String,
+    pub model: String,
+    /// `use` or `manage`
+    pub action: &'static str,
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tenant '{}' may not {} model '{}'", self.tenant, self.action, self.model)
+    }
+}
+
+impl std::error::Error for AccessDenied {}
+
+/// Every configured tenant, looked up by API key or name
+#[derive(Debug, Clone, Default)]
+pub struct Tenants {
+    by_key: HashMap<String, Tenant>,
+    by_name: HashMap<String, Tenant>,
+}
+
+impl Tenants {
+    pub fn from_config(configs: &[TenantConfig]) -> Self {
+        let mut tenants = Self::default();
+        for config in configs {
+            let tenant = Tenant::from_config(config);
+            for key in &config.api_keys {
+                tenants.by_key.insert(key.clone(), tenant.clone());
+            }
+            tenants.by_name.insert(config.name.clone(), tenant);
+        }
+        tenants
+    }
+
+    /// These tenants, with `keys` acting as the unrestricted tenant
+    pub fn with_api_keys(&self, keys: &[String]) -> Self {
+        let mut tenants = self.clone();
+        for key in keys {
+            tenants.by_key.insert(key.clone(), Tenant::unrestricted());
+        }
+        tenants
+    }
+
+    /// Whether any tenants or API keys are configured (and API keys therefore
+    /// required)
+    pub fn is_enabled(&self) -> bool {
+        !self.by_name.is_empty() || !self.by_key.is_empty()
+    }
+
+    /// Tenant for an API key; unrestricted when no tenants are configured, and
+    /// `None` for a missing or unknown key otherwise
+    pub fn for_key(&self, key: Option<&str>) -> Option<Tenant> {
+        if !self.is_enabled() {
+            return Some(Tenant::unrestricted());
+        }
+        self.by_key.get(key?).cloned()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<Tenant> {
+        self.by_name.get(name).cloned()
+    }
+}
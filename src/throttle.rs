@@ -0,0 +1,66 @@
+//! Server-wide limit on generated tokens per second
+//!
+//! Sustained generation heats up phones and single-board computers and drains
+//! their batteries. A [`TokenBucket`] shared by every process paces the text
+//! they hand out: once the burst allowance is spent, readers wait for the
+//! bucket to refill. A slowed reader stops draining lit's output, so the next
+//! prompt on that process starts later, and throttled requests slow down
+//! instead of failing.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Most tokens that can accumulate while idle
+    burst: f64,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Negative while callers are waiting to use tokens already taken
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Allow `rate` tokens per second on average and bursts of up to `burst`
+    pub fn new(rate: f64, burst: f64) -> Self {
+        let burst = burst.max(1.0);
+        Self {
+            rate: rate.max(f64::MIN_POSITIVE),
+            burst,
+            state: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take `tokens`, returning how long the caller must wait before using them
+    pub fn take(&self, tokens: u64) -> Duration {
+        let mut bucket = self.state.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * self.rate;
+        bucket.tokens = (bucket.tokens + refill).min(self.burst) - tokens as f64;
+        bucket.updated = now;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// Take `tokens`, sleeping until the bucket can cover them
+    pub async fn consume(&self, tokens: u64) {
+        let wait = self.take(tokens);
+        if !wait.is_zero() {
+            tracing::trace!(tokens, wait_ms = wait.as_millis() as u64, "Throttling generated tokens");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
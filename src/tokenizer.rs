@@ -0,0 +1,125 @@
+//! Token counts for the `usage` of completions
+//!
+//! lit doesn't say how many tokens a prompt or completion took, so they are
+//! counted here. Without more to go on, counts are estimated from the length
+//! of the text (about four characters per token). With the `tokenizers`
+//! feature, a model's Hugging Face `tokenizer.json`, set under `tokenizers` in
+//! the config, is run as-is and gives the model's exact counts.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::tenant::matches_pattern;
+
+/// Counts the tokens a model sees in text
+pub trait Tokenizer: Send + Sync + std::fmt::Debug {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// The length-based estimate, for models without a tokenizer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Estimate;
+
+impl Tokenizer for Estimate {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// A model's own tokenizer, loaded from its Hugging Face `tokenizer.json`
+#[cfg(feature = "tokenizers")]
+#[derive(Debug)]
+pub struct HfTokenizer {
+    tokenizer: tokenizers::Tokenizer,
+}
+
+#[cfg(feature = "tokenizers")]
+impl HfTokenizer {
+    pub fn load(path: &Path) -> Result<Self> {
+        let tokenizer = tokenizers::Tokenizer::from_file(path)
+            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer {}: {}", path.display(), e))?;
+        Ok(Self { tokenizer })
+    }
+
+    /// Read a `tokenizer.json` from its contents
+    pub fn parse(json: &str) -> Result<Self> {
+        let tokenizer: tokenizers::Tokenizer = json
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid tokenizer: {}", e))?;
+        Ok(Self { tokenizer })
+    }
+}
+
+#[cfg(feature = "tokenizers")]
+impl Tokenizer for HfTokenizer {
+    fn count(&self, text: &str) -> usize {
+        match self.tokenizer.encode_fast(text, false) {
+            Ok(encoding) => encoding.len(),
+            // Only fails on text the normalizer rejects; fall back rather than count nothing
+            Err(e) => {
+                tracing::debug!(error = %e, "Tokenizer failed, estimating the count");
+                Estimate.count(text)
+            }
+        }
+    }
+}
+
+/// Tokenizers by model name or pattern (e.g. `gemma-3n-*`)
+#[derive(Debug, Clone, Default)]
+pub struct Tokenizers {
+    tokenizers: HashMap<String, Arc<dyn Tokenizer>>,
+}
+
+impl Tokenizers {
+    /// Load the `tokenizer.json` files named in `config`
+    pub fn from_config(config: &HashMap<String, PathBuf>) -> Result<Self> {
+        let mut tokenizers = HashMap::new();
+        for (pattern, path) in config {
+            if let Some(tokenizer) = load(pattern, path)? {
+                tokenizers.insert(pattern.clone(), tokenizer);
+            }
+        }
+        Ok(Self { tokenizers })
+    }
+
+    /// These tokenizers with `tokenizer` for models matching `pattern`
+    pub fn with(&self, pattern: &str, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        let mut tokenizers = self.tokenizers.clone();
+        tokenizers.insert(pattern.to_string(), tokenizer);
+        Self { tokenizers }
+    }
+
+    /// Tokenizer of `model`: the most specific matching pattern's, else the estimate
+    pub fn for_model(&self, model: &str) -> Arc<dyn Tokenizer> {
+        self.tokenizers
+            .iter()
+            .filter(|(pattern, _)| matches_pattern(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, tokenizer)| tokenizer.clone())
+            .unwrap_or_else(|| Arc::new(Estimate))
+    }
+
+    /// Tokens `model` sees in `text`
+    pub fn count(&self, model: &str, text: &str) -> usize {
+        self.for_model(model).count(text)
+    }
+}
+
+#[cfg(feature = "tokenizers")]
+fn load(pattern: &str, path: &Path) -> Result<Option<Arc<dyn Tokenizer>>> {
+    let tokenizer = HfTokenizer::load(path)?;
+    tracing::debug!(pattern = %pattern, tokens = tokenizer.tokenizer.get_vocab_size(true), "Loaded tokenizer");
+    Ok(Some(Arc::new(tokenizer)))
+}
+
+#[cfg(not(feature = "tokenizers"))]
+fn load(pattern: &str, path: &Path) -> Result<Option<Arc<dyn Tokenizer>>> {
+    tracing::warn!(
+        pattern = %pattern,
+        path = %path.display(),
+        "tokenizers needs the tokenizers feature, estimating token counts"
+    );
+    Ok(None)
+}
@@ -0,0 +1,159 @@
+//! Model variants picked by device capability
+//!
+//! A model family such as `gemma-3n` comes in several sizes. Naming the family
+//! instead of a variant lets the same provisioning script run on a laptop and a
+//! single-board computer: the largest variant the device has the memory (and,
+//! if required, the GPU) for is pulled and loaded. `$LITERT_VARIANT` names a
+//! variant to use instead.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::config::VariantConfig;
+
+/// (model, minimum RAM in GB, requires a GPU)
+type KnownVariant = (&'static str, f64, bool);
+
+/// Variants of model families in the lit registry, largest first
+const KNOWN_FAMILIES: &[(&str, &[KnownVariant])] = &[(
+    "gemma-3n",
+    &[("gemma-3n-E4B", 8.0, false), ("gemma-3n-E2B", 0.0, false)],
+)];
+
+const GIB: f64 = (1u64 << 30) as f64;
+
+/// What the device running litert-lm has to offer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Physical memory, if it could be determined
+    pub memory_bytes: Option<u64>,
+    /// Whether a GPU lit can use appears to be present
+    pub gpu: bool,
+}
+
+impl DeviceInfo {
+    /// Probe this device once; later calls return the same result
+    pub fn current() -> &'static DeviceInfo {
+        static DEVICE: OnceLock<DeviceInfo> = OnceLock::new();
+        DEVICE.get_or_init(|| {
+            let device = DeviceInfo {
+                memory_bytes: probe_memory(),
+                gpu: probe_gpu(),
+            };
+            tracing::debug!(memory_gb = ?device.memory_gb(), gpu = device.gpu, "Probed device");
+            device
+        })
+    }
+
+    pub fn memory_gb(&self) -> Option<f64> {
+        self.memory_bytes.map(|bytes| bytes as f64 / GIB)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn probe_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn probe_memory() -> Option<u64> {
+    let output = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn probe_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn probe_gpu() -> bool {
+    // DRM render nodes, NVIDIA, and the Adreno and Mali drivers found on phones and boards
+    ["/dev/dri/renderD128", "/dev/nvidia0", "/dev/kgsl-3d0", "/dev/mali0"]
+        .iter()
+        .any(|path| std::path::Path::new(path).exists())
+}
+
+#[cfg(target_os = "macos")]
+fn probe_gpu() -> bool {
+    true
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn probe_gpu() -> bool {
+    false
+}
+
+/// One size of a model family
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub model: String,
+    /// Device memory the variant needs, in GB
+    pub min_memory_gb: f64,
+    pub requires_gpu: bool,
+}
+
+impl Variant {
+    fn fits(&self, device: &DeviceInfo) -> bool {
+        let memory = device.memory_gb().is_some_and(|gb| gb >= self.min_memory_gb);
+        (memory || self.min_memory_gb <= 0.0) && (device.gpu || !self.requires_gpu)
+    }
+}
+
+/// Model families and their variants, largest first
+#[derive(Debug, Clone, Default)]
+pub struct Variants {
+    families: HashMap<String, Vec<Variant>>,
+}
+
+impl Variants {
+    /// Built-in families, replaced or extended by the config file's
+    pub fn from_config(config: &HashMap<String, Vec<VariantConfig>>) -> Self {
+        let mut families: HashMap<String, Vec<Variant>> = KNOWN_FAMILIES
+            .iter()
+            .map(|(family, variants)| {
+                let variants = variants
+                    .iter()
+                    .map(|&(model, min_memory_gb, requires_gpu)| Variant {
+                        model: model.to_string(),
+                        min_memory_gb,
+                        requires_gpu,
+                    })
+                    .collect();
+                (family.to_string(), variants)
+            })
+            .collect();
+        for (family, variants) in config {
+            let variants = variants
+                .iter()
+                .map(|variant| Variant {
+                    model: variant.model.clone(),
+                    min_memory_gb: variant.min_memory_gb,
+                    requires_gpu: variant.requires_gpu,
+                })
+                .collect();
+            families.insert(family.clone(), variants);
+        }
+        families.retain(|_, variants: &mut Vec<Variant>| !variants.is_empty());
+        Self { families }
+    }
+
+    /// Variants of `family`, largest first
+    pub fn family(&self, family: &str) -> Option<&[Variant]> {
+        self.families.get(family).map(Vec::as_slice)
+    }
+
+    /// The largest variant of `family` that `device` can run, or the smallest
+    /// if it can run none
+    pub fn select(&self, family: &str, device: &DeviceInfo) -> Option<&Variant> {
+        let variants = self.family(family)?;
+        variants
+            .iter()
+            .find(|variant| variant.fits(device))
+            .or_else(|| variants.last())
+    }
+}
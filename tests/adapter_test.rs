@@ -0,0 +1,51 @@
+//! Tests for LoRA adapter storage (no lit binary required)
+
+mod common;
+
+use litert_lm::adapter::{self, AdapterStore};
+use litert_lm::LitManager;
+
+#[test]
+fn adapters_are_stored_per_base_model() -> anyhow::Result<()> {
+    let dir = common::temp_dir()?;
+    let source = dir.join("support-v2.bin");
+    std::fs::write(&source, b"lora weights")?;
+    let store = AdapterStore::new(dir.join("adapters"));
+
+    let added = store.add("gemma-3n-E4B", "support", &source)?;
+    assert_eq!(added.model(), "gemma-3n-E4B:support");
+    assert_eq!(added.path, dir.join("adapters/gemma-3n-E4B/support.bin"));
+    assert_eq!(std::fs::read(&added.path)?, b"lora weights");
+
+    store.add("gemma-3n-E2B", "support", &source)?;
+    let models: Vec<String> = store.list().iter().map(|a| a.model()).collect();
+    assert_eq!(models, ["gemma-3n-E2B:support", "gemma-3n-E4B:support"]);
+    assert_eq!(store.get("gemma-3n-E4B:support"), Some(added));
+    assert_eq!(store.get("gemma-3n-E4B"), None);
+
+    // Names that would escape the store are rejected
+    assert!(store.add("../gemma", "support", &source).is_err());
+    assert!(store.get("..:support").is_none());
+    assert_eq!(adapter::split_model("a:b:c"), None);
+
+    assert!(store.remove("gemma-3n-E4B:support")?);
+    assert!(!store.remove("gemma-3n-E4B:support")?);
+    assert!(!dir.join("adapters/gemma-3n-E4B").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn manager_lists_and_removes_adapters() -> anyhow::Result<()> {
+    let dir = common::cache_dir()?;
+    let manager = LitManager::new().await?;
+
+    let source = dir.join("tuned.safetensors");
+    std::fs::write(&source, b"lora weights")?;
+    manager.add_adapter("gemma-3n-E4B", "tuned", &source).await?;
+    let models: Vec<String> = manager.adapters().iter().map(|a| a.model()).collect();
+    assert_eq!(models, ["gemma-3n-E4B:tuned"]);
+
+    manager.remove_quiet("gemma-3n-E4B:tuned").await?;
+    assert!(manager.adapters().is_empty());
+    Ok(())
+}
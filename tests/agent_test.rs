@@ -0,0 +1,101 @@
+//! Tests for the tool-execution loop (no lit binary required)
+
+use litert_lm::agent::{parse_tool_call, run_agent, AgentOptions, ToolCall, ToolDefinition, ToolRegistry};
+use litert_lm::{ChatTemplate, Turn};
+use serde_json::json;
+
+fn weather_tools() -> ToolRegistry {
+    let tools = ToolRegistry::new();
+    tools.register_fn(
+        ToolDefinition {
+            name: "weather".to_string(),
+            description: "Current weather for a city".to_string(),
+            parameters: json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+        },
+        |args| async move { Ok(format!("Sunny in {}", args["city"].as_str().unwrap_or("?"))) },
+    );
+    tools
+}
+
+#[tokio::test]
+async fn agent_executes_tools_until_a_plain_answer() -> anyhow::Result<()> {
+    let tools = weather_tools();
+    let mut turns = vec![
+        r#"{"tool": "weather", "arguments": {"city": "Oslo"}}"#.to_string(),
+        "It is sunny in Oslo.".to_string(),
+    ]
+    .into_iter();
+    let mut transcripts = Vec::new();
+
+    let conversation = [Turn::new("user", "Weather in Oslo?")];
+    let run = run_agent(&tools, &conversation, ChatTemplate::Plain, &AgentOptions::default(), |transcript| {
+        transcripts.push(transcript);
+        let output = turns.next().unwrap();
+        async move { Ok(output) }
+    })
+    .await?;
+
+    assert_eq!(run.answer, "It is sunny in Oslo.");
+    assert_eq!(run.steps.len(), 1);
+    assert_eq!(run.steps[0].result, "Sunny in Oslo");
+    assert!(transcripts[0].contains("weather: Current weather for a city"));
+    assert!(transcripts[1].ends_with("tool: weather: Sunny in Oslo"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn agent_transcripts_are_rendered_in_the_models_template() -> anyhow::Result<()> {
+    let tools = weather_tools();
+    let mut outputs = vec![
+        r#"{"tool": "weather", "arguments": {"city": "Oslo"}}"#.to_string(),
+        "It is sunny in Oslo.".to_string(),
+    ]
+    .into_iter();
+    let mut transcripts = Vec::new();
+
+    let turns = [Turn::new("user", "Weather in Oslo?")];
+    run_agent(&tools, &turns, ChatTemplate::ChatMl, &AgentOptions::default(), |transcript| {
+        transcripts.push(transcript);
+        let output = outputs.next().unwrap();
+        async move { Ok(output) }
+    })
+    .await?;
+
+    // The tools prompt and every turn stay on one line
+    assert!(transcripts.iter().all(|transcript| !transcript.contains('\n')));
+    assert!(transcripts[0].starts_with("<|im_start|>system You can use the following tools: - weather:"));
+    assert!(transcripts[0].ends_with("<|im_start|>user Weather in Oslo?<|im_end|><|im_start|>assistant"));
+    assert!(transcripts[1].ends_with(
+        "<|im_start|>user Weather in Oslo?<|im_end|>\
+         <|im_start|>assistant {\"arguments\":{\"city\":\"Oslo\"},\"tool\":\"weather\"}<|im_end|>\
+         <|im_start|>tool weather: Sunny in Oslo<|im_end|><|im_start|>assistant"
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn agent_gives_up_after_max_steps() {
+    let tools = weather_tools();
+    let options = AgentOptions { max_steps: 2 };
+
+    let turns = [Turn::new("user", "loop")];
+    let result = run_agent(&tools, &turns, ChatTemplate::Plain, &options, |_| async {
+        Ok(r#"{"tool": "weather", "arguments": {"city": "Oslo"}}"#.to_string())
+    })
+    .await;
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn tool_calls_are_parsed_from_fenced_and_openai_style_output() {
+    let fenced = "```json\n{\"name\": \"weather\", \"arguments\": \"{\\\"city\\\": \\\"Lima\\\"}\"}\n```";
+    assert_eq!(
+        parse_tool_call(fenced),
+        Some(ToolCall {
+            name: "weather".to_string(),
+            arguments: json!({ "city": "Lima" }),
+        })
+    );
+    assert_eq!(parse_tool_call("The answer is 42."), None);
+}
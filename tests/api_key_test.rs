@@ -0,0 +1,60 @@
+//! Tests for API keys without tenants (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::{create_router, AppState, Config, LitManager, ProcessPool, Tenants};
+use std::sync::Arc;
+
+#[test]
+fn api_keys_act_as_the_unrestricted_tenant() {
+    let tenants = Tenants::default().with_api_keys(&["secret".to_string()]);
+    assert!(tenants.is_enabled());
+    assert!(tenants.for_key(None).is_none());
+    assert!(tenants.for_key(Some("wrong")).is_none());
+    assert!(tenants.for_key(Some("secret")).unwrap().can_manage("anything"));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn server_requires_a_known_key() -> anyhow::Result<()> {
+    // Lists one downloaded model
+    let dir = common::fake_lit(
+        "#!/bin/sh\ncase \"$1\" in\n list) printf 'ALIAS  MODEL_ID  SIZE\\ngemma3-1b  google/gemma3-1b  1.0GB\\n' ;;\nesac\n",
+    )?;
+    common::write_config(&dir, serde_json::json!({ "api_keys": ["from-config"] }))?;
+    std::env::set_var("LITERT_API_KEYS", "from-env, other-env");
+    assert_eq!(Config::load()?.api_keys(), ["from-config", "from-env", "other-env"]);
+
+    // As `serve --api-key from-flag` would
+    let manager = LitManager::new().await?.with_api_keys(&["from-flag".to_string()]);
+    let pool = Arc::new(ProcessPool::new(dir.join("lit"), "gemma-3n-E4B".to_string(), 1));
+    let app = create_router(AppState {
+        pool,
+        manager: Arc::new(manager),
+        readiness: Default::default(),
+        chunking: Default::default(),
+    });
+    let base = common::serve(app).await?;
+
+    let client = reqwest::Client::new();
+    let health = client.get(format!("{}/health", base)).send().await?;
+    assert_eq!(health.status(), 200);
+
+    let anonymous = client.get(format!("{}/v1/models", base)).send().await?;
+    assert_eq!(anonymous.status(), 401);
+    assert_eq!(anonymous.headers()["www-authenticate"], "Bearer");
+    let error: serde_json::Value = anonymous.json().await?;
+    assert_eq!(error["error"]["code"], "invalid_api_key");
+    assert_eq!(error["error"]["type"], "invalid_request_error");
+
+    let wrong = client.get(format!("{}/v1/models", base)).bearer_auth("guess").send().await?;
+    assert_eq!(wrong.status(), 401);
+    let error: serde_json::Value = wrong.json().await?;
+    assert_eq!(error["error"]["message"], "Incorrect API key provided");
+
+    for key in ["from-config", "from-env", "other-env", "from-flag"] {
+        let authorized = client.get(format!("{}/v1/models", base)).bearer_auth(key).send().await?;
+        assert_eq!(authorized.status(), 200, "{}", key);
+    }
+    Ok(())
+}
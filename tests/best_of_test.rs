@@ -0,0 +1,117 @@
+//! Tests for best-of-n sampling (no lit binary required)
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use litert_lm::generation::{generate_best, length_score, GenOptions, Scorer};
+use litert_lm::ChatTemplate;
+
+const ANSWERS: [&str; 3] = [
+    "Paris",
+    "The capital of France is Paris, on the Seine.",
+    "Paris Paris Paris Paris Paris Paris",
+];
+
+#[test]
+fn length_score_penalizes_short_and_repetitive_answers() {
+    assert_eq!(length_score("  "), 0.0);
+    assert!(length_score(ANSWERS[1]) > length_score(ANSWERS[0]));
+    assert!(length_score(ANSWERS[1]) > length_score(ANSWERS[2]));
+}
+
+#[test]
+fn best_of_must_cover_n() {
+    let options = GenOptions {
+        n: 2,
+        best_of: Some(1),
+        ..Default::default()
+    };
+    assert!(options.check().is_err());
+
+    let options = GenOptions {
+        best_of: Some(64),
+        ..Default::default()
+    };
+    assert!(options.check().is_err());
+}
+
+#[tokio::test]
+async fn generate_best_returns_top_n_by_length_score() -> anyhow::Result<()> {
+    let calls = AtomicUsize::new(0);
+    let options = GenOptions {
+        n: 2,
+        best_of: Some(3),
+        ..Default::default()
+    };
+
+    let best = generate_best("user: capital of France?", ChatTemplate::Plain, &options, |_| {
+        let answer = ANSWERS[calls.fetch_add(1, Ordering::SeqCst)];
+        async move { Ok((answer.to_string(), ())) }
+    })
+    .await?;
+
+    let texts: Vec<_> = best.into_iter().map(|(text, _)| text).collect();
+    assert_eq!(texts, vec![ANSWERS[1], ANSWERS[0]]);
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_eval_prefers_the_highest_rating() -> anyhow::Result<()> {
+    let calls = AtomicUsize::new(0);
+    let options = GenOptions {
+        best_of: Some(3),
+        scorer: Scorer::SelfEval,
+        ..Default::default()
+    };
+
+    let best = generate_best("user: capital of France?", ChatTemplate::Plain, &options, |prompt| {
+        let reply = if prompt.contains("Rate how well") {
+            // The terse answer gets the top rating
+            if prompt.contains(&format!("assistant: {}\n", ANSWERS[0])) { "9" } else { "4" }
+        } else {
+            ANSWERS[calls.fetch_add(1, Ordering::SeqCst)]
+        };
+        async move { Ok((reply.to_string(), ())) }
+    })
+    .await?;
+
+    assert_eq!(best.len(), 1);
+    assert_eq!(best[0].0, ANSWERS[0]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_eval_asks_for_the_rating_in_the_models_template() -> anyhow::Result<()> {
+    let options = GenOptions {
+        best_of: Some(2),
+        scorer: Scorer::SelfEval,
+        ..Default::default()
+    };
+    let prompt = ChatTemplate::Gemma.render_prompt(None, "capital of France?");
+    let reviews = std::sync::Mutex::new(Vec::new());
+
+    generate_best(&prompt, ChatTemplate::Gemma, &options, |prompt| {
+        let reply = match prompt.contains("Rate how well") {
+            true => {
+                reviews.lock().unwrap().push(prompt);
+                "7"
+            }
+            false => "Paris,\non the Seine.",
+        };
+        async move { Ok((reply.to_string(), ())) }
+    })
+    .await?;
+
+    let reviews = reviews.into_inner().unwrap();
+    assert_eq!(reviews.len(), 2);
+    assert_eq!(
+        reviews[0],
+        "<start_of_turn>user capital of France?<end_of_turn>\
+         <start_of_turn>model Paris, on the Seine.<end_of_turn>\
+         <start_of_turn>user Rate how well the previous answer responds to the request, \
+         from 1 (poor) to 10 (excellent).<end_of_turn>\
+         <start_of_turn>user Reply with only text matching the regular expression `10|[1-9]`.<end_of_turn>\
+         <start_of_turn>model"
+    );
+    Ok(())
+}
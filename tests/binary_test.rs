@@ -0,0 +1,71 @@
+//! Tests for picking and verifying the lit binary (no download required)
+#![cfg(unix)]
+
+use litert_lm::binary::{artifact_name, detect_libc, verify_binary, Libc};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+fn script(dir: &std::path::Path, name: &str, body: &[u8]) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, body).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn working_binary_reports_its_version() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-binary-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+
+    let lit = script(&dir, "lit", b"#!/bin/sh\necho 'lit v0.7.0'\n");
+    assert_eq!(verify_binary(&lit).await?, "lit v0.7.0");
+
+    // Printed to stderr instead
+    let lit = script(&dir, "lit-stderr", b"#!/bin/sh\necho 'v0.7.0' >&2\n");
+    assert_eq!(verify_binary(&lit).await?, "v0.7.0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn broken_binaries_are_rejected() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-binary-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+
+    // What a truncated or wrong-architecture download looks like to the OS
+    let garbage = script(&dir, "garbage", &[0x7f, b'E', b'L', b'F', 0, 0, 0]);
+    assert!(verify_binary(&garbage).await.is_err());
+
+    let failing = script(&dir, "failing", b"#!/bin/sh\necho 'Illegal instruction' >&2\nexit 132\n");
+    let error = verify_binary(&failing).await.unwrap_err();
+    assert!(error.to_string().contains("Illegal instruction"));
+
+    assert!(verify_binary(&dir.join("missing")).await.is_err());
+    Ok(())
+}
+
+#[test]
+fn detects_musl_unless_glibc_can_run() {
+    let root = std::env::temp_dir().join(format!("litert-libc-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(root.join("lib")).unwrap();
+    assert_eq!(detect_libc(&root), None);
+
+    std::fs::write(root.join("lib/ld-musl-x86_64.so.1"), b"").unwrap();
+    assert_eq!(detect_libc(&root), Some(Libc::Musl));
+
+    // gcompat provides a glibc loader
+    std::fs::create_dir_all(root.join("lib64")).unwrap();
+    std::fs::write(root.join("lib64/ld-linux-x86-64.so.2"), b"").unwrap();
+    assert_eq!(detect_libc(&root), Some(Libc::Glibc));
+}
+
+#[test]
+fn musl_and_unknown_platforms_get_actionable_errors() {
+    assert_eq!(artifact_name("linux", "x86_64", Some(Libc::Glibc)).unwrap(), "lit.linux_x86_64");
+    assert_eq!(artifact_name("linux", "aarch64", None).unwrap(), "lit.linux_arm64");
+
+    let musl = artifact_name("linux", "x86_64", Some(Libc::Musl)).unwrap_err().to_string();
+    assert!(musl.contains("musl") && musl.contains("gcompat") && musl.contains("binary.artifact"));
+
+    let unknown = artifact_name("freebsd", "x86_64", None).unwrap_err().to_string();
+    assert!(unknown.contains("freebsd/x86_64") && unknown.contains("binary.artifact"));
+}
@@ -0,0 +1,31 @@
+//! Tests for serving on a chosen address (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::LitManager;
+use std::time::Duration;
+
+#[tokio::test]
+async fn serve_on_listens_on_the_given_address() -> anyhow::Result<()> {
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf 'Hello.\\n>>>'; done\n")?;
+    std::env::set_var("LITERT_MODEL", "gemma3-1b");
+
+    // A port that was free a moment ago
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let manager = LitManager::new_with_pool_size(1).await?;
+    let server = tokio::spawn(async move { manager.serve_on(addr).await });
+
+    let url = format!("http://{}/health", addr);
+    let mut healthy = false;
+    for _ in 0..50 {
+        if let Ok(response) = reqwest::get(&url).await {
+            healthy = response.status().is_success();
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(healthy, "server didn't answer on {}", addr);
+    server.abort();
+    Ok(())
+}
@@ -0,0 +1,51 @@
+//! Tests for setting up a LitManager with its builder (uses a stand-in lit script)
+#![cfg(unix)]
+
+mod common;
+
+use litert_lm::{Backend, Config, LitManager};
+
+#[tokio::test]
+async fn builder_settings_replace_env_vars_and_defaults() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-builder-{}", uuid::Uuid::new_v4()));
+    let cache = dir.join("cache");
+    std::fs::create_dir_all(&dir)?;
+
+    // Outside the cache, under a name no download would use; echoes each prompt
+    let lit = common::write_script(
+        &dir.join("my-lit"),
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n",
+    )?;
+
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(&cache)
+        .pool_size(1)
+        .backend(Backend::Cpu)
+        .binary_path(&lit)
+        .default_model("gemma3-1b")
+        .build()
+        .await?;
+    assert!(cache.is_dir());
+    assert_eq!(manager.ensure_binary_path().await?, lit);
+    assert_eq!(manager.default_model(), Some("gemma3-1b"));
+
+    // A completion that names no model runs on the default one
+    assert!(manager.run_completion("", "hello").await?.contains("hello"));
+    let loaded = manager.loaded_models().await;
+    assert_eq!(loaded.len(), 1);
+    assert_eq!((loaded[0].model.as_str(), loaded[0].processes), ("gemma3-1b", 1));
+
+    manager.shutdown().await;
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn builder_rejects_a_missing_binary_and_an_empty_pool() {
+    let missing = LitManager::builder().config(Config::default()).binary_path("/nonexistent/lit").build().await;
+    assert!(missing.unwrap_err().to_string().contains("/nonexistent/lit"));
+
+    let empty = LitManager::builder().config(Config::default()).pool_size(0).build().await;
+    assert!(empty.unwrap_err().to_string().contains("pool_size"));
+}
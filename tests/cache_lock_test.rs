@@ -0,0 +1,62 @@
+//! Tests for cooperative cache locking
+
+use litert_lm::cache_lock::{CacheLock, LockOwner};
+use std::time::Duration;
+
+#[tokio::test]
+async fn second_holder_waits_until_release() -> anyhow::Result<()> {
+    let dir = tempdir();
+    let lock = CacheLock::acquire(&dir, "model-gemma", Duration::from_secs(1)).await?;
+    assert!(lock.path().exists());
+
+    let contended = CacheLock::acquire(&dir, "model-gemma", Duration::from_millis(300)).await;
+    assert!(contended.is_err());
+
+    drop(lock);
+    let relocked = CacheLock::acquire(&dir, "model-gemma", Duration::from_secs(1)).await?;
+    drop(relocked);
+    assert!(!dir.join("locks").join("model-gemma.lock").exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn lock_of_exited_process_is_recovered() -> anyhow::Result<()> {
+    let dir = tempdir();
+    let mut child = std::process::Command::new("true").spawn()?;
+    let dead_pid = child.id();
+    child.wait()?;
+
+    let stale = LockOwner {
+        pid: dead_pid,
+        ..LockOwner::current()
+    };
+    std::fs::create_dir_all(dir.join("locks"))?;
+    std::fs::write(dir.join("locks").join("binary.lock"), serde_json::to_vec(&stale)?)?;
+
+    let lock = CacheLock::acquire(&dir, "binary", Duration::from_secs(2)).await?;
+    let owner: LockOwner = serde_json::from_slice(&std::fs::read(lock.path())?)?;
+    assert_eq!(owner.pid, std::process::id());
+    Ok(())
+}
+
+#[tokio::test]
+async fn dropping_a_recovered_lock_leaves_the_new_one() -> anyhow::Result<()> {
+    let dir = tempdir();
+    let lock = CacheLock::acquire(&dir, "model-gemma", Duration::from_secs(1)).await?;
+
+    // Taken over as stale by another acquisition in this same process
+    let newer = LockOwner::current();
+    std::fs::write(lock.path(), serde_json::to_vec(&newer)?)?;
+    let path = lock.path().to_path_buf();
+    drop(lock);
+
+    let owner: LockOwner = serde_json::from_slice(&std::fs::read(&path)?)?;
+    assert_eq!(owner.nonce, newer.nonce);
+    Ok(())
+}
+
+fn tempdir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("litert-lock-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
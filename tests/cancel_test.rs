@@ -0,0 +1,45 @@
+//! Tests for cancelling downloads and completions (no lit binary required)
+
+mod common;
+
+use futures::StreamExt;
+use litert_lm::{Cancelled, CancellationToken, CompletionStream, LitManager};
+
+#[tokio::test]
+async fn cancelled_handle_stops_before_downloading() -> anyhow::Result<()> {
+    let dir = common::cache_dir()?;
+    let manager = LitManager::new().await?;
+
+    let token = CancellationToken::new();
+    let cancellable = manager.with_cancellation(token.clone());
+    token.cancel();
+
+    let err = cancellable.run_completion("gemma-3n-E4B", "Hello").await.unwrap_err();
+    assert!(err.is::<Cancelled>(), "{err:#}");
+    let err = cancellable.run_completion_stream("gemma-3n-E4B", "Hello").await.unwrap_err();
+    assert!(err.is::<Cancelled>(), "{err:#}");
+    let err = cancellable.pull_quiet("gemma-3n-E4B", None, None).await.unwrap_err();
+    assert!(err.is::<Cancelled>(), "{err:#}");
+
+    // Nothing was left half-downloaded in the cache
+    let leftovers: Vec<_> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("lit."))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_stream_has_no_finish_chunk() -> anyhow::Result<()> {
+    let chunks = vec![Ok("Hello".to_string()), Err(anyhow::Error::new(Cancelled)), Ok("late".to_string())];
+    let chunks: Vec<_> = CompletionStream::new("gemma-3n-E4B", futures::stream::iter(chunks))
+        .collect()
+        .await;
+
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0].as_ref().unwrap().text, "Hello");
+    assert!(chunks[1].as_ref().unwrap_err().is::<Cancelled>());
+    Ok(())
+}
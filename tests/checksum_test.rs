@@ -0,0 +1,77 @@
+//! Tests for model checksums (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::checksum::{parse_sha256, sha256_file};
+
+// SHA-256 of "abc"
+const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+#[test]
+fn files_hash_to_their_sha256() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("litert-sha256-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&path, b"abc")?;
+    assert_eq!(sha256_file(&path)?, (ABC.to_string(), 3));
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(parse_sha256(&format!("sha256:{}", ABC.to_uppercase()))?, ABC);
+    assert!(parse_sha256("abc123").is_err());
+    assert!(parse_sha256(&"g".repeat(64)).is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pulls_record_checksums_and_verify_finds_damage() -> anyhow::Result<()> {
+    use litert_lm::{Config, LitError, LitManager};
+
+    let dir = std::env::temp_dir().join(format!("litert-checksum-{}", uuid::Uuid::new_v4()));
+    let model_file = dir.join("models/gemma3-1b.litertlm");
+    std::fs::create_dir_all(model_file.parent().unwrap())?;
+
+    // `pull` stores "abc" as the model; `list` shows it with its path
+    let calls = dir.join("calls");
+    let lit = common::write_script(&dir.join("lit"), &format!(
+            "#!/bin/sh\n\
+             echo \"$@\" >> {calls}\n\
+             case \"$1\" in\n\
+             pull) printf abc > {file} ;;\n\
+             list) echo 'ALIAS      MODEL_ID                       PATH'\n\
+                   [ -e {file} ] && echo 'gemma3-1b  litert-community/Gemma3-1B-IT  {file}' ;;\n\
+             rm) rm -f {file} ;;\n\
+             esac\n\
+             exit 0\n",
+            calls = calls.display(),
+            file = model_file.display()
+        ))?;
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(dir.join("cache"))
+        .binary_path(&lit)
+        .build()
+        .await?;
+
+    // A model that isn't what was asked for is removed again
+    let wrong = "0".repeat(64);
+    let error = manager.pull_with_sha256("gemma3-1b", None, None, &wrong).await.unwrap_err();
+    match LitError::of(&error) {
+        Some(LitError::ChecksumMismatch { expected, actual, .. }) => assert_eq!((expected, actual), (&wrong, &ABC.to_string())),
+        other => panic!("{:?}", other),
+    }
+    assert!(std::fs::read_to_string(&calls)?.lines().any(|call| call == "rm gemma3-1b"));
+    assert!(manager.model_checksum("gemma3-1b").is_none());
+
+    manager.pull_with_sha256("gemma3-1b", None, None, ABC).await?;
+    let recorded = manager.model_checksum("gemma3-1b").unwrap();
+    assert_eq!((recorded.sha256.as_str(), recorded.size_bytes), (ABC, 3));
+    assert_eq!(recorded.path, model_file);
+    assert_eq!(manager.verify("gemma3-1b").await?.sha256, ABC);
+
+    // A file damaged after the pull no longer matches
+    std::fs::write(&model_file, b"abd")?;
+    let damaged = manager.verify("gemma3-1b").await.unwrap_err();
+    assert!(matches!(LitError::of(&damaged), Some(LitError::ChecksumMismatch { .. })), "{:#}", damaged);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
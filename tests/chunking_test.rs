@@ -0,0 +1,69 @@
+//! Tests for sentence and clause chunking of streamed completions
+
+use futures::StreamExt;
+use litert_lm::stream::{rechunk, SentenceSplitter};
+use litert_lm::{Chunking, GenOptions};
+
+fn split(chunking: Chunking, pieces: &[&str]) -> Vec<String> {
+    let mut splitter = SentenceSplitter::new(chunking);
+    let mut units: Vec<String> = pieces.iter().flat_map(|piece| splitter.push(piece)).collect();
+    units.extend(splitter.finish());
+    units
+}
+
+#[test]
+fn sentences_are_regrouped_across_chunks() {
+    let units = split(Chunking::Sentence, &["Hel", "lo there. How a", "re you? I'm", " fine!", " Bye"]);
+    assert_eq!(units, ["Hello there. ", "How are you? ", "I'm fine! ", "Bye"]);
+}
+
+#[test]
+fn numbers_abbreviations_and_quotes_stay_in_one_sentence() {
+    let units = split(
+        Chunking::Sentence,
+        &["Pi is 3.14 roughly. Dr. Smith said \"yes.\" Then", " he left.\nNext line"],
+    );
+    assert_eq!(units, ["Pi is 3.14 roughly. ", "Dr. Smith said \"yes.\" ", "Then he left.\n", "Next line"]);
+
+    // A trailing period can't be judged until the next chunk arrives
+    let mut splitter = SentenceSplitter::new(Chunking::Sentence);
+    assert!(splitter.push("It works.").is_empty());
+    assert_eq!(splitter.push(" Yes"), ["It works. "]);
+}
+
+#[test]
+fn clauses_split_long_sentences_only() {
+    let units = split(Chunking::Clause, &["Well, yes. After a long day at the office, I went home; then I slept."]);
+    assert_eq!(units, ["Well, yes. ", "After a long day at the office, ", "I went home; then I slept."]);
+
+    // Raw passes chunks through as they are
+    assert_eq!(split(Chunking::Raw, &["a. ", "b"]), ["a. ", "b"]);
+}
+
+#[test]
+fn cjk_sentence_ends_need_no_space() {
+    assert_eq!(split(Chunking::Sentence, &["你好。今天", "好吗？好"]), ["你好。", "今天好吗？", "好"]);
+}
+
+#[tokio::test]
+async fn rechunked_stream_keeps_the_text_and_errors() {
+    let pieces = ["One. Tw", "o. Thr", "ee"].map(|piece| Ok(piece.to_string()));
+    let units: Vec<String> = rechunk(futures::stream::iter(pieces), Chunking::Sentence)
+        .map(|unit| unit.unwrap())
+        .collect()
+        .await;
+    assert_eq!(units, ["One. ", "Two. ", "Three"]);
+
+    let failing = futures::stream::iter([Ok("Half a sen".to_string()), Err(anyhow::anyhow!("lit process crashed"))]);
+    let items: Vec<_> = rechunk(failing, Chunking::Sentence).collect().await;
+    assert!(items[0].is_err());
+    assert_eq!(items[1].as_deref().unwrap(), "Half a sen");
+}
+
+#[test]
+fn requests_choose_chunking_in_x_litert() {
+    let options: GenOptions = serde_json::from_value(serde_json::json!({ "chunking": "sentence" })).unwrap();
+    assert_eq!(options.chunking, Some(Chunking::Sentence));
+    assert!(serde_json::from_value::<GenOptions>(serde_json::json!({ "chunking": "word" })).is_err());
+    assert_eq!("clause".parse::<Chunking>(), Ok(Chunking::Clause));
+}
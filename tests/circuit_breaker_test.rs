@@ -0,0 +1,120 @@
+//! Tests for the per-model circuit breaker (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::breaker::CircuitChange;
+use litert_lm::config::CircuitBreakerConfig;
+use litert_lm::{CircuitBreaker, CircuitState};
+use std::sync::{Arc, Mutex};
+
+fn config(max_crashes: usize, cooldown_secs: u64) -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+        max_crashes,
+        cooldown_secs,
+        ..CircuitBreakerConfig::default()
+    }
+}
+
+#[test]
+fn opens_after_repeated_crashes_and_fails_fast() {
+    let breaker = CircuitBreaker::new("gemma3-1b", config(3, 60));
+    breaker.record_crash();
+    breaker.record_crash();
+    assert_eq!(breaker.check(), Ok(false));
+    assert_eq!(breaker.status().state, CircuitState::Closed);
+
+    breaker.record_crash();
+    let status = breaker.status();
+    assert_eq!(status.state, CircuitState::Open);
+    assert_eq!((status.trips, status.recent_crashes), (1, 3));
+
+    let unstable = breaker.check().unwrap_err();
+    assert_eq!(Some(unstable.until), status.until);
+    assert!(unstable.retry_after_secs() > 50);
+    let message = unstable.to_string();
+    assert!(message.starts_with("Model 'gemma3-1b' is unstable (3 crashes within 60s); backed off until 20"));
+    assert!(message.ends_with('Z'), "{}", message);
+}
+
+#[test]
+fn half_opens_after_the_cooldown_and_closes_on_success() {
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let seen = changes.clone();
+    let breaker =
+        CircuitBreaker::new("gemma3-1b", config(1, 0)).with_listener(move |change| seen.lock().unwrap().push(change.clone()));
+
+    // Successes while closed change nothing
+    breaker.record_success();
+    breaker.record_crash();
+    assert_eq!(breaker.status().state, CircuitState::Open);
+
+    // The cooldown is over: the first check restarts the model, later ones wait for it
+    assert_eq!(breaker.check(), Ok(true));
+    assert_eq!(breaker.check(), Ok(false));
+    assert_eq!(breaker.status().state, CircuitState::HalfOpen);
+
+    // A crash while half-open trips it again
+    breaker.record_crash();
+    assert_eq!(breaker.status().trips, 2);
+    assert_eq!(breaker.check(), Ok(true));
+    breaker.record_success();
+    let status = breaker.status();
+    assert_eq!((status.state, status.recent_crashes, status.until), (CircuitState::Closed, 0, None));
+
+    let changes = changes.lock().unwrap();
+    assert!(matches!(changes[0], CircuitChange::Opened { crashes: 1, .. }));
+    assert_eq!(changes[1], CircuitChange::HalfOpened);
+    assert!(matches!(changes[2], CircuitChange::Opened { crashes: 2, .. }));
+    assert_eq!(changes[3..], [CircuitChange::HalfOpened, CircuitChange::Closed]);
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn crashing_model_is_backed_off_and_restarted() -> anyhow::Result<()> {
+    use litert_lm::{LitManager, ManagerEvent, Unstable};
+    use std::time::Duration;
+
+    let dir = common::cache_dir()?;
+    common::write_config(&dir, serde_json::json!({"circuit_breaker": {"max_crashes": 2, "cooldown_secs": 1}}))?;
+
+    // Every process dies while loading the model
+    common::install_lit(&dir, "#!/bin/sh\nexit 1\n")?;
+    let manager = LitManager::new_with_pool_size(2).await?;
+    let mut events = manager.subscribe();
+
+    // The pool fails to start, and stops trying once the circuit opens
+    let error = manager.pool("gemma3-1b").await.unwrap_err();
+    assert!(error.is::<Unstable>(), "{:#}", error);
+    let opened = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let ManagerEvent::CircuitOpened { model, crashes, .. } = events.recv().await? {
+                return anyhow::Ok((model, crashes));
+            }
+        }
+    })
+    .await??;
+    assert_eq!(opened, ("gemma3-1b".to_string(), 2));
+
+    let error = manager.pool("gemma3-1b").await.unwrap_err();
+    assert!(error.is::<Unstable>());
+    assert!(error.to_string().contains("backed off until"), "{}", error);
+    assert_eq!(manager.circuit("gemma3-1b").map(|c| c.state), Some(CircuitState::Open));
+
+    // Fixed by the time the back-off is over: a fresh pool is started
+    common::install_lit(&dir, "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let pool = manager.pool("gemma3-1b").await?;
+    assert_eq!(pool.send_prompt("user: hi").await?.trim(), "user: hi");
+
+    let mut seen = Vec::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !seen.contains(&ManagerEvent::CircuitClosed { model: "gemma3-1b".to_string() }) {
+            seen.push(events.recv().await?);
+        }
+        anyhow::Ok(())
+    })
+    .await??;
+    assert!(seen.contains(&ManagerEvent::CircuitHalfOpen { model: "gemma3-1b".to_string() }));
+    assert_eq!(manager.circuit("gemma3-1b").map(|c| c.state), Some(CircuitState::Closed));
+    Ok(())
+}
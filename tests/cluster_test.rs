@@ -0,0 +1,64 @@
+//! Tests for cluster routing against in-process fake nodes (no lit binary required)
+
+mod common;
+
+use axum::{http::StatusCode, routing::get, routing::post, Json, Router};
+use litert_lm::Cluster;
+use serde_json::json;
+
+async fn spawn_node(models: &'static [&'static str], completion_status: StatusCode) -> String {
+    let app = Router::new()
+        .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+        .route(
+            "/v1/models",
+            get(move || async move {
+                let data: Vec<_> = models.iter().map(|id| json!({ "id": id, "object": "model" })).collect();
+                Json(json!({ "object": "list", "data": data }))
+            }),
+        )
+        .route(
+            "/v1/chat/completions",
+            post(move || async move { (completion_status, Json(json!({ "models": models }))) }),
+        );
+    common::serve(app).await.unwrap()
+}
+
+async fn closed_port() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+#[tokio::test]
+async fn routes_by_model_and_fails_over() -> anyhow::Result<()> {
+    let failing = spawn_node(&["gemma"], StatusCode::INTERNAL_SERVER_ERROR).await;
+    let working = spawn_node(&["gemma", "qwen"], StatusCode::OK).await;
+    let dead = closed_port().await;
+    let cluster = Cluster::new([failing.clone(), working.clone(), dead.clone()]);
+
+    cluster.check_health().await;
+    let healthy: Vec<_> = cluster.nodes().into_iter().filter(|n| n.healthy).map(|n| n.url).collect();
+    assert_eq!(healthy, vec![failing, working.clone()]);
+    assert_eq!(cluster.models(), vec!["gemma", "qwen"]);
+
+    let body = axum::body::Bytes::from(r#"{"model": "gemma"}"#);
+    for _ in 0..4 {
+        let forwarded = cluster
+            .forward("/v1/chat/completions", Some("gemma"), Default::default(), body.clone())
+            .await?;
+        assert_eq!(forwarded.node, working);
+        assert_eq!(forwarded.response.status(), 200);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_without_healthy_nodes() {
+    let cluster = Cluster::new([closed_port().await]);
+    cluster.check_health().await;
+
+    assert!(!cluster.is_healthy());
+    let result = cluster
+        .forward("/v1/chat/completions", None, Default::default(), Default::default())
+        .await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,104 @@
+//! Fixtures shared by the integration tests
+//!
+//! Tests that need lit install a stand-in shell script ([`fake_lit`]) that
+//! speaks its prompt protocol: print `>>>`, then answer each line read from
+//! stdin and print `>>>` again. The manager finds the script through
+//! `LITERT_CACHE_DIR`, which is process-wide, so each test binary keeps to one
+//! test that sets it.
+#![allow(dead_code)]
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// A fresh temporary directory
+pub fn temp_dir() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("litert-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// A fresh cache directory for the manager, with history off and no config
+/// file
+pub fn cache_dir() -> Result<PathBuf> {
+    let dir = temp_dir()?;
+    std::env::set_var("LITERT_CONFIG", dir.join("missing.json"));
+    std::env::set_var("LITERT_CACHE_DIR", &dir);
+    std::env::set_var("LITERT_HISTORY", "off");
+    Ok(dir)
+}
+
+/// Write `config` to the cache directory and point `LITERT_CONFIG` at it
+pub fn write_config(dir: &Path, config: serde_json::Value) -> Result<PathBuf> {
+    let path = dir.join("config.json");
+    std::fs::write(&path, serde_json::to_vec(&config)?)?;
+    std::env::set_var("LITERT_CONFIG", &path);
+    Ok(path)
+}
+
+/// A fresh cache directory with `script` installed as the lit binary
+pub fn fake_lit(script: &str) -> Result<PathBuf> {
+    let dir = cache_dir()?;
+    install_lit(&dir, script)?;
+    Ok(dir)
+}
+
+/// Install `script` as the lit binary in `dir`, replacing any earlier one
+pub fn install_lit(dir: &Path, script: &str) -> Result<PathBuf> {
+    write_script(&dir.join("lit.linux_x86_64"), script)
+}
+
+/// `script` as an executable in a fresh directory, for running without the
+/// manager (e.g. with [`ProcessPool::new`](litert_lm::ProcessPool::new))
+pub fn script(script: &str) -> Result<PathBuf> {
+    write_script(&temp_dir()?.join("lit"), script)
+}
+
+/// Write an executable script to `path`
+///
+/// The script is written next to it and renamed into place, so processes
+/// still running an earlier one are not disturbed.
+pub fn write_script(path: &Path, script: &str) -> Result<PathBuf> {
+    let staged = path.with_file_name(format!(".staged-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&staged, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    std::fs::rename(&staged, path)?;
+    Ok(path.to_path_buf())
+}
+
+/// Serve `app` on a free local port; returns its base URL
+#[cfg(feature = "server")]
+pub async fn serve(app: axum::Router) -> Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let base = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    Ok(base)
+}
+
+/// Serve the API of `manager`, with `model` as the default model; returns the
+/// base URL
+#[cfg(feature = "server")]
+pub async fn spawn_server(manager: &std::sync::Arc<litert_lm::LitManager>, model: &str) -> Result<String> {
+    use litert_lm::{create_router, AppState};
+
+    serve(create_router(AppState {
+        pool: manager.pool(model).await?,
+        manager: manager.clone(),
+        readiness: Default::default(),
+        chunking: Default::default(),
+    }))
+    .await
+}
+
+/// The events of a server-sent event stream, up to `[DONE]`
+pub fn sse_events<T: serde::de::DeserializeOwned>(body: &str) -> Result<Vec<T>> {
+    Ok(body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .take_while(|data| *data != "[DONE]")
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?)
+}
@@ -0,0 +1,88 @@
+//! Tests for per-model concurrency limits (no lit binary required)
+
+mod common;
+
+use litert_lm::concurrency::ConcurrencyLimits;
+use litert_lm::config::ConcurrencyConfig;
+use litert_lm::{create_router, AppState, LitManager, ProcessPool};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn limits(limits: &[(&str, usize)], default_limit: Option<usize>) -> ConcurrencyLimits {
+    ConcurrencyLimits::from_config(&ConcurrencyConfig {
+        limits: limits.iter().map(|(pattern, limit)| (pattern.to_string(), *limit)).collect::<HashMap<_, _>>(),
+        default_limit,
+        max_queue: None,
+    })
+}
+
+#[test]
+fn most_specific_pattern_sets_the_limit() {
+    let configured = limits(&[("gemma-3n-*", 2), ("gemma-3n-E4B", 1)], Some(8));
+    assert_eq!(configured.limit("gemma-3n-E4B"), Some(1));
+    assert_eq!(configured.limit("gemma-3n-E2B"), Some(2));
+    assert_eq!(configured.limit("qwen2.5-1.5b"), Some(8));
+    assert_eq!(limits(&[], None).limit("qwen2.5-1.5b"), None);
+}
+
+#[test]
+fn permits_are_counted_until_dropped() {
+    let limits = limits(&[("gemma-3n-*", 2)], None);
+    let first = limits.try_acquire("gemma-3n-E4B").unwrap();
+    let _second = limits.try_acquire("gemma-3n-E4B").unwrap();
+    assert_eq!(limits.in_flight("gemma-3n-E4B"), 2);
+
+    // Each model has its own count, even under a shared pattern
+    let _other = limits.try_acquire("gemma-3n-E2B").unwrap();
+    let saturated = limits.try_acquire("gemma-3n-E4B").unwrap_err();
+    assert_eq!(saturated.limit, 2);
+    assert_eq!(
+        saturated.to_string(),
+        "Model 'gemma-3n-E4B' already has 2 requests in flight, its limit; retry shortly"
+    );
+
+    drop(first);
+    assert_eq!(limits.in_flight("gemma-3n-E4B"), 1);
+    assert!(limits.try_acquire("gemma-3n-E4B").is_ok());
+
+    // Unlimited models are still counted
+    let _unlimited = limits.try_acquire("qwen2.5-1.5b").unwrap();
+    assert_eq!(limits.in_flight("qwen2.5-1.5b"), 1);
+}
+
+#[tokio::test]
+async fn requests_beyond_the_limit_get_429() -> anyhow::Result<()> {
+    let dir = common::cache_dir()?;
+    common::write_config(&dir, serde_json::json!({"concurrency": {"limits": {"gemma-3n-*": 1}}}))?;
+
+    let manager = Arc::new(LitManager::new().await?);
+    let app = create_router(AppState {
+        pool: Arc::new(ProcessPool::new(dir.join("lit"), "gemma-3n-E4B".to_string(), 1)),
+        manager: manager.clone(),
+        readiness: Default::default(),
+        chunking: Default::default(),
+    });
+    let base = common::serve(app).await?;
+
+    // A long generation holds the model's only slot
+    let running = manager.concurrency().try_acquire("gemma-3n-E4B")?;
+    let response = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&serde_json::json!({
+            "model": "gemma-3n-E4B",
+            "messages": [{ "role": "user", "content": "Hi" }]
+        }))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()["retry-after"], "1");
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["error"]["code"], "model_concurrency_exceeded");
+
+    assert_eq!(manager.concurrency().in_flight("gemma-3n-E4B"), 1);
+    drop(running);
+    assert_eq!(manager.concurrency().in_flight("gemma-3n-E4B"), 0);
+    let rejected = manager.metrics().concurrency_rejections.with_label_values(&["gemma-3n-E4B"]).get();
+    assert_eq!(rejected, 1);
+    Ok(())
+}
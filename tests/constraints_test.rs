@@ -0,0 +1,128 @@
+//! Tests for output constraints (no lit binary required)
+
+use litert_lm::generation::{generate_with, ConstraintViolation, GenOptions};
+use litert_lm::{ChatTemplate, Constraint};
+
+fn regex(pattern: &str) -> Constraint {
+    Constraint::Regex {
+        pattern: pattern.to_string(),
+    }
+}
+
+#[test]
+fn regex_constraint_matches_whole_output() {
+    let phone = regex(r"\(\d{3}\) \d{3}-\d{4}");
+    assert_eq!(phone.validate(" (555) 123-4567\n"), Ok("(555) 123-4567".to_string()));
+    assert!(phone.validate("Call (555) 123-4567").is_err());
+
+    let word = regex("(yes|no)[.!]?");
+    assert!(word.validate("no!").is_ok());
+    assert!(word.validate("maybe").is_err());
+
+    assert!(regex(r"[a-z]+\{").check().is_ok());
+    assert!(regex("(unclosed").check().is_err());
+    assert!(regex("*a").check().is_err());
+    // Patterns that would compile to a huge automaton are rejected up front
+    assert!(regex("(a{1000}){1000}").check().is_err());
+}
+
+#[test]
+fn regex_matching_does_not_backtrack() {
+    let nested = regex("(a|aa)+");
+    let started = std::time::Instant::now();
+    assert!(nested.validate(&format!("{}b", "a".repeat(5000))).is_err());
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+}
+
+#[test]
+fn choice_and_json_constraints_normalize_output() {
+    let choice = Constraint::Choice {
+        options: vec!["Positive".to_string(), "Negative".to_string()],
+    };
+    assert_eq!(choice.validate("\"negative\"."), Ok("Negative".to_string()));
+    assert!(choice.validate("neutral").is_err());
+
+    assert_eq!(
+        Constraint::Json.validate("```json\n{\"a\": 1}\n```"),
+        Ok("{\"a\": 1}".to_string())
+    );
+    assert!(Constraint::Json.validate("{\"a\": ").is_err());
+}
+
+#[tokio::test]
+async fn generate_with_reprompts_until_the_constraint_holds() -> anyhow::Result<()> {
+    let options = GenOptions {
+        constraint: Some(Constraint::Choice {
+            options: vec!["yes".to_string(), "no".to_string()],
+        }),
+        ..Default::default()
+    };
+    let mut replies = vec!["I think so", "yes"].into_iter();
+    let mut prompts = Vec::new();
+
+    let (text, ()) = generate_with("user: Is water wet?", ChatTemplate::Plain, &options, |prompt| {
+        prompts.push(prompt);
+        let reply = replies.next().unwrap().to_string();
+        async move { Ok((reply, ())) }
+    })
+    .await?;
+
+    assert_eq!(text, "yes");
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[0].ends_with("Reply with only exactly one of: yes, no."));
+    assert!(prompts[1].contains("assistant: I think so"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reprompts_are_turns_in_the_models_template() -> anyhow::Result<()> {
+    let options = GenOptions {
+        constraint: Some(Constraint::Choice {
+            options: vec!["yes".to_string(), "no".to_string()],
+        }),
+        ..Default::default()
+    };
+    let mut replies = vec!["I think\nso", "yes"].into_iter();
+    let mut prompts = Vec::new();
+    let prompt = ChatTemplate::Gemma.render_prompt(None, "Is water wet?");
+
+    generate_with(&prompt, ChatTemplate::Gemma, &options, |prompt| {
+        prompts.push(prompt);
+        let reply = replies.next().unwrap().to_string();
+        async move { Ok((reply, ())) }
+    })
+    .await?;
+
+    assert_eq!(
+        prompts[0],
+        "<start_of_turn>user Is water wet?<end_of_turn>\
+         <start_of_turn>user Reply with only exactly one of: yes, no.<end_of_turn><start_of_turn>model"
+    );
+    assert_eq!(
+        prompts[1],
+        "<start_of_turn>user Is water wet?<end_of_turn>\
+         <start_of_turn>user Reply with only exactly one of: yes, no.<end_of_turn>\
+         <start_of_turn>model I think so<end_of_turn>\
+         <start_of_turn>user That answer is invalid (`I think so` is not one of: yes, no). \
+         Reply with only exactly one of: yes, no.<end_of_turn><start_of_turn>model"
+    );
+    assert!(prompts.iter().all(|prompt| !prompt.contains('\n')));
+    Ok(())
+}
+
+#[tokio::test]
+async fn generate_with_fails_once_retries_are_exhausted() {
+    let options = GenOptions {
+        constraint: Some(Constraint::Json),
+        constraint_retries: 1,
+        ..Default::default()
+    };
+
+    let err = generate_with("user: hi", ChatTemplate::Plain, &options, |_| async { Ok(("hello".to_string(), ())) })
+        .await
+        .unwrap_err();
+
+    let violation = err.downcast_ref::<ConstraintViolation>().unwrap();
+    assert_eq!(violation.attempts, 2);
+    assert_eq!(violation.last_output, "hello");
+}
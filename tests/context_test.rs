@@ -0,0 +1,117 @@
+//! Tests for context window management (no lit binary required)
+
+use litert_lm::config::ContextConfig;
+use litert_lm::context::{fit, ContextOverflow, Overflow};
+use litert_lm::tokenizer::{Estimate, Tokenizer};
+use litert_lm::{ContextWindow, Turn};
+
+fn window(length: usize, overflow: Overflow) -> ContextWindow {
+    ContextWindow {
+        length,
+        reserve: 0,
+        overflow,
+    }
+}
+
+/// A system prompt followed by `turns` alternating user/assistant turns of ~25 tokens each
+fn conversation(turns: usize) -> Vec<Turn> {
+    let mut conversation = vec![Turn::new("system", "Be brief.")];
+    for i in 0..turns {
+        let role = if i % 2 == 0 { "user" } else { "assistant" };
+        conversation.push(Turn::new(role, format!("turn {} {}", i, "x".repeat(90))));
+    }
+    conversation
+}
+
+#[test]
+fn window_prefers_the_most_specific_configured_pattern() {
+    let mut config = ContextConfig::default();
+    config.lengths.insert("gemma*".to_string(), 2048);
+    config.lengths.insert("gemma3-1b*".to_string(), 1024);
+
+    assert_eq!(ContextWindow::for_model(&config, "gemma3-1b-it").length, 1024);
+    assert_eq!(ContextWindow::for_model(&config, "gemma-3n-E4B").length, 2048);
+    assert_eq!(ContextWindow::for_model(&config, "custom").length, config.default_length);
+    assert_eq!(
+        ContextWindow::for_model(&config, "custom").budget(),
+        config.default_length - config.reserve_tokens
+    );
+}
+
+#[tokio::test]
+async fn truncation_keeps_system_and_latest_turns() -> anyhow::Result<()> {
+    let turns = conversation(10);
+    let fitted = fit(turns.clone(), &window(100, Overflow::Truncate), |_| async {
+        panic!("truncation doesn't prompt the model")
+    })
+    .await?;
+
+    assert!(fitted.dropped > 0 && !fitted.summarized);
+    assert!(Estimate.count(&fitted.prompt) <= 100);
+    assert!(fitted.prompt.starts_with("system: Be brief.\n"));
+    assert!(fitted.prompt.ends_with(&turns[10].content));
+    assert!(!fitted.prompt.contains("turn 0 "));
+    Ok(())
+}
+
+#[tokio::test]
+async fn summarization_replaces_dropped_turns() -> anyhow::Result<()> {
+    let fitted = fit(conversation(10), &window(200, Overflow::Summarize), |prompt| async move {
+        assert!(prompt.contains("turn 0 ") && prompt.ends_with("needed to continue it."));
+        Ok("The user counted turns.".to_string())
+    })
+    .await?;
+
+    assert!(fitted.summarized);
+    assert!(Estimate.count(&fitted.prompt) <= 200);
+    assert!(fitted
+        .prompt
+        .starts_with("system: Be brief.\nsystem: Summary of the earlier conversation: The user counted turns.\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn oversized_latest_turn_is_rejected() {
+    let turns = vec![Turn::new("user", "y".repeat(1000))];
+    let err = fit(turns, &window(100, Overflow::Truncate), |_| async { Ok(String::new()) })
+        .await
+        .unwrap_err();
+
+    let overflow = err.downcast_ref::<ContextOverflow>().unwrap();
+    assert_eq!(overflow.limit, 100);
+    assert!(overflow.tokens > 100);
+}
+
+#[tokio::test]
+async fn long_conversations_are_counted_once_per_turn() -> anyhow::Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Default)]
+    struct Counting(AtomicUsize);
+
+    impl Tokenizer for Counting {
+        fn count(&self, text: &str) -> usize {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Estimate.count(text)
+        }
+    }
+
+    let tokenizer = Counting::default();
+    let turns = conversation(2000);
+    let fitted = litert_lm::context::fit_with(
+        turns.clone(),
+        &window(1000, Overflow::Truncate),
+        litert_lm::ChatTemplate::Plain,
+        &tokenizer,
+        |_| async { panic!("truncation doesn't prompt the model") },
+    )
+    .await?;
+
+    assert!(Estimate.count(&fitted.prompt) <= 1000);
+    // The same cut as dropping turns one at a time would find
+    let kept = 2000 - fitted.dropped;
+    assert!(fitted.prompt.contains(&turns[2001 - kept].content));
+    assert!(!fitted.prompt.contains(&turns[2000 - kept].content));
+    assert!(tokenizer.0.load(Ordering::Relaxed) < 2100, "{:?}", tokenizer);
+    Ok(())
+}
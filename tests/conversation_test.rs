@@ -0,0 +1,63 @@
+//! Tests for the conversations API (no lit binary required)
+
+mod common;
+
+use litert_lm::{create_router, AppState, LitManager, ProcessPool};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn conversations_are_stored_read_and_deleted() -> anyhow::Result<()> {
+    let dir = common::cache_dir()?;
+
+    let manager = LitManager::new().await?;
+    let pool = Arc::new(ProcessPool::new(dir.join("lit"), "gemma-3n-E4B".to_string(), 1));
+    let app = create_router(AppState {
+        pool,
+        manager: Arc::new(manager),
+        readiness: Default::default(),
+        chunking: Default::default(),
+    });
+    let base = format!("{}/v1/conversations", common::serve(app).await?);
+    let client = reqwest::Client::new();
+
+    let created = client
+        .post(&base)
+        .json(&serde_json::json!({
+            "model": "gemma-3n-E4B",
+            "messages": [{ "role": "system", "content": "Answer in French" }]
+        }))
+        .send()
+        .await?;
+    assert_eq!(created.status(), 201);
+    let created: serde_json::Value = created.json().await?;
+    let id = created["id"].as_str().unwrap().to_string();
+    assert_eq!(created["messages"][0]["role"], "system");
+
+    // Non-user messages are stored without generating a reply
+    let appended = client
+        .post(format!("{}/{}/messages", base, id))
+        .json(&serde_json::json!({ "role": "assistant", "content": "Bonjour !" }))
+        .send()
+        .await?;
+    assert_eq!(appended.status(), 200);
+
+    let fetched: serde_json::Value = client.get(format!("{}/{}", base, id)).send().await?.json().await?;
+    assert_eq!(fetched["model"], "gemma-3n-E4B");
+    assert_eq!(fetched["messages"][1]["content"], "Bonjour !");
+    assert!(dir.join("sessions").join(format!("{}.json", id)).exists());
+
+    let invalid_role = client
+        .post(format!("{}/{}/messages", base, id))
+        .json(&serde_json::json!({ "role": "robot", "content": "beep" }))
+        .send()
+        .await?;
+    assert_eq!(invalid_role.status(), 400);
+
+    let deleted = client.delete(format!("{}/{}", base, id)).send().await?;
+    assert_eq!(deleted.status(), 200);
+    let missing = client.get(format!("{}/{}", base, id)).send().await?;
+    assert_eq!(missing.status(), 404);
+    let escaped = client.get(format!("{}/..%2Fmissing", base)).send().await?;
+    assert_eq!(escaped.status(), 404);
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Tests for child process crash reports (no lit binary required)
+
+use litert_lm::crash::{prompt_hash, CrashReport, StderrTail};
+
+#[test]
+fn crash_report_is_written_with_stderr_tail() -> anyhow::Result<()> {
+    let tail = StderrTail::default();
+    tail.push("loading model\n");
+    tail.push("GPU delegate error: device lost\n\n");
+
+    let report = CrashReport::new(
+        "gemma-3n-E4B",
+        "gpu",
+        Some(4242),
+        None,
+        Some(prompt_hash("user: Hello")),
+        tail.snapshot(),
+    );
+
+    let dir = std::env::temp_dir().join(format!("litert-crash-test-{}", std::process::id()));
+    let path = report.write_to(&dir)?;
+    let written: CrashReport = serde_json::from_slice(&std::fs::read(&path)?)?;
+    std::fs::remove_dir_all(&dir)?;
+
+    assert_eq!(written.backend, "gpu");
+    assert_eq!(written.exit_status, "unknown");
+    assert_eq!(written.stderr_tail, vec!["loading model", "GPU delegate error: device lost"]);
+    // The hash is stable across runs so reports can be matched to recorded prompts
+    assert_eq!(written.in_flight_prompt_hash, Some(prompt_hash("user: Hello")));
+    assert_ne!(prompt_hash("user: Hello"), prompt_hash("user: Hello!"));
+    Ok(())
+}
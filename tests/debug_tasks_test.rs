@@ -0,0 +1,48 @@
+//! Tests for the /debug/tasks endpoint (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::LitManager;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn task_dump_shows_what_each_process_is_doing() -> anyhow::Result<()> {
+    // Takes a second to answer
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do sleep 1; printf 'Hello.\\n>>>'; done\n")?;
+    std::env::set_var("LITERT_DEBUG_ENDPOINTS", "1");
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let base = common::spawn_server(&manager, "gemma3-1b").await?;
+    let client = reqwest::Client::new();
+    let dump = || async {
+        let dump: serde_json::Value = client.get(format!("{}/debug/tasks", base)).send().await?.json().await?;
+        anyhow::Ok(dump)
+    };
+
+    let generating = tokio::spawn(
+        client
+            .post(format!("{}/v1/chat/completions", base))
+            .json(&serde_json::json!({ "model": "gemma3-1b", "messages": [{ "role": "user", "content": "hi" }] }))
+            .send(),
+    );
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let during = dump().await?;
+    assert_eq!(during["runtime"]["workers"], 2);
+    assert_eq!(during["pools_available"], true);
+    assert_eq!(during["pools"][0]["model"], "gemma3-1b");
+    let process = &during["pools"][0]["processes"][0];
+    assert_eq!(process["state"], "generating", "{}", during);
+    assert_eq!(process["pending"], 1);
+    assert!(process["pid"].as_u64().is_some());
+    assert_eq!(during["stuck"], 0);
+
+    assert!(generating.await??.status().is_success());
+    let after = dump().await?;
+    let process = &after["pools"][0]["processes"][0];
+    assert_eq!(process["state"], "idle", "{}", after);
+    assert_eq!(process["pending"], 0);
+    assert_eq!(process["served"], 1);
+    Ok(())
+}
@@ -0,0 +1,55 @@
+//! Tests for cancelling generations when the client goes away (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use futures::StreamExt;
+use litert_lm::LitManager;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn disconnecting_interrupts_the_generation() -> anyhow::Result<()> {
+    // Writes a word every 100ms when asked for a long answer, and stops on
+    // SIGINT, noting how far it got
+    let dir = common::fake_lit(
+        "#!/bin/sh\nstop=0\ntrap 'stop=1' INT\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         *long*) stop=0; i=0\n\
+          while [ $i -lt 100 ] && [ $stop = 0 ]; do printf 'word%s\\n' $i; sleep 0.1; i=$((i+1)); done\n\
+          echo $i > \"$(dirname \"$0\")/stopped\"; printf '>>>' ;;\n\
+         *) printf 'Hello.\\n>>>' ;;\n esac\ndone\n",
+    )?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&manager, "gemma3-1b").await?);
+    let client = reqwest::Client::new();
+    let ask = |content: &str, stream: bool| {
+        serde_json::json!({
+            "model": "gemma3-1b", "stream": stream, "messages": [{ "role": "user", "content": content }]
+        })
+    };
+
+    // Read a little of the stream, then hang up
+    let response = client.post(&url).json(&ask("a long answer", true)).send().await?;
+    let mut body = response.bytes_stream();
+    let mut received = String::new();
+    while !received.contains("word1") {
+        received.push_str(&String::from_utf8_lossy(&body.next().await.unwrap()?));
+    }
+    drop(body);
+
+    let stopped = dir.join("stopped");
+    for _ in 0..50 {
+        if stopped.exists() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let words: u32 = std::fs::read_to_string(&stopped)?.trim().parse()?;
+    assert!(words < 50, "lit wrote {} words", words);
+
+    // The process is free again, and nothing of the dropped answer leaks into the next one
+    let response: serde_json::Value = client.post(&url).json(&ask("hi", false)).send().await?.json().await?;
+    assert_eq!(response["choices"][0]["message"]["content"], "Hello.");
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! Tests for resumable model downloads against a stand-in server that drops
+//! the first connection halfway
+
+mod common;
+
+use litert_lm::download::{self, partial_path};
+use reqwest::header::HeaderMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const MODEL: &[u8] = b"0123456789";
+
+// Sends half the model and hangs up, then answers ranges as asked; hands back
+// the requests it got
+async fn flaky_server() -> anyhow::Result<(String, Arc<Mutex<Vec<String>>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/models/model.litertlm", listener.local_addr()?);
+    let requests: Arc<Mutex<Vec<String>>> = Arc::default();
+    let seen = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let request = String::from_utf8_lossy(&request).to_lowercase();
+            let first = seen.lock().unwrap().is_empty();
+            seen.lock().unwrap().push(request.clone());
+            let offset: Option<usize> = request
+                .lines()
+                .find_map(|line| line.strip_prefix("range: bytes="))
+                .and_then(|range| range.trim().trim_end_matches('-').parse().ok());
+            let response = match offset {
+                _ if first => format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n01234", MODEL.len()).into_bytes(),
+                Some(offset) => {
+                    let mut response = format!(
+                        "HTTP/1.1 206 Partial Content\r\ncontent-range: bytes {}-{}/{}\r\ncontent-length: {}\r\n\r\n",
+                        offset,
+                        MODEL.len() - 1,
+                        MODEL.len(),
+                        MODEL.len() - offset
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(&MODEL[offset..]);
+                    response
+                }
+                None => {
+                    let mut response = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n", MODEL.len()).into_bytes();
+                    response.extend_from_slice(MODEL);
+                    response
+                }
+            };
+            let _ = socket.write_all(&response).await;
+        }
+    });
+    Ok((url, requests))
+}
+
+#[tokio::test]
+async fn interrupted_downloads_resume_where_they_stopped() -> anyhow::Result<()> {
+    let (url, requests) = flaky_server().await?;
+    let dir = std::env::temp_dir().join(format!("litert-download-{}", uuid::Uuid::new_v4()));
+    let dest = download::path_for(&dir, &url);
+    assert_eq!(dest.file_name().unwrap(), "model.litertlm");
+    let client = reqwest::Client::new();
+
+    assert!(download::fetch(&client, &url, &dest, None, &HeaderMap::new(), |_, _| {}).await.is_err());
+    assert_eq!(std::fs::read(partial_path(&dest))?, b"01234");
+
+    let mut seen = Vec::new();
+    let size = download::fetch(&client, &url, &dest, Some("secret"), &HeaderMap::new(), |done, total| seen.push((done, total))).await?;
+    assert_eq!(size, 10);
+    assert_eq!(std::fs::read(&dest)?, MODEL);
+    assert!(!partial_path(&dest).exists());
+    assert_eq!(seen.first(), Some(&(5, Some(10))));
+    assert_eq!(seen.last(), Some(&(10, Some(10))));
+
+    let requests = requests.lock().unwrap();
+    assert!(requests[1].contains("range: bytes=5-"), "{}", requests[1]);
+    assert!(requests[1].contains("authorization: bearer secret"), "{}", requests[1]);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn url_pulls_are_retried_and_handed_to_lit_once_downloaded() -> anyhow::Result<()> {
+    use litert_lm::{Config, LitManager, RetryPolicy};
+    use std::time::Duration;
+
+    let (url, requests) = flaky_server().await?;
+    let dir = common::temp_dir()?;
+    let calls = dir.join("calls");
+    let lit = common::write_script(&dir.join("lit"), &format!("#!/bin/sh\necho \"$@\" >> {}\n", calls.display()))?;
+    let cache = dir.join("cache");
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(&cache)
+        .binary_path(&lit)
+        .build()
+        .await?
+        .with_retry_policy(RetryPolicy::default().with_backoff(Duration::from_millis(10), Duration::from_millis(10)));
+
+    manager.pull_quiet(&url, None, None).await?;
+    assert_eq!(requests.lock().unwrap().len(), 2);
+
+    // lit pulls the finished file from this process, which then drops it
+    let calls = std::fs::read_to_string(&calls)?;
+    let pull = calls.lines().find(|call| call.starts_with("pull ")).unwrap();
+    assert!(pull.starts_with("pull http://127.0.0.1:") && pull.ends_with("/model.litertlm"), "{}", pull);
+    assert!(!pull.contains("/models/"), "{}", pull);
+    let downloads = cache.join(download::DOWNLOADS_DIR);
+    assert_eq!(std::fs::read_dir(&downloads)?.count(), 0);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn headers_are_parsed_from_name_value_pairs() -> anyhow::Result<()> {
+    let (name, value) = download::parse_header("X-Api-Key:  k3y ")?;
+    assert_eq!((name.as_str(), value.to_str()?), ("x-api-key", "k3y"));
+    assert!(value.is_sensitive());
+    assert!(download::parse_header("X-Api-Key").is_err());
+    assert!(download::parse_header("Bad Name: value").is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn download_headers_are_sent_even_without_resuming() -> anyhow::Result<()> {
+    use litert_lm::{Config, LitManager, RetryPolicy};
+    use std::time::Duration;
+
+    let (url, requests) = flaky_server().await?;
+    let dir = common::temp_dir()?;
+    let lit = common::write_script(&dir.join("lit"), "#!/bin/sh\nexit 0\n")?;
+    let headers: HeaderMap = ["Authorization: Basic dXNlcjpwYXNz", "X-Api-Key: k3y"]
+        .iter()
+        .map(|header| download::parse_header(header))
+        .collect::<anyhow::Result<_>>()?;
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(dir.join("cache"))
+        .binary_path(&lit)
+        .build()
+        .await?
+        .with_retry_policy(RetryPolicy::default().with_backoff(Duration::from_millis(10), Duration::from_millis(10)))
+        .with_resumable_downloads(false)
+        .with_download_headers(headers);
+
+    manager.pull_quiet(&url, None, Some("hf_secret")).await?;
+
+    // The retry starts over, and the headers win over the token
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[1].contains("range:"), "{}", requests[1]);
+    assert!(requests[1].contains("x-api-key: k3y"), "{}", requests[1]);
+    assert!(requests[1].contains("authorization: basic dxnlcjpwyxnz"), "{}", requests[1]);
+    assert!(!requests[1].contains("bearer"), "{}", requests[1]);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
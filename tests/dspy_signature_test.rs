@@ -0,0 +1,58 @@
+//! Tests for the DSpy prompt adapter (no lit binary required)
+
+use litert_lm::dspy::{self, SignatureCache};
+use std::sync::Arc;
+
+fn dspy_prompt(question: &str) -> String {
+    format!(
+        "system: Your input fields are:\n1. `question` (String)\n\n\
+         Your output fields are:\n1. `answer` (String)\n\n\
+         All interactions will be structured in the following way, with the appropriate values filled in.\n\n\
+         [[ ## question ## ]]\n{{question}}\n\n[[ ## answer ## ]]\n{{answer}}\n\n[[ ## completed ## ]]\n\n\
+         In adhering to this structure, your objective is: \n        Given the fields `question`, produce the fields `answer`.\n\
+         user: [[ ## question ## ]]\n{}",
+        question
+    )
+}
+
+#[test]
+fn signatures_are_parsed_once_per_preamble() {
+    let cache = SignatureCache::new(2);
+    let first = dspy_prompt("What is 2 + 2?");
+    assert!(dspy::is_dspy_request(&first));
+
+    let signature = cache.get(&first);
+    assert_eq!(signature.output_fields, ["answer"]);
+    assert_eq!(
+        signature.instructions.as_deref(),
+        Some("Given the fields `question`, produce the fields `answer`.")
+    );
+
+    // Another question with the same preamble reuses the parsed signature
+    let again = cache.get(&dspy_prompt("What is the capital of France?"));
+    assert!(Arc::ptr_eq(&signature, &again));
+    assert_eq!(cache.len(), 1);
+
+    // The oldest signature is dropped beyond the capacity
+    cache.get(&first.replace("`answer`", "`reply`"));
+    cache.get(&first.replace("`answer`", "`result`"));
+    assert_eq!(cache.len(), 2);
+    assert!(!Arc::ptr_eq(&signature, &cache.get(&first)));
+}
+
+#[test]
+fn small_model_prompt_keeps_instructions_and_question() {
+    let prompt = dspy_prompt("What is 2 + 2?");
+    let signature = SignatureCache::default().get(&prompt);
+    let question = dspy::extract_dspy_question(&prompt).unwrap();
+    assert_eq!(question, "What is 2 + 2?");
+
+    let simplified = signature.prompt_for(&question);
+    assert!(simplified.starts_with("system: Given the fields"));
+    assert!(simplified.ends_with("\nWhat is 2 + 2?"));
+    assert!(!simplified.contains("[[ ##"));
+    assert_eq!(
+        dspy::format_dspy_response(" 4 ", &signature.output_fields),
+        "[[ ## answer ## ]]\n4\n\n[[ ## completed ## ]]\n"
+    );
+}
@@ -0,0 +1,88 @@
+//! ModelEmbedder with a tiny randomly initialized BERT (no downloads)
+
+use candle_core::Device;
+use candle_nn::{VarBuilder, VarMap};
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use litert_lm::retrieval::{DocumentIndex, Embedder, ModelEmbedder};
+use std::sync::Arc;
+
+const WORDS: &[&str] = &[
+    "[UNK]", "[PAD]", "the", "gpu", "cpu", "backend", "falls", "back", "to", "models", "are", "pulled",
+    "from", "registry", "driver", "missing",
+];
+
+fn tiny_model(dir: &std::path::Path) -> anyhow::Result<()> {
+    let config = Config {
+        vocab_size: WORDS.len(),
+        hidden_size: 16,
+        num_hidden_layers: 1,
+        num_attention_heads: 2,
+        intermediate_size: 32,
+        max_position_embeddings: 64,
+        ..Default::default()
+    };
+    let varmap = VarMap::new();
+    BertModel::load(VarBuilder::from_varmap(&varmap, DTYPE, &Device::Cpu), &config)?;
+    varmap.save(dir.join("model.safetensors"))?;
+    std::fs::write(dir.join("config.json"), serde_json::to_string(&config_json(&config))?)?;
+
+    let vocab: serde_json::Map<String, serde_json::Value> =
+        WORDS.iter().enumerate().map(|(i, w)| (w.to_string(), i.into())).collect();
+    let tokenizer = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": {"type": "Lowercase"},
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": null,
+        "decoder": null,
+        "model": {"type": "WordLevel", "vocab": vocab, "unk_token": "[UNK]"}
+    });
+    std::fs::write(dir.join("tokenizer.json"), tokenizer.to_string())?;
+    Ok(())
+}
+
+// bert::Config has no Serialize; write the fields Hugging Face configs carry
+fn config_json(config: &Config) -> serde_json::Value {
+    serde_json::json!({
+        "vocab_size": config.vocab_size,
+        "hidden_size": config.hidden_size,
+        "num_hidden_layers": config.num_hidden_layers,
+        "num_attention_heads": config.num_attention_heads,
+        "intermediate_size": config.intermediate_size,
+        "hidden_act": "gelu",
+        "hidden_dropout_prob": 0.1,
+        "max_position_embeddings": config.max_position_embeddings,
+        "type_vocab_size": 2,
+        "initializer_range": 0.02,
+        "layer_norm_eps": 1e-12,
+        "pad_token_id": 1,
+        "model_type": "bert"
+    })
+}
+
+#[test]
+fn model_embeddings_are_normalized_and_searchable() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-embeddings-{}", uuid::Uuid::new_v4()));
+    let model_dir = dir.join("tiny-bert");
+    std::fs::create_dir_all(&model_dir)?;
+    tiny_model(&model_dir)?;
+
+    let embedder = Arc::new(ModelEmbedder::load(&model_dir)?);
+    assert_eq!(embedder.id(), "model:tiny-bert");
+    let vector = embedder.embed("the gpu backend")?;
+    assert_eq!(vector.len(), 16);
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    assert!((norm - 1.0).abs() < 1e-4);
+    assert_eq!(vector, embedder.embed("The GPU backend")?);
+
+    let index = DocumentIndex::open_in_memory(embedder)?;
+    index.ingest_text("gpu.md", "the gpu backend falls back to the cpu")?;
+    index.ingest_text("pull.md", "models are pulled from the registry")?;
+    let hits = index.search("models are pulled from the registry", 2)?;
+    assert_eq!(hits[0].source, "pull.md");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
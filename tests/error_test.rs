@@ -0,0 +1,27 @@
+//! Tests for LitError
+
+use litert_lm::LitError;
+use std::time::Duration;
+
+#[test]
+fn lit_errors_are_found_behind_context() {
+    let error = anyhow::Error::from(LitError::BinaryUnavailable { reason: "HTTP 404".to_string() })
+        .context("Failed to start the pool");
+    assert_eq!(LitError::of(&error), Some(&LitError::BinaryUnavailable { reason: "HTTP 404".to_string() }));
+    assert!(LitError::of(&anyhow::anyhow!("something else")).is_none());
+}
+
+#[test]
+fn lit_errors_describe_themselves() {
+    let crashed = LitError::ProcessCrashed { stderr: vec!["loading".to_string(), "out of memory".to_string()] };
+    assert_eq!(crashed.to_string(), "lit process died: loading; out of memory");
+    assert_eq!(LitError::ProcessCrashed { stderr: Vec::new() }.to_string(), "lit process died");
+
+    let failed = LitError::DownloadFailed { model: "gemma3-1b".to_string(), reason: " \n".to_string() };
+    assert_eq!(failed.to_string(), "Failed to pull model 'gemma3-1b'");
+
+    let timeout = LitError::Timeout { after: Duration::from_secs(120) };
+    assert_eq!(timeout.to_string(), "Generation did not finish within 120s");
+    assert!(timeout.is_timeout());
+    assert!(!crashed.is_timeout());
+}
@@ -0,0 +1,60 @@
+//! Tests for the in-process LiteRT-LM backend (links the library; generations
+//! need a model file in $LITERT_LM_TEST_MODEL)
+
+use futures::StreamExt;
+use litert_lm::ffi::FfiBackend;
+use litert_lm::{Backend, Config, InferenceBackend, LitManager};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn unknown_models_fail_without_loading_anything() {
+    let backend = FfiBackend::new();
+    assert!(!backend.has_model("gemma-3n-E4B"));
+    let error = backend.complete("gemma-3n-E4B", "Hello!").await.unwrap_err();
+    assert!(error.to_string().contains("Unknown model"), "{:#}", error);
+    assert!(backend.loaded().await.is_empty());
+}
+
+#[tokio::test]
+async fn models_generate_in_process() -> anyhow::Result<()> {
+    let Some(path) = std::env::var_os("LITERT_LM_TEST_MODEL") else {
+        eprintln!("LITERT_LM_TEST_MODEL is not set; skipping");
+        return Ok(());
+    };
+    let backend = Arc::new(FfiBackend::new().with_model("test", path).with_backend(Backend::Cpu));
+
+    let reply = backend.complete("test", "Say hello.").await?;
+    assert!(!reply.trim().is_empty());
+    assert_eq!(backend.loaded().await, ["test"]);
+
+    // A reply dropped after its first chunk stops the generation, and the
+    // engine is free for the next one
+    let mut stream = backend.complete_stream("test", "Count from one to five hundred.").await?;
+    stream.next().await.unwrap()?;
+    drop(stream);
+    let reply = tokio::time::timeout(Duration::from_secs(60), backend.complete("test", "Say hello.")).await??;
+    assert!(!reply.trim().is_empty());
+
+    // A manager given the engine runs its completions on it, without lit
+    let cache = std::env::temp_dir().join(format!("litert-ffi-{}", uuid::Uuid::new_v4()));
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(&cache)
+        .build()
+        .await?
+        .with_ffi_backend(backend.clone());
+    assert!(!manager.run_completion("test", "Say hello.").await?.trim().is_empty());
+    let mut stream = manager.run_completion_stream("test", "Say hello.").await?;
+    let mut finish_reason = None;
+    while let Some(chunk) = stream.next().await {
+        finish_reason = chunk?.finish_reason.or(finish_reason);
+    }
+    assert!(finish_reason.is_some());
+    assert!(manager.loaded_models().await.is_empty());
+    std::fs::remove_dir_all(&cache)?;
+
+    assert!(backend.unload("test").await);
+    assert!(backend.loaded().await.is_empty());
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Tests for splitting lit's output into replies
+
+use litert_lm::process::Framer;
+
+/// Reply text and whether the prompt ended it, for `output` read `size`
+/// bytes at a time, after which lit goes quiet
+fn frame(output: &[u8], size: usize) -> (String, bool) {
+    let mut framer = Framer::new();
+    let mut text = String::new();
+    for chunk in output.chunks(size) {
+        text.push_str(&framer.push(chunk).text);
+    }
+    let frame = framer.settle();
+    text.push_str(&frame.text);
+    (text, frame.end)
+}
+
+/// Framed the same however the output is split across reads
+fn reply(output: &str) -> (String, bool) {
+    let whole = frame(output.as_bytes(), output.len().max(1));
+    for size in 1..output.len() {
+        assert_eq!(frame(output.as_bytes(), size), whole, "read {} bytes at a time", size);
+    }
+    whole
+}
+
+fn ended(text: &str) -> (String, bool) {
+    (text.to_string(), true)
+}
+
+#[test]
+fn replies_end_at_the_prompt() {
+    // Start-up, as `lit run` prints it
+    assert_eq!(
+        reply("Loading model gemma3-1b...\nModel 'gemma3-1b' loaded.\n>>> "),
+        ended("Loading model gemma3-1b...\nModel 'gemma3-1b' loaded.")
+    );
+    assert_eq!(reply("The capital of France is Paris.\n>>>"), ended("The capital of France is Paris."));
+    assert_eq!(reply("First line.\n\nSecond line.\n>>>"), ended("First line.\n\nSecond line."));
+    assert_eq!(reply(">>>"), ended(""));
+    // Still generating
+    assert_eq!(reply("Paris is the capital\n"), ("Paris is the capital".to_string(), false));
+}
+
+#[test]
+fn prompts_in_replies_are_text() {
+    assert_eq!(reply("Use >>> to shift right.\n>>>"), ended("Use >>> to shift right."));
+    assert_eq!(reply("> quoted\n>> nested\n>>>"), ended("> quoted\n>> nested"));
+    // A line starting like the prompt is told apart by what follows it,
+    // however the reads split it
+    assert_eq!(
+        reply("In a Python shell:\n>>> print(1 + 1)\n2\n>>>"),
+        ended("In a Python shell:\n>>> print(1 + 1)\n2")
+    );
+    assert_eq!(reply(">>>> deeper\n>>>"), ended(">>>> deeper"));
+}
+
+#[test]
+fn the_prompt_is_only_the_prompt_once_lit_goes_quiet() {
+    let mut framer = Framer::new();
+    assert_eq!(framer.push(b"In a Python shell:\n>>> ").text, "In a Python shell:");
+    assert!(framer.at_prompt());
+    // More on the line: reply text after all
+    assert_eq!(framer.push(b"print(1)\n").text, "\n>>> print(1)");
+    assert!(!framer.at_prompt());
+    assert!(!framer.settle().end);
+    framer.push(b">>>");
+    assert!(framer.settle().end);
+    // The next reply starts afresh
+    assert!(!framer.at_prompt());
+}
+
+#[test]
+fn escapes_and_carriage_returns_are_dropped() {
+    // A colored prompt
+    assert_eq!(reply("Hello!\r\n\x1b[1;32m>>>\x1b[0m "), ended("Hello!"));
+    assert_eq!(reply("\x1b]0;lit\x07Hi\n\x1b[32m>>> \x1b[0m"), ended("Hi"));
+    assert_eq!(reply("\x1b[1mbold\x1b[0m text\n>>>"), ended("bold text"));
+}
+
+#[test]
+fn characters_split_across_reads_are_kept_whole() {
+    assert_eq!(reply("Grüße aus Köln 👋\n>>>"), ended("Grüße aus Köln 👋"));
+
+    let mut framer = Framer::new();
+    assert_eq!(framer.push(b"caf\xc3").text, "caf");
+    assert_eq!(framer.push(b"\xa9\n>>>").text, "é");
+    let mut framer = Framer::new();
+    assert_eq!(framer.push(b"bad \xff byte\n>>>").text, "bad \u{fffd} byte");
+}
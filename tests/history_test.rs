@@ -0,0 +1,59 @@
+//! Tests for the persistent request history store (no lit binary required)
+
+use litert_lm::history::HistoryStore;
+use litert_lm::{HistoryQuery, HistoryRecord, HistoryStatus};
+use std::time::Instant;
+
+#[test]
+fn records_are_filtered_by_model_and_status() -> anyhow::Result<()> {
+    let store = HistoryStore::open_in_memory(false)?;
+    let started = Instant::now();
+
+    store.record(&HistoryRecord::new("a", "gemma-3n-E4B", "http", "hi").finish(started, Ok("hello")))?;
+    store.record(
+        &HistoryRecord::new("b", "gemma-3n-E4B", "http", "hi")
+            .finish(started, Err("process died".to_string())),
+    )?;
+    store.record(&HistoryRecord::new("c", "gemma3-1b", "library", "hi").finish(started, Ok("hey")))?;
+
+    let gemma = store.query(&HistoryQuery {
+        model: Some("gemma-3n-E4B".to_string()),
+        ..Default::default()
+    })?;
+    assert_eq!(gemma.len(), 2);
+
+    let errors = store.query(&HistoryQuery {
+        status: Some(HistoryStatus::Error),
+        ..Default::default()
+    })?;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].id, "b");
+    assert_eq!(errors[0].error.as_deref(), Some("process died"));
+
+    let future = store.query(&HistoryQuery {
+        since: Some(u64::MAX / 2),
+        ..Default::default()
+    })?;
+    assert!(future.is_empty());
+    Ok(())
+}
+
+#[test]
+fn content_is_only_kept_when_enabled() -> anyhow::Result<()> {
+    let started = Instant::now();
+    let record = HistoryRecord::new("a", "gemma-3n-E4B", "http", "secret prompt").finish(started, Ok("answer"));
+
+    let metadata_only = HistoryStore::open_in_memory(false)?;
+    metadata_only.record(&record)?;
+    let stored = metadata_only.get("a")?.expect("record exists");
+    assert_eq!(stored.prompt, None);
+    assert_eq!(stored.prompt_chars, "secret prompt".len());
+    assert_eq!(stored.response_chars, "answer".len());
+
+    let with_content = HistoryStore::open_in_memory(true)?;
+    with_content.record(&record)?;
+    let stored = with_content.get("a")?.expect("record exists");
+    assert_eq!(stored.prompt.as_deref(), Some("secret prompt"));
+    assert_eq!(stored.response.as_deref(), Some("answer"));
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Tests for HttpBackend against a stand-in OpenAI-compatible server
+
+use futures::StreamExt;
+use litert_lm::{HttpBackend, InferenceBackend};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// Answers one request with `body` as an event stream, and hands back the
+// request it got
+async fn serve_once(body: &'static str) -> anyhow::Result<(String, tokio::task::JoinHandle<String>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // Headers, then a JSON body that ends with its closing brace
+        while !String::from_utf8_lossy(&request).trim_end().ends_with('}') {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request).into_owned()
+    });
+    Ok((url, handle))
+}
+
+#[tokio::test]
+async fn completions_stream_from_an_openai_compatible_server() -> anyhow::Result<()> {
+    let (url, server) = serve_once(
+        "data: {\"choices\":[{\"text\":\"The capital\"}]}\n\n\
+         data: {\"choices\":[{\"text\":\" is Paris.\"}]}\n\n\
+         data: [DONE]\n\n",
+    )
+    .await?;
+    let backend: Arc<dyn InferenceBackend> = Arc::new(HttpBackend::new(&url).with_api_key("secret").with_max_tokens(16));
+
+    let chunks: Vec<String> = backend
+        .complete_stream("qwen2.5-1.5b", "Capital of France?")
+        .await?
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    assert_eq!(chunks, ["The capital", " is Paris."]);
+
+    let request = server.await?;
+    assert!(request.starts_with("POST /v1/completions "), "{}", request);
+    assert!(request.to_lowercase().contains("authorization: bearer secret"), "{}", request);
+    assert!(request.contains("\"max_tokens\":16"), "{}", request);
+    assert!(request.contains("\"prompt\":\"Capital of France?\""), "{}", request);
+    Ok(())
+}
+
+#[tokio::test]
+async fn errors_in_the_stream_fail_the_completion() -> anyhow::Result<()> {
+    let (url, _server) = serve_once(
+        "data: {\"choices\":[{\"text\":\"Half\"}]}\n\n\
+         data: {\"error\":{\"message\":\"model crashed\"}}\n\n",
+    )
+    .await?;
+
+    let error = HttpBackend::new(&url).complete("qwen2.5-1.5b", "Hello").await.unwrap_err();
+    assert_eq!(error.to_string(), "model crashed");
+    Ok(())
+}
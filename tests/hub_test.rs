@@ -0,0 +1,112 @@
+//! Tests for Hugging Face Hub discovery against a fake Hub (uses a stand-in lit script)
+
+mod common;
+
+use axum::{extract::Query, http::HeaderMap, routing::get, Json, Router};
+use litert_lm::hub::{self, HubClient};
+use litert_lm::{HubModel, HubQuery};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn model(files: &[&str]) -> HubModel {
+    serde_json::from_value(json!({
+        "id": "litert-community/Gemma3-1B-IT",
+        "siblings": files.iter().map(|f| json!({ "rfilename": f })).collect::<Vec<_>>(),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn only_litertlm_files_can_be_picked() {
+    let single = model(&["README.md", "gemma3-1b-it-int4.litertlm"]);
+    assert_eq!(single.files, ["gemma3-1b-it-int4.litertlm"]);
+    assert_eq!(single.default_alias(), "gemma3-1b-it");
+    assert_eq!(hub::pick_file(&single, None).unwrap(), "gemma3-1b-it-int4.litertlm");
+
+    let several = model(&["a.litertlm", "b.litertlm"]);
+    assert!(hub::pick_file(&several, None).unwrap_err().to_string().contains("--file"));
+    assert_eq!(hub::pick_file(&several, Some("b.litertlm")).unwrap(), "b.litertlm");
+    assert!(hub::pick_file(&several, Some("c.litertlm")).is_err());
+    assert!(hub::pick_file(&model(&["model.safetensors"]), None).is_err());
+}
+
+/// Lists one LiteRT-LM repository and one without a model file, and requires
+/// a token for repository details
+async fn spawn_hub() -> String {
+    let app = Router::new()
+        .route(
+            "/api/models",
+            get(|Query(params): Query<HashMap<String, String>>| async move {
+                assert_eq!(params.get("library").map(String::as_str), Some("litert-lm"));
+                assert_eq!(params.get("full").map(String::as_str), Some("true"));
+                Json(json!([
+                    {
+                        "id": "litert-community/Gemma3-1B-IT",
+                        "downloads": 1200,
+                        "likes": 40,
+                        "gated": "manual",
+                        "tags": ["litert-lm"],
+                        "siblings": [{ "rfilename": "gemma3-1b-it-int4.litertlm" }]
+                    },
+                    { "id": "someone/notes", "siblings": [{ "rfilename": "README.md" }] }
+                ]))
+            }),
+        )
+        .route(
+            "/api/models/litert-community/Gemma3-1B-IT",
+            get(|headers: HeaderMap| async move {
+                if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some("Bearer hf_test") {
+                    return Err(axum::http::StatusCode::UNAUTHORIZED);
+                }
+                Ok(Json(json!({
+                    "id": "litert-community/Gemma3-1B-IT",
+                    "sha": "abc123",
+                    "siblings": [{ "rfilename": "gemma3-1b-it-int4.litertlm" }]
+                })))
+            }),
+        );
+    common::serve(app).await.unwrap()
+}
+
+#[tokio::test]
+async fn searches_and_pulls_from_the_hub() -> anyhow::Result<()> {
+    let endpoint = spawn_hub().await;
+    std::env::set_var("HF_ENDPOINT", &endpoint);
+    std::env::remove_var("HF_TOKEN");
+
+    let found = HubClient::new(None).search(&HubQuery::new("gemma")).await?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, "litert-community/Gemma3-1B-IT");
+    assert!(found[0].gated);
+    assert_eq!(found[0].downloads, 1200);
+
+    let denied = HubClient::new(None).model("litert-community/Gemma3-1B-IT").await.unwrap_err();
+    assert!(format!("{:#}", denied).contains("HF_TOKEN"));
+    assert!(HubClient::new(None).model("../etc").await.is_err());
+
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    {
+        use litert_lm::LitManager;
+
+        // Records the arguments of every command
+        let dir = common::fake_lit("#!/bin/sh\necho \"$@\" >> \"$(dirname \"$0\")/calls\"\n")?;
+        // lit is handed the Hub URL rather than a download of it
+        let manager = LitManager::new().await?.with_resumable_downloads(false);
+
+        let provenance = manager
+            .pull_from_hub("litert-community/Gemma3-1B-IT", None, None, Some("hf_test"))
+            .await?;
+        let url = format!("{}/litert-community/Gemma3-1B-IT/resolve/abc123/gemma3-1b-it-int4.litertlm", endpoint);
+        assert_eq!(provenance.model, "gemma3-1b-it");
+        assert_eq!(provenance.revision, "abc123");
+        assert_eq!(provenance.url, url);
+        let calls = std::fs::read_to_string(dir.join("calls"))?;
+        let pull = calls.lines().find(|call| call.starts_with("pull ")).unwrap();
+        assert_eq!(pull, format!("pull {} --alias gemma3-1b-it --hf_token hf_test", url));
+        assert_eq!(manager.model_provenance("gemma3-1b-it"), Some(provenance));
+
+        manager.remove_quiet("gemma3-1b-it").await?;
+        assert_eq!(manager.model_provenance("gemma3-1b-it"), None);
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! Tests for listing and killing generations in progress (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::inflight::InflightRequests;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+#[test]
+fn requests_are_listed_until_done_and_killed_by_id() {
+    let requests = Arc::new(InflightRequests::default());
+    let first = requests.register("chatcmpl-1", "gemma3-1b", "default", "alice", CancellationToken::new());
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let second = requests.register("chatcmpl-2", "qwen2.5-1.5b", "team-a", "team-a", CancellationToken::new());
+
+    let listed = requests.list();
+    assert_eq!(listed.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(), ["chatcmpl-1", "chatcmpl-2"]);
+    assert_eq!((listed[0].model.as_str(), listed[0].client.as_str()), ("gemma3-1b", "alice"));
+    assert!(listed[0].age_ms >= listed[1].age_ms);
+
+    assert!(requests.kill("chatcmpl-2"));
+    assert!(second.token().is_cancelled());
+    assert!(!first.token().is_cancelled());
+    assert!(!requests.kill("chatcmpl-3"));
+
+    drop(second);
+    assert_eq!(requests.list().len(), 1);
+    drop(first);
+    assert!(requests.list().is_empty());
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn runaway_generations_are_killed_over_http() -> anyhow::Result<()> {
+    use futures::StreamExt;
+    use litert_lm::{InflightRequest, LitManager};
+    use std::time::Duration;
+
+    // Takes a while over prompts about runaways, back at its prompt early on
+    // SIGINT, and echoes the rest
+    common::fake_lit(
+        "#!/bin/sh\nstop=0\ntrap 'stop=1; kill $! 2>/dev/null' INT\nprintf '>>>'\nwhile read -r line; do\n\
+         case \"$line\" in\n\
+         *runaway*) stop=0; printf 'thinking\\n'; sleep 2 & [ $stop = 0 ] || kill $!; wait $!\n\
+          [ $stop = 1 ] || printf 'done\\n'; printf '>>>' ;;\n\
+         *) printf '%s\\n>>>' \"$line\" ;;\n\
+         esac\ndone\n",
+    )?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let base = common::spawn_server(&manager, "gemma3-1b").await?;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&serde_json::json!({
+            "model": "gemma3-1b",
+            "messages": [{ "role": "user", "content": "runaway" }],
+            "stream": true,
+            "user": "alice"
+        }))
+        .send()
+        .await?;
+    let mut body = response.bytes_stream();
+    // The role chunk comes first, then the text generated so far
+    let mut first = String::new();
+    while !first.contains("thinking") {
+        first.push_str(&String::from_utf8(body.next().await.unwrap()?.to_vec())?);
+    }
+
+    let listed: serde_json::Value = client.get(format!("{}/admin/requests", base)).send().await?.json().await?;
+    let listed: Vec<InflightRequest> = serde_json::from_value(listed["data"].clone())?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!((listed[0].model.as_str(), listed[0].client.as_str()), ("gemma3-1b", "alice"));
+    assert!(listed[0].id.starts_with("chatcmpl-"));
+    assert_eq!(manager.inflight()[0].id, listed[0].id);
+
+    let killed = client.delete(format!("{}/admin/requests/{}", base, listed[0].id)).send().await?;
+    assert_eq!(killed.status(), 200);
+
+    // The stream ends right away with the cancellation, before lit is done
+    let rest = tokio::time::timeout(Duration::from_secs(1), async {
+        let mut rest = String::new();
+        while let Some(chunk) = body.next().await {
+            rest.push_str(&String::from_utf8_lossy(&chunk?));
+        }
+        anyhow::Ok(rest)
+    })
+    .await??;
+    assert!(rest.contains("event: error\ndata: Operation cancelled"), "{}", rest);
+    assert!(!rest.contains("done"), "{}", rest);
+    // The error ends the stream; it doesn't finish as if it had succeeded
+    assert!(!rest.contains("finish_reason") && !rest.contains("[DONE]"), "{}", rest);
+    assert!(manager.inflight().is_empty());
+    let again = client.delete(format!("{}/admin/requests/{}", base, listed[0].id)).send().await?;
+    assert_eq!(again.status(), 404);
+
+    // The rest of the killed answer doesn't leak into the next one
+    let next: serde_json::Value = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&serde_json::json!({
+            "model": "gemma3-1b",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let content = next["choices"][0]["message"]["content"].as_str().unwrap();
+    assert!(!content.contains("done") && content.contains("hi"), "{}", content);
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Tests for unloading idle models (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::config::KeepAliveConfig;
+use litert_lm::KeepAlive;
+use std::time::Duration;
+
+#[test]
+fn keep_alive_is_read_like_ollama() {
+    let parse = |value: serde_json::Value| serde_json::from_value::<KeepAlive>(value);
+    assert_eq!(parse(serde_json::json!(300)).unwrap(), KeepAlive::For(Duration::from_secs(300)));
+    assert_eq!(parse(serde_json::json!("5m")).unwrap(), KeepAlive::For(Duration::from_secs(300)));
+    assert_eq!(parse(serde_json::json!("1h")).unwrap(), KeepAlive::For(Duration::from_secs(3600)));
+    assert_eq!(parse(serde_json::json!("0")).unwrap(), KeepAlive::For(Duration::ZERO));
+    assert_eq!(parse(serde_json::json!(-1)).unwrap(), KeepAlive::Forever);
+    assert_eq!(parse(serde_json::json!("-1m")).unwrap(), KeepAlive::Forever);
+    assert!(parse(serde_json::json!("5 minutes")).is_err());
+
+    let config: KeepAliveConfig =
+        serde_json::from_value(serde_json::json!({ "models": { "gemma-3n-*": "10m" }, "default": 60 })).unwrap();
+    assert_eq!(KeepAlive::for_model(&config, "gemma-3n-E4B"), KeepAlive::For(Duration::from_secs(600)));
+    assert_eq!(KeepAlive::for_model(&config, "gemma3-1b"), KeepAlive::For(Duration::from_secs(60)));
+    assert_eq!(KeepAlive::for_model(&KeepAliveConfig::default(), "gemma3-1b"), KeepAlive::Forever);
+    assert!(!KeepAlive::Forever.expired(Duration::MAX));
+    assert!(KeepAlive::For(Duration::ZERO).expired(Duration::ZERO));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn idle_pools_are_unloaded_and_started_again() -> anyhow::Result<()> {
+    use litert_lm::LitManager;
+    use std::sync::Arc;
+
+    // Echoes each prompt
+    let dir = common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+    common::write_config(&dir, serde_json::json!({"keep_alive": {"default": 1}}))?;
+
+    let manager = LitManager::new_with_pool_size(1).await?;
+    let pool = manager.pool("gemma3-1b").await?;
+    assert_eq!(pool.send_prompt("hello").await?.trim(), "hello");
+    assert!(manager.unload_idle().await.is_empty());
+
+    // A request asking to stay loaded outlasts the config's keep-alive
+    pool.set_keep_alive(KeepAlive::Forever);
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+    assert!(manager.unload_idle().await.is_empty());
+
+    pool.set_keep_alive(KeepAlive::For(Duration::ZERO));
+    assert_eq!(manager.unload_idle().await, ["gemma3-1b"]);
+    assert!(pool.is_retired());
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while pool.debug_info().iter().any(|process| process.state != "exited") {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await?;
+
+    // The next request starts the model again
+    let restarted = manager.pool("gemma3-1b").await?;
+    assert!(!Arc::ptr_eq(&pool, &restarted));
+    assert_eq!(restarted.send_prompt("again").await?.trim(), "again");
+    assert!(manager.unload("gemma3-1b").await);
+    assert!(!manager.unload("gemma3-1b").await);
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Tests for the x_litert latency stats on completions (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::{ChatCompletionChunk, LitManager};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn completions_carry_latency_stats() -> anyhow::Result<()> {
+    // Starts answering after 200ms, and finishes 200ms later
+    common::fake_lit(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n\
+         sleep 0.2; printf 'Hello there, '; sleep 0.2; printf 'friend.\\n>>>'\ndone\n",
+    )?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&manager, "gemma3-1b").await?);
+    let client = reqwest::Client::new();
+    let ask = |stream: bool| {
+        serde_json::json!({
+            "model": "gemma3-1b", "stream": stream, "messages": [{ "role": "user", "content": "hi" }]
+        })
+    };
+
+    let body: serde_json::Value = client.post(&url).json(&ask(false)).send().await?.json().await?;
+    let stats = &body["x_litert"];
+    let generation = stats["generation_ms"].as_u64().unwrap();
+    assert!(generation >= 400, "{}", stats);
+    assert!((200..generation).contains(&stats["ttft_ms"].as_u64().unwrap()), "{}", stats);
+    assert!(stats["completion_tokens"].as_u64().unwrap() > 0, "{}", stats);
+    assert!(stats["tokens_per_second"].as_f64().unwrap() > 0.0, "{}", stats);
+
+    // Streams carry them on the chunk with the finish reason, and only there
+    let sse = client.post(&url).json(&ask(true)).send().await?.text().await?;
+    let chunks: Vec<ChatCompletionChunk> = common::sse_events(&sse)?;
+    let with_choices: Vec<_> = chunks.iter().filter(|chunk| !chunk.choices.is_empty()).collect();
+    let (last, earlier) = with_choices.split_last().unwrap();
+    assert!(earlier.iter().all(|chunk| chunk.x_litert.is_none()));
+    assert_eq!(last.choices[0].finish_reason.as_deref(), Some("stop"));
+    let stats = last.x_litert.as_ref().unwrap();
+    assert!(stats.generation_ms >= 400, "{:?}", stats);
+    assert!(stats.ttft_ms.is_some_and(|ttft| (200..stats.generation_ms).contains(&ttft)), "{:?}", stats);
+    assert!(stats.completion_tokens > 0, "{:?}", stats);
+    Ok(())
+}
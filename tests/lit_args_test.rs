@@ -0,0 +1,62 @@
+//! Tests for per-request lit flags (`x_litert.args`)
+
+mod common;
+
+use litert_lm::{GenOptions, LitArgs};
+
+#[test]
+fn only_allowed_flags_with_valid_values_pass() {
+    let args = LitArgs::default().with("max_num_tokens", 4096).with("num_cpu_threads", 4);
+    assert_eq!(args.check(), Ok(()));
+    assert_eq!(args.flags(), ["--max_num_tokens", "4096", "--num_cpu_threads", "4"]);
+    assert_eq!(args.pool_key("gemma3-1b"), "gemma3-1b --max_num_tokens 4096 --num_cpu_threads 4");
+    assert_eq!(LitArgs::default().pool_key("gemma3-1b"), "gemma3-1b");
+
+    let unknown = LitArgs::default().with("model_path", "/etc/passwd").check().unwrap_err();
+    assert!(unknown.contains("args.model_path is not supported") && unknown.contains("max_num_tokens"));
+
+    for bad in [serde_json::json!(0), serde_json::json!(-1), serde_json::json!(1.5), serde_json::json!("4096")] {
+        assert!(LitArgs::default().with("max_num_tokens", bad).check().is_err());
+    }
+    assert!(LitArgs::default().with("num_cpu_threads", 257).check().is_err());
+}
+
+#[test]
+fn args_come_from_x_litert() {
+    let options: GenOptions = serde_json::from_value(serde_json::json!({ "args": { "max_num_tokens": 2048 } })).unwrap();
+    assert_eq!(options.args, LitArgs::default().with("max_num_tokens", 2048));
+    assert_eq!(options.check(), Ok(()));
+
+    let options: GenOptions = serde_json::from_value(serde_json::json!({ "args": { "verbose": 1 } })).unwrap();
+    assert!(options.check().is_err());
+
+    // Options without args serialize as before, keeping prompt cache keys stable
+    assert!(!serde_json::to_string(&GenOptions::default()).unwrap().contains("args"));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn requests_with_args_get_their_own_pool() -> anyhow::Result<()> {
+    use std::sync::Arc;
+
+    // Answers every prompt with the arguments it was started with
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$*\"; done\n")?;
+
+    let manager = litert_lm::LitManager::new_with_pool_size(1).await?;
+    let args = LitArgs::default().with("max_num_tokens", 4096);
+    let tuned = manager.pool_with_args("gemma3-1b", &args).await?;
+    let default = manager.pool("gemma3-1b").await?;
+    assert!(!Arc::ptr_eq(&tuned, &default));
+    assert!(Arc::ptr_eq(&tuned, &manager.pool_with_args("gemma3-1b", &args).await?));
+
+    assert_eq!(tuned.send_prompt("user: hi").await?.trim(), "run gemma3-1b --backend gpu --max_num_tokens 4096");
+    assert_eq!(default.send_prompt("user: hi").await?.trim(), "run gemma3-1b --backend gpu");
+
+    let pools = manager.stats().await.pools;
+    assert_eq!(pools.len(), 2);
+    assert!(pools[0].args.is_empty());
+    assert_eq!(pools[1].args, ["--max_num_tokens", "4096"]);
+
+    assert!(manager.pool_with_args("gemma3-1b", &LitArgs::default().with("verbose", 1)).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Tests for the load-testing harness against an in-process fake server
+
+mod common;
+
+use axum::{http::StatusCode, response::IntoResponse, routing::post, Router};
+use litert_lm::loadtest::{self, LoadTest, Percentiles, Target};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Streams "Hello world" as two chunks, failing every fourth request
+async fn spawn_server() -> String {
+    let count = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move || {
+            let count = count.clone();
+            async move {
+                if count.fetch_add(1, Ordering::SeqCst) % 4 == 3 {
+                    return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                let body = [
+                    r#"data: {"choices":[{"index":0,"delta":{"role":"assistant"}}]}"#,
+                    r#"data: {"choices":[{"index":0,"delta":{"content":"Hello "}}]}"#,
+                    r#"data: {"choices":[{"index":0,"delta":{"content":"world"}}]}"#,
+                    "data: [DONE]",
+                ]
+                .map(|line| format!("{}\n\n", line))
+                .concat();
+                ([("content-type", "text/event-stream")], body).into_response()
+            }
+        }),
+    );
+    common::serve(app).await.unwrap()
+}
+
+#[test]
+fn percentiles_use_nearest_rank() {
+    let durations = (1..=100).map(Duration::from_millis).collect();
+    let p = Percentiles::from_durations(durations);
+    assert_eq!((p.p50_ms, p.p90_ms, p.p99_ms, p.max_ms), (50, 90, 99, 100));
+    assert_eq!(Percentiles::from_durations(Vec::new()).max_ms, 0);
+}
+
+#[tokio::test]
+async fn reports_latency_throughput_and_errors() -> anyhow::Result<()> {
+    let url = spawn_server().await;
+    let test = LoadTest {
+        model: "gemma".to_string(),
+        prompt: "hi".to_string(),
+        concurrency: 2,
+        duration: Duration::from_millis(300),
+    };
+
+    let report = loadtest::run(&test, &Target::Server { url, api_key: None }).await?;
+
+    assert!(report.requests >= 8, "only {} requests", report.requests);
+    assert_eq!(report.errors, report.error_counts["HTTP 503 Service Unavailable"]);
+    assert!(report.errors > 0 && report.errors < report.requests);
+    assert!(report.latency.p50_ms >= 10);
+    assert!(report.ttft.p50_ms >= 10 && report.ttft.p50_ms <= report.latency.max_ms);
+    assert!(report.tokens_per_second > 0.0);
+    assert!(report.to_string().contains("errors"));
+    Ok(())
+}
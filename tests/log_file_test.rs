@@ -0,0 +1,36 @@
+//! Tests for size-rotated log files
+
+use litert_lm::log_file::{RotatingFile, LOG_FILE};
+use std::io::Write;
+
+fn read(dir: &std::path::Path, name: &str) -> String {
+    std::fs::read_to_string(dir.join(name)).unwrap_or_default()
+}
+
+#[test]
+fn rotates_whole_events_and_keeps_max_files() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-logs-{}", uuid::Uuid::new_v4()));
+    let mut log = RotatingFile::open(&dir, 10, 2)?;
+    assert_eq!(log.path(), dir.join(LOG_FILE));
+
+    for event in ["one\n", "two\n", "three\n", "four\n", "five\n", "six\n"] {
+        log.write_all(event.as_bytes())?;
+    }
+    log.flush()?;
+
+    // Events are never split across files, and the oldest file is gone
+    assert_eq!(read(&dir, LOG_FILE), "six\n");
+    assert_eq!(read(&dir, "litert-lm.log.1"), "four\nfive\n");
+    assert_eq!(read(&dir, "litert-lm.log.2"), "three\n");
+    assert!(!dir.join("litert-lm.log.3").exists());
+    Ok(())
+}
+
+#[test]
+fn reopening_appends_to_the_current_file() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-logs-{}", uuid::Uuid::new_v4()));
+    RotatingFile::open(&dir, 1024, 1)?.write_all(b"before restart\n")?;
+    RotatingFile::open(&dir, 1024, 1)?.write_all(b"after restart\n")?;
+    assert_eq!(read(&dir, LOG_FILE), "before restart\nafter restart\n");
+    Ok(())
+}
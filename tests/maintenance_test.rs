@@ -0,0 +1,95 @@
+//! Tests for the maintenance run (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::maintenance::{is_partial_file, prune_partial_files};
+use std::time::{Duration, SystemTime};
+
+const ID: &str = "0123456789abcdef0123456789abcdef";
+
+fn age(path: &std::path::Path, by: Duration) -> std::io::Result<()> {
+    std::fs::File::options().write(true).open(path)?.set_modified(SystemTime::now() - by)
+}
+
+#[test]
+fn stale_partial_files_are_pruned() -> anyhow::Result<()> {
+    assert!(is_partial_file(&format!("lit.partial-{}", ID)));
+    assert!(is_partial_file(&format!("model_usage.json.{}", ID)));
+    assert!(!is_partial_file("lit.linux_x86_64"));
+    assert!(!is_partial_file("model_usage.json"));
+    assert!(!is_partial_file(&format!("notes.md.{}", ID)));
+    assert!(!is_partial_file("lit.partial-1234"));
+
+    let dir = std::env::temp_dir().join(format!("litert-prune-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("adapters"))?;
+    let stale = dir.join(format!("lit.partial-{}", ID));
+    let nested = dir.join("adapters").join(format!("adapters.json.{}", ID));
+    let fresh = dir.join(format!("model_provenance.json.{}", ID));
+    let kept = dir.join("model_usage.json");
+    for path in [&stale, &nested, &fresh, &kept] {
+        std::fs::write(path, "12345")?;
+    }
+    for path in [&stale, &nested, &kept] {
+        age(path, Duration::from_secs(7200))?;
+    }
+
+    // Younger files may belong to a download still running
+    assert_eq!(prune_partial_files(&dir, Duration::from_secs(3600))?, (2, 10));
+    assert!(!stale.exists() && !nested.exists());
+    assert!(fresh.exists() && kept.exists());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn maintenance_runs_every_step_and_reports() -> anyhow::Result<()> {
+    use litert_lm::config::MaintenanceConfig;
+    use litert_lm::log_file::{RotatingFile, LOG_FILE};
+    use litert_lm::{maintenance, LitManager, ManagerEvent};
+    use std::io::Write;
+
+    // Lists the registry until the `offline` file exists
+    let dir = common::fake_lit(
+        "#!/bin/sh\n[ -e \"$(dirname \"$0\")/offline\" ] && { echo 'registry unreachable' >&2; exit 1; }\n\
+         echo 'Available models:'\n\
+         echo 'ALIAS            MODEL_ID                             SIZE'\n\
+         echo 'gemma3-1b        litert-community/Gemma3-1B-IT        1.0GB'\n\
+         echo 'qwen2.5-1.5b     litert-community/Qwen2.5-1.5B        1.6GB'\n",
+    )?;
+    let manager = LitManager::new_with_pool_size(1).await?;
+
+    // The saved listing is searched once lit can't reach the registry
+    assert_eq!(manager.refresh_registry().await?, 2);
+    std::fs::write(dir.join("offline"), "")?;
+    let found = manager.search_registry("qwen").await?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].entry.name, "qwen2.5-1.5b");
+
+    let partial = dir.join(format!("lit.partial-{}", ID));
+    std::fs::write(&partial, "half a binary")?;
+    age(&partial, Duration::from_secs(7200))?;
+    let logs = dir.join("logs");
+    let mut log = RotatingFile::open(&logs, 1024 * 1024, 2)?;
+    log.write_all(b"before\n")?;
+
+    let mut events = manager.subscribe();
+    let config = MaintenanceConfig {
+        enabled: true,
+        offline: true,
+        stale_after_secs: 3600,
+        ..MaintenanceConfig::default()
+    };
+    let summary = maintenance::run_once(&manager, &config).await;
+    assert_eq!((summary.pruned_files, summary.pruned_bytes), (1, 13));
+    assert!(summary.logs_rotated && summary.offline);
+    assert_eq!((summary.registry_models, summary.binary_update.as_ref()), (None, None));
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert!(!partial.exists());
+    assert_eq!(events.recv().await?, ManagerEvent::MaintenanceFinished { summary });
+
+    // The next log event starts a new file, however small the current one is
+    log.write_all(b"after\n")?;
+    assert_eq!(std::fs::read_to_string(logs.join(format!("{}.1", LOG_FILE)))?, "before\n");
+    assert_eq!(std::fs::read_to_string(logs.join(LOG_FILE))?, "after\n");
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! Tests for `max_tokens` (uses a stand-in lit script)
+
+mod common;
+
+use futures::StreamExt;
+use litert_lm::stream::limit_tokens;
+use litert_lm::tokenizer::Tokenizer;
+use std::sync::Arc;
+
+/// One token per word
+#[derive(Debug)]
+struct Words;
+
+impl Tokenizer for Words {
+    fn count(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+async fn limit(chunks: &[&str], max_tokens: Option<usize>) -> Vec<String> {
+    let chunks: Vec<anyhow::Result<String>> = chunks.iter().map(|chunk| Ok(chunk.to_string())).collect();
+    limit_tokens(futures::stream::iter(chunks), max_tokens, Arc::new(Words))
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn streams_end_at_max_tokens() {
+    assert_eq!(limit(&["one two", " three", " four"], Some(3)).await, ["one two", " three"]);
+    // The chunk that crosses the limit is cut to fit
+    assert_eq!(limit(&["one", " two three four"], Some(3)).await, ["one", " two three"]);
+    assert_eq!(limit(&["one two three four"], Some(1)).await, ["one"]);
+    // Shorter completions and unlimited streams pass through
+    assert_eq!(limit(&["one", " two"], Some(5)).await, ["one", " two"]);
+    assert_eq!(limit(&["one", " two"], None).await, ["one", " two"]);
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn completions_finish_with_length() -> anyhow::Result<()> {
+    use litert_lm::{ChatCompletionChunk, LitManager};
+    use std::time::{Duration, Instant};
+
+    // Echoes the prompt, then keeps going for a while, back at its prompt
+    // early on SIGINT
+    common::fake_lit(
+        "#!/bin/sh\nstop=0\ntrap 'stop=1; kill $! 2>/dev/null' INT\nprintf '>>>'\nwhile read -r line; do\n\
+         stop=0; printf '%s\\n' \"$line\"; sleep 2 & [ $stop = 0 ] || kill $!; wait $!\n\
+         [ $stop = 1 ] || printf 'late\\n'; printf '>>>'\ndone\n",
+    )?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?.with_tokenizer("gemma3-*", Arc::new(Words)));
+    let base = common::spawn_server(&manager, "gemma3-1b").await?;
+    let client = reqwest::Client::new();
+    let body = |content: &str, stream: bool| {
+        serde_json::json!({
+            "model": "gemma3-1b",
+            "messages": [{ "role": "user", "content": content }],
+            "max_tokens": 2,
+            "stream": stream
+        })
+    };
+
+    let started = Instant::now();
+    let response: serde_json::Value = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&body("one two three four", false))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert!(started.elapsed() < Duration::from_secs(2), "waited for the whole generation");
+    let content = response["choices"][0]["message"]["content"].as_str().unwrap();
+    assert_eq!(Words.count(content), 2, "{}", content);
+    assert!(!content.contains("late"), "{}", content);
+    assert_eq!(response["choices"][0]["finish_reason"], "length");
+    assert_eq!(response["usage"]["completion_tokens"], 2);
+
+    let started = Instant::now();
+    let mut events = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&body("five six seven eight", true))
+        .send()
+        .await?
+        .bytes_stream();
+    let mut sse = String::new();
+    while let Some(chunk) = events.next().await {
+        sse.push_str(&String::from_utf8_lossy(&chunk?));
+    }
+    assert!(started.elapsed() < Duration::from_secs(2), "waited for the whole generation");
+    let chunks: Vec<ChatCompletionChunk> = common::sse_events(&sse)?;
+    let text: String = chunks.iter().filter_map(|chunk| chunk.choices[0].delta.content.as_deref()).collect();
+    assert_eq!(Words.count(&text), 2, "{}", text);
+    assert_eq!(chunks.last().unwrap().choices[0].finish_reason.as_deref(), Some("length"));
+
+    // Best-of candidates are generated up front, each cut at the limit too
+    let mut best_of = body("nine ten eleven twelve", true);
+    best_of["best_of"] = serde_json::json!(2);
+    let started = Instant::now();
+    let mut events = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&best_of)
+        .send()
+        .await?
+        .bytes_stream();
+    let mut sse = String::new();
+    while let Some(chunk) = events.next().await {
+        sse.push_str(&String::from_utf8_lossy(&chunk?));
+    }
+    assert!(started.elapsed() < Duration::from_secs(2), "waited for the whole generation");
+    let chunks: Vec<ChatCompletionChunk> = common::sse_events(&sse)?;
+    let text: String = chunks.iter().filter_map(|chunk| chunk.choices[0].delta.content.as_deref()).collect();
+    assert_eq!(Words.count(&text), 2, "{}", text);
+    assert!(!text.contains("late"), "{}", text);
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! McpClientManager against an MCP server running in the test process
+
+mod common;
+
+use litert_lm::agent::{ToolCall, ToolRegistry};
+use litert_lm::config::{McpServerConfig, McpServerTransport};
+use litert_lm::mcp_client::McpClientManager;
+use rmcp::handler::server::{router::tool::ToolRouter, wrapper::Parameters};
+use rmcp::model::{CallToolResult, Content, ErrorData as McpError, ServerCapabilities, ServerInfo};
+use rmcp::transport::streamable_http_server::{session::local::LocalSessionManager, StreamableHttpService};
+use rmcp::{schemars, tool, tool_handler, tool_router, ServerHandler};
+use std::sync::Arc;
+
+#[derive(Clone)]
+struct Calculator {
+    tool_router: ToolRouter<Calculator>,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct AddRequest {
+    a: i64,
+    b: i64,
+}
+
+#[tool_router]
+impl Calculator {
+    fn new() -> Self {
+        Self {
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    #[tool(description = "Add two integers")]
+    async fn add(&self, Parameters(request): Parameters<AddRequest>) -> Result<CallToolResult, McpError> {
+        Ok(CallToolResult::success(vec![Content::text((request.a + request.b).to_string())]))
+    }
+
+    #[tool(description = "Always fails")]
+    async fn fail(&self) -> Result<CallToolResult, McpError> {
+        Ok(CallToolResult::error(vec![Content::text("division by zero")]))
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Calculator {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+async fn serve_calculator() -> anyhow::Result<String> {
+    let service = StreamableHttpService::new(
+        || Ok(Calculator::new()),
+        Arc::new(LocalSessionManager::default()),
+        Default::default(),
+    );
+    let base = common::serve(axum::Router::new().nest_service("/mcp", service)).await?;
+    Ok(format!("{}/mcp", base))
+}
+
+#[tokio::test]
+async fn remote_tools_are_listed_and_called() -> anyhow::Result<()> {
+    let url = serve_calculator().await?;
+    let servers = vec![
+        McpServerConfig {
+            name: "calc".to_string(),
+            transport: McpServerTransport::Http { url },
+        },
+        // Nothing listens here; the server is skipped rather than failing the rest
+        McpServerConfig {
+            name: "gone".to_string(),
+            transport: McpServerTransport::Http {
+                url: "http://127.0.0.1:9/mcp".to_string(),
+            },
+        },
+    ];
+    let clients = McpClientManager::connect(&servers).await;
+    assert_eq!(clients.servers(), vec!["calc"]);
+
+    let registry = ToolRegistry::new();
+    clients.register_tools(&registry);
+    assert_eq!(registry.names(), vec!["calc.add", "calc.fail"]);
+    let add = &registry.definitions()[0];
+    assert_eq!(add.description, "Add two integers");
+    assert!(add.parameters["properties"]["a"].is_object());
+
+    let sum = registry
+        .call(&ToolCall {
+            name: "calc.add".to_string(),
+            arguments: serde_json::json!({"a": 2, "b": 3}),
+        })
+        .await?;
+    assert_eq!(sum, "5");
+
+    // Tool errors reported by the server become errors of the call
+    let error = registry
+        .call(&ToolCall {
+            name: "calc.fail".to_string(),
+            arguments: serde_json::Value::Null,
+        })
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("division by zero"));
+
+    let error = registry
+        .call(&ToolCall {
+            name: "calc.add".to_string(),
+            arguments: serde_json::json!([2, 3]),
+        })
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("must be an object"));
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Tests for the crate-wide metrics registry (no lit binary required)
+
+use litert_lm::Metrics;
+
+#[test]
+fn snapshot_reflects_recorded_events() {
+    let metrics = Metrics::new();
+
+    metrics.record_http_request("chat_completions");
+    metrics.record_http_request("chat_completions");
+    metrics.record_http_error("chat_completions");
+    metrics
+        .process_spawns
+        .with_label_values(&["gemma-3n-E4B", "gpu", "error"])
+        .inc();
+    metrics
+        .process_spawns
+        .with_label_values(&["gemma-3n-E4B", "cpu", "success"])
+        .inc();
+    metrics.gpu_fallbacks.with_label_values(&["gemma-3n-E4B"]).inc();
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.http_requests_total, 2);
+    assert_eq!(snapshot.http_errors_total, 1);
+    assert_eq!(snapshot.recent_errors, 1);
+    assert_eq!(snapshot.process_spawns_total, 1);
+    assert_eq!(snapshot.process_spawn_failures_total, 1);
+    assert_eq!(snapshot.gpu_fallbacks_total, 1);
+}
+
+#[test]
+fn stats_from_older_servers_have_no_gpu_fallbacks() {
+    let stats: litert_lm::manager::ManagerStats = serde_json::from_value(serde_json::json!({
+        "pool_size": 2,
+        "pools": [{ "model": "gemma-3n-E4B", "processes": 2, "queue_depth": 0 }],
+        "metrics": {
+            "uptime_secs": 10, "http_requests_total": 0, "http_errors_total": 0,
+            "recent_errors": 0, "recent_error_window_secs": 300,
+            "binary_downloads_total": 0, "model_pulls_total": 0, "model_pull_failures_total": 0,
+            "process_spawns_total": 2, "process_spawn_failures_total": 0,
+            "process_exits_total": 0, "completions_total": 0
+        }
+    }))
+    .unwrap();
+    assert_eq!(stats.pools[0].gpu_fallbacks, 0);
+    assert_eq!(stats.metrics.gpu_fallbacks_total, 0);
+}
+
+#[test]
+fn render_uses_prometheus_text_format() {
+    let metrics = Metrics::new();
+    metrics
+        .model_pulls
+        .with_label_values(&["gemma-3n-E4B", "success"])
+        .inc();
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE litert_model_pulls_total counter"));
+    assert!(text.contains("litert_model_pulls_total{model=\"gemma-3n-E4B\",result=\"success\"} 1"));
+}
+
+#[test]
+fn generation_latency_is_labeled_by_backend() {
+    use litert_lm::GenerationTiming;
+    use std::time::Duration;
+
+    let metrics = Metrics::new();
+    let timing = GenerationTiming {
+        time_to_first_token: Some(Duration::from_millis(200)),
+        total: Duration::from_secs(2),
+    };
+    metrics.record_generation("gemma-3n-E4B", "gpu", &timing, 40);
+    metrics.record_generation("gemma-3n-E4B", "cpu", &GenerationTiming::default(), 0);
+
+    let text = metrics.render();
+    assert!(text.contains(
+        "litert_time_to_first_token_seconds_count{backend=\"gpu\",model=\"gemma-3n-E4B\"} 1"
+    ));
+    assert!(text.contains("litert_tokens_per_second_sum{backend=\"gpu\",model=\"gemma-3n-E4B\"} 20"));
+    // Nothing was generated, so there is no latency to record
+    assert!(!text.contains("backend=\"cpu\""));
+}
@@ -0,0 +1,95 @@
+//! Tests for the scripted mock backend (requires the `mock` feature)
+
+use futures::StreamExt;
+use litert_lm::mock::{MockBackend, MockManager};
+use litert_lm::{Constraint, GenOptions, InferenceBackend};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn scripted_responses_are_replayed_in_order() -> anyhow::Result<()> {
+    let backend = MockBackend::new()
+        .respond("Paris is the capital.")
+        .fail("model crashed")
+        .fallback("I don't know.");
+
+    let mut stream = backend.complete_stream("gemma-3n-E4B", "Capital of France?").await?;
+    let mut chunks = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        chunks.push(chunk?);
+    }
+    assert_eq!(chunks, ["Paris ", "is ", "the ", "capital."]);
+
+    let error = backend.complete("gemma-3n-E4B", "Again?").await.unwrap_err();
+    assert_eq!(error.to_string(), "model crashed");
+    assert_eq!(backend.complete("gemma-3n-E2B", "And now?").await?, "I don't know.");
+
+    let calls = backend.calls();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[2].model, "gemma-3n-E2B");
+    assert_eq!(calls[2].prompt, "And now?");
+    Ok(())
+}
+
+#[tokio::test]
+async fn latency_is_configurable() -> anyhow::Result<()> {
+    let backend = MockBackend::new()
+        .fallback("one two three")
+        .first_token_delay(Duration::from_millis(50))
+        .token_delay(Duration::from_millis(20));
+
+    let started = Instant::now();
+    assert_eq!(backend.complete("m", "p").await?, "one two three");
+    assert!(started.elapsed() >= Duration::from_millis(90));
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_manager_applies_generation_options() -> anyhow::Result<()> {
+    let manager = MockManager::new(MockBackend::new().respond("maybe").respond("yes"));
+    let options = GenOptions {
+        constraint: Some(Constraint::Choice {
+            options: vec!["yes".to_string(), "no".to_string()],
+        }),
+        ..Default::default()
+    };
+
+    assert_eq!(manager.run_completion_with("m", "Is water wet?", &options).await?, "yes");
+    assert_eq!(manager.backend().calls().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn streams_stop_at_stop_sequences_and_time_out() -> anyhow::Result<()> {
+    use litert_lm::LitError;
+
+    let manager = MockManager::new(
+        MockBackend::new()
+            .respond("The answer is 42. Anything else?")
+            .fallback("one two three four")
+            .token_delay(Duration::from_millis(100)),
+    );
+    let options = GenOptions {
+        stop: vec![".".to_string()],
+        ..Default::default()
+    };
+    let text: Vec<String> = manager
+        .run_completion_stream_with("m", "p", &options)
+        .await?
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+    assert_eq!(text.concat(), "The answer is 42");
+
+    // The deadline covers the whole reply, which ends with the timeout
+    let options = GenOptions {
+        timeout: Some(Duration::from_millis(150)),
+        ..Default::default()
+    };
+    let mut stream = manager.run_completion_stream_with("m", "p", &options).await?;
+    assert_eq!(stream.next().await.transpose()?.as_deref(), Some("one "));
+    assert_eq!(stream.next().await.transpose()?.as_deref(), Some("two "));
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(LitError::of(&error), Some(LitError::Timeout { .. })), "{:#}", error);
+    assert!(stream.next().await.is_none());
+    Ok(())
+}
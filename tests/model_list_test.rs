@@ -0,0 +1,83 @@
+//! Tests for the structured model listing (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::registry::parse_listing;
+use litert_lm::ModelInfo;
+
+#[test]
+fn listing_rows_become_model_infos() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-model-info-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("gemma3-1b"))?;
+    std::fs::write(dir.join("gemma3-1b/model.litertlm"), vec![0u8; 1234])?;
+    let listing = format!(
+        "Downloaded models:\n\
+         ALIAS        MODEL_ID                        SIZE    PATH\n\
+         gemma3-1b    litert-community/Gemma3-1B-IT   1.0GB   {}\n\
+         qwen2.5-1.5b 1.5B  1.6GB\n",
+        dir.join("gemma3-1b").display()
+    );
+    let entries = parse_listing(&listing);
+
+    // On disk, the files' size is taken over the listed one
+    let gemma = ModelInfo::from_entry(&entries[0], true);
+    assert_eq!(gemma.alias, "gemma3-1b");
+    assert_eq!(gemma.name, "litert-community/Gemma3-1B-IT");
+    assert_eq!(gemma.size_bytes, Some(1234));
+    assert_eq!(gemma.path.as_deref(), Some(dir.join("gemma3-1b").as_path()));
+
+    // Without a model id the alias names the model
+    let qwen = ModelInfo::from_entry(&entries[1], false);
+    assert_eq!((qwen.alias.as_str(), qwen.name.as_str()), ("qwen2.5-1.5b", "qwen2.5-1.5b"));
+    assert_eq!((qwen.size_bytes, qwen.downloaded, qwen.path), (Some(1_600_000_000), false, None));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn downloaded_models_are_listed_before_the_registry() -> anyhow::Result<()> {
+    use litert_lm::config::TenantConfig;
+    use litert_lm::{Config, LitManager, Tenant};
+
+    let dir = common::temp_dir()?;
+    let lit = common::write_script(
+        &dir.join("lit"),
+        "#!/bin/sh\n\
+         echo 'ALIAS            MODEL_ID                             SIZE'\n\
+         echo 'gemma3-1b        litert-community/Gemma3-1B-IT        1.0GB'\n\
+         [ \"$2\" = --show_all ] || exit 0\n\
+         echo 'qwen2.5-1.5b     litert-community/Qwen2.5-1.5B        1.6GB'\n\
+         echo 'team-a/tiny      litert-community/Tiny                0.1GB'\n",
+    )?;
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(dir.join("cache"))
+        .binary_path(&lit)
+        .build()
+        .await?;
+
+    let downloaded = manager.list_models_structured(false).await?;
+    assert_eq!(downloaded.len(), 1);
+    assert_eq!((downloaded[0].alias.as_str(), downloaded[0].downloaded), ("gemma3-1b", true));
+
+    let all = manager.list_models_structured(true).await?;
+    let listed: Vec<(&str, bool)> = all.iter().map(|model| (model.alias.as_str(), model.downloaded)).collect();
+    assert_eq!(listed, [("gemma3-1b", true), ("qwen2.5-1.5b", false), ("team-a/tiny", false)]);
+
+    // A tenant only sees its namespace
+    let tenant = manager.for_tenant(Tenant::from_config(&TenantConfig {
+        name: "team-a".to_string(),
+        api_keys: vec![],
+        models: vec!["team-a/*".to_string()],
+        manage_models: false,
+        weight: 1,
+    }));
+    let visible = tenant.list_models_structured(true).await?;
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].name, "litert-community/Tiny");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
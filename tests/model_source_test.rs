@@ -0,0 +1,93 @@
+//! Tests for model sources (uses a stand-in lit script that records its arguments)
+
+mod common;
+
+use litert_lm::source::{self, FileServer, GcsSource, ModelSources, S3Source};
+use litert_lm::{ModelSource, PullTarget};
+
+#[test]
+fn sources_are_picked_by_scheme() -> anyhow::Result<()> {
+    let sources = ModelSources::default();
+    assert_eq!(sources.select("gemma3-1b")?.name(), "registry");
+    assert_eq!(sources.select("https://example.com/m.litertlm")?.name(), "url");
+    assert_eq!(sources.select("S3://bucket/m.litertlm")?.name(), "s3");
+    assert_eq!(sources.select("gs://bucket/m.litertlm")?.name(), "gcs");
+    assert_eq!(sources.select("file:///models/m.litertlm")?.name(), "local");
+    assert_eq!(sources.select(file!())?.name(), "local");
+    let unknown = sources.select("ftp://host/m.litertlm").err().unwrap();
+    assert_eq!(unknown.to_string(), "No model source for ftp:// URLs");
+    Ok(())
+}
+
+#[test]
+fn default_aliases_come_from_file_names() {
+    assert_eq!(source::default_alias("s3://bucket/llm/Gemma3-1B-IT.litertlm"), Some("gemma3-1b-it".to_string()));
+    assert_eq!(source::default_alias("https://host/m/qwen.litertlm?X-Amz-Signature=abc"), Some("qwen".to_string()));
+    assert_eq!(source::default_alias("gemma3-1b"), None);
+}
+
+#[tokio::test]
+async fn object_urls_resolve_to_https() -> anyhow::Result<()> {
+    let s3 = S3Source::new().with_endpoint("http://minio:9000/").without_presigning();
+    assert_eq!(
+        s3.resolve("s3://models/llm/gemma 3.litertlm").await?,
+        PullTarget::Lit("http://minio:9000/models/llm/gemma%203.litertlm".to_string())
+    );
+    assert!(s3.resolve("s3://models").await.is_err());
+
+    let gcs = GcsSource::new().with_endpoint("https://storage.googleapis.com").without_presigning();
+    assert_eq!(
+        gcs.resolve("gs://models/gemma.litertlm").await?,
+        PullTarget::Lit("https://storage.googleapis.com/models/gemma.litertlm".to_string())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn local_files_are_served_until_dropped() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("litert-source-{}.litertlm", uuid::Uuid::new_v4()));
+    std::fs::write(&path, b"weights")?;
+
+    let server = FileServer::start(path.clone()).await?;
+    assert!(server.url().starts_with("http://127.0.0.1:"));
+    let body = reqwest::get(server.url()).await?.error_for_status()?.bytes().await?;
+    assert_eq!(&body[..], b"weights");
+
+    let url = server.url().to_string();
+    drop(server);
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(reqwest::get(&url).await.is_err());
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn pulls_go_through_the_model_source() -> anyhow::Result<()> {
+    use litert_lm::LitManager;
+    use std::sync::Arc;
+
+    // Records the arguments of every command
+    let dir = common::fake_lit("#!/bin/sh\necho \"$@\" >> \"$(dirname \"$0\")/calls\"\n")?;
+    let calls = dir.join("calls");
+    // lit is handed the object URLs rather than downloads of them
+    let manager = LitManager::new().await?.with_resumable_downloads(false).with_model_source(Arc::new(
+        S3Source::new().with_endpoint("http://minio:9000").without_presigning(),
+    ));
+
+    manager.pull_quiet("s3://models/Gemma3-1B.litertlm", None, None).await?;
+    manager.pull_quiet("s3://models/Gemma3-1B.litertlm", Some("prod"), None).await?;
+    manager.pull_quiet("gemma3-1b", None, None).await?;
+    let model_file = dir.join("local.litertlm");
+    std::fs::write(&model_file, b"weights")?;
+    manager.pull_quiet(model_file.to_str().unwrap(), None, None).await?;
+
+    let calls = std::fs::read_to_string(&calls)?;
+    // Each pull is followed by a `list` for the checksum
+    let calls: Vec<&str> = calls.lines().filter(|call| call.starts_with("pull ")).collect();
+    assert_eq!(calls[0], "pull http://minio:9000/models/Gemma3-1B.litertlm --alias gemma3-1b");
+    assert_eq!(calls[1], "pull http://minio:9000/models/Gemma3-1B.litertlm --alias prod");
+    assert_eq!(calls[2], "pull gemma3-1b");
+    assert!(calls[3].starts_with("pull http://127.0.0.1:"), "{}", calls[3]);
+    assert!(calls[3].ends_with("/local.litertlm --alias local"), "{}", calls[3]);
+    Ok(())
+}
@@ -0,0 +1,33 @@
+//! Tests for local moderation output handling (no lit binary required)
+
+use litert_lm::moderation::{self, CATEGORIES};
+
+#[test]
+fn scores_are_read_clamped_and_flagged() -> anyhow::Result<()> {
+    let output = "```json\n{\"violence\": 0.92, \"hate\": 0.1, \"sexual\": 3, \"harassment\": \"high\"}\n```";
+    let result = moderation::parse_scores(output)?;
+
+    assert!(result.flagged);
+    assert_eq!(result.category_scores.len(), CATEGORIES.len());
+    assert_eq!(result.category_scores["violence"], 0.92);
+    assert!(result.categories["violence"]);
+    assert!(!result.categories["hate"]);
+    assert_eq!(result.category_scores["sexual"], 1.0);
+    // Missing or non-numeric scores count as 0
+    assert_eq!(result.category_scores["harassment"], 0.0);
+    assert_eq!(result.category_scores["self-harm"], 0.0);
+
+    let clean = moderation::parse_scores("{}")?;
+    assert!(!clean.flagged);
+    assert!(moderation::parse_scores("[0.5]").is_err());
+    assert!(moderation::parse_scores("not json").is_err());
+    Ok(())
+}
+
+#[test]
+fn prompt_lists_every_category_and_quotes_the_input() {
+    let prompt = moderation::classification_prompt("say \"\"\" hi");
+    assert!(CATEGORIES.iter().all(|category| prompt.contains(category)));
+    // The input can't close the quoted block early
+    assert_eq!(prompt.matches("\"\"\"").count(), 2);
+}
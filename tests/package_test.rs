@@ -0,0 +1,89 @@
+//! Tests for exporting and importing model packages (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::package::{self, MANIFEST_FILE, MODEL_FILE};
+
+#[tokio::test]
+async fn packages_hold_the_model_and_a_manifest() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-package-{}", uuid::Uuid::new_v4()));
+    let model_dir = dir.join("gemma3-1b");
+    std::fs::create_dir_all(&model_dir)?;
+    std::fs::write(model_dir.join("Gemma3-1B-IT.litertlm"), b"weights")?;
+
+    // A model directory stands for its one model file
+    let file = package::model_file(&model_dir)?;
+    assert_eq!(file, model_dir.join("Gemma3-1B-IT.litertlm"));
+
+    let info = litert_lm::ModelInfo {
+        alias: "gemma3-1b".to_string(),
+        name: "litert-community/Gemma3-1B-IT".to_string(),
+        size_bytes: Some(7),
+        downloaded: true,
+        path: Some(model_dir.clone()),
+    };
+    let dest = dir.join("package");
+    let written = package::write(&file, &info, &dest).await?;
+    assert_eq!((written.alias.as_str(), written.size_bytes), ("gemma3-1b", 7));
+    let (read, model) = package::read(&dest)?;
+    assert_eq!(read, written);
+    assert_eq!(std::fs::read(model)?, b"weights");
+
+    // A package is never written over, and a cut-off copy is refused
+    assert!(package::write(&file, &info, &dest).await.is_err());
+    std::fs::write(dest.join(MODEL_FILE), b"wei")?;
+    let error = package::read(&dest).unwrap_err().to_string();
+    assert!(error.contains("incomplete or damaged"), "{}", error);
+    std::fs::remove_file(dest.join(MANIFEST_FILE))?;
+    assert!(package::read(&dest).is_err());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn exported_models_are_pulled_back_on_import() -> anyhow::Result<()> {
+    use litert_lm::{Config, LitError, LitManager};
+
+    let dir = std::env::temp_dir().join(format!("litert-export-{}", uuid::Uuid::new_v4()));
+    let models = dir.join("models");
+    std::fs::create_dir_all(models.join("gemma3-1b"))?;
+    std::fs::write(models.join("gemma3-1b/model.litertlm"), b"weights")?;
+
+    // Lists one model with its path and records every other call
+    let calls = dir.join("calls");
+    let lit = common::write_script(&dir.join("lit"), &format!(
+            "#!/bin/sh\n\
+             if [ \"$1\" = list ]; then\n\
+             echo 'ALIAS        MODEL_ID                        SIZE   PATH'\n\
+             echo 'gemma3-1b    litert-community/Gemma3-1B-IT   1.0GB  {}'\n\
+             exit 0\n\
+             fi\n\
+             echo \"$@\" >> {}\n",
+            models.join("gemma3-1b").display(),
+            calls.display()
+        ))?;
+    let manager = LitManager::builder()
+        .config(Config::default())
+        .cache_dir(dir.join("cache"))
+        .binary_path(&lit)
+        .build()
+        .await?;
+
+    let dest = dir.join("export");
+    let manifest = manager.export("gemma3-1b", &dest).await?;
+    assert_eq!(manifest.name, "litert-community/Gemma3-1B-IT");
+    assert_eq!(std::fs::read(dest.join(MODEL_FILE))?, b"weights");
+
+    let missing = manager.export("qwen2.5-1.5b", &dir.join("other")).await.unwrap_err();
+    assert!(matches!(LitError::of(&missing), Some(LitError::ModelNotFound { .. })), "{:#}", missing);
+
+    manager.import(&dest, Some("gemma3-1b-copy")).await?;
+    let calls = std::fs::read_to_string(&calls)?;
+    assert!(calls.starts_with("pull http://127.0.0.1:"), "{}", calls);
+    assert!(calls.trim_end().ends_with("/model.litertlm --alias gemma3-1b-copy"), "{}", calls);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,409 @@
+//! Tests for process pool startup (no lit binary required)
+
+mod common;
+
+use litert_lm::ProcessPool;
+
+#[tokio::test]
+async fn initialize_fails_without_spawning_processes() {
+    let dir = std::env::temp_dir().join(format!("litert-pool-{}", uuid::Uuid::new_v4()));
+    let mut pool = ProcessPool::new(dir.join("missing-lit"), "gemma-3n-E4B".to_string(), 3);
+
+    let error = pool.initialize().await.unwrap_err();
+    assert!(format!("{:#}", error).contains("Failed to spawn lit process"));
+    assert_eq!(pool.size(), 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn initialize_starts_every_process_in_order() -> anyhow::Result<()> {
+    // A stand-in for `lit run` that loads slowly, then echoes each prompt
+    let lit = common::script(
+        "#!/bin/sh\nsleep 1\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n",
+    )?;
+
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 3);
+    pool.initialize().await?;
+    assert_eq!(pool.size(), 3);
+
+    let indexes: Vec<usize> = pool.debug_info().iter().map(|process| process.index).collect();
+    assert_eq!(indexes, [0, 1, 2]);
+    assert!(pool.debug_info().iter().all(|process| process.backend == "gpu"));
+    assert_eq!(pool.send_prompt("hello").await?.trim(), "hello");
+    pool.warm_up().await?;
+
+    // A pool is an InferenceBackend for its own model
+    use litert_lm::InferenceBackend;
+    assert_eq!(pool.complete("gemma-3n-E4B", "hi").await?.trim(), "hi");
+    assert!(pool.complete("qwen2.5-1.5b", "hi").await.is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pools_start_lit_on_their_backend() -> anyhow::Result<()> {
+    use litert_lm::Backend;
+
+    // Fails to start on the GPU, and answers with the backend it runs on
+    let lit = common::script(
+        "#!/bin/sh\n[ \"$1\" = list ] && { printf 'ALIAS  MODEL_ID  SIZE\\ngemma-3n-E4B  google/gemma-3n-E4B  4.0GB\\n'; exit; }\n\
+         backend=$4\n[ \"$backend\" = gpu ] && { echo 'Error: no GPU'; exit 1; }\n\
+         printf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$backend\"; done\n",
+    )?;
+
+    let mut npu = ProcessPool::new(lit.clone(), "gemma-3n-E4B".to_string(), 1).with_backend(Backend::Npu);
+    npu.initialize().await?;
+    assert_eq!(npu.send_prompt("hi").await?.trim(), "npu");
+    assert_eq!(npu.backends(), ["npu"]);
+    npu.shutdown().await?;
+
+    // No falling back to the CPU
+    let mut gpu = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 1).with_backend(Backend::Gpu);
+    let error = gpu.initialize().await.unwrap_err();
+    assert!(format!("{:#}", error).contains("Error: no GPU"), "{:#}", error);
+    assert_eq!(gpu.size(), 0);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn processes_that_fail_to_load_on_the_gpu_fall_back_to_the_cpu() -> anyhow::Result<()> {
+    use litert_lm::Metrics;
+    use std::sync::Arc;
+
+    // Starts on the GPU but fails once it loads the model there, and answers
+    // with the backend it runs on
+    let lit = common::script(
+        "#!/bin/sh\n[ \"$1\" = list ] && { printf 'ALIAS  MODEL_ID  SIZE\\ngemma-3n-E4B  google/gemma-3n-E4B  4.0GB\\n'; exit; }\n\
+         backend=$4\nsleep 0.2\n[ \"$backend\" = gpu ] && { echo 'Error: failed to create GPU delegate'; exit 1; }\n\
+         printf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$backend\"; done\n",
+    )?;
+
+    let metrics = Arc::new(Metrics::new());
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 2).with_metrics(metrics.clone());
+    pool.initialize().await?;
+    assert_eq!(pool.send_prompt("hi").await?.trim(), "cpu");
+    assert_eq!(pool.backends(), ["cpu", "cpu"]);
+    assert_eq!(pool.gpu_fallbacks(), 2);
+    let info = pool.debug_info();
+    assert!(info[0].gpu_fallback.as_deref().is_some_and(|reason| reason.contains("failed to create GPU delegate")), "{:?}", info);
+    assert_eq!(metrics.gpu_fallbacks.with_label_values(&["gemma-3n-E4B"]).get(), 2);
+    pool.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn only_auto_backends_fall_back_to_the_cpu() -> anyhow::Result<()> {
+    use litert_lm::LitManager;
+
+    // Has both models, fails to load either on the GPU, and answers with the
+    // backend it runs on
+    let dir = common::fake_lit(
+        "#!/bin/sh\n[ \"$1\" = list ] && { printf 'ALIAS  MODEL_ID  SIZE\\ngemma3-1b  google/gemma3-1b  1.0GB\\n\\
+         gemma-3n-E4B  google/gemma-3n-E4B  4.0GB\\n'; exit; }\n\
+         backend=$4\n[ \"$backend\" = gpu ] && { echo 'Error: failed to create GPU delegate'; exit 1; }\n\
+         printf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$backend\"; done\n",
+    )?;
+    common::write_config(
+        &dir,
+        serde_json::json!({
+            "backends": { "gemma3-*": "auto", "gemma-3n-*": "gpu" },
+            "circuit_breaker": { "enabled": false }
+        }),
+    )?;
+    let manager = LitManager::new_with_pool_size(1).await?;
+
+    assert_eq!(manager.run_completion("gemma3-1b", "hi").await?.trim(), "cpu");
+    let loaded = manager.loaded_models().await;
+    assert_eq!(loaded[0].backends, ["cpu"]);
+    assert_eq!(loaded[0].gpu_fallbacks, 1);
+
+    let error = manager.run_completion("gemma-3n-E4B", "hi").await.unwrap_err();
+    assert!(format!("{:#}", error).contains("failed to create GPU delegate"), "{:#}", error);
+    assert_eq!(manager.loaded_models().await.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn backends_are_picked_by_the_most_specific_pattern() {
+    use litert_lm::Backend;
+    use std::collections::HashMap;
+
+    let config: HashMap<String, Backend> =
+        serde_json::from_str(r#"{"gemma-*": "cpu", "gemma-3n-*": "npu", "qwen": "gpu"}"#).unwrap();
+    assert_eq!(Backend::for_model(&config, "gemma-3n-E4B"), Backend::Npu);
+    assert_eq!(Backend::for_model(&config, "gemma3-1b"), Backend::Auto);
+    assert_eq!(Backend::for_model(&config, "gemma-2b"), Backend::Cpu);
+    assert_eq!(Backend::for_model(&config, "qwen"), Backend::Gpu);
+    assert_eq!("npu".parse::<Backend>(), Ok(Backend::Npu));
+    assert!("tpu".parse::<Backend>().is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn processes_start_within_their_limits() -> anyhow::Result<()> {
+    use litert_lm::{Limits, ResourceLimits};
+    use std::sync::Arc;
+
+    // Answers with its niceness, address space limit (KiB), and CPUs
+    let lit = common::script(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n\
+         printf '%s %s %s\\n>>>' \"$(nice)\" \"$(ulimit -v)\" \"$(grep Cpus_allowed_list /proc/$$/status | cut -f2)\"\n\
+         done\n",
+    )?;
+
+    let limits = ResourceLimits { memory_mb: Some(4096), nice: Some(5), cpus: Some(vec![0]) };
+    limits.check()?;
+    let limits = Arc::new(Limits::new(limits, None));
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 1).with_limits(limits);
+    pool.initialize().await?;
+    assert_eq!(pool.send_prompt("limits?").await?.trim(), "5 4194304 0");
+    pool.shutdown().await?;
+    Ok(())
+}
+
+#[test]
+fn limits_are_picked_by_the_most_specific_pattern() {
+    use litert_lm::config::LimitsConfig;
+    use litert_lm::ResourceLimits;
+
+    let config: LimitsConfig = serde_json::from_str(
+        r#"{"models": {"gemma-*": {"nice": 10}, "gemma-3n-*": {"memory_mb": 2048}}, "default": {"nice": 19}}"#,
+    )
+    .unwrap();
+    assert_eq!(ResourceLimits::for_model(&config, "gemma-3n-E4B").memory_mb, Some(2048));
+    assert_eq!(ResourceLimits::for_model(&config, "gemma-3n-E4B").nice, None);
+    assert_eq!(ResourceLimits::for_model(&config, "gemma-2b").nice, Some(10));
+    assert_eq!(ResourceLimits::for_model(&config, "qwen").nice, Some(19));
+    assert!(ResourceLimits::for_model(&LimitsConfig::default(), "qwen").is_empty());
+    assert!(ResourceLimits { nice: Some(20), ..Default::default() }.check().is_err());
+    assert!(ResourceLimits { memory_mb: Some(0), ..Default::default() }.check().is_err());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn conversations_go_back_to_the_process_holding_them() -> anyhow::Result<()> {
+    use futures::TryStreamExt;
+    use litert_lm::context::Turn;
+
+    // Echoes each prompt
+    let lit = common::script("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 2);
+    pool.initialize().await?;
+
+    // The first turn is sent whole, to whichever process is free
+    let mut turns = vec![Turn::new("user", "hi")];
+    let first = pool.place("chat", &turns).await?;
+    assert_eq!(first.held, 0);
+    let reply = first.process.send_prompt("hi").await?;
+    turns.push(Turn::new("assistant", reply.trim()));
+    pool.remember("chat", &first, &turns);
+
+    // The next one goes back to the same process, which holds both turns
+    turns.push(Turn::new("user", "and then?"));
+    let second = pool.place("chat", &turns).await?;
+    assert!(std::sync::Arc::ptr_eq(&first.process, &second.process));
+    assert_eq!(second.held, 2);
+
+    // A history the process doesn't hold is replayed
+    let edited = vec![Turn::new("user", "hello"), Turn::new("assistant", "hi"), Turn::new("user", "and then?")];
+    assert_eq!(pool.place("chat", &edited).await?.held, 0);
+    assert_eq!(pool.place("other", &turns).await?.held, 0);
+
+    // Only the new turn is sent to it
+    let reply: String = second.send_stream("and then?", "hi / hi / and then?").await?.try_collect().await?;
+    assert_eq!(reply.trim(), "and then?");
+    turns.push(Turn::new("assistant", reply.trim()));
+    pool.remember("chat", &second, &turns);
+
+    // A history whose process served someone else in the meantime is replayed,
+    // even when the turn was placed before that
+    turns.push(Turn::new("user", "more?"));
+    let third = pool.place("chat", &turns).await?;
+    assert_eq!(third.held, 4);
+    third.process.send_prompt("unrelated").await?;
+    assert_eq!(pool.place("chat", &turns).await?.held, 0);
+    let reply: String = third.send_stream("more?", "hi / hi / and then? / and then? / more?").await?.try_collect().await?;
+    assert_eq!(reply.trim(), "hi / hi / and then? / and then? / more?");
+    pool.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn dead_processes_are_respawned() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    // Echoes each prompt, and exits when told to die
+    let lit = common::script(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         die) exit 1 ;;\n\
+         *) printf '%s\\n>>>' \"$line\" ;;\n esac\ndone\n",
+    )?;
+
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 1);
+    pool.initialize().await?;
+    let first = pool.debug_info()[0].pid;
+    assert!(pool.send_prompt("die").await.is_err());
+
+    // Replaced on the next health check
+    let respawned = tokio::time::timeout(Duration::from_secs(15), async {
+        loop {
+            let process = &pool.debug_info()[0];
+            if process.pid != first && process.state != "exited" {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await;
+    assert!(respawned.is_ok(), "{:?}", pool.debug_info());
+    assert_eq!(pool.send_prompt("hello").await?.trim(), "hello");
+    pool.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn errors_of_dead_processes_quote_their_stderr() -> anyhow::Result<()> {
+    use litert_lm::LitError;
+
+    // Complains on stderr, and says why before exiting when told to die
+    let lit = common::script(
+        "#!/bin/sh\necho 'warning: running on a slow path' >&2\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         die) echo 'fatal: out of memory' >&2; exit 1 ;;\n\
+         *) printf '%s\\n>>>' \"$line\" ;;\n esac\ndone\n",
+    )?;
+
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 1);
+    pool.initialize().await?;
+    assert_eq!(pool.send_prompt("hello").await?.trim(), "hello");
+
+    let error = pool.send_prompt("die").await.unwrap_err();
+    let Some(LitError::ProcessCrashed { stderr }) = LitError::of(&error) else {
+        panic!("not a crash: {:#}", error);
+    };
+    assert_eq!(stderr.last().map(String::as_str), Some("fatal: out of memory"));
+    assert!(error.to_string().contains("lit process died: "), "{}", error);
+
+    let diagnostics = pool.diagnostics();
+    assert_eq!(diagnostics[0].stderr, ["warning: running on a slow path", "fatal: out of memory"]);
+    pool.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn models_lit_does_not_have_fail_as_not_found() -> anyhow::Result<()> {
+    use litert_lm::LitError;
+
+    // Has only gemma3-1b, and gives up on any model
+    let lit = common::script(
+        "#!/bin/sh\ncase \"$1\" in\n list) printf 'ALIAS  MODEL_ID  SIZE\\ngemma3-1b  google/gemma3-1b  1.0GB\\n' ;;\n\
+         *) echo 'Error: failed to load'; exit 1 ;;\nesac\n",
+    )?;
+
+    let mut pool = ProcessPool::new(lit.clone(), "gemma-3n-E4B".to_string(), 1);
+    let error = pool.initialize().await.unwrap_err();
+    assert_eq!(
+        LitError::of(&error),
+        Some(&LitError::ModelNotFound { model: "gemma-3n-E4B".to_string() }),
+        "{:#}",
+        error
+    );
+
+    // A downloaded model that fails to load is not missing, whatever lit says
+    let mut pool = ProcessPool::new(lit, "gemma3-1b".to_string(), 1);
+    let error = pool.initialize().await.unwrap_err();
+    assert_eq!(LitError::of(&error), None, "{:#}", error);
+    assert!(format!("{:#}", error).contains("Error: failed to load"), "{:#}", error);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn spent_processes_are_replaced_without_failing_their_prompts() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    // Echoes each prompt, taking its time over slow ones
+    let lit = common::script(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         slow) sleep 1; printf 'slow\\n>>>' ;;\n\
+         *) printf '%s\\n>>>' \"$line\" ;;\n esac\ndone\n",
+    )?;
+
+    let mut pool = ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 1).with_max_requests_per_process(Some(2));
+    pool.initialize().await?;
+    let first = pool.debug_info()[0].pid;
+    assert_eq!(pool.send_prompt("one").await?.trim(), "one");
+
+    // The second prompt spends the process while a third is queued behind it
+    let old = pool.get_process().await?;
+    let (slow, queued) = tokio::join!(old.send_prompt("slow"), async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        old.send_prompt("three").await
+    });
+    assert_eq!(slow?.trim(), "slow");
+    assert_eq!(queued?.trim(), "three");
+
+    let replaced = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let process = &pool.debug_info()[0];
+            if process.pid != first && process.state == "idle" {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    assert!(replaced.is_ok(), "{:?}", pool.debug_info());
+    assert_eq!(pool.send_prompt("four").await?.trim(), "four");
+    assert_eq!(pool.debug_info()[0].served, 1);
+
+    // The old process stops once it has answered everything it was sent
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while old.is_alive() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await?;
+    assert_eq!(old.served(), 3);
+    pool.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn stalled_generations_fail_and_the_process_is_respawned() -> anyhow::Result<()> {
+    use litert_lm::LitError;
+    use std::time::Duration;
+
+    // Starts replying, then goes quiet without printing the prompt
+    let lit = common::script(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         stall) printf 'thinking'; sleep 30 ;;\n\
+         *) printf '%s\\n>>>' \"$line\" ;;\n esac\ndone\n",
+    )?;
+
+    let mut pool =
+        ProcessPool::new(lit, "gemma-3n-E4B".to_string(), 1).with_stall_timeout(Some(Duration::from_secs(1)));
+    pool.initialize().await?;
+    let first = pool.debug_info()[0].pid;
+
+    let started = std::time::Instant::now();
+    let error = pool.send_prompt("stall").await.unwrap_err();
+    assert!(matches!(LitError::of(&error), Some(LitError::Stalled { .. })), "{:#}", error);
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    tokio::time::timeout(Duration::from_secs(15), async {
+        while pool.debug_info()[0].pid == first || pool.debug_info()[0].state == "exited" {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await?;
+    assert_eq!(pool.send_prompt("hello").await?.trim(), "hello");
+    pool.shutdown().await?;
+    Ok(())
+}
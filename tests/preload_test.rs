@@ -0,0 +1,59 @@
+//! Tests for preloading several models (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::{LitManager, Phase, Progress};
+use std::sync::{Arc, Mutex};
+
+#[tokio::test]
+async fn preload_starts_every_pool_once() -> anyhow::Result<()> {
+    // Loads any model after a second, then echoes each prompt
+    common::fake_lit(
+        "#!/bin/sh\nsleep 1\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n",
+    )?;
+
+    let reports: Arc<Mutex<Vec<Progress>>> = Arc::default();
+    let recorded = reports.clone();
+    let manager = LitManager::new_with_pool_size(1)
+        .await?
+        .with_progress(Arc::new(move |progress: &Progress| recorded.lock().unwrap().push(progress.clone())));
+
+    let models = ["gemma3-1b", "qwen2.5-0.5b", "gemma3-1b"].map(String::from);
+    let pools = manager.preload(&models).await?;
+    assert_eq!(pools.len(), 2);
+    assert_eq!(pools[0].model(), "gemma3-1b");
+    assert_eq!(pools[1].model(), "qwen2.5-0.5b");
+    assert!(Arc::ptr_eq(&pools[0], &manager.pool("gemma3-1b").await?));
+    assert_eq!(pools[1].send_prompt("user: hi").await?.trim(), "user: hi");
+
+    // One progress view for all models, ending when the last pool is up
+    {
+        let reports = reports.lock().unwrap();
+        assert!(reports.iter().all(|p| p.phase == Phase::LoadingModel && p.model.is_none()));
+        assert_eq!(reports.first().and_then(|p| p.percent), Some(0.0));
+        assert!(reports.last().is_some_and(|p| p.is_finished()));
+    }
+
+    // Already loaded models are not started again
+    assert_eq!(manager.preload(&models[..1]).await?.len(), 1);
+    assert!(Arc::ptr_eq(&pools[0], &manager.load("gemma3-1b").await?));
+
+    let loaded = manager.loaded_models().await;
+    let names: Vec<&str> = loaded.iter().map(|pool| pool.model.as_str()).collect();
+    assert_eq!(names, ["gemma3-1b", "qwen2.5-0.5b"]);
+    assert!(loaded.iter().all(|pool| pool.processes == 1 && pool.idle_secs.is_some()));
+
+    // Only the qwen pool has answered a prompt
+    let stats = manager.pool_stats().await;
+    assert_eq!(stats.len(), 2);
+    assert_eq!((stats[0].processes[0].served, stats[1].processes[0].served), (0, 1));
+    assert!(stats[1].processes[0].last_latency_ms.is_some() && !stats[1].processes[0].busy);
+    assert!(stats.iter().all(|pool| pool.running == 0 && pool.waiting == 0));
+
+    assert!(manager.unload("qwen2.5-0.5b").await);
+    let loaded = manager.loaded_models().await;
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].model, "gemma3-1b");
+    Ok(())
+}
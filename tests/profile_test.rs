@@ -0,0 +1,83 @@
+//! Tests for per-model generation profiles (no lit binary required)
+
+use litert_lm::config::Config;
+use litert_lm::generation;
+use litert_lm::{ChatTemplate, Constraint, GenOptions, Profiles, Turn};
+
+fn profiles() -> Profiles {
+    let config: Config = serde_json::from_value(serde_json::json!({
+        "profiles": [
+            {
+                "name": "gemma-3n-E4B-json",
+                "model": "gemma-3n-E4B",
+                "temperature": 0.2,
+                "stop": ["\n\n"],
+                "system_prompt": "Reply in JSON.",
+                "constraint": { "type": "json" }
+            },
+            { "name": "gemma-3n-E2B", "top_p": 0.9 }
+        ]
+    }))
+    .unwrap();
+    Profiles::from_config(&config.profiles)
+}
+
+#[test]
+fn profiles_fill_in_unset_options() {
+    let profiles = profiles();
+    let json = profiles.get("gemma-3n-E4B-json").unwrap();
+    assert_eq!(json.model(), "gemma-3n-E4B");
+    assert_eq!(profiles.get("gemma-3n-E2B").unwrap().model(), "gemma-3n-E2B");
+    assert!(profiles.get("gemma-3n-E4B").is_none());
+
+    let mut options = GenOptions::default();
+    json.apply(&mut options);
+    assert_eq!(options.temperature, Some(0.2));
+    assert_eq!(options.stop, vec!["\n\n".to_string()]);
+    assert_eq!(options.constraint, Some(Constraint::Json));
+
+    // Settings the request made itself win
+    let mut options = GenOptions {
+        temperature: Some(1.0),
+        stop: vec!["END".to_string()],
+        ..Default::default()
+    };
+    json.apply(&mut options);
+    assert_eq!(options.temperature, Some(1.0));
+    assert_eq!(options.stop, vec!["END".to_string()]);
+}
+
+#[test]
+fn system_prompt_only_added_without_one() {
+    let json = profiles().get("gemma-3n-E4B-json").unwrap().clone();
+
+    let mut turns = vec![Turn::new("user", "List three colors")];
+    json.apply_turns(&mut turns);
+    assert_eq!(turns[0], Turn::new("system", "Reply in JSON."));
+    assert_eq!(turns.len(), 2);
+
+    let mut turns = vec![Turn::new("system", "Be terse."), Turn::new("user", "Hi")];
+    json.apply_turns(&mut turns);
+    assert_eq!(turns[0], Turn::new("system", "Be terse."));
+    assert_eq!(turns.len(), 2);
+}
+
+#[tokio::test]
+async fn output_is_cut_at_the_first_stop_sequence() -> anyhow::Result<()> {
+    let options = GenOptions {
+        stop: vec!["###".to_string(), "\nuser:".to_string()],
+        ..Default::default()
+    };
+    let (text, ()) = generation::generate_with("prompt", ChatTemplate::Plain, &options, |_| async {
+        Ok(("Paris.\nuser: and Spain?###".to_string(), ()))
+    })
+    .await?;
+    assert_eq!(text, "Paris.");
+
+    let invalid = GenOptions {
+        temperature: Some(3.0),
+        ..Default::default()
+    };
+    assert!(invalid.check().is_err());
+    Ok(())
+}
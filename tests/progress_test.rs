@@ -0,0 +1,34 @@
+//! Tests for progress reporting (no lit binary required)
+
+use litert_lm::{Phase, Progress, ProgressReporter};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn byte_counts_derive_the_percentage() {
+    let progress = Progress::new(Phase::DownloadingBinary, None).bytes(256, Some(1024));
+    assert_eq!(progress.percent, Some(25.0));
+    assert!(!progress.is_finished());
+
+    let unknown_size = Progress::new(Phase::DownloadingBinary, None).bytes(256, None);
+    assert_eq!(unknown_size.percent, None);
+
+    let done = Progress::new(Phase::PullingModel, Some("gemma-3n-E4B")).percent(120.0);
+    assert_eq!(done.percent, Some(100.0));
+    assert!(done.is_finished());
+    assert_eq!(done.model.as_deref(), Some("gemma-3n-E4B"));
+    assert_eq!(done.phase.as_str(), "pulling_model");
+}
+
+#[test]
+fn closures_are_reporters() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorder = seen.clone();
+    let reporter: Arc<dyn ProgressReporter> = Arc::new(move |progress: &Progress| {
+        recorder.lock().unwrap().push(progress.percent);
+    });
+
+    for percent in [0.0, 50.0, 100.0] {
+        reporter.report(&Progress::new(Phase::LoadingModel, Some("gemma-3n-E4B")).percent(percent));
+    }
+    assert_eq!(*seen.lock().unwrap(), [Some(0.0), Some(50.0), Some(100.0)]);
+}
@@ -0,0 +1,70 @@
+//! Tests for the prompt cache
+
+use litert_lm::config::PromptCacheConfig;
+use litert_lm::prompt_cache::{CacheHit, PromptCache};
+use litert_lm::GenOptions;
+
+fn tempdir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("litert-prompt-cache-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn responses(text: &str) -> Vec<String> {
+    vec![text.to_string()]
+}
+
+#[test]
+fn memory_cache_evicts_least_recently_used() -> anyhow::Result<()> {
+    let config = PromptCacheConfig {
+        memory_entries: 2,
+        ..Default::default()
+    };
+    let cache = PromptCache::open(&config, None)?;
+    let options = GenOptions::default();
+
+    cache.put("default", "gemma", "a", &options, responses("A"));
+    cache.put("default", "gemma", "b", &options, responses("B"));
+    assert_eq!(cache.get("default", "gemma", "a", &options), Some((responses("A"), CacheHit::Memory)));
+    cache.put("default", "gemma", "c", &options, responses("C"));
+
+    assert!(cache.get("default", "gemma", "b", &options).is_none());
+    assert!(cache.get("default", "gemma", "a", &options).is_some());
+    // Tenants, models, and options are part of the key
+    assert!(cache.get("other", "gemma", "a", &options).is_none());
+    assert!(cache.get("default", "gemma-2", "a", &options).is_none());
+    let best_of = GenOptions {
+        best_of: Some(2),
+        ..Default::default()
+    };
+    assert!(cache.get("default", "gemma", "a", &best_of).is_none());
+    Ok(())
+}
+
+#[test]
+fn persistent_cache_survives_reopening_and_stays_under_its_size() -> anyhow::Result<()> {
+    let path = tempdir().join("prompt_cache.db");
+    let config = PromptCacheConfig {
+        enabled: true,
+        persist: true,
+        max_disk_bytes: 1000,
+        ..Default::default()
+    };
+    let options = GenOptions::default();
+
+    let cache = PromptCache::open(&config, Some(&path))?;
+    for i in 0..20 {
+        cache.put("default", "gemma", &format!("prompt {}", i), &options, responses(&"x".repeat(100)));
+    }
+    let (entries, bytes) = cache.disk_usage()?;
+    assert!(bytes <= 1000 && entries < 20);
+    drop(cache);
+
+    let reopened = PromptCache::open(&config, Some(&path))?;
+    assert_eq!(
+        reopened.get("default", "gemma", "prompt 19", &options),
+        Some((responses(&"x".repeat(100)), CacheHit::Disk))
+    );
+    assert!(reopened.get("default", "gemma", "prompt 0", &options).is_none());
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Tests for the model cache limit (no lit binary required)
+
+use litert_lm::quota::{plan_eviction, CachedModel, ModelUsage};
+use litert_lm::registry::{parse_listing, parse_size};
+
+const GB: u64 = 1_000_000_000;
+
+fn cached(name: &str, size_bytes: u64, last_used: Option<u64>) -> CachedModel {
+    CachedModel {
+        name: name.to_string(),
+        size_bytes,
+        last_used,
+    }
+}
+
+#[test]
+fn sizes_are_read_from_listings() {
+    assert_eq!(parse_size("4.2GB"), Some(4_200_000_000));
+    assert_eq!(parse_size("512MB"), Some(512_000_000));
+    assert_eq!(parse_size("1GiB"), Some(1 << 30));
+    assert_eq!(parse_size("1B"), None);
+    assert_eq!(parse_size("Gemma3-1B-IT"), None);
+
+    let entries = parse_listing("ALIAS  MODEL_ID  PARAMS  SIZE\ngemma3-1b  google/gemma3  1B  1.0 GB\n");
+    assert_eq!(entries[0].size_bytes(), Some(GB));
+}
+
+#[test]
+fn least_recently_used_unpinned_models_are_evicted() {
+    let models = [
+        cached("recent", 2 * GB, Some(300)),
+        cached("old", 2 * GB, Some(100)),
+        cached("pinned", 2 * GB, Some(50)),
+        cached("never-used", GB, None),
+    ];
+
+    // 7 GB cached + 2 GB incoming, 6 GB allowed: the two oldest unpinned go
+    let plan = plan_eviction(&models, 2 * GB, 6 * GB, |name| name == "pinned");
+    let evicted: Vec<&str> = plan.evict.iter().map(|model| model.name.as_str()).collect();
+    assert_eq!(evicted, ["never-used", "old"]);
+    assert_eq!(plan.total_bytes, 6 * GB);
+    assert!(plan.fits);
+
+    // Nothing to evict when the pull fits already
+    let plan = plan_eviction(&models, GB, 10 * GB, |_| false);
+    assert!(plan.evict.is_empty() && plan.fits);
+
+    // Too big even with every candidate gone
+    let plan = plan_eviction(&models, 5 * GB, 6 * GB, |name| name != "old");
+    assert_eq!(plan.evict.len(), 1);
+    assert!(!plan.fits);
+}
+
+#[tokio::test]
+async fn usage_is_recorded_and_forgotten() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-quota-{}", uuid::Uuid::new_v4()));
+    let usage = ModelUsage::new(dir.join("model_usage.json"));
+    assert!(usage.load().is_empty());
+
+    usage.touch("gemma3-1b").await?;
+    usage.touch("gemma-3n-E4B").await?;
+    assert!(usage.load()["gemma3-1b"] > 0);
+
+    usage.forget("gemma3-1b").await?;
+    let recorded = usage.load();
+    assert!(!recorded.contains_key("gemma3-1b"));
+    assert!(recorded.contains_key("gemma-3n-E4B"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_updates_are_not_lost() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-quota-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("model_usage.json");
+
+    // Separate handles, as separate invocations sharing the cache would have
+    let updates = (0..8).map(|i| {
+        let usage = ModelUsage::new(path.clone());
+        tokio::spawn(async move { usage.touch(&format!("model-{}", i)).await })
+    });
+    for update in futures::future::join_all(updates).await {
+        update??;
+    }
+    assert_eq!(ModelUsage::new(path).load().len(), 8);
+    Ok(())
+}
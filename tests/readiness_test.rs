@@ -0,0 +1,49 @@
+//! Tests for the startup readiness gate (no lit binary required)
+
+mod common;
+
+use litert_lm::{create_router, AppState, LitManager, ProcessPool, Readiness, ReadyState};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn completions_wait_for_warm_up() -> anyhow::Result<()> {
+    let dir = common::cache_dir()?;
+
+    let manager = LitManager::new().await?;
+    let readiness = Readiness::warming_up();
+    let app = create_router(AppState {
+        pool: Arc::new(ProcessPool::new(dir.join("lit"), "gemma-3n-E4B".to_string(), 1)),
+        manager: Arc::new(manager),
+        readiness: readiness.clone(),
+        chunking: Default::default(),
+    });
+    let base = common::serve(app).await?;
+    let client = reqwest::Client::new();
+
+    let probe = client.get(format!("{}/readyz", base)).send().await?;
+    assert_eq!(probe.status(), 503);
+    assert_eq!(probe.json::<serde_json::Value>().await?["status"], "warming_up");
+
+    let completion = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&serde_json::json!({
+            "model": "gemma-3n-E4B",
+            "messages": [{ "role": "user", "content": "Hi" }]
+        }))
+        .send()
+        .await?;
+    assert_eq!(completion.status(), 503);
+    assert!(completion.headers().contains_key("retry-after"));
+    let body: serde_json::Value = completion.json().await?;
+    assert_eq!(body["error"]["code"], "model_not_ready");
+
+    readiness.set(ReadyState::Failed { error: "out of memory".to_string() });
+    let probe: serde_json::Value = client.get(format!("{}/readyz", base)).send().await?.json().await?;
+    assert_eq!(probe["status"], "failed");
+    assert_eq!(probe["error"], "out of memory");
+
+    readiness.set(ReadyState::Ready);
+    let probe = client.get(format!("{}/readyz", base)).send().await?;
+    assert_eq!(probe.status(), 200);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! Tests for registry manifests of one's own (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::registry::parse_listing;
+use litert_lm::RegistryManifest;
+
+// SHA-256 of "abc"
+const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+#[test]
+fn manifests_are_read_as_json_or_toml() -> anyhow::Result<()> {
+    let json = RegistryManifest::parse(
+        &format!(
+            r#"{{ "models": [{{ "name": "acme-7b", "url": "s3://models/acme-7b.litertlm", "sha256": "{}", "size_bytes": 4200000000 }}] }}"#,
+            ABC.to_uppercase()
+        ),
+        "https://models.internal/registry.json",
+    )?;
+    let toml = RegistryManifest::parse(
+        &format!(
+            "[[models]]\nname = \"acme-7b\"\nurl = \"s3://models/acme-7b.litertlm\"\nsha256 = \"{}\"\nsize_bytes = 4200000000\n",
+            ABC
+        ),
+        "/etc/litert-lm/registry.toml",
+    )?;
+    assert_eq!(json, toml);
+    assert_eq!(json.get("acme-7b").and_then(|model| model.sha256.as_deref()), Some(ABC));
+
+    assert!(RegistryManifest::parse(r#"{ "models": [{ "name": "x", "url": "https://x", "sha256": "abc" }] }"#, "r.json").is_err());
+    assert!(RegistryManifest::parse(r#"{ "models": [{ "name": "x" }] }"#, "r.json").is_err());
+
+    // Rows read like lit's, and leave out models listed already
+    let rows = json.listing_rows("ALIAS  MODEL_ID  SIZE\ngemma3-1b  litert-community/Gemma3-1B-IT  1.0GB\n");
+    let entries = parse_listing(&rows);
+    assert_eq!(entries.len(), 1);
+    assert_eq!((entries[0].name.as_str(), entries[0].size_bytes()), ("acme-7b", Some(4_200_000_000)));
+    assert!(json.listing_rows("acme-7b  google/acme  1.0GB\n").is_empty());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn manifest_models_are_listed_and_pulled_by_name() -> anyhow::Result<()> {
+    use litert_lm::{Config, LitManager};
+
+    let dir = common::temp_dir()?;
+    std::fs::write(dir.join("acme-7b.litertlm"), b"abc")?;
+    let manifest = dir.join("registry.json");
+    std::fs::write(
+        &manifest,
+        serde_json::json!({ "models": [{
+            "name": "acme-7b",
+            "url": format!("file://{}", dir.join("acme-7b.litertlm").display()),
+            "sha256": ABC,
+        }] })
+        .to_string(),
+    )?;
+
+    // `pull` stores "abc"; `list` shows it with its path once pulled
+    let stored = dir.join("models/acme-7b.litertlm");
+    let calls = dir.join("calls");
+    let lit = common::write_script(&dir.join("lit"), &format!(
+            "#!/bin/sh\n\
+             echo \"$@\" >> {calls}\n\
+             case \"$1\" in\n\
+             pull) mkdir -p {models}; printf abc > {file} ;;\n\
+             list) echo 'ALIAS      MODEL_ID                       SIZE'\n\
+                   [ -e {file} ] && echo 'acme-7b  acme/Acme-7B  {file}'\n\
+                   [ \"$2\" = --show_all ] && echo 'gemma3-1b  litert-community/Gemma3-1B-IT  1.0GB' ;;\n\
+             esac\n\
+             exit 0\n",
+            calls = calls.display(),
+            models = dir.join("models").display(),
+            file = stored.display()
+        ))?;
+    let config = Config {
+        registries: vec![manifest.display().to_string()],
+        ..Config::default()
+    };
+    let manager = LitManager::builder()
+        .config(config)
+        .cache_dir(dir.join("cache"))
+        .binary_path(&lit)
+        .build()
+        .await?;
+
+    let listed: Vec<(String, bool)> = manager
+        .list_models_structured(true)
+        .await?
+        .into_iter()
+        .map(|model| (model.alias, model.downloaded))
+        .collect();
+    assert_eq!(listed, [("gemma3-1b".to_string(), false), ("acme-7b".to_string(), false)]);
+
+    // Pulled from the manifest's URL under its name, and checked against its digest
+    manager.pull("acme-7b", None, None).await?;
+    let calls = std::fs::read_to_string(&calls)?;
+    let pull = calls.lines().find(|call| call.starts_with("pull ")).unwrap();
+    assert!(pull.starts_with("pull http://127.0.0.1:") && pull.ends_with(" --alias acme-7b"), "{}", pull);
+    assert_eq!(manager.model_checksum("acme-7b").map(|record| record.sha256).as_deref(), Some(ABC));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Tests for registry search (no lit binary required)
+
+use litert_lm::registry::{parse_listing, search};
+
+const LISTING: &str = "\
+Available models:
+ALIAS            MODEL_ID                             SIZE
+gemma3-1b        litert-community/Gemma3-1B-IT        1.0GB
+gemma-3n-E2B     google/gemma-3n-E2B-it-litert-lm     3.1GB
+gemma-3n-E4B     google/gemma-3n-E4B-it-litert-lm     4.2GB
+qwen2.5-1.5b     litert-community/Qwen2.5-1.5B        1.6GB
+
+";
+
+#[test]
+fn listing_rows_become_entries() {
+    let entries = parse_listing(LISTING);
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].name, "gemma3-1b");
+    assert_eq!(entries[0].details, ["litert-community/Gemma3-1B-IT", "1.0GB"]);
+}
+
+#[test]
+fn search_ranks_name_matches_first() {
+    let entries = parse_listing(LISTING);
+    let names = |query: &str| -> Vec<String> {
+        search(&entries, query).into_iter().map(|m| m.entry.name).collect()
+    };
+
+    // Every word has to match, ignoring case and separators
+    assert_eq!(names("gemma 1b"), ["gemma3-1b"]);
+    assert_eq!(names("GEMMA 3n"), ["gemma-3n-E2B", "gemma-3n-E4B"]);
+    assert_eq!(names("gemma3n e4b"), ["gemma-3n-E4B"]);
+    // Details such as the size or repository match too
+    assert_eq!(names("4.2GB"), ["gemma-3n-E4B"]);
+    assert_eq!(names("community"), ["gemma3-1b", "qwen2.5-1.5b"]);
+    // Typos that keep the letters in order still match
+    assert_eq!(names("qwn"), ["qwen2.5-1.5b"]);
+    assert!(names("llama").is_empty());
+
+    // Exact names outrank partial ones
+    let matches = search(&entries, "gemma3-1b");
+    assert_eq!(matches[0].entry.name, "gemma3-1b");
+    assert_eq!(matches[0].score, 100);
+}
@@ -0,0 +1,76 @@
+//! Tests for replaying recorded requests (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::replay::{self, DiffLine, ReplayStatus};
+
+#[test]
+fn diff_keeps_common_lines_and_marks_changes() {
+    let diff = replay::diff_lines("one\ntwo\nthree", "one\n2\nthree\nfour");
+    assert_eq!(
+        diff,
+        vec![
+            DiffLine::Same("one".to_string()),
+            DiffLine::Removed("two".to_string()),
+            DiffLine::Added("2".to_string()),
+            DiffLine::Same("three".to_string()),
+            DiffLine::Added("four".to_string()),
+        ]
+    );
+    assert!(replay::diff_lines("same", "same").iter().all(|l| matches!(l, DiffLine::Same(_))));
+}
+
+#[test]
+fn records_are_read_from_history_json_lines() -> anyhow::Result<()> {
+    let text = r#"{"id":"a","model":"gemma3-1b","source":"http","created_at":1,"duration_ms":5,"status":"success","error":null,"prompt_chars":2,"response_chars":5,"prompt":"hi","response":"hello"}
+
+{"id":"b","model":"gemma3-1b","source":"http","created_at":2,"duration_ms":5,"status":"error","error":"boom","prompt_chars":2,"response_chars":0,"prompt":null,"response":null}
+"#;
+    let records = replay::parse_records(text)?;
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].prompt.as_deref(), Some("hi"));
+    assert_eq!(records[1].error.as_deref(), Some("boom"));
+
+    let err = replay::parse_records("{not json}").unwrap_err();
+    assert!(err.to_string().contains("line 1"));
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn replay_diffs_new_responses_with_recorded_ones() -> anyhow::Result<()> {
+    use litert_lm::{HistoryRecord, LitManager};
+    use std::time::Instant;
+
+    // Echoes each prompt
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+    let manager = LitManager::new_with_pool_size(1).await?;
+
+    let started = Instant::now();
+    let records = vec![
+        HistoryRecord::new("same", "gemma3-1b", "http", "hello").finish(started, Ok("hello")),
+        HistoryRecord::new("changed", "gemma3-1b", "http", "hello").finish(started, Ok("goodbye")),
+        HistoryRecord {
+            prompt: None,
+            ..HistoryRecord::new("metadata-only", "gemma3-1b", "http", "").finish(started, Ok("x"))
+        },
+    ];
+
+    let results = replay::replay(&manager, &records, None).await;
+    assert_eq!(results[0].status, ReplayStatus::Same);
+    assert!(results[0].diff.is_empty());
+    assert_eq!(results[1].status, ReplayStatus::Changed);
+    assert_eq!(
+        results[1].diff,
+        vec![DiffLine::Removed("goodbye".to_string()), DiffLine::Added("hello".to_string())]
+    );
+    assert_eq!(results[2].status, ReplayStatus::Skipped);
+    assert!(results[2].error.as_deref().is_some_and(|e| e.contains("LITERT_HISTORY_CONTENT")));
+
+    let rendered = replay::render(&results);
+    assert!(rendered.contains("changed changed (gemma3-1b)\n    -goodbye\n    +hello\n"));
+
+    // Without history, a request ID can't be looked up
+    assert!(replay::load(&manager, "no-such-id").await.is_err());
+    Ok(())
+}
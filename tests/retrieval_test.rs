@@ -0,0 +1,89 @@
+//! Tests for the local retrieval index (no lit binary required)
+
+use litert_lm::hnsw::Hnsw;
+use litert_lm::retrieval::{chunk_text, DocumentIndex, HashingEmbedder};
+use std::sync::Arc;
+
+#[test]
+fn search_ranks_the_relevant_document_first() -> anyhow::Result<()> {
+    let index = DocumentIndex::open_in_memory(Arc::new(HashingEmbedder::default()))?;
+    index.ingest_text(
+        "gpu.md",
+        "The GPU backend falls back to the CPU when the OpenCL driver is missing.",
+    )?;
+    index.ingest_text(
+        "pull.md",
+        "Models are pulled from the registry or a Hugging Face URL with an optional token.",
+    )?;
+
+    let hits = index.search("why does the gpu backend fall back to cpu", 2)?;
+    assert_eq!(hits[0].source, "gpu.md");
+
+    // Re-ingesting replaces the earlier version instead of duplicating chunks
+    index.ingest_text("gpu.md", "Nothing about drivers here.")?;
+    let documents = index.documents()?;
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents.iter().map(|d| d.chunks).sum::<usize>(), 2);
+    Ok(())
+}
+
+#[test]
+fn long_text_is_chunked_with_overlap() {
+    let text = "Sentence number one. ".repeat(200);
+    let chunks = chunk_text(&text, 1000, 200);
+
+    assert!(chunks.len() > 4);
+    assert!(chunks.iter().all(|c| c.chars().count() <= 1000));
+    // Consecutive chunks share text
+    let tail: String = chunks[0].chars().rev().take(50).collect::<Vec<_>>().into_iter().rev().collect();
+    assert!(chunks[1].contains(tail.trim()));
+}
+
+#[test]
+fn graph_search_finds_the_nearest_vectors() {
+    let mut rng = fastrand::Rng::with_seed(7);
+    let mut random_unit = || {
+        let mut v: Vec<f32> = (0..32).map(|_| rng.f32() - 0.5).collect();
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        v.iter_mut().for_each(|x| *x /= norm);
+        v
+    };
+    let vectors: Vec<Vec<f32>> = (0..2000).map(|_| random_unit()).collect();
+    let mut graph = Hnsw::new();
+    for vector in &vectors {
+        graph.insert(vector.clone());
+    }
+
+    // Recall of the ten nearest, against an exact scan
+    let mut found = 0;
+    for _ in 0..50 {
+        let query = random_unit();
+        let mut exact: Vec<(usize, f32)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(id, v)| (id, v.iter().zip(&query).map(|(a, b)| a * b).sum()))
+            .collect();
+        exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let hits = graph.search(&query, 10);
+        found += hits.iter().filter(|(id, _)| exact[..10].iter().any(|(e, _)| e == id)).count();
+    }
+    assert!(found >= 450, "recall {}/500", found);
+
+    // Removed vectors are not found, even searching with themselves
+    graph.remove(3);
+    assert_eq!(graph.len(), 1999);
+    assert!(graph.search(&vectors[3], 5).iter().all(|(id, _)| *id != 3));
+    assert_eq!(graph.search(&vectors[4], 1)[0].0, 4);
+}
+
+#[test]
+fn removed_documents_leave_search_results() -> anyhow::Result<()> {
+    let index = DocumentIndex::open_in_memory(Arc::new(HashingEmbedder::default()))?;
+    index.ingest_text("gpu.md", "The GPU backend falls back to the CPU.")?;
+    index.ingest_text("pull.md", "Models are pulled from the registry.")?;
+
+    assert!(index.remove("gpu.md")?);
+    let hits = index.search("gpu backend", 5)?;
+    assert!(hits.iter().all(|hit| hit.source != "gpu.md"));
+    Ok(())
+}
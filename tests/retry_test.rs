@@ -0,0 +1,66 @@
+//! Tests for the retry policy (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::retry::Permanent;
+use litert_lm::{Cancelled, ErrorClass, RetryPolicy};
+use std::time::Duration;
+
+#[test]
+fn backoff_doubles_up_to_the_limit() {
+    let policy = RetryPolicy::default().with_backoff(Duration::from_millis(100), Duration::from_millis(350));
+    let waits: Vec<u64> = (1..=4).map(|attempt| policy.backoff(attempt).as_millis() as u64).collect();
+    assert_eq!(waits, [100, 200, 350, 350]);
+    assert_eq!(policy.backoff(usize::MAX), Duration::from_millis(350));
+}
+
+#[test]
+fn only_listed_classes_are_retried_within_the_attempts() {
+    let policy = RetryPolicy::default().with_attempts(2).with_retry_on([ErrorClass::Network]);
+    let error = anyhow::anyhow!("connection reset");
+    assert!(policy.should_retry(ErrorClass::Network, 1, &error));
+    assert!(!policy.should_retry(ErrorClass::Network, 2, &error));
+    assert!(!policy.should_retry(ErrorClass::Process, 1, &error));
+
+    // Cancellation and permanent failures end the operation
+    assert!(!policy.should_retry(ErrorClass::Network, 1, &anyhow::Error::new(Cancelled)));
+    let missing = anyhow::Error::new(Permanent).context("Failed to download binary: HTTP 404");
+    assert!(!policy.should_retry(ErrorClass::Network, 1, &missing));
+    assert_eq!(missing.to_string(), "Failed to download binary: HTTP 404");
+
+    assert!(!RetryPolicy::none().should_retry(ErrorClass::Network, 1, &error));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn pool_starts_are_retried() -> anyhow::Result<()> {
+    use litert_lm::LitManager;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = common::cache_dir()?;
+
+    // Not executable yet, so processes fail to start
+    let lit = dir.join("lit.linux_x86_64");
+    std::fs::write(&lit, "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+    std::fs::set_permissions(&lit, std::fs::Permissions::from_mode(0o644))?;
+    let manager = LitManager::new_with_pool_size(1).await?;
+
+    // Without retries, the failed start is final
+    let no_retries = manager.with_retry_policy(RetryPolicy::none());
+    assert!(no_retries.pool("gemma3-1b").await.is_err());
+
+    // The binary is fixed while the retry waits
+    let retrying = manager.with_retry_policy(
+        RetryPolicy::default().with_backoff(Duration::from_millis(500), Duration::from_millis(500)),
+    );
+    assert_eq!(retrying.retry_policy().attempts, 3);
+    let fix = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        std::fs::set_permissions(&lit, std::fs::Permissions::from_mode(0o755))
+    });
+    let pool = retrying.pool("gemma3-1b").await?;
+    fix.await??;
+    assert_eq!(pool.send_prompt("user: hi").await?.trim(), "user: hi");
+    assert_eq!(manager.metrics().retries.with_label_values(&["pool_start", "process"]).get(), 1);
+    Ok(())
+}
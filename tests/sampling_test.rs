@@ -0,0 +1,83 @@
+//! Tests for sampling settings passed to lit
+
+mod common;
+
+use litert_lm::{GenOptions, Sampling};
+
+#[test]
+fn sampling_becomes_lit_flags() {
+    let sampling = Sampling {
+        temperature: Some(0.7),
+        top_p: None,
+        top_k: Some(40),
+        seed: None,
+    };
+    assert_eq!(sampling.flags(), ["--temperature", "0.7", "--top_k", "40"]);
+    assert!(Sampling::default().flags().is_empty());
+    let seeded = Sampling {
+        seed: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(seeded.flags(), ["--seed", "7"]);
+
+    // Unset settings fall back to the defaults
+    let defaults = Sampling {
+        temperature: Some(0.2),
+        top_p: Some(0.9),
+        top_k: None,
+        seed: None,
+    };
+    let merged = sampling.or(defaults);
+    assert_eq!((merged.temperature, merged.top_p, merged.top_k), (Some(0.7), Some(0.9), Some(40)));
+
+    let options = GenOptions {
+        top_k: Some(0),
+        ..Default::default()
+    };
+    assert_eq!(options.check(), Err("top_k must be at least 1".to_string()));
+    // Options without top_k serialize as before, keeping prompt cache keys stable
+    assert!(!serde_json::to_string(&GenOptions::default()).unwrap().contains("top_k"));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn pools_start_with_the_sampling_settings() -> anyhow::Result<()> {
+    use std::sync::Arc;
+
+    // Answers every prompt with the arguments it was started with
+    let dir = common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$*\"; done\n")?;
+    common::write_config(&dir, serde_json::json!({ "sampling": { "gemma3-*": { "temperature": 0.2 } } }))?;
+
+    let manager = litert_lm::LitManager::new_with_pool_size(1).await?;
+    let default = manager.pool("gemma3-1b").await?;
+    assert_eq!(default.send_prompt("user: hi").await?.trim(), "run gemma3-1b --backend gpu --temperature 0.2");
+
+    // Asking for the defaults is served by the default pool
+    let same = GenOptions {
+        temperature: Some(0.2),
+        ..Default::default()
+    };
+    assert!(Arc::ptr_eq(&default, &manager.pool_with_options("gemma3-1b", &same).await?));
+
+    let hot = GenOptions {
+        temperature: Some(1.2),
+        top_k: Some(40),
+        ..Default::default()
+    };
+    let tuned = manager.pool_with_options("gemma3-1b", &hot).await?;
+    assert!(!Arc::ptr_eq(&tuned, &default));
+    assert_eq!(
+        tuned.send_prompt("user: hi").await?.trim(),
+        "run gemma3-1b --backend gpu --temperature 1.2 --top_k 40"
+    );
+
+    let response = manager.run_completion_with("gemma3-1b", "user: hi", &hot).await?;
+    assert_eq!(response.trim(), "run gemma3-1b --backend gpu --temperature 1.2 --top_k 40");
+
+    let out_of_range = GenOptions {
+        temperature: Some(3.0),
+        ..Default::default()
+    };
+    assert!(manager.pool_with_options("gemma3-1b", &out_of_range).await.is_err());
+    Ok(())
+}
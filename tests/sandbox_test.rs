@@ -0,0 +1,93 @@
+//! Tests for sandboxed lit processes (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::config::SandboxConfig;
+use litert_lm::sandbox::{Sandbox, SandboxUser};
+use std::path::PathBuf;
+
+const PASSWD: &str = "root:x:0:0:root:/root:/bin/bash\n\
+                      # comment\n\
+                      lit:x:998:997:LiteRT-LM:/var/lib/lit:/usr/sbin/nologin\n";
+
+#[test]
+fn users_resolve_by_name_or_id() {
+    let lit = SandboxUser {
+        name: Some("lit".to_string()),
+        uid: 998,
+        gid: 997,
+        home: Some(PathBuf::from("/var/lib/lit")),
+    };
+    assert_eq!(SandboxUser::find(PASSWD, "lit"), Some(lit.clone()));
+    assert_eq!(SandboxUser::find(PASSWD, "998"), Some(lit.clone()));
+    assert_eq!(SandboxUser::find(PASSWD, "998:100"), Some(SandboxUser { gid: 100, ..lit }));
+
+    // Bare ids need no entry, e.g. in minimal containers
+    let anonymous = SandboxUser::find(PASSWD, "4242").unwrap();
+    assert_eq!((anonymous.name, anonymous.uid, anonymous.gid), (None, 4242, 4242));
+
+    assert_eq!(SandboxUser::find(PASSWD, "nobody"), None);
+    assert_eq!(SandboxUser::find(PASSWD, "998:staff"), None);
+}
+
+#[test]
+fn config_is_checked_up_front() {
+    assert!(!Sandbox::from_config(&SandboxConfig::default()).unwrap().is_enabled());
+
+    let missing = SandboxConfig {
+        working_dir: Some(std::env::temp_dir().join(format!("litert-missing-{}", uuid::Uuid::new_v4()))),
+        ..SandboxConfig::default()
+    };
+    let error = Sandbox::from_config(&missing).unwrap_err();
+    assert!(error.to_string().starts_with("Sandbox working directory"), "{}", error);
+
+    let unknown = SandboxConfig {
+        user: Some("no-such-user-litert".to_string()),
+        ..SandboxConfig::default()
+    };
+    let error = Sandbox::from_config(&unknown).unwrap_err();
+    assert_eq!(error.to_string(), "Unknown sandbox user 'no-such-user-litert'");
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn lit_processes_start_inside_the_sandbox() -> anyhow::Result<()> {
+    use litert_lm::LitManager;
+
+    // Records what it was started with, then echoes prompts
+    let dir = common::fake_lit(
+        "#!/bin/sh\n\
+         { pwd; echo \"keep=$LITERT_SANDBOX_KEEP drop=$LITERT_SANDBOX_DROP\"; \
+         grep -E '^(NoNewPrivs|Seccomp):' /proc/self/status; \
+         if unshare -U true 2>/dev/null; then echo unshare=allowed; else echo unshare=denied; fi; } > started\n\
+         printf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n",
+    )?;
+    let work = dir.join("work");
+    std::fs::create_dir_all(&work)?;
+    common::write_config(
+        &dir,
+        serde_json::json!({
+            "sandbox": {
+                "env": ["PATH", "LITERT_SANDBOX_KEEP"],
+                "working_dir": work,
+                "seccomp": true
+            }
+        }),
+    )?;
+    std::env::set_var("LITERT_SANDBOX_KEEP", "kept");
+    std::env::set_var("LITERT_SANDBOX_DROP", "leaked");
+
+    let manager = LitManager::new_with_pool_size(1).await?;
+    let pool = manager.pool("gemma3-1b").await?;
+    assert_eq!(pool.send_prompt("user: hi").await?.trim(), "user: hi");
+
+    let started = std::fs::read_to_string(work.join("started"))?;
+    let lines: Vec<&str> = started.lines().collect();
+    assert_eq!(lines[0], work.to_str().unwrap());
+    assert_eq!(lines[1], "keep=kept drop=");
+    assert_eq!(lines[2].split_whitespace().collect::<Vec<_>>(), ["NoNewPrivs:", "1"]);
+    assert_eq!(lines[3].split_whitespace().collect::<Vec<_>>(), ["Seccomp:", "2"]);
+    // The filter fails the denied syscalls
+    assert_eq!(lines[4], "unshare=denied");
+    Ok(())
+}
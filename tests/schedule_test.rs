@@ -0,0 +1,97 @@
+//! Tests for loading and unloading models on a schedule
+
+mod common;
+
+use litert_lm::config::ScheduleConfig;
+use litert_lm::schedule::{Cron, ModelSchedule, ScheduleAction};
+
+/// 2024-01-01 22:00 UTC, a Monday
+const MONDAY_22H: u64 = 1_704_146_400;
+const HOUR: u64 = 3600;
+const DAY: u64 = 86_400;
+
+fn cron(expression: &str) -> Cron {
+    Cron::parse(expression).unwrap()
+}
+
+#[test]
+fn rejects_malformed_expressions() {
+    for bad in ["0 22 * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "0 24 * * *", "0 0 0 * *", "a * * * *"] {
+        assert!(Cron::parse(bad).is_err(), "{} should be rejected", bad);
+    }
+}
+
+#[test]
+fn matches_minutes_hours_and_days_in_utc() {
+    assert!(cron("0 22 * * *").matches(MONDAY_22H));
+    assert!(cron("0 22 * * *").matches(MONDAY_22H + 59));
+    assert!(!cron("0 22 * * *").matches(MONDAY_22H + 60));
+    assert!(cron("*/15 * * * *").matches(MONDAY_22H + 45 * 60));
+    assert!(cron("0 20-23/2 * * *").matches(MONDAY_22H));
+
+    // Weekdays, with Sunday as 0 or 7
+    assert!(cron("0 22 * * 1-5").matches(MONDAY_22H));
+    assert!(!cron("0 22 * * 0,6").matches(MONDAY_22H));
+    assert!(cron("0 22 * * 7").matches(MONDAY_22H + 6 * DAY));
+    assert!(cron("0 22 * * 0").matches(MONDAY_22H + 6 * DAY));
+
+    // Day of month and month, including a leap day
+    assert!(cron("0 22 1 1 *").matches(MONDAY_22H));
+    assert!(!cron("0 22 1 2 *").matches(MONDAY_22H));
+    assert!(cron("0 22 29 2 *").matches(MONDAY_22H + 59 * DAY));
+
+    // Restricting both day fields matches either
+    assert!(cron("0 22 15 * 1").matches(MONDAY_22H));
+    assert!(!cron("0 22 15 * 2").matches(MONDAY_22H));
+}
+
+#[test]
+fn start_state_follows_the_last_transition() {
+    let schedule = ModelSchedule::from_config(&ScheduleConfig {
+        model: "gemma-3n-E4B".to_string(),
+        load: Some("0 22 * * *".to_string()),
+        unload: Some("0 6 * * *".to_string()),
+    })
+    .unwrap();
+
+    assert_eq!(schedule.due(MONDAY_22H), Some(ScheduleAction::Load));
+    assert_eq!(schedule.due(MONDAY_22H + 8 * HOUR), Some(ScheduleAction::Unload));
+    assert_eq!(schedule.due(MONDAY_22H + HOUR), None);
+
+    // Overnight the model belongs loaded, during the day unloaded
+    assert_eq!(schedule.state_at(MONDAY_22H + 5 * HOUR), Some(ScheduleAction::Load));
+    assert_eq!(schedule.state_at(MONDAY_22H + 14 * HOUR), Some(ScheduleAction::Unload));
+
+    let empty = ScheduleConfig {
+        model: "gemma-3n-E4B".to_string(),
+        load: None,
+        unload: None,
+    };
+    assert!(ModelSchedule::from_config(&empty).is_err());
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn loads_and_unloads_emit_events() -> anyhow::Result<()> {
+    use litert_lm::{LitArgs, ManagerEvent};
+
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+
+    let manager = litert_lm::LitManager::new_with_pool_size(1).await?;
+    let mut events = manager.subscribe();
+    let loaded = ManagerEvent::ModelLoaded { model: "gemma3-1b".to_string() };
+
+    manager.preload(&["gemma3-1b".to_string()]).await?;
+    assert_eq!(events.try_recv()?, loaded);
+    manager.pool_with_args("gemma3-1b", &LitArgs::default().with("max_num_tokens", 2048)).await?;
+    assert_eq!(events.try_recv()?, loaded);
+
+    // Every pool of the model goes at once
+    assert!(manager.unload("gemma3-1b").await);
+    assert_eq!(events.try_recv()?, ManagerEvent::ModelUnloaded { model: "gemma3-1b".to_string() });
+    assert!(manager.stats().await.pools.is_empty());
+
+    assert!(!manager.unload("gemma3-1b").await);
+    assert!(events.try_recv().is_err());
+    Ok(())
+}
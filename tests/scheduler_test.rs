@@ -0,0 +1,117 @@
+//! Tests for weighted fair queuing between tenants
+
+use litert_lm::scheduler::{FairScheduler, Rejected};
+use std::sync::{Arc, Mutex};
+
+/// Queue requests behind a held slot, then release it and return the admission order
+async fn admission_order(requests: &[(&'static str, u32)]) -> Vec<&'static str> {
+    let scheduler = FairScheduler::new(1);
+    let held = scheduler.acquire("warmup", 1).await;
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    let mut tasks = Vec::new();
+    for (i, &(tenant, weight)) in requests.iter().enumerate() {
+        let queued = scheduler.clone();
+        let order = order.clone();
+        tasks.push(tokio::spawn(async move {
+            let _slot = queued.acquire(tenant, weight).await;
+            order.lock().unwrap().push(tenant);
+        }));
+        while scheduler.waiting() <= i {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    drop(held);
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(scheduler.running(), 0);
+    let order = order.lock().unwrap().clone();
+    order
+}
+
+#[tokio::test]
+async fn light_tenant_is_not_starved_by_a_backlog() {
+    let order = admission_order(&[("heavy", 1), ("heavy", 1), ("heavy", 1), ("heavy", 1), ("light", 1)]).await;
+    assert_eq!(order, vec!["heavy", "light", "heavy", "heavy", "heavy"]);
+}
+
+#[tokio::test]
+async fn weights_set_the_share_of_a_saturated_pool() {
+    let order = admission_order(&[
+        ("a", 2),
+        ("a", 2),
+        ("a", 2),
+        ("a", 2),
+        ("b", 1),
+        ("b", 1),
+    ])
+    .await;
+    // Both start at the same virtual time; afterwards "a" gets two slots per "b" slot
+    assert_eq!(order, vec!["a", "b", "a", "a", "b", "a"]);
+}
+
+#[tokio::test]
+async fn abandoned_waiters_do_not_leak_slots() {
+    let scheduler = FairScheduler::new(1);
+    let held = scheduler.acquire("a", 1).await;
+
+    let waiting = tokio::spawn({
+        let scheduler = scheduler.clone();
+        async move {
+            let _slot = scheduler.acquire("b", 1).await;
+        }
+    });
+    while scheduler.waiting() == 0 {
+        tokio::task::yield_now().await;
+    }
+    waiting.abort();
+    let _ = waiting.await;
+
+    drop(held);
+    let _again = scheduler.acquire("c", 1).await;
+    assert_eq!(scheduler.running(), 1);
+}
+
+#[tokio::test]
+async fn a_full_queue_turns_requests_away() {
+    let scheduler = FairScheduler::with_max_waiting(1, Some(1));
+    let held = scheduler.acquire("a", 1).await.unwrap();
+
+    let queued = tokio::spawn({
+        let scheduler = scheduler.clone();
+        async move { scheduler.acquire("b", 1).await.is_ok() }
+    });
+    while scheduler.waiting() == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert!(matches!(scheduler.acquire("c", 1).await, Err(Rejected::QueueFull)));
+
+    // The queued request still gets the slot
+    drop(held);
+    assert!(queued.await.unwrap());
+    assert!(scheduler.acquire("c", 1).await.is_ok());
+}
+
+#[tokio::test]
+async fn idle_tenants_are_forgotten() {
+    let scheduler = FairScheduler::new(1);
+    for i in 0..100 {
+        let _slot = scheduler.acquire(&format!("tenant-{}", i), 1).await.unwrap();
+    }
+    assert_eq!(scheduler.tenants(), 0);
+
+    let held = scheduler.acquire("a", 1).await.unwrap();
+    let queued = tokio::spawn({
+        let scheduler = scheduler.clone();
+        async move { scheduler.acquire("b", 1).await.map(drop) }
+    });
+    while scheduler.waiting() == 0 {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(scheduler.tenants(), 2);
+    drop(held);
+    queued.await.unwrap().unwrap();
+    assert_eq!(scheduler.tenants(), 0);
+}
@@ -0,0 +1,36 @@
+//! Tests for the `run --json` event format (no lit binary required)
+
+use litert_lm::tokenizer::Estimate;
+use litert_lm::{GenerationTiming, LitertExtension, SessionEvent};
+use std::time::Duration;
+
+#[test]
+fn events_are_tagged_json_lines() {
+    let token = serde_json::to_value(SessionEvent::Token { text: "Hel".to_string() }).unwrap();
+    assert_eq!(token, serde_json::json!({ "event": "token", "text": "Hel" }));
+
+    let ready = serde_json::to_value(SessionEvent::Ready {
+        session: "abc".to_string(),
+        model: "gemma-3n-E4B".to_string(),
+        messages: 2,
+    })
+    .unwrap();
+    assert_eq!(ready["event"], "ready");
+    assert_eq!(ready["messages"], 2);
+
+    // Stats carry the same fields as the server's x_litert object, inline
+    let timing = GenerationTiming {
+        time_to_first_token: Some(Duration::from_millis(100)),
+        total: Duration::from_millis(1100),
+    };
+    let stats = SessionEvent::Stats(LitertExtension::from_timing(&timing, "Hello there, world!", &Estimate));
+    let line = serde_json::to_string(&stats).unwrap();
+    assert!(!line.contains('\n'));
+    let stats: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(stats["event"], "stats");
+    assert_eq!(stats["ttft_ms"], 100);
+    assert_eq!(stats["completion_tokens"], 5);
+
+    let parsed: SessionEvent = serde_json::from_str(r#"{"event":"error","message":"boom"}"#).unwrap();
+    assert!(matches!(parsed, SessionEvent::Error { message } if message == "boom"));
+}
@@ -0,0 +1,106 @@
+//! Tests for saving and resuming chat sessions (no lit binary required)
+
+mod common;
+
+use litert_lm::config::TenantConfig;
+use litert_lm::session::SavedSession;
+use litert_lm::{ChatSession, LitManager, Tenants, TranscriptFormat, Turn};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// One cache directory for every test, since the environment is process-wide
+fn cache_dir() -> PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| common::cache_dir().unwrap()).clone()
+}
+
+#[tokio::test]
+async fn saved_sessions_resume_with_their_history() -> anyhow::Result<()> {
+    let dir = cache_dir();
+    let manager = LitManager::new().await?;
+
+    let fresh = manager.chat_session("gemma-3n-E4B");
+    assert!(fresh.history().is_empty());
+    let path = ChatSession::default_path(&manager, fresh.id());
+    fresh.save_session(&path)?;
+    assert!(path.starts_with(dir.join("sessions")));
+
+    let saved = SavedSession {
+        id: "trip".to_string(),
+        model: "gemma-3n-E4B".to_string(),
+        saved_at: 0,
+        turns: vec![Turn::new("user", "Plan a trip"), Turn::new("assistant", "Where to?")],
+        tenant: None,
+        timestamps: Vec::new(),
+    };
+    let original = dir.join("trip.json");
+    std::fs::write(&original, serde_json::to_vec(&saved)?)?;
+
+    let resumed = ChatSession::resume_session(manager.clone(), &original)?;
+    assert_eq!(resumed.id(), "trip");
+    assert_eq!(resumed.model(), "gemma-3n-E4B");
+    assert_eq!(resumed.history(), saved.turns.as_slice());
+    // Saved before timestamps were recorded
+    assert_eq!(resumed.timestamp(0), None);
+
+    // A tenant can't resume a session for a model outside its namespace
+    let tenants = Tenants::from_config(&[TenantConfig {
+        name: "lab".to_string(),
+        api_keys: Vec::new(),
+        models: vec!["qwen*".to_string()],
+        manage_models: false,
+        weight: 1,
+    }]);
+    let lab = manager.for_tenant(tenants.by_name("lab").unwrap());
+    assert!(ChatSession::resume_session(lab, &original).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_export_as_transcripts() -> anyhow::Result<()> {
+    let dir = cache_dir();
+    let manager = LitManager::new().await?;
+
+    let mut session = manager.chat_session("gemma3-1b");
+    session.push_turn(Turn::new("user", "Why is the sky blue?"));
+    session.push_turn(Turn::new("assistant", "Rayleigh scattering."));
+    let sent_at = session.timestamp(0).expect("timestamp of a new turn");
+
+    let markdown = session.export(TranscriptFormat::Markdown)?;
+    assert!(markdown.starts_with(&format!("# Chat session {}\n", session.id())));
+    assert!(markdown.contains("- Model: gemma3-1b\n"));
+    assert!(markdown.contains("tokens, "));
+    assert!(markdown.contains("\n## user · 20"));
+    assert!(markdown.ends_with("Z\n\nRayleigh scattering.\n"), "{}", markdown);
+
+    let jsonl = session.export(TranscriptFormat::Jsonl)?;
+    let lines: Vec<serde_json::Value> =
+        jsonl.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["type"], "session");
+    assert_eq!(lines[0]["model"], "gemma3-1b");
+    assert!(lines[0]["settings"]["context_length"].as_u64().unwrap() > 0);
+    assert_eq!(lines[1]["type"], "message");
+    assert_eq!(lines[1]["role"], "user");
+    assert_eq!(lines[1]["timestamp"], sent_at);
+    assert_eq!(lines[2]["content"], "Rayleigh scattering.");
+
+    // Timestamps are saved with the session
+    let path = dir.join("sky.json");
+    session.save_session(&path)?;
+    let resumed = ChatSession::resume_session(manager, &path)?;
+    assert_eq!(resumed.timestamp(1), session.timestamp(1));
+
+    // Resetting forgets the conversation but not which one it was
+    let id = session.id().to_string();
+    session.reset();
+    assert!(session.history().is_empty());
+    assert_eq!(session.timestamp(0), None);
+    assert_eq!(session.id(), id);
+    assert_eq!(session.model(), "gemma3-1b");
+
+    assert_eq!("jsonl".parse::<TranscriptFormat>()?, TranscriptFormat::Jsonl);
+    assert_eq!("Markdown".parse::<TranscriptFormat>()?.extension(), "md");
+    assert!("html".parse::<TranscriptFormat>().is_err());
+    Ok(())
+}
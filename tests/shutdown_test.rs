@@ -0,0 +1,36 @@
+//! Tests for stopping lit processes on shutdown (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::LitManager;
+
+#[tokio::test]
+async fn shutdown_stops_every_process() -> anyhow::Result<()> {
+    // Answers slowly, so that a generation is in progress when shutting down
+    common::fake_lit(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do sleep 0.5; printf 'Done.\\n>>>'; done\n",
+    )?;
+
+    let manager = LitManager::new_with_pool_size(2).await?;
+    let pool = manager.pool("gemma3-1b").await?;
+    let pids: Vec<u32> = pool.debug_info().iter().filter_map(|info| info.pid).collect();
+    assert_eq!(pids.len(), 2);
+
+    let generating = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.send_prompt("hi").await }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    manager.shutdown().await;
+
+    // The generation in progress finished first
+    assert_eq!(generating.await??.trim(), "Done.");
+    // Even though the pool is still referenced here, its processes are gone
+    for pid in pids {
+        assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists(), "lit {} still running", pid);
+    }
+    assert!(pool.send_prompt("hi").await.is_err());
+    assert!(manager.stats().await.pools.is_empty());
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Tests for traffic splitting (no lit binary required)
+
+use litert_lm::config::{SplitArm, SplitConfig};
+use litert_lm::{Split, Splits};
+
+fn split(arms: &[(&str, u32)]) -> SplitConfig {
+    SplitConfig {
+        name: "chat".to_string(),
+        arms: arms
+            .iter()
+            .map(|&(model, weight)| SplitArm {
+                model: model.to_string(),
+                weight,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn traffic_follows_arm_weights() {
+    let split = Split::from_config(&split(&[("gemma-3n-E4B", 90), ("gemma-3n-E2B", 10)]));
+
+    let small = (0..10_000)
+        .filter(|_| split.pick(None) == Some("gemma-3n-E2B"))
+        .count();
+    assert!((700..1300).contains(&small), "E2B served {} of 10000", small);
+}
+
+#[test]
+fn users_stick_to_one_arm() {
+    let split = Split::from_config(&split(&[("a", 1), ("b", 1), ("off", 0)]));
+
+    for user in ["alice", "bob", "carol"] {
+        let first = split.pick(Some(user));
+        assert!((0..20).all(|_| split.pick(Some(user)) == first));
+    }
+    assert!((0..1000).all(|_| split.pick(None) != Some("off")));
+    assert_eq!(split.models().collect::<Vec<_>>(), vec!["a", "b"]);
+}
+
+#[test]
+fn splits_without_arms_are_ignored() {
+    let splits = Splits::from_config(&[split(&[]), split(&[("off", 0)])]);
+    assert!(splits.get("chat").is_none());
+}
@@ -0,0 +1,60 @@
+//! Tests for the shape of streamed chat completions (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::{ChatCompletionChunk, LitManager};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn streams_follow_the_openai_event_sequence() -> anyhow::Result<()> {
+    // Echoes each prompt
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&manager, "gemma3-1b").await?);
+
+    let body = reqwest::Client::new()
+        .post(&url)
+        .json(&serde_json::json!({
+            "model": "gemma3-1b",
+            "stream": true,
+            "stream_options": { "include_usage": true },
+            "messages": [{ "role": "user", "content": "hello there" }]
+        }))
+        .send()
+        .await?
+        .text()
+        .await?;
+    let data: Vec<&str> = body.lines().filter_map(|line| line.strip_prefix("data: ")).collect();
+    assert_eq!(data.last(), Some(&"[DONE]"));
+    let chunks: Vec<ChatCompletionChunk> = data[..data.len() - 1]
+        .iter()
+        .map(|data| serde_json::from_str(data))
+        .collect::<Result<_, _>>()?;
+    assert!(chunks.windows(2).all(|pair| pair[0].id == pair[1].id));
+
+    // The role comes first, before any text
+    let first = &chunks[0].choices[0];
+    assert_eq!(first.delta.role.as_deref(), Some("assistant"));
+    assert_eq!(first.delta.content.as_deref(), Some(""));
+    assert_eq!(first.finish_reason, None);
+
+    // Then the text, with no finish reason
+    let (usage, choices) = chunks[1..].split_last().unwrap();
+    let (last, text) = choices.split_last().unwrap();
+    assert!(!text.is_empty());
+    assert!(text.iter().all(|chunk| chunk.choices[0].finish_reason.is_none() && chunk.choices[0].delta.role.is_none()));
+    let content: String = text.iter().filter_map(|chunk| chunk.choices[0].delta.content.as_deref()).collect();
+    assert!(content.contains("hello there"), "{}", content);
+
+    // The last choice has an empty delta and the finish reason
+    assert_eq!(last.choices[0].delta.role, None);
+    assert_eq!(last.choices[0].delta.content, None);
+    assert_eq!(last.choices[0].finish_reason.as_deref(), Some("stop"));
+
+    // Usage follows in a chunk without choices
+    assert!(usage.choices.is_empty());
+    assert!(usage.usage.is_some_and(|usage| usage.completion_tokens > 0));
+    Ok(())
+}
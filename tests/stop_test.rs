@@ -0,0 +1,79 @@
+//! Tests for stop sequences in streamed completions (uses a stand-in lit script)
+
+mod common;
+
+use futures::StreamExt;
+use litert_lm::stream::stop_at;
+
+async fn cut(chunks: &[&str], stop: &[&str]) -> Vec<String> {
+    let chunks: Vec<anyhow::Result<String>> = chunks.iter().map(|chunk| Ok(chunk.to_string())).collect();
+    let stop = stop.iter().map(|s| s.to_string()).collect();
+    stop_at(futures::stream::iter(chunks), stop)
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn streams_end_at_the_first_stop_sequence() {
+    assert_eq!(cut(&["Hello", " wor", "ld###more"], &["###"]).await, ["Hello", " wor", "ld"]);
+    // Split across chunks, and the earliest of several
+    assert_eq!(cut(&["one\n", "\ntwo", " END"], &["END", "\n\n"]).await, ["one"]);
+    // Text that only looked like the start of one is sent once it is clear
+    assert_eq!(cut(&["a #", "# b", " c"], &["###"]).await, ["a ", "## b", " c"]);
+    assert_eq!(cut(&["ends with #"], &["###"]).await, ["ends with ", "#"]);
+    // Without stop sequences chunks pass through
+    assert_eq!(cut(&["a", "", "b"], &[]).await, ["a", "", "b"]);
+}
+
+#[tokio::test]
+async fn the_rest_of_the_generation_is_dropped() {
+    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    tx.send(Ok("answer. STOP and more".to_string())).await.unwrap();
+    let mut stream = stop_at(tokio_stream::wrappers::ReceiverStream::new(rx), vec!["STOP".to_string()]);
+    assert_eq!(stream.next().await.unwrap().unwrap(), "answer. ");
+    // Ends without waiting for the generation to finish
+    assert!(stream.next().await.is_none());
+    assert!(tx.is_closed());
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn streamed_completions_stop_early() -> anyhow::Result<()> {
+    use litert_lm::{ChatCompletionChunk, LitManager};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    // Echoes the prompt, then keeps going for a while
+    common::fake_lit(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n' \"$line\"; sleep 2; printf 'late\\n>>>'; done\n",
+    )?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let base = common::spawn_server(&manager, "gemma3-1b").await?;
+
+    let started = Instant::now();
+    let mut events = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&serde_json::json!({
+            "model": "gemma3-1b",
+            "messages": [{ "role": "user", "content": "first ### second" }],
+            "stream": true,
+            "stop": "###"
+        }))
+        .send()
+        .await?
+        .bytes_stream();
+    let mut sse = String::new();
+    while let Some(chunk) = events.next().await {
+        sse.push_str(&String::from_utf8_lossy(&chunk?));
+    }
+    assert!(started.elapsed() < Duration::from_secs(2), "waited for the whole generation");
+
+    let chunks: Vec<ChatCompletionChunk> = common::sse_events(&sse)?;
+    let text: String = chunks.iter().filter_map(|chunk| chunk.choices[0].delta.content.as_deref()).collect();
+    assert!(text.ends_with("first "), "{}", text);
+    assert!(!text.contains("second") && !text.contains("late"), "{}", text);
+    assert_eq!(chunks.last().unwrap().choices[0].finish_reason.as_deref(), Some("stop"));
+    Ok(())
+}
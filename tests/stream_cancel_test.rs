@@ -0,0 +1,53 @@
+//! Tests for cancelling a completion stream mid-reply (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use futures::StreamExt;
+use litert_lm::{Cancelled, CancellationToken, LitManager};
+use std::time::Duration;
+
+#[tokio::test]
+async fn cancelling_interrupts_lit_and_frees_the_process() -> anyhow::Result<()> {
+    // Writes a word every 100ms when asked for a long answer, and stops on
+    // SIGINT, noting how far it got
+    let dir = common::fake_lit(
+        "#!/bin/sh\nstop=0\ntrap 'stop=1' INT\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         *long*) stop=0; i=0\n\
+          while [ $i -lt 100 ] && [ $stop = 0 ]; do printf 'word%s\\n' $i; sleep 0.1; i=$((i+1)); done\n\
+          echo $i > \"$(dirname \"$0\")/stopped\"; printf '>>>' ;;\n\
+         *) printf 'Hello.\\n>>>' ;;\n esac\ndone\n",
+    )?;
+
+    let manager = LitManager::new_with_pool_size(1).await?;
+    let cancel = CancellationToken::new();
+    let mut stream = manager
+        .run_completion_stream_cancellable("gemma3-1b", "a long answer", cancel.clone())
+        .await?;
+    let first = stream.next().await.unwrap()?;
+    assert!(first.text.contains("word0"), "{:?}", first.text);
+
+    cancel.cancel();
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(error.is::<Cancelled>(), "{:#}", error);
+    assert!(stream.next().await.is_none());
+
+    // lit was interrupted well before writing all 100 words
+    let stopped = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Ok(count) = std::fs::read_to_string(dir.join("stopped")) {
+                return count.trim().parse::<u32>().unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await?;
+    assert!(stopped < 50, "{}", stopped);
+
+    // The same process answers the next prompt
+    let pid = manager.pool("gemma3-1b").await?.debug_info()[0].pid;
+    let reply = tokio::time::timeout(Duration::from_secs(5), manager.run_completion("gemma3-1b", "hi")).await??;
+    assert_eq!(reply.trim(), "Hello.");
+    assert_eq!(manager.pool("gemma3-1b").await?.debug_info()[0].pid, pid);
+    Ok(())
+}
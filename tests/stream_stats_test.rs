@@ -0,0 +1,51 @@
+//! Tests for the generation stats event at the end of a stream (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::{LitManager, StreamStats};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn stats_event_precedes_done_when_requested() -> anyhow::Result<()> {
+    // Echoes each prompt
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&manager, "gemma3-1b").await?);
+    let client = reqwest::Client::new();
+
+    let stream = |stats: bool| {
+        client
+            .post(&url)
+            .json(&serde_json::json!({
+                "model": "gemma3-1b",
+                "stream": true,
+                "messages": [{ "role": "user", "content": "hello there" }],
+                "x_litert": { "stats": stats }
+            }))
+            .send()
+    };
+
+    let body = stream(true).await?.text().await?;
+    let events: Vec<&str> = body.split("\n\n").filter(|e| !e.trim().is_empty()).collect();
+    assert_eq!(events.last().map(|e| e.trim()), Some("data: [DONE]"));
+    let stats_event = events[events.len() - 2];
+    let data = stats_event
+        .strip_prefix("event: x_litert.stats\ndata: ")
+        .expect("stats event before [DONE]");
+    let stats: StreamStats = serde_json::from_str(data)?;
+    assert!(stats.id.starts_with("chatcmpl-"));
+    assert!(stats.queue_ms.is_some());
+    assert!(stats.ttft_ms.is_some_and(|ttft| ttft <= stats.generation_ms));
+    assert!(stats.prompt_tokens > 0);
+    assert!(stats.completion_tokens > 0);
+    assert_eq!(stats.backend.as_deref(), Some("gpu"));
+    assert!(stats.pid.is_some());
+
+    // Without the flag, the stream ends with the usual chunks only
+    let body = stream(false).await?.text().await?;
+    assert!(!body.contains("x_litert.stats"));
+    assert!(body.trim_end().ends_with("data: [DONE]"));
+    Ok(())
+}
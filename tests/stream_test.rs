@@ -0,0 +1,98 @@
+//! Tests for typed completion streams (no lit binary required)
+
+use futures::StreamExt;
+use litert_lm::{ChatCompletionChunk, CompletionStream, FinishReason};
+
+fn text_stream() -> CompletionStream {
+    let chunks = ["Hello", ", ", "world"].map(|chunk| Ok(chunk.to_string()));
+    CompletionStream::new("gemma-3n-E4B", futures::stream::iter(chunks))
+}
+
+#[tokio::test]
+async fn chunks_are_numbered_and_end_with_a_finish_reason() -> anyhow::Result<()> {
+    let chunks: Vec<_> = text_stream().collect().await;
+    let chunks = chunks.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+
+    assert_eq!(chunks.len(), 4);
+    assert!(chunks.iter().enumerate().all(|(i, chunk)| chunk.index == i));
+    assert!(chunks.windows(2).all(|pair| pair[0].elapsed <= pair[1].elapsed));
+    assert_eq!(chunks[0].to_string(), "Hello");
+    assert_eq!(chunks[2].finish_reason, None);
+    assert_eq!(chunks[3].finish_reason, Some(FinishReason::Stop));
+    assert_eq!(chunks[3].text, "");
+
+    assert_eq!(text_stream().text().await?, "Hello, world");
+    Ok(())
+}
+
+#[tokio::test]
+async fn chat_chunks_match_the_server_format() -> anyhow::Result<()> {
+    let stream = text_stream();
+    let id = stream.id().to_string();
+    let chunks: Vec<_> = stream.into_chat_chunks().collect().await;
+    let chunks = chunks.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+
+    assert!(chunks.iter().all(|chunk| chunk.id == id && chunk.object == "chat.completion.chunk"));
+    assert_eq!(chunks[0].choices[0].delta.role.as_deref(), Some("assistant"));
+    assert_eq!(chunks[1].choices[0].delta.role, None);
+    let last = chunks.last().unwrap();
+    assert_eq!(last.choices[0].finish_reason.as_deref(), Some("stop"));
+    assert!(last.x_litert.as_ref().is_some_and(|stats| stats.completion_tokens > 0));
+
+    // Clients can parse the server's events back into the same types
+    let json = serde_json::to_string(last)?;
+    let parsed: ChatCompletionChunk = serde_json::from_str(&json)?;
+    assert_eq!(parsed.choices[0].finish_reason.as_deref(), Some("stop"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn events_tell_tokens_from_the_outcome() -> anyhow::Result<()> {
+    use litert_lm::tokenizer::Tokenizer;
+    use litert_lm::{CompletionEvent, LitError, Usage};
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct Words;
+
+    impl Tokenizer for Words {
+        fn count(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+    }
+
+    let events: Vec<CompletionEvent> = text_stream()
+        .counted_by(Arc::new(Words))
+        .prompt_tokens(3)
+        .max_tokens(Some(2))
+        .into_events()
+        .collect()
+        .await;
+    let tokens: Vec<&str> = events
+        .iter()
+        .filter_map(|event| match event {
+            CompletionEvent::Token(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(tokens, ["Hello", ", ", "world"]);
+    match events.last() {
+        Some(CompletionEvent::Done { finish_reason, usage, .. }) => {
+            assert_eq!((*finish_reason, *usage), (FinishReason::Length, Usage::new(3, 2)));
+        }
+        other => panic!("{:?}", other),
+    }
+
+    // A failure is the last event, with the error it failed with
+    let failing = futures::stream::iter(vec![
+        Ok("Half".to_string()),
+        Err(LitError::ProcessCrashed { stderr: vec![] }.into()),
+    ]);
+    let events: Vec<CompletionEvent> = CompletionStream::new("gemma-3n-E4B", failing).into_events().collect().await;
+    assert_eq!(events.len(), 2);
+    match &events[1] {
+        CompletionEvent::Error(e) => assert!(matches!(LitError::of(e), Some(LitError::ProcessCrashed { .. }))),
+        other => panic!("{:?}", other),
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Tests for chat templates
+
+mod common;
+
+use litert_lm::context::{fit_with, ContextWindow, Overflow};
+use litert_lm::tokenizer::Estimate;
+use litert_lm::{ChatTemplate, Turn};
+use std::collections::HashMap;
+
+fn conversation() -> Vec<Turn> {
+    vec![
+        Turn::new("system", "Be brief."),
+        Turn::new("user", "Hi"),
+        Turn::new("assistant", "Hello!"),
+        Turn::new("user", "Why is the sky blue?"),
+    ]
+}
+
+#[test]
+fn templates_are_picked_by_model_name() {
+    let none = HashMap::new();
+    assert_eq!(ChatTemplate::for_model(&none, "gemma-3n-E4B"), ChatTemplate::Gemma);
+    assert_eq!(ChatTemplate::for_model(&none, "gemma3-1b:support"), ChatTemplate::Gemma);
+    assert_eq!(ChatTemplate::for_model(&none, "qwen2.5-1.5b"), ChatTemplate::ChatMl);
+    assert_eq!(ChatTemplate::for_model(&none, "phi-4-mini"), ChatTemplate::Phi);
+    assert_eq!(ChatTemplate::for_model(&none, "my-model"), ChatTemplate::Plain);
+
+    let configured: HashMap<String, ChatTemplate> =
+        serde_json::from_str(r#"{ "my-*": "chatml", "gemma3-1b": "plain" }"#).unwrap();
+    assert_eq!(ChatTemplate::for_model(&configured, "my-model"), ChatTemplate::ChatMl);
+    assert_eq!(ChatTemplate::for_model(&configured, "gemma3-1b"), ChatTemplate::Plain);
+    assert_eq!(ChatTemplate::for_model(&configured, "gemma3-4b"), ChatTemplate::Gemma);
+}
+
+#[test]
+fn conversations_render_in_the_template() {
+    assert_eq!(
+        ChatTemplate::Gemma.render(&conversation()),
+        "<start_of_turn>user Be brief. Hi<end_of_turn><start_of_turn>model Hello!<end_of_turn>\
+         <start_of_turn>user Why is the sky blue?<end_of_turn><start_of_turn>model"
+    );
+    assert_eq!(
+        ChatTemplate::ChatMl.render(&conversation()[2..]),
+        "<|im_start|>assistant Hello!<|im_end|><|im_start|>user Why is the sky blue?<|im_end|><|im_start|>assistant"
+    );
+    assert_eq!(
+        ChatTemplate::Phi.render(&conversation()[..2]),
+        "<|system|>Be brief.<|end|><|user|>Hi<|end|><|assistant|>"
+    );
+    assert_eq!(
+        ChatTemplate::Plain.render(&conversation()[..2]),
+        "system: Be brief.\nuser: Hi"
+    );
+    // Prompts are sent on one line
+    assert!(!ChatTemplate::Gemma.render(&conversation()).contains('\n'));
+
+    // Plain prompts are sent as written
+    assert_eq!(ChatTemplate::Plain.render_prompt(None, "Hello"), "Hello");
+    assert_eq!(
+        ChatTemplate::Gemma.render_prompt(Some("Be brief."), "Hello"),
+        "<start_of_turn>user Be brief. Hello<end_of_turn><start_of_turn>model"
+    );
+}
+
+#[test]
+fn multi_line_messages_are_folded_onto_their_turns_line() {
+    let turns = [
+        Turn::new("system", "Be brief.\nAnswer in English."),
+        Turn::new("user", "Fix this:\r\n\nfn main() {\n    println!(\"hi\")\n}"),
+    ];
+    assert_eq!(
+        ChatTemplate::Gemma.render(&turns),
+        "<start_of_turn>user Be brief. Answer in English. Fix this: fn main() {     println!(\"hi\") }<end_of_turn>\
+         <start_of_turn>model"
+    );
+    for template in [ChatTemplate::ChatMl, ChatTemplate::Phi] {
+        assert!(!template.render(&turns).contains(['\n', '\r']), "{:?}", template);
+    }
+    assert_eq!(
+        ChatTemplate::Plain.render(&turns),
+        "system: Be brief. Answer in English.\nuser: Fix this: fn main() {     println!(\"hi\") }"
+    );
+    assert!(!ChatTemplate::Gemma.render_prompt(Some("Be brief.\nReally."), "line one\nline two").contains('\n'));
+}
+
+#[tokio::test]
+async fn fitted_conversations_use_the_template() -> anyhow::Result<()> {
+    let window = ContextWindow {
+        length: 80,
+        reserve: 0,
+        overflow: Overflow::Summarize,
+    };
+    let mut turns = conversation();
+    turns.insert(1, Turn::new("user", "word ".repeat(60)));
+    let fitted = fit_with(turns, &window, ChatTemplate::Gemma, &Estimate, |prompt| async move {
+        assert!(prompt.starts_with("<start_of_turn>user ") && prompt.ends_with("<start_of_turn>model"));
+        Ok("They said hi.".to_string())
+    })
+    .await?;
+    assert!(fitted.summarized);
+    assert!(fitted.prompt.contains("Summary of the earlier conversation: They said hi."));
+    assert!(fitted.prompt.ends_with("<start_of_turn>user Why is the sky blue?<end_of_turn><start_of_turn>model"));
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn completions_are_sent_in_the_template() -> anyhow::Result<()> {
+    use litert_lm::GenOptions;
+
+    // Echoes each prompt
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+
+    let manager = litert_lm::LitManager::new_with_pool_size(1).await?;
+    let response = manager.run_completion("gemma3-1b", "hi").await?;
+    assert_eq!(response.trim(), "<start_of_turn>user hi<end_of_turn><start_of_turn>model");
+
+    let raw = GenOptions {
+        raw: true,
+        cache: false,
+        ..Default::default()
+    };
+    assert_eq!(manager.run_completion_with("gemma3-1b", "hi", &raw).await?.trim(), "hi");
+    Ok(())
+}
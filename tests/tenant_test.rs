@@ -0,0 +1,75 @@
+//! Tests for tenant namespaces (no lit binary required)
+
+mod common;
+
+use litert_lm::config::TenantConfig;
+use litert_lm::tenant::AccessDenied;
+use litert_lm::{create_router, AppState, LitManager, ProcessPool, Tenants};
+use std::sync::Arc;
+
+fn lab_tenants() -> Vec<TenantConfig> {
+    vec![TenantConfig {
+        name: "lab".to_string(),
+        api_keys: vec!["lab-key".to_string()],
+        models: vec!["gemma-*".to_string()],
+        manage_models: false,
+        weight: 1,
+    }]
+}
+
+#[test]
+fn tenants_resolve_keys_and_match_model_patterns() {
+    assert!(Tenants::default().for_key(None).unwrap().can_manage("anything"));
+
+    let tenants = Tenants::from_config(&lab_tenants());
+    assert!(tenants.for_key(None).is_none());
+    assert!(tenants.for_key(Some("wrong")).is_none());
+
+    let lab = tenants.for_key(Some("lab-key")).unwrap();
+    assert_eq!(lab.name(), "lab");
+    assert!(lab.can_use("gemma-3n-E4B"));
+    assert!(!lab.can_use("qwen2.5-1.5b"));
+    assert!(!lab.can_manage("gemma-3n-E4B"));
+}
+
+#[tokio::test]
+async fn server_and_manager_enforce_the_namespace() -> anyhow::Result<()> {
+    let dir = common::cache_dir()?;
+    common::write_config(&dir, serde_json::json!({ "tenants": lab_tenants() }))?;
+    let manager = LitManager::new().await?;
+    let lab = manager.tenants().by_name("lab").unwrap();
+    let scoped = manager.for_tenant(lab);
+    let err = scoped.pull_quiet("gemma-3n-E4B", None, None).await.unwrap_err();
+    assert!(err.is::<AccessDenied>());
+    let err = scoped.pool("qwen2.5-1.5b").await.unwrap_err();
+    assert!(err.is::<AccessDenied>());
+
+    let pool = Arc::new(ProcessPool::new(dir.join("lit"), "gemma-3n-E4B".to_string(), 1));
+    let app = create_router(AppState {
+        pool,
+        manager: Arc::new(manager),
+        readiness: Default::default(),
+        chunking: Default::default(),
+    });
+    let base = common::serve(app).await?;
+
+    let client = reqwest::Client::new();
+    let health = client.get(format!("{}/health", base)).send().await?;
+    assert_eq!(health.status(), 200);
+    let anonymous = client.get(format!("{}/v1/stats", base)).send().await?;
+    assert_eq!(anonymous.status(), 401);
+    let authorized = client.get(format!("{}/v1/stats", base)).bearer_auth("lab-key").send().await?;
+    assert_eq!(authorized.status(), 200);
+
+    let denied = client
+        .post(format!("{}/v1/chat/completions", base))
+        .bearer_auth("lab-key")
+        .json(&serde_json::json!({
+            "model": "qwen2.5-1.5b",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .send()
+        .await?;
+    assert_eq!(denied.status(), 403);
+    Ok(())
+}
@@ -0,0 +1,27 @@
+//! Tests for the token-throughput limiter
+
+use litert_lm::throttle::TokenBucket;
+use std::time::{Duration, Instant};
+
+#[test]
+fn burst_is_free_and_debt_is_paced() {
+    let bucket = TokenBucket::new(100.0, 50.0);
+
+    assert_eq!(bucket.take(50), Duration::ZERO);
+    // 10 tokens over an empty bucket at 100/s, then another 10 queued behind them
+    let first = bucket.take(10);
+    let second = bucket.take(10);
+    assert!(first > Duration::from_millis(90) && first <= Duration::from_millis(100));
+    assert!(second > Duration::from_millis(190) && second <= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn consume_waits_for_the_bucket_to_refill() {
+    let bucket = TokenBucket::new(1000.0, 10.0);
+    let started = Instant::now();
+
+    bucket.consume(10).await;
+    assert!(started.elapsed() < Duration::from_millis(20));
+    bucket.consume(50).await;
+    assert!(started.elapsed() >= Duration::from_millis(45));
+}
@@ -0,0 +1,64 @@
+//! Tests for the request timeout (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::LitManager;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn wedged_generations_time_out_and_their_process_is_respawned() -> anyhow::Result<()> {
+    // Hangs when asked to be slow, and takes a while when asked to pause
+    let dir = common::fake_lit(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         *slow*) sleep 5 ;;\n\
+         *pause*) sleep 0.7; printf 'Paused.\\n>>>' ;;\n\
+         *) printf 'Hello.\\n>>>' ;;\n esac\ndone\n",
+    )?;
+    common::write_config(&dir, serde_json::json!({ "request_timeout_secs": 1 }))?;
+    let client = reqwest::Client::new();
+    let ask = |content: &str| serde_json::json!({ "model": "gemma3-1b", "messages": [{ "role": "user", "content": content }] });
+
+    // Waiting for the one process doesn't count towards the timeout
+    let single = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&single, "gemma3-1b").await?);
+    let (first, second) = tokio::join!(
+        client.post(&url).json(&ask("pause")).send(),
+        client.post(&url).json(&ask("pause")).send()
+    );
+    assert_eq!(first?.status(), reqwest::StatusCode::OK);
+    assert_eq!(second?.status(), reqwest::StatusCode::OK);
+
+    let manager = Arc::new(LitManager::new_with_pool_size(2).await?);
+    let pool = manager.pool("gemma3-1b").await?;
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&manager, "gemma3-1b").await?);
+    let pids: Vec<_> = pool.debug_info().iter().map(|process| process.pid).collect();
+
+    let response = client.post(&url).json(&ask("be slow")).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::GATEWAY_TIMEOUT);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["error"]["code"], "timeout");
+
+    // The wedged process is killed and respawned; the other one is left alone
+    let mut respawned = 0;
+    for _ in 0..50 {
+        let info = pool.debug_info();
+        respawned = info.iter().filter(|process| !pids.contains(&process.pid)).count();
+        if respawned == 1 && info.iter().all(|process| process.state == "idle") {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(respawned, 1);
+    assert!(Arc::ptr_eq(&pool, &manager.pool("gemma3-1b").await?), "the pool was replaced");
+
+    // Both processes serve requests again
+    for _ in 0..2 {
+        let response = client.post(&url).json(&ask("hi")).send().await?;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await?;
+        assert_eq!(body["choices"][0]["message"]["content"], "Hello.");
+    }
+    Ok(())
+}
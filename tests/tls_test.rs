@@ -0,0 +1,22 @@
+//! Tests for loading TLS certificates
+use litert_lm::TlsConfig;
+
+#[tokio::test]
+async fn unusable_certificates_are_rejected() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("litert-tls-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let tls = TlsConfig {
+        cert: dir.join("cert.pem"),
+        key: dir.join("key.pem"),
+    };
+
+    // Missing files
+    let err = tls.load().await.unwrap_err();
+    assert!(err.to_string().contains("cert.pem"), "{}", err);
+
+    // Files that aren't PEM
+    std::fs::write(&tls.cert, "not a certificate")?;
+    std::fs::write(&tls.key, "not a key")?;
+    assert!(tls.load().await.is_err());
+    Ok(())
+}
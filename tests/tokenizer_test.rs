@@ -0,0 +1,123 @@
+//! Tests for token counting and `usage` (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::tokenizer::{Estimate, Tokenizer, Tokenizers};
+use std::sync::Arc;
+
+/// A `tokenizer.json` splitting lowercased text into words and punctuation
+fn word_level(words: &[&str]) -> String {
+    let vocab: serde_json::Map<String, serde_json::Value> =
+        words.iter().chain(&["[UNK]"]).enumerate().map(|(i, w)| (w.to_string(), i.into())).collect();
+    serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": {"type": "Lowercase"},
+        "pre_tokenizer": {"type": "Whitespace"},
+        "post_processor": null,
+        "decoder": null,
+        "model": {"type": "WordLevel", "vocab": vocab, "unk_token": "[UNK]"}
+    })
+    .to_string()
+}
+
+#[cfg(feature = "tokenizers")]
+#[test]
+fn tokenizer_json_files_count_exactly() -> anyhow::Result<()> {
+    use litert_lm::tokenizer::HfTokenizer;
+
+    let tokenizer = HfTokenizer::parse(&word_level(&["count", "these", "tokens", ","]))?;
+    assert_eq!(tokenizer.count("Count these tokens, please"), 5);
+    assert_eq!(tokenizer.count(""), 0);
+    assert!(HfTokenizer::parse("{\"model\": {}}").is_err());
+    Ok(())
+}
+
+#[test]
+fn models_use_their_most_specific_tokenizer() {
+    #[derive(Debug)]
+    struct Fixed(usize);
+    impl Tokenizer for Fixed {
+        fn count(&self, _: &str) -> usize {
+            self.0
+        }
+    }
+
+    let tokenizers = Tokenizers::default()
+        .with("gemma*", Arc::new(Fixed(1)))
+        .with("gemma-3n-*", Arc::new(Fixed(2)));
+    assert_eq!(tokenizers.count("gemma3-1b", "hello there"), 1);
+    assert_eq!(tokenizers.count("gemma-3n-E4B", "hello there"), 2);
+    // Other models get the estimate
+    assert_eq!(tokenizers.count("qwen2.5-1.5b", "hello there"), Estimate.count("hello there"));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn completions_report_their_usage() -> anyhow::Result<()> {
+    use futures::StreamExt;
+    use litert_lm::{ChatCompletionChunk, LitManager};
+
+    // Echoes each prompt
+    let dir = common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+    let tokenizer = dir.join("tokenizer.json");
+    std::fs::write(&tokenizer, word_level(&["user", ":", "count", "these", "tokens"]))?;
+    common::write_config(&dir, serde_json::json!({ "tokenizers": { "gemma3-*": tokenizer } }))?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let base = common::spawn_server(&manager, "gemma3-1b").await?;
+    let client = reqwest::Client::new();
+    let body = |stream: bool| {
+        let mut body = serde_json::json!({
+            "model": "gemma3-1b",
+            "messages": [{ "role": "user", "content": "count these tokens" }],
+            "stream": stream
+        });
+        if stream {
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+        body
+    };
+
+    let response: serde_json::Value = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&body(false))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let content = response["choices"][0]["message"]["content"].as_str().unwrap();
+    let counted = manager.tokenizers().count("gemma3-1b", content);
+    let usage: litert_lm::Usage = serde_json::from_value(response["usage"].clone())?;
+    assert!(counted > 0 && usage.prompt_tokens > 0);
+    assert_eq!(usage.completion_tokens as usize, counted);
+    assert_eq!(usage.total_tokens, usage.prompt_tokens + usage.completion_tokens);
+
+    // Streams end with their counts, in a chunk without choices
+    let mut events = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&body(true))
+        .send()
+        .await?
+        .bytes_stream();
+    let mut sse = String::new();
+    while let Some(chunk) = events.next().await {
+        sse.push_str(&String::from_utf8_lossy(&chunk?));
+    }
+    let data: Vec<&str> = sse.lines().filter_map(|line| line.strip_prefix("data: ")).collect();
+    assert_eq!(data.last(), Some(&"[DONE]"));
+    let last: ChatCompletionChunk = serde_json::from_str(data[data.len() - 2])?;
+    assert!(last.choices.is_empty());
+    let mut streamed = String::new();
+    for earlier in &data[..data.len() - 2] {
+        let chunk: ChatCompletionChunk = serde_json::from_str(earlier)?;
+        assert_eq!(chunk.usage, None);
+        streamed.extend(chunk.choices[0].delta.content.as_deref());
+    }
+    let streamed_usage = last.usage.unwrap();
+    assert_eq!(streamed_usage.prompt_tokens, usage.prompt_tokens);
+    assert_eq!(streamed_usage.completion_tokens as usize, manager.tokenizers().count("gemma3-1b", &streamed));
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Tests for emulated tool calling in chat completions (uses a stand-in lit script)
+
+mod common;
+
+use litert_lm::server::ChatCompletionRequest;
+
+fn rejected_param(body: serde_json::Value) -> Option<String> {
+    serde_json::from_value::<ChatCompletionRequest>(body).unwrap().validate().unwrap_err().param
+}
+
+fn weather_tool() -> serde_json::Value {
+    serde_json::json!({
+        "type": "function",
+        "function": {
+            "name": "get_weather",
+            "description": "Current weather for a city",
+            "parameters": { "type": "object", "properties": { "city": { "type": "string" } } }
+        }
+    })
+}
+
+#[test]
+fn tool_fields_are_validated() {
+    let hi = serde_json::json!([{ "role": "user", "content": "hi" }]);
+    let ok: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+        "model": "gemma3-1b",
+        "messages": [
+            { "role": "user", "content": "Weather in Oslo?" },
+            { "role": "assistant", "content": null, "tool_calls": [{
+                "id": "call_1", "type": "function",
+                "function": { "name": "get_weather", "arguments": "{\"city\":\"Oslo\"}" }
+            }] },
+            { "role": "tool", "tool_call_id": "call_1", "content": "12C" }
+        ],
+        "tools": [weather_tool()],
+        "tool_choice": { "type": "function", "function": { "name": "get_weather" } },
+        "parallel_tool_calls": false
+    }))
+    .unwrap();
+    assert_eq!(ok.validate(), Ok(()));
+
+    let cases = [
+        (serde_json::json!({ "model": "m", "messages": hi, "tools": [{ "type": "retrieval", "function": { "name": "f" } }] }), "tools[0].type"),
+        (serde_json::json!({ "model": "m", "messages": hi, "tools": [weather_tool()], "tool_choice": "sometimes" }), "tool_choice"),
+        (serde_json::json!({ "model": "m", "messages": hi, "tool_choice": "required" }), "tool_choice"),
+        (
+            serde_json::json!({
+                "model": "m", "messages": hi, "tools": [weather_tool()],
+                "tool_choice": { "type": "function", "function": { "name": "get_time" } }
+            }),
+            "tool_choice",
+        ),
+    ];
+    for (body, param) in cases {
+        assert_eq!(rejected_param(body).as_deref(), Some(param));
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+#[tokio::test]
+async fn tool_calls_are_parsed_from_replies() -> anyhow::Result<()> {
+    use litert_lm::{ChatCompletionChunk, LitManager};
+    use std::sync::Arc;
+
+    // Calls the weather tool when asked about Oslo, and answers once it has the result
+    common::fake_lit(
+        "#!/bin/sh\nprintf '>>>'\nwhile read -r line; do\n case \"$line\" in\n\
+         *12C*) printf 'It is 12C.\\n>>>' ;;\n\
+         *Oslo*) printf '{\"tool\": \"get_weather\", \"arguments\": {\"city\": \"Oslo\"}}\\n>>>' ;;\n\
+         *) printf 'Hello.\\n>>>' ;;\n esac\ndone\n",
+    )?;
+
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let url = format!("{}/v1/chat/completions", common::spawn_server(&manager, "gemma3-1b").await?);
+    let client = reqwest::Client::new();
+    let question = serde_json::json!({ "role": "user", "content": "Weather in Oslo?" });
+
+    let response: serde_json::Value = client
+        .post(&url)
+        .json(&serde_json::json!({ "model": "gemma3-1b", "messages": [question], "tools": [weather_tool()] }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    let choice = &response["choices"][0];
+    assert_eq!(choice["finish_reason"], "tool_calls");
+    assert!(choice["message"]["content"].is_null());
+    let call = &choice["message"]["tool_calls"][0];
+    assert!(call["id"].as_str().unwrap().starts_with("call_"));
+    assert_eq!(call["type"], "function");
+    assert_eq!(call["function"]["name"], "get_weather");
+    let arguments: serde_json::Value = serde_json::from_str(call["function"]["arguments"].as_str().unwrap())?;
+    assert_eq!(arguments, serde_json::json!({ "city": "Oslo" }));
+
+    // The client runs the tool and sends back the result
+    let answer: serde_json::Value = client
+        .post(&url)
+        .json(&serde_json::json!({
+            "model": "gemma3-1b",
+            "messages": [
+                question,
+                choice["message"],
+                { "role": "tool", "tool_call_id": call["id"], "content": "12C" }
+            ],
+            "tools": [weather_tool()]
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(answer["choices"][0]["message"]["content"], "It is 12C.");
+    assert_eq!(answer["choices"][0]["finish_reason"], "stop");
+
+    // Without tools on offer the same reply is plain text
+    let none: serde_json::Value = client
+        .post(&url)
+        .json(&serde_json::json!({
+            "model": "gemma3-1b", "messages": [question], "tools": [weather_tool()], "tool_choice": "none"
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert!(none["choices"][0]["message"]["content"].as_str().unwrap().contains("get_weather"));
+    assert!(none["choices"][0]["message"].get("tool_calls").is_none());
+
+    let body = client
+        .post(&url)
+        .json(&serde_json::json!({
+            "model": "gemma3-1b", "messages": [question], "tools": [weather_tool()], "stream": true
+        }))
+        .send()
+        .await?
+        .text()
+        .await?;
+    let chunks: Vec<ChatCompletionChunk> = common::sse_events(&body)?;
+    // The call isn't streamed as text
+    assert!(chunks.iter().all(|chunk| chunk.choices[0].delta.content.as_deref().unwrap_or_default().is_empty()));
+    let calls: Vec<_> = chunks.iter().filter_map(|chunk| chunk.choices[0].delta.tool_calls.clone()).collect();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0][0].index, Some(0));
+    assert_eq!(calls[0][0].function.name, "get_weather");
+    assert_eq!(chunks.last().unwrap().choices[0].finish_reason.as_deref(), Some("tool_calls"));
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Tests for tracing completions end to end (uses a stand-in lit script)
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+mod common;
+
+use litert_lm::LitManager;
+use opentelemetry::trace::{SpanId, TraceId, TracerProvider};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+use std::sync::Arc;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+#[tokio::test]
+async fn completions_continue_the_callers_trace() -> anyhow::Result<()> {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("trace_test")))
+        .try_init()?;
+
+    // Echoes each prompt
+    common::fake_lit("#!/bin/sh\nprintf '>>>'\nwhile read -r line; do printf '%s\\n>>>' \"$line\"; done\n")?;
+    let manager = Arc::new(LitManager::new_with_pool_size(1).await?);
+    let base = common::spawn_server(&manager, "gemma3-1b").await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        .json(&serde_json::json!({ "model": "gemma3-1b", "messages": [{ "role": "user", "content": "hi" }] }))
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+
+    // The request span closes once the response has been sent
+    let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736")?;
+    let mut spans = Vec::new();
+    for _ in 0..50 {
+        spans = exporter.get_finished_spans()?;
+        if spans.iter().any(|span| span.name == "request") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    let span = |name: &str| {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("no {} span", name))
+    };
+
+    // The request is a child of the caller's span...
+    let request = span("request");
+    assert_eq!(request.span_context.trace_id(), trace_id);
+    assert_eq!(request.parent_span_id, SpanId::from_hex("00f067aa0ba902b7")?);
+    assert!(request.parent_span_is_remote);
+
+    // ...and everything down to lit's generation nests under it
+    let completion = span("completion");
+    assert_eq!(completion.parent_span_id, request.span_context.span_id());
+    for name in ["pool.dispatch", "process.queue_wait", "process.generate"] {
+        assert_eq!(span(name).span_context.trace_id(), trace_id, "{}", name);
+    }
+    assert_eq!(span("pool.dispatch").parent_span_id, completion.span_context.span_id());
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Tests for chat completion request validation (no lit binary required)
+
+use axum::response::IntoResponse;
+use litert_lm::server::{ChatCompletionRequest, InvalidRequest};
+
+fn request(body: serde_json::Value) -> ChatCompletionRequest {
+    serde_json::from_value(body).unwrap()
+}
+
+fn rejected_param(body: serde_json::Value) -> Option<String> {
+    request(body).validate().unwrap_err().param
+}
+
+#[test]
+fn invalid_fields_are_named() {
+    let hi = serde_json::json!([{ "role": "user", "content": "hi" }]);
+
+    let ok = request(serde_json::json!({
+        "model": "gemma-3n-E4B",
+        "messages": [{ "role": "system", "content": "Be brief" }, { "role": "user", "content": "hi" }],
+        "temperature": 0.2,
+        "max_tokens": 64,
+        "seed": 7,
+        "logprobs": false,
+        "response_format": { "type": "text" }
+    }));
+    assert_eq!(ok.validate(), Ok(()));
+
+    let cases = [
+        (serde_json::json!({ "model": "gemma-3n-E4B", "messages": [] }), "messages"),
+        (
+            serde_json::json!({ "model": "gemma-3n-E4B", "messages": [{ "role": "robot", "content": "hi" }] }),
+            "messages[0].role",
+        ),
+        (serde_json::json!({ "model": "gemma-3n-E4B", "messages": hi, "temperature": 3.5 }), "temperature"),
+        (serde_json::json!({ "model": "gemma-3n-E4B", "messages": hi, "top_p": 0.0 }), "top_p"),
+        (serde_json::json!({ "model": "gemma-3n-E4B", "messages": hi, "max_tokens": 0 }), "max_tokens"),
+        (
+            serde_json::json!({ "model": "gemma-3n-E4B", "messages": hi, "functions": [{ "name": "f" }] }),
+            "functions",
+        ),
+        (
+            serde_json::json!({ "model": "gemma-3n-E4B", "messages": hi, "response_format": { "type": "json_object" } }),
+            "response_format",
+        ),
+        (
+            serde_json::json!({ "model": "gemma-3n-E4B", "messages": hi, "stream_options": { "include_usage": true } }),
+            "stream_options",
+        ),
+    ];
+    for (body, param) in cases {
+        assert_eq!(rejected_param(body).as_deref(), Some(param));
+    }
+}
+
+#[tokio::test]
+async fn errors_use_the_openai_format() -> anyhow::Result<()> {
+    let response = InvalidRequest::new("temperature", "temperature must be between 0 and 2").into_response();
+    assert_eq!(response.status(), 400);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await?;
+    let json: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(json["error"]["type"], "invalid_request_error");
+    assert_eq!(json["error"]["param"], "temperature");
+    assert_eq!(json["error"]["message"], "temperature must be between 0 and 2");
+    Ok(())
+}
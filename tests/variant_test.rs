@@ -0,0 +1,49 @@
+//! Tests for picking model variants by device capability
+
+use litert_lm::config::Config;
+use litert_lm::{DeviceInfo, Variants};
+
+const GB: u64 = 1 << 30;
+
+fn device(memory_gb: Option<u64>, gpu: bool) -> DeviceInfo {
+    DeviceInfo {
+        memory_bytes: memory_gb.map(|gb| gb * GB),
+        gpu,
+    }
+}
+
+#[test]
+fn largest_variant_that_fits_is_selected() {
+    let variants = Variants::from_config(&Default::default());
+    let pick = |device: &DeviceInfo| variants.select("gemma-3n", device).map(|v| v.model.clone());
+
+    assert_eq!(pick(&device(Some(16), false)).as_deref(), Some("gemma-3n-E4B"));
+    assert_eq!(pick(&device(Some(4), true)).as_deref(), Some("gemma-3n-E2B"));
+    // Unknown memory plays it safe
+    assert_eq!(pick(&device(None, true)).as_deref(), Some("gemma-3n-E2B"));
+    assert!(variants.select("gemma-3n-E4B", &device(Some(16), true)).is_none());
+}
+
+#[test]
+fn configured_families_replace_built_in_ones() {
+    let config: Config = serde_json::from_value(serde_json::json!({
+        "variants": {
+            "gemma-3n": [
+                { "model": "gemma-3n-E4B", "min_memory_gb": 6, "requires_gpu": true },
+                { "model": "gemma-3n-E2B" }
+            ],
+            "qwen2.5": [
+                { "model": "qwen2.5-1.5b", "min_memory_gb": 4 },
+                { "model": "qwen2.5-0.5b" }
+            ]
+        }
+    }))
+    .unwrap();
+    let variants = Variants::from_config(&config.variants);
+
+    let gemma = |device: &DeviceInfo| variants.select("gemma-3n", device).unwrap().model.clone();
+    assert_eq!(gemma(&device(Some(8), true)), "gemma-3n-E4B");
+    assert_eq!(gemma(&device(Some(8), false)), "gemma-3n-E2B");
+    assert_eq!(variants.select("qwen2.5", &device(Some(8), false)).unwrap().model, "qwen2.5-1.5b");
+    assert_eq!(variants.family("qwen2.5").unwrap().len(), 2);
+}